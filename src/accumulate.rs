@@ -0,0 +1,378 @@
+// src/accumulate.rs
+//
+// Long-exposure accumulation
+//
+// Light painting with an animated sketch needs many frames stacked into one image.
+// While accumulation is on, the chain renders into a texture of the layer's, and
+// each frame is folded into a 32-bit float accumulation texture, as a running
+// average or a running maximum, before the frame is copied on to the target, or the
+// accumulation shown in its place. Two accumulation textures are ping-ponged between,
+// each frame reading the one the frame before wrote. In stereo each eye folds into
+// an accumulation of its own. Only built with the `temporal` feature.
+
+use nannou::wgpu;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::error::Result;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// How an [`Accumulation`] folds each frame into the ones before it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "snake_case"))]
+pub enum AccumulationMode {
+    /// The mean of the frames, like a long exposure through a dense filter: moving
+    /// lights leave faint trails, still ones stay as they are.
+    #[default]
+    Average,
+    /// The brightest each pixel has been, for light painting: moving lights leave
+    /// trails as bright as themselves.
+    Max,
+}
+
+/// Settings of the long-exposure accumulation, see
+/// [`Nnpipe::set_accumulation`](crate::Nnpipe::set_accumulation).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct Accumulation {
+    pub mode: AccumulationMode,
+    /// Whether frames show the accumulation so far rather than the live frame.
+    pub show: bool,
+}
+
+impl Default for Accumulation {
+    fn default() -> Self {
+        Self {
+            mode: AccumulationMode::Average,
+            show: true,
+        }
+    }
+}
+
+impl Accumulation {
+    // The settings as the shaders' uniform, for a frame with `frames` before it
+    fn uniform(&self, frames: u32) -> [f32; 4] {
+        let mode = match self.mode {
+            AccumulationMode::Average => 0.0,
+            AccumulationMode::Max => 1.0,
+        };
+        [mode, self.show as u32 as f32, frames as f32, 0.0]
+    }
+}
+
+// The frames folded together so far, in the two textures ping-ponged between
+struct Exposure {
+    textures: [wgpu::Texture; 2],
+    views: [wgpu::TextureView; 2],
+    // Frame `n` writes accumulation `n % 2`, reading the other
+    accumulate_bind_groups: [wgpu::BindGroup; 2],
+    // Reading accumulation `n % 2`
+    draw_bind_groups: [wgpu::BindGroup; 2],
+    // Frames accumulated since the last reset
+    frames: AtomicU32,
+}
+
+impl Exposure {
+    // The accumulation texture the last frame wrote
+    fn latest(&self) -> usize {
+        (self.frames.load(Ordering::Relaxed).max(1) - 1) as usize % 2
+    }
+}
+
+pub(crate) struct AccumulationLayer {
+    settings: Accumulation,
+    // The chain's output, at the pipeline's size
+    source_view: wgpu::TextureView,
+    uniform_buffer: UniformBuffer,
+    accumulate_pipeline: Arc<wgpu::RenderPipeline>,
+    draw_pipeline: Arc<wgpu::RenderPipeline>,
+    // One per eye in stereo
+    exposures: Vec<Exposure>,
+}
+
+impl AccumulationLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        size: [u32; 2],
+        settings: Accumulation,
+        exposures: usize,
+    ) -> Self {
+        let texture = |format, usage| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | usage,
+                )
+                .format(format)
+                .build(device)
+        };
+        let source_view = texture(
+            wgpu::TextureFormat::Rgba16Float,
+            wgpu::TextureUsages::empty(),
+        )
+        .view()
+        .build();
+
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Accumulation Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform(0)),
+        );
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Accumulation Bind Group Layout",
+            &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Accumulation Pipeline Layout", &bind_group_layout);
+
+        let pipeline = |label, source: &str, format| {
+            let shader = cache.shader(device, label, &expand(source));
+            cache.pipeline(
+                device,
+                &pipeline_layout,
+                &shader,
+                &shader,
+                label,
+                format,
+                None,
+            )
+        };
+        let accumulate_pipeline = pipeline(
+            "Accumulation Pipeline",
+            include_str!("shaders/accumulate.wgsl"),
+            wgpu::TextureFormat::Rgba32Float,
+        );
+        let draw_pipeline = pipeline(
+            "Accumulation Draw Pipeline",
+            include_str!("shaders/accumulate_draw.wgsl"),
+            wgpu::TextureFormat::Rgba16Float,
+        );
+
+        let bind_group = |label, accumulation_view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&source_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(accumulation_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let exposures = (0..exposures)
+            .map(|_| {
+                // Full floats, so the average keeps the light of frames far into the
+                // exposure
+                let textures = [(); 2].map(|_| {
+                    texture(
+                        wgpu::TextureFormat::Rgba32Float,
+                        wgpu::TextureUsages::COPY_SRC,
+                    )
+                });
+                let views = [0, 1].map(|i| textures[i].view().build());
+                let accumulate_bind_groups =
+                    [1, 0].map(|i| bind_group("Accumulation Bind Group", &views[i]));
+                let draw_bind_groups =
+                    [0, 1].map(|i| bind_group("Accumulation Draw Bind Group", &views[i]));
+                Exposure {
+                    textures,
+                    views,
+                    accumulate_bind_groups,
+                    draw_bind_groups,
+                    frames: AtomicU32::new(0),
+                }
+            })
+            .collect();
+
+        Self {
+            settings,
+            source_view,
+            uniform_buffer,
+            accumulate_pipeline,
+            draw_pipeline,
+            exposures,
+        }
+    }
+
+    pub fn settings(&self) -> Accumulation {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Accumulation) {
+        self.settings = settings;
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Where the chain renders while accumulating
+    pub fn source_view(&self) -> &wgpu::TextureView {
+        &self.source_view
+    }
+
+    // Frames accumulated into the first exposure, the left eye's in stereo
+    pub fn frames(&self) -> u32 {
+        self.exposures[0].frames.load(Ordering::Relaxed)
+    }
+
+    // Start every exposure over with the next frame
+    pub fn reset(&self) {
+        for exposure in &self.exposures {
+            exposure.frames.store(0, Ordering::Relaxed);
+        }
+    }
+
+    // The texture the first exposure was last written to
+    pub fn view(&self) -> &wgpu::TextureView {
+        let exposure = &self.exposures[0];
+        &exposure.views[exposure.latest()]
+    }
+
+    // Write the uniform of exposure `exposure`'s next frame, ahead of the upload
+    pub fn prepare(&self, exposure: usize) {
+        let frames = self.exposures[exposure].frames.load(Ordering::Relaxed);
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&self.settings.uniform(frames)));
+    }
+
+    // Fold the chain's output into exposure `exposure`, and copy the frame or the
+    // accumulation to `target`, which is the pipeline's size
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "accumulation", skip_all)
+    )]
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        exposure: usize,
+    ) {
+        let exposure = &self.exposures[exposure];
+        let current = exposure.frames.fetch_add(1, Ordering::Relaxed) as usize % 2;
+        let passes = [
+            (
+                "Accumulation pass",
+                &exposure.views[current],
+                &self.accumulate_pipeline,
+                &exposure.accumulate_bind_groups[current],
+            ),
+            (
+                "Accumulation draw pass",
+                target,
+                &self.draw_pipeline,
+                &exposure.draw_bind_groups[current],
+            ),
+        ];
+        for (label, view, pipeline, bind_group) in passes {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+    }
+
+    // Read the first exposure back from the GPU, in rows from the top left
+    pub async fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<[f32; 4]>> {
+        let exposure = &self.exposures[0];
+        let texture = &exposure.textures[exposure.latest()];
+        let [width, height] = texture.size();
+        let row_bytes = (width * 16).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Accumulation Readback Buffer"),
+            size: (row_bytes * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Accumulation Readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            texture.extent(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Wait);
+        // The buffer is only dropped with the device if the sender never ran
+        receiver.await.unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+        let pixels = slice
+            .get_mapped_range()
+            .chunks(row_bytes as usize)
+            .flat_map(|row| {
+                let row: &[[f32; 4]] = bytemuck::cast_slice(&row[..(width * 16) as usize]);
+                row.to_vec()
+            })
+            .collect();
+        buffer.unmap();
+        Ok(pixels)
+    }
+}
@@ -0,0 +1,150 @@
+// src/burn_in.rs
+//
+// Burn-in overlay
+//
+// Review renders and recordings of debugging sessions are only useful if a frame
+// can be tied back to when it was made and what the knobs were at. The burn-in
+// writes the frame number, the timecode and the values of chosen params into a
+// corner of every frame, through the text overlay shared with the stats HUD.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use nannou::wgpu;
+
+use crate::cache::PipelineCache;
+use crate::nnpipe::Nnpipe;
+use crate::overlay::TextOverlay;
+use crate::upload::UniformBuffer;
+
+/// Corner of the frame the [`BurnIn`] or the [`StatsHud`](crate::StatsHud) is drawn
+/// into.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BurnInCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Settings of the burn-in overlay, see [`Nnpipe::set_burn_in`](crate::Nnpipe::set_burn_in).
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurnIn {
+    pub corner: BurnInCorner,
+    /// Size of a font pixel in target pixels.
+    pub scale: u32,
+    /// Show the number of frames drawn with the burn-in, counting from 0.
+    pub frame_number: bool,
+    /// Show the pipeline's [`time`](Nnpipe::time) as `HH:MM:SS:FF` timecode.
+    pub timecode: bool,
+    /// Frames per second of the timecode.
+    pub fps: f32,
+    /// Paths of the params to show the values of, see [`Nnpipe::list_params`].
+    pub params: Vec<String>,
+}
+
+impl Default for BurnIn {
+    fn default() -> Self {
+        Self {
+            corner: BurnInCorner::TopLeft,
+            scale: 2,
+            frame_number: true,
+            timecode: true,
+            fps: 30.0,
+            params: Vec::new(),
+        }
+    }
+}
+
+impl BurnIn {
+    /// Also show the value of the param at `path`.
+    pub fn with_param(mut self, path: impl Into<String>) -> Self {
+        self.params.push(path.into());
+        self
+    }
+
+    // `time` in seconds as timecode, with frames counted at `fps`
+    fn timecode(&self, time: f32) -> String {
+        let fps = self.fps.round().max(1.0) as u64;
+        let frames = (time.max(0.0) * self.fps.max(1.0)) as u64;
+        let seconds = frames / fps;
+        format!(
+            "{:02}:{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            frames % fps
+        )
+    }
+}
+
+pub(crate) struct BurnInLayer {
+    settings: BurnIn,
+    // Frames drawn since the layer was created
+    frames: AtomicU64,
+    overlay: TextOverlay,
+}
+
+impl BurnInLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cache: &PipelineCache,
+        vertex_shader: &wgpu::ShaderModule,
+        settings: BurnIn,
+    ) -> Self {
+        Self {
+            settings,
+            frames: AtomicU64::new(0),
+            overlay: TextOverlay::new(device, queue, cache, vertex_shader),
+        }
+    }
+
+    pub fn settings(&self) -> &BurnIn {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: BurnIn) {
+        self.settings = settings;
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn set_frames(&self, frames: u64) {
+        self.frames.store(frames, Ordering::Relaxed);
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        self.overlay.uniform_buffer()
+    }
+
+    // Write the text of the next frame, drawn into a target of `target_size`, and
+    // count the frame
+    pub fn write_text(&self, pipeline: &Nnpipe, target_size: [u32; 2]) {
+        let frame = self.frames.fetch_add(1, Ordering::Relaxed);
+        let settings = &self.settings;
+        let mut lines = Vec::new();
+        if settings.frame_number {
+            lines.push(format!("FRAME {frame}"));
+        }
+        if settings.timecode {
+            lines.push(format!("TC {}", settings.timecode(pipeline.time())));
+        }
+        for path in &settings.params {
+            let line = match pipeline.get_param(path) {
+                Some(value) => format!("{path} {value:.3}"),
+                None => format!("{path} -"),
+            };
+            lines.push(line);
+        }
+        self.overlay
+            .write(&lines, &[], settings.corner, settings.scale, target_size);
+    }
+
+    // Draw the text box over `target`
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        self.overlay.encode(encoder, target);
+    }
+}
@@ -0,0 +1,263 @@
+// src/cache.rs
+//
+// Shader, layout and pipeline cache that several `Nnpipe` instances can share
+//
+// Layers, extra windows and preview pipelines all compile the same shaders. Passing
+// one `PipelineCache` to each of them (see `Nnpipe::with_cache`) makes them reuse
+// the compiled modules and pipelines instead of building their own copies.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use nannou::wgpu;
+
+use crate::nnpipe::create_fullscreen_pipeline;
+use crate::pass::InputKind;
+
+/// A cheaply cloneable handle to a shared pipeline cache.
+///
+/// Entries belong to a single device. Using the cache with a different device (for
+/// example after [`Nnpipe::recover`](crate::Nnpipe::recover)) drops everything cached
+/// for the previous one.
+#[derive(Clone, Default)]
+pub struct PipelineCache {
+    entries: Arc<Mutex<CacheEntries>>,
+}
+
+#[derive(Default)]
+struct CacheEntries {
+    device: Option<wgpu_upstream::Id<wgpu::Device>>,
+    shaders: HashMap<u64, Arc<wgpu::ShaderModule>>,
+    bind_group_layouts: HashMap<&'static str, Arc<wgpu::BindGroupLayout>>,
+    pipeline_layouts: HashMap<&'static str, Arc<wgpu::PipelineLayout>>,
+    input_layouts:
+        HashMap<(wgpu_upstream::Id<wgpu::BindGroupLayout>, Vec<InputKind>), InputLayouts>,
+    pipelines: HashMap<PipelineKey, Arc<wgpu::RenderPipeline>>,
+    compute_pipelines: HashMap<ComputePipelineKey, Arc<wgpu::ComputePipeline>>,
+}
+
+// The group 1 layout of a pass's inputs, and the pipeline layout with it
+type InputLayouts = (Arc<wgpu::BindGroupLayout>, Arc<wgpu::PipelineLayout>);
+
+// Everything that distinguishes one fullscreen pipeline from another
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    layout: wgpu_upstream::Id<wgpu::PipelineLayout>,
+    vertex: wgpu_upstream::Id<wgpu::ShaderModule>,
+    fragment: wgpu_upstream::Id<wgpu::ShaderModule>,
+    format: wgpu::TextureFormat,
+    blend: Option<wgpu::BlendState>,
+}
+
+// The layout and module of a compute pass pipeline
+type ComputePipelineKey = (
+    wgpu_upstream::Id<wgpu::PipelineLayout>,
+    wgpu_upstream::Id<wgpu::ShaderModule>,
+);
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of pipelines currently cached.
+    pub fn pipeline_count(&self) -> usize {
+        let entries = self.entries.lock().unwrap();
+        entries.pipelines.len() + entries.compute_pipelines.len()
+    }
+
+    // Lock the entries, clearing them first if they were built for another device
+    fn entries(&self, device: &wgpu::Device) -> MutexGuard<'_, CacheEntries> {
+        let mut entries = self.entries.lock().unwrap();
+        let id = device.global_id();
+        if entries.device != Some(id) {
+            *entries = CacheEntries {
+                device: Some(id),
+                ..Default::default()
+            };
+        }
+        entries
+    }
+
+    pub(crate) fn shader(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+    ) -> Arc<wgpu::ShaderModule> {
+        self.entries(device)
+            .shaders
+            .entry(source_hash(source))
+            .or_insert_with(|| {
+                Arc::new(device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some(label),
+                    source: wgpu::ShaderSource::Wgsl(source.into()),
+                }))
+            })
+            .clone()
+    }
+
+    pub(crate) fn bind_group_layout(
+        &self,
+        device: &wgpu::Device,
+        name: &'static str,
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> Arc<wgpu::BindGroupLayout> {
+        self.entries(device)
+            .bind_group_layouts
+            .entry(name)
+            .or_insert_with(|| {
+                Arc::new(
+                    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some(name),
+                        entries,
+                    }),
+                )
+            })
+            .clone()
+    }
+
+    pub(crate) fn pipeline_layout(
+        &self,
+        device: &wgpu::Device,
+        name: &'static str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Arc<wgpu::PipelineLayout> {
+        self.grouped_pipeline_layout(device, name, &[bind_group_layout])
+    }
+
+    // A pipeline layout binding several groups, in order
+    pub(crate) fn grouped_pipeline_layout(
+        &self,
+        device: &wgpu::Device,
+        name: &'static str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Arc<wgpu::PipelineLayout> {
+        self.entries(device)
+            .pipeline_layouts
+            .entry(name)
+            .or_insert_with(|| {
+                Arc::new(
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some(name),
+                        bind_group_layouts,
+                        push_constant_ranges: &[],
+                    }),
+                )
+            })
+            .clone()
+    }
+
+    // Layouts for passes binding inputs of `kinds` in group 1, after the pass bindings
+    // of `pass_layout` in group 0. `entries` describe the kinds.
+    pub(crate) fn input_layouts(
+        &self,
+        device: &wgpu::Device,
+        pass_layout: &wgpu::BindGroupLayout,
+        kinds: &[InputKind],
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> InputLayouts {
+        self.entries(device)
+            .input_layouts
+            .entry((pass_layout.global_id(), kinds.to_vec()))
+            .or_insert_with(|| {
+                let inputs_layout =
+                    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some("Pass Inputs Bind Group Layout"),
+                        entries,
+                    });
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("Pass Inputs Pipeline Layout"),
+                        bind_group_layouts: &[pass_layout, &inputs_layout],
+                        push_constant_ranges: &[],
+                    });
+                (Arc::new(inputs_layout), Arc::new(pipeline_layout))
+            })
+            .clone()
+    }
+
+    // Look up a fullscreen pipeline, building it on a miss
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn pipeline(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        vertex_shader: &wgpu::ShaderModule,
+        fragment_shader: &wgpu::ShaderModule,
+        label: &str,
+        format: wgpu::TextureFormat,
+        blend: Option<wgpu::BlendState>,
+    ) -> Arc<wgpu::RenderPipeline> {
+        let key = PipelineKey {
+            layout: layout.global_id(),
+            vertex: vertex_shader.global_id(),
+            fragment: fragment_shader.global_id(),
+            format,
+            blend,
+        };
+
+        self.entries(device)
+            .pipelines
+            .entry(key)
+            .or_insert_with(|| {
+                Arc::new(create_fullscreen_pipeline(
+                    device,
+                    layout,
+                    vertex_shader,
+                    fragment_shader,
+                    label,
+                    format,
+                    blend,
+                ))
+            })
+            .clone()
+    }
+
+    // Look up the pipeline of a compute pass, with entry point `cs_main`, building it
+    // on a miss
+    pub(crate) fn compute_pipeline(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        label: &str,
+    ) -> Arc<wgpu::ComputePipeline> {
+        self.entries(device)
+            .compute_pipelines
+            .entry((layout.global_id(), shader.global_id()))
+            .or_insert_with(|| {
+                Arc::new(
+                    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                        label: Some(label),
+                        layout: Some(layout),
+                        module: shader,
+                        entry_point: "cs_main",
+                    }),
+                )
+            })
+            .clone()
+    }
+
+    // Drop a shader that failed to compile, along with any pipeline built from it
+    pub(crate) fn evict_shader(&self, device: &wgpu::Device, source: &str) {
+        let mut entries = self.entries(device);
+        if let Some(shader) = entries.shaders.remove(&source_hash(source)) {
+            let id = shader.global_id();
+            entries
+                .pipelines
+                .retain(|key, _| key.vertex != id && key.fragment != id);
+            entries
+                .compute_pipelines
+                .retain(|(_, shader), _| *shader != id);
+        }
+    }
+}
+
+fn source_hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,332 @@
+// src/capture.rs
+//
+// Non-blocking frame capture
+//
+// `Nnpipe::read_output` waits for the GPU, which stalls the render loop for a frame
+// or more: fine for a screenshot, not for recording. A capture instead copies each
+// frame into one of a pool of staging buffers and maps it in the background; frames
+// are picked up a few frames later, once the GPU got to them, in capture order.
+//
+// Recording at a lower frame rate than the pipeline renders at, a shutter averages
+// the frames rendered while each recorded frame's shutter was open into it, for the
+// motion blur of a film camera. Frames rendered while it's closed aren't read back.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use nannou::wgpu;
+
+use crate::error::Result;
+use crate::nnpipe::{decode_readback, readback_size, Nnpipe};
+
+/// A frame read back by a [`FrameCapture`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    /// Number of the capture call the frame comes from, counting from 0. With a
+    /// [`Shutter`], the number of the recorded frame's interval since time 0.
+    pub index: u64,
+    /// The pipeline's [`time`](Nnpipe::time) when it was captured. With a
+    /// [`Shutter`], when the recorded frame's shutter opened.
+    pub time: f32,
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels, in rows from the top left.
+    pub pixels: Vec<[f32; 4]>,
+}
+
+/// Frame blending of a [`FrameCapture`] recording at `fps` frames per second, see
+/// [`FrameCapture::set_shutter`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shutter {
+    /// Frames per second of the recording.
+    pub fps: f32,
+    /// Degrees of each recorded frame's interval the shutter is open for, up to 360.
+    /// A film camera's 180 blurs motion over half of the interval, 360 blends every
+    /// rendered frame.
+    pub angle: f32,
+}
+
+impl Default for Shutter {
+    fn default() -> Self {
+        Self {
+            fps: 30.0,
+            angle: 180.0,
+        }
+    }
+}
+
+impl Shutter {
+    // The recorded frame's interval `time` falls in, if its shutter is open then
+    fn exposure(&self, time: f32) -> Option<u64> {
+        let frames = time.max(0.0) * self.fps.max(1e-3);
+        let open = self.angle.clamp(0.0, 360.0) / 360.0;
+        (frames.fract() < open).then_some(frames as u64)
+    }
+}
+
+// Sum of the frames read back for a recorded frame so far
+#[derive(Debug)]
+struct Blend {
+    exposure: u64,
+    fps: f32,
+    size: [u32; 2],
+    sum: Vec<[f32; 4]>,
+    count: u32,
+}
+
+impl Blend {
+    fn finish(self) -> Frame {
+        let scale = 1.0 / self.count as f32;
+        Frame {
+            index: self.exposure,
+            time: self.exposure as f32 / self.fps,
+            width: self.size[0],
+            height: self.size[1],
+            pixels: self
+                .sum
+                .into_iter()
+                .map(|pixel| pixel.map(|channel| channel * scale))
+                .collect(),
+        }
+    }
+}
+
+// The map result, set from wgpu's callback
+type MapState = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+// A staging buffer and the frame it holds
+#[derive(Debug)]
+struct Staging {
+    buffer: wgpu::Buffer,
+    size: [u32; 2],
+    index: u64,
+    time: f32,
+    // The recorded frame it's blended into, with a shutter
+    exposure: u64,
+    mapped: MapState,
+}
+
+/// Reads frames back from a pipeline without waiting for the GPU.
+///
+/// Call [`FrameCapture::capture`] after rendering a frame, and
+/// [`FrameCapture::poll_ready`] every frame to collect the ones that have arrived:
+///
+/// ```ignore
+/// pipeline.render(device, queue, &mut renderer, &draw);
+/// capture.capture(&pipeline, device, queue);
+/// while let Some(frame) = capture.poll_ready(device) {
+///     encoder.push(frame?);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FrameCapture {
+    pool_size: usize,
+    // Buffers waiting for the GPU, oldest first
+    pending: VecDeque<Staging>,
+    // Buffers ready for reuse
+    free: Vec<Staging>,
+    next_index: u64,
+    dropped: u64,
+    shutter: Option<Shutter>,
+    // The recorded frame being blended
+    blend: Option<Blend>,
+    // The last recorded frame whose shutter was seen closed
+    closed: Option<u64>,
+}
+
+impl FrameCapture {
+    /// A capture with `pool_size` staging buffers, at least 1. The GPU typically
+    /// runs two or three frames behind, so 3 or 4 buffers avoid dropping frames.
+    pub fn new(pool_size: usize) -> Self {
+        Self {
+            pool_size: pool_size.max(1),
+            pending: VecDeque::new(),
+            free: Vec::new(),
+            next_index: 0,
+            dropped: 0,
+            shutter: None,
+            blend: None,
+            closed: None,
+        }
+    }
+
+    /// Blend the frames rendered during each recorded frame's exposure into it, or
+    /// capture every frame on its own with `None`. See [`Shutter`].
+    ///
+    /// With a shutter, [`FrameCapture::capture`] is called after rendering every frame
+    /// as before, and frames are assigned to recorded frames by the pipeline's
+    /// [`time`](Nnpipe::time), which offline renders step by a fraction of the
+    /// recording's frame interval. A recorded frame is ready once a later one is
+    /// captured or its shutter is seen closed; [`FrameCapture::finish`] hands out the
+    /// last. Set it between recordings; changing it drops the frame being blended.
+    pub fn set_shutter(&mut self, shutter: Option<Shutter>) {
+        self.shutter = shutter;
+        self.blend = None;
+        self.closed = None;
+    }
+
+    pub fn shutter(&self) -> Option<Shutter> {
+        self.shutter
+    }
+
+    /// Frames waiting for the GPU.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Frames not captured because every staging buffer was in use.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Copy `pipeline`'s output texture, as left by the last [`Nnpipe::render`], into
+    /// a staging buffer and start mapping it. Returns `false`, dropping the frame, if
+    /// all buffers are still pending, or skipping it, if a [`Shutter`] is closed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn capture(
+        &mut self,
+        pipeline: &Nnpipe,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> bool {
+        let index = self.next_index;
+        self.next_index += 1;
+        let time = pipeline.time();
+        let exposure = match &self.shutter {
+            Some(shutter) => match shutter.exposure(time) {
+                Some(exposure) => exposure,
+                None => {
+                    self.closed = Some((time.max(0.0) * shutter.fps.max(1e-3)) as u64);
+                    return false;
+                }
+            },
+            None => index,
+        };
+        if self.pending.len() >= self.pool_size {
+            self.dropped += 1;
+            return false;
+        }
+
+        // Reuse a buffer of the right size, or make one; resizes retire the others
+        let size = pipeline.size();
+        let mut staging = match self.free.iter().position(|staging| staging.size == size) {
+            Some(position) => self.free.swap_remove(position),
+            None => {
+                self.free.clear();
+                Staging {
+                    buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Frame Capture Buffer"),
+                        size: readback_size(size),
+                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                        mapped_at_creation: false,
+                    }),
+                    size,
+                    index,
+                    time: 0.0,
+                    exposure,
+                    mapped: MapState::default(),
+                }
+            }
+        };
+        staging.index = index;
+        staging.time = time;
+        staging.exposure = exposure;
+        *staging.mapped.lock().unwrap() = None;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Frame Capture"),
+        });
+        pipeline.copy_output(&mut encoder, &staging.buffer);
+        queue.submit(Some(encoder.finish()));
+
+        let mapped = staging.mapped.clone();
+        staging
+            .buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result);
+            });
+        self.pending.push_back(staging);
+        true
+    }
+
+    /// The oldest captured frame, if the GPU is done with it. Never blocks; call it
+    /// until it returns `None` to drain every frame that's ready.
+    pub fn poll_ready(&mut self, device: &wgpu::Device) -> Option<Result<Frame>> {
+        // Run map callbacks whose work is done. The browser does this on its own.
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Poll);
+        #[cfg(target_arch = "wasm32")]
+        let _ = device;
+
+        loop {
+            if let Some(frame) = self.take_exposed() {
+                return Some(Ok(frame));
+            }
+
+            let result = self.pending.front()?.mapped.lock().unwrap().take()?;
+            let staging = self.pending.pop_front()?;
+            if let Err(error) = result {
+                return Some(Err(error.into()));
+            }
+
+            let pixels = decode_readback(
+                &staging.buffer.slice(..).get_mapped_range(),
+                staging.size[0],
+            );
+            staging.buffer.unmap();
+            let frame = Frame {
+                index: staging.index,
+                time: staging.time,
+                width: staging.size[0],
+                height: staging.size[1],
+                pixels,
+            };
+            let (exposure, size) = (staging.exposure, staging.size);
+            self.free.push(staging);
+            if self.shutter.is_none() {
+                return Some(Ok(frame));
+            }
+
+            // Sum into the recorded frame, handing out the one before once it's done
+            match &mut self.blend {
+                Some(blend) if blend.exposure == exposure && blend.size == size => {
+                    for (sum, pixel) in blend.sum.iter_mut().zip(frame.pixels) {
+                        *sum = std::array::from_fn(|i| sum[i] + pixel[i]);
+                    }
+                    blend.count += 1;
+                }
+                blend => {
+                    let done = blend.replace(Blend {
+                        exposure,
+                        fps: self.shutter.map_or(1.0, |shutter| shutter.fps.max(1e-3)),
+                        size,
+                        sum: frame.pixels,
+                        count: 1,
+                    });
+                    if let Some(done) = done {
+                        return Some(Ok(done.finish()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// The recorded frame still being blended, if any, as it stands. Call it at the
+    /// end of a recording with a [`Shutter`], once [`FrameCapture::poll_ready`] has no
+    /// more frames.
+    pub fn finish(&mut self) -> Option<Frame> {
+        self.blend.take().map(Blend::finish)
+    }
+
+    // The recorded frame being blended, if its shutter closed with none of its frames
+    // left pending
+    fn take_exposed(&mut self) -> Option<Frame> {
+        let exposure = self.blend.as_ref()?.exposure;
+        let done = match self.pending.front() {
+            Some(staging) => staging.exposure != exposure,
+            None => self.closed.is_some_and(|closed| closed >= exposure),
+        };
+        done.then(|| self.finish())?
+    }
+}
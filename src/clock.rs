@@ -0,0 +1,61 @@
+// src/clock.rs
+//
+// Effect clock
+//
+// Grain, noise, sparkles and every pass reading `globals.time` animate with the
+// pipeline's own clock rather than the host's, so a performer can slow the post
+// effects down, freeze them on a frame or jump them to a moment while the sketch
+// under them carries on. The clock runs at a rate from the wall clock, and keeps the
+// effect time it reached whenever its course changes.
+
+// The effect time, from the wall clock
+#[derive(Clone, Debug)]
+pub(crate) struct EffectClock {
+    // Wall-clock instant and effect time the clock last changed course at
+    anchor: web_time::Instant,
+    anchor_time: f32,
+    rate: f32,
+    paused: bool,
+}
+
+impl EffectClock {
+    pub fn new() -> Self {
+        Self {
+            anchor: web_time::Instant::now(),
+            anchor_time: 0.0,
+            rate: 1.0,
+            paused: false,
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        match self.paused {
+            true => self.anchor_time,
+            false => self.anchor_time + self.anchor.elapsed().as_secs_f32() * self.rate,
+        }
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn set_rate(&mut self, rate: f32) {
+        self.jump(self.time());
+        self.rate = rate.max(0.0);
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.jump(self.time());
+        self.paused = paused;
+    }
+
+    // Carry on from `time`
+    pub fn jump(&mut self, time: f32) {
+        self.anchor = web_time::Instant::now();
+        self.anchor_time = time;
+    }
+}
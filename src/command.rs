@@ -0,0 +1,138 @@
+// src/command.rs
+//
+// Deferred pipeline commands
+//
+// Param changes, pass toggles, chain reorders, presets and trigger events reach a
+// running show from MIDI, OSC, the network and the UI at once. Rather than each
+// source mutating the pipeline on its own schedule, they all push commands onto one
+// queue, which any thread can hold a clone of. The pipeline drains it once per frame
+// in `Nnpipe::update`, running the commands in the order they were pushed, so every
+// frame sees a consistent state and the sources never race each other.
+
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "config")]
+use crate::config::PipelineConfig;
+use crate::nnpipe::Nnpipe;
+
+/// Most errors kept for [`Nnpipe::take_command_errors`]; older ones are dropped.
+pub const MAX_COMMAND_ERRORS: usize = 256;
+
+/// A change of the pipeline, pushed onto a [`CommandQueue`] and run on the next
+/// [`Nnpipe::update`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum PipelineCommand {
+    /// Set the param at `path`, like [`Nnpipe::set_param`].
+    SetParam { path: String, value: f32 },
+    /// Turn the pass at index `pass` on or off.
+    TogglePass { pass: usize, enabled: bool },
+    /// Put the passes in a new order, like [`Nnpipe::reorder_passes`].
+    ReorderChain(Vec<usize>),
+    /// Apply a preset's settings, like [`Nnpipe::apply_config`].
+    #[cfg(feature = "config")]
+    LoadPreset(Box<PipelineConfig>),
+    /// Fire the triggers registered under a name, like [`Nnpipe::fire`].
+    TriggerEvent(String),
+}
+
+/// A command that couldn't run, see [`Nnpipe::take_command_errors`].
+#[derive(Clone, Debug)]
+pub struct CommandError {
+    pub command: PipelineCommand,
+    pub message: String,
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "command error: {}", self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Pushes [`PipelineCommand`]s for a pipeline from any thread, see
+/// [`Nnpipe::command_queue`].
+///
+/// Clones share the same queue. Commands wait there until the pipeline's next
+/// [`Nnpipe::update`], which runs them in the order they were pushed.
+#[derive(Clone, Debug, Default)]
+pub struct CommandQueue {
+    pending: Arc<Mutex<Vec<PipelineCommand>>>,
+}
+
+impl CommandQueue {
+    pub fn push(&self, command: PipelineCommand) {
+        self.pending.lock().unwrap().push(command);
+    }
+
+    /// Whether commands are waiting for the next update.
+    pub fn is_pending(&self) -> bool {
+        !self.pending.lock().unwrap().is_empty()
+    }
+
+    fn take(&self) -> Vec<PipelineCommand> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+}
+
+impl Nnpipe {
+    /// The pipeline's command queue, for pushing changes from other threads.
+    ///
+    /// All clones, and all [`Nnpipe::params_handle`]s, share one queue, which
+    /// outlives resizes and device recoveries. Commands that fail are queued for
+    /// [`Nnpipe::take_command_errors`].
+    pub fn command_queue(&self) -> CommandQueue {
+        self.commands.clone()
+    }
+
+    /// Drain the errors of commands run since the last call, oldest first. Only the
+    /// latest [`MAX_COMMAND_ERRORS`] are kept, so a host that never drains them
+    /// doesn't grow without bound.
+    pub fn take_command_errors(&mut self) -> Vec<CommandError> {
+        self.command_errors.drain(..).collect()
+    }
+
+    // Run the commands waiting in the queue, in order
+    pub(crate) fn run_commands(&mut self) {
+        for command in self.commands.take() {
+            if let Err(message) = self.run_command(&command) {
+                if self.command_errors.len() == MAX_COMMAND_ERRORS {
+                    self.command_errors.pop_front();
+                }
+                self.command_errors
+                    .push_back(CommandError { command, message });
+            }
+        }
+    }
+
+    fn run_command(&mut self, command: &PipelineCommand) -> Result<(), String> {
+        match command {
+            PipelineCommand::SetParam { path, value } => {
+                if !self.set_param(path, *value) {
+                    return Err(format!("no param '{path}'"));
+                }
+            }
+            PipelineCommand::TogglePass { pass, enabled } => {
+                let path = format!("passes.{pass}.enabled");
+                if !self.set_param(&path, if *enabled { 1.0 } else { 0.0 }) {
+                    return Err(format!("no pass {pass}"));
+                }
+            }
+            PipelineCommand::ReorderChain(order) => {
+                if !self.reorder_passes(order) {
+                    return Err(format!("{order:?} isn't an order of the chain's passes"));
+                }
+            }
+            #[cfg(feature = "config")]
+            PipelineCommand::LoadPreset(config) => {
+                self.apply_config(config).map_err(|e| e.message)?;
+            }
+            PipelineCommand::TriggerEvent(name) => {
+                if !self.fire(name) {
+                    return Err(format!("no trigger '{name}'"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,273 @@
+// src/compare.rs
+//
+// Preset comparisons and thumbnails
+//
+// Designing looks for a show means flipping between candidates, and flipping hides
+// differences that are obvious side by side. A comparison renders one scene through
+// several configs into a single tiled texture, which can be drawn in a window or read
+// back and saved; thumbnails do the same into small images for preset browsers. Only
+// built with the `config` feature.
+
+use nannou::image::{ImageBuffer, RgbaImage};
+use nannou::wgpu;
+
+use crate::config::{ConfigError, PipelineConfig};
+use crate::error::{check_size, NnpipeError, Result};
+use crate::nnpipe::{encode_srgb8, read_texture, Nnpipe};
+
+/// Renders a scene through 2 to 4 [`PipelineConfig`]s, tiled into one texture.
+///
+/// Two presets are placed side by side, three or four in a 2x2 grid, in reading
+/// order:
+///
+/// ```ignore
+/// let comparison = PresetComparison::new(&device, 960, 540, 2)?;
+/// comparison.render(&mut pipeline, &device, &queue, &scene_view, &[warm, cold])?;
+/// draw.texture(comparison.view());
+/// ```
+pub struct PresetComparison {
+    count: usize,
+    tile_size: [u32; 2],
+
+    // Each preset renders here, then gets copied into its tile
+    tile_texture: wgpu::Texture,
+    tile_view: wgpu::TextureView,
+
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl PresetComparison {
+    /// A comparison of `count` presets (clamped to 2..=4), each in a
+    /// `tile_width` x `tile_height` tile.
+    ///
+    /// Fails if the tiled texture exceeds the device's texture limits.
+    pub fn new(
+        device: &wgpu::Device,
+        tile_width: u32,
+        tile_height: u32,
+        count: usize,
+    ) -> Result<Self> {
+        let count = count.clamp(2, 4);
+        let rows = if count > 2 { 2 } else { 1 };
+        let width = tile_width.saturating_mul(2);
+        let height = tile_height.saturating_mul(rows);
+        check_size(device, width, height)?;
+
+        let tile_texture = wgpu::TextureBuilder::new()
+            .size([tile_width, tile_height])
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device);
+        let tile_view = tile_texture.view().build();
+
+        let texture = wgpu::TextureBuilder::new()
+            .size([width, height])
+            .usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_DST
+                    | wgpu::TextureUsages::COPY_SRC,
+            )
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device);
+        let view = texture.view().build();
+
+        Ok(Self {
+            count,
+            tile_size: [tile_width, tile_height],
+            tile_texture,
+            tile_view,
+            texture,
+            view,
+        })
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn tile_size(&self) -> [u32; 2] {
+        self.tile_size
+    }
+
+    /// Size of the whole tiled texture.
+    pub fn size(&self) -> [u32; 2] {
+        self.texture.size()
+    }
+
+    /// The tiled Rgba16Float texture.
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Run `scene_view` through `pipeline` once per preset, each preset applied on
+    /// top of the pipeline's current settings, and tile the frames.
+    ///
+    /// Presets beyond the comparison's count are ignored, and tiles without a preset
+    /// are left black. All presets see the same time, and the pipeline's settings are
+    /// restored afterwards. Fails, after rendering every tile, if a preset names
+    /// passes or params the pipeline doesn't have.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(presets = presets.len())))]
+    pub fn render(
+        &self,
+        pipeline: &mut Nnpipe,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene_view: &wgpu::TextureView,
+        presets: &[PipelineConfig],
+    ) -> Result<(), ConfigError> {
+        let saved = SavedState::save(pipeline);
+        self.clear(device, queue);
+        let mut result = Ok(());
+        for (index, preset) in presets.iter().take(self.count).enumerate() {
+            let applied = pipeline.apply_settings(preset);
+            result = result.and(applied);
+            pipeline.process_texture(device, queue, scene_view, &self.tile_view);
+            self.copy_tile(device, queue, index);
+            saved.reset(pipeline);
+        }
+        saved.restore(pipeline);
+        result
+    }
+
+    /// Read the tiled texture back from the GPU, like
+    /// [`Nnpipe::read_output`](crate::Nnpipe::read_output).
+    pub async fn read_output(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<[f32; 4]>> {
+        read_texture(device, queue, &self.texture).await
+    }
+
+    // Clear every tile to black
+    fn clear(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Preset Comparison Clear"),
+        });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Preset comparison clear pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // Copy the tile texture into tile `index`
+    fn copy_tile(&self, device: &wgpu::Device, queue: &wgpu::Queue, index: usize) {
+        let [width, height] = self.tile_size;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Preset Comparison Tile"),
+        });
+        encoder.copy_texture_to_texture(
+            self.tile_texture.as_image_copy(),
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: (index as u32 % 2) * width,
+                    y: (index as u32 / 2) * height,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            self.tile_texture.extent(),
+        );
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+impl Nnpipe {
+    /// Render `scene_view` through each of `presets`, applied on top of the current
+    /// settings, into `width` x `height` thumbnails for a preset browser.
+    ///
+    /// The effects run at the pipeline's resolution and the result is scaled down
+    /// like any smaller target (see [`Nnpipe::scaler_mut`]), so thumbnails show the
+    /// bloom as it looks full size. Colors are clamped to 0..1 and encoded to 8-bit
+    /// sRGB, like the PNGs image viewers expect; alpha stays linear. All presets see the same time,
+    /// and the settings are restored afterwards.
+    ///
+    /// Fails if a preset names passes or params the pipeline doesn't have, if the size
+    /// exceeds the device's texture limits, or if a readback fails.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(presets = presets.len())))]
+    pub async fn preset_thumbnails(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene_view: &wgpu::TextureView,
+        presets: &[PipelineConfig],
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<RgbaImage>> {
+        check_size(device, width, height)?;
+        let texture = wgpu::TextureBuilder::new()
+            .size([width, height])
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device);
+        let view = texture.view().build();
+
+        let saved = SavedState::save(self);
+        let result = async {
+            let mut thumbnails = Vec::with_capacity(presets.len());
+            for preset in presets {
+                let applied = self.apply_settings(preset);
+                self.process_texture(device, queue, scene_view, &view);
+                saved.reset(self);
+                applied?;
+
+                let pixels = read_texture(device, queue, &texture).await?;
+                let data = encode_srgb8(&pixels);
+                let thumbnail: RgbaImage =
+                    ImageBuffer::from_raw(width, height, data).expect("one pixel per texel");
+                thumbnails.push(thumbnail);
+            }
+            Ok::<_, NnpipeError>(thumbnails)
+        }
+        .await;
+        saved.restore(self);
+        result
+    }
+}
+
+// The settings a preset render changes, to be put back after every preset
+struct SavedState {
+    config: PipelineConfig,
+    fixed_time: Option<f32>,
+}
+
+impl SavedState {
+    // Save `pipeline`'s settings and pin its time, so every preset sees the same
+    fn save(pipeline: &mut Nnpipe) -> Self {
+        let saved = Self {
+            config: pipeline.config(),
+            fixed_time: pipeline.fixed_time(),
+        };
+        pipeline.set_time(Some(pipeline.time()));
+        saved
+    }
+
+    // Undo the preset applied since saving. The config holds every pass, so applying
+    // it can't fail; channel thresholds are only in it while set.
+    fn reset(&self, pipeline: &mut Nnpipe) {
+        let _ = pipeline.apply_settings(&self.config);
+        pipeline.set_channel_thresholds(self.config.bloom.channel_thresholds);
+    }
+
+    // Go back to the clock the pipeline had, once done
+    fn restore(&self, pipeline: &mut Nnpipe) {
+        pipeline.set_time(self.fixed_time);
+    }
+}
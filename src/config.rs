@@ -0,0 +1,808 @@
+// src/config.rs
+//
+// Pipeline parameters in TOML or RON files
+//
+// A config holds the bloom settings, the settings of the layers that are on and the
+// params of effect passes. It can be
+// written out from a running pipeline, edited in a text editor and watched, so looks
+// are tweaked live while the sketch runs. A config can also describe the chain
+// itself, its resolution, formats and pass shaders, so whole setups are stored and
+// shared as files and rebuilt with `Nnpipe::from_config`. Only built with the
+// `config` feature.
+
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
+
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "stylize")]
+use crate::distance_glow::DistanceGlow;
+#[cfg(feature = "stylize")]
+use crate::drop_shadow::DropShadow;
+use crate::error::Result;
+#[cfg(feature = "bloom")]
+use crate::flare::LensFlare;
+use crate::nnpipe::{Nnpipe, UpsampleFilter};
+use crate::params::LayerSettings;
+use crate::pass::{LookupTexture, PassSampler};
+use crate::projection::OutputProjection;
+use crate::quality::QualityPreset;
+#[cfg(feature = "stylize")]
+use crate::sparkle::Sparkles;
+use crate::ssr::Reflections;
+use crate::warp::OutputWarp;
+
+/// A config file that failed to load or apply.
+#[derive(Clone, Debug)]
+pub struct ConfigError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "config error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+pub(crate) fn error(message: impl ToString) -> ConfigError {
+    ConfigError {
+        message: message.to_string(),
+    }
+}
+
+/// Pipeline parameters, as read from or written to a config file.
+///
+/// Every setting is optional: applying a config only changes what it mentions, so a
+/// file can hold just the few values being tuned.
+///
+/// ```toml
+/// [bloom]
+/// threshold = 0.7
+/// intensity = 1.5
+///
+/// [[passes]]
+/// label = "Scanline Displacement"
+/// params = { amplitude = 12.0, speed = 0.3 }
+/// ```
+///
+/// A config with a `[chain]` section and a `source` for every pass describes a whole
+/// pipeline for [`Nnpipe::from_config`]; see [`Nnpipe::chain_config`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<ChainConfig>,
+    pub bloom: BloomConfig,
+    /// The lens flare's settings, see [`Nnpipe::set_lens_flare`]. Like the other
+    /// layers' sections, it holds all of them, those it leaves out at their defaults.
+    #[cfg(feature = "bloom")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flare: Option<LensFlare>,
+    /// The sparkles' settings, see [`Nnpipe::set_sparkles`].
+    #[cfg(feature = "stylize")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparkles: Option<Sparkles>,
+    /// The screen-space reflections' settings, see [`Nnpipe::enable_reflections`].
+    /// They take the host's depth and normal buffers, so [`Nnpipe::from_config`]
+    /// leaves them off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reflections: Option<Reflections>,
+    /// The drop shadow's settings, see [`Nnpipe::set_drop_shadow`].
+    #[cfg(feature = "stylize")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drop_shadow: Option<DropShadow>,
+    /// The distance glow's settings, see [`Nnpipe::set_distance_glow`].
+    #[cfg(feature = "stylize")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance_glow: Option<DistanceGlow>,
+    pub passes: Vec<PassConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<OutputConfig>,
+}
+
+/// How [`Nnpipe::from_config`] creates a pipeline. Applying a config to an existing
+/// pipeline ignores it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainConfig {
+    /// Base size, see [`Nnpipe::new`].
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "one")]
+    pub samples: u32,
+    /// See [`Nnpipe::set_render_scale`].
+    #[serde(default = "one_f32")]
+    pub render_scale: f32,
+    /// The quality preset the bloom starts from, `high` unless set.
+    #[serde(default)]
+    pub quality: QualityPreset,
+    /// Overrides the preset's bloom scale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bloom_scale: Option<f32>,
+    /// Overrides the preset's bloom format, by its WebGPU name, e.g. `"rgba8unorm"`.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "bloom_format"
+    )]
+    pub bloom_format: Option<wgpu::TextureFormat>,
+    /// See [`Nnpipe::set_equirectangular`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub equirectangular: bool,
+}
+
+// Bloom formats by their WebGPU names; the floating point formats and 8-bit RGBA
+// are the ones worth rendering a bloom to
+mod bloom_format {
+    use nannou::wgpu::TextureFormat;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    const FORMATS: &[(&str, TextureFormat)] = &[
+        ("rgba8unorm", TextureFormat::Rgba8Unorm),
+        ("rgba16float", TextureFormat::Rgba16Float),
+        ("rgba32float", TextureFormat::Rgba32Float),
+        ("rg11b10ufloat", TextureFormat::Rg11b10Float),
+        ("rgb10a2unorm", TextureFormat::Rgb10a2Unorm),
+    ];
+
+    pub fn serialize<S: Serializer>(
+        format: &Option<TextureFormat>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let name = FORMATS
+            .iter()
+            .find(|(_, known)| Some(*known) == *format)
+            .map(|(name, _)| *name);
+        match name {
+            Some(name) => serializer.serialize_some(name),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<TextureFormat>, D::Error> {
+        let Some(name) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        FORMATS
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, format)| Some(*format))
+            .ok_or_else(|| D::Error::custom(format!("unknown bloom format '{name}'")))
+    }
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn one_f32() -> f32 {
+    1.0
+}
+
+/// Bloom settings of a [`PipelineConfig`], named after the pipeline's setters.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BloomConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel_thresholds: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intensity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adaptive_blur_scaling: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_blur_radius: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intensity_curve: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub horizontal_blur_strength: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertical_blur_strength: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stretch: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blur_angle: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saturation: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hue_shift: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub premultiplied_alpha: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sanitize_scene: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upsample_filter: Option<UpsampleFilter>,
+}
+
+impl BloomConfig {
+    // The setting of the bloom param `name`, as in param paths
+    pub(crate) fn param_mut(&mut self, name: &str) -> Option<&mut Option<f32>> {
+        Some(match name {
+            "threshold" => &mut self.threshold,
+            "intensity" => &mut self.intensity,
+            "adaptive_blur_scaling" => &mut self.adaptive_blur_scaling,
+            "max_blur_radius" => &mut self.max_blur_radius,
+            "intensity_curve" => &mut self.intensity_curve,
+            "horizontal_blur_strength" => &mut self.horizontal_blur_strength,
+            "vertical_blur_strength" => &mut self.vertical_blur_strength,
+            "stretch" => &mut self.stretch,
+            "blur_angle" => &mut self.blur_angle,
+            "saturation" => &mut self.saturation,
+            "hue_shift" => &mut self.hue_shift,
+            _ => return None,
+        })
+    }
+}
+
+/// Settings of one effect pass in a [`PipelineConfig`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PassConfig {
+    /// Index of the pass. Takes precedence over `label`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// Label of the pass, the first pass with this label being used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Wet/dry mix of the pass, see [`Pass::mix`](crate::Pass::mix).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mix: Option<f32>,
+    pub params: BTreeMap<String, f32>,
+    /// WGSL of the pass, for [`Nnpipe::from_config`]. Its params are reflected from
+    /// the shader (see [`Nnpipe::add_reflected_pass`]).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Whether the source is a compute shader, for [`Nnpipe::from_config`] (see
+    /// [`Nnpipe::add_compute_pass`]).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub compute: bool,
+    /// Values of the shader's compile-time constants, for [`Nnpipe::from_config`]
+    /// (see [`Nnpipe::set_pass_constants`]).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub constants: BTreeMap<String, f32>,
+    /// The pass's data array, for [`Nnpipe::from_config`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub data: Vec<f32>,
+    /// The pass's lookup texture, for [`Nnpipe::from_config`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookup: Option<LookupTexture>,
+    /// The pass's sampler, for [`Nnpipe::from_config`] (see
+    /// [`Nnpipe::set_pass_sampler`]).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampler: Option<PassSampler>,
+}
+
+/// Settings of the scaler or an output in a [`PipelineConfig`], for projection
+/// setups to be stored with the chain.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Index of the output (see [`Nnpipe::add_output`]), or the scaler if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// See [`Output::set_warp`](crate::Output::set_warp).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warp: Option<OutputWarp>,
+    /// See [`Output::set_projection`](crate::Output::set_projection).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projection: Option<OutputProjection>,
+}
+
+impl PassConfig {
+    // The settings of the config for pass `index`, without its shader and resources
+    pub(crate) fn settings(&self, index: usize) -> PassConfig {
+        PassConfig {
+            index: Some(index),
+            enabled: self.enabled,
+            mix: self.mix,
+            params: self.params.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl PipelineConfig {
+    /// Load a `.ron` file, or any other file as TOML.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|e| error(format!("{}: {e}", path.display())))?;
+        let result = if is_ron(path) {
+            Self::from_ron(&source)
+        } else {
+            Self::from_toml(&source)
+        };
+        result.map_err(|e| error(format!("{}: {}", path.display(), e.message)))
+    }
+
+    /// Write the config to `path`, as RON for `.ron` files and TOML otherwise.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let source = if is_ron(path) {
+            self.to_ron()?
+        } else {
+            self.to_toml()?
+        };
+        std::fs::write(path, source).map_err(|e| error(format!("{}: {e}", path.display())))
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, ConfigError> {
+        toml::from_str(source).map_err(error)
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, ConfigError> {
+        ron::from_str(source).map_err(error)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(self).map_err(error)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ConfigError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(error)
+    }
+
+    // The sources of all passes, failing if one has none
+    pub(crate) fn sources(&self) -> Result<Vec<&str>, ConfigError> {
+        self.passes
+            .iter()
+            .enumerate()
+            .map(|(index, pass_config)| {
+                pass_config
+                    .source
+                    .as_deref()
+                    .ok_or_else(|| error(format!("no source for pass {index}")))
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn is_ron(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ron"))
+}
+
+/// Reloads a config file into a pipeline whenever it changes on disk.
+///
+/// Checking only reads the file's modification time, so [`ConfigWatcher::poll`] can
+/// be called every frame. Not available on the web, which has no file system.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ConfigWatcher {
+    /// Watch `path`. The first poll loads it.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            modified: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Apply the file to `pipeline` if it changed since the last poll. Returns `None`
+    /// if it didn't, or the result of loading and applying it.
+    ///
+    /// A file that fails to parse (e.g. saved halfway through an edit) leaves the
+    /// pipeline unchanged, and is tried again on its next change.
+    pub fn poll(&mut self, pipeline: &mut Nnpipe) -> Option<Result<(), ConfigError>> {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let result =
+            PipelineConfig::load(&self.path).and_then(|config| pipeline.apply_config(&config));
+        Some(result)
+    }
+}
+
+impl Nnpipe {
+    /// The pipeline's current bloom settings and the params of all its passes.
+    pub fn config(&self) -> PipelineConfig {
+        let bloom = BloomConfig {
+            threshold: Some(self.brightness_threshold),
+            channel_thresholds: self.channel_thresholds,
+            intensity: Some(self.bloom_intensity),
+            adaptive_blur_scaling: Some(self.adaptive_blur_scaling),
+            max_blur_radius: Some(self.max_blur_radius),
+            intensity_curve: Some(self.intensity_curve),
+            horizontal_blur_strength: Some(self.horizontal_blur_strength),
+            vertical_blur_strength: Some(self.vertical_blur_strength),
+            stretch: Some(self.bloom_stretch),
+            blur_angle: Some(self.blur_angle),
+            saturation: Some(self.bloom_saturation),
+            hue_shift: Some(self.bloom_hue_shift),
+            premultiplied_alpha: Some(self.premultiplied_alpha),
+            sanitize_scene: Some(self.sanitize_scene),
+            upsample_filter: Some(self.upsample_filter),
+        };
+        let passes = (0..)
+            .map_while(|index| self.custom_pass(index))
+            .enumerate()
+            .map(|(index, pass)| PassConfig {
+                index: Some(index),
+                label: Some(pass.label.clone()),
+                enabled: Some(pass.enabled),
+                mix: Some(pass.mix),
+                params: pass.params().iter().cloned().collect(),
+                ..Default::default()
+            })
+            .collect();
+        PipelineConfig {
+            chain: None,
+            bloom,
+            #[cfg(feature = "bloom")]
+            flare: self.lens_flare(),
+            #[cfg(feature = "stylize")]
+            sparkles: self.sparkles(),
+            reflections: self.reflections(),
+            #[cfg(feature = "stylize")]
+            drop_shadow: self.drop_shadow(),
+            #[cfg(feature = "stylize")]
+            distance_glow: self.distance_glow(),
+            passes,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Like [`Nnpipe::config`], with the chain's setup, every pass's shader, data,
+    /// lookup texture and sampler, and the warps and projections of the scaler and
+    /// outputs, for [`Nnpipe::from_config`] to rebuild the pipeline from.
+    pub fn chain_config(&self) -> PipelineConfig {
+        let [width, height] = self.base_size();
+        let quality = self.quality();
+        let mut config = self.config();
+        config.chain = Some(ChainConfig {
+            width,
+            height,
+            samples: self.samples(),
+            render_scale: self.render_scale(),
+            quality: quality.preset,
+            bloom_scale: Some(self.bloom_scale()),
+            bloom_format: Some(quality.bloom_format),
+            equirectangular: self.equirectangular(),
+        });
+        for (index, pass_config) in config.passes.iter_mut().enumerate() {
+            let pass = self.custom_pass(index).expect("one config per pass");
+            pass_config.index = None;
+            pass_config.source = Some(pass.source().to_string());
+            pass_config.compute = pass.is_compute();
+            pass_config.constants = pass.constants().iter().cloned().collect();
+            pass_config.data = pass.data().to_vec();
+            pass_config.lookup = pass.lookup().cloned();
+            pass_config.sampler = Some(pass.sampler()).filter(|s| *s != PassSampler::default());
+        }
+        let outputs = (0..).map_while(|index| Some((Some(index), self.output(index)?)));
+        config.outputs = std::iter::once((None, self.scaler()))
+            .chain(outputs)
+            .map(|(index, output)| OutputConfig {
+                index,
+                warp: Some(output.warp().clone()).filter(|warp| !warp.is_identity()),
+                projection: Some(output.projection())
+                    .filter(|projection| *projection != OutputProjection::Flat),
+            })
+            .filter(|output| output.warp.is_some() || output.projection.is_some())
+            .collect();
+        config
+    }
+
+    /// Create a pipeline from a config with a [`ChainConfig`], adding a pass for each
+    /// of its passes, switching on the layers it has settings for and applying its
+    /// settings.
+    ///
+    /// Fails if the config has no chain or a pass has no source, if the chain can't
+    /// be created on `device` (see [`Nnpipe::with_quality`]), if a pass's constants
+    /// can't be specialized, or if a pass lacks a param the config sets. Passes whose
+    /// shader fails to compile or reflect run as passthroughs, with the errors queued
+    /// for [`Nnpipe::take_shader_errors`]. The new pipeline has no outputs or
+    /// reflections, so only the scaler's settings are applied; apply the config again
+    /// once outputs are added or reflections enabled for theirs.
+    pub fn from_config(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &PipelineConfig,
+    ) -> Result<Self> {
+        let chain = config
+            .chain
+            .as_ref()
+            .ok_or_else(|| error("no chain to create a pipeline from"))?;
+        let sources = config.sources()?;
+
+        let mut quality = chain.quality.profile();
+        if let Some(scale) = chain.bloom_scale {
+            quality.bloom_scale = scale;
+        }
+        if let Some(format) = chain.bloom_format {
+            quality.bloom_format = format;
+        }
+        let mut pipeline =
+            Nnpipe::with_quality(device, chain.width, chain.height, chain.samples, quality)?;
+        pipeline.set_render_scale(device, queue, chain.render_scale)?;
+        pipeline.set_equirectangular(device, queue, chain.equirectangular);
+        #[cfg(feature = "bloom")]
+        pipeline.set_lens_flare(device, config.flare);
+        #[cfg(feature = "stylize")]
+        pipeline.set_sparkles(device, config.sparkles);
+        #[cfg(feature = "stylize")]
+        pipeline.set_drop_shadow(device, config.drop_shadow);
+        #[cfg(feature = "stylize")]
+        pipeline.set_distance_glow(device, config.distance_glow);
+
+        let mut passes = Vec::with_capacity(sources.len());
+        for (pass_config, source) in config.passes.iter().zip(sources) {
+            let index = pipeline.add_config_pass(device, queue, pass_config, source)?;
+            // Settings go to the pass just added, even if labels repeat
+            passes.push(pass_config.settings(index));
+        }
+
+        let scaler = config
+            .outputs
+            .iter()
+            .filter(|output| output.index.is_none());
+        let settings = PipelineConfig {
+            chain: None,
+            bloom: config.bloom.clone(),
+            #[cfg(feature = "bloom")]
+            flare: config.flare,
+            #[cfg(feature = "stylize")]
+            sparkles: config.sparkles,
+            reflections: None,
+            #[cfg(feature = "stylize")]
+            drop_shadow: config.drop_shadow,
+            #[cfg(feature = "stylize")]
+            distance_glow: config.distance_glow,
+            passes,
+            outputs: scaler.cloned().collect(),
+        };
+        pipeline.apply_config(&settings)?;
+        Ok(pipeline)
+    }
+
+    // Append a pass running `source`, with the shader kind, constants, data and
+    // lookup of `pass_config`
+    pub(crate) fn add_config_pass(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pass_config: &PassConfig,
+        source: &str,
+    ) -> Result<usize> {
+        let label = pass_config.label.as_deref().unwrap_or("Custom Pass");
+        let index = if pass_config.compute {
+            self.add_reflected_compute_pass(device, label, source)
+        } else {
+            self.add_reflected_pass(device, label, source)
+        };
+        self.set_config_resources(device, queue, index, pass_config)?;
+        Ok(index)
+    }
+
+    // Give pass `index` the constants, data, lookup and sampler of `pass_config`,
+    // where they differ from its own
+    pub(crate) fn set_config_resources(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        pass_config: &PassConfig,
+    ) -> Result<()> {
+        // Checked ahead of the constants, so a bad lookup leaves the pass alone
+        if let Some(lookup) = &pass_config.lookup {
+            lookup.check()?;
+        }
+        let pass = self.custom_pass(index).expect("a pass of the chain");
+        let constants_changed =
+            pass.constants().iter().cloned().collect::<BTreeMap<_, _>>() != pass_config.constants;
+        let data_changed = pass.data() != pass_config.data;
+        let lookup_changed = pass.lookup() != pass_config.lookup.as_ref();
+
+        if constants_changed {
+            let constants: Vec<(&str, f32)> = pass_config
+                .constants
+                .iter()
+                .map(|(name, value)| (name.as_str(), *value))
+                .collect();
+            self.set_pass_constants(device, index, &constants)?;
+        }
+        if data_changed {
+            self.set_pass_data(device, queue, index, &pass_config.data);
+        }
+        if let Some(lookup) = pass_config.lookup.as_ref().filter(|_| lookup_changed) {
+            self.replace_pass_lookup(device, queue, index, lookup.clone());
+        }
+        self.set_pass_sampler(device, index, pass_config.sampler.unwrap_or_default());
+        Ok(())
+    }
+
+    /// Apply the settings present in `config`.
+    ///
+    /// Everything that matches is applied; layers that are off, and passes, params
+    /// and outputs that don't exist in the pipeline, are skipped and reported in the
+    /// returned error. The chain and
+    /// the passes' sources, shader kinds, constants, data, lookups and samplers are
+    /// left alone.
+    ///
+    /// With the history enabled, the settings before it are recorded as a step to
+    /// undo (see [`Nnpipe::enable_history`]).
+    pub fn apply_config(&mut self, config: &PipelineConfig) -> Result<(), ConfigError> {
+        self.record(None);
+        self.apply_settings(config)
+    }
+
+    // Apply `config` like `apply_config`, without recording it in the history
+    pub(crate) fn apply_settings(&mut self, config: &PipelineConfig) -> Result<(), ConfigError> {
+        let bloom = &config.bloom;
+        if let Some(threshold) = bloom.threshold {
+            self.set_brightness_threshold(threshold);
+        }
+        if let Some(thresholds) = bloom.channel_thresholds {
+            self.set_channel_thresholds(Some(thresholds));
+        }
+        if let Some(intensity) = bloom.intensity {
+            self.set_bloom_intensity(intensity);
+        }
+        if let Some(scaling) = bloom.adaptive_blur_scaling {
+            self.set_adaptive_blur_scaling(scaling);
+        }
+        if let Some(radius) = bloom.max_blur_radius {
+            self.set_max_blur_radius(radius);
+        }
+        if let Some(curve) = bloom.intensity_curve {
+            self.set_intensity_curve(curve);
+        }
+        if let Some(strength) = bloom.horizontal_blur_strength {
+            self.set_horizontal_blur_strength(strength);
+        }
+        if let Some(strength) = bloom.vertical_blur_strength {
+            self.set_vertical_blur_strength(strength);
+        }
+        if let Some(stretch) = bloom.stretch {
+            self.set_bloom_stretch(stretch);
+        }
+        if let Some(angle) = bloom.blur_angle {
+            self.set_blur_angle(angle);
+        }
+        if let Some(saturation) = bloom.saturation {
+            self.set_bloom_saturation(saturation);
+        }
+        if let Some(hue_shift) = bloom.hue_shift {
+            self.set_bloom_hue_shift(hue_shift);
+        }
+        if let Some(enabled) = bloom.premultiplied_alpha {
+            self.set_premultiplied_alpha(enabled);
+        }
+        if let Some(enabled) = bloom.sanitize_scene {
+            self.set_sanitize_scene(enabled);
+        }
+        if let Some(filter) = bloom.upsample_filter {
+            self.set_upsample_filter(filter);
+        }
+
+        let mut missing = Vec::new();
+        #[cfg(feature = "bloom")]
+        if let Some(flare) = config.flare {
+            if !LensFlare::set(self, flare) {
+                missing.push("lens flare".to_string());
+            }
+        }
+        #[cfg(feature = "stylize")]
+        if let Some(sparkles) = config.sparkles {
+            if !Sparkles::set(self, sparkles) {
+                missing.push("sparkles".to_string());
+            }
+        }
+        if let Some(reflections) = config.reflections {
+            if !Reflections::set(self, reflections) {
+                missing.push("reflections".to_string());
+            }
+        }
+        #[cfg(feature = "stylize")]
+        if let Some(shadow) = config.drop_shadow {
+            if !DropShadow::set(self, shadow) {
+                missing.push("drop shadow".to_string());
+            }
+        }
+        #[cfg(feature = "stylize")]
+        if let Some(glow) = config.distance_glow {
+            if !DistanceGlow::set(self, glow) {
+                missing.push("distance glow".to_string());
+            }
+        }
+
+        for pass_config in &config.passes {
+            let index = match self.config_pass(pass_config) {
+                Ok(index) => index,
+                Err(pass) => {
+                    missing.push(pass);
+                    continue;
+                }
+            };
+            let pass = self.custom_pass_mut(index).expect("found by index");
+
+            if let Some(enabled) = pass_config.enabled {
+                pass.enabled = enabled;
+            }
+            if let Some(mix) = pass_config.mix {
+                pass.mix = mix;
+            }
+            for (name, value) in &pass_config.params {
+                if !pass.set_param(name, *value) {
+                    missing.push(format!("param '{name}' of pass '{}'", pass.label));
+                }
+            }
+        }
+
+        for output_config in &config.outputs {
+            let output = match output_config.index {
+                Some(index) => match self.output_mut(index) {
+                    Some(output) => output,
+                    None => {
+                        missing.push(format!("output {index}"));
+                        continue;
+                    }
+                },
+                None => self.scaler_mut(),
+            };
+            if let Some(warp) = &output_config.warp {
+                output.set_warp(warp.clone());
+            }
+            if let Some(projection) = output_config.projection {
+                output.set_projection(projection);
+            }
+        }
+
+        missing_error(&missing)
+    }
+
+    // Index of the pass `pass_config` applies to, or a description of it for errors
+    pub(crate) fn config_pass(&self, pass_config: &PassConfig) -> Result<usize, String> {
+        let index = pass_config.index.or_else(|| {
+            let label = pass_config.label.as_deref()?;
+            (0..)
+                .map_while(|index| self.custom_pass(index))
+                .position(|pass| pass.label == label)
+        });
+        match index {
+            Some(index) if self.custom_pass(index).is_some() => Ok(index),
+            _ => Err(match (&pass_config.index, &pass_config.label) {
+                (Some(index), _) => format!("pass {index}"),
+                (None, Some(label)) => format!("pass '{label}'"),
+                (None, None) => "pass without an index or label".to_string(),
+            }),
+        }
+    }
+}
+
+// The error listing what a config named but the pipeline lacks, if anything
+pub(crate) fn missing_error(missing: &[String]) -> Result<(), ConfigError> {
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(error(format!("no {}", missing.join(", no "))))
+    }
+}
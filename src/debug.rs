@@ -0,0 +1,254 @@
+// src/debug.rs
+//
+// Debug views
+//
+// Tuning the bloom means balancing stages against each other: a threshold that
+// looks right in the composite may leave the blur with nothing but noise. The quad
+// view shows the scene, the bright pass, the blur and the result side by side, so
+// the whole chain can be read at a glance. The false color view shows where the
+// scene's luminance sits against middle grey and display white, and what the
+// threshold sends into the bloom.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// What [`Nnpipe::process`](crate::Nnpipe::process) draws into its target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebugView {
+    /// The processed frame.
+    #[default]
+    Off,
+    /// A labeled 2x2 grid of the scene, the brightness pass, the blurred bloom and the
+    /// composite (after any effect passes), each stretched over a quarter of the
+    /// target.
+    Quad,
+    /// The scene's HDR luminance painted by exposure band, like a camera's false
+    /// color, with white stripes over what the brightness pass lets into the bloom:
+    ///
+    /// - purple: crushed, under 0.0025
+    /// - blue: near black, under 0.02
+    /// - green: middle grey, 0.13 to 0.25
+    /// - pink: a stop over middle grey, 0.3 to 0.45
+    /// - yellow: near white, 0.75 to 1
+    /// - red: over display white, 1 and up
+    ///
+    /// Luminance is linear; other ranges show as grey.
+    FalseColor,
+}
+
+// Draws a debug view. The chain renders into its own composite texture, so the
+// final frame can be shown next to the stages even when the target is the
+// pipeline's output texture.
+pub(crate) struct DebugLayer {
+    composite_view: wgpu::TextureView,
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl DebugLayer {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        vertex_shader: &wgpu::ShaderModule,
+        sampler: &wgpu::Sampler,
+        scene_view: &wgpu::TextureView,
+        brightness_view: &wgpu::TextureView,
+        blur_view: &wgpu::TextureView,
+        view: DebugView,
+    ) -> Self {
+        let [width, height] = scene_view.size();
+        let composite_view = wgpu::TextureBuilder::new()
+            .size([width, height])
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device)
+            .view()
+            .build();
+
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Debug View Uniform Buffer",
+            bytemuck::cast_slice(&[1.0f32, 1.0, 0.0, 0.0]),
+        );
+
+        let shader = match view {
+            DebugView::FalseColor => cache.shader(
+                device,
+                "False Color Shader",
+                &expand(include_str!("shaders/false_color.wgsl")),
+            ),
+            _ => cache.shader(
+                device,
+                "Quad View Shader",
+                include_str!("shaders/quad_view.wgsl"),
+            ),
+        };
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Debug View Bind Group Layout",
+            &[
+                // Scene, brightness, blur and composite textures
+                texture_entry(0),
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                // Sampler binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Target size binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Debug View Pipeline Layout", &bind_group_layout);
+
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            vertex_shader,
+            &shader,
+            "Debug View Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            [scene_view, brightness_view, blur_view, &composite_view],
+            sampler,
+            &uniform_buffer,
+        );
+
+        Self {
+            composite_view,
+            uniform_buffer,
+            pipeline,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    // Where the chain renders while the debug view is shown
+    pub fn composite_view(&self) -> &wgpu::TextureView {
+        &self.composite_view
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // A bind group showing `scene_view` as the scene instead of the pipeline's own
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        sampler: &wgpu::Sampler,
+        scene_view: &wgpu::TextureView,
+        brightness_view: &wgpu::TextureView,
+        blur_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        create_bind_group(
+            device,
+            &self.bind_group_layout,
+            [scene_view, brightness_view, blur_view, &self.composite_view],
+            sampler,
+            &self.uniform_buffer,
+        )
+    }
+
+    // Set the size of the target the next frame is drawn into. Must precede the
+    // frame's uniform upload.
+    pub fn set_target_size(&self, size: [u32; 2]) {
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&[size[0] as f32, size[1] as f32]));
+    }
+
+    // Record the debug view into `view`, with `bind_group` or the one showing the
+    // pipeline's scene
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_group: Option<&wgpu::BindGroup>,
+        view: &wgpu::TextureView,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug view pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group.unwrap_or(&self.bind_group), &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    views: [&wgpu::TextureView; 4],
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    let mut entries: Vec<wgpu::BindGroupEntry> = views
+        .iter()
+        .enumerate()
+        .map(|(binding, view)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: wgpu::BindingResource::TextureView(view),
+        })
+        .collect();
+    entries.push(wgpu::BindGroupEntry {
+        binding: 4,
+        resource: wgpu::BindingResource::Sampler(sampler),
+    });
+    entries.push(wgpu::BindGroupEntry {
+        binding: 5,
+        resource: wgpu::BindingResource::Buffer(uniform_buffer.as_entire_buffer_binding()),
+    });
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Debug View Bind Group"),
+        layout,
+        entries: &entries,
+    })
+}
@@ -0,0 +1,349 @@
+// src/distance_glow.rs
+//
+// Distance-field glow
+//
+// The Gaussian bloom spreads light softly and fades fast, so it can't draw a crisp
+// halo some way off a shape. This glow measures how far every pixel is from the
+// silhouette in the scene matte instead, with a jump flood: the matte's pixels are
+// seeds, and passes with halving steps, from about half the frame down to a single
+// pixel, have each pixel keep the nearest seed it or its neighbors a step away know
+// of. The distances are exact enough for any band around the shape to be lit, hard
+// edged or soft, near or far. The glow is added over the composite, ahead of the
+// effect passes.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// Settings of the distance glow, see
+/// [`Nnpipe::set_distance_glow`](crate::Nnpipe::set_distance_glow).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct DistanceGlow {
+    /// Linear RGB color of the glow.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance from the silhouette at which the glow starts, in pixels. Above 0 the
+    /// glow floats off the shape as a ring.
+    pub offset: f32,
+    /// Width of the glowing band past the offset, in pixels.
+    pub radius: f32,
+    /// Part of the band the glow fades out over, from 0 for a hard outline to 1 for a
+    /// glow fading from its inner edge.
+    pub softness: f32,
+}
+
+impl Default for DistanceGlow {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 0.6, 0.2],
+            intensity: 1.0,
+            offset: 0.0,
+            radius: 16.0,
+            softness: 1.0,
+        }
+    }
+}
+
+impl DistanceGlow {
+    // The settings as the shader's uniform
+    fn uniform(&self) -> [f32; 8] {
+        let [r, g, b] = self.color;
+        [
+            r,
+            g,
+            b,
+            self.intensity,
+            self.offset.max(0.0),
+            self.radius.max(0.0),
+            self.softness.clamp(0.0, 1.0),
+            0.0,
+        ]
+    }
+}
+
+pub(crate) struct DistanceGlowLayer {
+    settings: DistanceGlow,
+    uniform_buffer: UniformBuffer,
+    bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    seed_pipeline: Arc<wgpu::RenderPipeline>,
+    flood_pipeline: Arc<wgpu::RenderPipeline>,
+    glow_pipeline: Arc<wgpu::RenderPipeline>,
+    // Nearest seeds, ping-ponged between by the flood steps
+    seed_views: [wgpu::TextureView; 2],
+    // The step of each flood pass, largest first
+    step_buffers: Vec<wgpu::Buffer>,
+    // Reads the matte into the first seed texture
+    seed_bind_group: wgpu::BindGroup,
+    // One per flood step, reading the seed texture the step before wrote
+    flood_bind_groups: Vec<wgpu::BindGroup>,
+    // Reads the seed texture the last step wrote
+    glow_bind_group: wgpu::BindGroup,
+}
+
+impl DistanceGlowLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        matte_view: &wgpu::TextureView,
+        size: [u32; 2],
+        settings: DistanceGlow,
+    ) -> Self {
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Distance Glow Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        // The seeds are unfilterable 32-bit floats, which the matte can stand in for
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Distance Glow Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                uniform_entry(1),
+                uniform_entry(2),
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Distance Glow Pipeline Layout", &bind_group_layout);
+
+        // Seeds hold pixel coordinates, too large for half floats on big frames
+        let seed_format = wgpu::TextureFormat::Rgba32Float;
+        let pipeline = |label, source: &str, format, blend| {
+            let shader = cache.shader(device, label, &expand(source));
+            cache.pipeline(
+                device,
+                &pipeline_layout,
+                &shader,
+                &shader,
+                label,
+                format,
+                blend,
+            )
+        };
+        let seed_pipeline = pipeline(
+            "Jump Flood Seed Pipeline",
+            include_str!("shaders/jfa_seed.wgsl"),
+            seed_format,
+            None,
+        );
+        let flood_pipeline = pipeline(
+            "Jump Flood Pipeline",
+            include_str!("shaders/jfa_flood.wgsl"),
+            seed_format,
+            None,
+        );
+        // The glow adds its light to the frame
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let glow_pipeline = pipeline(
+            "Distance Glow Pipeline",
+            include_str!("shaders/distance_glow.wgsl"),
+            wgpu::TextureFormat::Rgba16Float,
+            Some(wgpu::BlendState {
+                color: additive,
+                alpha: additive,
+            }),
+        );
+
+        let seed_views = [(); 2].map(|_| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                )
+                .format(seed_format)
+                .build(device)
+                .view()
+                .build()
+        });
+
+        // Halving from the largest power of two below the longer side
+        let longer = size[0].max(size[1]).max(2);
+        let mut step = 1 << (31 - (longer - 1).leading_zeros());
+        let mut step_buffers = Vec::new();
+        while step >= 1 {
+            step_buffers.push(
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Jump Flood Step Buffer"),
+                    contents: bytemuck::cast_slice(&[step as f32, 0.0, 0.0, 0.0]),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                }),
+            );
+            step /= 2;
+        }
+
+        let create_bind_group = |view, step_buffer: &wgpu::Buffer| {
+            create_bind_group(
+                device,
+                &bind_group_layout,
+                view,
+                &uniform_buffer,
+                step_buffer,
+            )
+        };
+        let seed_bind_group = create_bind_group(matte_view, &step_buffers[0]);
+        let flood_bind_groups = step_buffers
+            .iter()
+            .enumerate()
+            .map(|(i, step_buffer)| create_bind_group(&seed_views[i % 2], step_buffer))
+            .collect();
+        let last = &seed_views[step_buffers.len() % 2];
+        let glow_bind_group = create_bind_group(last, &step_buffers[0]);
+
+        Self {
+            settings,
+            uniform_buffer,
+            bind_group_layout,
+            seed_pipeline,
+            flood_pipeline,
+            glow_pipeline,
+            seed_views,
+            step_buffers,
+            seed_bind_group,
+            flood_bind_groups,
+            glow_bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> DistanceGlow {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: DistanceGlow) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Seed from another matte, after the matte was added, removed or replaced
+    pub fn set_matte_view(&mut self, device: &wgpu::Device, matte_view: &wgpu::TextureView) {
+        self.seed_bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            matte_view,
+            &self.uniform_buffer,
+            &self.step_buffers[0],
+        );
+    }
+
+    // Flood the distances to the matte's silhouette and add the glow over `target`
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "distance_glow", skip_all)
+    )]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let seed = (
+            "Jump flood seed pass",
+            &self.seed_views[0],
+            &self.seed_pipeline,
+            &self.seed_bind_group,
+        );
+        let floods = self
+            .flood_bind_groups
+            .iter()
+            .enumerate()
+            .map(|(i, bind_group)| {
+                (
+                    "Jump flood pass",
+                    &self.seed_views[1 - i % 2],
+                    &self.flood_pipeline,
+                    bind_group,
+                )
+            });
+        for (label, view, pipeline, bind_group) in std::iter::once(seed).chain(floods) {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Distance glow pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.glow_pipeline);
+        pass.set_bind_group(0, &self.glow_bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    uniform_buffer: &UniformBuffer,
+    step_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Distance Glow Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: step_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
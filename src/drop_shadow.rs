@@ -0,0 +1,392 @@
+// src/drop_shadow.rs
+//
+// Drop shadow
+//
+// For scenes drawn with alpha: the scene's alpha, shifted by the shadow's offset, is
+// blurred with the bloom's blur shader at a fixed radius and composited under the
+// scene in the shadow's color, ahead of the brightness pass, so the bloom and every
+// effect after it see the shadowed scene. Frames drawn into the pipeline's scene
+// texture are shadowed in place, through a copy of the scene; textures handed to
+// `Nnpipe::process_texture` are shadowed into the copy, which stands in for them.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// Settings of the drop shadow, see
+/// [`Nnpipe::set_drop_shadow`](crate::Nnpipe::set_drop_shadow).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct DropShadow {
+    /// Offset of the shadow from the content casting it, in pixels, right and down.
+    pub offset: [f32; 2],
+    /// Radius of the shadow's blur in pixels, at least 1.
+    pub blur_radius: f32,
+    /// Linear RGB color of the shadow.
+    pub color: [f32; 3],
+    /// Opacity of the shadow under fully opaque content, from 0 to 1.
+    pub opacity: f32,
+}
+
+impl Default for DropShadow {
+    fn default() -> Self {
+        Self {
+            offset: [8.0, 8.0],
+            blur_radius: 12.0,
+            color: [0.0; 3],
+            opacity: 0.6,
+        }
+    }
+}
+
+impl DropShadow {
+    // The settings as the shaders' uniform
+    fn uniform(&self) -> [f32; 8] {
+        let [x, y] = self.offset;
+        let [r, g, b] = self.color;
+        [x, y, self.opacity.clamp(0.0, 1.0), 0.0, r, g, b, 0.0]
+    }
+}
+
+pub(crate) struct DropShadowLayer {
+    settings: DropShadow,
+    uniform_buffer: UniformBuffer,
+    // The blur's radius, which the alpha of 1 in the silhouette keeps it at
+    max_radius_buffer: UniformBuffer,
+    bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    silhouette_pipeline: Arc<wgpu::RenderPipeline>,
+    blur_pipeline: Arc<wgpu::RenderPipeline>,
+    shadow_pipeline: Arc<wgpu::RenderPipeline>,
+    // The silhouette and the blurred shadow, ping-ponged through the blur
+    shadow_views: [wgpu::TextureView; 2],
+    blur_bind_groups: [wgpu::BindGroup; 2],
+    // The copy of the scene the shadow is composited under, or into
+    scene_texture: wgpu::Texture,
+    scene_view: wgpu::TextureView,
+    // Silhouette and shadow bind groups reading the copy
+    scene_bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl DropShadowLayer {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        blur_bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        alpha_mode_buffer: &wgpu::Buffer,
+        size: [u32; 2],
+        settings: DropShadow,
+    ) -> Self {
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Drop Shadow Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+        let max_radius_buffer = UniformBuffer::new(
+            device,
+            "Drop Shadow Blur Radius Buffer",
+            bytemuck::cast_slice(&[settings.blur_radius.max(1.0)]),
+        );
+
+        let texture_entry = |binding, filterable| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Drop Shadow Bind Group Layout",
+            &[
+                texture_entry(0, true),
+                texture_entry(1, false),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                uniform_entry(3),
+                uniform_entry(4),
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Drop Shadow Pipeline Layout", &bind_group_layout);
+        let blur_layout =
+            cache.pipeline_layout(device, "Blur Pipeline Layout", blur_bind_group_layout);
+
+        let format = wgpu::TextureFormat::Rgba16Float;
+        let pipeline = |layout, label, source: &str| {
+            let shader = cache.shader(device, label, &expand(source));
+            cache.pipeline(device, layout, &shader, &shader, label, format, None)
+        };
+        let silhouette_pipeline = pipeline(
+            &pipeline_layout,
+            "Drop Shadow Silhouette Pipeline",
+            include_str!("shaders/drop_shadow_silhouette.wgsl"),
+        );
+        let blur_pipeline = pipeline(
+            &blur_layout,
+            "Drop Shadow Blur Pipeline",
+            include_str!("shaders/blur.wgsl"),
+        );
+        let shadow_pipeline = pipeline(
+            &pipeline_layout,
+            "Drop Shadow Pipeline",
+            include_str!("shaders/drop_shadow.wgsl"),
+        );
+
+        let target = |usage| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | usage,
+                )
+                .format(format)
+                .build(device)
+        };
+        let shadow_views = [(); 2].map(|_| target(wgpu::TextureUsages::empty()).view().build());
+        let scene_texture = target(wgpu::TextureUsages::COPY_DST);
+        let scene_view = scene_texture.view().build();
+
+        // Horizontally from the silhouette, then vertically back into its texture
+        let blur_bind_groups = [([1.0f32, 0.0], 0), ([0.0, 1.0], 1)].map(|(direction, source)| {
+            let direction_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Drop Shadow Blur Direction Buffer"),
+                contents: bytemuck::cast_slice(&direction),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let scaling_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Drop Shadow Blur Scaling Buffer"),
+                contents: bytemuck::cast_slice(&[1.0f32]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Drop Shadow Blur Bind Group"),
+                layout: blur_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&shadow_views[source]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: direction_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: scaling_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: max_radius_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let scene_bind_groups = create_scene_bind_groups(
+            device,
+            &bind_group_layout,
+            &scene_view,
+            &shadow_views,
+            sampler,
+            &uniform_buffer,
+            alpha_mode_buffer,
+        );
+
+        Self {
+            settings,
+            uniform_buffer,
+            max_radius_buffer,
+            bind_group_layout,
+            silhouette_pipeline,
+            blur_pipeline,
+            shadow_pipeline,
+            shadow_views,
+            blur_bind_groups,
+            scene_texture,
+            scene_view,
+            scene_bind_groups,
+        }
+    }
+
+    pub fn settings(&self) -> DropShadow {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: DropShadow) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+        self.max_radius_buffer
+            .write(0, bytemuck::cast_slice(&[settings.blur_radius.max(1.0)]));
+    }
+
+    pub fn uniform_buffers(&self) -> [&UniformBuffer; 2] {
+        [&self.uniform_buffer, &self.max_radius_buffer]
+    }
+
+    // The shadowed copy of a scene passed to `scene_bind_groups`
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.scene_view
+    }
+
+    // Silhouette and shadow bind groups for shadowing another scene view into the
+    // layer's copy of the scene, with the pipeline's sampler and alpha mode
+    pub fn scene_bind_groups(
+        &self,
+        device: &wgpu::Device,
+        scene_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        alpha_mode_buffer: &wgpu::Buffer,
+    ) -> [wgpu::BindGroup; 2] {
+        create_scene_bind_groups(
+            device,
+            &self.bind_group_layout,
+            scene_view,
+            &self.shadow_views,
+            sampler,
+            &self.uniform_buffer,
+            alpha_mode_buffer,
+        )
+    }
+
+    // Composite the shadow under the scene in `scene_bind_groups`, into the layer's
+    // copy, or else under the pipeline's `scene_texture`, in place
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "drop_shadow", skip_all)
+    )]
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scene_texture: &wgpu::Texture,
+        scene_view: &wgpu::TextureView,
+        scene_bind_groups: Option<&[wgpu::BindGroup; 2]>,
+    ) {
+        let ([silhouette_bind_group, shadow_bind_group], target) = match scene_bind_groups {
+            Some(bind_groups) => (bind_groups, &self.scene_view),
+            None => {
+                encoder.copy_texture_to_texture(
+                    scene_texture.as_image_copy(),
+                    self.scene_texture.as_image_copy(),
+                    scene_texture.extent(),
+                );
+                (&self.scene_bind_groups, scene_view)
+            }
+        };
+
+        let passes = [
+            (
+                "Drop shadow silhouette pass",
+                &self.shadow_views[0],
+                &self.silhouette_pipeline,
+                silhouette_bind_group,
+            ),
+            (
+                "Drop shadow horizontal blur pass",
+                &self.shadow_views[1],
+                &self.blur_pipeline,
+                &self.blur_bind_groups[0],
+            ),
+            (
+                "Drop shadow vertical blur pass",
+                &self.shadow_views[0],
+                &self.blur_pipeline,
+                &self.blur_bind_groups[1],
+            ),
+            (
+                "Drop shadow pass",
+                target,
+                &self.shadow_pipeline,
+                shadow_bind_group,
+            ),
+        ];
+        for (label, view, pipeline, bind_group) in passes {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+    }
+}
+
+// The silhouette pass reads the scene and the shadow pass the blurred shadow, which
+// the silhouette pass writes, so each binds the other shadow texture
+fn create_scene_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    scene_view: &wgpu::TextureView,
+    shadow_views: &[wgpu::TextureView; 2],
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &UniformBuffer,
+    alpha_mode_buffer: &wgpu::Buffer,
+) -> [wgpu::BindGroup; 2] {
+    [&shadow_views[1], &shadow_views[0]].map(|shadow_view| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Drop Shadow Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(scene_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(shadow_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: alpha_mode_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    })
+}
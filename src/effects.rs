@@ -0,0 +1,785 @@
+// src/effects.rs
+//
+// Built-in effect passes
+//
+// Each effect is a regular pass running one of the crate's shaders, so it can be
+// enabled, reordered with custom passes and tweaked through its params like any other.
+
+use nannou::wgpu;
+
+use crate::error::{NnpipeError, Result};
+use crate::nnpipe::Nnpipe;
+use crate::pass::{LookupTexture, PassInput};
+use crate::trigger::Trigger;
+
+const BORDER_SOURCE: &str = include_str!("shaders/border.wgsl");
+const CONVOLUTION_SOURCE: &str = include_str!("shaders/convolution.wgsl");
+const EMBOSS_SOURCE: &str = include_str!("shaders/emboss.wgsl");
+const FOCUS_BLUR_SOURCE: &str = include_str!("shaders/focus_blur.wgsl");
+const LUMA_KEY_SOURCE: &str = include_str!("shaders/luma_key.wgsl");
+const PALETTE_SOURCE: &str = include_str!("shaders/palette.wgsl");
+const PIP_SOURCE: &str = include_str!("shaders/pip.wgsl");
+const SCANLINE_SOURCE: &str = include_str!("shaders/scanline.wgsl");
+const SHOCKWAVE_SOURCE: &str = include_str!("shaders/shockwave.wgsl");
+
+// Colors a palette pass can hold; every pixel is compared against each of them
+const MAX_PALETTE_COLORS: usize = 256;
+
+/// A frame around the picture, for [`Nnpipe::add_border_pass`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Border {
+    /// Width of the border in pixels.
+    pub thickness: f32,
+    /// Straight RGBA color of the border.
+    pub color: [f32; 4],
+    /// Width in pixels over which the border fades into the picture.
+    pub feather: f32,
+    /// Radius in pixels of the corners of the opening; 0 keeps them square.
+    pub corner_radius: f32,
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Self {
+            thickness: 16.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+            feather: 0.0,
+            corner_radius: 0.0,
+        }
+    }
+}
+
+impl Border {
+    // Params of the border pass
+    fn params(&self) -> [(&'static str, f32); 7] {
+        let [r, g, b, a] = self.color;
+        [
+            ("thickness", self.thickness.max(0.0)),
+            ("feather", self.feather.max(0.0)),
+            ("radius", self.corner_radius.max(0.0)),
+            ("color_r", r),
+            ("color_g", g),
+            ("color_b", b),
+            ("color_a", a),
+        ]
+    }
+}
+
+/// An N x N convolution kernel for [`Nnpipe::add_convolution_pass`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConvolutionKernel {
+    size: u32,
+    weights: Vec<f32>,
+
+    /// Divide the result by the sum of the weights, so the kernel keeps the frame's
+    /// brightness. Ignored for kernels whose weights sum to zero (edge detection).
+    pub normalize: bool,
+
+    /// Added to every channel after the convolution, e.g. 0.5 to center an emboss.
+    pub bias: f32,
+}
+
+impl ConvolutionKernel {
+    /// A `size` x `size` kernel from its weights in row-major order. The kernel is
+    /// centered on the pixel, rounding toward the top left for even sizes.
+    ///
+    /// Fails with [`NnpipeError::InvalidKernel`] for a size of 0, and with
+    /// [`NnpipeError::PixelCount`] if there aren't `size` x `size` weights.
+    pub fn new(size: u32, weights: Vec<f32>) -> Result<Self> {
+        if size == 0 {
+            return Err(NnpipeError::InvalidKernel {
+                width: size,
+                height: size,
+            });
+        }
+        if weights.len() != size as usize * size as usize {
+            return Err(NnpipeError::PixelCount {
+                width: size,
+                height: size,
+                len: weights.len(),
+            });
+        }
+        Ok(Self::from_weights(size, weights))
+    }
+
+    // A kernel from weights known to match `size`
+    fn from_weights(size: u32, weights: Vec<f32>) -> Self {
+        Self {
+            size,
+            weights,
+            normalize: false,
+            bias: 0.0,
+        }
+    }
+
+    pub fn normalized(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    pub fn with_bias(mut self, bias: f32) -> Self {
+        self.bias = bias;
+        self
+    }
+
+    /// Sharpen by `amount`, 0 leaving the frame unchanged.
+    pub fn sharpen(amount: f32) -> Self {
+        let a = -amount;
+        Self::from_weights(3, vec![0.0, a, 0.0, a, 1.0 + 4.0 * amount, a, 0.0, a, 0.0])
+    }
+
+    /// A relief lit from the top left, centered on mid grey.
+    pub fn emboss() -> Self {
+        Self::from_weights(3, vec![-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0]).with_bias(0.5)
+    }
+
+    /// An even blur over `size` x `size` pixels. Fails like [`ConvolutionKernel::new`]
+    /// for a size of 0.
+    pub fn box_blur(size: u32) -> Result<Self> {
+        Ok(Self::new(size, vec![1.0; (size * size) as usize])?.normalized(true))
+    }
+
+    /// Blur along a line of `length` pixels at `angle` radians.
+    pub fn motion_blur(length: u32, angle: f32) -> Self {
+        let size = length.max(1) | 1;
+        let center = (size / 2) as f32;
+        let (sin, cos) = angle.sin_cos();
+        let weights = (0..size * size)
+            .map(|i| {
+                let x = (i % size) as f32 - center;
+                let y = (i / size) as f32 - center;
+                // Distance from the line through the center
+                (1.0 - (x * sin - y * cos).abs()).max(0.0)
+            })
+            .collect();
+        Self::from_weights(size, weights).normalized(true)
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    // Params of the convolution pass: size, scale, bias
+    fn params(&self) -> [(&'static str, f32); 3] {
+        let sum: f32 = self.weights.iter().sum();
+        let scale = if self.normalize && sum.abs() > f32::EPSILON {
+            1.0 / sum
+        } else {
+            1.0
+        };
+        [
+            ("size", self.size as f32),
+            ("scale", scale),
+            ("bias", self.bias),
+        ]
+    }
+}
+
+/// Shape of the sharp region of a [`FocusBlur`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FocusShape {
+    /// A circle around the center.
+    #[default]
+    Radial,
+    /// A band through the center, like a tilt-shift lens.
+    Linear,
+}
+
+/// A fake depth of field for scenes without depth, for [`Nnpipe::add_focus_blur_pass`].
+///
+/// The blur grows with the screen-space distance from a focus region instead of the
+/// distance from a focal plane. Distances are in units of the frame's height, so a
+/// radial focus stays round at any aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FocusBlur {
+    pub shape: FocusShape,
+    /// Center of the focus region in UV, (0, 0) being the top left.
+    pub center: [f32; 2],
+    /// Distance from the center that stays sharp.
+    pub radius: f32,
+    /// Distance over which the blur ramps up beyond the radius.
+    pub falloff: f32,
+    /// Blur radius in pixels, reached at `radius + falloff`.
+    pub max_blur: f32,
+    /// Clockwise rotation of a linear band in radians, 0 being horizontal.
+    pub angle: f32,
+}
+
+impl Default for FocusBlur {
+    fn default() -> Self {
+        Self {
+            shape: FocusShape::Radial,
+            center: [0.5, 0.5],
+            radius: 0.2,
+            falloff: 0.3,
+            max_blur: 8.0,
+            angle: 0.0,
+        }
+    }
+}
+
+impl FocusBlur {
+    // Params of the focus blur pass
+    fn params(&self) -> [(&'static str, f32); 7] {
+        let shape = match self.shape {
+            FocusShape::Radial => 0.0,
+            FocusShape::Linear => 1.0,
+        };
+        [
+            ("shape", shape),
+            ("center_x", self.center[0]),
+            ("center_y", self.center[1]),
+            ("radius", self.radius),
+            ("falloff", self.falloff),
+            ("max_blur", self.max_blur),
+            ("angle", self.angle),
+        ]
+    }
+}
+
+/// Whose brightness a [`LumaKey`] keys on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LumaKeySource {
+    /// The frame: the second texture shows through its bright areas, e.g. a video
+    /// filling the highlights of the scene.
+    #[default]
+    Frame,
+    /// The second texture: its bright areas are laid over the frame, e.g. a video of
+    /// white text or fire on black.
+    Layer,
+}
+
+/// Layering a second texture over the frame by brightness, for
+/// [`Nnpipe::add_luma_key_pass`]. The luma key is the chroma key's counterpart for
+/// footage shot against black or white rather than a colored screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LumaKey {
+    pub source: LumaKeySource,
+    /// Luma, in the frame's linear scale, above which the second texture shows.
+    pub threshold: f32,
+    /// Width of the luma range the key fades in over, centered on the threshold; 0
+    /// gives a hard edge.
+    pub softness: f32,
+    /// Show the second texture below the threshold instead.
+    pub invert: bool,
+    /// Opacity of the second texture where it's fully keyed in.
+    pub opacity: f32,
+}
+
+impl Default for LumaKey {
+    fn default() -> Self {
+        Self {
+            source: LumaKeySource::Frame,
+            threshold: 0.5,
+            softness: 0.1,
+            invert: false,
+            opacity: 1.0,
+        }
+    }
+}
+
+impl LumaKey {
+    // Params of the luma key pass
+    fn params(&self) -> [(&'static str, f32); 5] {
+        let source = match self.source {
+            LumaKeySource::Frame => 0.0,
+            LumaKeySource::Layer => 1.0,
+        };
+        [
+            ("source", source),
+            ("threshold", self.threshold),
+            ("softness", self.softness.max(0.0)),
+            ("invert", if self.invert { 1.0 } else { 0.0 }),
+            ("opacity", self.opacity),
+        ]
+    }
+}
+
+/// Placement of a picture-in-picture inset, for [`Nnpipe::add_pip_pass`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PictureInPicture {
+    /// Center of the inset in UV, (0, 0) being the top left.
+    pub center: [f32; 2],
+    /// Width of the inset as a fraction of the frame's width. The height follows
+    /// from the inset texture's aspect ratio.
+    pub width: f32,
+    /// Width of the border around the inset in pixels; 0 leaves it out.
+    pub border: f32,
+    /// Straight RGBA color of the border.
+    pub border_color: [f32; 4],
+    /// Opacity of the inset and its border.
+    pub opacity: f32,
+}
+
+impl Default for PictureInPicture {
+    fn default() -> Self {
+        Self {
+            center: [0.8, 0.2],
+            width: 0.25,
+            border: 2.0,
+            border_color: [1.0, 1.0, 1.0, 1.0],
+            opacity: 1.0,
+        }
+    }
+}
+
+impl PictureInPicture {
+    // Params of the picture-in-picture pass
+    fn params(&self) -> [(&'static str, f32); 9] {
+        let [r, g, b, a] = self.border_color;
+        [
+            ("center_x", self.center[0]),
+            ("center_y", self.center[1]),
+            ("width", self.width),
+            ("border", self.border.max(0.0)),
+            ("border_r", r),
+            ("border_g", g),
+            ("border_b", b),
+            ("border_a", a),
+            ("opacity", self.opacity),
+        ]
+    }
+}
+
+/// What drives the shift of each row of a [`ScanlineDisplacement`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanlineMode {
+    /// A sine wave rolling down the frame, a rolling shutter wobble.
+    #[default]
+    Sine,
+    /// Smooth random bands that flicker and drift, a broken-signal look.
+    Noise,
+    /// A waveform set with [`Nnpipe::set_scanline_waveform`], e.g. audio samples,
+    /// stretched over the frame's height.
+    Waveform,
+}
+
+/// Sideways shifting of the frame's rows, for [`Nnpipe::add_scanline_pass`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScanlineDisplacement {
+    pub mode: ScanlineMode,
+    /// Largest shift in pixels. Rows shifted past an edge wrap around to the other.
+    pub amplitude: f32,
+    /// Sine waves or noise bands per frame height. Unused by waveforms.
+    pub frequency: f32,
+    /// How fast the pattern moves down the frame, in frame heights per second for
+    /// waveforms and cycles per second otherwise. Negative moves it up.
+    pub speed: f32,
+}
+
+impl Default for ScanlineDisplacement {
+    fn default() -> Self {
+        Self {
+            mode: ScanlineMode::Sine,
+            amplitude: 8.0,
+            frequency: 3.0,
+            speed: 0.5,
+        }
+    }
+}
+
+impl ScanlineDisplacement {
+    // Params of the scanline pass
+    fn params(&self) -> [(&'static str, f32); 4] {
+        let mode = match self.mode {
+            ScanlineMode::Sine => 0.0,
+            ScanlineMode::Noise => 1.0,
+            ScanlineMode::Waveform => 2.0,
+        };
+        [
+            ("mode", mode),
+            ("amplitude", self.amplitude),
+            ("frequency", self.frequency),
+            ("speed", self.speed),
+        ]
+    }
+}
+
+/// A fixed set of colors for [`Nnpipe::add_palette_pass`], at most 256 of them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    // Linear RGB, like the frame
+    colors: Vec<[f32; 3]>,
+}
+
+impl Palette {
+    /// A palette of linear RGB colors. Colors past the first 256 are dropped.
+    ///
+    /// Fails with [`NnpipeError::EmptyPalette`] without colors.
+    pub fn new(mut colors: Vec<[f32; 3]>) -> Result<Self> {
+        if colors.is_empty() {
+            return Err(NnpipeError::EmptyPalette);
+        }
+        colors.truncate(MAX_PALETTE_COLORS);
+        Ok(Self { colors })
+    }
+
+    /// A palette of `0xRRGGBB` sRGB colors, the way palettes are usually published.
+    /// Fails like [`Palette::new`].
+    pub fn from_hex(colors: &[u32]) -> Result<Self> {
+        Self::new(hex_colors(colors))
+    }
+
+    /// The four greens of the original Game Boy.
+    pub fn game_boy() -> Self {
+        Self {
+            colors: hex_colors(&[0x0f380f, 0x306230, 0x8bac0f, 0x9bbc0f]),
+        }
+    }
+
+    /// The PICO-8 fantasy console's 16 colors.
+    pub fn pico8() -> Self {
+        let colors = hex_colors(&[
+            0x000000, 0x1d2b53, 0x7e2553, 0x008751, 0xab5236, 0x5f574f, 0xc2c3c7, 0xfff1e8,
+            0xff004d, 0xffa300, 0xffec27, 0x00e436, 0x29adff, 0x83769c, 0xff77a8, 0xffccaa,
+        ]);
+        Self { colors }
+    }
+
+    /// Black and white.
+    pub fn monochrome() -> Self {
+        Self {
+            colors: vec![[0.0; 3], [1.0; 3]],
+        }
+    }
+
+    pub fn colors(&self) -> &[[f32; 3]] {
+        &self.colors
+    }
+
+    // The colors in the first row, their Lab coordinates in the second
+    fn lookup(&self) -> LookupTexture {
+        let colors = self.colors.iter().map(|&[r, g, b]| [r, g, b, 1.0]);
+        let labs = self.colors.iter().map(|&color| {
+            let [l, a, b] = linear_to_lab(color);
+            [l, a, b, 1.0]
+        });
+        LookupTexture {
+            width: self.colors.len() as u32,
+            height: 2,
+            data: colors.chain(labs).collect(),
+        }
+    }
+}
+
+// `0xRRGGBB` sRGB colors in linear RGB
+fn hex_colors(colors: &[u32]) -> Vec<[f32; 3]> {
+    colors
+        .iter()
+        .map(|hex| [hex >> 16, hex >> 8, *hex].map(|c| srgb_to_linear((c & 0xff) as f32 / 255.0)))
+        .collect()
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Same conversion as the palette shader's, so colors match their own entries exactly
+fn linear_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| c.clamp(0.0, 1.0));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+impl Nnpipe {
+    /// Add a pass framing the picture with a border and return its index. See
+    /// [`Border`].
+    ///
+    /// Added last, the border stays clean over every other effect, e.g. for gallery
+    /// and stream layouts.
+    pub fn add_border_pass(&mut self, device: &wgpu::Device, border: &Border) -> usize {
+        self.add_custom_pass(device, "Border", BORDER_SOURCE, &border.params())
+    }
+
+    /// Change the border of a pass added with [`Nnpipe::add_border_pass`].
+    pub fn set_border(&mut self, index: usize, border: &Border) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in border.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Add a pass convolving the frame with `kernel` and return its index.
+    ///
+    /// Each pixel costs size² texture loads, so large kernels get expensive quickly;
+    /// wide blurs are better left to the bloom.
+    pub fn add_convolution_pass(
+        &mut self,
+        device: &wgpu::Device,
+        kernel: &ConvolutionKernel,
+    ) -> usize {
+        self.add_custom_pass_with_data(
+            device,
+            "Convolution",
+            CONVOLUTION_SOURCE,
+            &kernel.params(),
+            kernel.weights(),
+        )
+    }
+
+    /// Swap the kernel of a pass added with [`Nnpipe::add_convolution_pass`].
+    pub fn set_convolution_kernel(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        kernel: &ConvolutionKernel,
+    ) {
+        self.set_pass_data(device, queue, index, kernel.weights());
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in kernel.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Add an emboss pass, a stamped-metal relief of the frame, and return its index.
+    ///
+    /// `light_angle` is where the light comes from, in radians clockwise from the
+    /// left; `depth` the height of the relief; `blend` goes from a grey relief (0) to
+    /// the frame's own colors shaded by it (1). All three can be changed later as the
+    /// pass's `light_angle`, `depth` and `blend` params.
+    pub fn add_emboss_pass(
+        &mut self,
+        device: &wgpu::Device,
+        light_angle: f32,
+        depth: f32,
+        blend: f32,
+    ) -> usize {
+        self.add_custom_pass(
+            device,
+            "Emboss",
+            EMBOSS_SOURCE,
+            &[
+                ("light_angle", light_angle),
+                ("depth", depth),
+                ("blend", blend),
+            ],
+        )
+    }
+
+    /// Add a gradient focus pass, a cheap depth of field for 2D scenes, and return its
+    /// index.
+    pub fn add_focus_blur_pass(&mut self, device: &wgpu::Device, focus: &FocusBlur) -> usize {
+        self.add_custom_pass(device, "Focus Blur", FOCUS_BLUR_SOURCE, &focus.params())
+    }
+
+    /// Move or reshape the focus of a pass added with [`Nnpipe::add_focus_blur_pass`].
+    pub fn set_focus_blur(&mut self, index: usize, focus: &FocusBlur) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in focus.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Add a pass compositing `layer` over the frame by brightness and return its
+    /// index. See [`LumaKey`].
+    ///
+    /// `layer` must be a filterable float texture with `TEXTURE_BINDING` usage; it's
+    /// stretched over the frame. Swap it, e.g. for the latest frame of a video, with
+    /// [`Nnpipe::set_luma_key_layer`].
+    pub fn add_luma_key_pass(
+        &mut self,
+        device: &wgpu::Device,
+        key: &LumaKey,
+        layer: &wgpu::TextureView,
+    ) -> usize {
+        self.add_custom_pass_with_inputs(
+            device,
+            "Luma Key",
+            LUMA_KEY_SOURCE,
+            &key.params(),
+            &[PassInput::Texture(layer)],
+        )
+    }
+
+    /// Change the key of a pass added with [`Nnpipe::add_luma_key_pass`].
+    pub fn set_luma_key(&mut self, index: usize, key: &LumaKey) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in key.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Swap the texture a pass added with [`Nnpipe::add_luma_key_pass`] lays over
+    /// the frame. Textures of the same kind swap without recompiling the pass.
+    pub fn set_luma_key_layer(
+        &mut self,
+        device: &wgpu::Device,
+        index: usize,
+        layer: &wgpu::TextureView,
+    ) -> Result<()> {
+        self.set_pass_inputs(device, index, &[PassInput::Texture(layer)])
+    }
+
+    /// Add a pass snapping every pixel to the nearest color of `palette` and return
+    /// its index.
+    ///
+    /// Colors are matched in CIE Lab, so the pick follows perceived difference rather
+    /// than raw RGB distance. `dither` mixes in a 4x4 ordered (Bayer) pattern before
+    /// matching, 0 for flat bands and around 1 for the classic crosshatch; it can be
+    /// changed later as the pass's `dither` param.
+    pub fn add_palette_pass(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        palette: &Palette,
+        dither: f32,
+    ) -> usize {
+        let index = self.add_custom_pass(
+            device,
+            "Palette",
+            PALETTE_SOURCE,
+            &[("count", 0.0), ("dither", dither)],
+        );
+        self.set_palette(device, queue, index, palette);
+        index
+    }
+
+    /// Swap the colors of a pass added with [`Nnpipe::add_palette_pass`].
+    pub fn set_palette(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        palette: &Palette,
+    ) {
+        self.replace_pass_lookup(device, queue, index, palette.lookup());
+        if let Some(pass) = self.custom_pass_mut(index) {
+            pass.set_param("count", palette.colors.len() as f32);
+        }
+    }
+
+    /// Add a pass placing `inset` over the frame as a picture-in-picture and return
+    /// its index. See [`PictureInPicture`].
+    ///
+    /// `inset` must be a filterable float texture with `TEXTURE_BINDING` usage, such
+    /// as a video frame or another pipeline's `output_texture`, for preview monitors
+    /// and multi-feed shows. Like other passes it can be reordered, e.g. ahead of a
+    /// grade that should apply to both pictures. Swap the texture with
+    /// [`Nnpipe::set_pip_texture`].
+    pub fn add_pip_pass(
+        &mut self,
+        device: &wgpu::Device,
+        pip: &PictureInPicture,
+        inset: &wgpu::TextureView,
+    ) -> usize {
+        self.add_custom_pass_with_inputs(
+            device,
+            "Picture in Picture",
+            PIP_SOURCE,
+            &pip.params(),
+            &[PassInput::Texture(inset)],
+        )
+    }
+
+    /// Move, resize or restyle the inset of a pass added with
+    /// [`Nnpipe::add_pip_pass`].
+    pub fn set_pip(&mut self, index: usize, pip: &PictureInPicture) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in pip.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Swap the texture shown by a pass added with [`Nnpipe::add_pip_pass`].
+    pub fn set_pip_texture(
+        &mut self,
+        device: &wgpu::Device,
+        index: usize,
+        inset: &wgpu::TextureView,
+    ) -> Result<()> {
+        self.set_pass_inputs(device, index, &[PassInput::Texture(inset)])
+    }
+
+    /// Add a pass shifting each row of the frame sideways by an animated amount and
+    /// return its index.
+    pub fn add_scanline_pass(
+        &mut self,
+        device: &wgpu::Device,
+        displacement: &ScanlineDisplacement,
+    ) -> usize {
+        self.add_custom_pass(
+            device,
+            "Scanline Displacement",
+            SCANLINE_SOURCE,
+            &displacement.params(),
+        )
+    }
+
+    /// Change the displacement of a pass added with [`Nnpipe::add_scanline_pass`].
+    pub fn set_scanline_displacement(&mut self, index: usize, displacement: &ScanlineDisplacement) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in displacement.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Set the waveform a [`ScanlineMode::Waveform`] pass follows, one shift per
+    /// sample in units of the amplitude, so -1..1 audio samples can be passed as is.
+    /// Call it every frame with the latest samples to drive the pass from audio.
+    pub fn set_scanline_waveform(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        samples: &[f32],
+    ) {
+        self.set_pass_data(device, queue, index, samples);
+    }
+
+    /// Add a shockwave pass, a ring of refraction expanding from a point, and return
+    /// its index. The pass is idle until its trigger is fired with
+    /// [`Nnpipe::fire_at`] under `trigger`, after which the ring spreads over
+    /// `duration` seconds. Its size can be changed as the pass's `radius` (in frame
+    /// heights), `width` and `strength` (in pixels) params.
+    pub fn add_shockwave_pass(
+        &mut self,
+        device: &wgpu::Device,
+        trigger: &str,
+        duration: f32,
+    ) -> usize {
+        let index = self.add_custom_pass(
+            device,
+            "Shockwave",
+            SHOCKWAVE_SOURCE,
+            &[
+                ("center_x", 0.5),
+                ("center_y", 0.5),
+                ("progress", 0.0),
+                ("radius", 0.8),
+                ("width", 0.08),
+                ("strength", 24.0),
+            ],
+        );
+        // A linear ramp that drops back to idle at the end
+        let ramp = Trigger::new(index, "progress", 1.0)
+            .with_envelope(duration, 0.0)
+            .with_position("center_x", "center_y");
+        self.add_trigger(trigger, ramp);
+        index
+    }
+}
@@ -0,0 +1,175 @@
+// src/error.rs
+//
+// Crate-wide error type
+//
+// Setup calls check what wgpu would otherwise panic on (oversized textures, formats
+// that can't be rendered to, unsupported sample counts) and report it as an
+// `NnpipeError`, so a sketch can fall back instead of aborting mid-show.
+
+use nannou::wgpu;
+
+use crate::pass::ShaderError;
+
+#[cfg(feature = "config")]
+use crate::config::ConfigError;
+#[cfg(feature = "grading")]
+use crate::lut::LutError;
+
+/// `Result` with [`NnpipeError`] as the default error.
+pub type Result<T, E = NnpipeError> = std::result::Result<T, E>;
+
+/// An error from setting up or running the pipeline.
+#[derive(Debug)]
+pub enum NnpipeError {
+    /// A pass's shader failed to compile or link.
+    ShaderCompile(ShaderError),
+    /// A texture format that can't be used where it was given, e.g. a non-depth
+    /// format for the scene's depth buffer.
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// A size of zero, or beyond the device's texture size limit.
+    TextureTooLarge { width: u32, height: u32, max: u32 },
+    /// An MSAA sample count the pipeline's textures can't use on this device.
+    IncompatibleSampleCount(u32),
+    /// A file couldn't be read or written.
+    Io(std::io::Error),
+    /// A color LUT's contents are invalid.
+    #[cfg(feature = "grading")]
+    InvalidLut(LutError),
+    /// A config names passes or params the pipeline doesn't have.
+    #[cfg(feature = "config")]
+    Config(ConfigError),
+    /// Reading a frame back from the GPU failed.
+    Readback(wgpu::BufferAsyncError),
+    /// A stereo call while stereo rendering is off, see
+    /// [`Nnpipe::set_stereo`](crate::Nnpipe::set_stereo).
+    StereoOff,
+    /// An output index the pipeline has no output at.
+    UnknownOutput(usize),
+    /// Pixels of another count than their width and height give, e.g. for
+    /// [`encode_exr`](crate::encode_exr).
+    PixelCount { width: u32, height: u32, len: usize },
+    /// A convolution or aperture kernel without pixels.
+    InvalidKernel { width: u32, height: u32 },
+    /// A palette without colors.
+    EmptyPalette,
+}
+
+impl std::fmt::Display for NnpipeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShaderCompile(error) => error.fmt(f),
+            Self::UnsupportedFormat(format) => write!(f, "unsupported texture format {format:?}"),
+            Self::TextureTooLarge { width, height, max } => write!(
+                f,
+                "a {width}x{height} texture doesn't fit the device's limit of 1 to {max} \
+                 pixels per side"
+            ),
+            Self::IncompatibleSampleCount(samples) => {
+                write!(f, "unsupported sample count {samples}")
+            }
+            Self::Io(error) => error.fmt(f),
+            #[cfg(feature = "grading")]
+            Self::InvalidLut(error) => error.fmt(f),
+            #[cfg(feature = "config")]
+            Self::Config(error) => error.fmt(f),
+            Self::Readback(error) => write!(f, "readback failed: {error}"),
+            Self::StereoOff => write!(f, "stereo rendering is off"),
+            Self::UnknownOutput(index) => write!(f, "no output at index {index}"),
+            Self::PixelCount { width, height, len } => {
+                write!(f, "expected {width}x{height} pixels, got {len}")
+            }
+            Self::InvalidKernel { width, height } => {
+                write!(f, "a {width}x{height} kernel has no pixels")
+            }
+            Self::EmptyPalette => write!(f, "a palette needs at least one color"),
+        }
+    }
+}
+
+impl std::error::Error for NnpipeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ShaderCompile(error) => Some(error),
+            Self::Io(error) => Some(error),
+            #[cfg(feature = "grading")]
+            Self::InvalidLut(error) => Some(error),
+            #[cfg(feature = "config")]
+            Self::Config(error) => Some(error),
+            Self::Readback(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ShaderError> for NnpipeError {
+    fn from(error: ShaderError) -> Self {
+        Self::ShaderCompile(error)
+    }
+}
+
+impl From<std::io::Error> for NnpipeError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[cfg(feature = "grading")]
+impl From<LutError> for NnpipeError {
+    fn from(error: LutError) -> Self {
+        Self::InvalidLut(error)
+    }
+}
+
+#[cfg(feature = "config")]
+impl From<ConfigError> for NnpipeError {
+    fn from(error: ConfigError) -> Self {
+        Self::Config(error)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for NnpipeError {
+    fn from(error: wgpu::BufferAsyncError) -> Self {
+        Self::Readback(error)
+    }
+}
+
+// Check a texture size against the device's limits
+pub(crate) fn check_size(device: &wgpu::Device, width: u32, height: u32) -> Result<()> {
+    let max = device.limits().max_texture_dimension_2d;
+    if width == 0 || height == 0 || width > max || height > max {
+        return Err(NnpipeError::TextureTooLarge { width, height, max });
+    }
+    Ok(())
+}
+
+// Sample counts every device supports for the pipeline's Rgba16Float scene are 1
+// and 4; others need adapter-specific format features
+pub(crate) fn check_samples(device: &wgpu::Device, samples: u32) -> Result<()> {
+    let adapter_specific = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    let supported = match samples {
+        1 | 4 => true,
+        2 | 8 | 16 => adapter_specific,
+        _ => false,
+    };
+    if !supported {
+        return Err(NnpipeError::IncompatibleSampleCount(samples));
+    }
+    Ok(())
+}
+
+// Check that views of `format` can be rendered to on every device
+pub(crate) fn check_render_format(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Result<()> {
+    let features = format.guaranteed_format_features(device.features());
+    let renderable = features
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT);
+    if !renderable || format.is_depth_stencil_format() {
+        return Err(NnpipeError::UnsupportedFormat(format));
+    }
+    Ok(())
+}
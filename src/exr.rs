@@ -0,0 +1,101 @@
+// src/exr.rs
+//
+// OpenEXR export
+//
+// Frames read back from the pipeline are HDR, which 8-bit PNGs clip. They're
+// written here as single-part, uncompressed scanline OpenEXR files with 32-bit float
+// RGBA channels, which compositing and grading tools open as is. Only writing is
+// supported, and only as much of the format as that needs. The web has no files to
+// write; it gets the bytes with `encode_exr`.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use crate::error::{NnpipeError, Result};
+
+// Channels are stored in alphabetical order, each as a run of the scanline's values
+const CHANNELS: [(&str, usize); 4] = [("A", 3), ("B", 2), ("G", 1), ("R", 0)];
+
+/// Write `pixels`, RGBA in rows from the top left, to an OpenEXR file at `path`.
+///
+/// Fails with [`NnpipeError::PixelCount`] if `pixels` doesn't hold `width` x
+/// `height` pixels, and with [`NnpipeError::Io`] if the file can't be written.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_exr(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    pixels: &[[f32; 4]],
+) -> Result<()> {
+    std::fs::write(path, encode_exr(width, height, pixels)?)?;
+    Ok(())
+}
+
+/// Encode `pixels`, RGBA in rows from the top left, as the bytes of an OpenEXR file.
+///
+/// Fails with [`NnpipeError::PixelCount`] if `pixels` doesn't hold `width` x
+/// `height` pixels.
+pub fn encode_exr(width: u32, height: u32, pixels: &[[f32; 4]]) -> Result<Vec<u8>> {
+    if pixels.len() != width as usize * height as usize {
+        return Err(NnpipeError::PixelCount {
+            width,
+            height,
+            len: pixels.len(),
+        });
+    }
+
+    // Magic number, then version 2 with no flags: a single-part scanline file
+    let mut bytes = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+
+    let mut channels = Vec::new();
+    for (name, _) in CHANNELS {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&2i32.to_le_bytes()); // 32-bit float
+        channels.extend_from_slice(&[0; 4]); // Not perceptually linear, reserved
+        channels.extend_from_slice(&1i32.to_le_bytes()); // No subsampling
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let attributes: [(&str, &str, Vec<u8>); 8] = [
+        ("channels", "chlist", channels),
+        ("compression", "compression", vec![0]), // None
+        ("dataWindow", "box2i", window.clone()),
+        ("displayWindow", "box2i", window),
+        ("lineOrder", "lineOrder", vec![0]), // Increasing y
+        ("pixelAspectRatio", "float", 1f32.to_le_bytes().to_vec()),
+        ("screenWindowCenter", "v2f", [0; 8].to_vec()),
+        ("screenWindowWidth", "float", 1f32.to_le_bytes().to_vec()),
+    ];
+    for (name, kind, value) in attributes {
+        for text in [name, kind] {
+            bytes.extend_from_slice(text.as_bytes());
+            bytes.push(0);
+        }
+        bytes.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        bytes.extend_from_slice(&value);
+    }
+    bytes.push(0);
+
+    // An offset table of one chunk per scanline, each its y, size and data
+    let row_size = width as usize * CHANNELS.len() * 4;
+    let chunk_size = 8 + row_size;
+    let table_end = bytes.len() + height as usize * 8;
+    for y in 0..height as usize {
+        bytes.extend_from_slice(&((table_end + y * chunk_size) as u64).to_le_bytes());
+    }
+    for (y, row) in pixels.chunks(width.max(1) as usize).enumerate() {
+        bytes.extend_from_slice(&(y as i32).to_le_bytes());
+        bytes.extend_from_slice(&(row_size as i32).to_le_bytes());
+        for (_, channel) in CHANNELS {
+            for pixel in row {
+                bytes.extend_from_slice(&pixel[channel].to_le_bytes());
+            }
+        }
+    }
+    Ok(bytes)
+}
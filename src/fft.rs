@@ -0,0 +1,517 @@
+// src/fft.rs
+//
+// FFT convolution bloom
+//
+// Instead of the separable Gaussian blur, the brightness texture is convolved with an
+// image kernel (a star, a hexagonal aperture, any measured PSF) in the frequency
+// domain. The kernel's spectrum is computed once when it is set; every frame then
+// costs a forward transform, a complex multiply and an inverse transform.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::error::{NnpipeError, Result};
+use crate::preprocess::expand;
+
+/// The shape of the glare around each bright pixel, used by the FFT bloom.
+///
+/// The kernel is centered on the bright pixel and stretched to span a bit more than
+/// the frame's longer side. Its overall brightness doesn't matter: it is normalized so the bloom keeps
+/// the energy of the bright parts, while its color tints the glare.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApertureKernel {
+    width: u32,
+    height: u32,
+    data: Vec<[f32; 4]>,
+}
+
+impl ApertureKernel {
+    /// A kernel from linear RGBA pixels in row-major order.
+    ///
+    /// Fails with [`NnpipeError::InvalidKernel`] if it's empty, and with
+    /// [`NnpipeError::PixelCount`] if `data` doesn't hold `width` x `height` pixels.
+    pub fn from_rgba(width: u32, height: u32, data: Vec<[f32; 4]>) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(NnpipeError::InvalidKernel { width, height });
+        }
+        if data.len() != width as usize * height as usize {
+            return Err(NnpipeError::PixelCount {
+                width,
+                height,
+                len: data.len(),
+            });
+        }
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// A kernel from an image, e.g. a photographed point spread function. Fails like
+    /// [`ApertureKernel::from_rgba`] for an empty image.
+    #[cfg(feature = "io")]
+    pub fn from_image(image: &nannou::image::DynamicImage) -> Result<Self> {
+        let image = image.to_rgba8();
+        let data = image
+            .pixels()
+            .map(|pixel| pixel.0.map(|channel| channel as f32 / 255.0))
+            .collect();
+        Self::from_rgba(image.width(), image.height(), data)
+    }
+
+    /// A bright core with `rays` thin streaks, like a camera's diffraction spikes, of
+    /// `size` x `size` pixels. Fails with [`NnpipeError::InvalidKernel`] for a size of 0.
+    pub fn star(rays: u32, size: u32) -> Result<Self> {
+        Self::generate(size, |x, y| {
+            let r = (x * x + y * y).sqrt();
+            let angle = y.atan2(x);
+            let core = (-r * 40.0).exp();
+            let streaks = (angle * rays as f32 * 0.5).cos().abs().powf(400.0) * (-r * 6.0).exp();
+            core + streaks * 0.5
+        })
+    }
+
+    /// Glare through a six-bladed aperture: a hexagonal glow with faint spikes. Fails
+    /// like [`ApertureKernel::star`].
+    pub fn hexagon(size: u32) -> Result<Self> {
+        Self::generate(size, |x, y| {
+            let r = (x * x + y * y).sqrt();
+            let hex = x
+                .abs()
+                .max((x * 0.5 + y * 0.866).abs())
+                .max((x * 0.5 - y * 0.866).abs());
+            let glow = (-hex * 25.0).exp() + (-hex * 6.0).exp() * 0.15;
+            let spikes = (y.atan2(x) * 3.0).cos().abs().powf(400.0) * (-r * 8.0).exp();
+            glow + spikes * 0.3
+        })
+    }
+
+    // A white kernel of `size` x `size` from a function of the offset from the center,
+    // in [-1, 1]
+    fn generate(size: u32, f: impl Fn(f32, f32) -> f32) -> Result<Self> {
+        let half = size as f32 * 0.5;
+        let data = (0..size * size)
+            .map(|i| {
+                let x = ((i % size) as f32 + 0.5 - half) / half;
+                let y = ((i / size) as f32 + 0.5 - half) / half;
+                let value = f(x, y);
+                [value, value, value, 1.0]
+            })
+            .collect();
+        Self::from_rgba(size, size, data)
+    }
+
+    // Resample to the n x n domain with the center moved to the origin (wrapping
+    // around), normalized to unit luminance
+    fn domain_data(&self, n: u32) -> Vec<[f32; 4]> {
+        let mut data = vec![[0.0; 4]; (n * n) as usize];
+        for y in 0..n {
+            for x in 0..n {
+                let source_x = (x * self.width / n).min(self.width - 1);
+                let source_y = (y * self.height / n).min(self.height - 1);
+                let pixel = self.data[(source_y * self.width + source_x) as usize];
+                let target_x = (x + n - n / 2) % n;
+                let target_y = (y + n - n / 2) % n;
+                data[(target_y * n + target_x) as usize] = [pixel[0], pixel[1], pixel[2], 0.0];
+            }
+        }
+
+        let luminance: f32 = data
+            .iter()
+            .map(|p| p[0] * 0.2126 + p[1] * 0.7152 + p[2] * 0.0722)
+            .sum();
+        if luminance > 0.0 {
+            for pixel in &mut data {
+                for channel in &mut pixel[..3] {
+                    *channel /= luminance;
+                }
+            }
+        }
+        data
+    }
+}
+
+// Mirrors `FftParams` in fft.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct FftParams {
+    domain_scale: [f32; 2],
+    target_size: [f32; 2],
+    vertical: u32,
+    inverse: u32,
+    _padding: [u32; 2],
+}
+
+pub(crate) struct FftBloom {
+    size: u32,
+
+    load_pipeline: wgpu::ComputePipeline,
+    fft_pipeline: wgpu::ComputePipeline,
+    multiply_pipeline: wgpu::ComputePipeline,
+    resolve_pipeline: Arc<wgpu::RenderPipeline>,
+
+    // Forward rows, forward columns, inverse rows, inverse columns
+    bind_groups: [wgpu::BindGroup; 4],
+    resolve_bind_group: wgpu::BindGroup,
+}
+
+impl FftBloom {
+    // Largest domain the device's compute limits allow, up to what the bloom needs and
+    // `max_size`. None if the device can't run the transform at all.
+    pub fn domain_size(device: &wgpu::Device, bloom_size: [u32; 2], max_size: u32) -> Option<u32> {
+        let limits = device.limits();
+        let fits = |size: u32| {
+            size / 2 <= limits.max_compute_invocations_per_workgroup
+                && size / 2 <= limits.max_compute_workgroup_size_x
+                && size * 32 <= limits.max_compute_workgroup_storage_size
+        };
+
+        let mut size = bloom_size[0]
+            .max(bloom_size[1])
+            .next_power_of_two()
+            .clamp(64, max_size.max(64));
+        while size > 64 && !fits(size) {
+            size /= 2;
+        }
+
+        (fits(size) && limits.max_storage_buffers_per_shader_stage >= 4).then_some(size)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cache: &PipelineCache,
+        vertex_shader: &wgpu::ShaderModule,
+        brightness_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        format: wgpu::TextureFormat,
+        size: u32,
+        bloom_size: [u32; 2],
+        kernel: &ApertureKernel,
+    ) -> Self {
+        let log_size = size.trailing_zeros();
+        let fft_source = include_str!("shaders/fft.wgsl")
+            .replace("const N: u32 = 256u;", &format!("const N: u32 = {size}u;"))
+            .replace(
+                "const LOG_N: u32 = 8u;",
+                &format!("const LOG_N: u32 = {log_size}u;"),
+            )
+            .replace(
+                "const HALF_N: u32 = 128u;",
+                &format!("const HALF_N: u32 = {}u;", size / 2),
+            )
+            .replace(
+                "@workgroup_size(128)",
+                &format!("@workgroup_size({})", size / 2),
+            );
+        let resolve_source = expand(include_str!("shaders/fft_resolve.wgsl"))
+            .replace("const N: u32 = 256u;", &format!("const N: u32 = {size}u;"));
+
+        let fft_shader = cache.shader(device, "FFT Shader", &fft_source);
+        let resolve_shader = cache.shader(device, "FFT Resolve Shader", &resolve_source);
+
+        // Complex domain: real and imaginary parts of RGB, for the frame and the kernel
+        let domain_bytes = (size * size) as wgpu::BufferAddress * 16;
+        let kernel_data = kernel.domain_data(size);
+        let re_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("FFT Real Buffer"),
+            contents: bytemuck::cast_slice(&kernel_data),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+        });
+        let im_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("FFT Imaginary Buffer"),
+            size: domain_bytes,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let kernel_buffers =
+            ["FFT Kernel Real Buffer", "FFT Kernel Imaginary Buffer"].map(|label| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size: domain_bytes,
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            });
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "FFT Bind Group Layout",
+            &[
+                // Params uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Domain and kernel spectrum bindings
+                storage_entry(1, false),
+                storage_entry(2, false),
+                storage_entry(3, true),
+                storage_entry(4, true),
+                // Brightness texture binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        );
+
+        // The frame fills the top-left of a square domain a third larger than its
+        // longer side; the padding keeps the glow from wrapping around the edges
+        let side = bloom_size[0].max(bloom_size[1]) as f32 * 4.0 / 3.0;
+        let domain_scale = [side / bloom_size[0] as f32, side / bloom_size[1] as f32];
+
+        let params_buffers = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(vertical, inverse)| {
+            let params = FftParams {
+                domain_scale,
+                target_size: [bloom_size[0] as f32, bloom_size[1] as f32],
+                vertical,
+                inverse,
+                _padding: [0; 2],
+            };
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("FFT Params Buffer"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+        });
+
+        let bind_groups = params_buffers.each_ref().map(|params_buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("FFT Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(
+                            params_buffer.as_entire_buffer_binding(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Buffer(
+                            re_buffer.as_entire_buffer_binding(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(
+                            im_buffer.as_entire_buffer_binding(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Buffer(
+                            kernel_buffers[0].as_entire_buffer_binding(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: wgpu::BindingResource::Buffer(
+                            kernel_buffers[1].as_entire_buffer_binding(),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: wgpu::BindingResource::TextureView(brightness_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            })
+        });
+
+        let pipeline_layout =
+            cache.pipeline_layout(device, "FFT Pipeline Layout", &bind_group_layout);
+        let compute_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("FFT Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &fft_shader,
+                entry_point,
+            })
+        };
+        let load_pipeline = compute_pipeline("load");
+        let fft_pipeline = compute_pipeline("fft");
+        let multiply_pipeline = compute_pipeline("multiply");
+
+        // Resolve back into the bloom texture
+        let resolve_bind_group_layout = cache.bind_group_layout(
+            device,
+            "FFT Resolve Bind Group Layout",
+            &[
+                // Domain binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Params uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+
+        let resolve_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("FFT Resolve Bind Group"),
+            layout: &resolve_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(re_buffer.as_entire_buffer_binding()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(
+                        params_buffers[0].as_entire_buffer_binding(),
+                    ),
+                },
+            ],
+        });
+
+        let resolve_pipeline_layout = cache.pipeline_layout(
+            device,
+            "FFT Resolve Pipeline Layout",
+            &resolve_bind_group_layout,
+        );
+        let resolve_pipeline = cache.pipeline(
+            device,
+            &resolve_pipeline_layout,
+            vertex_shader,
+            &resolve_shader,
+            "FFT Resolve Pipeline",
+            format,
+            None,
+        );
+
+        let fft_bloom = Self {
+            size,
+            load_pipeline,
+            fft_pipeline,
+            multiply_pipeline,
+            resolve_pipeline,
+            bind_groups,
+            resolve_bind_group,
+        };
+
+        // Transform the kernel, which the domain buffers were created with, once
+        let ce_desc = wgpu::CommandEncoderDescriptor {
+            label: Some("FFT Kernel"),
+        };
+        let mut encoder = device.create_command_encoder(&ce_desc);
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("FFT kernel pass"),
+            });
+            fft_bloom.dispatch_fft(&mut pass, false);
+        }
+        encoder.copy_buffer_to_buffer(&re_buffer, 0, &kernel_buffers[0], 0, domain_bytes);
+        encoder.copy_buffer_to_buffer(&im_buffer, 0, &kernel_buffers[1], 0, domain_bytes);
+        queue.submit(Some(encoder.finish()));
+
+        fft_bloom
+    }
+
+    // Transform rows, then columns
+    fn dispatch_fft<'a>(&'a self, pass: &mut wgpu::ComputePass<'a>, inverse: bool) {
+        let first = if inverse { 2 } else { 0 };
+        pass.set_pipeline(&self.fft_pipeline);
+        for bind_group in &self.bind_groups[first..first + 2] {
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.dispatch_workgroups(self.size, 1, 1);
+        }
+    }
+
+    // Record the convolution of the brightness texture into `target`, a bloom texture
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "fft_bloom", skip_all))]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let groups = self.size.div_ceil(8);
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("FFT bloom pass"),
+            });
+
+            pass.set_pipeline(&self.load_pipeline);
+            pass.set_bind_group(0, &self.bind_groups[0], &[]);
+            pass.dispatch_workgroups(groups, groups, 1);
+
+            self.dispatch_fft(&mut pass, false);
+
+            pass.set_pipeline(&self.multiply_pipeline);
+            pass.set_bind_group(0, &self.bind_groups[0], &[]);
+            pass.dispatch_workgroups(groups, groups, 1);
+
+            self.dispatch_fft(&mut pass, true);
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("FFT resolve pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.resolve_pipeline);
+        pass.set_bind_group(0, &self.resolve_bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
@@ -0,0 +1,230 @@
+// src/flare.rs
+//
+// Lens flare
+//
+// A screen-space imitation of the reflections between lens elements. Each pixel
+// gathers the brightness texture at steps along the line through the center of the
+// frame, which mirrors the bright areas into a row of ghosts, with red and blue pulled
+// apart like in a real lens. A halo ring is gathered the same way at a fixed distance
+// from the center. The result is added over the composite in a single fullscreen
+// pass, ahead of the effect passes.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+// Ghosts the shader gathers at most
+const MAX_GHOSTS: u32 = 16;
+
+/// Settings of the lens flare, see [`Nnpipe::set_lens_flare`](crate::Nnpipe::set_lens_flare).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct LensFlare {
+    /// Number of ghosts, up to 16.
+    pub ghosts: u32,
+    /// Distance between the ghosts, as a fraction of the way from a bright area to
+    /// the center of the frame.
+    pub spacing: f32,
+    /// Color the ghosts are multiplied by.
+    pub tint: [f32; 3],
+    pub ghost_intensity: f32,
+    /// Radius of the halo ring, as a fraction of the frame's height.
+    pub halo_radius: f32,
+    /// Brightness of the halo; 0 turns it off.
+    pub halo_intensity: f32,
+    /// Offset of the red and blue copies of the ghosts and halo, as a fraction of the
+    /// frame.
+    pub chromatic_shift: f32,
+}
+
+impl Default for LensFlare {
+    fn default() -> Self {
+        Self {
+            ghosts: 4,
+            spacing: 0.35,
+            tint: [1.0, 0.85, 0.7],
+            ghost_intensity: 0.5,
+            halo_radius: 0.45,
+            halo_intensity: 0.25,
+            chromatic_shift: 0.01,
+        }
+    }
+}
+
+impl LensFlare {
+    // The settings as the shader's uniform
+    fn uniform(&self) -> [f32; 12] {
+        let [r, g, b] = self.tint;
+        [
+            r,
+            g,
+            b,
+            self.ghost_intensity,
+            self.ghosts.min(MAX_GHOSTS) as f32,
+            self.spacing,
+            self.halo_radius,
+            self.halo_intensity,
+            self.chromatic_shift,
+            0.0,
+            0.0,
+            0.0,
+        ]
+    }
+}
+
+pub(crate) struct FlareLayer {
+    settings: LensFlare,
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl FlareLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        brightness_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        globals_buffer: &UniformBuffer,
+        settings: LensFlare,
+    ) -> Self {
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Lens Flare Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Lens Flare Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                uniform_entry(2),
+                uniform_entry(3),
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Lens Flare Pipeline Layout", &bind_group_layout);
+
+        // The flare adds its light to the frame
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let shader = cache.shader(
+            device,
+            "Lens Flare Shader",
+            &expand(include_str!("shaders/flare.wgsl")),
+        );
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Lens Flare Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            Some(wgpu::BlendState {
+                color: additive,
+                alpha: additive,
+            }),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Lens Flare Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(brightness_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: globals_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            settings,
+            uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> LensFlare {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: LensFlare) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Add the flare over `target`
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "lens_flare", skip_all)
+    )]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Lens flare pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
@@ -0,0 +1,271 @@
+// src/golden.rs
+//
+// Golden-image testing
+//
+// Runs a pipeline on a headless adapter over procedurally generated input and compares
+// the result against stored reference images, so shader changes can be regression
+// tested. The crate's own tests in `tests/golden.rs` use it; sketches can use it to
+// pin down their looks the same way.
+
+use std::path::Path;
+
+use nannou::image::{ImageBuffer, Rgba};
+use nannou::wgpu;
+
+use crate::error::Result;
+use crate::nnpipe::Nnpipe;
+
+/// Set this environment variable to write reference images instead of checking them.
+pub const UPDATE_VAR: &str = "NNPIPE_UPDATE_GOLDEN";
+
+/// A device and queue on a headless adapter, or `None` if there is none (e.g. on a
+/// CI machine without a GPU or software rasterizer), in which case tests should be
+/// skipped. `WGPU_BACKEND` picks the backend, like in wgpu's own examples.
+pub fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    headless_device_with(wgpu::Features::empty())
+}
+
+/// Like [`headless_device`], with those of `features` the adapter supports enabled.
+pub fn headless_device_with(features: wgpu::Features) -> Option<(wgpu::Device, wgpu::Queue)> {
+    let backends = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+    let instance = wgpu::Instance::new(wgpu_upstream::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+    let adapter =
+        futures::executor::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))?;
+    let descriptor = wgpu::DeviceDescriptor {
+        label: Some("Nnpipe Golden Device"),
+        features: features & adapter.features(),
+        limits: adapter.limits(),
+    };
+    futures::executor::block_on(adapter.request_device(&descriptor, None)).ok()
+}
+
+/// A procedurally generated input frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestPattern {
+    /// Red across, green down and blue falling across, for color effects.
+    Gradient,
+    /// Dark and light grey squares of `cell` pixels, for edges and blurs.
+    Checkerboard { cell: u32 },
+    /// Small overbright dots on black, for the bloom.
+    Dots,
+}
+
+impl TestPattern {
+    /// The pattern's RGBA pixels, in rows from the top left.
+    pub fn pixels(&self, width: u32, height: u32) -> Vec<[f32; 4]> {
+        (0..width * height)
+            .map(|i| self.pixel(i % width, i / width, width, height))
+            .collect()
+    }
+
+    fn pixel(&self, x: u32, y: u32, width: u32, height: u32) -> [f32; 4] {
+        let u = (x as f32 + 0.5) / width as f32;
+        let v = (y as f32 + 0.5) / height as f32;
+        match *self {
+            TestPattern::Gradient => [u, v, 1.0 - u, 1.0],
+            TestPattern::Checkerboard { cell } => {
+                let cell = cell.max(1);
+                let light = (x / cell + y / cell) % 2 == 1;
+                let value = if light { 0.9 } else { 0.1 };
+                [value, value, value, 1.0]
+            }
+            TestPattern::Dots => {
+                // 3 x 3 pixel dots at the centers of a 4 x 3 grid, each a different hue
+                let (cell_x, cell_y) = ((width / 4).max(1), (height / 3).max(1));
+                let (column, row) = (x / cell_x, y / cell_y);
+                let center = [column * cell_x + cell_x / 2, row * cell_y + cell_y / 2];
+                if x.abs_diff(center[0]) <= 1 && y.abs_diff(center[1]) <= 1 {
+                    let hue = (row * 4 + column) as f32 / 12.0;
+                    let [r, g, b] = [0.0, 1.0 / 3.0, 2.0 / 3.0].map(|offset| {
+                        let t = ((hue + offset) * std::f32::consts::TAU).cos();
+                        4.0 * (0.5 + 0.5 * t)
+                    });
+                    [r, g, b, 1.0]
+                } else {
+                    [0.0, 0.0, 0.0, 1.0]
+                }
+            }
+        }
+    }
+
+    /// Upload the pattern as a texture [`Nnpipe::process_texture`] can take as its
+    /// scene.
+    pub fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+    ) -> wgpu::Texture {
+        let texture = wgpu::TextureBuilder::new()
+            .size([width, height])
+            .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device);
+
+        let data: Vec<u16> = self
+            .pixels(width, height)
+            .iter()
+            .flat_map(|pixel| pixel.map(f32_to_f16))
+            .collect();
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&data),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 8),
+                rows_per_image: None,
+            },
+            texture.extent(),
+        );
+        texture
+    }
+}
+
+/// Run `pipeline` on `input` and read the result back.
+pub fn render(
+    pipeline: &Nnpipe,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+) -> Result<Vec<[f32; 4]>> {
+    let input_view = input.view().build();
+    pipeline.process_texture(device, queue, &input_view, &pipeline.output_view);
+    futures::executor::block_on(pipeline.read_output(device, queue))
+}
+
+/// A frame that doesn't match its reference image, or a reference image that couldn't
+/// be read or written.
+#[derive(Clone, Debug)]
+pub struct GoldenError {
+    pub message: String,
+}
+
+impl std::fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "golden image mismatch: {}", self.message)
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+fn error(message: impl Into<String>) -> GoldenError {
+    GoldenError {
+        message: message.into(),
+    }
+}
+
+/// Compare two frames channel by channel. Channels are clamped to 0..1 first, the
+/// range reference images hold.
+pub fn compare(
+    expected: &[[f32; 4]],
+    actual: &[[f32; 4]],
+    width: u32,
+    tolerance: f32,
+) -> Result<(), GoldenError> {
+    let mut count = 0;
+    let mut first = None;
+    let mut max_difference = 0.0_f32;
+    for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+        for (e, a) in expected.iter().zip(actual) {
+            let difference = (e.clamp(0.0, 1.0) - a.clamp(0.0, 1.0)).abs();
+            if difference > tolerance {
+                count += 1;
+                first.get_or_insert(i as u32);
+                max_difference = max_difference.max(difference);
+            }
+        }
+    }
+
+    match first {
+        None => Ok(()),
+        Some(i) => Err(error(format!(
+            "{count} channels differ by more than {tolerance}, by up to {max_difference}, \
+             first at {},{}",
+            i % width,
+            i / width
+        ))),
+    }
+}
+
+/// Check a frame against the reference image at `path`, a 16-bit PNG, allowing each
+/// channel to differ by `tolerance`. With [`UPDATE_VAR`] set, the frame is written as
+/// the new reference instead.
+pub fn check(
+    path: impl AsRef<Path>,
+    pixels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    tolerance: f32,
+) -> Result<(), GoldenError> {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        return save(path, pixels, width, height);
+    }
+    if !path.exists() {
+        return Err(error(format!(
+            "no reference image {}; run with {UPDATE_VAR}=1 to create it",
+            path.display()
+        )));
+    }
+
+    let image = nannou::image::open(path)
+        .map_err(|e| error(format!("{}: {e}", path.display())))?
+        .to_rgba16();
+    if image.dimensions() != (width, height) {
+        let (expected_width, expected_height) = image.dimensions();
+        return Err(error(format!(
+            "frame is {width}x{height}, reference is {expected_width}x{expected_height}"
+        )));
+    }
+
+    let expected: Vec<[f32; 4]> = image
+        .pixels()
+        .map(|pixel| pixel.0.map(|channel| channel as f32 / 65535.0))
+        .collect();
+    compare(&expected, pixels, width, tolerance)
+}
+
+fn save(path: &Path, pixels: &[[f32; 4]], width: u32, height: u32) -> Result<(), GoldenError> {
+    let data: Vec<u16> = pixels
+        .iter()
+        .flat_map(|pixel| pixel.map(|c| (c.clamp(0.0, 1.0) * 65535.0).round() as u16))
+        .collect();
+    let image = ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, data)
+        .ok_or_else(|| error("frame doesn't match its size"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| error(format!("{}: {e}", parent.display())))?;
+    }
+    image
+        .save(path)
+        .map_err(|e| error(format!("{}: {e}", path.display())))
+}
+
+// Encode a half float, rounding toward zero; test patterns stay well in range
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if value.is_nan() {
+        sign | 0x7e00
+    } else if exponent >= 31 {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        // Subnormal, or zero below the smallest one
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = (mantissa | 0x80_0000) >> (14 - exponent);
+        sign | mantissa as u16
+    } else {
+        sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
@@ -0,0 +1,393 @@
+// src/grading.rs
+//
+// Color grading passes
+//
+// Like the built-in effects, each grade is a regular pass; its settings map onto the
+// pass's params, so they can also be animated one by one with `Pass::set_param`.
+
+use nannou::wgpu;
+
+use crate::lut::ColorLut;
+use crate::nnpipe::Nnpipe;
+use crate::pass::LookupTexture;
+
+const SPLIT_TONING_SOURCE: &str = include_str!("shaders/split_toning.wgsl");
+const COLOR_WHEELS_SOURCE: &str = include_str!("shaders/color_wheels.wgsl");
+const CURVES_SOURCE: &str = include_str!("shaders/curves.wgsl");
+const COLOR_LUT_SOURCE: &str = include_str!("shaders/color_lut.wgsl");
+const COLOR_VISION_SOURCE: &str = include_str!("shaders/color_vision.wgsl");
+
+// Entries per curve in the lookup texture
+const CURVE_RESOLUTION: u32 = 256;
+
+/// Separate tints for the shadows and highlights, for [`Nnpipe::add_split_toning_pass`].
+///
+/// Only the hue and saturation of a tint are applied: any grey leaves its range
+/// unchanged, and a more saturated color tints more strongly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SplitToning {
+    pub shadow_tint: [f32; 3],
+    pub highlight_tint: [f32; 3],
+    /// Where the split between the tints lies: -1 gives most of the range to the
+    /// shadow tint, 1 to the highlight tint.
+    pub balance: f32,
+}
+
+impl Default for SplitToning {
+    // The classic teal shadows and warm highlights
+    fn default() -> Self {
+        Self {
+            shadow_tint: [0.4, 0.55, 0.65],
+            highlight_tint: [0.65, 0.55, 0.4],
+            balance: 0.0,
+        }
+    }
+}
+
+impl SplitToning {
+    // Params of the split toning pass
+    fn params(&self) -> [(&'static str, f32); 7] {
+        let [shadow_r, shadow_g, shadow_b] = self.shadow_tint;
+        let [highlight_r, highlight_g, highlight_b] = self.highlight_tint;
+        [
+            ("shadow_r", shadow_r),
+            ("shadow_g", shadow_g),
+            ("shadow_b", shadow_b),
+            ("highlight_r", highlight_r),
+            ("highlight_g", highlight_g),
+            ("highlight_b", highlight_b),
+            ("balance", self.balance),
+        ]
+    }
+}
+
+/// Three-way color correction, for [`Nnpipe::add_color_wheels_pass`].
+///
+/// Each control is an RGB vector, as set by a color wheel: lift offsets the shadows,
+/// gamma bends the midtones and gain scales the highlights. The defaults leave the
+/// frame unchanged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorWheels {
+    /// Added to the blacks, fading out toward white. 0 is neutral.
+    pub lift: [f32; 3],
+    /// Midtone exponent; above 1 brightens. 1 is neutral.
+    pub gamma: [f32; 3],
+    /// Multiplier of the whole range. 1 is neutral.
+    pub gain: [f32; 3],
+}
+
+impl Default for ColorWheels {
+    fn default() -> Self {
+        Self {
+            lift: [0.0; 3],
+            gamma: [1.0; 3],
+            gain: [1.0; 3],
+        }
+    }
+}
+
+impl ColorWheels {
+    // Params of the color wheels pass
+    fn params(&self) -> [(&'static str, f32); 9] {
+        let [lift_r, lift_g, lift_b] = self.lift;
+        let [gamma_r, gamma_g, gamma_b] = self.gamma;
+        let [gain_r, gain_g, gain_b] = self.gain;
+        [
+            ("lift_r", lift_r),
+            ("lift_g", lift_g),
+            ("lift_b", lift_b),
+            ("gamma_r", gamma_r),
+            ("gamma_g", gamma_g),
+            ("gamma_b", gamma_b),
+            ("gain_r", gain_r),
+            ("gain_g", gain_g),
+            ("gain_b", gain_b),
+        ]
+    }
+}
+
+/// A color vision deficiency, for [`ColorVision`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorDeficiency {
+    /// No working long-wavelength (red) cones.
+    #[default]
+    Protanopia,
+    /// No working medium-wavelength (green) cones, the most common.
+    Deuteranopia,
+    /// No working short-wavelength (blue) cones.
+    Tritanopia,
+}
+
+/// Simulation or correction of a color vision deficiency, for
+/// [`Nnpipe::add_color_vision_pass`].
+///
+/// Simulating shows the frame as someone with the deficiency sees it, to check that
+/// an installation or chart still reads. Daltonizing shifts the contrast the
+/// deficiency loses into colors it keeps, for showing to them. Put the pass last, as
+/// it works on the final colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorVision {
+    pub deficiency: ColorDeficiency,
+    /// From 0, normal vision, to 1, a full dichromat; in between are the milder
+    /// anomalous trichromacies.
+    pub severity: f32,
+    /// Correct for the deficiency instead of simulating it.
+    pub daltonize: bool,
+}
+
+impl Default for ColorVision {
+    fn default() -> Self {
+        Self {
+            deficiency: ColorDeficiency::default(),
+            severity: 1.0,
+            daltonize: false,
+        }
+    }
+}
+
+impl ColorVision {
+    // Params of the color vision pass
+    fn params(&self) -> [(&'static str, f32); 3] {
+        let deficiency = match self.deficiency {
+            ColorDeficiency::Protanopia => 0.0,
+            ColorDeficiency::Deuteranopia => 1.0,
+            ColorDeficiency::Tritanopia => 2.0,
+        };
+        [
+            ("deficiency", deficiency),
+            ("daltonize", if self.daltonize { 1.0 } else { 0.0 }),
+            ("severity", self.severity.clamp(0.0, 1.0)),
+        ]
+    }
+}
+
+/// Tone curves, for [`Nnpipe::add_curves_pass`].
+///
+/// Each curve is a list of `[input, output]` control points in 0..1, joined by a
+/// smooth curve that doesn't overshoot between them. The master curve applies to all
+/// channels before their own curves. Inputs above 1 (HDR highlights) keep their
+/// distance above the curve's end point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curves {
+    pub master: Vec<[f32; 2]>,
+    pub red: Vec<[f32; 2]>,
+    pub green: Vec<[f32; 2]>,
+    pub blue: Vec<[f32; 2]>,
+}
+
+impl Default for Curves {
+    fn default() -> Self {
+        let identity = vec![[0.0, 0.0], [1.0, 1.0]];
+        Self {
+            master: identity.clone(),
+            red: identity.clone(),
+            green: identity.clone(),
+            blue: identity,
+        }
+    }
+}
+
+impl Curves {
+    // One row of (red, green, blue, master) per input value
+    fn lookup(&self) -> LookupTexture {
+        let curves = [&self.red, &self.green, &self.blue, &self.master]
+            .map(|points| rasterize_curve(points));
+        let data = (0..CURVE_RESOLUTION as usize)
+            .map(|i| curves.each_ref().map(|curve| curve[i]))
+            .collect();
+        LookupTexture {
+            width: CURVE_RESOLUTION,
+            height: 1,
+            data,
+        }
+    }
+}
+
+// Sample a monotone cubic (Fritsch-Carlson) through the control points, holding the
+// end values outside them. No points is the identity.
+fn rasterize_curve(points: &[[f32; 2]]) -> Vec<f32> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    points.dedup_by(|a, b| a[0] == b[0]);
+
+    let inputs = (0..CURVE_RESOLUTION).map(|i| i as f32 / (CURVE_RESOLUTION - 1) as f32);
+    match points.len() {
+        0 => return inputs.collect(),
+        1 => return inputs.map(|_| points[0][1]).collect(),
+        _ => {}
+    }
+
+    // Secant slopes, then tangents limited so no segment overshoots
+    let secants: Vec<f32> = points
+        .windows(2)
+        .map(|w| (w[1][1] - w[0][1]) / (w[1][0] - w[0][0]))
+        .collect();
+    let mut tangents = vec![0.0; points.len()];
+    tangents[0] = secants[0];
+    tangents[points.len() - 1] = secants[secants.len() - 1];
+    for i in 1..points.len() - 1 {
+        tangents[i] = if secants[i - 1] * secants[i] <= 0.0 {
+            0.0
+        } else {
+            (secants[i - 1] + secants[i]) * 0.5
+        };
+    }
+    for (i, &secant) in secants.iter().enumerate() {
+        if secant == 0.0 {
+            tangents[i] = 0.0;
+            tangents[i + 1] = 0.0;
+            continue;
+        }
+        let a = tangents[i] / secant;
+        let b = tangents[i + 1] / secant;
+        let length = (a * a + b * b).sqrt();
+        if length > 3.0 {
+            tangents[i] = 3.0 / length * a * secant;
+            tangents[i + 1] = 3.0 / length * b * secant;
+        }
+    }
+
+    inputs
+        .map(|x| {
+            let last = points.len() - 1;
+            if x <= points[0][0] {
+                return points[0][1];
+            }
+            if x >= points[last][0] {
+                return points[last][1];
+            }
+            let i = points
+                .windows(2)
+                .position(|w| x < w[1][0])
+                .unwrap_or(last - 1);
+            let [x0, y0] = points[i];
+            let [x1, y1] = points[i + 1];
+            let h = x1 - x0;
+            let t = (x - x0) / h;
+            let t2 = t * t;
+            let t3 = t2 * t;
+            (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+                + (t3 - 2.0 * t2 + t) * h * tangents[i]
+                + (-2.0 * t3 + 3.0 * t2) * y1
+                + (t3 - t2) * h * tangents[i + 1]
+        })
+        .collect()
+}
+
+impl Nnpipe {
+    /// Add a split toning pass and return its index.
+    pub fn add_split_toning_pass(&mut self, device: &wgpu::Device, toning: &SplitToning) -> usize {
+        self.add_custom_pass(
+            device,
+            "Split Toning",
+            SPLIT_TONING_SOURCE,
+            &toning.params(),
+        )
+    }
+
+    /// Change the tints of a pass added with [`Nnpipe::add_split_toning_pass`].
+    pub fn set_split_toning(&mut self, index: usize, toning: &SplitToning) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in toning.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Add a lift/gamma/gain pass and return its index.
+    pub fn add_color_wheels_pass(&mut self, device: &wgpu::Device, wheels: &ColorWheels) -> usize {
+        self.add_custom_pass(
+            device,
+            "Color Wheels",
+            COLOR_WHEELS_SOURCE,
+            &wheels.params(),
+        )
+    }
+
+    /// Change the correction of a pass added with [`Nnpipe::add_color_wheels_pass`].
+    pub fn set_color_wheels(&mut self, index: usize, wheels: &ColorWheels) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in wheels.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Add a color vision simulation or daltonization pass and return its index.
+    pub fn add_color_vision_pass(&mut self, device: &wgpu::Device, vision: &ColorVision) -> usize {
+        self.add_custom_pass(
+            device,
+            "Color Vision",
+            COLOR_VISION_SOURCE,
+            &vision.params(),
+        )
+    }
+
+    /// Change the deficiency of a pass added with [`Nnpipe::add_color_vision_pass`].
+    pub fn set_color_vision(&mut self, index: usize, vision: &ColorVision) {
+        if let Some(pass) = self.custom_pass_mut(index) {
+            for (name, value) in vision.params() {
+                pass.set_param(name, value);
+            }
+        }
+    }
+
+    /// Add a curves pass and return its index.
+    pub fn add_curves_pass(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        curves: &Curves,
+    ) -> usize {
+        let index = self.add_custom_pass(device, "Curves", CURVES_SOURCE, &[]);
+        self.set_curves(device, queue, index, curves);
+        index
+    }
+
+    /// Change the curves of a pass added with [`Nnpipe::add_curves_pass`]. The lookup
+    /// texture is rewritten in place, so curves can be edited live.
+    pub fn set_curves(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        curves: &Curves,
+    ) {
+        self.replace_pass_lookup(device, queue, index, curves.lookup());
+    }
+
+    /// Add a pass mapping colors through a 3D `lut` and return its index.
+    ///
+    /// Colors are clamped to 0..1 before the lookup, as LUTs are made for display
+    /// colors; put the pass after the composite's tone mapping. Its `amount` param
+    /// blends from the original colors (0) to the mapped ones (1).
+    pub fn add_color_lut_pass(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lut: &ColorLut,
+    ) -> usize {
+        let index = self.add_custom_pass(
+            device,
+            "Color LUT",
+            COLOR_LUT_SOURCE,
+            &[("size", lut.size() as f32), ("amount", 1.0)],
+        );
+        self.set_color_lut(device, queue, index, lut);
+        index
+    }
+
+    /// Swap the table of a pass added with [`Nnpipe::add_color_lut_pass`].
+    pub fn set_color_lut(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        lut: &ColorLut,
+    ) {
+        self.replace_pass_lookup(device, queue, index, lut.lookup());
+        if let Some(pass) = self.custom_pass_mut(index) {
+            pass.set_param("size", lut.size() as f32);
+        }
+    }
+}
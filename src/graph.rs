@@ -0,0 +1,133 @@
+// src/graph.rs
+//
+// Chain graph
+//
+// Which passes run, in what order and through which textures depends on a dozen
+// settings, from the effect passes enabled to the scopes and the bloom's kernel.
+// `Nnpipe::chain_graph` describes the chain as it would run for the next frame: a
+// node per pass with the texture it writes, its format and size, and an edge per
+// texture one pass reads from another. Host tools draw it as a node graph, or as
+// Graphviz with `ChainGraph::to_dot`, for debugging and documentation.
+
+use std::fmt::Write;
+
+use nannou::wgpu;
+
+/// The passes of a pipeline's chain and the textures between them, see
+/// [`Nnpipe::chain_graph`](crate::Nnpipe::chain_graph).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChainGraph {
+    /// The passes, in the order they run.
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A pass of the chain, or the scene it starts from.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphNode {
+    /// Unique name of the node, e.g. `composite`. Effect passes are
+    /// `passes.<index>` and outputs `outputs.<index>`, like their param paths, and
+    /// pass graph passes `graph.<output>`, after the texture they write.
+    pub id: String,
+    /// Name to show: the label of effect passes.
+    pub label: String,
+    /// Name of the texture the pass writes. Passes adding onto a texture share it
+    /// with the pass before them.
+    pub texture: String,
+    /// The texture's format, like `Rgba16Float`.
+    pub format: String,
+    /// The texture's size, or `None` for outputs, which take the size of the target
+    /// they're drawn into.
+    pub size: Option<[u32; 2]>,
+}
+
+/// A texture one pass reads from another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    /// The texture, as written by `from`.
+    pub texture: String,
+}
+
+impl ChainGraph {
+    pub fn node(&self, id: &str) -> Option<&GraphNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// The nodes `id` reads from.
+    pub fn inputs<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a GraphNode> + 'a {
+        self.edges
+            .iter()
+            .filter(move |edge| edge.to == id)
+            .filter_map(|edge| self.node(&edge.from))
+    }
+
+    /// The graph in Graphviz's DOT language, left to right, with each node showing
+    /// its texture, format and size.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph chain {\n    rankdir=LR;\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let size = match node.size {
+                Some([width, height]) => format!("{width}x{height}"),
+                None => "target size".to_string(),
+            };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\\n{} {} {}\"];",
+                escape(&node.id),
+                escape(&node.label),
+                escape(&node.texture),
+                node.format,
+                size
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                escape(&edge.from),
+                escape(&edge.to),
+                escape(&edge.texture)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // Add a node writing `texture`, reading the textures of the nodes in `inputs`
+    pub(crate) fn add(
+        &mut self,
+        id: impl Into<String>,
+        label: impl Into<String>,
+        texture: impl Into<String>,
+        format: wgpu::TextureFormat,
+        size: Option<[u32; 2]>,
+        inputs: &[&str],
+    ) {
+        let id = id.into();
+        for input in inputs {
+            let texture = self.node(input).expect("added before").texture.clone();
+            self.edges.push(GraphEdge {
+                from: input.to_string(),
+                to: id.clone(),
+                texture,
+            });
+        }
+        self.nodes.push(GraphNode {
+            id,
+            label: label.into(),
+            texture: texture.into(),
+            format: format!("{format:?}"),
+            size,
+        });
+    }
+}
+
+// `text` escaped for a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,264 @@
+// src/groups.rs
+//
+// Param groups with mute and solo
+//
+// Tracking down which effect causes an artifact mid-set means switching effects off
+// one by one, without losing how they were set. Params are grouped by the effect they
+// belong to, the bloom, a built-in layer like the lens flare, or an effect pass, named
+// by the path prefix they share, and a group can be muted, bypassing the effect, or
+// soloed, bypassing every effect not soloed. Neither touches the effect's own
+// settings, nor is saved with them. The groups of the matte, stabilization and
+// accumulation have no params, only their mute and solo. A muted matte or reflections
+// are left black, as if off.
+
+use crate::nnpipe::Nnpipe;
+
+/// The params of one effect, see [`Nnpipe::param_groups`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamGroup {
+    /// Prefix of the group's param paths: `bloom`, a layer's, like `flare`, or
+    /// `passes.<index>`.
+    pub path: String,
+    /// `bloom`, the layer's name, like `lens flare`, or the pass's label.
+    pub name: String,
+    /// Paths of the group's params, see [`Nnpipe::list_params`].
+    pub params: Vec<String>,
+    pub muted: bool,
+    pub soloed: bool,
+}
+
+// A group by its path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Group {
+    Bloom,
+    Layer(Layer),
+    Pass(usize),
+}
+
+impl Group {
+    pub fn parse(path: &str) -> Option<Self> {
+        if path == "bloom" {
+            return Some(Group::Bloom);
+        }
+        if let Some((layer, ..)) = LAYER_GROUPS.iter().find(|(_, prefix, _)| *prefix == path) {
+            return Some(Group::Layer(*layer));
+        }
+        path.strip_prefix("passes.")?.parse().ok().map(Group::Pass)
+    }
+}
+
+// A built-in layer with a group of its own, in chain order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Layer {
+    Matte,
+    DropShadow,
+    Reflections,
+    Stabilization,
+    Flare,
+    Sparkles,
+    DistanceGlow,
+    Accumulation,
+}
+
+// Every layer with the path and name of its group
+const LAYER_GROUPS: [(Layer, &str, &str); 8] = [
+    (Layer::Matte, "matte", "matte"),
+    (Layer::DropShadow, "drop_shadow", "drop shadow"),
+    (Layer::Reflections, "reflections", "reflections"),
+    (Layer::Stabilization, "stabilization", "stabilization"),
+    (Layer::Flare, "flare", "lens flare"),
+    (Layer::Sparkles, "sparkles", "sparkles"),
+    (Layer::DistanceGlow, "distance_glow", "distance glow"),
+    (Layer::Accumulation, "accumulation", "accumulation"),
+];
+
+// Mute and solo of every layer's group, kept while the layer is off
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct LayerGroups {
+    muted: [bool; LAYER_GROUPS.len()],
+    soloed: [bool; LAYER_GROUPS.len()],
+}
+
+impl Nnpipe {
+    /// The params grouped by effect: the bloom's, then those of the built-in layers
+    /// that are on, then each pass's, in chain order. Macros belong to no group.
+    pub fn param_groups(&self) -> Vec<ParamGroup> {
+        let params = self.list_params();
+        let group = |path: String, name: &str, muted, soloed| {
+            let prefix = format!("{path}.");
+            ParamGroup {
+                params: params
+                    .iter()
+                    .filter(|param| param.starts_with(&prefix))
+                    .cloned()
+                    .collect(),
+                path,
+                name: name.to_string(),
+                muted,
+                soloed,
+            }
+        };
+        let bloom = group(
+            "bloom".to_string(),
+            "bloom",
+            self.bloom_muted,
+            self.bloom_soloed,
+        );
+        let layers = LAYER_GROUPS
+            .iter()
+            .filter(|(layer, ..)| self.layer_on(*layer))
+            .map(|&(layer, path, name)| {
+                let groups = &self.layer_groups;
+                let index = layer as usize;
+                group(
+                    path.to_string(),
+                    name,
+                    groups.muted[index],
+                    groups.soloed[index],
+                )
+            });
+        let passes = (0..)
+            .map_while(|index| self.custom_pass(index).map(|pass| (index, pass)))
+            .map(|(index, pass)| {
+                group(
+                    format!("passes.{index}"),
+                    &pass.label,
+                    pass.muted,
+                    pass.soloed,
+                )
+            });
+        std::iter::once(bloom).chain(layers).chain(passes).collect()
+    }
+
+    /// Bypass the effect of the group at `group`, `bloom`, a layer's like `flare`, or
+    /// `passes.<index>`, or bring it back with `false`. Returns `false` if there's no
+    /// such group, or the layer is off.
+    ///
+    /// Also set through [`Nnpipe::set_param`] as `<group>.mute`, 0 or 1.
+    pub fn set_group_muted(&mut self, group: &str, muted: bool) -> bool {
+        match Group::parse(group) {
+            Some(group) => self.set_muted(group, muted),
+            None => false,
+        }
+    }
+
+    /// Bypass every effect whose group isn't soloed, unless it's the last group soloed
+    /// and `soloed` is `false`. Muted groups stay bypassed. Returns `false` if there's
+    /// no such group, or the layer is off.
+    ///
+    /// Also set through [`Nnpipe::set_param`] as `<group>.solo`, 0 or 1.
+    pub fn set_group_soloed(&mut self, group: &str, soloed: bool) -> bool {
+        match Group::parse(group) {
+            Some(group) => self.set_soloed(group, soloed),
+            None => false,
+        }
+    }
+
+    /// Unsolo every group.
+    pub fn clear_solo(&mut self) {
+        self.bloom_soloed = false;
+        self.layer_groups.soloed = Default::default();
+        let mut index = 0;
+        while let Some(pass) = self.custom_pass_mut(index) {
+            pass.soloed = false;
+            index += 1;
+        }
+    }
+
+    pub(crate) fn muted(&self, group: Group) -> Option<bool> {
+        match group {
+            Group::Bloom => Some(self.bloom_muted),
+            Group::Layer(layer) => self
+                .layer_on(layer)
+                .then_some(self.layer_groups.muted[layer as usize]),
+            Group::Pass(index) => self.custom_pass(index).map(|pass| pass.muted),
+        }
+    }
+
+    pub(crate) fn soloed(&self, group: Group) -> Option<bool> {
+        match group {
+            Group::Bloom => Some(self.bloom_soloed),
+            Group::Layer(layer) => self
+                .layer_on(layer)
+                .then_some(self.layer_groups.soloed[layer as usize]),
+            Group::Pass(index) => self.custom_pass(index).map(|pass| pass.soloed),
+        }
+    }
+
+    pub(crate) fn set_muted(&mut self, group: Group, muted: bool) -> bool {
+        match group {
+            Group::Bloom => self.bloom_muted = muted,
+            Group::Layer(layer) if self.layer_on(layer) => {
+                self.layer_groups.muted[layer as usize] = muted;
+            }
+            Group::Layer(_) => return false,
+            Group::Pass(index) => match self.custom_pass_mut(index) {
+                Some(pass) => pass.muted = muted,
+                None => return false,
+            },
+        }
+        true
+    }
+
+    pub(crate) fn set_soloed(&mut self, group: Group, soloed: bool) -> bool {
+        match group {
+            Group::Bloom => self.bloom_soloed = soloed,
+            Group::Layer(layer) if self.layer_on(layer) => {
+                self.layer_groups.soloed[layer as usize] = soloed;
+            }
+            Group::Layer(_) => return false,
+            Group::Pass(index) => match self.custom_pass_mut(index) {
+                Some(pass) => pass.soloed = soloed,
+                None => return false,
+            },
+        }
+        true
+    }
+
+    // Whether an effect whose group is muted and soloed as given runs
+    pub(crate) fn group_audible(&self, muted: bool, soloed: bool) -> bool {
+        let solo = self.bloom_soloed
+            || LAYER_GROUPS.iter().any(|(layer, ..)| {
+                self.layer_on(*layer) && self.layer_groups.soloed[*layer as usize]
+            })
+            || (0..)
+                .map_while(|index| self.custom_pass(index))
+                .any(|pass| pass.soloed);
+        !muted && (soloed || !solo)
+    }
+
+    // Whether `layer` runs, if it's on
+    pub(crate) fn layer_audible(&self, layer: Layer) -> bool {
+        let index = layer as usize;
+        self.group_audible(
+            self.layer_groups.muted[index],
+            self.layer_groups.soloed[index],
+        )
+    }
+
+    // Whether `layer` is on, and has a group
+    fn layer_on(&self, layer: Layer) -> bool {
+        match layer {
+            Layer::Matte => self.matte().is_some(),
+            #[cfg(feature = "stylize")]
+            Layer::DropShadow => self.drop_shadow().is_some(),
+            Layer::Reflections => self.reflections().is_some(),
+            #[cfg(feature = "temporal")]
+            Layer::Stabilization => self.bloom_stabilization().is_some(),
+            #[cfg(feature = "bloom")]
+            Layer::Flare => self.lens_flare().is_some(),
+            #[cfg(feature = "stylize")]
+            Layer::Sparkles => self.sparkles().is_some(),
+            #[cfg(feature = "stylize")]
+            Layer::DistanceGlow => self.distance_glow().is_some(),
+            #[cfg(feature = "temporal")]
+            Layer::Accumulation => self.accumulation().is_some(),
+            #[cfg(not(feature = "bloom"))]
+            Layer::Flare => false,
+            #[cfg(not(feature = "stylize"))]
+            Layer::DropShadow | Layer::Sparkles | Layer::DistanceGlow => false,
+            #[cfg(not(feature = "temporal"))]
+            Layer::Stabilization | Layer::Accumulation => false,
+        }
+    }
+}
@@ -0,0 +1,172 @@
+// src/guides.rs
+//
+// Alignment guides
+//
+// Aligning projectors and framing a composition both want reference lines over the
+// actual output: a grid, the center, and the title- and action-safe areas broadcast
+// framing keeps text and action within. The guides are one fullscreen pass blended
+// over the final frame, discarding every pixel off a line, so they cost next to
+// nothing and never touch the effects.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// Settings of the alignment guides, see [`Nnpipe::set_guides`](crate::Nnpipe::set_guides).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Guides {
+    /// Grid cells across and down; the lines between them are drawn, so 0 or 1
+    /// leaves out the lines of that direction.
+    pub grid: [u32; 2],
+    /// Draw a cross at the center of the frame.
+    pub crosshair: bool,
+    /// Size of the title-safe rectangle as a fraction of the frame; 0 hides it.
+    pub title_safe: f32,
+    /// Size of the action-safe rectangle as a fraction of the frame; 0 hides it.
+    pub action_safe: f32,
+    /// Straight RGBA color of the lines, blended over the frame by its alpha.
+    pub color: [f32; 4],
+    /// Width of the lines in target pixels.
+    pub line_width: f32,
+}
+
+impl Default for Guides {
+    fn default() -> Self {
+        Self {
+            grid: [3, 3],
+            crosshair: true,
+            title_safe: 0.8,
+            action_safe: 0.9,
+            color: [1.0, 1.0, 1.0, 0.5],
+            line_width: 1.0,
+        }
+    }
+}
+
+impl Guides {
+    // The settings as the shader's uniform, for a target of `target_size`
+    fn uniform(&self, target_size: [u32; 2]) -> [f32; 12] {
+        let [r, g, b, a] = self.color;
+        [
+            r,
+            g,
+            b,
+            a,
+            target_size[0] as f32,
+            target_size[1] as f32,
+            self.grid[0] as f32,
+            self.grid[1] as f32,
+            self.title_safe,
+            self.action_safe,
+            if self.crosshair { 1.0 } else { 0.0 },
+            self.line_width.max(0.0),
+        ]
+    }
+}
+
+pub(crate) struct GuidesLayer {
+    settings: Guides,
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl GuidesLayer {
+    pub fn new(device: &wgpu::Device, cache: &PipelineCache, settings: Guides) -> Self {
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Guides Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform([1, 1])),
+        );
+
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Guides Bind Group Layout",
+            &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Guides Pipeline Layout", &bind_group_layout);
+
+        let shader = cache.shader(
+            device,
+            "Guides Shader",
+            &expand(include_str!("shaders/guides.wgsl")),
+        );
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Guides Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Guides Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            settings,
+            uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> Guides {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Guides) {
+        self.settings = settings;
+    }
+
+    // Lay the guides out for a target of `target_size`, ahead of the upload
+    pub fn set_target_size(&self, target_size: [u32; 2]) {
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&self.settings.uniform(target_size)));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Draw the guides over `target`
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "guides", skip_all))]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Guides pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
@@ -0,0 +1,261 @@
+// src/gutter.rs
+//
+// Bloom gutter
+//
+// The blur passes clamp to the edges of the bloom textures, so light near the frame's
+// edges spreads as if its edge pixels went on forever, which pulls the bloom towards
+// the borders, and lights just outside the frame don't bloom into it at all. With a
+// gutter, scenes drawn by `process` are drawn into a copy of the scene texture padded
+// on every side, its center is copied into the scene texture for the rest of the
+// chain, and the bloom is extracted and blurred at the padded size. The center of the
+// blurred result is copied into the pipeline's bloom texture, so the composite crops
+// the gutter away without knowing about it.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+
+pub(crate) struct GutterLayer {
+    gutter: u32,
+    // Gutter at the bloom's resolution
+    bloom_gutter: u32,
+    scene_texture: wgpu::Texture,
+    scene_view: wgpu::TextureView,
+    brightness_texture: wgpu::Texture,
+    brightness_view: wgpu::TextureView,
+    blur_h_view: wgpu::TextureView,
+    blur_v_texture: wgpu::Texture,
+    blur_v_view: wgpu::TextureView,
+    brightness_bind_group: wgpu::BindGroup,
+    blur_h_bind_group: wgpu::BindGroup,
+    blur_v_bind_group: wgpu::BindGroup,
+}
+
+impl GutterLayer {
+    // `brightness_bind_group` makes a brightness pass bind group from the padded scene,
+    // exclusion mask and resolution; `blur_bind_group` a blur pass bind group reading
+    // a padded bloom texture, horizontally or not
+    pub fn new(
+        device: &wgpu::Device,
+        gutter: u32,
+        [width, height]: [u32; 2],
+        [bloom_width, bloom_height]: [u32; 2],
+        bloom_format: wgpu::TextureFormat,
+        brightness_bind_group: impl FnOnce(
+            &wgpu::TextureView,
+            &wgpu::TextureView,
+            &wgpu::Buffer,
+        ) -> wgpu::BindGroup,
+        blur_bind_group: impl Fn(&wgpu::TextureView, bool) -> wgpu::BindGroup,
+    ) -> Self {
+        let bloom_gutter = (gutter as f32 * bloom_width as f32 / width as f32).round() as u32;
+        let size = [width + 2 * gutter, height + 2 * gutter];
+        let bloom_size = [
+            bloom_width + 2 * bloom_gutter,
+            bloom_height + 2 * bloom_gutter,
+        ];
+
+        let texture = |size: [u32; 2], format| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC
+                        | wgpu::TextureUsages::COPY_DST,
+                )
+                .format(format)
+                .build(device)
+        };
+        let scene_texture = texture(size, wgpu::TextureFormat::Rgba16Float);
+        let brightness_texture = texture(bloom_size, bloom_format);
+        let blur_h_texture = texture(bloom_size, bloom_format);
+        let blur_v_texture = texture(bloom_size, bloom_format);
+        let scene_view = scene_texture.view().build();
+        let brightness_view = brightness_texture.view().build();
+        let blur_h_view = blur_h_texture.view().build();
+        let blur_v_view = blur_v_texture.view().build();
+
+        // Padded sizes for the brightness pass
+        let resolution_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Gutter Resolution Buffer"),
+            contents: bytemuck::cast_slice(&[
+                size[0] as f32,
+                size[1] as f32,
+                bloom_size[0] as f32,
+                bloom_size[1] as f32,
+            ]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        // The stencil exclusion doesn't cover the gutter; this mask excludes nothing
+        let exclusion_view = wgpu::TextureBuilder::new()
+            .size([1, 1])
+            .dimension(wgpu::TextureDimension::D2)
+            .usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(wgpu::TextureFormat::R8Unorm)
+            .build(device)
+            .view()
+            .build();
+
+        let brightness_bind_group =
+            brightness_bind_group(&scene_view, &exclusion_view, &resolution_buffer);
+        let blur_h_bind_group = blur_bind_group(&brightness_view, true);
+        let blur_v_bind_group = blur_bind_group(&blur_h_view, false);
+
+        Self {
+            gutter,
+            bloom_gutter,
+            scene_texture,
+            scene_view,
+            brightness_texture,
+            brightness_view,
+            blur_h_view,
+            blur_v_texture,
+            blur_v_view,
+            brightness_bind_group,
+            blur_h_bind_group,
+            blur_v_bind_group,
+        }
+    }
+
+    pub fn gutter(&self) -> u32 {
+        self.gutter
+    }
+
+    // The padded scene, for `process` to draw into
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.scene_view
+    }
+
+    // Copy the frame's part of the padded scene into the pipeline's scene texture
+    pub fn encode_crop_scene(&self, encoder: &mut wgpu::CommandEncoder, scene: &wgpu::Texture) {
+        copy_region(encoder, &self.scene_texture, scene, self.gutter, true);
+    }
+
+    // Extract the padded scene's bright areas, and copy the frame's part into
+    // `brightness`
+    pub fn encode_brightness(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        brightness: &wgpu::Texture,
+    ) {
+        encode_pass(
+            encoder,
+            "Gutter brightness pass",
+            &self.brightness_view,
+            pipeline,
+            &self.brightness_bind_group,
+        );
+        copy_region(
+            encoder,
+            &self.brightness_texture,
+            brightness,
+            self.bloom_gutter,
+            true,
+        );
+    }
+
+    // Copy `brightness` back over the frame's part of the padded one, after it was
+    // changed in place
+    pub fn encode_restore_brightness(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        brightness: &wgpu::Texture,
+    ) {
+        copy_region(
+            encoder,
+            brightness,
+            &self.brightness_texture,
+            self.bloom_gutter,
+            false,
+        );
+    }
+
+    // Blur the padded brightness, and copy the frame's part into `bloom`
+    pub fn encode_blur(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        bloom: &wgpu::Texture,
+    ) {
+        encode_pass(
+            encoder,
+            "Gutter horizontal blur pass",
+            &self.blur_h_view,
+            pipeline,
+            &self.blur_h_bind_group,
+        );
+        encode_pass(
+            encoder,
+            "Gutter vertical blur pass",
+            &self.blur_v_view,
+            pipeline,
+            &self.blur_v_bind_group,
+        );
+        copy_region(
+            encoder,
+            &self.blur_v_texture,
+            bloom,
+            self.bloom_gutter,
+            true,
+        );
+    }
+}
+
+// Record a fullscreen pass into `view`
+fn encode_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+) {
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        })],
+        depth_stencil_attachment: None,
+    });
+
+    pass.set_pipeline(pipeline);
+    pass.set_bind_group(0, bind_group, &[]);
+    pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+}
+
+// Copy between a padded texture and one of the frame's size, from the padded one's
+// center if `from_padded`, else into it
+fn copy_region(
+    encoder: &mut wgpu::CommandEncoder,
+    source: &wgpu::Texture,
+    destination: &wgpu::Texture,
+    gutter: u32,
+    from_padded: bool,
+) {
+    let (frame, padded) = if from_padded {
+        (destination, source)
+    } else {
+        (source, destination)
+    };
+    let center = wgpu::ImageCopyTexture {
+        texture: padded,
+        mip_level: 0,
+        origin: wgpu::Origin3d {
+            x: gutter,
+            y: gutter,
+            z: 0,
+        },
+        aspect: wgpu::TextureAspect::All,
+    };
+    let (source, destination) = if from_padded {
+        (center, frame.as_image_copy())
+    } else {
+        (frame.as_image_copy(), center)
+    };
+    encoder.copy_texture_to_texture(source, destination, frame.extent());
+}
@@ -0,0 +1,52 @@
+// src/handle.rs
+//
+// Thread-safe parameter handle
+//
+// Audio analysis, network listeners and other producers of param values tend to run
+// on threads of their own, while the pipeline lives on the render thread. A handle is
+// a cloneable, `Send` way to set params by path from any of them: the values go into
+// the pipeline's command queue, and the pipeline applies them once per frame in
+// `Nnpipe::update`, so a frame never renders half of a change.
+
+use crate::command::{CommandQueue, PipelineCommand};
+use crate::nnpipe::Nnpipe;
+
+/// Sets params of a pipeline from any thread, see [`Nnpipe::params_handle`].
+///
+/// Clones share the pipeline's [`CommandQueue`]. Values set on it wait there until
+/// the pipeline's next [`Nnpipe::update`], which applies them in the order they were
+/// set.
+#[derive(Clone, Debug)]
+pub struct ParamsHandle {
+    commands: CommandQueue,
+}
+
+impl ParamsHandle {
+    /// Set the param at `path` (see [`Nnpipe::list_params`]) on the next update.
+    /// Paths the pipeline doesn't have are reported by
+    /// [`Nnpipe::take_command_errors`] then.
+    pub fn set(&self, path: impl Into<String>, value: f32) {
+        self.commands.push(PipelineCommand::SetParam {
+            path: path.into(),
+            value,
+        });
+    }
+
+    /// Whether changes, or other commands, are waiting for the next update.
+    pub fn is_pending(&self) -> bool {
+        self.commands.is_pending()
+    }
+}
+
+impl Nnpipe {
+    /// A handle for setting params from other threads.
+    ///
+    /// All handles of a pipeline share its command queue, which outlives resizes and
+    /// device recoveries. The changes are applied through [`Nnpipe::set_param`] at
+    /// the start of [`Nnpipe::update`].
+    pub fn params_handle(&self) -> ParamsHandle {
+        ParamsHandle {
+            commands: self.command_queue(),
+        }
+    }
+}
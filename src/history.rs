@@ -0,0 +1,148 @@
+// src/history.rs
+//
+// Undo and redo of settings changes
+//
+// With the history enabled, the pipeline's settings are saved as a config before
+// every change made through `set_param`, `apply_config` or `morph_to_preset`, and
+// undoing applies the last saved config back. Sweeping one param records a single
+// step rather than one per value, so a knob turned during a show undoes in one go.
+// Changes made through the setters aren't seen; hosts mark those with `checkpoint`.
+// Only built with the `config` feature.
+
+use std::collections::VecDeque;
+
+use crate::config::PipelineConfig;
+use crate::nnpipe::Nnpipe;
+use crate::params::remap_pass_path;
+
+pub(crate) struct History {
+    limit: usize,
+    undo: VecDeque<PipelineConfig>,
+    redo: Vec<PipelineConfig>,
+    // Param path the latest step was recorded for, whose further changes it covers
+    sweeping: Option<String>,
+}
+
+impl History {
+    fn push(&mut self, config: PipelineConfig) {
+        if self.undo.len() == self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(config);
+    }
+
+    // Follow passes moved by `Nnpipe::reorder_passes` to their new indices
+    pub(crate) fn remap_passes(&mut self, new_index: &[usize]) {
+        for config in self.undo.iter_mut().chain(&mut self.redo) {
+            for pass_config in &mut config.passes {
+                if let Some(index) = &mut pass_config.index {
+                    *index = new_index.get(*index).copied().unwrap_or(*index);
+                }
+            }
+        }
+        if let Some(path) = &mut self.sweeping {
+            *path = remap_pass_path(path, new_index);
+        }
+    }
+}
+
+impl Nnpipe {
+    /// Keep a history of settings changes for [`Nnpipe::undo`] and [`Nnpipe::redo`],
+    /// of up to `limit` steps.
+    ///
+    /// Changes made through [`Nnpipe::set_param`], [`Nnpipe::apply_config`] and
+    /// [`Nnpipe::morph_to_preset`] are recorded, consecutive changes of the same param
+    /// as one step. Call [`Nnpipe::checkpoint`] before changes made through the
+    /// setters. Enabling it again keeps the steps recorded so far, within the new
+    /// limit.
+    pub fn enable_history(&mut self, limit: usize) {
+        let history = self.history.get_or_insert_with(|| History {
+            limit,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            sweeping: None,
+        });
+        history.limit = limit.max(1);
+        while history.undo.len() > history.limit {
+            history.undo.pop_front();
+        }
+    }
+
+    /// Stop recording changes and drop the history.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Record the current settings as a step to undo back to, e.g. ahead of changes
+    /// made through the setters. Does nothing without a history.
+    pub fn checkpoint(&mut self) {
+        self.record(None);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history
+            .as_ref()
+            .is_some_and(|history| !history.undo.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history
+            .as_ref()
+            .is_some_and(|history| !history.redo.is_empty())
+    }
+
+    /// Go back to the settings before the last recorded change, stopping any morph.
+    /// Returns `false` if there's nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let current = self.config();
+        let Some(history) = &mut self.history else {
+            return false;
+        };
+        let Some(config) = history.undo.pop_back() else {
+            return false;
+        };
+        history.redo.push(current);
+        history.sweeping = None;
+        self.restore_settings(&config);
+        true
+    }
+
+    /// Reapply the last change undone, if no change was recorded since. Returns
+    /// `false` if there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let current = self.config();
+        let Some(history) = &mut self.history else {
+            return false;
+        };
+        let Some(config) = history.redo.pop() else {
+            return false;
+        };
+        history.push(current);
+        history.sweeping = None;
+        self.restore_settings(&config);
+        true
+    }
+
+    // Record the settings ahead of a change, unless it continues a sweep of the param
+    // at `path`
+    pub(crate) fn record(&mut self, path: Option<&str>) {
+        match &self.history {
+            None => return,
+            Some(history) if path.is_some() && history.sweeping.as_deref() == path => return,
+            Some(_) => {}
+        }
+        let current = self.config();
+        let history = self.history.as_mut().expect("checked above");
+        history.push(current);
+        history.redo.clear();
+        history.sweeping = path.map(str::to_string);
+    }
+
+    // Put back settings saved with `config`. It holds every pass, so applying it
+    // can't fail; channel thresholds are only in it while set.
+    fn restore_settings(&mut self, config: &PipelineConfig) {
+        self.morph = None;
+        let _ = self.apply_settings(config);
+        self.set_channel_thresholds(config.bloom.channel_thresholds);
+    }
+}
@@ -0,0 +1,338 @@
+// src/hud.rs
+//
+// Stats HUD
+//
+// Every host ends up wanting to know how fast the pipeline runs, and each used to
+// build its own counter. The HUD measures the wall-clock time between the frames the
+// pipeline draws and shows the frame rate, the mean and worst frame time, and a graph
+// of the recent frame times in a corner of the output, through the text overlay
+// shared with the burn-in.
+//
+// It can also list how long each stage of the chain took on the GPU. Devices with
+// timestamp queries get a timestamp written between the stages, resolved into a
+// buffer that's mapped once the frame is submitted and read a frame or two later;
+// frames drawn while a readback is in flight go untimed. Other devices list the
+// CPU time spent recording each stage instead, which shows what the chain costs
+// the host but not the GPU.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use nannou::wgpu;
+
+use crate::burn_in::BurnInCorner;
+use crate::cache::PipelineCache;
+use crate::overlay::{TextOverlay, MAX_GRAPH_LEN, MAX_LINES};
+use crate::upload::UniformBuffer;
+
+/// Settings of the stats HUD, see [`Nnpipe::set_stats_hud`](crate::Nnpipe::set_stats_hud).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatsHud {
+    pub corner: BurnInCorner,
+    /// Size of a font pixel in target pixels.
+    pub scale: u32,
+    /// Frame rate the graph is drawn against: bars reaching past its middle line
+    /// took longer than a frame at this rate, and turn red.
+    pub target_fps: f32,
+    /// Show the graph of the recent frame times below the numbers.
+    pub graph: bool,
+    /// List the time each stage of the chain took, measured on the GPU where the
+    /// device has [`wgpu::Features::TIMESTAMP_QUERY`], and as the CPU time spent
+    /// recording the stage otherwise. See [`Nnpipe::stage_times`](crate::Nnpipe::stage_times).
+    pub stages: bool,
+}
+
+impl Default for StatsHud {
+    fn default() -> Self {
+        Self {
+            corner: BurnInCorner::TopRight,
+            scale: 2,
+            target_fps: 60.0,
+            graph: true,
+            stages: false,
+        }
+    }
+}
+
+// Wall-clock times between drawn frames, in seconds, oldest first
+#[derive(Debug, Default)]
+struct FrameTimes {
+    last: Option<web_time::Instant>,
+    times: VecDeque<f32>,
+}
+
+// Most stages a frame is timed in, as many as fit below the frame rate and times
+const MAX_STAGES: usize = MAX_LINES - 3;
+
+type MapState = Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>;
+
+// Timestamp queries between the stages, and the buffers they're read back through
+struct Timestamps {
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    mapped: MapState,
+}
+
+#[derive(Debug, Default)]
+struct Stages {
+    // Stages of the frame being recorded, or read back, with the CPU time spent
+    // recording each in milliseconds
+    recorded: Vec<(String, f32)>,
+    // Whether the frame being recorded is timed
+    recording: bool,
+    // Timestamps were resolved for `recorded`, and the readback is mapped once true
+    pending: bool,
+    requested: bool,
+    // End of the last stage recorded, for the CPU times
+    last: Option<web_time::Instant>,
+    // Last measured stages and their times in milliseconds
+    times: Vec<(String, f32)>,
+}
+
+pub(crate) struct HudLayer {
+    settings: StatsHud,
+    frame_times: Mutex<FrameTimes>,
+    stages: Mutex<Stages>,
+    timestamps: Option<Timestamps>,
+    overlay: TextOverlay,
+}
+
+impl HudLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cache: &PipelineCache,
+        vertex_shader: &wgpu::ShaderModule,
+        settings: StatsHud,
+    ) -> Self {
+        let timestamps = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| {
+                let count = MAX_STAGES as u32 + 1;
+                let size = count as wgpu::BufferAddress * 8;
+                let buffer = |label, usage| {
+                    device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some(label),
+                        size,
+                        usage,
+                        mapped_at_creation: false,
+                    })
+                };
+                Timestamps {
+                    query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                        label: Some("Stats HUD Timestamps"),
+                        ty: wgpu::QueryType::Timestamp,
+                        count,
+                    }),
+                    resolve: buffer(
+                        "Stats HUD Timestamp Resolve",
+                        wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    ),
+                    readback: buffer(
+                        "Stats HUD Timestamp Readback",
+                        wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    ),
+                    period: queue.get_timestamp_period(),
+                    mapped: MapState::default(),
+                }
+            });
+        Self {
+            settings,
+            frame_times: Mutex::default(),
+            stages: Mutex::default(),
+            timestamps,
+            overlay: TextOverlay::new(device, queue, cache, vertex_shader),
+        }
+    }
+
+    pub fn settings(&self) -> StatsHud {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: StatsHud) {
+        self.settings = settings;
+    }
+
+    // Carry the measured frame times over from the layer of a rebuilt pipeline
+    pub fn take_frame_times(&self, previous: &HudLayer) {
+        let previous = std::mem::take(&mut *previous.frame_times.lock().expect("not poisoned"));
+        *self.frame_times.lock().expect("not poisoned") = previous;
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        self.overlay.uniform_buffer()
+    }
+
+    // Last measured stages and their times in milliseconds, empty unless listed
+    pub fn stage_times(&self) -> Vec<(String, f32)> {
+        match self.settings.stages {
+            true => self.stages.lock().expect("not poisoned").times.clone(),
+            false => Vec::new(),
+        }
+    }
+
+    // Start timing the stages of the frame being recorded into `encoder`, unless
+    // they aren't listed or the last timed frame is still being read back
+    pub fn begin_stages(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut stages = self.stages.lock().expect("not poisoned");
+        stages.recording = self.settings.stages && !stages.pending;
+        if !stages.recording {
+            return;
+        }
+        stages.recorded.clear();
+        stages.last = Some(web_time::Instant::now());
+        if let Some(timestamps) = &self.timestamps {
+            encoder.write_timestamp(&timestamps.query_set, 0);
+        }
+    }
+
+    // End the stage called `label` at this point of `encoder`. Stages past the
+    // most that are listed are added to the last one.
+    pub fn end_stage(&self, encoder: &mut wgpu::CommandEncoder, label: &str) {
+        let mut stages = self.stages.lock().expect("not poisoned");
+        if !stages.recording {
+            return;
+        }
+        let now = web_time::Instant::now();
+        let time = stages
+            .last
+            .replace(now)
+            .map_or(0.0, |last| (now - last).as_secs_f32() * 1000.0);
+        if stages.recorded.len() == MAX_STAGES {
+            let other = &mut stages.recorded[MAX_STAGES - 1];
+            other.0 = "OTHER".to_string();
+            other.1 += time;
+        } else {
+            stages.recorded.push((label.to_string(), time));
+        }
+        if let Some(timestamps) = &self.timestamps {
+            let index = stages.recorded.len() as u32;
+            encoder.write_timestamp(&timestamps.query_set, index);
+        }
+    }
+
+    // Stop timing the frame, and resolve its timestamps for reading back once the
+    // frame is submitted
+    pub fn finish_stages(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut stages = self.stages.lock().expect("not poisoned");
+        if !std::mem::take(&mut stages.recording) {
+            return;
+        }
+        let Some(timestamps) = &self.timestamps else {
+            stages.times = std::mem::take(&mut stages.recorded);
+            return;
+        };
+        let count = stages.recorded.len() as u32 + 1;
+        encoder.resolve_query_set(&timestamps.query_set, 0..count, &timestamps.resolve, 0);
+        encoder.copy_buffer_to_buffer(
+            &timestamps.resolve,
+            0,
+            &timestamps.readback,
+            0,
+            count as wgpu::BufferAddress * 8,
+        );
+        stages.pending = true;
+        stages.requested = false;
+    }
+
+    // Read back the timestamps of the last timed frame if they're mapped, and map
+    // them if the frame has been submitted since. Every frame the pipeline records
+    // is submitted before the next one starts.
+    fn read_stages(&self, stages: &mut Stages) {
+        let Some(timestamps) = &self.timestamps else {
+            return;
+        };
+        if !stages.pending {
+            return;
+        }
+        if !stages.requested {
+            let mapped = timestamps.mapped.clone();
+            timestamps
+                .readback
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    *mapped.lock().expect("not poisoned") = Some(result);
+                });
+            stages.requested = true;
+            return;
+        }
+        let Some(result) = timestamps.mapped.lock().expect("not poisoned").take() else {
+            return;
+        };
+        if result.is_ok() {
+            let count = stages.recorded.len() + 1;
+            let ticks: Vec<u64> = {
+                let range = timestamps.readback.slice(..).get_mapped_range();
+                bytemuck::cast_slice(&range[..count * 8]).to_vec()
+            };
+            timestamps.readback.unmap();
+            let period = timestamps.period as f64 * 1e-6;
+            stages.times = stages
+                .recorded
+                .iter()
+                .zip(ticks.windows(2))
+                .map(|((label, _), ticks)| {
+                    let time = ticks[1].saturating_sub(ticks[0]) as f64 * period;
+                    (label.clone(), time as f32)
+                })
+                .collect();
+        }
+        stages.pending = false;
+    }
+
+    // Time the frame about to be drawn into a target of `target_size`, and write
+    // the stats including it
+    pub fn write_stats(&self, target_size: [u32; 2]) {
+        let settings = &self.settings;
+        let mut frame_times = self.frame_times.lock().expect("not poisoned");
+        let now = web_time::Instant::now();
+        if let Some(last) = frame_times.last.replace(now) {
+            if frame_times.times.len() == MAX_GRAPH_LEN {
+                frame_times.times.pop_front();
+            }
+            frame_times.times.push_back((now - last).as_secs_f32());
+        }
+
+        let times = &frame_times.times;
+        let lines = if times.is_empty() {
+            vec!["FPS -".to_string()]
+        } else {
+            let mean = times.iter().sum::<f32>() / times.len() as f32;
+            let worst = times.iter().copied().fold(0.0, f32::max);
+            vec![
+                format!("FPS {:.1}", 1.0 / mean.max(1e-6)),
+                format!("MEAN {:.1} MS", mean * 1000.0),
+                format!("MAX {:.1} MS", worst * 1000.0),
+            ]
+        };
+        // The graph's full height is two frames at the target rate
+        let graph: Vec<f32> = if settings.graph {
+            let budget = 1.0 / settings.target_fps.max(1e-3);
+            times.iter().map(|time| time / (2.0 * budget)).collect()
+        } else {
+            Vec::new()
+        };
+        let mut lines = lines;
+        if settings.stages {
+            let mut stages = self.stages.lock().expect("not poisoned");
+            self.read_stages(&mut stages);
+            lines.extend(
+                stages
+                    .times
+                    .iter()
+                    .map(|(label, time)| format!("{label} {time:.2}")),
+            );
+        }
+        self.overlay
+            .write(&lines, &graph, settings.corner, settings.scale, target_size);
+    }
+
+    // Draw the HUD over `target`
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        self.overlay.encode(encoder, target);
+    }
+}
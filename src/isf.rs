@@ -0,0 +1,365 @@
+// src/isf.rs
+//
+// ISF (Interactive Shader Format) effects
+//
+// An ISF file is a GLSL fragment shader with a JSON header declaring its inputs. The
+// header's float, bool, long, event, color and point2D inputs become the pass's
+// params, one per component (`tint_r`, ..., `center_x`, `center_y`), and the shader
+// is wrapped in a prelude that maps the ISF built-ins onto the pass bindings:
+// `IMG_NORM_PIXEL` and friends read the chain's frame, `RENDERSIZE` and `TIME` come
+// from the globals, and coordinates have their origin at the bottom left like ISF
+// expects. The result goes through the GLSL translation of `src/translate.rs`.
+//
+// Single-pass filters with one image input are supported: multi-pass shaders,
+// persistent buffers and audio inputs aren't. Only built with the `isf` feature.
+
+use nannou::wgpu;
+use serde::Deserialize;
+
+use crate::nnpipe::Nnpipe;
+use crate::pass::{ShaderError, PASSTHROUGH_SOURCE};
+use crate::translate::glsl_to_wgsl;
+
+/// An ISF file that couldn't be imported.
+#[derive(Clone, Debug)]
+pub struct IsfError {
+    pub message: String,
+}
+
+impl std::fmt::Display for IsfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ISF error: {}", self.message)
+    }
+}
+
+impl std::error::Error for IsfError {}
+
+fn error(message: impl ToString) -> IsfError {
+    IsfError {
+        message: message.to_string(),
+    }
+}
+
+/// The kind of an [`IsfInput`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IsfInputType {
+    Float,
+    /// A switch, true from 0.5.
+    Bool,
+    /// A momentary switch, like `Bool`.
+    Event,
+    /// An integer, such as a menu choice.
+    Long,
+    /// RGBA.
+    Color,
+    Point2D,
+}
+
+impl IsfInputType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "float" => IsfInputType::Float,
+            "bool" => IsfInputType::Bool,
+            "event" => IsfInputType::Event,
+            "long" => IsfInputType::Long,
+            "color" => IsfInputType::Color,
+            "point2D" => IsfInputType::Point2D,
+            _ => return None,
+        })
+    }
+
+    // Suffixes of the params the input becomes
+    fn components(self) -> &'static [&'static str] {
+        match self {
+            IsfInputType::Color => &["_r", "_g", "_b", "_a"],
+            IsfInputType::Point2D => &["_x", "_y"],
+            _ => &[""],
+        }
+    }
+
+    // GLSL type of the input as the shader sees it
+    fn glsl_type(self) -> &'static str {
+        match self {
+            IsfInputType::Float => "float",
+            IsfInputType::Bool | IsfInputType::Event => "bool",
+            IsfInputType::Long => "int",
+            IsfInputType::Color => "vec4",
+            IsfInputType::Point2D => "vec2",
+        }
+    }
+}
+
+/// A parameter input declared in an ISF header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsfInput {
+    pub name: String,
+    pub ty: IsfInputType,
+    /// One value per component; 0 for components the header leaves out.
+    pub default: Vec<f32>,
+    /// The header's range, if it has one, per component.
+    pub min: Option<Vec<f32>>,
+    pub max: Option<Vec<f32>>,
+}
+
+impl IsfInput {
+    /// Names of the pass params the input becomes.
+    pub fn param_names(&self) -> Vec<String> {
+        self.ty
+            .components()
+            .iter()
+            .map(|suffix| format!("{}{suffix}", self.name))
+            .collect()
+    }
+}
+
+/// An ISF filter, parsed and wrapped for the pass bindings.
+///
+/// ```ignore
+/// let index = pipeline.add_isf_pass(&device, "Kaleidoscope", &std::fs::read_to_string(path)?);
+/// ```
+#[derive(Clone, Debug)]
+pub struct IsfShader {
+    description: Option<String>,
+    inputs: Vec<IsfInput>,
+    glsl: String,
+}
+
+// The header fields the importer uses; the rest (credits, categories, ...) are ignored
+#[derive(Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct Header {
+    description: Option<String>,
+    #[serde(default)]
+    inputs: Vec<HeaderInput>,
+    #[serde(default)]
+    passes: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct HeaderInput {
+    name: String,
+    #[serde(rename = "TYPE")]
+    ty: String,
+    default: Option<serde_json::Value>,
+    min: Option<serde_json::Value>,
+    max: Option<serde_json::Value>,
+}
+
+impl IsfShader {
+    /// Parse an ISF file: the JSON header in the leading `/*{ ... }*/` comment, and the
+    /// GLSL after it.
+    ///
+    /// Fails if the header is missing or malformed, or declares more than one image
+    /// input, several passes or inputs of unsupported types. The GLSL is only checked
+    /// when the pass is compiled.
+    pub fn parse(source: &str) -> Result<Self, IsfError> {
+        let start = source
+            .find("/*")
+            .ok_or_else(|| error("no JSON header comment"))?;
+        let end = source[start..]
+            .find("*/")
+            .map(|end| start + end)
+            .ok_or_else(|| error("unterminated JSON header comment"))?;
+        let header: Header = serde_json::from_str(&source[start + 2..end])
+            .map_err(|json_error| error(format!("header: {json_error}")))?;
+        if header.passes.len() > 1 {
+            return Err(error("multi-pass shaders aren't supported"));
+        }
+
+        let mut inputs = Vec::new();
+        let mut images = 0;
+        for input in header.inputs {
+            if input.ty == "image" {
+                images += 1;
+                if images > 1 {
+                    return Err(error(format!(
+                        "image input '{}': only one image, the chain's frame, is supported",
+                        input.name
+                    )));
+                }
+                continue;
+            }
+            let ty = IsfInputType::parse(&input.ty).ok_or_else(|| {
+                error(format!(
+                    "input '{}' has unsupported type '{}'",
+                    input.name, input.ty
+                ))
+            })?;
+            let count = ty.components().len();
+            let values = |value: Option<serde_json::Value>| {
+                value.map(|value| components(&value, count)).transpose()
+            };
+            let default = values(input.default)
+                .map_err(|message| error(format!("default of '{}': {message}", input.name)))?
+                .unwrap_or_else(|| vec![0.0; count]);
+            let min = values(input.min)
+                .map_err(|message| error(format!("min of '{}': {message}", input.name)))?;
+            let max = values(input.max)
+                .map_err(|message| error(format!("max of '{}': {message}", input.name)))?;
+            inputs.push(IsfInput {
+                name: input.name,
+                ty,
+                default,
+                min,
+                max,
+            });
+        }
+
+        let glsl = wrap(&source[end + 2..], &inputs);
+        Ok(Self {
+            description: header.description,
+            inputs,
+            glsl,
+        })
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn inputs(&self) -> &[IsfInput] {
+        &self.inputs
+    }
+
+    /// The wrapped GLSL, which errors' line numbers refer to.
+    pub fn glsl(&self) -> &str {
+        &self.glsl
+    }
+
+    /// The pass params, at the inputs' defaults.
+    pub fn params(&self) -> Vec<(String, f32)> {
+        self.inputs
+            .iter()
+            .flat_map(|input| input.param_names().into_iter().zip(input.default.clone()))
+            .collect()
+    }
+}
+
+// A header value as `count` components: a number, a bool or an array of numbers,
+// with missing components 0
+fn components(value: &serde_json::Value, count: usize) -> Result<Vec<f32>, String> {
+    let number = |value: &serde_json::Value| match value {
+        serde_json::Value::Number(number) => number.as_f64().map(|number| number as f32),
+        serde_json::Value::Bool(on) => Some(if *on { 1.0 } else { 0.0 }),
+        _ => None,
+    };
+    let mut values = match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(number)
+            .collect::<Option<Vec<f32>>>()
+            .ok_or("not an array of numbers")?,
+        value => vec![number(value).ok_or("not a number")?],
+    };
+    values.resize(count, 0.0);
+    Ok(values)
+}
+
+// The GLSL body of an ISF file with the prelude mapping the ISF built-ins onto the
+// pass bindings, and a `main` that sets up the inputs and coordinates first
+fn wrap(body: &str, inputs: &[IsfInput]) -> String {
+    let members: Vec<String> = inputs
+        .iter()
+        .flat_map(IsfInput::param_names)
+        .map(|name| format!("    float {name};\n"))
+        .collect();
+    let params_block = if members.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "layout(set = 0, binding = 2) uniform Params {{\n{}}} params;\n",
+            members.concat()
+        )
+    };
+    let declarations: String = inputs
+        .iter()
+        .map(|input| format!("{} {};\n", input.ty.glsl_type(), input.name))
+        .collect();
+    let assignments: String = inputs
+        .iter()
+        .map(|input| {
+            let names = input.param_names();
+            let values: Vec<String> = names.iter().map(|name| format!("params.{name}")).collect();
+            let value = match input.ty {
+                IsfInputType::Float => values[0].clone(),
+                IsfInputType::Bool | IsfInputType::Event => format!("{} > 0.5", values[0]),
+                IsfInputType::Long => format!("int(round({}))", values[0]),
+                IsfInputType::Color => format!("vec4({})", values.join(", ")),
+                IsfInputType::Point2D => format!("vec2({})", values.join(", ")),
+            };
+            format!("    {} = {value};\n", input.name)
+        })
+        .collect();
+
+    format!(
+        "#version 450
+layout(set = 0, binding = 0) uniform texture2D src_tex;
+layout(set = 0, binding = 1) uniform sampler src_sampler;
+{params_block}layout(set = 0, binding = 3) uniform Globals {{
+    vec2 resolution;
+    float time;
+    uint seed;
+}} globals;
+layout(location = 0) out vec4 isf_FragColor;
+
+vec4 isf_FragCoord;
+vec2 isf_FragNormCoord;
+{declarations}
+vec4 isf_sample(vec2 coord) {{
+    return texture(sampler2D(src_tex, src_sampler), vec2(coord.x, 1.0 - coord.y));
+}}
+
+#define RENDERSIZE globals.resolution
+#define TIME globals.time
+#define PASSINDEX 0
+#define IMG_SIZE(image) RENDERSIZE
+#define IMG_NORM_PIXEL(image, coord) isf_sample(coord)
+#define IMG_PIXEL(image, coord) isf_sample((coord) / RENDERSIZE)
+#define IMG_THIS_NORM_PIXEL(image) isf_sample(isf_FragNormCoord)
+#define IMG_THIS_PIXEL(image) isf_sample(isf_FragNormCoord)
+#define gl_FragColor isf_FragColor
+#define gl_FragCoord isf_FragCoord
+#define main isf_main
+{body}
+#undef main
+#undef gl_FragCoord
+
+void main() {{
+    isf_FragCoord = vec4(gl_FragCoord.x, RENDERSIZE.y - gl_FragCoord.y, gl_FragCoord.zw);
+    isf_FragNormCoord = isf_FragCoord.xy / RENDERSIZE;
+{assignments}    isf_main();
+}}
+"
+    )
+}
+
+impl Nnpipe {
+    /// Append an ISF filter as a custom pass and return its index. Its inputs become
+    /// params at their defaults, see [`IsfShader`].
+    ///
+    /// If the file can't be imported or its shader fails to compile, the pass runs as
+    /// a passthrough and the error is queued for [`Nnpipe::take_shader_errors`].
+    pub fn add_isf_pass(&mut self, device: &wgpu::Device, label: &str, source: &str) -> usize {
+        let shader = IsfShader::parse(source).map_err(|isf_error| ShaderError {
+            label: label.to_string(),
+            message: isf_error.to_string(),
+        });
+        let translated = shader.and_then(|shader| {
+            glsl_to_wgsl(label, shader.glsl()).map(|wgsl| (wgsl, shader.params()))
+        });
+        match translated {
+            Ok((wgsl, params)) => {
+                let params: Vec<(&str, f32)> = params
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), *value))
+                    .collect();
+                self.add_custom_pass(device, label, &wgsl, &params)
+            }
+            Err(error) => {
+                self.queue_shader_error(error);
+                self.add_custom_pass(device, label, PASSTHROUGH_SOURCE, &[])
+            }
+        }
+    }
+}
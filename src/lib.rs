@@ -1,2 +1,160 @@
+#[cfg(feature = "temporal")]
+mod accumulate;
+mod burn_in;
+mod cache;
+mod capture;
+mod clock;
+mod command;
+#[cfg(feature = "config")]
+mod compare;
+#[cfg(feature = "config")]
+mod config;
+mod debug;
+#[cfg(feature = "stylize")]
+mod distance_glow;
+#[cfg(feature = "stylize")]
+mod drop_shadow;
+#[cfg(feature = "stylize")]
+mod effects;
+mod error;
+mod exr;
+#[cfg(feature = "bloom")]
+mod fft;
+#[cfg(feature = "bloom")]
+mod flare;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod golden;
+#[cfg(feature = "grading")]
+mod grading;
+mod graph;
+mod groups;
+mod guides;
+mod gutter;
+mod handle;
+#[cfg(feature = "config")]
+mod history;
+mod hud;
+#[cfg(feature = "isf")]
+mod isf;
+#[cfg(feature = "grading")]
+mod lut;
+mod macros;
+mod magnifier;
+mod matte;
+#[cfg(feature = "config")]
+mod morph;
 mod nnpipe;
+mod output;
+mod overlay;
+mod params;
+mod pass;
+mod pass_graph;
+mod preprocess;
+mod projection;
+mod quality;
+#[cfg(feature = "config")]
+mod randomize;
+mod reflect;
+mod regions;
+mod replay;
+mod resolution;
+mod scopes;
+#[cfg(feature = "scripting")]
+mod script;
+pub mod simple;
+#[cfg(feature = "stylize")]
+mod sparkle;
+mod specialize;
+mod ssr;
+#[cfg(feature = "temporal")]
+mod stabilize;
+#[cfg(feature = "config")]
+mod state;
+mod stereo;
+#[cfg(feature = "shader-import")]
+mod translate;
+mod trigger;
+mod upload;
+mod warp;
+mod zebra;
+#[cfg(feature = "temporal")]
+pub use accumulate::{Accumulation, AccumulationMode};
+pub use burn_in::{BurnIn, BurnInCorner};
+pub use cache::PipelineCache;
+pub use capture::{Frame, FrameCapture, Shutter};
+pub use command::{CommandError, CommandQueue, PipelineCommand, MAX_COMMAND_ERRORS};
+#[cfg(feature = "config")]
+pub use compare::PresetComparison;
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub use config::ConfigWatcher;
+#[cfg(feature = "config")]
+pub use config::{BloomConfig, ChainConfig, ConfigError, OutputConfig, PassConfig, PipelineConfig};
+pub use debug::DebugView;
+#[cfg(feature = "stylize")]
+pub use distance_glow::DistanceGlow;
+#[cfg(feature = "stylize")]
+pub use drop_shadow::DropShadow;
+#[cfg(feature = "stylize")]
+pub use effects::{
+    Border, ConvolutionKernel, FocusBlur, FocusShape, LumaKey, LumaKeySource, Palette,
+    PictureInPicture, ScanlineDisplacement, ScanlineMode,
+};
+pub use error::{NnpipeError, Result};
+pub use exr::encode_exr;
+#[cfg(not(target_arch = "wasm32"))]
+pub use exr::write_exr;
+#[cfg(feature = "bloom")]
+pub use fft::ApertureKernel;
+#[cfg(feature = "bloom")]
+pub use flare::LensFlare;
+#[cfg(feature = "grading")]
+pub use grading::{ColorDeficiency, ColorVision, ColorWheels, Curves, SplitToning};
+pub use graph::{ChainGraph, GraphEdge, GraphNode};
+pub use groups::ParamGroup;
+pub use guides::Guides;
+pub use handle::ParamsHandle;
+pub use hud::StatsHud;
+#[cfg(feature = "isf")]
+pub use isf::{IsfError, IsfInput, IsfInputType, IsfShader};
+#[cfg(feature = "grading")]
+pub use lut::{ColorLut, LutError};
+pub use macros::MacroTarget;
+pub use magnifier::Magnifier;
+pub use matte::{Matte, MatteSource};
+#[cfg(feature = "config")]
+pub use morph::Easing;
 pub use nnpipe::*;
+pub use output::{
+    MaskShape, Output, OutputAdjustments, OutputFit, OutputMask, OutputScaling, OutputTransform,
+};
+pub use pass::{
+    LookupTexture, Pass, PassInput, PassSampler, SamplerAddressing, SamplerFilter, ShaderError,
+};
+pub use pass_graph::{
+    GraphAllocation, GraphPass, PassGraph, PassGraphError, GRAPH_INPUT, GRAPH_OUTPUT,
+};
+pub use preprocess::{PreprocessError, ShaderPreprocessor};
+pub use projection::{FisheyeProjection, FisheyeSource, OutputProjection};
+pub use quality::{QualityPreset, QualityProfile};
+#[cfg(feature = "config")]
+pub use randomize::{RandomConstraints, RandomScope};
+pub use reflect::{ParamDescriptor, ParamType};
+pub use regions::ParamRegion;
+pub use replay::InstantReplay;
+pub use resolution::ResolutionController;
+pub use scopes::{Scope, ScopeData, Scopes};
+#[cfg(feature = "scripting")]
+pub use script::{Script, ScriptError};
+#[cfg(feature = "stylize")]
+pub use sparkle::Sparkles;
+pub use ssr::Reflections;
+#[cfg(feature = "temporal")]
+pub use stabilize::BloomStabilization;
+#[cfg(feature = "config")]
+pub use state::{NamedMacro, NamedTrigger, PipelineState};
+pub use stereo::{Eye, StereoLayout};
+#[cfg(feature = "shader-import")]
+pub use translate::{glsl_to_wgsl, spirv_to_wgsl};
+pub use trigger::Trigger;
+pub use warp::{OutputWarp, WarpMesh, MAX_MESH_SIZE};
+pub use zebra::Zebra;
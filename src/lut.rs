@@ -0,0 +1,186 @@
+// src/lut.rs
+//
+// 3D color lookup tables
+//
+// Looks made in a grading tool are commonly exported as Adobe/Resolve `.cube` files
+// or as HALD CLUT images (an identity image graded like a photo). Both are loaded
+// into the same table, which the color LUT pass uploads as its lookup texture.
+
+#[cfg(all(feature = "io", not(target_arch = "wasm32")))]
+use std::path::Path;
+
+#[cfg(all(feature = "io", not(target_arch = "wasm32")))]
+use crate::error::NnpipeError;
+use crate::pass::LookupTexture;
+
+// Width of the lookup texture the table is wrapped into, well under texture limits
+const LOOKUP_WIDTH: u32 = 4096;
+
+/// A LUT that failed to load or parse.
+#[derive(Clone, Debug)]
+pub struct LutError {
+    pub message: String,
+}
+
+impl std::fmt::Display for LutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid color LUT: {}", self.message)
+    }
+}
+
+impl std::error::Error for LutError {}
+
+fn error(message: impl Into<String>) -> LutError {
+    LutError {
+        message: message.into(),
+    }
+}
+
+/// A 3D color lookup table, for [`Nnpipe::add_color_lut_pass`](crate::Nnpipe::add_color_lut_pass).
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorLut {
+    size: u32,
+    // size³ RGB entries, red changing fastest
+    data: Vec<[f32; 3]>,
+}
+
+impl ColorLut {
+    /// A table of `size` entries per axis, in order with red changing fastest, then
+    /// green, then blue.
+    pub fn new(size: u32, data: Vec<[f32; 3]>) -> Result<Self, LutError> {
+        if size < 2 {
+            return Err(error("a LUT needs at least 2 entries per axis"));
+        }
+        if data.len() != (size * size * size) as usize {
+            return Err(error(format!(
+                "expected {} entries for size {size}, found {}",
+                size * size * size,
+                data.len()
+            )));
+        }
+        Ok(Self { size, data })
+    }
+
+    /// Load a `.cube` file, or any other file as a HALD CLUT image. Not available on
+    /// the web, which has no file system; parse the file's contents instead.
+    #[cfg(all(feature = "io", not(target_arch = "wasm32")))]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NnpipeError> {
+        let path = path.as_ref();
+        let is_cube = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("cube"));
+
+        if is_cube {
+            let source = std::fs::read_to_string(path)?;
+            Ok(Self::from_cube(&source)?)
+        } else {
+            let image = nannou::image::open(path).map_err(|e| match e {
+                nannou::image::ImageError::IoError(e) => NnpipeError::Io(e),
+                e => error(format!("{}: {e}", path.display())).into(),
+            })?;
+            Ok(Self::from_hald(&image)?)
+        }
+    }
+
+    /// Parse the text of a `.cube` file. Only 3D tables over the default 0..1 domain
+    /// are supported.
+    pub fn from_cube(source: &str) -> Result<Self, LutError> {
+        let mut size = None;
+        let mut data = Vec::new();
+
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            match keyword {
+                "LUT_3D_SIZE" => {
+                    let value = words.next().and_then(|word| word.parse().ok());
+                    size = Some(value.ok_or_else(|| error("invalid LUT_3D_SIZE"))?);
+                }
+                "LUT_1D_SIZE" => return Err(error("1D .cube LUTs aren't supported")),
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let default = if keyword == "DOMAIN_MIN" { 0.0 } else { 1.0 };
+                    if words.any(|word| word.parse() != Ok(default)) {
+                        return Err(error("only the default 0..1 domain is supported"));
+                    }
+                }
+                "TITLE" => {}
+                _ => {
+                    let values: Vec<f32> = line
+                        .split_whitespace()
+                        .map(|word| word.parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| error(format!("unexpected line '{line}'")))?;
+                    let [r, g, b] = values[..] else {
+                        return Err(error(format!("unexpected line '{line}'")));
+                    };
+                    data.push([r, g, b]);
+                }
+            }
+        }
+
+        let size = size.ok_or_else(|| error("missing LUT_3D_SIZE"))?;
+        Self::new(size, data)
+    }
+
+    /// Convert a HALD CLUT image. A HALD of level `n` is a square image of n³ pixels
+    /// per side, holding a table of n² entries per axis.
+    #[cfg(feature = "io")]
+    pub fn from_hald(image: &nannou::image::DynamicImage) -> Result<Self, LutError> {
+        let image = image.to_rgba16();
+        let (width, height) = image.dimensions();
+
+        let level = (1..=16).find(|level| level * level * level == width);
+        let Some(level) = level.filter(|_| width == height) else {
+            return Err(error(format!(
+                "a {width}x{height} image isn't a HALD CLUT, which is square with a cube \
+                 number of pixels per side"
+            )));
+        };
+
+        // The pixels in reading order are the table's entries in order
+        let data = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b, _] = pixel.0;
+                [r, g, b].map(|channel| channel as f32 / 65535.0)
+            })
+            .collect();
+        Self::new(level * level, data)
+    }
+
+    /// A table that leaves colors unchanged.
+    pub fn identity(size: u32) -> Self {
+        let size = size.max(2);
+        let last = (size - 1) as f32;
+        let data = (0..size * size * size)
+            .map(|i| {
+                [i % size, i / size % size, i / (size * size)].map(|value| value as f32 / last)
+            })
+            .collect();
+        Self { size, data }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    // The table wrapped row by row into a texture
+    pub(crate) fn lookup(&self) -> LookupTexture {
+        let entries = self.data.len() as u32;
+        let width = entries.min(LOOKUP_WIDTH);
+        let height = entries.div_ceil(width);
+
+        let mut data: Vec<[f32; 4]> = self.data.iter().map(|&[r, g, b]| [r, g, b, 1.0]).collect();
+        data.resize((width * height) as usize, [0.0; 4]);
+        LookupTexture {
+            width,
+            height,
+            data,
+        }
+    }
+}
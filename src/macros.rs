@@ -0,0 +1,124 @@
+// src/macros.rs
+//
+// Macro controls
+//
+// A performer has two hands and a handful of faders. A macro is a single 0-1 control
+// driving any number of params at once, each between its own two values along its own
+// curve, so one fader can raise the bloom, lower its threshold and add grain together.
+// Macros have param paths of their own, `macros.<name>`, so MIDI, OSC and scripts
+// reach them like any other param.
+
+use crate::nnpipe::Nnpipe;
+
+/// A param driven by a macro, see [`Nnpipe::add_macro`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct MacroTarget {
+    /// Path of the param, see [`Nnpipe::list_params`].
+    pub path: String,
+    /// Value of the param with the macro at 0.
+    pub start: f32,
+    /// Value of the param with the macro at 1, below `start` to lower it.
+    pub end: f32,
+    /// Exponent of the response: 1 is linear, above 1 the param moves slowly at first
+    /// and quickly towards the end, below 1 the other way round.
+    pub curve: f32,
+}
+
+impl MacroTarget {
+    /// A target moving the param at `path` linearly from `start` to `end`.
+    pub fn new(path: impl Into<String>, start: f32, end: f32) -> Self {
+        Self {
+            path: path.into(),
+            start,
+            end,
+            curve: 1.0,
+        }
+    }
+
+    pub fn with_curve(mut self, curve: f32) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    // The param's value with the macro at `amount`
+    fn value(&self, amount: f32) -> f32 {
+        let t = amount.clamp(0.0, 1.0).powf(self.curve.max(1e-3));
+        self.start + (self.end - self.start) * t
+    }
+}
+
+// A registered macro and the value it was last set to
+#[derive(Clone, Debug)]
+pub(crate) struct MacroState {
+    pub name: String,
+    pub targets: Vec<MacroTarget>,
+    pub value: f32,
+}
+
+impl Nnpipe {
+    /// Register a macro driving `targets`, replacing any macro named `name`.
+    ///
+    /// The macro starts at 0 without touching its targets; set it with
+    /// [`Nnpipe::set_macro`] or through its path, `macros.<name>`. Targets may be any
+    /// param but other macros.
+    pub fn add_macro(&mut self, name: impl Into<String>, targets: Vec<MacroTarget>) {
+        let name = name.into();
+        self.macros.retain(|state| state.name != name);
+        self.macros.push(MacroState {
+            name,
+            targets,
+            value: 0.0,
+        });
+    }
+
+    /// Remove the macro named `name`, leaving its targets where it last put them.
+    /// Returns `false` if there's none.
+    pub fn remove_macro(&mut self, name: &str) -> bool {
+        let count = self.macros.len();
+        self.macros.retain(|state| state.name != name);
+        self.macros.len() != count
+    }
+
+    pub fn macro_targets(&self, name: &str) -> Option<&[MacroTarget]> {
+        self.find_macro(name).map(|state| state.targets.as_slice())
+    }
+
+    pub fn macro_value(&self, name: &str) -> Option<f32> {
+        self.find_macro(name).map(|state| state.value)
+    }
+
+    /// Set the macro named `name` to `value`, from 0 to 1, moving all its targets.
+    /// Returns `false` if there's no such macro.
+    pub fn set_macro(&mut self, name: &str, value: f32) -> bool {
+        self.set_param(&format!("macros.{name}"), value)
+    }
+
+    // Set the macro and its targets, without recording it in the history. Targets
+    // the pipeline doesn't have are skipped.
+    pub(crate) fn write_macro(&mut self, name: &str, value: f32) -> bool {
+        let Some(state) = self.macros.iter_mut().find(|state| state.name == name) else {
+            return false;
+        };
+        state.value = value.clamp(0.0, 1.0);
+        let targets: Vec<(String, f32)> = state
+            .targets
+            .iter()
+            .filter(|target| !target.path.starts_with("macros."))
+            .map(|target| (target.path.clone(), target.value(value)))
+            .collect();
+        for (path, value) in targets {
+            self.apply_param(&path, value);
+        }
+        true
+    }
+
+    // Names of all macros, in registration order
+    pub(crate) fn macro_names(&self) -> impl Iterator<Item = &str> {
+        self.macros.iter().map(|state| state.name.as_str())
+    }
+
+    fn find_macro(&self, name: &str) -> Option<&MacroState> {
+        self.macros.iter().find(|state| state.name == name)
+    }
+}
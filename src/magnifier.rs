@@ -0,0 +1,268 @@
+// src/magnifier.rs
+//
+// Pixel-peeper magnifier
+//
+// Bloom falloff, banding and the edges of effects are too fine to judge at the
+// frame's own scale. The magnifier shows the pixels around a point blown up into an
+// inset in a corner of the frame. While it's on, the chain renders into a texture of
+// the magnifier's, which a single pass then copies to the target with the inset on
+// top; the same texture lets the exact HDR value under the point be read back.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::burn_in::BurnInCorner;
+use crate::cache::PipelineCache;
+use crate::error::Result;
+use crate::nnpipe::{decode_readback, readback_size};
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// Settings of the magnifier, see [`Nnpipe::set_magnifier`](crate::Nnpipe::set_magnifier).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Magnifier {
+    /// Point the inset is centered on, in the pipeline's pixels from the top left.
+    /// Pixel `x` spans `x..x + 1`, so `x + 0.5` centers it.
+    pub center: [f32; 2],
+    /// Size of a magnified pixel in the inset, in pixels.
+    pub zoom: f32,
+    /// Side of the square inset, in the pipeline's pixels.
+    pub size: u32,
+    pub corner: BurnInCorner,
+}
+
+impl Default for Magnifier {
+    fn default() -> Self {
+        Self {
+            center: [0.5, 0.5],
+            zoom: 8.0,
+            size: 128,
+            corner: BurnInCorner::BottomRight,
+        }
+    }
+}
+
+impl Magnifier {
+    // The settings as the shader's uniform
+    fn uniform(&self) -> [f32; 8] {
+        [
+            self.center[0],
+            self.center[1],
+            self.zoom.max(1.0),
+            self.size as f32,
+            self.corner as u32 as f32,
+            0.0,
+            0.0,
+            0.0,
+        ]
+    }
+}
+
+pub(crate) struct MagnifierLayer {
+    settings: Magnifier,
+    // The chain's output, at the pipeline's size
+    source_texture: wgpu::Texture,
+    source_view: wgpu::TextureView,
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl MagnifierLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        size: [u32; 2],
+        settings: Magnifier,
+    ) -> Self {
+        let source_texture = wgpu::TextureBuilder::new()
+            .size(size)
+            .usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            )
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device);
+        let source_view = source_texture.view().build();
+
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Magnifier Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Magnifier Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Magnifier Pipeline Layout", &bind_group_layout);
+
+        let shader = cache.shader(
+            device,
+            "Magnifier Shader",
+            &expand(include_str!("shaders/magnifier.wgsl")),
+        );
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Magnifier Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Magnifier Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            settings,
+            source_texture,
+            source_view,
+            uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> Magnifier {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Magnifier) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Where the chain renders while the magnifier is on
+    pub fn source_view(&self) -> &wgpu::TextureView {
+        &self.source_view
+    }
+
+    // Read the chain's last output at the center back from the GPU
+    pub async fn read_center(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<[f32; 4]> {
+        let [width, height] = self.source_texture.size();
+        let [x, y] = self.settings.center;
+        let pixel = [
+            (x.max(0.0) as u32).min(width - 1),
+            (y.max(0.0) as u32).min(height - 1),
+        ];
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Magnifier Readback Buffer"),
+            size: readback_size([1, 1]),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Magnifier Readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.source_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: pixel[0],
+                    y: pixel[1],
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Wait);
+        // The buffer is only dropped with the device if the sender never ran
+        receiver.await.unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+        let value = decode_readback(&slice.get_mapped_range(), 1)[0];
+        buffer.unmap();
+        Ok(value)
+    }
+
+    // Copy the chain's output to `target`, which is the pipeline's size, with the
+    // inset on top
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "magnifier", skip_all))]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Magnifier pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
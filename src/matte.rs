@@ -0,0 +1,263 @@
+// src/matte.rs
+//
+// Matte extraction
+//
+// Drawn content makes a handy mask: text or shapes drawn into the scene can decide
+// where an effect applies. The matte layer thresholds the scene's alpha or luminance
+// into a soft-edged mask each frame, ahead of the bloom, into a texture of its own
+// that effect passes see at binding 8 and the host can use or read back.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// The scene channel a [`Matte`] is derived from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "snake_case"))]
+pub enum MatteSource {
+    /// The scene's alpha, for content drawn over a transparent background.
+    #[default]
+    Alpha,
+    /// The scene's linear luminance, for content drawn over black.
+    Luminance,
+}
+
+/// Settings of the scene matte, see [`Nnpipe::set_matte`](crate::Nnpipe::set_matte).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct Matte {
+    pub source: MatteSource,
+    /// Value of the source at which the matte is half on.
+    pub threshold: f32,
+    /// Width of the soft edge around the threshold, in units of the source. At 0 the
+    /// matte is either fully on or off.
+    pub feather: f32,
+    /// Whether the matte is on below the threshold rather than above it.
+    pub invert: bool,
+}
+
+impl Default for Matte {
+    fn default() -> Self {
+        Self {
+            source: MatteSource::Alpha,
+            threshold: 0.5,
+            feather: 0.1,
+            invert: false,
+        }
+    }
+}
+
+impl Matte {
+    // The settings as the shader's uniform
+    fn uniform(&self) -> [f32; 4] {
+        let source = match self.source {
+            MatteSource::Alpha => 0.0,
+            MatteSource::Luminance => 1.0,
+        };
+        [
+            self.threshold,
+            self.feather.max(0.0),
+            source,
+            if self.invert { 1.0 } else { 0.0 },
+        ]
+    }
+}
+
+pub(crate) struct MatteLayer {
+    settings: Matte,
+    // The matte in every channel, at the pipeline's size
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    // Reads the pipeline's scene texture
+    bind_group: wgpu::BindGroup,
+}
+
+impl MatteLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        scene_view: &wgpu::TextureView,
+        settings: Matte,
+    ) -> Self {
+        let texture = wgpu::TextureBuilder::new()
+            .size(scene_view.size())
+            .usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            )
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device);
+        let view = texture.view().build();
+
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Matte Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Matte Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Matte Pipeline Layout", &bind_group_layout);
+
+        let shader = cache.shader(
+            device,
+            "Matte Shader",
+            &expand(include_str!("shaders/matte.wgsl")),
+        );
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Matte Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        let bind_group = create_bind_group(device, &bind_group_layout, &uniform_buffer, scene_view);
+
+        Self {
+            settings,
+            texture,
+            view,
+            uniform_buffer,
+            pipeline,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> Matte {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Matte) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    // A bind group for extracting the matte of another scene view
+    pub fn scene_bind_group(
+        &self,
+        device: &wgpu::Device,
+        scene_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            scene_view,
+        )
+    }
+
+    // Extract the matte of the scene in `scene_bind_group`, or of the pipeline's scene
+    // texture
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "matte", skip_all))]
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scene_bind_group: Option<&wgpu::BindGroup>,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Matte pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, scene_bind_group.unwrap_or(&self.bind_group), &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+
+    // Leave the matte empty, as if there were none
+    pub fn clear(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Matte clear pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &UniformBuffer,
+    scene_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Matte Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(scene_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
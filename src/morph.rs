@@ -0,0 +1,258 @@
+// src/morph.rs
+//
+// Preset morphs
+//
+// Applying a preset mid-performance jumps straight to the new look. A morph glides
+// there instead: as the host calls `Nnpipe::update` every frame, the numeric settings
+// the preset mentions are interpolated from where they were when the morph started,
+// passes it turns on or off fade in or out through their mix, and lookup textures of
+// the same size are crossfaded texel by texel. What can't be interpolated (toggles,
+// the upsample filter, lookups of another size) switches at the end, when the preset
+// is applied as a whole. Only built with the `config` feature.
+
+use nannou::wgpu;
+
+use crate::config::{missing_error, ConfigError, PipelineConfig};
+use crate::nnpipe::Nnpipe;
+use crate::pass::LookupTexture;
+
+/// How a morph's progress follows its time, see [`Nnpipe::morph_to_preset`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Starting slowly, quadratic.
+    EaseIn,
+    /// Ending slowly, quadratic.
+    EaseOut,
+    /// Starting and ending slowly, a smoothstep.
+    EaseInOut,
+}
+
+impl Easing {
+    /// The eased progress at `t`, clamped to 0..=1.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+// A setting a morph interpolates
+enum Setting {
+    // A bloom setting, by its param path
+    Bloom(String),
+    PassParam(usize, String),
+    PassMix(usize),
+}
+
+// A running morph, with the start and end of everything it interpolates
+pub(crate) struct Morph {
+    preset: PipelineConfig,
+    duration: f32,
+    easing: Easing,
+    elapsed: f32,
+    settings: Vec<(Setting, f32, f32)>,
+    channel_thresholds: Option<([f32; 3], [f32; 3])>,
+    // Lookups by pass, with the start one if it can be crossfaded
+    lookups: Vec<(usize, Option<LookupTexture>, LookupTexture)>,
+    // Mixes of the passes faded out, given back once they're disabled
+    faded_out: Vec<(usize, f32)>,
+}
+
+fn lerp(start: f32, end: f32, t: f32) -> f32 {
+    start + (end - start) * t
+}
+
+impl Nnpipe {
+    /// Glide from the current settings to those of `preset` over `duration` seconds,
+    /// as [`Nnpipe::update`] advances time.
+    ///
+    /// The bloom settings and pass params the preset mentions are interpolated along
+    /// `easing`, passes it turns on or off fade in or out through their mix, and its
+    /// lookup textures are crossfaded from the passes' current ones of the same size.
+    /// Everything else in the preset, including lookups that can't be crossfaded,
+    /// applies at the end. A new morph replaces a running one, starting from wherever
+    /// that got to.
+    ///
+    /// Like [`Nnpipe::apply_config`], passes and params the pipeline doesn't have are
+    /// skipped and reported in the returned error, as are lookups whose data doesn't
+    /// match their size; the rest still morphs. With the
+    /// history enabled, the settings before the morph are recorded as one step.
+    pub fn morph_to_preset(
+        &mut self,
+        preset: &PipelineConfig,
+        duration: f32,
+        easing: Easing,
+    ) -> Result<(), ConfigError> {
+        self.record(None);
+        let bloom = &preset.bloom;
+        let mut settings = Vec::new();
+        for (name, end) in [
+            ("threshold", bloom.threshold),
+            ("intensity", bloom.intensity),
+            ("adaptive_blur_scaling", bloom.adaptive_blur_scaling),
+            ("max_blur_radius", bloom.max_blur_radius),
+            ("intensity_curve", bloom.intensity_curve),
+            ("horizontal_blur_strength", bloom.horizontal_blur_strength),
+            ("vertical_blur_strength", bloom.vertical_blur_strength),
+            ("stretch", bloom.stretch),
+            ("blur_angle", bloom.blur_angle),
+            ("saturation", bloom.saturation),
+            ("hue_shift", bloom.hue_shift),
+        ] {
+            let path = format!("bloom.{name}");
+            if let (Some(start), Some(end)) = (self.get_param(&path), end) {
+                settings.push((Setting::Bloom(path), start, end));
+            }
+        }
+        let channel_thresholds = self.channel_thresholds.zip(bloom.channel_thresholds);
+
+        let mut missing = Vec::new();
+        let mut lookups = Vec::new();
+        let mut faded_out = Vec::new();
+        for pass_config in &preset.passes {
+            let index = match self.config_pass(pass_config) {
+                Ok(index) => index,
+                Err(pass) => {
+                    missing.push(pass);
+                    continue;
+                }
+            };
+            let pass = self.custom_pass_mut(index).expect("found by index");
+
+            for (name, end) in &pass_config.params {
+                match pass.param(name) {
+                    Some(start) => {
+                        settings.push((Setting::PassParam(index, name.clone()), start, *end))
+                    }
+                    None => missing.push(format!("param '{name}' of pass '{}'", pass.label)),
+                }
+            }
+
+            // Passes turned on start from their input, those turned off end on it
+            let mix = pass_config.mix.unwrap_or(pass.mix);
+            match (pass.enabled, pass_config.enabled) {
+                (false, Some(true)) => {
+                    pass.enabled = true;
+                    settings.push((Setting::PassMix(index), 0.0, mix));
+                }
+                (true, Some(false)) => {
+                    settings.push((Setting::PassMix(index), pass.mix, 0.0));
+                    faded_out.push((index, mix));
+                }
+                _ if pass_config.mix.is_some() => {
+                    settings.push((Setting::PassMix(index), pass.mix, mix));
+                }
+                _ => {}
+            }
+
+            match &pass_config.lookup {
+                Some(lookup) if lookup.check().is_err() => {
+                    missing.push(format!("valid lookup of pass '{}'", pass.label));
+                }
+                Some(lookup) => {
+                    let start = pass.lookup().filter(|start| {
+                        [start.width, start.height] == [lookup.width, lookup.height]
+                    });
+                    lookups.push((index, start.cloned(), lookup.clone()));
+                }
+                None => {}
+            }
+        }
+
+        self.morph = Some(Morph {
+            preset: preset.clone(),
+            duration,
+            easing,
+            elapsed: 0.0,
+            settings,
+            channel_thresholds,
+            lookups,
+            faded_out,
+        });
+        missing_error(&missing)
+    }
+
+    /// Whether a morph started with [`Nnpipe::morph_to_preset`] is still running.
+    pub fn morphing(&self) -> bool {
+        self.morph.is_some()
+    }
+
+    // Advance the running morph by `dt` seconds
+    pub(crate) fn advance_morph(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, dt: f32) {
+        let Some(mut morph) = self.morph.take() else {
+            return;
+        };
+        morph.elapsed += dt.max(0.0);
+        if morph.elapsed < morph.duration {
+            let t = morph.easing.apply(morph.elapsed / morph.duration);
+            self.write_morph(device, queue, &morph, t);
+            self.morph = Some(morph);
+            return;
+        }
+
+        // The preset's own values land exactly, and switch what can't be interpolated.
+        // What it names but the pipeline lacks was reported when the morph started.
+        let _ = self.apply_settings(&morph.preset);
+        for (index, mix) in morph.faded_out {
+            if let Some(pass) = self.custom_pass_mut(index) {
+                pass.mix = mix;
+            }
+        }
+        for (index, _, lookup) in morph.lookups {
+            if self.custom_pass(index).is_some() {
+                self.replace_pass_lookup(device, queue, index, lookup);
+            }
+        }
+    }
+
+    // Write the settings `t` of the way through `morph`
+    fn write_morph(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, morph: &Morph, t: f32) {
+        for (setting, start, end) in &morph.settings {
+            let value = lerp(*start, *end, t);
+            match setting {
+                // The morph was recorded as one step when it started
+                Setting::Bloom(path) => {
+                    self.apply_param(path, value);
+                }
+                Setting::PassParam(index, name) => {
+                    if let Some(pass) = self.custom_pass_mut(*index) {
+                        pass.set_param(name, value);
+                    }
+                }
+                Setting::PassMix(index) => {
+                    if let Some(pass) = self.custom_pass_mut(*index) {
+                        pass.mix = value;
+                    }
+                }
+            }
+        }
+
+        if let Some((start, end)) = morph.channel_thresholds {
+            let thresholds = [0, 1, 2].map(|channel| lerp(start[channel], end[channel], t));
+            self.set_channel_thresholds(Some(thresholds));
+        }
+
+        for (index, start, end) in &morph.lookups {
+            let Some(start) = start else {
+                continue;
+            };
+            if self.custom_pass(*index).is_none() {
+                continue;
+            }
+            let data = start
+                .data
+                .iter()
+                .zip(&end.data)
+                .map(|(start, end)| [0, 1, 2, 3].map(|c| lerp(start[c], end[c], t)))
+                .collect();
+            let lookup = LookupTexture { data, ..*end };
+            self.replace_pass_lookup(device, queue, *index, lookup);
+        }
+    }
+}
@@ -4,15 +4,112 @@
 
 use nannou::prelude::*;
 use nannou::wgpu;
+use std::collections::VecDeque;
+#[cfg(all(feature = "temporal", not(target_arch = "wasm32")))]
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "temporal")]
+use crate::accumulate::{Accumulation, AccumulationLayer};
+use crate::burn_in::{BurnIn, BurnInLayer};
+use crate::cache::PipelineCache;
+use crate::clock::EffectClock;
+use crate::command::{CommandError, CommandQueue};
+use crate::debug::{DebugLayer, DebugView};
+#[cfg(feature = "stylize")]
+use crate::distance_glow::{DistanceGlow, DistanceGlowLayer};
+#[cfg(feature = "stylize")]
+use crate::drop_shadow::{DropShadow, DropShadowLayer};
+use crate::error::{check_render_format, check_samples, check_size, NnpipeError, Result};
+#[cfg(all(feature = "temporal", not(target_arch = "wasm32")))]
+use crate::exr::write_exr;
+#[cfg(feature = "bloom")]
+use crate::fft::{ApertureKernel, FftBloom};
+#[cfg(feature = "bloom")]
+use crate::flare::{FlareLayer, LensFlare};
+use crate::graph::ChainGraph;
+use crate::groups::{Layer, LayerGroups};
+use crate::guides::{Guides, GuidesLayer};
+use crate::gutter::GutterLayer;
+#[cfg(feature = "config")]
+use crate::history::History;
+use crate::hud::{HudLayer, StatsHud};
+use crate::macros::MacroState;
+use crate::magnifier::{Magnifier, MagnifierLayer};
+use crate::matte::{Matte, MatteLayer};
+#[cfg(feature = "config")]
+use crate::morph::Morph;
+use crate::output::Output;
+use crate::params::remap_pass_path;
+use crate::pass::{
+    LookupTexture, Pass, PassBindings, PassInput, PassInputs, PassResources, PassSampler,
+    ShaderError, COMPUTE_PASSTHROUGH_SOURCE, PASSTHROUGH_SOURCE,
+};
+use crate::pass_graph::{GraphLayer, PassGraph, PassGraphError, GRAPH_INPUT};
+use crate::preprocess::{expand, ShaderPreprocessor};
+use crate::quality::{QualityPreset, QualityProfile};
+use crate::reflect::ParamDescriptor;
+use crate::regions::ParamRegion;
+use crate::scopes::{Scope, ScopeData, Scopes, ScopesLayer};
+#[cfg(feature = "stylize")]
+use crate::sparkle::{SparkleLayer, Sparkles};
+use crate::ssr::{ReflectionLayer, Reflections};
+#[cfg(feature = "temporal")]
+use crate::stabilize::{BloomStabilization, StabilizeLayer};
+use crate::stereo::{Eye, StereoLayer, StereoLayout};
+use crate::trigger::{Trigger, TriggerState};
+use crate::upload::{UniformBuffer, Uploader};
+use crate::zebra::{Zebra, ZebraLayer};
+
+/// How the composite upsamples a bloom running below full resolution.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum UpsampleFilter {
+    /// A single hardware bilinear tap. Cheapest, but blocky at low bloom scales.
+    #[default]
+    Bilinear,
+    /// Catmull-Rom bicubic, sharp and smooth (9 bilinear taps).
+    CatmullRom,
+    /// 3x3 tent filter, the softest option.
+    Tent,
+}
+
+impl UpsampleFilter {
+    // Value of the composite shader's filter uniform
+    fn shader_value(self) -> f32 {
+        match self {
+            UpsampleFilter::Bilinear => 0.0,
+            UpsampleFilter::CatmullRom => 1.0,
+            UpsampleFilter::Tent => 2.0,
+        }
+    }
+}
 
 #[allow(dead_code)]
 pub struct Nnpipe {
+    // Construction parameters, kept so resources can be rebuilt
+    width: u32,
+    height: u32,
+    samples: u32,
+    bloom_scale: f32,
+    quality: QualityProfile,
+    equirectangular: bool,
+    // Size given at creation or resize; the chain runs at it times the render scale
+    base_size: [u32; 2],
+    render_scale: f32,
+
     // Textures for the pipeline
     pub scene_texture: wgpu::Texture,
     pub brightness_texture: wgpu::Texture,
     pub blur_h_texture: wgpu::Texture,
     pub blur_v_texture: wgpu::Texture,
     pub composite_texture: wgpu::Texture,
+    pub effect_texture: wgpu::Texture,
+    pub output_texture: wgpu::Texture,
 
     // Texture views
     pub scene_view: wgpu::TextureView,
@@ -20,49 +117,371 @@ pub struct Nnpipe {
     pub blur_h_view: wgpu::TextureView,
     pub blur_v_view: wgpu::TextureView,
     pub composite_view: wgpu::TextureView,
+    pub effect_view: wgpu::TextureView,
+    pub output_view: wgpu::TextureView,
 
     // Render pipelines for each pass
-    brightness_pipeline: wgpu::RenderPipeline,
-    blur_pipeline: wgpu::RenderPipeline,
-    composite_pipeline: wgpu::RenderPipeline,
+    brightness_pipeline: Arc<wgpu::RenderPipeline>,
+    blur_pipeline: Arc<wgpu::RenderPipeline>,
+    composite_pipeline: Arc<wgpu::RenderPipeline>,
+    composite_premultiplied_pipeline: Arc<wgpu::RenderPipeline>,
 
     // Adaptive bloom
     pub adaptive_blur_scaling: f32,
     pub max_blur_radius: f32,
     pub intensity_curve: f32,
 
+    /// Spread of the horizontal blur, scaling the spacing of its samples.
+    pub horizontal_blur_strength: f32,
+
+    /// Spread of the vertical blur, scaling the spacing of its samples.
+    pub vertical_blur_strength: f32,
+
+    /// Anamorphic stretch of the bloom: above 1 it spreads wider horizontally, below 1
+    /// taller vertically, for a subtle lens feel without a streak pass. 1 is the
+    /// default shape.
+    pub bloom_stretch: f32,
+
+    /// Clockwise rotation of the blur axes in radians, tilting the bloom (and its
+    /// stretch) away from screen X/Y. Cheap enough to animate every frame.
+    pub blur_angle: f32,
+
     // Pipeline parameters
     pub brightness_threshold: f32,
     pub bloom_intensity: f32,
 
+    /// Separate thresholds for R, G and B, replacing the luminance threshold while
+    /// set. Each channel blooms on its own once it passes its threshold, so e.g. a
+    /// low red threshold lets red lights glow while other colors at the same
+    /// brightness stay sharp.
+    pub channel_thresholds: Option<[f32; 3]>,
+
+    /// Treat the scene as premultiplied alpha and keep its transparency through the
+    /// chain, so the result can be composited over other content. Bloom adds coverage
+    /// where it spills over transparent areas. When off, the result is the scene's
+    /// alpha applied over black.
+    pub premultiplied_alpha: bool,
+
+    /// Replace NaN and infinite scene pixels with black before the bloom and
+    /// composite see them. A single broken pixel from a host shader otherwise spreads
+    /// through the blur into a blob the size of the bloom.
+    pub sanitize_scene: bool,
+
+    /// Filter used to upsample the bloom when it runs below full resolution.
+    pub upsample_filter: UpsampleFilter,
+
+    /// Saturation of the bloom alone, 1 keeping the highlights' own colors.
+    pub bloom_saturation: f32,
+
+    /// Hue rotation of the bloom alone, in radians.
+    pub bloom_hue_shift: f32,
+
     // Shader bind groups
     pub brightness_bind_group: wgpu::BindGroup,
     pub blur_h_bind_group: wgpu::BindGroup,
     pub blur_v_bind_group: wgpu::BindGroup,
     pub composite_bind_group: wgpu::BindGroup,
 
+    // Layouts for the bind groups that read the scene
+    brightness_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    composite_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    blur_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+
     // Sampler for texture sampling
     sampler: wgpu::Sampler,
 
     // Uniform buffers for parameters
-    threshold_buffer: wgpu::Buffer,
-    blur_h_buffer: wgpu::Buffer,
-    blur_v_buffer: wgpu::Buffer,
-    intensity_buffer: wgpu::Buffer,
-
-    adaptive_scaling_buffer: wgpu::Buffer,
-    max_radius_buffer: wgpu::Buffer,
-    intensity_curve_buffer: wgpu::Buffer,
+    threshold_buffer: UniformBuffer,
+    blur_h_buffer: UniformBuffer,
+    blur_v_buffer: UniformBuffer,
+    intensity_buffer: UniformBuffer,
+
+    adaptive_scaling_buffer: UniformBuffer,
+    max_radius_buffer: UniformBuffer,
+    intensity_curve_buffer: UniformBuffer,
+    alpha_mode_buffer: UniformBuffer,
+    sanitize_buffer: UniformBuffer,
+    resolution_buffer: wgpu::Buffer,
+    upsample_filter_buffer: UniformBuffer,
+    bloom_color_buffer: UniformBuffer,
+
+    // Shared shader and pipeline cache
+    cache: PipelineCache,
+
+    // Effect passes run after the composite, ping-ponging between the
+    // composite and effect textures
+    pass_resources: PassResources,
+    passes: Vec<Pass>,
+    // Copies the output of a compute pass ending the chain to the chain's target, and
+    // blends passes' inputs back over their outputs by their mix. Created with the
+    // first pass
+    passthrough: Option<Pass>,
+    // Passes wired by named textures, run after the effect passes
+    graph_layer: Option<GraphLayer>,
+    globals_buffer: UniformBuffer,
+    uploader: Uploader,
+    clock: EffectClock,
+    // Time the passes see instead of the clock's, for reproducible renders
+    fixed_time: Option<f32>,
+    // Seed of the passes' noise
+    seed: u32,
+    shader_errors: Vec<ShaderError>,
+
+    // One-shot param envelopes, fired by name
+    triggers: Vec<TriggerState>,
+
+    // Controls driving several params at once, set by name
+    pub(crate) macros: Vec<MacroState>,
+
+    // Changes pushed from any thread, run on update, and the errors of those that
+    // failed
+    pub(crate) commands: CommandQueue,
+    pub(crate) command_errors: VecDeque<CommandError>,
+
+    // Preset morph advanced by `update`
+    #[cfg(feature = "config")]
+    pub(crate) morph: Option<Morph>,
+    // Settings to undo and redo, while enabled
+    #[cfg(feature = "config")]
+    pub(crate) history: Option<History>,
+
+    // Additional outputs fed from the output texture
+    outputs: Vec<Output>,
+
+    // Draws the output texture into process targets of another size
+    scaler: Output,
+
+    // Debug view drawn instead of the frame, and its resources
+    debug_view: DebugView,
+    debug_layer: Option<DebugLayer>,
+
+    // Optional depth-stencil texture for the scene pass
+    depth_format: Option<wgpu::TextureFormat>,
+    depth_texture: Option<wgpu::Texture>,
+    depth_view: Option<wgpu::TextureView>,
+    depth_sample_view: Option<wgpu::TextureView>,
+
+    /// Keep regions marked in the scene's stencil buffer out of the effects.
+    ///
+    /// Anything drawn with a nonzero stencil value (UI overlays, text, ...) is left out
+    /// of the bloom and drawn back untouched over the end of the chain. Needs a depth
+    /// format with a stencil aspect (see [`Nnpipe::enable_depth`]) and a single-sampled
+    /// scene; otherwise it has no effect.
+    pub stencil_exclusion: bool,
+
+    // Mask of the excluded regions, rebuilt from the stencil buffer every frame
+    exclusion_texture: wgpu::Texture,
+    exclusion_view: wgpu::TextureView,
+    exclusion_mask_pipeline: Option<wgpu::RenderPipeline>,
+    exclusion_restore_pipeline: Arc<wgpu::RenderPipeline>,
+    exclusion_bind_group: wgpu::BindGroup,
+
+    // Copies the scene to the output in place of the whole chain while bypassed
+    bypass: bool,
+
+    // Mute and solo of the bloom's and the layers' param groups, see `groups.rs`
+    pub(crate) bloom_muted: bool,
+    pub(crate) bloom_soloed: bool,
+    pub(crate) layer_groups: LayerGroups,
+    bypass_pipeline: Arc<wgpu::RenderPipeline>,
+
+    // FFT convolution bloom, replacing the blur passes while a kernel is set
+    #[cfg(feature = "bloom")]
+    fft_kernel: Option<ApertureKernel>,
+    #[cfg(feature = "bloom")]
+    fft_bloom: Option<FftBloom>,
+
+    // Screen-space reflections of scenes with their own depth and normals, added to
+    // the scene in the composite. Without them, it reads a black texture instead.
+    reflection_layer: Option<ReflectionLayer>,
+    empty_reflection_view: wgpu::TextureView,
+
+    // Ghosts and halo of the bright areas, added to the composite
+    #[cfg(feature = "bloom")]
+    pub(crate) flare_layer: Option<FlareLayer>,
+
+    // Glints over the bright spots, added to the composite
+    #[cfg(feature = "stylize")]
+    pub(crate) sparkle_layer: Option<SparkleLayer>,
+
+    // Blurred shadow of the scene's alpha, composited under the scene
+    #[cfg(feature = "stylize")]
+    pub(crate) drop_shadow_layer: Option<DropShadowLayer>,
+
+    // Outline glow around the matte's silhouette, added to the composite
+    #[cfg(feature = "stylize")]
+    pub(crate) distance_glow_layer: Option<DistanceGlowLayer>,
+
+    // History of the brightness texture, blended into it to steady the bloom
+    #[cfg(feature = "temporal")]
+    stabilize_layer: Option<StabilizeLayer>,
+
+    // Padded copies of the scene and bloom textures, for lights at the frame's edges
+    gutter_layer: Option<GutterLayer>,
+
+    // Mask thresholded from the scene, bound to the passes
+    matte_layer: Option<MatteLayer>,
+
+    // Frames of the chain's output folded into a long exposure
+    #[cfg(feature = "temporal")]
+    accumulation_layer: Option<AccumulationLayer>,
+
+    // Histogram, waveform and vectorscope of the chain's output
+    scopes_layer: Option<ScopesLayer>,
+
+    // Blown-up inset of the pixels around a point, drawn with the chain's output
+    magnifier_layer: Option<MagnifierLayer>,
+
+    // Zebra stripes and non-finite pixels marked on the chain's output
+    zebra_layer: Option<ZebraLayer>,
+
+    // Grid, crosshair and safe areas drawn over the final frame
+    guides_layer: Option<GuidesLayer>,
+
+    // Frame number, timecode and param values drawn over the final frame
+    burn_in_layer: Option<BurnInLayer>,
+
+    // Frame rate and frame time graph drawn over the final frame
+    hud_layer: Option<HudLayer>,
+
+    // Eye scenes and frames of stereo rendering
+    stereo_layer: Option<StereoLayer>,
+
+    // Parts of the frame rendered with other params, and the frame they render into,
+    // created on first use
+    pub(crate) param_regions: Vec<ParamRegion>,
+    region_view: Option<wgpu::TextureView>,
 }
 
 impl Nnpipe {
-    pub fn new(device: &wgpu::Device, width: u32, height: u32, samples: u32) -> Self {
+    /// Create a pipeline rendering at `width` x `height`.
+    ///
+    /// This is a fixed virtual resolution: the chain always runs at this size, and
+    /// targets of any other size (e.g. a resized window) get the result scaled in at
+    /// the end, as configured by [`Nnpipe::scaler_mut`].
+    ///
+    /// Fails if the size exceeds the device's texture limits, or if the scene can't
+    /// be multisampled `samples` times on it.
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, samples: u32) -> Result<Self> {
+        Self::with_cache(device, width, height, samples, &PipelineCache::new())
+    }
+
+    /// Like [`Nnpipe::new`], but reusing shaders and pipelines from `cache`.
+    ///
+    /// Share one cache between all pipelines on the same device (layers, windows,
+    /// previews) to avoid compiling the same pipelines for each of them.
+    pub fn with_cache(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        samples: u32,
+        cache: &PipelineCache,
+    ) -> Result<Self> {
+        check_size(device, width, height)?;
+        check_samples(device, samples)?;
+        let quality = QualityPreset::High.profile();
+        Ok(Self::build(
+            device, width, height, samples, quality, 1.0, false, cache,
+        ))
+    }
+
+    /// Like [`Nnpipe::new`], with the bloom set up by a [`QualityPreset`] or a
+    /// [`QualityProfile`] instead of at `High`.
+    ///
+    /// Pass [`QualityPreset::detect`] to scale the bloom down on weak adapters:
+    ///
+    /// ```ignore
+    /// let quality = QualityPreset::detect(&adapter);
+    /// let pipeline = Nnpipe::with_quality(&device, 1920, 1080, 4, quality)?;
+    /// ```
+    pub fn with_quality(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        samples: u32,
+        quality: impl Into<QualityProfile>,
+    ) -> Result<Self> {
+        let cache = PipelineCache::new();
+        Self::with_quality_and_cache(device, width, height, samples, quality, &cache)
+    }
+
+    /// Like [`Nnpipe::with_quality`], but reusing shaders and pipelines from `cache`
+    /// like [`Nnpipe::with_cache`].
+    pub fn with_quality_and_cache(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        samples: u32,
+        quality: impl Into<QualityProfile>,
+        cache: &PipelineCache,
+    ) -> Result<Self> {
+        let quality = quality.into();
+        check_size(device, width, height)?;
+        check_samples(device, samples)?;
+        check_render_format(device, quality.bloom_format)?;
+        let bloom_scale = quality.bloom_scale.clamp(0.1, 1.0);
+        Ok(Self::build(
+            device,
+            width,
+            height,
+            samples,
+            quality,
+            bloom_scale,
+            false,
+            cache,
+        ))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(device, cache)))]
+    fn build(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        samples: u32,
+        quality: QualityProfile,
+        bloom_scale: f32,
+        equirectangular: bool,
+        cache: &PipelineCache,
+    ) -> Self {
+        // The bloom textures can run at a fraction of the pipeline's resolution
+        let bloom_width = ((width as f32 * bloom_scale).round() as u32).max(1);
+        let bloom_height = ((height as f32 * bloom_scale).round() as u32).max(1);
+
         // Create textures
-        let scene_texture = create_render_texture(device, width, height, samples);
-        let brightness_texture = create_render_texture(device, width, height, 1);
-        let blur_h_texture = create_render_texture(device, width, height, 1);
-        let blur_v_texture = create_render_texture(device, width, height, 1);
-        let composite_texture = create_render_texture(device, width, height, 1);
+        let format = wgpu::TextureFormat::Rgba16Float;
+        let bloom_format = quality.bloom_format;
+        let scene_texture = create_render_texture(device, width, height, samples, format);
+        let brightness_texture =
+            create_render_texture(device, bloom_width, bloom_height, 1, bloom_format);
+        let blur_h_texture =
+            create_render_texture(device, bloom_width, bloom_height, 1, bloom_format);
+        let blur_v_texture =
+            create_render_texture(device, bloom_width, bloom_height, 1, bloom_format);
+        // Compute passes write the effect chain's textures as storage textures
+        let chain_texture = || {
+            wgpu::TextureBuilder::new()
+                .size([width, height])
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING,
+                )
+                .format(format)
+                .build(device)
+        };
+        let composite_texture = chain_texture();
+        let effect_texture = chain_texture();
+        // The output can also be copied out, for read_output
+        let output_texture = wgpu::TextureBuilder::new()
+            .size([width, height])
+            .usage(
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            )
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device);
 
         // Create texture views
         let scene_view = scene_texture.view().build();
@@ -70,11 +489,26 @@ impl Nnpipe {
         let blur_h_view = blur_h_texture.view().build();
         let blur_v_view = blur_v_texture.view().build();
         let composite_view = composite_texture.view().build();
+        let effect_view = effect_texture.view().build();
+        let output_view = output_texture.view().build();
 
-        // Create a sampler for texture sampling
+        // Exclusion mask, empty until the stencil exclusion is used
+        let exclusion_texture = wgpu::TextureBuilder::new()
+            .size([width, height])
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(wgpu::TextureFormat::R8Unorm)
+            .build(device);
+        let exclusion_view = exclusion_texture.view().build();
+
+        // Create a sampler for texture sampling. Panoramas wrap around horizontally.
+        let address_mode_u = if equirectangular {
+            wgpu::AddressMode::Repeat
+        } else {
+            wgpu::AddressMode::ClampToEdge
+        };
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("Bloom sampler"),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_u,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
@@ -85,429 +519,778 @@ impl Nnpipe {
 
         // Create uniform buffers
         let brightness_threshold = 0.55f32;
-        let threshold_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Threshold Buffer"),
-            contents: bytemuck::cast_slice(&[brightness_threshold]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let channel_thresholds = None;
+        let threshold_buffer = UniformBuffer::new(
+            device,
+            "Threshold Buffer",
+            bytemuck::cast_slice(&threshold_data(brightness_threshold, channel_thresholds)),
+        );
 
-        // Horizontal blur direction (1.0, 0.0)
-        let blur_h_direction = [1.0f32, 0.0f32];
-        let blur_h_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Horizontal Blur Buffer"),
-            contents: bytemuck::cast_slice(&blur_h_direction),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        // Blur directions, scaled by the strength of each axis
+        let horizontal_blur_strength = 1.0f32;
+        let vertical_blur_strength = 0.7f32;
+        let bloom_stretch = 1.0f32;
+        let blur_angle = 0.0f32;
+        let (blur_h_direction, blur_v_direction) = blur_directions(
+            horizontal_blur_strength,
+            vertical_blur_strength,
+            bloom_stretch,
+            blur_angle,
+        );
+        let blur_h_buffer = UniformBuffer::new(
+            device,
+            "Horizontal Blur Buffer",
+            bytemuck::cast_slice(&blur_h_direction),
+        );
 
-        // Vertical blur direction (0.0, 1.0)
-        let blur_v_direction = [0.0f32, 0.7f32];
-        let blur_v_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertical Blur Buffer"),
-            contents: bytemuck::cast_slice(&blur_v_direction),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let blur_v_buffer = UniformBuffer::new(
+            device,
+            "Vertical Blur Buffer",
+            bytemuck::cast_slice(&blur_v_direction),
+        );
 
         // Bloom intensity
         let bloom_intensity = 3.0f32;
-        let intensity_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Intensity Buffer"),
-            contents: bytemuck::cast_slice(&[bloom_intensity]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let intensity_buffer = UniformBuffer::new(
+            device,
+            "Intensity Buffer",
+            bytemuck::cast_slice(&[bloom_intensity]),
+        );
 
         // Additional buffers for adaptive bloom
         let adaptive_blur_scaling = 5.0f32;
-        let adaptive_scaling_buffer =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Adaptive Scaling Buffer"),
-                contents: bytemuck::cast_slice(&[adaptive_blur_scaling]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
+        let adaptive_scaling_buffer = UniformBuffer::new(
+            device,
+            "Adaptive Scaling Buffer",
+            bytemuck::cast_slice(&[adaptive_blur_scaling]),
+        );
 
-        let max_blur_radius = 40.0f32;
-        let max_radius_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Max Radius Buffer"),
-            contents: bytemuck::cast_slice(&[max_blur_radius]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let max_blur_radius = quality.max_blur_radius;
+        let max_radius_buffer = UniformBuffer::new(
+            device,
+            "Max Radius Buffer",
+            bytemuck::cast_slice(&[max_blur_radius]),
+        );
 
         let intensity_curve = 5.0f32;
-        let intensity_curve_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Intensity Curve Buffer"),
-            contents: bytemuck::cast_slice(&[intensity_curve]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let intensity_curve_buffer = UniformBuffer::new(
+            device,
+            "Intensity Curve Buffer",
+            bytemuck::cast_slice(&[intensity_curve]),
+        );
 
-        // Create shader modules
-        let brightness_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Brightness Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/brightness.wgsl").into()),
-        });
+        // 0.0 for straight alpha over black, 1.0 for premultiplied alpha
+        let premultiplied_alpha = false;
+        let alpha_mode_buffer = UniformBuffer::new(
+            device,
+            "Alpha Mode Buffer",
+            bytemuck::cast_slice(&[premultiplied_alpha as u32 as f32]),
+        );
 
-        let blur_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Blur Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blur.wgsl").into()),
-        });
+        // 1.0 to replace non-finite scene pixels with black
+        let sanitize_scene = false;
+        let sanitize_buffer = UniformBuffer::new(
+            device,
+            "Sanitize Buffer",
+            bytemuck::cast_slice(&[sanitize_scene as u32 as f32]),
+        );
 
-        let composite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Composite Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/composite.wgsl").into()),
+        // Pipeline and bloom resolution, for passes whose target differs from their input
+        let resolution_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Resolution Buffer"),
+            contents: bytemuck::cast_slice(&[
+                width as f32,
+                height as f32,
+                bloom_width as f32,
+                bloom_height as f32,
+            ]),
+            usage: wgpu::BufferUsages::UNIFORM,
         });
 
-        // Create bind group layouts
-        let brightness_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Brightness Bind Group Layout"),
-                entries: &[
-                    // Texture binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    // Sampler binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    // Threshold uniform binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
-            });
+        let upsample_filter = quality.upsample_filter;
+        let upsample_filter_buffer = UniformBuffer::new(
+            device,
+            "Upsample Filter Buffer",
+            bytemuck::cast_slice(&[upsample_filter.shader_value()]),
+        );
 
-        // Similar bind group layouts for blur and composite passes...
-        let blur_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Blur Bind Group Layout"),
-                entries: &[
-                    // Texture binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    // Sampler binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    // Direction uniform binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3, // This would be the next available binding
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 4,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
-            });
+        // Bloom saturation and hue shift
+        let bloom_saturation = 1.0f32;
+        let bloom_hue_shift = 0.0f32;
+        let bloom_color_buffer = UniformBuffer::new(
+            device,
+            "Bloom Color Buffer",
+            bytemuck::cast_slice(&[bloom_saturation, bloom_hue_shift, 0.0, 0.0]),
+        );
 
-        let composite_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Composite Bind Group Layout"),
-                entries: &[
-                    // Scene texture binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    // Bloom texture binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    // Sampler binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    // Intensity uniform binding
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 4, // This would be the next available binding
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                ],
-            });
+        // Resolution and time, shared by all effect passes
+        let globals_buffer = UniformBuffer::new(
+            device,
+            "Globals Buffer",
+            bytemuck::cast_slice(&[width as f32, height as f32, 0.0, 0.0]),
+        );
 
-        // Create bind groups
-        let brightness_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Brightness Bind Group"),
-            layout: &brightness_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&scene_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        threshold_buffer.as_entire_buffer_binding(),
-                    ),
-                },
-            ],
-        });
+        // Create shader modules
+        let brightness_shader = cache.shader(
+            device,
+            "Brightness Shader",
+            &expand(include_str!("shaders/brightness.wgsl")),
+        );
 
-        let blur_h_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Horizontal Blur Bind Group"),
-            layout: &blur_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
+        let mut blur_preprocessor = ShaderPreprocessor::new();
+        if equirectangular {
+            blur_preprocessor = blur_preprocessor.with_define("EQUIRECTANGULAR", "1");
+        }
+        let blur_shader = cache.shader(
+            device,
+            "Blur Shader",
+            &blur_preprocessor
+                .process(include_str!("shaders/blur.wgsl"))
+                .expect("crate shaders preprocess"),
+        );
+
+        let composite_shader = cache.shader(
+            device,
+            "Composite Shader",
+            &expand(include_str!("shaders/composite.wgsl")),
+        );
+
+        // Create bind group layouts
+        let brightness_bind_group_layout = cache.bind_group_layout(
+            device,
+            "Brightness Bind Group Layout",
+            &[
+                // Texture binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&brightness_view),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Sampler binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Threshold uniform binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        blur_h_buffer.as_entire_buffer_binding(),
-                    ),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Exclusion mask binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        adaptive_scaling_buffer.as_entire_buffer_binding(),
-                    ),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Resolution uniform binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 4,
-                    resource: wgpu::BindingResource::Buffer(
-                        max_radius_buffer.as_entire_buffer_binding(),
-                    ),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Sanitize uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
             ],
-        });
+        );
 
-        let blur_v_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Vertical Blur Bind Group"),
-            layout: &blur_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
+        // Similar bind group layouts for blur and composite passes...
+        let blur_bind_group_layout = cache.bind_group_layout(
+            device,
+            "Blur Bind Group Layout",
+            &[
+                // Texture binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&blur_h_view),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Sampler binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Direction uniform binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        blur_v_buffer.as_entire_buffer_binding(),
-                    ),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        adaptive_scaling_buffer.as_entire_buffer_binding(),
-                    ),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3, // This would be the next available binding
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                wgpu::BindGroupLayoutEntry {
                     binding: 4,
-                    resource: wgpu::BindingResource::Buffer(
-                        max_radius_buffer.as_entire_buffer_binding(),
-                    ),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
             ],
-        });
+        );
 
-        let composite_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Composite Bind Group"),
-            layout: &composite_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
+        let composite_bind_group_layout = cache.bind_group_layout(
+            device,
+            "Composite Bind Group Layout",
+            &[
+                // Scene texture binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&scene_view),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Bloom texture binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&blur_v_view),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Sampler binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
+                // Intensity uniform binding
+                wgpu::BindGroupLayoutEntry {
                     binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        intensity_buffer.as_entire_buffer_binding(),
-                    ),
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::Buffer(
-                        intensity_curve_buffer.as_entire_buffer_binding(),
-                    ),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4, // This would be the next available binding
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Alpha mode uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Resolution uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Upsample filter uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Bloom color uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 8,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Sharp and blurred reflection bindings
+                wgpu::BindGroupLayoutEntry {
+                    binding: 9,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 10,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sanitize uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 11,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
             ],
-        });
+        );
+
+        // Create bind groups
+        let brightness_bind_group = create_brightness_bind_group(
+            device,
+            &brightness_bind_group_layout,
+            &scene_view,
+            &exclusion_view,
+            &sampler,
+            &threshold_buffer,
+            &resolution_buffer,
+            &sanitize_buffer,
+        );
+
+        let blur_h_bind_group = create_blur_bind_group(
+            device,
+            &blur_bind_group_layout,
+            &brightness_view,
+            &sampler,
+            [&blur_h_buffer, &adaptive_scaling_buffer, &max_radius_buffer],
+        );
+
+        let blur_v_bind_group = create_blur_bind_group(
+            device,
+            &blur_bind_group_layout,
+            &blur_h_view,
+            &sampler,
+            [&blur_v_buffer, &adaptive_scaling_buffer, &max_radius_buffer],
+        );
+
+        let empty_reflection_view = wgpu::TextureBuilder::new()
+            .size([1, 1])
+            .dimension(wgpu::TextureDimension::D2)
+            .usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(format)
+            .build(device)
+            .view()
+            .build();
+        let composite_bind_group = create_composite_bind_group(
+            device,
+            &composite_bind_group_layout,
+            &scene_view,
+            &blur_v_view,
+            &sampler,
+            &intensity_buffer,
+            &intensity_curve_buffer,
+            &alpha_mode_buffer,
+            &sanitize_buffer,
+            &resolution_buffer,
+            &upsample_filter_buffer,
+            &bloom_color_buffer,
+            [&empty_reflection_view; 2],
+        );
 
         // Create render pipeline layouts
-        let brightness_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Brightness Pipeline Layout"),
-                bind_group_layouts: &[&brightness_bind_group_layout],
-                push_constant_ranges: &[],
-            });
+        let brightness_pipeline_layout = cache.pipeline_layout(
+            device,
+            "Brightness Pipeline Layout",
+            &brightness_bind_group_layout,
+        );
 
-        let blur_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Blur Pipeline Layout"),
-            bind_group_layouts: &[&blur_bind_group_layout],
-            push_constant_ranges: &[],
-        });
+        let blur_pipeline_layout =
+            cache.pipeline_layout(device, "Blur Pipeline Layout", &blur_bind_group_layout);
 
-        let composite_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Composite Pipeline Layout"),
-                bind_group_layouts: &[&composite_bind_group_layout],
-                push_constant_ranges: &[],
-            });
+        let composite_pipeline_layout = cache.pipeline_layout(
+            device,
+            "Composite Pipeline Layout",
+            &composite_bind_group_layout,
+        );
 
         // Create render pipelines
         let brightness_pipeline = create_render_pipeline(
             device,
+            cache,
             &brightness_pipeline_layout,
             &brightness_shader,
             "Brightness Pipeline",
-            wgpu::TextureFormat::Rgba16Float,
+            bloom_format,
         );
 
         let blur_pipeline = create_render_pipeline(
             device,
+            cache,
             &blur_pipeline_layout,
             &blur_shader,
             "Blur Pipeline",
-            wgpu::TextureFormat::Rgba16Float,
+            bloom_format,
         );
 
         let composite_pipeline = create_render_pipeline(
             device,
+            cache,
             &composite_pipeline_layout,
             &composite_shader,
             "Composite Pipeline",
             wgpu::TextureFormat::Rgba16Float,
         );
 
-        // Return the fully initialized PostProcessing struct
-        Self {
-            scene_texture,
-            brightness_texture,
-            blur_h_texture,
-            blur_v_texture,
-            composite_texture,
-            scene_view,
-            brightness_view,
-            blur_h_view,
-            blur_v_view,
-            composite_view,
-            sampler,
-            brightness_pipeline,
-            blur_pipeline,
-            composite_pipeline,
-            threshold_buffer,
-            blur_h_buffer,
-            blur_v_buffer,
-            intensity_buffer,
-
+        // The premultiplied composite writes its alpha as is instead of blending
+        let composite_premultiplied_pipeline = cache.pipeline(
+            device,
+            &composite_pipeline_layout,
+            &composite_shader,
+            &composite_shader,
+            "Composite Premultiplied Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        let pass_resources = PassResources::new(device, cache);
+
+        let scaler = Output::new(
+            device,
+            cache,
+            &output_view,
+            &sampler,
+            wgpu::TextureFormat::Rgba16Float,
+        );
+
+        // Reads the scene through the brightness pass's bind group, which holds it
+        let bypass_shader =
+            cache.shader(device, "Bypass Shader", include_str!("shaders/bypass.wgsl"));
+        let bypass_pipeline = cache.pipeline(
+            device,
+            &brightness_pipeline_layout,
+            &pass_resources.vertex_shader,
+            &bypass_shader,
+            "Bypass Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        // Draws the scene back over excluded regions at the end of the chain
+        let exclusion_bind_group_layout = cache.bind_group_layout(
+            device,
+            "Exclusion Bind Group Layout",
+            &[
+                // Scene texture binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Mask texture binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        );
+
+        let exclusion_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Exclusion Bind Group"),
+            layout: &exclusion_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&scene_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&exclusion_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let exclusion_pipeline_layout = cache.pipeline_layout(
+            device,
+            "Exclusion Pipeline Layout",
+            &exclusion_bind_group_layout,
+        );
+
+        let exclusion_restore_shader = cache.shader(
+            device,
+            "Exclusion Restore Shader",
+            include_str!("shaders/exclusion_restore.wgsl"),
+        );
+
+        // Mix by the mask in the color channels, keep the chain's alpha
+        let exclusion_restore_pipeline = cache.pipeline(
+            device,
+            &exclusion_pipeline_layout,
+            &pass_resources.vertex_shader,
+            &exclusion_restore_shader,
+            "Exclusion Restore Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+        );
+
+        // Return the fully initialized PostProcessing struct
+        Self {
+            width,
+            height,
+            samples,
+            bloom_scale,
+            quality,
+            equirectangular,
+            base_size: [width, height],
+            render_scale: 1.0,
+            scene_texture,
+            brightness_texture,
+            blur_h_texture,
+            blur_v_texture,
+            composite_texture,
+            effect_texture,
+            output_texture,
+            scene_view,
+            brightness_view,
+            blur_h_view,
+            blur_v_view,
+            composite_view,
+            effect_view,
+            output_view,
+            sampler,
+            brightness_pipeline,
+            blur_pipeline,
+            composite_pipeline,
+            composite_premultiplied_pipeline,
+            threshold_buffer,
+            blur_h_buffer,
+            blur_v_buffer,
+            intensity_buffer,
+
             adaptive_scaling_buffer,
             max_radius_buffer,
             intensity_curve_buffer,
+            alpha_mode_buffer,
+            sanitize_buffer,
+            resolution_buffer,
+            upsample_filter_buffer,
+            bloom_color_buffer,
 
             brightness_threshold,
             bloom_intensity,
+            channel_thresholds,
             adaptive_blur_scaling,
             max_blur_radius,
             intensity_curve,
+            horizontal_blur_strength,
+            vertical_blur_strength,
+            bloom_stretch,
+            blur_angle,
+            premultiplied_alpha,
+            sanitize_scene,
+            upsample_filter,
+            bloom_saturation,
+            bloom_hue_shift,
 
             brightness_bind_group,
             blur_h_bind_group,
             blur_v_bind_group,
             composite_bind_group,
+
+            brightness_bind_group_layout,
+            composite_bind_group_layout,
+            blur_bind_group_layout,
+
+            pass_resources,
+            cache: cache.clone(),
+            passes: Vec::new(),
+            passthrough: None,
+            graph_layer: None,
+            globals_buffer,
+            uploader: Uploader::new(),
+            clock: EffectClock::new(),
+            fixed_time: None,
+            seed: 0,
+            shader_errors: Vec::new(),
+            triggers: Vec::new(),
+            macros: Vec::new(),
+            commands: CommandQueue::default(),
+            command_errors: VecDeque::new(),
+            #[cfg(feature = "config")]
+            morph: None,
+            #[cfg(feature = "config")]
+            history: None,
+
+            outputs: Vec::new(),
+            scaler,
+            debug_view: DebugView::Off,
+            debug_layer: None,
+
+            depth_format: None,
+            depth_texture: None,
+            depth_view: None,
+            depth_sample_view: None,
+
+            stencil_exclusion: false,
+            exclusion_texture,
+            exclusion_view,
+            exclusion_mask_pipeline: None,
+            exclusion_restore_pipeline,
+            exclusion_bind_group,
+
+            bypass: false,
+            bypass_pipeline,
+            bloom_muted: false,
+            bloom_soloed: false,
+            layer_groups: LayerGroups::default(),
+
+            #[cfg(feature = "bloom")]
+            fft_kernel: None,
+            #[cfg(feature = "bloom")]
+            fft_bloom: None,
+            reflection_layer: None,
+            empty_reflection_view,
+            #[cfg(feature = "bloom")]
+            flare_layer: None,
+            #[cfg(feature = "stylize")]
+            sparkle_layer: None,
+            #[cfg(feature = "stylize")]
+            drop_shadow_layer: None,
+            #[cfg(feature = "stylize")]
+            distance_glow_layer: None,
+            #[cfg(feature = "temporal")]
+            stabilize_layer: None,
+            gutter_layer: None,
+            matte_layer: None,
+            #[cfg(feature = "temporal")]
+            accumulation_layer: None,
+            scopes_layer: None,
+            magnifier_layer: None,
+            zebra_layer: None,
+            guides_layer: None,
+            burn_in_layer: None,
+            hud_layer: None,
+            stereo_layer: None,
+            param_regions: Vec::new(),
+            region_view: None,
         }
     }
 
+    /// Advance time-driven changes of the settings by `dt` seconds: first the commands
+    /// pushed onto [`Nnpipe::command_queue`] since the last update run, then preset
+    /// morphs move on. Call it once per frame before processing, e.g. from nannou's
+    /// `update`.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, dt: f32) {
+        self.run_commands();
+        #[cfg(feature = "config")]
+        self.advance_morph(device, queue, dt);
+        #[cfg(not(feature = "config"))]
+        let _ = (device, queue, dt);
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
     pub fn process(
         &self,
         device: &wgpu::Device,
@@ -516,33 +1299,500 @@ impl Nnpipe {
         draw_renderer: &mut nannou::draw::Renderer,
         draw: &nannou::Draw,
     ) {
+        let gutter = self.gutter_layer.as_ref();
+        self.process_scene(
+            device,
+            queue,
+            texture_view,
+            gutter,
+            |encoder, scene_view| {
+                // Drawing at the render scale keeps the Draw's framing at any scale, and
+                // the Draw's origin at the center of a padded scene
+                draw_renderer.encode_render_pass(
+                    device,
+                    encoder,
+                    draw,
+                    self.render_scale,
+                    scene_view.size(),
+                    scene_view,
+                    None,
+                );
+            },
+        );
+    }
+
+    /// Like [`Nnpipe::process`], but the scene is recorded by `record_scene`.
+    ///
+    /// The closure gets the command encoder the post passes will be recorded into and
+    /// the scene texture view. Any render passes it records into the scene view (your
+    /// own pipelines, several nannou `Draw`s, ...) become the scene, and everything is
+    /// submitted together. With a [bloom gutter](Nnpipe::set_bloom_gutter) the scene
+    /// view is padded by the gutter on every side, the frame at its center, so lights
+    /// just outside the frame can be drawn too.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn process_with<F>(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_view: &wgpu::TextureView,
+        record_scene: F,
+    ) where
+        F: FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+    {
+        let gutter = self.gutter_layer.as_ref();
+        self.process_scene(device, queue, texture_view, gutter, record_scene);
+    }
+
+    // Record the scene with `record_scene`, into the gutter's padded scene if given,
+    // and run the chain on it
+    fn process_scene<F>(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture_view: &wgpu::TextureView,
+        gutter: Option<&GutterLayer>,
+        record_scene: F,
+    ) where
+        F: FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+    {
+        let ce_desc = wgpu::CommandEncoderDescriptor {
+            label: Some("Nnpipe"),
+        };
+        let mut encoder = device.create_command_encoder(&ce_desc);
+
         // First, render the scene to the scene texture
+        match gutter {
+            Some(gutter) => {
+                record_scene(&mut encoder, gutter.scene_view());
+                gutter.encode_crop_scene(&mut encoder, &self.scene_texture);
+            }
+            None => record_scene(&mut encoder, &self.scene_view),
+        }
+
+        // Now record the post-processing passes
+        self.encode_effects(
+            device,
+            &mut encoder,
+            &self.brightness_bind_group,
+            &self.composite_bind_group,
+            None,
+            None,
+            None,
+            None,
+            texture_view,
+            self.stencil_exclusion,
+            gutter,
+            Eye::Left,
+        );
+
+        queue.submit(Some(encoder.finish()));
+        self.uploader.recall();
+
+        // Make sure all commands are completed. The web can't block on the GPU, and
+        // doesn't need to: the browser runs the commands in submission order.
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    /// Run the whole chain into the internal output texture without presenting it.
+    ///
+    /// Follow with one [`Nnpipe::present`] call per output to drive several windows
+    /// from a single render.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        draw_renderer: &mut nannou::draw::Renderer,
+        draw: &nannou::Draw,
+    ) {
+        self.process(device, queue, &self.output_view, draw_renderer, draw);
+    }
+
+    /// Run the effect chain on an existing texture instead of a nannou `Draw`.
+    ///
+    /// `input_view` is used as the scene, so content rendered with your own wgpu
+    /// pipelines can go through the same effects. It must be a single-sampled float
+    /// texture view with `TEXTURE_BINDING` usage, ideally the size of the pipeline.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn process_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        input_view: &wgpu::TextureView,
+        output_view: &wgpu::TextureView,
+    ) {
+        self.process_eye(device, queue, input_view, output_view, Eye::Left);
+    }
+
+    // Run the chain on `input_view` as `eye`'s view, with that eye's temporal history
+    fn process_eye(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        input_view: &wgpu::TextureView,
+        output_view: &wgpu::TextureView,
+        eye: Eye,
+    ) {
+        // The matte is of the input itself, and everything else sees its shadowed copy
+        let matte_bind_group = self
+            .matte_layer
+            .as_ref()
+            .map(|layer| layer.scene_bind_group(device, input_view));
+        let (input_view, shadow_bind_groups) = self.drop_shadow_scene(device, input_view);
+
+        let brightness_bind_group = create_brightness_bind_group(
+            device,
+            &self.brightness_bind_group_layout,
+            input_view,
+            &self.exclusion_view,
+            &self.sampler,
+            &self.threshold_buffer,
+            &self.resolution_buffer,
+            &self.sanitize_buffer,
+        );
+        let composite_bind_group = create_composite_bind_group(
+            device,
+            &self.composite_bind_group_layout,
+            input_view,
+            &self.blur_v_view,
+            &self.sampler,
+            &self.intensity_buffer,
+            &self.intensity_curve_buffer,
+            &self.alpha_mode_buffer,
+            &self.sanitize_buffer,
+            &self.resolution_buffer,
+            &self.upsample_filter_buffer,
+            &self.bloom_color_buffer,
+            self.reflection_views(),
+        );
+        let reflection_bind_group = self
+            .reflection_layer
+            .as_ref()
+            .map(|layer| layer.scene_bind_group(device, input_view));
+
+        let debug_bind_group = self.debug_layer.as_ref().map(|debug_layer| {
+            debug_layer.bind_group(
+                device,
+                &self.sampler,
+                input_view,
+                &self.brightness_view,
+                &self.blur_v_view,
+            )
+        });
+
         let ce_desc = wgpu::CommandEncoderDescriptor {
-            label: Some("Scene renderer"),
+            label: Some("Nnpipe"),
         };
         let mut encoder = device.create_command_encoder(&ce_desc);
 
-        draw_renderer.encode_render_pass(
+        self.encode_effects(
             device,
             &mut encoder,
-            draw,
-            1.0,
-            self.scene_texture.size(),
-            &self.scene_view,
+            &brightness_bind_group,
+            &composite_bind_group,
+            reflection_bind_group.as_ref(),
+            matte_bind_group.as_ref(),
+            shadow_bind_groups.as_ref(),
+            debug_bind_group.as_ref(),
+            output_view,
+            false,
             None,
+            eye,
         );
 
         queue.submit(Some(encoder.finish()));
+        self.uploader.recall();
+
+        // Make sure all commands are completed. The web can't block on the GPU, and
+        // doesn't need to: the browser runs the commands in submission order.
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Wait);
+    }
 
-        // Now execute the post-processing passes
+    /// Draw the frame produced by [`Nnpipe::render`] into `view` through output `index`.
+    ///
+    /// `view` may have any size; its format must match the one the output was added with.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip(self, device, queue, view))
+    )]
+    pub fn present(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        view: &wgpu::TextureView,
+    ) -> Result<()> {
+        let output = self
+            .outputs
+            .get(index)
+            .ok_or(NnpipeError::UnknownOutput(index))?;
+        output.present(device, queue, &self.uploader, view);
+        Ok(())
+    }
 
-        // 1. Brightness extraction pass
+    /// Read the frame produced by [`Nnpipe::render`] back from the GPU, as rows of
+    /// RGBA pixels from the top left.
+    ///
+    /// Works on the web, where the future resolves once the browser has mapped the
+    /// frame; natively it waits for the GPU before returning, like
+    /// [`Nnpipe::process`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(width = self.width, height = self.height)))]
+    pub async fn read_output(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<[f32; 4]>> {
+        read_texture(device, queue, &self.output_texture).await
+    }
+
+    // Record a copy of the output texture into `buffer`, of `readback_size` bytes
+    pub(crate) fn copy_output(&self, encoder: &mut wgpu::CommandEncoder, buffer: &wgpu::Buffer) {
+        copy_readback(encoder, &self.output_texture, buffer);
+    }
+
+    // Record the post-processing passes, ending in `texture_view`. The bind groups
+    // determine which texture is treated as the scene; the reflection and debug view
+    // ones default to the scene texture's. `exclusion` applies the
+    // stencil exclusion, which only makes sense for the internal scene texture, and
+    // `gutter` extracts and blurs the bloom from the padded scene recorded into it.
+    // Temporal effects read and write the history of `eye`, the left eye's being the
+    // only one outside stereo.
+    //
+    // The chain runs at the pipeline's resolution. A target of any other size is
+    // drawn from the output texture by the scaler.
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    fn encode_effects(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        brightness_bind_group: &wgpu::BindGroup,
+        composite_bind_group: &wgpu::BindGroup,
+        reflection_bind_group: Option<&wgpu::BindGroup>,
+        matte_bind_group: Option<&wgpu::BindGroup>,
+        shadow_bind_groups: Option<&[wgpu::BindGroup; 2]>,
+        debug_bind_group: Option<&wgpu::BindGroup>,
+        texture_view: &wgpu::TextureView,
+        exclusion: bool,
+        gutter: Option<&GutterLayer>,
+        eye: Eye,
+    ) {
+        if self.bypass {
+            self.encode_bypass(device, encoder, brightness_bind_group, texture_view);
+            return;
+        }
+
+        // Upload the uniforms changed since the last frame, ahead of the passes. The
+        // globals and intensity are refreshed every frame, and only upload when the
+        // size, time, seed or audible intensity moved
+        let size = self.scene_texture.size();
+        let globals = [
+            (size[0] as f32).to_bits(),
+            (size[1] as f32).to_bits(),
+            self.time().to_bits(),
+            self.seed,
+        ];
+        self.globals_buffer.write(0, bytemuck::cast_slice(&globals));
+        // A muted or soloed-out bloom adds nothing to the composite
+        let intensity = match self.group_audible(self.bloom_muted, self.bloom_soloed) {
+            true => self.bloom_intensity,
+            false => 0.0,
+        };
+        self.intensity_buffer
+            .write(0, bytemuck::cast_slice(&[intensity]));
+        self.write_triggers();
+        if let Some(debug_layer) = &self.debug_layer {
+            debug_layer.set_target_size(texture_view.size());
+        }
+        #[cfg(feature = "bloom")]
+        let flare_buffer = self
+            .flare_layer
+            .as_ref()
+            .map(|layer| layer.uniform_buffer());
+        #[cfg(not(feature = "bloom"))]
+        let flare_buffer = None;
+        #[cfg(feature = "stylize")]
+        let sparkle_buffer = self
+            .sparkle_layer
+            .as_ref()
+            .map(|layer| layer.uniform_buffer());
+        #[cfg(feature = "stylize")]
+        let distance_glow_buffer = self
+            .distance_glow_layer
+            .as_ref()
+            .map(|layer| layer.uniform_buffer());
+        #[cfg(feature = "stylize")]
+        let drop_shadow_buffers = self
+            .drop_shadow_layer
+            .iter()
+            .flat_map(|layer| layer.uniform_buffers());
+        #[cfg(not(feature = "stylize"))]
+        let (sparkle_buffer, distance_glow_buffer) = (None, None);
+        #[cfg(not(feature = "stylize"))]
+        let drop_shadow_buffers = None;
+        #[cfg(feature = "temporal")]
+        let accumulation_buffer = self.active_accumulation().map(|layer| {
+            layer.prepare(eye.index());
+            layer.uniform_buffer()
+        });
+        #[cfg(not(feature = "temporal"))]
+        let accumulation_buffer = None;
+        if let Some(layer) = &self.guides_layer {
+            layer.set_target_size(texture_view.size());
+        }
+        if let Some(layer) = &self.burn_in_layer {
+            layer.write_text(self, texture_view.size());
+        }
+        if let Some(layer) = &self.hud_layer {
+            layer.write_stats(texture_view.size());
+        }
+        self.uploader.upload(
+            device,
+            encoder,
+            [
+                &self.threshold_buffer,
+                &self.blur_h_buffer,
+                &self.blur_v_buffer,
+                &self.intensity_buffer,
+                &self.adaptive_scaling_buffer,
+                &self.max_radius_buffer,
+                &self.intensity_curve_buffer,
+                &self.alpha_mode_buffer,
+                &self.sanitize_buffer,
+                &self.upsample_filter_buffer,
+                &self.bloom_color_buffer,
+                &self.globals_buffer,
+            ]
+            .into_iter()
+            .chain(self.passes.iter().map(|pass| pass.params_buffer()))
+            .chain(
+                self.graph_layer
+                    .iter()
+                    .flat_map(|layer| layer.params_buffers()),
+            )
+            .chain(
+                self.debug_layer
+                    .iter()
+                    .map(|debug_layer| debug_layer.uniform_buffer()),
+            )
+            .chain(
+                self.reflection_layer
+                    .iter()
+                    .flat_map(|layer| layer.uniform_buffers()),
+            )
+            .chain(flare_buffer)
+            .chain(sparkle_buffer)
+            .chain(distance_glow_buffer)
+            .chain(drop_shadow_buffers)
+            .chain(self.matte_layer.iter().map(|layer| layer.uniform_buffer()))
+            .chain(accumulation_buffer)
+            .chain(self.scopes_layer.iter().map(|layer| layer.uniform_buffer()))
+            .chain(
+                self.magnifier_layer
+                    .iter()
+                    .map(|layer| layer.uniform_buffer()),
+            )
+            .chain(self.zebra_layer.iter().map(|layer| layer.uniform_buffer()))
+            .chain(self.guides_layer.iter().map(|layer| layer.uniform_buffer()))
+            .chain(
+                self.burn_in_layer
+                    .iter()
+                    .map(|layer| layer.uniform_buffer()),
+            )
+            .chain(self.hud_layer.iter().map(|layer| layer.uniform_buffer())),
+        );
+
+        let exclusion_mask = match (&self.exclusion_mask_pipeline, &self.depth_view) {
+            (Some(pipeline), Some(depth_view)) if exclusion => Some((pipeline, depth_view)),
+            _ => None,
+        };
+
+        // The stats HUD times the stages between here and the overlays
+        let hud = self.hud_layer.as_ref();
+        let end_stage = |encoder: &mut wgpu::CommandEncoder, label: &str| {
+            if let Some(layer) = hud {
+                layer.end_stage(encoder, label);
+            }
+        };
+        if let Some(layer) = hud {
+            layer.begin_stages(encoder);
+        }
+
+        let scaled = texture_view.size() != [self.width, self.height];
+        let magnifier = self
+            .magnifier_layer
+            .as_ref()
+            .filter(|_| self.debug_layer.is_none());
+        let zebra = self
+            .zebra_layer
+            .as_ref()
+            .filter(|_| self.debug_layer.is_none());
+        let scopes = self
+            .scopes_layer
+            .as_ref()
+            .filter(|_| self.debug_layer.is_none());
+        // The frame at the pipeline's size, after the accumulation, the scopes, the
+        // magnifier and the zebra stripes, each of which copies the frame on to the next
+        let frame_target = if scaled {
+            &self.output_view
+        } else {
+            texture_view
+        };
+        let magnifier_target = zebra.map_or(frame_target, |zebra| zebra.source_view());
+        let scopes_target = magnifier.map_or(magnifier_target, |magnifier| magnifier.source_view());
+        let accumulation_target = scopes.map_or(scopes_target, |scopes| scopes.source_view());
+        let chain_target = match &self.debug_layer {
+            Some(debug_layer) => debug_layer.composite_view(),
+            None => self
+                .accumulation_source_view()
+                .unwrap_or(accumulation_target),
+        };
+
+        // 0. Exclusion mask pass, left empty unless the exclusion is active
         {
-            let ce_desc = wgpu::CommandEncoderDescriptor {
-                label: Some("Brightness extraction"),
-            };
-            let mut encoder = device.create_command_encoder(&ce_desc);
+            let depth_stencil_attachment =
+                exclusion_mask.map(|(_, depth_view)| wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: None,
+                    stencil_ops: None,
+                });
+
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Exclusion mask pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.exclusion_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment,
+            });
 
+            if let Some((pipeline, _)) = exclusion_mask {
+                pass.set_pipeline(pipeline);
+                pass.set_stencil_reference(0);
+                pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+            }
+        }
+
+        // 0b. Matte of the scene, for the passes and the host, ahead of its shadow
+        if let Some(layer) = &self.matte_layer {
+            match self.layer_audible(Layer::Matte) {
+                true => layer.encode(encoder, matte_bind_group),
+                false => layer.clear(encoder),
+            }
+        }
+
+        // 0c. Drop shadow under the scene, which the bloom sees too
+        self.encode_drop_shadow(encoder, shadow_bind_groups);
+
+        // 1. Brightness extraction pass
+        if let Some(gutter) = gutter {
+            gutter.encode_brightness(encoder, &self.brightness_pipeline, &self.brightness_texture);
+        } else {
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Brightness pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -557,20 +1807,36 @@ impl Nnpipe {
             });
 
             pass.set_pipeline(&self.brightness_pipeline);
-            pass.set_bind_group(0, &self.brightness_bind_group, &[]);
+            pass.set_bind_group(0, brightness_bind_group, &[]);
             pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
 
-            drop(pass);
-            queue.submit(Some(encoder.finish()));
+        // 1b. Reflections, traced at the bloom's resolution
+        if let Some(layer) = &self.reflection_layer {
+            match self.layer_audible(Layer::Reflections) {
+                true => layer.encode(encoder, reflection_bind_group),
+                false => layer.clear(encoder),
+            }
         }
 
-        // 2. Horizontal blur pass
-        {
-            let ce_desc = wgpu::CommandEncoderDescriptor {
-                label: Some("Horizontal blur"),
-            };
-            let mut encoder = device.create_command_encoder(&ce_desc);
+        // 1c. Temporal filtering of the brightness, which the gutter's blur sees too
+        if self.encode_bloom_stabilization(encoder, eye) {
+            if let Some(gutter) = gutter {
+                gutter.encode_restore_brightness(encoder, &self.brightness_texture);
+            }
+        }
+        end_stage(encoder, "Brightness");
+
+        // 2-3. Convolution with the aperture kernel, in place of the blur passes
+        let convolved = self.encode_fft_bloom(encoder);
 
+        // 2-3. Blur passes at the gutter's padded size, cropped into the bloom texture
+        if let (false, Some(gutter)) = (convolved, gutter) {
+            gutter.encode_blur(encoder, &self.blur_pipeline, &self.blur_v_texture);
+        }
+
+        // 2. Horizontal blur pass
+        if !convolved && gutter.is_none() {
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Horizontal blur pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -587,18 +1853,10 @@ impl Nnpipe {
             pass.set_pipeline(&self.blur_pipeline);
             pass.set_bind_group(0, &self.blur_h_bind_group, &[]);
             pass.draw(0..3, 0..1); // Draw a fullscreen triangle
-
-            drop(pass);
-            queue.submit(Some(encoder.finish()));
         }
 
         // 3. Vertical blur pass
-        {
-            let ce_desc = wgpu::CommandEncoderDescriptor {
-                label: Some("Vertical blur"),
-            };
-            let mut encoder = device.create_command_encoder(&ce_desc);
-
+        if !convolved && gutter.is_none() {
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Vertical blur pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -615,22 +1873,33 @@ impl Nnpipe {
             pass.set_pipeline(&self.blur_pipeline);
             pass.set_bind_group(0, &self.blur_v_bind_group, &[]);
             pass.draw(0..3, 0..1); // Draw a fullscreen triangle
-
-            drop(pass);
-            queue.submit(Some(encoder.finish()));
         }
+        end_stage(encoder, "Blur");
 
-        // 4. Final composite pass to the output texture
-        {
-            let ce_desc = wgpu::CommandEncoderDescriptor {
-                label: Some("Final composite"),
-            };
-            let mut encoder = device.create_command_encoder(&ce_desc);
+        // The composite and effect passes render ahead of the pass graph, if any
+        let effects_target = match &self.graph_layer {
+            Some(layer) => layer.source_view(),
+            None => chain_target,
+        };
+
+        // The composite goes straight to the output unless effect passes follow it
+        let enabled_passes: Vec<&Pass> = self
+            .passes
+            .iter()
+            .filter(|p| p.enabled && p.mix > 0.0 && self.group_audible(p.muted, p.soloed))
+            .collect();
+        let composite_target = if enabled_passes.is_empty() {
+            effects_target
+        } else {
+            &self.composite_view
+        };
 
+        // 4. Final composite pass
+        {
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Composite pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: texture_view, // Render directly to the output
+                    view: composite_target,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
@@ -640,99 +1909,3259 @@ impl Nnpipe {
                 depth_stencil_attachment: None,
             });
 
-            pass.set_pipeline(&self.composite_pipeline);
-            pass.set_bind_group(0, &self.composite_bind_group, &[]);
+            if self.premultiplied_alpha {
+                pass.set_pipeline(&self.composite_premultiplied_pipeline);
+            } else {
+                pass.set_pipeline(&self.composite_pipeline);
+            }
+            pass.set_bind_group(0, composite_bind_group, &[]);
             pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+
+        // 4b. Lens flare, sparkles and the distance glow added over the composite
+        self.encode_lens_flare(encoder, composite_target);
+        self.encode_sparkles(encoder, composite_target);
+        self.encode_distance_glow(encoder, composite_target);
+        end_stage(encoder, "Composite");
 
-            drop(pass);
-            queue.submit(Some(encoder.finish()));
+        // 5. Effect passes, ping-ponging between the composite and effect textures.
+        // The last enabled pass renders directly to the output, or is copied to it if
+        // it's a compute pass. Passes mixed below 1 get their input blended back over.
+        if !enabled_passes.is_empty() {
+            let ping_pong = [&self.composite_view, &self.effect_view];
+            let passthrough = self.passthrough.as_ref().expect("created with the passes");
+            let mix_pipeline = &self.pass_resources.mix_pipeline;
+            for (i, pass) in enabled_passes.iter().enumerate() {
+                let input = i % 2;
+                let last = i + 1 == enabled_passes.len();
+                let dry = pass.dry();
+                if pass.is_compute() {
+                    pass.dispatch(encoder, input, [self.width, self.height]);
+                    if dry > 0.0 {
+                        let output = ping_pong[1 - input];
+                        passthrough.encode_blend(encoder, mix_pipeline, input, output, dry);
+                    }
+                    if last {
+                        passthrough.encode(encoder, 1 - input, effects_target);
+                    }
+                } else {
+                    let target = if last {
+                        effects_target
+                    } else {
+                        ping_pong[1 - input]
+                    };
+                    pass.encode(encoder, input, target);
+                    if dry > 0.0 {
+                        passthrough.encode_blend(encoder, mix_pipeline, input, target, dry);
+                    }
+                }
+                end_stage(encoder, &pass.label);
+            }
         }
 
-        // Make sure all commands are completed
-        device.poll(wgpu::Maintain::Wait);
-    }
+        // 5b. Pass graph, from the effect passes' output to the chain's
+        if let Some(layer) = &self.graph_layer {
+            layer.encode(encoder, chain_target);
+            end_stage(encoder, "Graph");
+        }
 
-    /******************* Helper methods for updating parameters ****************** */
+        // 6. Draw the untouched scene back over the excluded regions
+        if exclusion_mask.is_some() {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Exclusion restore pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: chain_target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
 
-    pub fn set_brightness_threshold(&mut self, queue: &wgpu::Queue, threshold: f32) {
-        self.brightness_threshold = threshold;
-        queue.write_buffer(
-            &self.threshold_buffer,
-            0,
-            bytemuck::cast_slice(&[threshold]),
-        );
-    }
+            pass.set_pipeline(&self.exclusion_restore_pipeline);
+            pass.set_bind_group(0, &self.exclusion_bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
 
-    pub fn set_bloom_intensity(&mut self, queue: &wgpu::Queue, intensity: f32) {
-        self.bloom_intensity = intensity;
-        queue.write_buffer(
-            &self.intensity_buffer,
-            0,
-            bytemuck::cast_slice(&[intensity]),
-        );
-    }
+        // 6b. Fold the chain's output into the accumulation, then count what it shows
+        // into the scopes, then copy it on with the magnifier's inset, then with the
+        // zebra stripes, which mark the inset too
+        self.encode_accumulation(encoder, accumulation_target, eye);
+        if let Some(scopes) = scopes {
+            scopes.encode(encoder, scopes_target);
+        }
+        if let Some(magnifier) = magnifier {
+            magnifier.encode(encoder, magnifier_target);
+        }
+        if let Some(zebra) = zebra {
+            zebra.encode(encoder, frame_target);
+        }
 
-    pub fn set_adaptive_blur_scaling(&mut self, queue: &wgpu::Queue, scaling: f32) {
+        // 7. Scale to a target whose size differs from the pipeline's, or draw the
+        // debug view in place of the frame
+        if let Some(debug_layer) = &self.debug_layer {
+            debug_layer.encode(encoder, debug_bind_group, texture_view);
+        } else if scaled {
+            self.scaler
+                .encode(device, &self.uploader, encoder, texture_view);
+        }
+        end_stage(encoder, "Finish");
+        if let Some(layer) = hud {
+            layer.finish_stages(encoder);
+        }
+
+        // 8. Guides, burn-in and stats HUD over the final frame
+        if let Some(layer) = &self.guides_layer {
+            layer.encode(encoder, texture_view);
+        }
+        if let Some(layer) = &self.burn_in_layer {
+            layer.encode(encoder, texture_view);
+        }
+        if let Some(layer) = &self.hud_layer {
+            layer.encode(encoder, texture_view);
+        }
+    }
+
+    // Record the scene's copy to `texture_view` while bypassed, through the scaler
+    // if it's of another size. Uniforms set in the meantime stay dirty until the
+    // chain runs again.
+    fn encode_bypass(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        brightness_bind_group: &wgpu::BindGroup,
+        texture_view: &wgpu::TextureView,
+    ) {
+        let scaled = texture_view.size() != [self.width, self.height];
+        let target = if scaled {
+            &self.output_view
+        } else {
+            texture_view
+        };
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Bypass pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            pass.set_pipeline(&self.bypass_pipeline);
+            pass.set_bind_group(0, brightness_bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+
+        if scaled {
+            self.scaler
+                .encode(device, &self.uploader, encoder, texture_view);
+        }
+    }
+
+    // The frame param regions render into, at the pipeline's size
+    pub(crate) fn take_region_view(&mut self, device: &wgpu::Device) -> wgpu::TextureView {
+        self.region_view.take().unwrap_or_else(|| {
+            wgpu::TextureBuilder::new()
+                .size([self.width, self.height])
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                )
+                .format(wgpu::TextureFormat::Rgba16Float)
+                .build(device)
+                .view()
+                .build()
+        })
+    }
+
+    // Draw `pixels` of the region's frame in `region_view` over the same pixels of
+    // `output_view`, or of the output texture if `output_view` is scaled or None
+    pub(crate) fn draw_region(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        region_view: &wgpu::TextureView,
+        output_view: Option<&wgpu::TextureView>,
+        [x, y, width, height]: [u32; 4],
+    ) {
+        let target = match output_view {
+            Some(view) if view.size() == [self.width, self.height] => view,
+            _ => &self.output_view,
+        };
+        // The bypass pass copies the scene of a brightness bind group
+        let bind_group = create_brightness_bind_group(
+            device,
+            &self.brightness_bind_group_layout,
+            region_view,
+            &self.exclusion_view,
+            &self.sampler,
+            &self.threshold_buffer,
+            &self.resolution_buffer,
+            &self.sanitize_buffer,
+        );
+
+        let ce_desc = wgpu::CommandEncoderDescriptor {
+            label: Some("Param Region"),
+        };
+        let mut encoder = device.create_command_encoder(&ce_desc);
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Param region pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_scissor_rect(x, y, width, height);
+            pass.set_pipeline(&self.bypass_pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // Scale the output texture into `output_view` if the regions were drawn there,
+    // and keep the region frame for the next call
+    pub(crate) fn finish_regions(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        region_view: wgpu::TextureView,
+        output_view: Option<&wgpu::TextureView>,
+    ) {
+        if let Some(view) = output_view.filter(|view| view.size() != [self.width, self.height]) {
+            self.scaler.present(device, queue, &self.uploader, view);
+        }
+        self.region_view = Some(region_view);
+    }
+
+    /******************* Bypass ****************** */
+
+    pub fn bypass(&self) -> bool {
+        self.bypass
+    }
+
+    /// Skip the whole chain and copy the scene straight to the output, for quick
+    /// before/after checks or to save the GPU the effects.
+    ///
+    /// Nothing but the copy (and the scaling to targets of another size) is encoded
+    /// while bypassed; the debug view is skipped too. Settings changed in the meantime
+    /// apply once the bypass is turned off.
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.bypass = bypass;
+    }
+
+    /******************* Chain graph ****************** */
+
+    /// The chain as it would run for the next frame: a node per pass, from the scene
+    /// to the outputs, and the textures between them. See [`ChainGraph`].
+    ///
+    /// Effect passes that are disabled, mixed out, muted or soloed out are left out,
+    /// as are the layers left out of the debug view while it's on.
+    pub fn chain_graph(&self) -> ChainGraph {
+        let mut graph = ChainGraph::default();
+        let info = |view: &wgpu::TextureView| (view.format(), Some(view.size()));
+        let output = info(&self.output_view);
+        let (format, size) = info(&self.scene_view);
+        graph.add("scene", "Scene", "scene", format, size, &[]);
+
+        if self.bypass {
+            let (format, size) = output;
+            graph.add("bypass", "Bypass", "output", format, size, &["scene"]);
+            self.add_output_nodes(&mut graph, "bypass");
+            return graph;
+        }
+
+        if let Some(layer) = &self.matte_layer {
+            let (format, size) = info(layer.view());
+            graph.add("matte", "Matte", "matte", format, size, &["scene"]);
+        }
+        #[cfg(feature = "stylize")]
+        let scene = match &self.drop_shadow_layer {
+            Some(layer) => {
+                let (format, size) = info(layer.scene_view());
+                let id = "drop_shadow";
+                graph.add(id, "Drop shadow", id, format, size, &["scene"]);
+                id
+            }
+            None => "scene",
+        };
+        #[cfg(not(feature = "stylize"))]
+        let scene = "scene";
+
+        // Bloom
+        let (format, size) = info(&self.brightness_view);
+        graph.add(
+            "brightness",
+            "Brightness",
+            "brightness",
+            format,
+            size,
+            &[scene],
+        );
+        #[cfg(feature = "temporal")]
+        let brightness = match &self.stabilize_layer {
+            Some(_) => {
+                let id = "stabilization";
+                graph.add(
+                    id,
+                    "Stabilization",
+                    "brightness",
+                    format,
+                    size,
+                    &["brightness"],
+                );
+                id
+            }
+            None => "brightness",
+        };
+        #[cfg(not(feature = "temporal"))]
+        let brightness = "brightness";
+        #[cfg(feature = "bloom")]
+        let convolved = self.fft_bloom.is_some();
+        #[cfg(not(feature = "bloom"))]
+        let convolved = false;
+        let (format, size) = info(&self.blur_v_view);
+        let bloom = if convolved {
+            let id = "convolution";
+            graph.add(id, "Convolution", "blur_v", format, size, &[brightness]);
+            id
+        } else {
+            let (h_format, h_size) = info(&self.blur_h_view);
+            graph.add(
+                "blur_h",
+                "Horizontal blur",
+                "blur_h",
+                h_format,
+                h_size,
+                &[brightness],
+            );
+            graph.add(
+                "blur_v",
+                "Vertical blur",
+                "blur_v",
+                format,
+                size,
+                &["blur_h"],
+            );
+            "blur_v"
+        };
+        let mut composite_inputs = vec![scene, bloom];
+        if let Some(layer) = &self.reflection_layer {
+            let (format, size) = info(layer.views()[0]);
+            graph.add(
+                "reflections",
+                "Reflections",
+                "reflections",
+                format,
+                size,
+                &[scene],
+            );
+            composite_inputs.push("reflections");
+        }
+
+        // The layers copying the chain's output on to the output texture, after the
+        // effect passes, each reading a texture of its own
+        let mut tail: Vec<(&str, &str, &wgpu::TextureView)> = Vec::new();
+        if let Some(layer) = &self.debug_layer {
+            tail.push(("debug", "Debug view", layer.composite_view()));
+        } else {
+            #[cfg(feature = "temporal")]
+            if let Some(layer) = &self.accumulation_layer {
+                tail.push(("accumulation", "Accumulation", layer.source_view()));
+            }
+            if let Some(layer) = &self.scopes_layer {
+                tail.push(("scopes", "Scopes", layer.source_view()));
+            }
+            if let Some(layer) = &self.magnifier_layer {
+                tail.push(("magnifier", "Magnifier", layer.source_view()));
+            }
+            if let Some(layer) = &self.zebra_layer {
+                tail.push(("zebra", "Zebra", layer.source_view()));
+            }
+        }
+        let target = |index: usize| match tail.get(index) {
+            Some((id, _, view)) => (format!("{id}_source"), info(view)),
+            None => ("output".to_string(), output),
+        };
+        // The composite and effect passes write the pass graph's input, if any
+        let effects_target = || match &self.graph_layer {
+            Some(layer) => ("graph_pool.0".to_string(), info(layer.source_view())),
+            None => target(0),
+        };
+
+        // Composite and effect passes, ping-ponging between two textures
+        let passes: Vec<(usize, &Pass)> = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.enabled && p.mix > 0.0 && self.group_audible(p.muted, p.soloed))
+            .collect();
+        let ping_pong = [
+            ("composite", info(&self.composite_view)),
+            ("effect", info(&self.effect_view)),
+        ];
+        let (texture, (format, size)) = match passes.is_empty() {
+            true => effects_target(),
+            false => (ping_pong[0].0.to_string(), ping_pong[0].1),
+        };
+        graph.add(
+            "composite",
+            "Composite",
+            &texture,
+            format,
+            size,
+            &composite_inputs,
+        );
+        let mut last = "composite".to_string();
+        #[cfg(feature = "bloom")]
+        let flare = self.flare_layer.is_some();
+        #[cfg(not(feature = "bloom"))]
+        let flare = false;
+        #[cfg(feature = "stylize")]
+        let (sparkles, glow) = (
+            self.sparkle_layer.is_some(),
+            self.distance_glow_layer.is_some(),
+        );
+        #[cfg(not(feature = "stylize"))]
+        let (sparkles, glow) = (false, false);
+        // Added onto the composite, from the textures they read
+        let additions = [
+            ("lens_flare", "Lens flare", flare, brightness),
+            ("sparkles", "Sparkles", sparkles, brightness),
+            (
+                "distance_glow",
+                "Distance glow",
+                glow && self.matte_layer.is_some(),
+                "matte",
+            ),
+        ];
+        for (id, label, _, input) in additions.iter().filter(|(_, _, present, _)| *present) {
+            graph.add(*id, *label, &texture, format, size, &[&last, input]);
+            last = id.to_string();
+        }
+        for (i, (index, pass)) in passes.iter().enumerate() {
+            let (texture, (format, size)) = match i + 1 == passes.len() {
+                true => effects_target(),
+                false => {
+                    let (texture, info) = ping_pong[1 - i % 2];
+                    (texture.to_string(), info)
+                }
+            };
+            let id = format!("passes.{index}");
+            graph.add(&id, &pass.label, texture, format, size, &[&last]);
+            last = id;
+        }
+        if let Some(layer) = &self.graph_layer {
+            // Scheduled so the pass writing the graph's output comes last
+            let input = last.clone();
+            for (pass, output) in layer.scheduled() {
+                let (texture, (format, size)) = match output {
+                    Some(n) => (format!("graph_pool.{n}"), info(layer.texture_view(n))),
+                    None => target(0),
+                };
+                let inputs: Vec<String> = pass
+                    .inputs
+                    .iter()
+                    .map(|name| match name.as_str() {
+                        GRAPH_INPUT => input.clone(),
+                        _ => format!("graph.{name}"),
+                    })
+                    .collect();
+                let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+                let id = format!("graph.{}", pass.output);
+                graph.add(&id, &pass.label, texture, format, size, &inputs);
+                last = id;
+            }
+        }
+
+        for (i, (id, label, _)) in tail.iter().enumerate() {
+            let (texture, (format, size)) = target(i + 1);
+            graph.add(*id, *label, texture, format, size, &[&last]);
+            last = id.to_string();
+        }
+
+        // Overlays drawn over the final frame
+        let (format, size) = output;
+        let overlays = [
+            ("guides", "Guides", self.guides_layer.is_some()),
+            ("burn_in", "Burn-in", self.burn_in_layer.is_some()),
+            ("hud", "Stats HUD", self.hud_layer.is_some()),
+        ];
+        for (id, label, _) in overlays.iter().filter(|(.., present)| *present) {
+            graph.add(*id, *label, "output", format, size, &[&last]);
+            last = id.to_string();
+        }
+        self.add_output_nodes(&mut graph, &last);
+        graph
+    }
+
+    // Add a node per output, drawing the output texture as left by `last`
+    fn add_output_nodes(&self, graph: &mut ChainGraph, last: &str) {
+        for (index, output) in self.outputs.iter().enumerate() {
+            let id = format!("outputs.{index}");
+            let label = format!("Output {index}");
+            graph.add(&id, label, &id, output.format(), None, &[last]);
+        }
+    }
+
+    /******************* Custom effect passes ****************** */
+
+    /// Append a custom WGSL effect pass to the end of the chain and return its index.
+    ///
+    /// See `src/pass.rs` for the bindings available to the shader. If the shader fails
+    /// to compile, the pass runs as a passthrough and the error is queued for
+    /// [`Nnpipe::take_shader_errors`].
+    pub fn add_custom_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+    ) -> usize {
+        self.add_custom_pass_with_data(device, label, source, params, &[])
+    }
+
+    /// Add a custom pass that also reads an array of `data` at binding 5, for values
+    /// that don't fit the params uniform (kernel weights, lookup tables, ...).
+    pub fn add_custom_pass_with_data(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+        data: &[f32],
+    ) -> usize {
+        self.push_pass(device, label, source, params, data, false, None)
+    }
+
+    /// Add a custom pass that binds buffers and textures of the sketch's own in
+    /// `@group(1)`, each at its position in `inputs` (see `src/pass.rs`).
+    ///
+    /// The pass binds them every frame until they're replaced with
+    /// [`Nnpipe::set_pass_inputs`]; write to them with the queue as usual. The pass
+    /// keeps them alive, so they can be dropped on the sketch's side. A pipeline
+    /// recovered on a new device drops them along with the device, and the pass falls
+    /// back to a passthrough until it's added again.
+    pub fn add_custom_pass_with_inputs(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+        inputs: &[PassInput],
+    ) -> usize {
+        let inputs = PassInputs::new(device, &self.pass_resources, false, inputs);
+        self.push_pass(device, label, source, params, &[], false, inputs)
+    }
+
+    /// Append a custom pass running a compute shader and return its index.
+    ///
+    /// The shader sees the bindings of other passes, writes the frame to `dst_tex`,
+    /// and is dispatched in 8x8 workgroups over it (see `src/pass.rs`). Compute and
+    /// fragment passes mix freely in the chain, so an effect can gather over the
+    /// whole frame (a histogram, a pixel sort) between two fragment passes. If the
+    /// shader fails to compile, the pass runs as a passthrough and the error is
+    /// queued for [`Nnpipe::take_shader_errors`].
+    pub fn add_compute_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+    ) -> usize {
+        self.push_pass(device, label, source, params, &[], true, None)
+    }
+
+    /// Add a compute pass that binds buffers and textures of the sketch's own in
+    /// `@group(1)`, like [`Nnpipe::add_custom_pass_with_inputs`].
+    pub fn add_compute_pass_with_inputs(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+        inputs: &[PassInput],
+    ) -> usize {
+        let inputs = PassInputs::new(device, &self.pass_resources, true, inputs);
+        self.push_pass(device, label, source, params, &[], true, inputs)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+        data: &[f32],
+        compute: bool,
+        inputs: Option<PassInputs>,
+    ) -> usize {
+        let (pass, error) = Pass::new(
+            device,
+            &self.pass_resources,
+            &self.pass_bindings(),
+            label,
+            source,
+            params,
+            data,
+            compute,
+            inputs,
+        );
+        self.shader_errors.extend(error);
+        self.passes.push(pass);
+
+        // Compute passes can't write the chain's target, so one ending the chain is
+        // followed by a copy, and mixed passes blend their input back with a passthrough
+        if self.passthrough.is_none() {
+            let (passthrough, _) = Pass::new(
+                device,
+                &self.pass_resources,
+                &self.pass_bindings(),
+                "Chain Passthrough",
+                PASSTHROUGH_SOURCE,
+                &[],
+                &[],
+                false,
+                None,
+            );
+            self.passthrough = Some(passthrough);
+        }
+        self.passes.len() - 1
+    }
+
+    /// Append a custom pass whose params are reflected from its `Params` struct (see
+    /// [`ParamDescriptor::reflect`]) and return its index.
+    ///
+    /// The params start at their defaults and show up in [`Nnpipe::list_params`] and
+    /// in configs like hand-declared ones. If the struct can't be reflected, the pass
+    /// runs as a passthrough without params and the error is queued for
+    /// [`Nnpipe::take_shader_errors`].
+    pub fn add_reflected_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+    ) -> usize {
+        self.add_reflected(device, label, source, false)
+    }
+
+    /// Append a compute pass whose params are reflected from its `Params` struct, like
+    /// [`Nnpipe::add_reflected_pass`].
+    pub fn add_reflected_compute_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+    ) -> usize {
+        self.add_reflected(device, label, source, true)
+    }
+
+    fn add_reflected(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        compute: bool,
+    ) -> usize {
+        let (source, params) = match ParamDescriptor::reflect(label, source) {
+            Ok(descriptors) => (source, ParamDescriptor::params(&descriptors)),
+            Err(error) => {
+                self.queue_shader_error(error);
+                let passthrough = if compute {
+                    COMPUTE_PASSTHROUGH_SOURCE
+                } else {
+                    PASSTHROUGH_SOURCE
+                };
+                (passthrough, Vec::new())
+            }
+        };
+        let params: Vec<(&str, f32)> = params
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+            .collect();
+        self.push_pass(device, label, source, &params, &[], compute, None)
+    }
+
+    /// Hot-reload the shader of a custom pass. On a compile error the previous shader
+    /// keeps running.
+    pub fn reload_custom_pass(
+        &mut self,
+        device: &wgpu::Device,
+        index: usize,
+        source: &str,
+    ) -> Result<()> {
+        self.passes[index].reload(device, &self.pass_resources, source)?;
+        Ok(())
+    }
+
+    /// Rebuild a custom pass with the values of compile-time `constants`, replacing
+    /// any set before. Each names a typed `const` declaration in the pass's WGSL, e.g.
+    /// `const SAMPLES: u32 = 16u;`, whose value is substituted before compiling (see
+    /// `src/specialize.rs`).
+    ///
+    /// Use them for loop counts and feature switches, so each quality level runs a
+    /// shader without the branches it doesn't take. Every set of values compiles
+    /// once; switching back to one reuses its cached pipeline. On an error the pass
+    /// keeps its previous shader and constants.
+    pub fn set_pass_constants(
+        &mut self,
+        device: &wgpu::Device,
+        index: usize,
+        constants: &[(&str, f32)],
+    ) -> Result<()> {
+        self.passes[index].set_constants(device, &self.pass_resources, constants)?;
+        Ok(())
+    }
+
+    /// Replace the data array of a custom pass. The same length is written in place;
+    /// any other length moves the pass to a new buffer.
+    pub fn set_pass_data(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        data: &[f32],
+    ) {
+        if self.passes[index].set_data(device, queue, data) {
+            self.rebind_pass(device, index);
+        }
+    }
+
+    /// Give a custom pass a lookup texture at binding 6, replacing any previous one.
+    ///
+    /// Fails with [`NnpipeError::PixelCount`] if the lookup is empty or its data
+    /// doesn't hold `width` x `height` texels.
+    pub fn set_pass_lookup(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        lookup: LookupTexture,
+    ) -> Result<()> {
+        lookup.check()?;
+        self.replace_pass_lookup(device, queue, index, lookup);
+        Ok(())
+    }
+
+    // Like `set_pass_lookup`, for lookups known to be valid
+    pub(crate) fn replace_pass_lookup(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        lookup: LookupTexture,
+    ) {
+        if self.passes[index].set_lookup(device, queue, lookup) {
+            self.rebind_pass(device, index);
+        }
+    }
+
+    /// Give a custom pass a sampler of its own at binding 1, e.g. repeating for
+    /// tiling or kaleidoscopes, or nearest for pixelation. The default
+    /// [`PassSampler`] goes back to the pipeline's shared clamped, linear sampler.
+    pub fn set_pass_sampler(&mut self, device: &wgpu::Device, index: usize, sampler: PassSampler) {
+        if self.passes[index].set_sampler(device, sampler) {
+            self.rebind_pass(device, index);
+        }
+    }
+
+    /// Replace the buffers and textures a custom pass binds in `@group(1)`, see
+    /// [`Nnpipe::add_custom_pass_with_inputs`]. Inputs of the same kinds as before
+    /// are swapped without recompiling; otherwise the shader is rebuilt for the new
+    /// layout, and on an error the pass keeps its previous inputs.
+    pub fn set_pass_inputs(
+        &mut self,
+        device: &wgpu::Device,
+        index: usize,
+        inputs: &[PassInput],
+    ) -> Result<()> {
+        let compute = self.passes[index].is_compute();
+        let inputs = PassInputs::new(device, &self.pass_resources, compute, inputs);
+        self.passes[index].set_inputs(device, &self.pass_resources, inputs)?;
+        Ok(())
+    }
+
+    // Recreate a pass's bind groups after one of its resources was replaced
+    fn rebind_pass(&mut self, device: &wgpu::Device, index: usize) {
+        let bind_groups = self.passes[index].create_bind_groups(
+            device,
+            &self.pass_resources,
+            &self.pass_bindings(),
+        );
+        self.passes[index].set_bind_groups(bind_groups);
+    }
+
+    pub fn custom_pass(&self, index: usize) -> Option<&Pass> {
+        self.passes.get(index)
+    }
+
+    pub fn custom_pass_mut(&mut self, index: usize) -> Option<&mut Pass> {
+        self.passes.get_mut(index)
+    }
+
+    /// Put the passes in a new order: `order` lists the current indices of the passes
+    /// as they should run. Returns `false`, leaving the chain alone, unless it lists
+    /// each pass once.
+    ///
+    /// Triggers, macro targets, param region overrides and the recorded history
+    /// follow their passes to the new indices; a running morph stops.
+    pub fn reorder_passes(&mut self, order: &[usize]) -> bool {
+        if order.len() != self.passes.len() {
+            return false;
+        }
+        let mut new_index = vec![usize::MAX; self.passes.len()];
+        for (position, &index) in order.iter().enumerate() {
+            match new_index.get_mut(index) {
+                Some(slot) if *slot == usize::MAX => *slot = position,
+                _ => return false,
+            }
+        }
+
+        let mut passes: Vec<_> = std::mem::take(&mut self.passes)
+            .into_iter()
+            .map(Some)
+            .collect();
+        self.passes = order
+            .iter()
+            .map(|&index| passes[index].take().expect("listed once"))
+            .collect();
+
+        for state in &mut self.triggers {
+            state.trigger.pass = new_index[state.trigger.pass];
+        }
+        for state in &mut self.macros {
+            for target in &mut state.targets {
+                target.path = remap_pass_path(&target.path, &new_index);
+            }
+        }
+        for region in &mut self.param_regions {
+            for (path, _) in &mut region.overrides {
+                *path = remap_pass_path(path, &new_index);
+            }
+        }
+        #[cfg(feature = "config")]
+        {
+            self.morph = None;
+            if let Some(history) = &mut self.history {
+                history.remap_passes(&new_index);
+            }
+        }
+        true
+    }
+
+    // Take all passes out of the chain, to put some back in another order
+    #[cfg(feature = "config")]
+    pub(crate) fn take_passes(&mut self) -> Vec<Pass> {
+        std::mem::take(&mut self.passes)
+    }
+
+    // Append a pass taken out with `take_passes`
+    #[cfg(feature = "config")]
+    pub(crate) fn push_taken_pass(&mut self, pass: Pass) -> usize {
+        self.passes.push(pass);
+        self.passes.len() - 1
+    }
+
+    // Drop the passes after the first `len`
+    #[cfg(feature = "config")]
+    pub(crate) fn truncate_passes(&mut self, len: usize) {
+        self.passes.truncate(len);
+    }
+
+    // Queue an error found before a pass's shader got to compile
+    pub(crate) fn queue_shader_error(&mut self, error: ShaderError) {
+        self.shader_errors.push(error);
+    }
+
+    /// Drain the shader errors collected since the last call.
+    pub fn take_shader_errors(&mut self) -> Vec<ShaderError> {
+        std::mem::take(&mut self.shader_errors)
+    }
+
+    /******************* Pass graph ****************** */
+
+    /// Run `graph` after the effect passes, or stop with `None`. See [`PassGraph`].
+    ///
+    /// The graph reads the effect passes' output, or the composite's without any, as
+    /// `input`, and its `output` goes on down the chain in its place. Its passes are
+    /// scheduled once, here, so each runs after the passes whose textures it reads,
+    /// and those nothing reads are left out. Its textures, `input` included, are
+    /// Rgba16Float at the pipeline's resolution, drawn from a pool sized by
+    /// [`PassGraph::allocate`] so textures never needed at once share one. Shader
+    /// errors are queued for
+    /// [`Nnpipe::take_shader_errors`], the failing passes passing their first input
+    /// through; a graph that can't be scheduled leaves the previous one in place.
+    pub fn set_pass_graph(
+        &mut self,
+        device: &wgpu::Device,
+        graph: Option<PassGraph>,
+    ) -> Result<(), PassGraphError> {
+        let Some(graph) = graph else {
+            self.graph_layer = None;
+            return Ok(());
+        };
+        let errors = self.build_graph_layer(device, graph)?;
+        self.shader_errors.extend(errors);
+        Ok(())
+    }
+
+    /// The graph set with [`Nnpipe::set_pass_graph`], with its params as last set.
+    pub fn pass_graph(&self) -> Option<&PassGraph> {
+        self.graph_layer.as_ref().map(|layer| layer.graph())
+    }
+
+    /// Set a param of the graph pass writing `output`. Returns `false` without a
+    /// graph, or if that pass has no parameter by that name.
+    ///
+    /// Like [`Pass::set_param`], the value is uploaded with the next frame.
+    pub fn set_graph_param(&mut self, output: &str, name: &str, value: f32) -> bool {
+        self.graph_layer
+            .as_mut()
+            .is_some_and(|layer| layer.set_param(output, name, value))
+    }
+
+    // Schedule `graph` and build its passes against the pipeline's textures, returning
+    // their shader errors
+    fn build_graph_layer(
+        &mut self,
+        device: &wgpu::Device,
+        graph: PassGraph,
+    ) -> Result<Vec<ShaderError>, PassGraphError> {
+        let (layer, errors) = self.new_graph_layer(device, graph)?;
+        self.graph_layer = Some(layer);
+        Ok(errors)
+    }
+
+    // Schedule `graph` and build its passes, without running them yet
+    pub(crate) fn new_graph_layer(
+        &self,
+        device: &wgpu::Device,
+        graph: PassGraph,
+    ) -> Result<(GraphLayer, Vec<ShaderError>), PassGraphError> {
+        GraphLayer::new(
+            device,
+            &self.pass_resources,
+            &self.pass_bindings(),
+            [self.width, self.height],
+            graph,
+        )
+    }
+
+    // Run a graph built with `new_graph_layer`, queueing its shader errors, or none
+    #[cfg(feature = "config")]
+    pub(crate) fn replace_graph_layer(&mut self, layer: Option<(GraphLayer, Vec<ShaderError>)>) {
+        self.graph_layer = layer.map(|(layer, errors)| {
+            self.shader_errors.extend(errors);
+            layer
+        });
+    }
+
+    /******************* Time and noise ****************** */
+
+    /// Seconds the effect passes see as `globals.time`: the pipeline's clock, which
+    /// starts at 0 when it's created, unless pinned with [`Nnpipe::set_time`].
+    ///
+    /// The clock runs apart from the host's, see [`Nnpipe::set_time_rate`],
+    /// [`Nnpipe::set_time_paused`] and [`Nnpipe::jump_time`]. Triggers, the burn-in's
+    /// timecode and captures follow it too.
+    pub fn time(&self) -> f32 {
+        self.fixed_time.unwrap_or_else(|| self.clock.time())
+    }
+
+    /// Run the clock at `rate` times the wall clock's speed, at least 0: below 1 for
+    /// slow motion of the effects, above 1 to speed them up. The time reached so far
+    /// is kept.
+    pub fn set_time_rate(&mut self, rate: f32) {
+        self.clock.set_rate(rate);
+    }
+
+    pub fn time_rate(&self) -> f32 {
+        self.clock.rate()
+    }
+
+    /// Freeze the clock, and every effect animated by it, at the time it reached, or
+    /// carry on from there with `false`.
+    pub fn set_time_paused(&mut self, paused: bool) {
+        self.clock.set_paused(paused);
+    }
+
+    pub fn time_paused(&self) -> bool {
+        self.clock.paused()
+    }
+
+    /// Set the clock to `time` seconds, carrying on from there at its rate, or staying
+    /// there while paused.
+    pub fn jump_time(&mut self, time: f32) {
+        self.clock.jump(time);
+    }
+
+    /// Pin the time the effect passes and triggers see, or go back to the clock with
+    /// `None`, which has run on in the meantime.
+    ///
+    /// Offline renders set it to each frame's timestamp, so the animation follows the
+    /// frame count rather than how long frames took to render.
+    pub fn set_time(&mut self, time: Option<f32>) {
+        self.fixed_time = time;
+    }
+
+    /// The time pinned with [`Nnpipe::set_time`], if any.
+    pub fn fixed_time(&self) -> Option<f32> {
+        self.fixed_time
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    /// Seed the noise of the effect passes, passed to shaders as `globals.seed`.
+    ///
+    /// Noise only depends on the seed, the time and the pixel, so with the time pinned
+    /// too, identical runs render identical frames.
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+
+    /******************* Triggers ****************** */
+
+    /// Register `trigger` under `name`, to be fired with [`Nnpipe::fire`]. Any number
+    /// of triggers can share a name, e.g. a flash and a glitch burst on the same beat.
+    pub fn add_trigger(&mut self, name: impl Into<String>, trigger: Trigger) {
+        self.triggers.push(TriggerState {
+            name: name.into(),
+            trigger,
+            fired: None,
+        });
+    }
+
+    /// Remove the triggers registered under `name`, leaving their params at the
+    /// values they were set to.
+    pub fn remove_triggers(&mut self, name: &str) {
+        let (removed, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.triggers)
+            .into_iter()
+            .partition(|state| state.name == name);
+        self.triggers = kept;
+
+        for state in removed {
+            if let Some(pass) = self.passes.get(state.trigger.pass) {
+                if let Some(value) = pass.param(&state.trigger.param) {
+                    pass.write_param(&state.trigger.param, value);
+                }
+            }
+        }
+    }
+
+    // Every registered trigger with its name, in registration order
+    #[cfg(feature = "config")]
+    pub(crate) fn named_triggers(&self) -> impl Iterator<Item = (&str, &Trigger)> {
+        self.triggers
+            .iter()
+            .map(|state| (state.name.as_str(), &state.trigger))
+    }
+
+    pub fn triggers<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Trigger> + 'a {
+        self.triggers
+            .iter()
+            .filter(move |state| state.name == name)
+            .map(|state| &state.trigger)
+    }
+
+    /// Fire the triggers registered under `name`, restarting any envelope still
+    /// running. Returns `false` if there are none.
+    pub fn fire(&mut self, name: &str) -> bool {
+        let now = self.time();
+        let mut fired = false;
+        for state in self.triggers.iter_mut().filter(|state| state.name == name) {
+            state.fired = Some(now);
+            fired = true;
+        }
+        fired
+    }
+
+    /// Like [`Nnpipe::fire`], also setting the position params of the triggers to
+    /// `point`, in UV with (0, 0) at the top left. This is how effects like a
+    /// shockwave learn where to start.
+    pub fn fire_at(&mut self, name: &str, point: [f32; 2]) -> bool {
+        for state in self.triggers.iter().filter(|state| state.name == name) {
+            let (Some(pass), Some(position)) = (
+                self.passes.get_mut(state.trigger.pass),
+                &state.trigger.position,
+            ) else {
+                continue;
+            };
+            pass.set_param(&position[0], point[0]);
+            pass.set_param(&position[1], point[1]);
+        }
+        self.fire(name)
+    }
+
+    // Write the params driven by fired triggers: their own value plus the offsets of
+    // every trigger on them
+    fn write_triggers(&self) {
+        let now = self.time();
+        let mut driven: Vec<(usize, &str, f32)> = Vec::new();
+        for state in self.triggers.iter().filter(|state| state.fired.is_some()) {
+            let offset = state.offset(now);
+            let key = (state.trigger.pass, state.trigger.param.as_str());
+            match driven
+                .iter_mut()
+                .find(|(pass, param, _)| (*pass, *param) == key)
+            {
+                Some((_, _, total)) => *total += offset,
+                None => driven.push((key.0, key.1, offset)),
+            }
+        }
+
+        for (pass, param, offset) in driven {
+            if let Some(pass) = self.passes.get(pass) {
+                if let Some(value) = pass.param(param) {
+                    pass.write_param(param, value + offset);
+                }
+            }
+        }
+    }
+
+    /******************* Outputs ****************** */
+
+    /// Add an output that renders into views of `format` and return its index.
+    ///
+    /// Outputs share the scene and effect textures; each one only adds a final
+    /// pass with its own resolution and [`OutputAdjustments`](crate::OutputAdjustments).
+    ///
+    /// Fails for formats that can't be rendered to on every device.
+    pub fn add_output(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> Result<usize> {
+        check_render_format(device, format)?;
+        Ok(self.push_output(device, format))
+    }
+
+    fn push_output(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) -> usize {
+        let output = Output::new(
+            device,
+            &self.cache,
+            &self.output_view,
+            &self.sampler,
+            format,
+        );
+        self.outputs.push(output);
+        self.outputs.len() - 1
+    }
+
+    /// The resolution the whole chain runs at: the size the pipeline was created or
+    /// resized to, times the render scale.
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    /// The size the pipeline was created or resized to.
+    pub fn base_size(&self) -> [u32; 2] {
+        self.base_size
+    }
+
+    /// MSAA samples of the scene texture.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Run the chain at `scale` times the base size, from 0.1 to 1, e.g. to trade
+    /// sharpness for frame rate (see [`ResolutionController`](crate::ResolutionController)).
+    ///
+    /// nannou `Draw`s keep their framing, as they're drawn with `scale` as the scale
+    /// factor, and [`Nnpipe::process`] targets of the base size get the result scaled
+    /// in. Params in pixels (blur radii, displacement amplitudes) stay in the chain's
+    /// pixels. Rebuilds the pipeline's resources unless the size stays the same.
+    pub fn set_render_scale(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scale: f32,
+    ) -> Result<()> {
+        let scale = scale.clamp(0.1, 1.0);
+        let [width, height] = scaled_size(self.base_size, scale);
+        if [width, height] != [self.width, self.height] {
+            check_size(device, width, height)?;
+            self.rebuild(device, queue, width, height);
+        }
+        self.render_scale = scale;
+        Ok(())
+    }
+
+    /// The output that scales the result into `process` targets whose size differs
+    /// from the pipeline's. It letterboxes by default; set its fit, scaling,
+    /// background, transform and mask like any other output's.
+    pub fn scaler(&self) -> &Output {
+        &self.scaler
+    }
+
+    pub fn scaler_mut(&mut self) -> &mut Output {
+        &mut self.scaler
+    }
+
+    pub fn debug_view(&self) -> DebugView {
+        self.debug_view
+    }
+
+    /// Draw a debug view into process targets instead of the frame, e.g.
+    /// [`DebugView::Quad`] to see every stage of the chain while tuning it, or
+    /// [`DebugView::FalseColor`] to set the exposure and brightness threshold.
+    ///
+    /// Outputs and captures get the debug view too, as they're fed from the output
+    /// texture [`Nnpipe::render`] draws it into.
+    pub fn set_debug_view(&mut self, device: &wgpu::Device, view: DebugView) {
+        self.debug_view = view;
+        self.debug_layer = match view {
+            DebugView::Off => None,
+            DebugView::Quad | DebugView::FalseColor => Some(DebugLayer::new(
+                device,
+                &self.cache,
+                &self.pass_resources.vertex_shader,
+                &self.sampler,
+                &self.scene_view,
+                &self.brightness_view,
+                &self.blur_v_view,
+                view,
+            )),
+        };
+    }
+
+    pub fn output(&self, index: usize) -> Option<&Output> {
+        self.outputs.get(index)
+    }
+
+    pub fn output_mut(&mut self, index: usize) -> Option<&mut Output> {
+        self.outputs.get_mut(index)
+    }
+
+    /// Recreate all textures, pipelines and bind groups on `device`, keeping the
+    /// current parameter values.
+    ///
+    /// Use this after a device loss or an adapter change (e.g. switchable laptop
+    /// graphics) instead of restarting the app. Every resource held by the pipeline
+    /// belongs to the old device, so the new device and its queue must be passed in.
+    ///
+    /// Fails, leaving the pipeline as it was, if the new device can't hold its
+    /// textures.
+    pub fn recover(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<()> {
+        check_size(device, self.width, self.height)?;
+        check_samples(device, self.samples)?;
+        self.rebuild(device, queue, self.width, self.height);
+        Ok(())
+    }
+
+    /// Resize every texture to `width` x `height` (times the render scale), keeping
+    /// parameters, passes and outputs.
+    ///
+    /// Fails, leaving the pipeline as it was, if the size exceeds the device's limits.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        check_size(device, width, height)?;
+        let [scaled_width, scaled_height] = scaled_size([width, height], self.render_scale);
+        self.rebuild(device, queue, scaled_width, scaled_height);
+        self.base_size = [width, height];
+        Ok(())
+    }
+
+    // Recreate all resources at the given size and carry the current state over
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, device, queue)))]
+    fn rebuild(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        let previous = std::mem::replace(
+            self,
+            Self::build(
+                device,
+                width,
+                height,
+                self.samples,
+                self.quality,
+                self.bloom_scale,
+                self.equirectangular,
+                &self.cache,
+            ),
+        );
+
+        self.brightness_threshold = previous.brightness_threshold;
+        self.channel_thresholds = previous.channel_thresholds;
+        self.bloom_intensity = previous.bloom_intensity;
+        self.adaptive_blur_scaling = previous.adaptive_blur_scaling;
+        self.max_blur_radius = previous.max_blur_radius;
+        self.intensity_curve = previous.intensity_curve;
+        self.horizontal_blur_strength = previous.horizontal_blur_strength;
+        self.vertical_blur_strength = previous.vertical_blur_strength;
+        self.bloom_stretch = previous.bloom_stretch;
+        self.blur_angle = previous.blur_angle;
+        self.premultiplied_alpha = previous.premultiplied_alpha;
+        self.sanitize_scene = previous.sanitize_scene;
+        self.upsample_filter = previous.upsample_filter;
+        self.bloom_saturation = previous.bloom_saturation;
+        self.bloom_hue_shift = previous.bloom_hue_shift;
+        self.stencil_exclusion = previous.stencil_exclusion;
+        self.bypass = previous.bypass;
+        self.bloom_muted = previous.bloom_muted;
+        self.bloom_soloed = previous.bloom_soloed;
+        self.layer_groups = previous.layer_groups;
+
+        self.write_parameters();
+
+        // Depth comes before the passes, which bind it
+        if let Some(format) = previous.depth_format {
+            self.create_depth(device, format);
+        }
+
+        for pass in &previous.passes {
+            let params: Vec<(&str, f32)> = pass
+                .params()
+                .iter()
+                .map(|(name, value)| (name.as_str(), *value))
+                .collect();
+            let inputs = pass
+                .inputs()
+                .filter(|inputs| inputs.belongs_to(device))
+                .cloned();
+            let index = self.push_pass(
+                device,
+                &pass.label,
+                pass.source(),
+                &params,
+                pass.data(),
+                pass.is_compute(),
+                inputs,
+            );
+            self.passes[index].enabled = pass.enabled;
+            self.passes[index].mix = pass.mix;
+            self.passes[index].muted = pass.muted;
+            self.passes[index].soloed = pass.soloed;
+            if let Some(lookup) = pass.lookup() {
+                self.replace_pass_lookup(device, queue, index, lookup.clone());
+            }
+            self.set_pass_sampler(device, index, pass.sampler());
+            if !pass.constants().is_empty() {
+                let constants: Vec<(&str, f32)> = pass
+                    .constants()
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), *value))
+                    .collect();
+                if let Err(error) =
+                    self.passes[index].set_constants(device, &self.pass_resources, &constants)
+                {
+                    self.shader_errors.push(error);
+                }
+            }
+        }
+        // The graph was scheduled when it was set, and its shader errors reported then
+        if let Some(layer) = previous.graph_layer {
+            let _ = self.build_graph_layer(device, layer.graph().clone());
+        }
+        self.shader_errors.extend(previous.shader_errors);
+        self.base_size = previous.base_size;
+        self.render_scale = previous.render_scale;
+        self.clock = previous.clock;
+        self.fixed_time = previous.fixed_time;
+        self.seed = previous.seed;
+        self.triggers = previous.triggers;
+        self.macros = previous.macros;
+        self.param_regions = previous.param_regions;
+        self.commands = previous.commands;
+        self.command_errors = previous.command_errors;
+        #[cfg(feature = "config")]
+        {
+            self.morph = previous.morph;
+            self.history = previous.history;
+        }
+
+        for output in &previous.outputs {
+            let index = self.push_output(device, output.format());
+            self.outputs[index].copy_settings(output);
+        }
+        self.scaler.copy_settings(&previous.scaler);
+        self.set_debug_view(device, previous.debug_view);
+
+        #[cfg(feature = "bloom")]
+        self.set_fft_bloom(device, queue, previous.fft_kernel);
+        if let Some(mut layer) = previous
+            .reflection_layer
+            .filter(|layer| layer.belongs_to(device))
+        {
+            layer.rebuild(
+                device,
+                &self.cache,
+                &self.scene_view,
+                &self.sampler,
+                self.brightness_view.size(),
+                self.quality.bloom_format,
+            );
+            self.reflection_layer = Some(layer);
+            self.write_composite_bind_group(device);
+        }
+        #[cfg(feature = "bloom")]
+        self.set_lens_flare(device, previous.flare_layer.map(|layer| layer.settings()));
+        #[cfg(feature = "stylize")]
+        self.set_sparkles(device, previous.sparkle_layer.map(|layer| layer.settings()));
+        #[cfg(feature = "temporal")]
+        self.set_bloom_stabilization(
+            device,
+            previous.stabilize_layer.map(|layer| layer.settings()),
+        );
+        if let Some(layer) = &previous.gutter_layer {
+            // Dropped if the padded textures no longer fit the device
+            let _ = self.set_bloom_gutter(device, layer.gutter());
+        }
+        self.set_matte(device, previous.matte_layer.map(|layer| layer.settings()));
+        #[cfg(feature = "stylize")]
+        self.set_drop_shadow(
+            device,
+            previous.drop_shadow_layer.map(|layer| layer.settings()),
+        );
+        #[cfg(feature = "stylize")]
+        self.set_distance_glow(
+            device,
+            previous.distance_glow_layer.map(|layer| layer.settings()),
+        );
+        #[cfg(feature = "temporal")]
+        self.set_accumulation(
+            device,
+            previous.accumulation_layer.map(|layer| layer.settings()),
+        );
+        self.set_magnifier(
+            device,
+            previous.magnifier_layer.map(|layer| layer.settings()),
+        );
+        self.set_zebra(device, previous.zebra_layer.map(|layer| layer.settings()));
+        self.set_scopes(device, previous.scopes_layer.map(|layer| layer.settings()));
+        self.set_guides(device, previous.guides_layer.map(|layer| layer.settings()));
+        if let Some(layer) = previous.burn_in_layer {
+            self.set_burn_in(device, queue, Some(layer.settings().clone()));
+            if let Some(new_layer) = &self.burn_in_layer {
+                new_layer.set_frames(layer.frames());
+            }
+        }
+        if let Some(layer) = previous.hud_layer {
+            self.set_stats_hud(device, queue, Some(layer.settings()));
+            if let Some(new_layer) = &self.hud_layer {
+                new_layer.take_frame_times(&layer);
+            }
+        }
+        self.set_stereo(device, previous.stereo_layer.is_some());
+    }
+
+    /******************* Scene depth ****************** */
+
+    /// Give the scene a depth-stencil texture of `format`, owned by the pipeline.
+    ///
+    /// The texture matches the scene's size and sample count and is recreated on
+    /// resize and recover. Effect passes can read its depth at binding 4. nannou's
+    /// `Draw` renderer keeps its own depth buffer, so this is for scenes recorded with
+    /// [`Nnpipe::process_with`]: attach [`Nnpipe::depth_view`] to your scene passes.
+    ///
+    /// Rebuilds the pipeline's resources, so call it at setup rather than per frame.
+    /// Fails if `format` has no depth aspect.
+    pub fn enable_depth(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> Result<()> {
+        if !format.has_depth_aspect() {
+            return Err(NnpipeError::UnsupportedFormat(format));
+        }
+        self.depth_format = Some(format);
+        self.rebuild(device, queue, self.width, self.height);
+        Ok(())
+    }
+
+    pub fn disable_depth(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.depth_format = None;
+        self.rebuild(device, queue, self.width, self.height);
+    }
+
+    pub fn depth_texture(&self) -> Option<&wgpu::Texture> {
+        self.depth_texture.as_ref()
+    }
+
+    /// The depth-stencil attachment view for the scene pass, if depth is enabled.
+    pub fn depth_view(&self) -> Option<&wgpu::TextureView> {
+        self.depth_view.as_ref()
+    }
+
+    fn create_depth(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        let depth_texture = wgpu::TextureBuilder::new()
+            .size([self.width, self.height])
+            .dimension(wgpu::TextureDimension::D2)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+            .sample_count(self.samples)
+            .format(format)
+            .build(device);
+
+        self.depth_format = Some(format);
+        self.depth_view = Some(
+            depth_texture
+                .view()
+                .aspect(wgpu::TextureAspect::All)
+                .build(),
+        );
+        // Stencil-only formats have nothing for passes to sample
+        self.depth_sample_view = format
+            .aspect_specific_format(wgpu::TextureAspect::DepthOnly)
+            .map(|depth_format| {
+                depth_texture
+                    .view()
+                    .format(depth_format)
+                    .aspect(wgpu::TextureAspect::DepthOnly)
+                    .build()
+            });
+        self.depth_texture = Some(depth_texture);
+
+        // The mask pass reads the stencil, which has to match its single-sampled target
+        self.exclusion_mask_pipeline = (format.has_stencil_aspect() && self.samples == 1)
+            .then(|| create_exclusion_mask_pipeline(device, &self.cache, format));
+    }
+
+    /******************* Screen-space reflections ****************** */
+
+    /// Add reflections of the scene to itself, traced in screen space through the
+    /// scene's own depth and normal buffers. See [`Reflections`].
+    ///
+    /// `depth_view` is the depth aspect of a single-sampled depth texture.
+    /// `normal_view` holds view-space normals in RGB, scaled to 0..1 as `n * 0.5 +
+    /// 0.5`, and each surface's roughness in alpha: smooth surfaces reflect sharply,
+    /// rough ones blurred by up to [`Reflections::blur_radius`]. Both should match
+    /// the scene's size. The reflections are added to the scene ahead of the
+    /// composite's tone mapping.
+    ///
+    /// The buffers are kept through resizes, so enable reflections again with new
+    /// ones if their size changes. Recovering from a lost device drops them.
+    pub fn enable_reflections(
+        &mut self,
+        device: &wgpu::Device,
+        depth_view: &wgpu::TextureView,
+        normal_view: &wgpu::TextureView,
+        reflections: Reflections,
+    ) {
+        self.reflection_layer = Some(ReflectionLayer::new(
+            device,
+            &self.cache,
+            depth_view,
+            normal_view,
+            &self.scene_view,
+            &self.sampler,
+            &self.blur_bind_group_layout,
+            self.brightness_view.size(),
+            self.quality.bloom_format,
+            reflections,
+        ));
+        self.write_composite_bind_group(device);
+    }
+
+    pub fn disable_reflections(&mut self, device: &wgpu::Device) {
+        self.reflection_layer = None;
+        self.write_composite_bind_group(device);
+    }
+
+    /// Change the reflections' settings. Does nothing unless they're enabled.
+    pub fn set_reflections(&mut self, reflections: Reflections) {
+        if let Some(layer) = &mut self.reflection_layer {
+            layer.set_settings(reflections);
+        }
+    }
+
+    pub fn reflections(&self) -> Option<Reflections> {
+        self.reflection_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    // The reflections the composite adds to the scene, black without any
+    fn reflection_views(&self) -> [&wgpu::TextureView; 2] {
+        match &self.reflection_layer {
+            Some(layer) => layer.views(),
+            None => [&self.empty_reflection_view; 2],
+        }
+    }
+
+    // Recreate the composite bind group of the scene texture, for other reflections
+    fn write_composite_bind_group(&mut self, device: &wgpu::Device) {
+        self.composite_bind_group = create_composite_bind_group(
+            device,
+            &self.composite_bind_group_layout,
+            &self.scene_view,
+            &self.blur_v_view,
+            &self.sampler,
+            &self.intensity_buffer,
+            &self.intensity_curve_buffer,
+            &self.alpha_mode_buffer,
+            &self.sanitize_buffer,
+            &self.resolution_buffer,
+            &self.upsample_filter_buffer,
+            &self.bloom_color_buffer,
+            self.reflection_views(),
+        );
+    }
+
+    // Resources every effect pass binds
+    fn pass_bindings(&self) -> PassBindings<'_> {
+        // Passes can only sample single-sampled depth
+        let depth_view = match &self.depth_sample_view {
+            Some(view) if self.samples == 1 => view,
+            _ => &self.pass_resources.empty_depth_view,
+        };
+        let matte_view = match &self.matte_layer {
+            Some(layer) => layer.view(),
+            None => &self.pass_resources.empty_matte_view,
+        };
+
+        PassBindings {
+            inputs: [&self.composite_view, &self.effect_view],
+            sampler: &self.sampler,
+            globals_buffer: &self.globals_buffer,
+            depth_view,
+            matte_view,
+        }
+    }
+
+    // Upload every parameter field to its uniform buffer
+    fn write_parameters(&self) {
+        self.threshold_buffer.write(
+            0,
+            bytemuck::cast_slice(&threshold_data(
+                self.brightness_threshold,
+                self.channel_thresholds,
+            )),
+        );
+        self.intensity_buffer
+            .write(0, bytemuck::cast_slice(&[self.bloom_intensity]));
+        self.adaptive_scaling_buffer
+            .write(0, bytemuck::cast_slice(&[self.adaptive_blur_scaling]));
+        self.max_radius_buffer
+            .write(0, bytemuck::cast_slice(&[self.max_blur_radius]));
+        self.intensity_curve_buffer
+            .write(0, bytemuck::cast_slice(&[self.intensity_curve]));
+        self.write_blur_directions();
+        self.alpha_mode_buffer.write(
+            0,
+            bytemuck::cast_slice(&[self.premultiplied_alpha as u32 as f32]),
+        );
+        self.sanitize_buffer.write(
+            0,
+            bytemuck::cast_slice(&[self.sanitize_scene as u32 as f32]),
+        );
+        self.upsample_filter_buffer.write(
+            0,
+            bytemuck::cast_slice(&[self.upsample_filter.shader_value()]),
+        );
+        self.write_bloom_color();
+    }
+
+    /******************* Stereo ****************** */
+
+    /// Turn stereo rendering on or off.
+    ///
+    /// In stereo, [`Nnpipe::process_stereo`] runs a view per [`Eye`] through the chain
+    /// with the same params, into a frame per eye at the pipeline's resolution, and
+    /// outputs present them with [`Nnpipe::present_eye`] and
+    /// [`Nnpipe::present_stereo`]. Temporal effects, the bloom stabilization and the
+    /// accumulation, keep a history per eye, which start over when stereo is turned on
+    /// or off. The accumulation's frame count, view and readback are the left eye's.
+    pub fn set_stereo(&mut self, device: &wgpu::Device, enabled: bool) {
+        let toggled = enabled != self.stereo_layer.is_some();
+        self.stereo_layer =
+            enabled.then(|| StereoLayer::new(device, &self.cache, [self.width, self.height]));
+        // Rebuilt with as many histories as there are eyes
+        #[cfg(feature = "temporal")]
+        if toggled {
+            let stabilization = self.stabilize_layer.take().map(|layer| layer.settings());
+            self.set_bloom_stabilization(device, stabilization);
+            let accumulation = self.accumulation_layer.take().map(|layer| layer.settings());
+            self.set_accumulation(device, accumulation);
+        }
+        #[cfg(not(feature = "temporal"))]
+        let _ = toggled;
+    }
+
+    pub fn stereo(&self) -> bool {
+        self.stereo_layer.is_some()
+    }
+
+    /// Run the `left` and `right` eye views through the chain, each into its eye's
+    /// frame. The views are used as scenes, as in [`Nnpipe::process_texture`].
+    ///
+    /// Fails with [`NnpipeError::StereoOff`] unless stereo is on.
+    pub fn process_stereo(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        left: &wgpu::TextureView,
+        right: &wgpu::TextureView,
+    ) -> Result<()> {
+        let layer = self.stereo_frames()?;
+        for (eye, view) in [(Eye::Left, left), (Eye::Right, right)] {
+            self.process_eye(device, queue, view, layer.frame_view(eye), eye);
+        }
+        Ok(())
+    }
+
+    /// Like [`Nnpipe::process_stereo`], for one view with both eyes packed in by
+    /// `layout`. Each eye's part is scaled to the pipeline's resolution.
+    ///
+    /// Fails with [`NnpipeError::StereoOff`] unless stereo is on.
+    pub fn process_packed_stereo(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        packed: &wgpu::TextureView,
+        layout: StereoLayout,
+    ) -> Result<()> {
+        let layer = self.stereo_frames()?;
+        layer.split(device, queue, packed, &self.sampler, layout);
+        for eye in Eye::BOTH {
+            let scene_view = layer.scene_view(eye);
+            self.process_eye(device, queue, scene_view, layer.frame_view(eye), eye);
+        }
+        Ok(())
+    }
+
+    /// The frame [`Nnpipe::process_stereo`] produced for `eye`, if stereo is on.
+    pub fn eye_view(&self, eye: Eye) -> Option<&wgpu::TextureView> {
+        self.stereo_layer
+            .as_ref()
+            .map(|layer| layer.frame_view(eye))
+    }
+
+    /// Draw `eye`'s frame into `view` through output `index`, as
+    /// [`Nnpipe::present`] draws the pipeline's.
+    ///
+    /// Fails with [`NnpipeError::StereoOff`] unless stereo is on, and with
+    /// [`NnpipeError::UnknownOutput`] without an output at `index`.
+    pub fn present_eye(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        eye: Eye,
+        index: usize,
+        view: &wgpu::TextureView,
+    ) -> Result<()> {
+        let frame_view = self.stereo_frames()?.frame_view(eye);
+        let output = self.stereo_output(index)?;
+        let bind_group = output.source_bind_group(device, frame_view, &self.sampler);
+        let [width, height] = view.size();
+        output.present_region(
+            device,
+            queue,
+            &self.uploader,
+            view,
+            &bind_group,
+            [0, 0, width, height],
+            true,
+        );
+        Ok(())
+    }
+
+    /// Draw both eyes' frames into `view` through output `index`, packed by `layout`,
+    /// e.g. for a headset or a 3D display taking side-by-side frames. The output's
+    /// settings apply to each eye's part on its own.
+    ///
+    /// Fails like [`Nnpipe::present_eye`].
+    pub fn present_stereo(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: usize,
+        view: &wgpu::TextureView,
+        layout: StereoLayout,
+    ) -> Result<()> {
+        let layer = self.stereo_frames()?;
+        let output = self.stereo_output(index)?;
+        for eye in Eye::BOTH {
+            let bind_group = output.source_bind_group(device, layer.frame_view(eye), &self.sampler);
+            let region = layout.region(eye, view.size());
+            // The first eye clears the whole view
+            output.present_region(
+                device,
+                queue,
+                &self.uploader,
+                view,
+                &bind_group,
+                region,
+                eye == Eye::Left,
+            );
+        }
+        Ok(())
+    }
+
+    // Histories the temporal effects keep, one per eye
+    #[cfg(feature = "temporal")]
+    fn temporal_histories(&self) -> usize {
+        match self.stereo_layer {
+            Some(_) => Eye::BOTH.len(),
+            None => 1,
+        }
+    }
+
+    fn stereo_frames(&self) -> Result<&StereoLayer> {
+        self.stereo_layer.as_ref().ok_or(NnpipeError::StereoOff)
+    }
+
+    fn stereo_output(&self, index: usize) -> Result<&Output> {
+        self.outputs
+            .get(index)
+            .ok_or(NnpipeError::UnknownOutput(index))
+    }
+
+    /******************* Helper methods for updating parameters ****************** */
+    //
+    // Setters only update the buffers' CPU copies; the changes are uploaded together
+    // with the next frame, so they need no queue.
+
+    pub fn set_brightness_threshold(&mut self, threshold: f32) {
+        self.brightness_threshold = threshold;
+        self.threshold_buffer.write(
+            0,
+            bytemuck::cast_slice(&threshold_data(threshold, self.channel_thresholds)),
+        );
+    }
+
+    /// Threshold R, G and B separately, or go back to the luminance threshold with
+    /// `None`.
+    pub fn set_channel_thresholds(&mut self, thresholds: Option<[f32; 3]>) {
+        self.channel_thresholds = thresholds;
+        self.threshold_buffer.write(
+            0,
+            bytemuck::cast_slice(&threshold_data(self.brightness_threshold, thresholds)),
+        );
+    }
+
+    pub fn set_bloom_intensity(&mut self, intensity: f32) {
+        self.bloom_intensity = intensity;
+        self.intensity_buffer
+            .write(0, bytemuck::cast_slice(&[intensity]));
+    }
+
+    pub fn set_adaptive_blur_scaling(&mut self, scaling: f32) {
         self.adaptive_blur_scaling = scaling;
-        queue.write_buffer(
-            &self.adaptive_scaling_buffer,
+        self.adaptive_scaling_buffer
+            .write(0, bytemuck::cast_slice(&[scaling]));
+    }
+
+    pub fn set_max_blur_radius(&mut self, radius: f32) {
+        self.max_blur_radius = radius;
+        self.max_radius_buffer
+            .write(0, bytemuck::cast_slice(&[radius]));
+    }
+
+    pub fn set_intensity_curve(&mut self, curve: f32) {
+        self.intensity_curve = curve;
+        self.intensity_curve_buffer
+            .write(0, bytemuck::cast_slice(&[curve]));
+    }
+
+    pub fn set_horizontal_blur_strength(&mut self, strength: f32) {
+        self.horizontal_blur_strength = strength;
+        self.write_blur_directions();
+    }
+
+    pub fn set_vertical_blur_strength(&mut self, strength: f32) {
+        self.vertical_blur_strength = strength;
+        self.write_blur_directions();
+    }
+
+    pub fn set_bloom_stretch(&mut self, stretch: f32) {
+        self.bloom_stretch = stretch;
+        self.write_blur_directions();
+    }
+
+    pub fn set_blur_angle(&mut self, angle: f32) {
+        self.blur_angle = angle;
+        self.write_blur_directions();
+    }
+
+    fn write_blur_directions(&self) {
+        let (horizontal, vertical) = blur_directions(
+            self.horizontal_blur_strength,
+            self.vertical_blur_strength,
+            self.bloom_stretch,
+            self.blur_angle,
+        );
+        self.blur_h_buffer
+            .write(0, bytemuck::cast_slice(&horizontal));
+        self.blur_v_buffer.write(0, bytemuck::cast_slice(&vertical));
+    }
+
+    pub fn set_premultiplied_alpha(&mut self, enabled: bool) {
+        self.premultiplied_alpha = enabled;
+        self.alpha_mode_buffer
+            .write(0, bytemuck::cast_slice(&[enabled as u32 as f32]));
+    }
+
+    pub fn set_sanitize_scene(&mut self, enabled: bool) {
+        self.sanitize_scene = enabled;
+        self.sanitize_buffer
+            .write(0, bytemuck::cast_slice(&[enabled as u32 as f32]));
+    }
+
+    pub fn set_upsample_filter(&mut self, filter: UpsampleFilter) {
+        self.upsample_filter = filter;
+        self.upsample_filter_buffer
+            .write(0, bytemuck::cast_slice(&[filter.shader_value()]));
+    }
+
+    pub fn set_bloom_saturation(&mut self, saturation: f32) {
+        self.bloom_saturation = saturation;
+        self.write_bloom_color();
+    }
+
+    pub fn set_bloom_hue_shift(&mut self, hue_shift: f32) {
+        self.bloom_hue_shift = hue_shift;
+        self.write_bloom_color();
+    }
+
+    fn write_bloom_color(&self) {
+        self.bloom_color_buffer.write(
             0,
-            bytemuck::cast_slice(&[scaling]),
+            bytemuck::cast_slice(&[self.bloom_saturation, self.bloom_hue_shift]),
+        );
+    }
+
+    /// Replace the Gaussian blur with a convolution by `kernel`, computed with FFTs.
+    ///
+    /// The kernel is an image of the glare around a single bright pixel: a star, a lens
+    /// aperture, a measured point spread function. Its spectrum is computed here, so
+    /// set it at setup rather than per frame. The convolution runs on a power-of-two
+    /// domain of up to 512 texels square (see [`QualityProfile::max_fft_size`]), so
+    /// large frames are convolved at reduced resolution. Blur parameters don't apply
+    /// while a kernel is set; `None` goes back to the Gaussian blur.
+    ///
+    /// Returns `false`, and keeps the Gaussian blur, if the device can't run compute
+    /// shaders.
+    #[cfg(feature = "bloom")]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn set_fft_bloom(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        kernel: Option<ApertureKernel>,
+    ) -> bool {
+        self.fft_bloom = None;
+        self.fft_kernel = None;
+        let Some(kernel) = kernel else {
+            return true;
+        };
+
+        let bloom_size = self.brightness_view.size();
+        let max_size = self.quality.max_fft_size;
+        let Some(size) = FftBloom::domain_size(device, bloom_size, max_size) else {
+            return false;
+        };
+
+        self.fft_bloom = Some(FftBloom::new(
+            device,
+            queue,
+            &self.cache,
+            &self.pass_resources.vertex_shader,
+            &self.brightness_view,
+            &self.sampler,
+            self.quality.bloom_format,
+            size,
+            bloom_size,
+            &kernel,
+        ));
+        self.fft_kernel = Some(kernel);
+        true
+    }
+
+    #[cfg(feature = "bloom")]
+    pub fn fft_kernel(&self) -> Option<&ApertureKernel> {
+        self.fft_kernel.as_ref()
+    }
+
+    // Record the FFT convolution, if a kernel is set. Returns `true` if it replaced
+    // the blur passes.
+    #[cfg(feature = "bloom")]
+    fn encode_fft_bloom(&self, encoder: &mut wgpu::CommandEncoder) -> bool {
+        let Some(fft_bloom) = &self.fft_bloom else {
+            return false;
+        };
+        fft_bloom.encode(encoder, &self.blur_v_view);
+        true
+    }
+
+    #[cfg(not(feature = "bloom"))]
+    fn encode_fft_bloom(&self, _encoder: &mut wgpu::CommandEncoder) -> bool {
+        false
+    }
+
+    /// Add a lens flare fed by the bright areas of the frame, or remove it with
+    /// `None`: ghosts mirrored through the center of the frame, and a halo. See
+    /// [`LensFlare`].
+    ///
+    /// Like the sparkles, the flare is added over the composite, so the effect passes
+    /// apply to it.
+    #[cfg(feature = "bloom")]
+    pub fn set_lens_flare(&mut self, device: &wgpu::Device, flare: Option<LensFlare>) {
+        let Some(flare) = flare else {
+            self.flare_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.flare_layer {
+            layer.set_settings(flare);
+            return;
+        }
+        self.flare_layer = Some(FlareLayer::new(
+            device,
+            &self.cache,
+            &self.brightness_view,
+            &self.sampler,
+            &self.globals_buffer,
+            flare,
+        ));
+    }
+
+    #[cfg(feature = "bloom")]
+    pub fn lens_flare(&self) -> Option<LensFlare> {
+        self.flare_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    #[cfg(feature = "bloom")]
+    fn encode_lens_flare(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let layer = self.flare_layer.as_ref();
+        if let Some(layer) = layer.filter(|_| self.layer_audible(Layer::Flare)) {
+            layer.encode(encoder, target);
+        }
+    }
+
+    #[cfg(not(feature = "bloom"))]
+    fn encode_lens_flare(&self, _encoder: &mut wgpu::CommandEncoder, _target: &wgpu::TextureView) {}
+
+    /// Scatter short-lived glints over the bright spots of the frame, as the
+    /// brightness pass finds them, or remove them with `None`. See [`Sparkles`].
+    ///
+    /// The glints are added over the composite, so the effect passes apply to them.
+    #[cfg(feature = "stylize")]
+    pub fn set_sparkles(&mut self, device: &wgpu::Device, sparkles: Option<Sparkles>) {
+        let Some(sparkles) = sparkles else {
+            self.sparkle_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.sparkle_layer {
+            layer.set_settings(sparkles);
+            return;
+        }
+        self.sparkle_layer = Some(SparkleLayer::new(
+            device,
+            &self.cache,
+            &self.brightness_view,
+            &self.sampler,
+            &self.globals_buffer,
+            sparkles,
+        ));
+    }
+
+    #[cfg(feature = "stylize")]
+    pub fn sparkles(&self) -> Option<Sparkles> {
+        self.sparkle_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    #[cfg(feature = "stylize")]
+    fn encode_sparkles(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let layer = self.sparkle_layer.as_ref();
+        if let Some(layer) = layer.filter(|_| self.layer_audible(Layer::Sparkles)) {
+            layer.encode(encoder, target, [self.width, self.height]);
+        }
+    }
+
+    #[cfg(not(feature = "stylize"))]
+    fn encode_sparkles(&self, _encoder: &mut wgpu::CommandEncoder, _target: &wgpu::TextureView) {}
+
+    /// Cast a blurred shadow of the scene's alpha under the scene, or remove it with
+    /// `None`. See [`DropShadow`].
+    ///
+    /// For scenes drawn with alpha, over a transparent background. The shadow is
+    /// composited under the scene before the brightness pass, so the bloom and the
+    /// effect passes see it, while the matte is extracted from the scene without it.
+    #[cfg(feature = "stylize")]
+    pub fn set_drop_shadow(&mut self, device: &wgpu::Device, shadow: Option<DropShadow>) {
+        let Some(shadow) = shadow else {
+            self.drop_shadow_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.drop_shadow_layer {
+            layer.set_settings(shadow);
+            return;
+        }
+        self.drop_shadow_layer = Some(DropShadowLayer::new(
+            device,
+            &self.cache,
+            &self.blur_bind_group_layout,
+            &self.sampler,
+            &self.alpha_mode_buffer,
+            [self.width, self.height],
+            shadow,
+        ));
+    }
+
+    #[cfg(feature = "stylize")]
+    pub fn drop_shadow(&self) -> Option<DropShadow> {
+        self.drop_shadow_layer
+            .as_ref()
+            .map(|layer| layer.settings())
+    }
+
+    // The drop shadow, unless it's off, muted or soloed out
+    #[cfg(feature = "stylize")]
+    fn active_drop_shadow(&self) -> Option<&DropShadowLayer> {
+        self.drop_shadow_layer
+            .as_ref()
+            .filter(|_| self.layer_audible(Layer::DropShadow))
+    }
+
+    // The view standing in for `input_view` as the scene, and the bind groups that
+    // shadow it into that view
+    #[cfg(feature = "stylize")]
+    fn drop_shadow_scene<'a>(
+        &'a self,
+        device: &wgpu::Device,
+        input_view: &'a wgpu::TextureView,
+    ) -> (&'a wgpu::TextureView, Option<[wgpu::BindGroup; 2]>) {
+        match self.active_drop_shadow() {
+            Some(layer) => {
+                let bind_groups = layer.scene_bind_groups(
+                    device,
+                    input_view,
+                    &self.sampler,
+                    &self.alpha_mode_buffer,
+                );
+                (layer.scene_view(), Some(bind_groups))
+            }
+            None => (input_view, None),
+        }
+    }
+
+    #[cfg(not(feature = "stylize"))]
+    fn drop_shadow_scene<'a>(
+        &'a self,
+        _device: &wgpu::Device,
+        input_view: &'a wgpu::TextureView,
+    ) -> (&'a wgpu::TextureView, Option<[wgpu::BindGroup; 2]>) {
+        (input_view, None)
+    }
+
+    #[cfg(feature = "stylize")]
+    fn encode_drop_shadow(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scene_bind_groups: Option<&[wgpu::BindGroup; 2]>,
+    ) {
+        if let Some(layer) = self.active_drop_shadow() {
+            layer.encode(
+                encoder,
+                &self.scene_texture,
+                &self.scene_view,
+                scene_bind_groups,
+            );
+        }
+    }
+
+    #[cfg(not(feature = "stylize"))]
+    fn encode_drop_shadow(
+        &self,
+        _encoder: &mut wgpu::CommandEncoder,
+        _scene_bind_groups: Option<&[wgpu::BindGroup; 2]>,
+    ) {
+    }
+
+    /// Light a band around the silhouette of the scene matte, or remove the glow with
+    /// `None`. See [`DistanceGlow`].
+    ///
+    /// The glow is drawn from each pixel's distance to the matte, so it needs one,
+    /// see [`Nnpipe::set_matte`]; without a matte nothing glows. Like the sparkles,
+    /// it's added over the composite, so the effect passes apply to it.
+    #[cfg(feature = "stylize")]
+    pub fn set_distance_glow(&mut self, device: &wgpu::Device, glow: Option<DistanceGlow>) {
+        let Some(glow) = glow else {
+            self.distance_glow_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.distance_glow_layer {
+            layer.set_settings(glow);
+            return;
+        }
+        let matte_view = match &self.matte_layer {
+            Some(layer) => layer.view(),
+            None => &self.pass_resources.empty_matte_view,
+        };
+        self.distance_glow_layer = Some(DistanceGlowLayer::new(
+            device,
+            &self.cache,
+            matte_view,
+            [self.width, self.height],
+            glow,
+        ));
+    }
+
+    #[cfg(feature = "stylize")]
+    pub fn distance_glow(&self) -> Option<DistanceGlow> {
+        self.distance_glow_layer
+            .as_ref()
+            .map(|layer| layer.settings())
+    }
+
+    #[cfg(feature = "stylize")]
+    fn encode_distance_glow(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let layer = self.distance_glow_layer.as_ref();
+        if let Some(layer) = layer.filter(|_| self.layer_audible(Layer::DistanceGlow)) {
+            layer.encode(encoder, target);
+        }
+    }
+
+    #[cfg(not(feature = "stylize"))]
+    fn encode_distance_glow(
+        &self,
+        _encoder: &mut wgpu::CommandEncoder,
+        _target: &wgpu::TextureView,
+    ) {
+    }
+
+    /// Blend each frame's brightness with the previous frames' before it's blurred,
+    /// so thin bright lines and specks don't make the bloom shimmer, or stop with
+    /// `None`. See [`BloomStabilization`].
+    ///
+    /// The history starts over with the next frame whenever the stabilization is
+    /// turned on or the pipeline's resources are rebuilt.
+    #[cfg(feature = "temporal")]
+    pub fn set_bloom_stabilization(
+        &mut self,
+        device: &wgpu::Device,
+        stabilization: Option<BloomStabilization>,
+    ) {
+        let Some(stabilization) = stabilization else {
+            self.stabilize_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.stabilize_layer {
+            layer.set_settings(stabilization);
+            return;
+        }
+        self.stabilize_layer = Some(StabilizeLayer::new(
+            device,
+            &self.cache,
+            &self.brightness_view,
+            self.quality.bloom_format,
+            stabilization,
+            self.temporal_histories(),
+        ));
+    }
+
+    #[cfg(feature = "temporal")]
+    pub fn bloom_stabilization(&self) -> Option<BloomStabilization> {
+        self.stabilize_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    // Whether the brightness was filtered
+    #[cfg(feature = "temporal")]
+    fn encode_bloom_stabilization(&self, encoder: &mut wgpu::CommandEncoder, eye: Eye) -> bool {
+        let Some(layer) = &self.stabilize_layer else {
+            return false;
+        };
+        // Muted, the history starts over once it's back
+        if !self.layer_audible(Layer::Stabilization) {
+            layer.reset(eye.index());
+            return false;
+        }
+        layer.encode(encoder, &self.brightness_view, eye.index());
+        true
+    }
+
+    #[cfg(not(feature = "temporal"))]
+    fn encode_bloom_stabilization(&self, _encoder: &mut wgpu::CommandEncoder, _eye: Eye) -> bool {
+        false
+    }
+
+    /// Fold every frame into a long exposure, averaging the frames or keeping the
+    /// brightest each pixel has been, or stop with `None`. See [`Accumulation`].
+    ///
+    /// The accumulation sees the chain's output at the pipeline's resolution, before
+    /// the scopes, magnifier, zebra stripes and overlays, and is kept in 32-bit floats.
+    /// It starts over when turned on, with [`Nnpipe::reset_accumulation`] and whenever
+    /// the pipeline's resources are rebuilt, and can be saved with
+    /// [`Nnpipe::save_accumulation_exr`]. Frames are left out of it while the debug
+    /// view is on, the pipeline is bypassed or its group is muted (see
+    /// [`Nnpipe::param_groups`]).
+    #[cfg(feature = "temporal")]
+    pub fn set_accumulation(&mut self, device: &wgpu::Device, accumulation: Option<Accumulation>) {
+        let Some(accumulation) = accumulation else {
+            self.accumulation_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.accumulation_layer {
+            layer.set_settings(accumulation);
+            return;
+        }
+        self.accumulation_layer = Some(AccumulationLayer::new(
+            device,
+            &self.cache,
+            [self.width, self.height],
+            accumulation,
+            self.temporal_histories(),
+        ));
+    }
+
+    #[cfg(feature = "temporal")]
+    pub fn accumulation(&self) -> Option<Accumulation> {
+        self.accumulation_layer
+            .as_ref()
+            .map(|layer| layer.settings())
+    }
+
+    /// Start the accumulation over with the next frame.
+    #[cfg(feature = "temporal")]
+    pub fn reset_accumulation(&self) {
+        if let Some(layer) = &self.accumulation_layer {
+            layer.reset();
+        }
+    }
+
+    /// How many frames have been accumulated since the accumulation started over, or
+    /// `None` without an accumulation.
+    #[cfg(feature = "temporal")]
+    pub fn accumulated_frames(&self) -> Option<u32> {
+        self.accumulation_layer.as_ref().map(|layer| layer.frames())
+    }
+
+    /// The texture the accumulation was last written to, an Rgba32Float texture at the
+    /// pipeline's size, or `None` without an accumulation.
+    #[cfg(feature = "temporal")]
+    pub fn accumulation_view(&self) -> Option<&wgpu::TextureView> {
+        self.accumulation_layer.as_ref().map(|layer| layer.view())
+    }
+
+    /// Read the accumulation back from the GPU, as RGBA in rows from the top left.
+    /// `None` without an accumulation.
+    ///
+    /// Waits for the GPU like [`Nnpipe::read_output`].
+    #[cfg(feature = "temporal")]
+    pub async fn read_accumulation(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Option<Vec<[f32; 4]>>> {
+        match &self.accumulation_layer {
+            Some(layer) => layer.read(device, queue).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Save the accumulation to an OpenEXR file at `path`, see [`write_exr`]. Returns
+    /// whether there was an accumulation to save.
+    #[cfg(all(feature = "temporal", not(target_arch = "wasm32")))]
+    pub async fn save_accumulation_exr(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> Result<bool> {
+        let Some(pixels) = self.read_accumulation(device, queue).await? else {
+            return Ok(false);
+        };
+        write_exr(path, self.width, self.height, &pixels)?;
+        Ok(true)
+    }
+
+    // The accumulation when frames are folded into it
+    #[cfg(feature = "temporal")]
+    fn active_accumulation(&self) -> Option<&AccumulationLayer> {
+        self.accumulation_layer
+            .as_ref()
+            .filter(|_| self.debug_layer.is_none() && self.layer_audible(Layer::Accumulation))
+    }
+
+    #[cfg(feature = "temporal")]
+    fn accumulation_source_view(&self) -> Option<&wgpu::TextureView> {
+        self.active_accumulation().map(|layer| layer.source_view())
+    }
+
+    #[cfg(not(feature = "temporal"))]
+    fn accumulation_source_view(&self) -> Option<&wgpu::TextureView> {
+        None
+    }
+
+    #[cfg(feature = "temporal")]
+    fn encode_accumulation(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        eye: Eye,
+    ) {
+        if let Some(layer) = self.active_accumulation() {
+            layer.encode(encoder, target, eye.index());
+        }
+    }
+
+    #[cfg(not(feature = "temporal"))]
+    fn encode_accumulation(
+        &self,
+        _encoder: &mut wgpu::CommandEncoder,
+        _target: &wgpu::TextureView,
+        _eye: Eye,
+    ) {
+    }
+
+    /// Show the pixels around a point blown up into an inset in a corner of every
+    /// frame, or remove the inset with `None`. See [`Magnifier`].
+    ///
+    /// The inset shows the chain's output at the pipeline's resolution, and is scaled
+    /// along with the frame to targets of another size. It's left out of the debug
+    /// view and while the pipeline is bypassed. [`Nnpipe::read_magnified_pixel`]
+    /// reads the exact value at the center.
+    pub fn set_magnifier(&mut self, device: &wgpu::Device, magnifier: Option<Magnifier>) {
+        let Some(magnifier) = magnifier else {
+            self.magnifier_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.magnifier_layer {
+            layer.set_settings(magnifier);
+            return;
+        }
+        self.magnifier_layer = Some(MagnifierLayer::new(
+            device,
+            &self.cache,
+            [self.width, self.height],
+            magnifier,
+        ));
+    }
+
+    pub fn magnifier(&self) -> Option<Magnifier> {
+        self.magnifier_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    /// Read the HDR value of the pixel at the magnifier's center back from the GPU,
+    /// as the chain left it in the last frame, before the scaling and overlays. `None`
+    /// without a magnifier.
+    ///
+    /// Waits for the GPU like [`Nnpipe::read_output`], so it's for inspecting a
+    /// pixel now and then, e.g. when the cursor moves, rather than every frame.
+    pub async fn read_magnified_pixel(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Option<[f32; 4]>> {
+        match &self.magnifier_layer {
+            Some(layer) => layer.read_center(device, queue).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Stripe the pixels of every frame over a luminance and paint NaN and infinite
+    /// ones a solid color, or remove the marks with `None`. See [`Zebra`].
+    ///
+    /// The marks go on the chain's output at the pipeline's resolution, magnifier
+    /// inset included, and are scaled along with the frame to targets of another
+    /// size. They're left out of the debug view and while the pipeline is bypassed.
+    pub fn set_zebra(&mut self, device: &wgpu::Device, zebra: Option<Zebra>) {
+        let Some(zebra) = zebra else {
+            self.zebra_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.zebra_layer {
+            layer.set_settings(zebra);
+            return;
+        }
+        self.zebra_layer = Some(ZebraLayer::new(
+            device,
+            &self.cache,
+            [self.width, self.height],
+            zebra,
+        ));
+    }
+
+    pub fn zebra(&self) -> Option<Zebra> {
+        self.zebra_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    /// Threshold the scene's alpha or luminance into a matte each frame, or stop with
+    /// `None`. See [`Matte`].
+    ///
+    /// The matte is extracted before the bloom, at the pipeline's resolution, into an
+    /// Rgba16Float texture holding it in every channel. Effect passes see it as
+    /// `matte_tex` at binding 8, black while the matte is off, and the host can use
+    /// it through [`Nnpipe::matte_view`].
+    pub fn set_matte(&mut self, device: &wgpu::Device, matte: Option<Matte>) {
+        match (matte, &mut self.matte_layer) {
+            (Some(matte), Some(layer)) => {
+                layer.set_settings(matte);
+                return;
+            }
+            (None, None) => return,
+            (Some(matte), None) => {
+                self.matte_layer = Some(MatteLayer::new(
+                    device,
+                    &self.cache,
+                    &self.scene_view,
+                    matte,
+                ));
+            }
+            (None, Some(_)) => self.matte_layer = None,
+        }
+        // The passes and the distance glow bind the new matte texture
+        for index in 0..self.passes.len() {
+            self.rebind_pass(device, index);
+        }
+        if let Some(layer) = self.graph_layer.take() {
+            let _ = self.build_graph_layer(device, layer.graph().clone());
+        }
+        #[cfg(feature = "stylize")]
+        if let Some(layer) = &mut self.distance_glow_layer {
+            let matte_view = match &self.matte_layer {
+                Some(matte_layer) => matte_layer.view(),
+                None => &self.pass_resources.empty_matte_view,
+            };
+            layer.set_matte_view(device, matte_view);
+        }
+    }
+
+    pub fn matte(&self) -> Option<Matte> {
+        self.matte_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    /// The texture the matte was last extracted into, or `None` without a matte.
+    pub fn matte_view(&self) -> Option<&wgpu::TextureView> {
+        self.matte_layer.as_ref().map(|layer| layer.view())
+    }
+
+    /// Read the matte of the last frame back from the GPU, row by row. `None` without
+    /// a matte.
+    ///
+    /// Waits for the GPU like [`Nnpipe::read_output`].
+    pub async fn read_matte(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Option<Vec<f32>>> {
+        let Some(layer) = &self.matte_layer else {
+            return Ok(None);
+        };
+        let pixels = read_texture(device, queue, layer.texture()).await?;
+        Ok(Some(pixels.into_iter().map(|[value, ..]| value).collect()))
+    }
+
+    /// Compute a histogram, a waveform and a vectorscope of every frame, or stop with
+    /// `None`. See [`Scopes`].
+    ///
+    /// The scopes see the chain's output at the pipeline's resolution, before the
+    /// magnifier, zebra stripes and overlays, and are drawn into textures of their own
+    /// for the host to show, see [`Nnpipe::scope_view`]. They're left out of the debug
+    /// view and while the pipeline is bypassed.
+    pub fn set_scopes(&mut self, device: &wgpu::Device, scopes: Option<Scopes>) {
+        let Some(scopes) = scopes else {
+            self.scopes_layer = None;
+            return;
+        };
+        if let Some(layer) = self
+            .scopes_layer
+            .as_mut()
+            .filter(|layer| layer.settings().size == scopes.size)
+        {
+            layer.set_settings(scopes);
+            return;
+        }
+        self.scopes_layer = Some(ScopesLayer::new(
+            device,
+            &self.cache,
+            [self.width, self.height],
+            scopes,
+        ));
+    }
+
+    pub fn scopes(&self) -> Option<Scopes> {
+        self.scopes_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    /// The texture `scope` was last drawn into, an Rgba16Float square the size of
+    /// [`Scopes::size`], or `None` without scopes.
+    pub fn scope_view(&self, scope: Scope) -> Option<&wgpu::TextureView> {
+        self.scopes_layer
+            .as_ref()
+            .map(|layer| layer.scope_view(scope))
+    }
+
+    /// Read the bins of the scopes back from the GPU, as counted from the last frame.
+    /// `None` without scopes.
+    ///
+    /// Waits for the GPU like [`Nnpipe::read_output`], so it's for checking levels now
+    /// and then rather than every frame.
+    pub async fn read_scopes(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Option<ScopeData>> {
+        match &self.scopes_layer {
+            Some(layer) => layer.read(device, queue).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Draw alignment guides over every frame: grid lines, a center crosshair and the
+    /// title- and action-safe rectangles, or remove them with `None`. See [`Guides`].
+    ///
+    /// The guides are laid out on the target each frame is drawn to, so they frame
+    /// the output as shown, after scaling. They're drawn under the burn-in and stats
+    /// HUD, and skipped while the pipeline is bypassed.
+    pub fn set_guides(&mut self, device: &wgpu::Device, guides: Option<Guides>) {
+        let Some(guides) = guides else {
+            self.guides_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.guides_layer {
+            layer.set_settings(guides);
+            return;
+        }
+        self.guides_layer = Some(GuidesLayer::new(device, &self.cache, guides));
+    }
+
+    pub fn guides(&self) -> Option<Guides> {
+        self.guides_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    /// Write the frame number, timecode and param values into a corner of every
+    /// frame, or remove the overlay with `None`. See [`BurnIn`].
+    ///
+    /// The text is drawn last, over the scaled frame or the debug view, in a 3x5
+    /// pixel font; lowercase letters show as uppercase. Up to 12 lines of 40
+    /// characters are shown, long lines keeping their end. Nothing is drawn while
+    /// the pipeline is bypassed, and the frame number counts frames drawn with the
+    /// overlay.
+    pub fn set_burn_in(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        burn_in: Option<BurnIn>,
+    ) {
+        let Some(burn_in) = burn_in else {
+            self.burn_in_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.burn_in_layer {
+            layer.set_settings(burn_in);
+            return;
+        }
+        self.burn_in_layer = Some(BurnInLayer::new(
+            device,
+            queue,
+            &self.cache,
+            &self.pass_resources.vertex_shader,
+            burn_in,
+        ));
+    }
+
+    pub fn burn_in(&self) -> Option<&BurnIn> {
+        self.burn_in_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    /// Show the frame rate, mean and worst frame time and a graph of the recent
+    /// frame times in a corner of every frame, or remove the HUD with `None`. See
+    /// [`StatsHud`].
+    ///
+    /// Frame times are the wall-clock time between the frames the pipeline draws, so
+    /// they include whatever the host does in between, over the last 64 frames. Like
+    /// the burn-in, the HUD is drawn last and skipped while the pipeline is bypassed.
+    pub fn set_stats_hud(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        hud: Option<StatsHud>,
+    ) {
+        let Some(hud) = hud else {
+            self.hud_layer = None;
+            return;
+        };
+        if let Some(layer) = &mut self.hud_layer {
+            layer.set_settings(hud);
+            return;
+        }
+        self.hud_layer = Some(HudLayer::new(
+            device,
+            queue,
+            &self.cache,
+            &self.pass_resources.vertex_shader,
+            hud,
+        ));
+    }
+
+    pub fn stats_hud(&self) -> Option<StatsHud> {
+        self.hud_layer.as_ref().map(|layer| layer.settings())
+    }
+
+    /// The stages of the chain the stats HUD lists and their last measured times in
+    /// milliseconds: the brightness extraction, the blur, the composite, each
+    /// enabled effect pass by its label, the pass graph, and the rest of the chain
+    /// as `"Finish"`. Empty unless the HUD lists its [`stages`](StatsHud::stages).
+    ///
+    /// With timestamp queries the times are the GPU's, a frame or two behind the
+    /// latest one.
+    pub fn stage_times(&self) -> Vec<(String, f32)> {
+        self.hud_layer
+            .as_ref()
+            .map_or_else(Vec::new, |layer| layer.stage_times())
+    }
+
+    /// Show the default [`StatsHud`] if it's hidden, or hide it, e.g. from a key
+    /// binding. Returns whether it's shown now.
+    pub fn toggle_stats_hud(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let hud = self.hud_layer.is_none().then(StatsHud::default);
+        self.set_stats_hud(device, queue, hud);
+        self.hud_layer.is_some()
+    }
+
+    pub fn bloom_scale(&self) -> f32 {
+        self.bloom_scale
+    }
+
+    /// The quality settings the pipeline was created with or last switched to. Later
+    /// changes to the bloom scale, blur radius or upsample filter don't update them.
+    pub fn quality(&self) -> QualityProfile {
+        self.quality
+    }
+
+    /// Switch to another [`QualityPreset`] or [`QualityProfile`], e.g. from a
+    /// settings menu. Rebuilds the pipeline's resources, and resets the bloom scale,
+    /// blur radius cap and upsample filter to the profile's.
+    ///
+    /// Fails, leaving the pipeline as it was, if the profile's bloom format can't be
+    /// rendered to.
+    pub fn set_quality(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        quality: impl Into<QualityProfile>,
+    ) -> Result<()> {
+        let quality = quality.into();
+        check_render_format(device, quality.bloom_format)?;
+        self.quality = quality;
+        self.bloom_scale = quality.bloom_scale.clamp(0.1, 1.0);
+        self.rebuild(device, queue, self.width, self.height);
+        self.set_max_blur_radius(quality.max_blur_radius);
+        self.set_upsample_filter(quality.upsample_filter);
+        Ok(())
+    }
+
+    /// Run the bloom at `scale` times the pipeline's resolution (e.g. 0.5 for half
+    /// resolution). Blur radii are in bloom texels, so the glow also widens as the
+    /// scale drops. Rebuilds the pipeline's resources.
+    pub fn set_bloom_scale(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scale: f32) {
+        self.bloom_scale = scale.clamp(0.1, 1.0);
+        self.rebuild(device, queue, self.width, self.height);
+    }
+
+    pub fn equirectangular(&self) -> bool {
+        self.equirectangular
+    }
+
+    /// Treat frames as 360° equirectangular panoramas, e.g. to post-process renders
+    /// for VR or domes. The shared sampler wraps around horizontally, so the bloom's
+    /// blur and passes sampling through it carry across the wrap line without a
+    /// seam, and the bloom's blur widens towards the poles to keep its angular size.
+    /// Nothing crosses the poles themselves. Rebuilds the pipeline's resources.
+    pub fn set_equirectangular(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        enabled: bool,
+    ) {
+        if enabled != self.equirectangular {
+            self.equirectangular = enabled;
+            self.rebuild(device, queue, self.width, self.height);
+        }
+    }
+
+    /// Draw the scenes of [`Nnpipe::process`] and [`Nnpipe::render`] `gutter` pixels
+    /// beyond every edge of the frame, and bloom them at that size before cropping
+    /// back to the frame, or stop with 0.
+    ///
+    /// Without a gutter the blur clamps at the frame's edges, so the glow of lights
+    /// on an edge leans along it and lights just outside the frame don't glow into
+    /// it. [`Nnpipe::bloom_reach`] is the gutter that covers the widest blur. The
+    /// nannou `Draw` sees a larger window with the same origin and scale; the
+    /// scenes of [`Nnpipe::process_with`] and [`Nnpipe::process_texture`] aren't
+    /// padded. The FFT bloom doesn't use the gutter, and the stencil exclusion
+    /// doesn't keep excluded areas out of a padded bloom.
+    ///
+    /// Fails if the pipeline is multisampled, or the padded textures don't fit the
+    /// device.
+    pub fn set_bloom_gutter(&mut self, device: &wgpu::Device, gutter: u32) -> Result<()> {
+        if gutter == 0 {
+            self.gutter_layer = None;
+            return Ok(());
+        }
+        if self.samples > 1 {
+            return Err(NnpipeError::IncompatibleSampleCount(self.samples));
+        }
+        check_size(device, self.width + 2 * gutter, self.height + 2 * gutter)?;
+        let bloom_size = self.brightness_view.size();
+        let layer = GutterLayer::new(
+            device,
+            gutter,
+            [self.width, self.height],
+            bloom_size,
+            self.quality.bloom_format,
+            |scene_view, exclusion_view, resolution_buffer| {
+                create_brightness_bind_group(
+                    device,
+                    &self.brightness_bind_group_layout,
+                    scene_view,
+                    exclusion_view,
+                    &self.sampler,
+                    &self.threshold_buffer,
+                    resolution_buffer,
+                    &self.sanitize_buffer,
+                )
+            },
+            |source_view, horizontal| {
+                let direction_buffer = if horizontal {
+                    &self.blur_h_buffer
+                } else {
+                    &self.blur_v_buffer
+                };
+                create_blur_bind_group(
+                    device,
+                    &self.blur_bind_group_layout,
+                    source_view,
+                    &self.sampler,
+                    [
+                        direction_buffer,
+                        &self.adaptive_scaling_buffer,
+                        &self.max_radius_buffer,
+                    ],
+                )
+            },
         );
+        self.gutter_layer = Some(layer);
+        Ok(())
     }
 
-    pub fn set_max_blur_radius(&mut self, queue: &wgpu::Queue, radius: f32) {
-        self.max_blur_radius = radius;
-        queue.write_buffer(&self.max_radius_buffer, 0, bytemuck::cast_slice(&[radius]));
+    pub fn bloom_gutter(&self) -> u32 {
+        self.gutter_layer.as_ref().map_or(0, |layer| layer.gutter())
     }
 
-    pub fn set_intensity_curve(&mut self, queue: &wgpu::Queue, curve: f32) {
-        self.intensity_curve = curve;
-        queue.write_buffer(
-            &self.intensity_curve_buffer,
-            0,
-            bytemuck::cast_slice(&[curve]),
+    /// The farthest the blur spreads light, in pixels of the pipeline's resolution,
+    /// from the max blur radius, blur strengths, stretch and angle. As a bloom gutter
+    /// it lets lights anywhere outside the frame that could reach into it glow.
+    pub fn bloom_reach(&self) -> u32 {
+        let (horizontal, vertical) = blur_directions(
+            self.horizontal_blur_strength,
+            self.vertical_blur_strength,
+            self.bloom_stretch,
+            self.blur_angle,
         );
+        let bloom_scale = self.brightness_view.size()[0] as f32 / self.width as f32;
+        let reach = |axis: usize| {
+            (horizontal[axis].abs() + vertical[axis].abs()) * self.max_blur_radius / bloom_scale
+        };
+        reach(0).max(reach(1)).ceil() as u32
+    }
+}
+
+// Steps of the two blur passes along the rotated axes. The anamorphic stretch trades
+// one axis for the other, keeping the glow's area about the same.
+fn blur_directions(
+    horizontal: f32,
+    vertical: f32,
+    stretch: f32,
+    angle: f32,
+) -> ([f32; 2], [f32; 2]) {
+    let scale = stretch.max(0.01).sqrt();
+    let (sin, cos) = angle.sin_cos();
+    let horizontal = horizontal * scale;
+    let vertical = vertical / scale;
+    (
+        [cos * horizontal, sin * horizontal],
+        [-sin * vertical, cos * vertical],
+    )
+}
+
+// Contents of the threshold uniform: the per-channel thresholds and a flag to use
+// them, or the luminance threshold and no flag
+fn threshold_data(threshold: f32, channel_thresholds: Option<[f32; 3]>) -> [f32; 4] {
+    match channel_thresholds {
+        Some([r, g, b]) => [r, g, b, 1.0],
+        None => [threshold, threshold, threshold, 0.0],
     }
 }
 
+// A size scaled by the render scale, at least a pixel per side
+fn scaled_size(size: [u32; 2], scale: f32) -> [u32; 2] {
+    size.map(|side| ((side as f32 * scale).round() as u32).max(1))
+}
+
+// Bytes per row of an output readback: Rgba16Float, padded to the copy alignment
+fn padded_row_bytes(width: u32) -> u32 {
+    (width * 8).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+}
+
+// Read an Rgba16Float texture back from the GPU
+pub(crate) async fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> Result<Vec<[f32; 4]>> {
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: readback_size(texture.size()),
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback"),
+    });
+    copy_readback(&mut encoder, texture, &buffer);
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    device.poll(wgpu::Maintain::Wait);
+    // The buffer is only dropped with the device if the sender never ran
+    receiver.await.unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+    let pixels = decode_readback(&slice.get_mapped_range(), texture.size()[0]);
+    buffer.unmap();
+    Ok(pixels)
+}
+
+// Record a copy of `texture` into `buffer`, of `readback_size` bytes
+fn copy_readback(
+    encoder: &mut wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+    buffer: &wgpu::Buffer,
+) {
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes(texture.size()[0])),
+                rows_per_image: None,
+            },
+        },
+        texture.extent(),
+    );
+}
+
+// Size of a buffer holding an output readback of `size`
+pub(crate) fn readback_size(size: [u32; 2]) -> wgpu::BufferAddress {
+    (padded_row_bytes(size[0]) * size[1]) as wgpu::BufferAddress
+}
+
+// Decode the rows of an output readback into RGBA pixels
+pub(crate) fn decode_readback(data: &[u8], width: u32) -> Vec<[f32; 4]> {
+    data.chunks(padded_row_bytes(width) as usize)
+        .flat_map(|row| row[..(width * 8) as usize].chunks_exact(8))
+        .map(|pixel| {
+            let channel = |i: usize| f16_to_f32(u16::from_le_bytes([pixel[i], pixel[i + 1]]));
+            [channel(0), channel(2), channel(4), channel(6)]
+        })
+        .collect()
+}
+
+// Encode linear RGBA pixels as 8-bit sRGB for image files, alpha staying linear
+pub(crate) fn encode_srgb8(pixels: &[[f32; 4]]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|&[r, g, b, a]| [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a])
+        .map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect()
+}
+
+// Inverse of the palette's sRGB decoding in effects.rs, which needs `stylize`
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Decode a half float, as stored in the pipeline's textures
+fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    let magnitude = match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    };
+    sign * magnitude
+}
+
 // Helper function to create render texture
 fn create_render_texture(
     device: &wgpu::Device,
     width: u32,
     height: u32,
     samples: u32,
+    format: wgpu::TextureFormat,
 ) -> wgpu::Texture {
     wgpu::TextureBuilder::new()
         .size([width, height])
-        .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+        .usage(
+            // The bloom gutter copies into the scene and bloom textures, and out of the
+            // brightness texture
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+        )
         .sample_count(samples)
-        .format(wgpu::TextureFormat::Rgba16Float)
+        .format(format)
         .build(device)
 }
 
-// Helper function to create render pipeline
+// Helper function to create the brightness bind group for a scene view
+#[allow(clippy::too_many_arguments)]
+fn create_brightness_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    scene_view: &wgpu::TextureView,
+    exclusion_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    threshold_buffer: &wgpu::Buffer,
+    resolution_buffer: &wgpu::Buffer,
+    sanitize_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Brightness Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(scene_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(
+                    threshold_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(exclusion_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Buffer(
+                    resolution_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Buffer(sanitize_buffer.as_entire_buffer_binding()),
+            },
+        ],
+    })
+}
+
+// Helper function to create a blur pass bind group reading `source_view`, from its
+// direction, adaptive scaling and max radius buffers
+fn create_blur_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    source_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    buffers: [&wgpu::Buffer; 3],
+) -> wgpu::BindGroup {
+    let [direction_buffer, adaptive_scaling_buffer, max_radius_buffer] = buffers;
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Blur Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(
+                    direction_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    adaptive_scaling_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Buffer(
+                    max_radius_buffer.as_entire_buffer_binding(),
+                ),
+            },
+        ],
+    })
+}
+
+// Helper function to create the composite bind group for a scene view
+#[allow(clippy::too_many_arguments)]
+fn create_composite_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    scene_view: &wgpu::TextureView,
+    bloom_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    intensity_buffer: &wgpu::Buffer,
+    intensity_curve_buffer: &wgpu::Buffer,
+    alpha_mode_buffer: &wgpu::Buffer,
+    sanitize_buffer: &wgpu::Buffer,
+    resolution_buffer: &wgpu::Buffer,
+    upsample_filter_buffer: &wgpu::Buffer,
+    bloom_color_buffer: &wgpu::Buffer,
+    reflection_views: [&wgpu::TextureView; 2],
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Composite Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(scene_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(bloom_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    intensity_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Buffer(
+                    intensity_curve_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Buffer(
+                    alpha_mode_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Buffer(
+                    resolution_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::Buffer(
+                    upsample_filter_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::Buffer(
+                    bloom_color_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 9,
+                resource: wgpu::BindingResource::TextureView(reflection_views[0]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 10,
+                resource: wgpu::BindingResource::TextureView(reflection_views[1]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 11,
+                resource: wgpu::BindingResource::Buffer(sanitize_buffer.as_entire_buffer_binding()),
+            },
+        ],
+    })
+}
+
+// Helper function to create (or reuse) a render pipeline
 fn create_render_pipeline(
     device: &wgpu::Device,
+    cache: &PipelineCache,
     layout: &wgpu::PipelineLayout,
     shader: &wgpu::ShaderModule,
     label: &str,
     format: wgpu::TextureFormat,
+) -> Arc<wgpu::RenderPipeline> {
+    cache.pipeline(
+        device,
+        layout,
+        shader,
+        shader,
+        label,
+        format,
+        Some(wgpu::BlendState::ALPHA_BLENDING),
+    )
+}
+
+// Helper function to create a fullscreen-triangle pipeline with separate vertex and
+// fragment modules
+pub(crate) fn create_fullscreen_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    vertex_shader: &wgpu::ShaderModule,
+    fragment_shader: &wgpu::ShaderModule,
+    label: &str,
+    format: wgpu::TextureFormat,
+    blend: Option<wgpu::BlendState>,
 ) -> wgpu::RenderPipeline {
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some(label),
         layout: Some(layout),
         vertex: wgpu::VertexState {
-            module: shader,
+            module: vertex_shader,
             entry_point: "vs_main",
             buffers: &[],
         },
         fragment: Some(wgpu::FragmentState {
-            module: shader,
+            module: fragment_shader,
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                blend,
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -754,3 +5183,69 @@ fn create_render_pipeline(
         multiview: None,
     })
 }
+
+// Helper function to create the pipeline that turns the scene's stencil into the
+// exclusion mask. Its stencil test passes wherever the stencil isn't zero.
+fn create_exclusion_mask_pipeline(
+    device: &wgpu::Device,
+    cache: &PipelineCache,
+    depth_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let vertex_shader = cache.shader(
+        device,
+        "Fullscreen Shader",
+        include_str!("shaders/fullscreen.wgsl"),
+    );
+    let mask_shader = cache.shader(
+        device,
+        "Exclusion Mask Shader",
+        include_str!("shaders/exclusion_mask.wgsl"),
+    );
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Exclusion Mask Pipeline Layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+
+    let stencil_face = wgpu::StencilFaceState {
+        compare: wgpu::CompareFunction::NotEqual,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op: wgpu::StencilOperation::Keep,
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Exclusion Mask Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &vertex_shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &mask_shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::R8Unorm,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: depth_format,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState {
+                front: stencil_face,
+                back: stencil_face,
+                read_mask: 0xff,
+                write_mask: 0,
+            },
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,788 @@
+// src/output.rs
+//
+// Outputs draw the processed frame into windows or other targets. Each output has
+// its own format, resolution (taken from the target view) and final adjustments,
+// so one pipeline can feed a control monitor and several projectors.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::projection::OutputProjection;
+use crate::upload::{UniformBuffer, Uploader};
+use crate::warp::{OutputWarp, MAX_MESH_SIZE};
+
+// Tessellation of a meshed warp along either side, so the frame bends smoothly
+// between the mesh's points
+const MESH_SUBDIVISIONS: u32 = 64;
+
+/// Final per-output adjustments, applied after the effect chain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputAdjustments {
+    pub exposure: f32,
+    pub gamma: f32,
+    pub saturation: f32,
+}
+
+impl Default for OutputAdjustments {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            gamma: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+/// Live transform of the frame within an output, for projection alignment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputTransform {
+    /// Scale around the frame's center; values above 1.0 zoom in.
+    pub zoom: f32,
+    /// Offset in fractions of the frame's size; positive values move it right and down.
+    pub pan: [f32; 2],
+    /// Clockwise rotation in radians.
+    pub rotation: f32,
+    /// Visible part of the frame as normalized `[x, y, width, height]`. Everything
+    /// outside it shows the output's background.
+    pub crop: [f32; 4],
+}
+
+impl Default for OutputTransform {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: [0.0, 0.0],
+            rotation: 0.0,
+            crop: [0.0, 0.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// How the frame is fitted into an output whose aspect ratio differs from the pipeline's.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFit {
+    /// Show the whole frame without distortion, with bars wherever it doesn't fill
+    /// the target.
+    #[default]
+    Contain,
+    /// Fill the whole target, distorting the frame.
+    Stretch,
+    /// Match the target's width, with bars above and below (or cropping if the
+    /// frame is taller than the target).
+    Letterbox,
+    /// Match the target's height, with bars left and right (or cropping if the
+    /// frame is wider than the target).
+    Pillarbox,
+    /// Fill the whole target without distortion, cropping whatever doesn't fit.
+    Cover,
+}
+
+impl OutputFit {
+    // Value of the output shader's fit uniform
+    fn shader_value(self) -> f32 {
+        match self {
+            OutputFit::Stretch => 0.0,
+            OutputFit::Letterbox => 1.0,
+            OutputFit::Pillarbox => 2.0,
+            OutputFit::Cover => 3.0,
+            OutputFit::Contain => 4.0,
+        }
+    }
+}
+
+/// How the frame's pixels are resampled into an output of a different resolution.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputScaling {
+    /// Bilinear filtering, smooth at any scale.
+    #[default]
+    Linear,
+    /// Point sampling, so every frame pixel shows as a solid block.
+    Nearest,
+    /// Point sampling with the fit's scale rounded down to a whole number (when
+    /// enlarging), so every frame pixel covers the same number of target pixels.
+    /// Keeps pixel art crisp, at the cost of wider bars.
+    Integer,
+}
+
+impl OutputScaling {
+    // Value of the output shader's scaling uniform
+    fn shader_value(self) -> f32 {
+        match self {
+            OutputScaling::Linear => 0.0,
+            OutputScaling::Nearest => 1.0,
+            OutputScaling::Integer => 2.0,
+        }
+    }
+}
+
+/// Shape an output is cut to, e.g. for round or rounded projection surfaces.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum MaskShape {
+    /// The whole target.
+    #[default]
+    None,
+    /// The whole target with its corners rounded, `radius` target pixels across.
+    RoundedRect { radius: f32 },
+    /// The largest ellipse fitting the target.
+    Ellipse,
+    /// The largest circle fitting the target, centered.
+    Circle,
+    /// The texture set with [`Output::set_mask_texture`], stretched over the target.
+    /// Its red channel holds coverage from 0 to 1, or with `distance`, the signed
+    /// distance to the shape's edge in target pixels, negative inside (in a float
+    /// format such as `R16Float`). Shows the whole target while no texture is set.
+    Texture { distance: bool },
+}
+
+/// Mask shaping an output for non-rectangular surfaces. Everything outside the
+/// shape shows the output's background.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OutputMask {
+    pub shape: MaskShape,
+    /// Width of the soft edge inside the shape, in target pixels. Coverage textures
+    /// keep their own edges.
+    pub feather: f32,
+    /// Show the frame outside the shape instead of inside it.
+    pub invert: bool,
+}
+
+impl OutputMask {
+    // Values of the output shader's mask shape and radius uniforms
+    fn shader_values(self, has_texture: bool) -> [f32; 2] {
+        match self.shape {
+            MaskShape::None => [0.0, 0.0],
+            MaskShape::RoundedRect { radius } => [1.0, radius.max(0.0)],
+            MaskShape::Ellipse => [2.0, 0.0],
+            MaskShape::Circle => [3.0, 0.0],
+            MaskShape::Texture { .. } if !has_texture => [0.0, 0.0],
+            MaskShape::Texture { distance: false } => [4.0, 0.0],
+            MaskShape::Texture { distance: true } => [5.0, 0.0],
+        }
+    }
+}
+
+pub struct Output {
+    format: wgpu::TextureFormat,
+    adjustments: OutputAdjustments,
+    fit: OutputFit,
+    scaling: OutputScaling,
+    background: [f32; 4],
+    transform: OutputTransform,
+    mask: OutputMask,
+    warp: OutputWarp,
+    projection: OutputProjection,
+
+    pipeline: Arc<wgpu::RenderPipeline>,
+    uniform_buffer: UniformBuffer,
+    // The warp mesh's points, one per vec4
+    mesh_buffer: UniformBuffer,
+    bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    bind_group: wgpu::BindGroup,
+    mask_layout: Arc<wgpu::BindGroupLayout>,
+    // Reads the mask texture, or a blank one while none is set
+    mask_bind_group: Arc<wgpu::BindGroup>,
+    has_mask_texture: bool,
+    device: wgpu_upstream::Id<wgpu::Device>,
+}
+
+impl Output {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        source_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let adjustments = OutputAdjustments::default();
+        let fit = OutputFit::default();
+        let scaling = OutputScaling::default();
+        let background = [0.0, 0.0, 0.0, 1.0];
+        let transform = OutputTransform::default();
+        let mask = OutputMask::default();
+        let [mask_shape, mask_radius] = mask.shader_values(false);
+        let warp = OutputWarp::default();
+        let projection = OutputProjection::default();
+        let ([projection_mode, dome_half_fov, source_tangent], projection_rotation) =
+            projection.shader_values();
+
+        let [source_width, source_height] = source_view.size();
+        let uniforms = OutputUniforms {
+            target_size: [1.0, 1.0],
+            exposure: adjustments.exposure,
+            gamma: adjustments.gamma,
+            saturation: adjustments.saturation,
+            fit: fit.shader_value(),
+            source_size: [source_width as f32, source_height as f32],
+            background,
+            pan: transform.pan,
+            zoom: transform.zoom,
+            rotation: transform.rotation,
+            crop: transform.crop,
+            scaling: scaling.shader_value(),
+            mask_shape,
+            mask_radius,
+            mask_feather: mask.feather,
+            mask_invert: 0.0,
+            warp_subdivisions: 1.0,
+            mesh_size: [0.0; 2],
+            homography: identity_rows().map(|[x, y, z]| [x, y, z, 0.0]),
+            projection: projection_mode,
+            dome_half_fov,
+            source_tangent,
+            _padding: 0.0,
+            projection_rotation: projection_rotation.map(|[x, y, z]| [x, y, z, 0.0]),
+        };
+
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Output Uniform Buffer",
+            bytemuck::bytes_of(&uniforms),
+        );
+        let mesh_buffer = UniformBuffer::new(
+            device,
+            "Output Mesh Buffer",
+            &vec![0; (MAX_MESH_SIZE * MAX_MESH_SIZE) as usize * 16],
+        );
+
+        let shader = cache.shader(
+            device,
+            "Output Shader",
+            &expand(include_str!("shaders/output.wgsl")),
+        );
+
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Output Bind Group Layout",
+            &[
+                // Source texture binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                // Sampler binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Output uniform binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Warp mesh binding
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            source_view,
+            sampler,
+            &uniform_buffer,
+            &mesh_buffer,
+        );
+
+        let mask_layout = cache.bind_group_layout(
+            device,
+            "Output Mask Bind Group Layout",
+            &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        );
+        let mask_bind_group = create_mask_bind_group(device, &mask_layout, &blank_mask(device));
+
+        let pipeline_layout = cache.grouped_pipeline_layout(
+            device,
+            "Output Pipeline Layout",
+            &[&bind_group_layout, &mask_layout],
+        );
+
+        // The shader has a vertex stage of its own, which moves the frame's corners
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Output Pipeline",
+            format,
+            None,
+        );
+
+        Self {
+            format,
+            adjustments,
+            fit,
+            scaling,
+            background,
+            transform,
+            mask,
+            warp,
+            projection,
+            pipeline,
+            uniform_buffer,
+            mesh_buffer,
+            bind_group_layout,
+            bind_group,
+            mask_layout,
+            mask_bind_group,
+            has_mask_texture: false,
+            device: device.global_id(),
+        }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn adjustments(&self) -> OutputAdjustments {
+        self.adjustments
+    }
+
+    pub fn set_adjustments(&mut self, adjustments: OutputAdjustments) {
+        self.adjustments = adjustments;
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, exposure),
+            bytemuck::cast_slice(&[
+                adjustments.exposure,
+                adjustments.gamma,
+                adjustments.saturation,
+            ]),
+        );
+    }
+
+    pub fn fit(&self) -> OutputFit {
+        self.fit
+    }
+
+    pub fn set_fit(&mut self, fit: OutputFit) {
+        self.fit = fit;
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, fit),
+            bytemuck::cast_slice(&[fit.shader_value()]),
+        );
+    }
+
+    pub fn scaling(&self) -> OutputScaling {
+        self.scaling
+    }
+
+    /// Choose how the frame is resampled, e.g. [`OutputScaling::Integer`] for pixel
+    /// art processed at a low resolution.
+    pub fn set_scaling(&mut self, scaling: OutputScaling) {
+        self.scaling = scaling;
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, scaling),
+            bytemuck::cast_slice(&[scaling.shader_value()]),
+        );
+    }
+
+    /// Color of the bars left by the fit mode and of everything outside the crop rect.
+    pub fn background(&self) -> [f32; 4] {
+        self.background
+    }
+
+    pub fn set_background(&mut self, background: [f32; 4]) {
+        self.background = background;
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, background),
+            bytemuck::cast_slice(&background),
+        );
+    }
+
+    pub fn transform(&self) -> OutputTransform {
+        self.transform
+    }
+
+    /// Set the pan/zoom/rotation/crop transform. Cheap enough to call every frame.
+    pub fn set_transform(&mut self, transform: OutputTransform) {
+        self.transform = transform;
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, pan),
+            bytemuck::cast_slice(&[
+                transform.pan[0],
+                transform.pan[1],
+                transform.zoom,
+                transform.rotation,
+                transform.crop[0],
+                transform.crop[1],
+                transform.crop[2],
+                transform.crop[3],
+            ]),
+        );
+    }
+
+    pub fn mask(&self) -> OutputMask {
+        self.mask
+    }
+
+    /// Shape the output with a mask, in target pixels, so it fits a round or
+    /// otherwise non-rectangular surface. Applies after the fit and transform.
+    pub fn set_mask(&mut self, mask: OutputMask) {
+        self.mask = mask;
+        self.write_mask();
+    }
+
+    /// Whether a texture is set for [`MaskShape::Texture`].
+    pub fn has_mask_texture(&self) -> bool {
+        self.has_mask_texture
+    }
+
+    /// Set or clear the texture read by [`MaskShape::Texture`] masks. It's stretched
+    /// over the target and sampled with linear filtering, so its format must be
+    /// filterable.
+    pub fn set_mask_texture(&mut self, device: &wgpu::Device, view: Option<&wgpu::TextureView>) {
+        self.has_mask_texture = view.is_some();
+        self.mask_bind_group = match view {
+            Some(view) => create_mask_bind_group(device, &self.mask_layout, view),
+            None => create_mask_bind_group(device, &self.mask_layout, &blank_mask(device)),
+        };
+        self.write_mask();
+    }
+
+    fn write_mask(&self) {
+        let [shape, radius] = self.mask.shader_values(self.has_mask_texture);
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, mask_shape),
+            bytemuck::cast_slice(&[
+                shape,
+                radius,
+                self.mask.feather.max(0.0),
+                if self.mask.invert { 1.0 } else { 0.0 },
+            ]),
+        );
+    }
+
+    pub fn warp(&self) -> &OutputWarp {
+        &self.warp
+    }
+
+    /// Warp the output for projection mapping: pin the frame's corners to correct a
+    /// projector's keystone, and bend it with a mesh for uneven surfaces. The fit,
+    /// transform and mask apply within the warped frame, and the target around it
+    /// shows the background. Meshes of an unsupported size or with a point missing
+    /// are left out, as are corner pins with three corners in line.
+    pub fn set_warp(&mut self, warp: OutputWarp) {
+        let homography = warp.homography().unwrap_or_else(identity_rows);
+        let (subdivisions, mesh_size) = match warp.valid_mesh() {
+            Some(mesh) => {
+                let points: Vec<[f32; 4]> =
+                    mesh.points.iter().map(|&[x, y]| [x, y, 0.0, 0.0]).collect();
+                self.mesh_buffer.write(0, bytemuck::cast_slice(&points));
+                (MESH_SUBDIVISIONS, [mesh.columns as f32, mesh.rows as f32])
+            }
+            None => (1, [0.0; 2]),
+        };
+        let mut values = vec![subdivisions as f32, mesh_size[0], mesh_size[1]];
+        values.extend(
+            homography
+                .iter()
+                .flat_map(|row| [row[0], row[1], row[2], 0.0]),
+        );
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, warp_subdivisions),
+            bytemuck::cast_slice(&values),
+        );
+        self.warp = warp;
+    }
+
+    pub fn projection(&self) -> OutputProjection {
+        self.projection
+    }
+
+    /// Lay the frame out for a dome, e.g. as a 180° domemaster of a flat view or a
+    /// 360° panorama. The fit no longer applies; the transform and crop do, to the
+    /// frame as looked up.
+    pub fn set_projection(&mut self, projection: OutputProjection) {
+        self.projection = projection;
+        let (values, rotation) = projection.shader_values();
+        let mut values = vec![values[0], values[1], values[2], 0.0];
+        values.extend(
+            rotation
+                .iter()
+                .flat_map(|row| [row[0], row[1], row[2], 0.0]),
+        );
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, projection),
+            bytemuck::cast_slice(&values),
+        );
+    }
+
+    // Take over every user setting of `other`, e.g. when outputs are rebuilt. The
+    // mask texture only carries over on the same device.
+    pub(crate) fn copy_settings(&mut self, other: &Output) {
+        self.set_adjustments(other.adjustments);
+        self.set_fit(other.fit);
+        self.set_scaling(other.scaling);
+        self.set_background(other.background);
+        self.set_transform(other.transform);
+        if other.has_mask_texture && other.device == self.device {
+            self.mask_bind_group = other.mask_bind_group.clone();
+            self.has_mask_texture = true;
+        }
+        self.set_mask(other.mask);
+        self.set_warp(other.warp.clone());
+        self.set_projection(other.projection);
+    }
+
+    // Draw the processed frame into `view`, which must have this output's format
+    pub(crate) fn present(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        uploader: &Uploader,
+        view: &wgpu::TextureView,
+    ) {
+        let ce_desc = wgpu::CommandEncoderDescriptor {
+            label: Some("Output"),
+        };
+        let mut encoder = device.create_command_encoder(&ce_desc);
+        self.encode(device, uploader, &mut encoder, view);
+        queue.submit(Some(encoder.finish()));
+        uploader.recall();
+    }
+
+    // A bind group like the output's own, reading `source_view` instead of the
+    // pipeline's output texture, whose size it must have
+    pub(crate) fn source_bind_group(
+        &self,
+        device: &wgpu::Device,
+        source_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        create_bind_group(
+            device,
+            &self.bind_group_layout,
+            source_view,
+            sampler,
+            &self.uniform_buffer,
+            &self.mesh_buffer,
+        )
+    }
+
+    // Draw the frame of `bind_group`, from `source_bind_group`, into `region` of
+    // `view`, as x, y, width and height, clearing all of `view` first if `clear`
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn present_region(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        uploader: &Uploader,
+        view: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+        region: [u32; 4],
+        clear: bool,
+    ) {
+        let ce_desc = wgpu::CommandEncoderDescriptor {
+            label: Some("Output"),
+        };
+        let mut encoder = device.create_command_encoder(&ce_desc);
+        self.encode_region(
+            device,
+            uploader,
+            &mut encoder,
+            view,
+            bind_group,
+            region,
+            clear,
+        );
+        queue.submit(Some(encoder.finish()));
+        uploader.recall();
+    }
+
+    // Record the output pass into `encoder`, after copies of the uniforms changed
+    // since it was last encoded. The caller recalls `uploader` once it submits.
+    pub(crate) fn encode(
+        &self,
+        device: &wgpu::Device,
+        uploader: &Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let [width, height] = view.size();
+        let region = [0, 0, width, height];
+        self.encode_region(
+            device,
+            uploader,
+            encoder,
+            view,
+            &self.bind_group,
+            region,
+            true,
+        );
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "output", skip_all, fields(format = ?self.format)))]
+    #[allow(clippy::too_many_arguments)]
+    fn encode_region(
+        &self,
+        device: &wgpu::Device,
+        uploader: &Uploader,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+        [x, y, width, height]: [u32; 4],
+        clear: bool,
+    ) {
+        self.uniform_buffer.write(
+            std::mem::offset_of!(OutputUniforms, target_size),
+            bytemuck::cast_slice(&[width as f32, height as f32]),
+        );
+        uploader.upload(device, encoder, [&self.uniform_buffer, &self.mesh_buffer]);
+
+        // Whatever the warped frame leaves uncovered shows the background
+        let [r, g, b, a] = self.background.map(|channel| channel as f64);
+        let load = match clear {
+            true => wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a }),
+            false => wgpu::LoadOp::Load,
+        };
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Output pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0.0, 1.0);
+        pass.set_scissor_rect(x, y, width, height);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.set_bind_group(1, &self.mask_bind_group, &[]);
+        // Two triangles per cell of the warp's tessellation
+        let subdivisions = match self.warp.valid_mesh() {
+            Some(_) => MESH_SUBDIVISIONS,
+            None => 1,
+        };
+        pass.draw(0..subdivisions * subdivisions * 6, 0..1);
+    }
+}
+
+// Mirrors `OutputUniforms` in output.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct OutputUniforms {
+    target_size: [f32; 2],
+    exposure: f32,
+    gamma: f32,
+    saturation: f32,
+    fit: f32,
+    source_size: [f32; 2],
+    background: [f32; 4],
+    pan: [f32; 2],
+    zoom: f32,
+    rotation: f32,
+    crop: [f32; 4],
+    scaling: f32,
+    mask_shape: f32,
+    mask_radius: f32,
+    mask_feather: f32,
+    mask_invert: f32,
+    warp_subdivisions: f32,
+    mesh_size: [f32; 2],
+    // Rows of the corner pin's homography, padded to vec4s
+    homography: [[f32; 4]; 3],
+    projection: f32,
+    dome_half_fov: f32,
+    source_tangent: f32,
+    _padding: f32,
+    // Rows of the fisheye's orientation, padded to vec4s
+    projection_rotation: [[f32; 4]; 3],
+}
+
+fn identity_rows() -> [[f32; 3]; 3] {
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+}
+
+// Stands in for the mask texture while none is set
+fn blank_mask(device: &wgpu::Device) -> wgpu::TextureView {
+    wgpu::TextureBuilder::new()
+        .size([1, 1])
+        .dimension(wgpu::TextureDimension::D2)
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING)
+        .format(wgpu::TextureFormat::R8Unorm)
+        .build(device)
+        .view()
+        .build()
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    source_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+    mesh_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Output Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(source_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(uniform_buffer.as_entire_buffer_binding()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(mesh_buffer.as_entire_buffer_binding()),
+            },
+        ],
+    })
+}
+
+fn create_mask_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+) -> Arc<wgpu::BindGroup> {
+    Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Output Mask Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(view),
+        }],
+    }))
+}
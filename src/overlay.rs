@@ -0,0 +1,283 @@
+// src/overlay.rs
+//
+// Text overlays
+//
+// The burn-in and the stats HUD both draw a few lines of text into a corner of the
+// final frame. They share this renderer: a tiny 3x5 pixel font kept in an atlas
+// texture, and one fullscreen triangle that discards everything outside the text's
+// box. The text, and an optional bar graph below it, is packed into a uniform on the
+// CPU each frame and uploaded with the other uniforms.
+
+use std::sync::Arc;
+
+use nannou::wgpu;
+
+use crate::burn_in::BurnInCorner;
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+// Limits of the text and graph, as sized in the shader's uniform
+pub(crate) const MAX_LINES: usize = 12;
+pub(crate) const MAX_COLUMNS: usize = 40;
+pub(crate) const MAX_GRAPH_LEN: usize = 64;
+const UNIFORM_LEN: usize = 8 + MAX_LINES * MAX_COLUMNS / 4 + MAX_GRAPH_LEN;
+
+// Glyphs of ASCII 32 to 95 (lowercase letters are drawn as uppercase), rows of 3
+// pixels from the top, high bit on the left. Characters not listed are blank.
+const GLYPHS: &[(char, [u8; 5])] = &[
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('\'', [0b010, 0b010, 0b000, 0b000, 0b000]),
+    ('(', [0b001, 0b010, 0b010, 0b010, 0b001]),
+    (')', [0b100, 0b010, 0b010, 0b010, 0b100]),
+    ('*', [0b000, 0b101, 0b010, 0b101, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    (';', [0b000, 0b010, 0b000, 0b010, 0b100]),
+    ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+    ('?', [0b111, 0b001, 0b010, 0b000, 0b010]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b111, 0b100, 0b100, 0b100, 0b111]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b111, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b111, 0b100, 0b100]),
+    ('G', [0b111, 0b100, 0b101, 0b101, 0b111]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b111]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b111, 0b101, 0b111, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('[', [0b110, 0b100, 0b100, 0b100, 0b110]),
+    (']', [0b011, 0b001, 0b001, 0b001, 0b011]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+];
+
+pub(crate) struct TextOverlay {
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl TextOverlay {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        cache: &PipelineCache,
+        vertex_shader: &wgpu::ShaderModule,
+    ) -> Self {
+        // One row of glyphs side by side, 3 texels each
+        let atlas_width = 64 * 3;
+        let mut atlas = vec![0u8; atlas_width * 5];
+        for (glyph, rows) in GLYPHS {
+            let offset = (*glyph as usize - 32) * 3;
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..3 {
+                    if row >> (2 - x) & 1 == 1 {
+                        atlas[y * atlas_width + offset + x] = 255;
+                    }
+                }
+            }
+        }
+        let atlas_texture = wgpu::TextureBuilder::new()
+            .size([atlas_width as u32, 5])
+            .dimension(wgpu::TextureDimension::D2)
+            .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+            .format(wgpu::TextureFormat::R8Unorm)
+            .build(device);
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &atlas_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &atlas,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(atlas_width as u32),
+                rows_per_image: None,
+            },
+            atlas_texture.extent(),
+        );
+        let atlas_view = atlas_texture.view().build();
+
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Text Overlay Uniform Buffer",
+            bytemuck::cast_slice(&[0u32; UNIFORM_LEN]),
+        );
+
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Text Overlay Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Text Overlay Pipeline Layout", &bind_group_layout);
+
+        let shader = cache.shader(
+            device,
+            "Text Overlay Shader",
+            &expand(include_str!("shaders/text_overlay.wgsl")),
+        );
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            vertex_shader,
+            &shader,
+            "Text Overlay Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Text Overlay Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Write the `lines` and `graph` of the next frame, drawn into `corner` of a target
+    // of `target_size` with font pixels of `scale` target pixels
+    pub fn write(
+        &self,
+        lines: &[String],
+        graph: &[f32],
+        corner: BurnInCorner,
+        scale: u32,
+        target_size: [u32; 2],
+    ) {
+        // Long lines keep their end, where the values are
+        let lines: Vec<Vec<u8>> = lines
+            .iter()
+            .take(MAX_LINES)
+            .map(|line| {
+                let bytes = line.to_ascii_uppercase().into_bytes();
+                bytes[bytes.len().saturating_sub(MAX_COLUMNS)..].to_vec()
+            })
+            .collect();
+        let columns = lines.iter().map(Vec::len).max().unwrap_or(0);
+        let graph = &graph[graph.len().saturating_sub(MAX_GRAPH_LEN)..];
+
+        let mut text = [0u8; MAX_LINES * MAX_COLUMNS];
+        for (row, line) in lines.iter().enumerate() {
+            text[row * columns..row * columns + line.len()].copy_from_slice(line);
+        }
+        let mut bars = [0.0f32; MAX_GRAPH_LEN];
+        bars[..graph.len()].copy_from_slice(graph);
+        let header = [
+            (target_size[0] as f32).to_bits(),
+            (target_size[1] as f32).to_bits(),
+            (scale.max(1) as f32).to_bits(),
+            (corner as u32 as f32).to_bits(),
+            lines.len() as u32,
+            columns as u32,
+            graph.len() as u32,
+            0,
+        ];
+        self.uniform_buffer.write(0, bytemuck::cast_slice(&header));
+        self.uniform_buffer
+            .write(header.len() * 4, bytemuck::cast_slice(&text));
+        self.uniform_buffer.write(
+            (header.len() + text.len() / 4) * 4,
+            bytemuck::cast_slice(&bars),
+        );
+    }
+
+    // Draw the text box over `target`
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "text_overlay", skip_all)
+    )]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Text overlay pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
@@ -0,0 +1,624 @@
+// src/params.rs
+//
+// String-keyed parameters
+//
+// OSC, MIDI and scripting layers map incoming addresses to parameters. Rather than
+// one match arm per setter in every layer, every numeric parameter has a path: the
+// bloom settings under `bloom.`, named like the config file's, and the params of
+// effect passes under `passes.<index>.`, plus `passes.<index>.enabled` as 0 or 1 and
+// `passes.<index>.mix`, the pass's wet/dry mix. The settings of layers that can be
+// switched on, like the screen-space reflections, are under a namespace of their own,
+// `reflections.`, but only while the layer is on: switching one on takes the device,
+// which a param can't carry. Colors and offsets are one param per component,
+// `<name>.x` and so on, as with passes. Macros are under `macros.`. The mute and
+// solo of the param groups of the bloom, the layers and the passes are `<group>.mute`
+// and `<group>.solo`, but aren't listed: they switch effects off for debugging rather
+// than make up the look.
+
+#[cfg(feature = "stylize")]
+use crate::distance_glow::DistanceGlow;
+#[cfg(feature = "stylize")]
+use crate::drop_shadow::DropShadow;
+#[cfg(feature = "bloom")]
+use crate::flare::LensFlare;
+use crate::groups::Group;
+use crate::nnpipe::Nnpipe;
+#[cfg(feature = "stylize")]
+use crate::sparkle::Sparkles;
+use crate::ssr::Reflections;
+
+type Getter = fn(&Nnpipe) -> f32;
+type Setter = fn(&mut Nnpipe, f32);
+
+// A setting of a layer by name, with how it's read from and written to the layer's
+// settings
+type Field<T> = (&'static str, fn(&T) -> f32, fn(&mut T, f32));
+
+// Bloom settings by name, with the field they read and the setter they go through
+const BLOOM_PARAMS: &[(&str, Getter, Setter)] = &[
+    (
+        "threshold",
+        |pipeline| pipeline.brightness_threshold,
+        Nnpipe::set_brightness_threshold,
+    ),
+    (
+        CHANNEL_THRESHOLD_PARAMS[0],
+        |pipeline| channel_threshold(pipeline, 0),
+        |pipeline, value| set_channel_threshold(pipeline, 0, value),
+    ),
+    (
+        CHANNEL_THRESHOLD_PARAMS[1],
+        |pipeline| channel_threshold(pipeline, 1),
+        |pipeline, value| set_channel_threshold(pipeline, 1, value),
+    ),
+    (
+        CHANNEL_THRESHOLD_PARAMS[2],
+        |pipeline| channel_threshold(pipeline, 2),
+        |pipeline, value| set_channel_threshold(pipeline, 2, value),
+    ),
+    (
+        "intensity",
+        |pipeline| pipeline.bloom_intensity,
+        Nnpipe::set_bloom_intensity,
+    ),
+    (
+        "adaptive_blur_scaling",
+        |pipeline| pipeline.adaptive_blur_scaling,
+        Nnpipe::set_adaptive_blur_scaling,
+    ),
+    (
+        "max_blur_radius",
+        |pipeline| pipeline.max_blur_radius,
+        Nnpipe::set_max_blur_radius,
+    ),
+    (
+        "intensity_curve",
+        |pipeline| pipeline.intensity_curve,
+        Nnpipe::set_intensity_curve,
+    ),
+    (
+        "horizontal_blur_strength",
+        |pipeline| pipeline.horizontal_blur_strength,
+        Nnpipe::set_horizontal_blur_strength,
+    ),
+    (
+        "vertical_blur_strength",
+        |pipeline| pipeline.vertical_blur_strength,
+        Nnpipe::set_vertical_blur_strength,
+    ),
+    (
+        "stretch",
+        |pipeline| pipeline.bloom_stretch,
+        Nnpipe::set_bloom_stretch,
+    ),
+    (
+        "blur_angle",
+        |pipeline| pipeline.blur_angle,
+        Nnpipe::set_blur_angle,
+    ),
+    (
+        "saturation",
+        |pipeline| pipeline.bloom_saturation,
+        Nnpipe::set_bloom_saturation,
+    ),
+    (
+        "hue_shift",
+        |pipeline| pipeline.bloom_hue_shift,
+        Nnpipe::set_bloom_hue_shift,
+    ),
+];
+
+// Bloom params of the channel thresholds, which the randomizer rolls only with a range
+pub(crate) const CHANNEL_THRESHOLD_PARAMS: [&str; 3] =
+    ["threshold_r", "threshold_g", "threshold_b"];
+
+// Threshold of one channel, the luminance threshold until they're set separately
+fn channel_threshold(pipeline: &Nnpipe, channel: usize) -> f32 {
+    pipeline
+        .channel_thresholds
+        .map_or(pipeline.brightness_threshold, |thresholds| {
+            thresholds[channel]
+        })
+}
+
+// Setting one channel's threshold switches to channel thresholds, the others
+// starting at the luminance threshold
+fn set_channel_threshold(pipeline: &mut Nnpipe, channel: usize, value: f32) {
+    let mut thresholds = pipeline
+        .channel_thresholds
+        .unwrap_or([pipeline.brightness_threshold; 3]);
+    thresholds[channel] = value;
+    pipeline.set_channel_thresholds(Some(thresholds));
+}
+
+// Settings of a layer that can be switched on, with params under `<PREFIX>.` while
+// it's on
+pub(crate) trait LayerSettings: Copy + 'static {
+    const PREFIX: &'static str;
+    const FIELDS: &'static [Field<Self>];
+
+    fn get(pipeline: &Nnpipe) -> Option<Self>;
+
+    // Replace the settings of the layer, or return `false` if it's off
+    fn set(pipeline: &mut Nnpipe, settings: Self) -> bool;
+}
+
+impl LayerSettings for Reflections {
+    const PREFIX: &'static str = "reflections";
+    const FIELDS: &'static [Field<Self>] = &[
+        (
+            "intensity",
+            |reflections| reflections.intensity,
+            |reflections, value| reflections.intensity = value,
+        ),
+        (
+            "max_distance",
+            |reflections| reflections.max_distance,
+            |reflections, value| reflections.max_distance = value,
+        ),
+        (
+            "steps",
+            |reflections| reflections.steps as f32,
+            |reflections, value| reflections.steps = value.round().max(0.0) as u32,
+        ),
+        (
+            "thickness",
+            |reflections| reflections.thickness,
+            |reflections, value| reflections.thickness = value,
+        ),
+        (
+            "blur_radius",
+            |reflections| reflections.blur_radius,
+            |reflections, value| reflections.blur_radius = value,
+        ),
+        (
+            "edge_fade",
+            |reflections| reflections.edge_fade,
+            |reflections, value| reflections.edge_fade = value,
+        ),
+    ];
+
+    fn get(pipeline: &Nnpipe) -> Option<Self> {
+        pipeline.reflections()
+    }
+
+    fn set(pipeline: &mut Nnpipe, settings: Self) -> bool {
+        pipeline.set_reflections(settings);
+        pipeline.reflections().is_some()
+    }
+}
+
+#[cfg(feature = "bloom")]
+impl LayerSettings for LensFlare {
+    const PREFIX: &'static str = "flare";
+    const FIELDS: &'static [Field<Self>] = &[
+        (
+            "ghosts",
+            |flare| flare.ghosts as f32,
+            |flare, value| flare.ghosts = value.round().max(0.0) as u32,
+        ),
+        (
+            "spacing",
+            |flare| flare.spacing,
+            |flare, value| flare.spacing = value,
+        ),
+        (
+            "tint.x",
+            |flare| flare.tint[0],
+            |flare, value| flare.tint[0] = value,
+        ),
+        (
+            "tint.y",
+            |flare| flare.tint[1],
+            |flare, value| flare.tint[1] = value,
+        ),
+        (
+            "tint.z",
+            |flare| flare.tint[2],
+            |flare, value| flare.tint[2] = value,
+        ),
+        (
+            "ghost_intensity",
+            |flare| flare.ghost_intensity,
+            |flare, value| flare.ghost_intensity = value,
+        ),
+        (
+            "halo_radius",
+            |flare| flare.halo_radius,
+            |flare, value| flare.halo_radius = value,
+        ),
+        (
+            "halo_intensity",
+            |flare| flare.halo_intensity,
+            |flare, value| flare.halo_intensity = value,
+        ),
+        (
+            "chromatic_shift",
+            |flare| flare.chromatic_shift,
+            |flare, value| flare.chromatic_shift = value,
+        ),
+    ];
+
+    fn get(pipeline: &Nnpipe) -> Option<Self> {
+        pipeline.lens_flare()
+    }
+
+    fn set(pipeline: &mut Nnpipe, settings: Self) -> bool {
+        let layer = pipeline.flare_layer.as_mut();
+        layer.map(|layer| layer.set_settings(settings)).is_some()
+    }
+}
+
+#[cfg(feature = "stylize")]
+impl LayerSettings for Sparkles {
+    const PREFIX: &'static str = "sparkles";
+    const FIELDS: &'static [Field<Self>] = &[
+        (
+            "threshold",
+            |sparkles| sparkles.threshold,
+            |sparkles, value| sparkles.threshold = value,
+        ),
+        (
+            "density",
+            |sparkles| sparkles.density,
+            |sparkles, value| sparkles.density = value,
+        ),
+        (
+            "size",
+            |sparkles| sparkles.size,
+            |sparkles, value| sparkles.size = value,
+        ),
+        (
+            "lifetime",
+            |sparkles| sparkles.lifetime,
+            |sparkles, value| sparkles.lifetime = value,
+        ),
+        (
+            "intensity",
+            |sparkles| sparkles.intensity,
+            |sparkles, value| sparkles.intensity = value,
+        ),
+        (
+            "cell_size",
+            |sparkles| sparkles.cell_size,
+            |sparkles, value| sparkles.cell_size = value,
+        ),
+    ];
+
+    fn get(pipeline: &Nnpipe) -> Option<Self> {
+        pipeline.sparkles()
+    }
+
+    fn set(pipeline: &mut Nnpipe, settings: Self) -> bool {
+        let layer = pipeline.sparkle_layer.as_mut();
+        layer.map(|layer| layer.set_settings(settings)).is_some()
+    }
+}
+
+#[cfg(feature = "stylize")]
+impl LayerSettings for DropShadow {
+    const PREFIX: &'static str = "drop_shadow";
+    const FIELDS: &'static [Field<Self>] = &[
+        (
+            "offset.x",
+            |shadow| shadow.offset[0],
+            |shadow, value| shadow.offset[0] = value,
+        ),
+        (
+            "offset.y",
+            |shadow| shadow.offset[1],
+            |shadow, value| shadow.offset[1] = value,
+        ),
+        (
+            "blur_radius",
+            |shadow| shadow.blur_radius,
+            |shadow, value| shadow.blur_radius = value,
+        ),
+        (
+            "color.x",
+            |shadow| shadow.color[0],
+            |shadow, value| shadow.color[0] = value,
+        ),
+        (
+            "color.y",
+            |shadow| shadow.color[1],
+            |shadow, value| shadow.color[1] = value,
+        ),
+        (
+            "color.z",
+            |shadow| shadow.color[2],
+            |shadow, value| shadow.color[2] = value,
+        ),
+        (
+            "opacity",
+            |shadow| shadow.opacity,
+            |shadow, value| shadow.opacity = value,
+        ),
+    ];
+
+    fn get(pipeline: &Nnpipe) -> Option<Self> {
+        pipeline.drop_shadow()
+    }
+
+    fn set(pipeline: &mut Nnpipe, settings: Self) -> bool {
+        let layer = pipeline.drop_shadow_layer.as_mut();
+        layer.map(|layer| layer.set_settings(settings)).is_some()
+    }
+}
+
+#[cfg(feature = "stylize")]
+impl LayerSettings for DistanceGlow {
+    const PREFIX: &'static str = "distance_glow";
+    const FIELDS: &'static [Field<Self>] = &[
+        (
+            "color.x",
+            |glow| glow.color[0],
+            |glow, value| glow.color[0] = value,
+        ),
+        (
+            "color.y",
+            |glow| glow.color[1],
+            |glow, value| glow.color[1] = value,
+        ),
+        (
+            "color.z",
+            |glow| glow.color[2],
+            |glow, value| glow.color[2] = value,
+        ),
+        (
+            "intensity",
+            |glow| glow.intensity,
+            |glow, value| glow.intensity = value,
+        ),
+        (
+            "offset",
+            |glow| glow.offset,
+            |glow, value| glow.offset = value,
+        ),
+        (
+            "radius",
+            |glow| glow.radius,
+            |glow, value| glow.radius = value,
+        ),
+        (
+            "softness",
+            |glow| glow.softness,
+            |glow, value| glow.softness = value,
+        ),
+    ];
+
+    fn get(pipeline: &Nnpipe) -> Option<Self> {
+        pipeline.distance_glow()
+    }
+
+    fn set(pipeline: &mut Nnpipe, settings: Self) -> bool {
+        let layer = pipeline.distance_glow_layer.as_mut();
+        layer.map(|layer| layer.set_settings(settings)).is_some()
+    }
+}
+
+// The params of a layer, without its settings' type
+struct LayerParams {
+    prefix: &'static str,
+    get: fn(&Nnpipe, &str) -> Option<f32>,
+    set: fn(&mut Nnpipe, &str, f32) -> bool,
+    // Names of the params, none while the layer is off
+    names: fn(&Nnpipe) -> Vec<&'static str>,
+}
+
+impl LayerParams {
+    const fn of<T: LayerSettings>() -> Self {
+        Self {
+            prefix: T::PREFIX,
+            get: layer_param::<T>,
+            set: set_layer_param::<T>,
+            names: layer_param_names::<T>,
+        }
+    }
+}
+
+// Layers in the order their params are listed
+const LAYERS: &[LayerParams] = &[
+    #[cfg(feature = "bloom")]
+    LayerParams::of::<LensFlare>(),
+    #[cfg(feature = "stylize")]
+    LayerParams::of::<Sparkles>(),
+    LayerParams::of::<Reflections>(),
+    #[cfg(feature = "stylize")]
+    LayerParams::of::<DropShadow>(),
+    #[cfg(feature = "stylize")]
+    LayerParams::of::<DistanceGlow>(),
+];
+
+fn layer_param<T: LayerSettings>(pipeline: &Nnpipe, name: &str) -> Option<f32> {
+    let (_, get, _) = T::FIELDS.iter().find(|(field, ..)| *field == name)?;
+    Some(get(&T::get(pipeline)?))
+}
+
+fn set_layer_param<T: LayerSettings>(pipeline: &mut Nnpipe, name: &str, value: f32) -> bool {
+    let field = T::FIELDS.iter().find(|(field, ..)| *field == name);
+    let (Some((_, _, set)), Some(mut settings)) = (field, T::get(pipeline)) else {
+        return false;
+    };
+    set(&mut settings, value);
+    T::set(pipeline, settings)
+}
+
+fn layer_param_names<T: LayerSettings>(pipeline: &Nnpipe) -> Vec<&'static str> {
+    match T::get(pipeline) {
+        Some(_) => T::FIELDS.iter().map(|(name, ..)| *name).collect(),
+        None => Vec::new(),
+    }
+}
+
+// A parsed parameter path
+enum ParamPath<'a> {
+    Bloom(Getter, Setter),
+    Layer(&'static LayerParams, &'a str),
+    PassEnabled(usize),
+    PassMix(usize),
+    Pass(usize, &'a str),
+    Macro(&'a str),
+    Mute(Group),
+    Solo(Group),
+}
+
+impl<'a> ParamPath<'a> {
+    fn parse(path: &'a str) -> Option<Self> {
+        if let Some((group, action)) = path.rsplit_once('.') {
+            match (Group::parse(group), action) {
+                (Some(group), "mute") => return Some(ParamPath::Mute(group)),
+                (Some(group), "solo") => return Some(ParamPath::Solo(group)),
+                _ => {}
+            }
+        }
+        if let Some(name) = path.strip_prefix("bloom.") {
+            let (_, get, set) = BLOOM_PARAMS.iter().find(|(param, ..)| *param == name)?;
+            return Some(ParamPath::Bloom(*get, *set));
+        }
+        if let Some(name) = path.strip_prefix("macros.") {
+            return Some(ParamPath::Macro(name));
+        }
+        let (prefix, name) = path.split_once('.')?;
+        if let Some(layer) = LAYERS.iter().find(|layer| layer.prefix == prefix) {
+            return Some(ParamPath::Layer(layer, name));
+        }
+        let (index, name) = name.split_once('.').filter(|_| prefix == "passes")?;
+        let index = index.parse().ok()?;
+        Some(match name {
+            "enabled" => ParamPath::PassEnabled(index),
+            "mix" => ParamPath::PassMix(index),
+            _ => ParamPath::Pass(index, name),
+        })
+    }
+}
+
+// `path` with its pass index, if any, replaced by the index at that position of
+// `new_index`
+pub(crate) fn remap_pass_path(path: &str, new_index: &[usize]) -> String {
+    let index = match ParamPath::parse(path) {
+        Some(
+            ParamPath::PassEnabled(index)
+            | ParamPath::PassMix(index)
+            | ParamPath::Pass(index, _)
+            | ParamPath::Mute(Group::Pass(index))
+            | ParamPath::Solo(Group::Pass(index)),
+        ) => index,
+        _ => return path.to_string(),
+    };
+    match new_index.get(index) {
+        Some(new) => {
+            let (_, name) = path["passes.".len()..].split_once('.').expect("parsed");
+            format!("passes.{new}.{name}")
+        }
+        None => path.to_string(),
+    }
+}
+
+impl Nnpipe {
+    /// Set the parameter at `path`, e.g. `bloom.intensity` or `passes.0.amplitude`.
+    /// Returns `false` if there's no such parameter.
+    ///
+    /// See [`Nnpipe::list_params`] for the paths. A layer's params, like
+    /// `reflections.intensity`, are only there while the layer is on.
+    /// `bloom.threshold_r`, `_g` and `_b` read the luminance threshold until one of
+    /// them is set, which switches the bloom to
+    /// [channel thresholds](Nnpipe::set_channel_thresholds).
+    /// `passes.<index>.enabled` turns a pass on for values of 0.5 and above, and
+    /// `passes.<index>.mix` sets its wet/dry mix, taking precedence over a param of
+    /// the pass named `mix`. `macros.<name>` sets a macro (see
+    /// [`Nnpipe::add_macro`]). `<group>.mute`, like `bloom.mute` or
+    /// `passes.<index>.mute`, mutes a param group for values of 0.5 and above, and
+    /// `.solo` solos it, see [`Nnpipe::param_groups`]; these aren't recorded in the
+    /// history.
+    pub fn set_param(&mut self, path: &str, value: f32) -> bool {
+        #[cfg(feature = "config")]
+        if !matches!(
+            ParamPath::parse(path),
+            Some(ParamPath::Mute(_) | ParamPath::Solo(_))
+        ) && self.get_param(path).is_some()
+        {
+            self.record(Some(path));
+        }
+        self.apply_param(path, value)
+    }
+
+    // Set the parameter at `path` like `set_param`, without recording it in the history
+    pub(crate) fn apply_param(&mut self, path: &str, value: f32) -> bool {
+        match ParamPath::parse(path) {
+            Some(ParamPath::Bloom(_, set)) => {
+                set(self, value);
+                true
+            }
+            Some(ParamPath::Layer(layer, name)) => (layer.set)(self, name, value),
+            Some(ParamPath::PassEnabled(index)) => match self.custom_pass_mut(index) {
+                Some(pass) => {
+                    pass.enabled = value >= 0.5;
+                    true
+                }
+                None => false,
+            },
+            Some(ParamPath::PassMix(index)) => match self.custom_pass_mut(index) {
+                Some(pass) => {
+                    pass.mix = value;
+                    true
+                }
+                None => false,
+            },
+            Some(ParamPath::Pass(index, name)) => self
+                .custom_pass_mut(index)
+                .is_some_and(|pass| pass.set_param(name, value)),
+            Some(ParamPath::Macro(name)) => self.write_macro(name, value),
+            Some(ParamPath::Mute(group)) => self.set_muted(group, value >= 0.5),
+            Some(ParamPath::Solo(group)) => self.set_soloed(group, value >= 0.5),
+            None => false,
+        }
+    }
+
+    /// The value of the parameter at `path`, if there is one.
+    pub fn get_param(&self, path: &str) -> Option<f32> {
+        match ParamPath::parse(path)? {
+            ParamPath::Bloom(get, _) => Some(get(self)),
+            ParamPath::Layer(layer, name) => (layer.get)(self, name),
+            ParamPath::PassEnabled(index) => {
+                let pass = self.custom_pass(index)?;
+                Some(if pass.enabled { 1.0 } else { 0.0 })
+            }
+            ParamPath::PassMix(index) => Some(self.custom_pass(index)?.mix),
+            ParamPath::Pass(index, name) => self.custom_pass(index)?.param(name),
+            ParamPath::Macro(name) => self.macro_value(name),
+            ParamPath::Mute(group) => Some(if self.muted(group)? { 1.0 } else { 0.0 }),
+            ParamPath::Solo(group) => Some(if self.soloed(group)? { 1.0 } else { 0.0 }),
+        }
+    }
+
+    /// Paths of every parameter [`Nnpipe::set_param`] accepts: the bloom settings,
+    /// then the settings of the layers that are on, then each pass's `enabled`, `mix`
+    /// and params in order, then the macros.
+    pub fn list_params(&self) -> Vec<String> {
+        let bloom = BLOOM_PARAMS
+            .iter()
+            .map(|(name, ..)| format!("bloom.{name}"));
+        let layers = LAYERS.iter().flat_map(|layer| {
+            (layer.names)(self)
+                .into_iter()
+                .map(|name| format!("{}.{name}", layer.prefix))
+        });
+        let passes = (0..)
+            .map_while(|index| self.custom_pass(index).map(|pass| (index, pass)))
+            .flat_map(|(index, pass)| {
+                [
+                    format!("passes.{index}.enabled"),
+                    format!("passes.{index}.mix"),
+                ]
+                .into_iter()
+                .chain(
+                    pass.params()
+                        .iter()
+                        .map(move |(name, _)| format!("passes.{index}.{name}")),
+                )
+            });
+        let macros = self.macro_names().map(|name| format!("macros.{name}"));
+        bloom.chain(layers).chain(passes).chain(macros).collect()
+    }
+}
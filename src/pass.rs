@@ -0,0 +1,1225 @@
+// src/pass.rs
+//
+// Fullscreen effect passes that run after the bloom composite
+//
+// A pass is a WGSL fragment shader with an `fs_main` entry point. The vertex stage
+// is supplied by the crate (a fullscreen triangle), and every pass sees the same
+// bindings:
+//
+//     @group(0) @binding(0) var src_tex: texture_2d<f32>;   // output of the previous stage
+//     @group(0) @binding(1) var src_sampler: sampler;       // clamped and linear unless set
+//     @group(0) @binding(2) var<uniform> params: Params;     // the pass's f32 params, in order
+//     @group(0) @binding(3) var<uniform> globals: Globals;   // resolution: vec2<f32>, time: f32, seed: u32
+//     @group(0) @binding(4) var depth_tex: texture_depth_2d; // scene depth, if enabled
+//     @group(0) @binding(5) var<storage, read> data: array<f32>; // the pass's data array
+//     @group(0) @binding(6) var lookup_tex: texture_2d<f32>; // the pass's lookup texture
+//     @group(0) @binding(8) var matte_tex: texture_2d<f32>;  // scene matte, if enabled
+//
+// The lookup texture holds unfilterable 32-bit floats, so read it with `textureLoad`.
+// Noise should be derived from `globals.seed` rather than the clock or the frame
+// count, so renders with a pinned time and seed are reproducible.
+//
+// A shader only needs to declare the bindings it uses. Buffers and textures of the
+// sketch's own (particle data, extra images, ...) can be bound in group 1 with
+// `Nnpipe::add_custom_pass_with_inputs`, at their position in the list:
+//
+//     @group(1) @binding(0) var<storage, read> particles: array<Particle>;
+//     @group(1) @binding(1) var overlay_tex: texture_2d<f32>;
+//
+// Compute passes (`Nnpipe::add_compute_pass`) see the same bindings, and write their
+// output to a storage texture instead of returning it:
+//
+//     @group(0) @binding(7) var dst_tex: texture_storage_2d<rgba16float, write>;
+//
+//     @compute @workgroup_size(8, 8)
+//     fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) { ... }
+//
+// They're dispatched in 8x8 workgroups over the whole frame, rounded up, so the
+// invocations past its edges should return early. Texels they don't store are left
+// undefined rather than black.
+//
+// Every pass also has a wet/dry `mix`. Below 1, the pass's input is blended back over
+// its output by a passthrough draw; at 0 the pass is skipped altogether.
+//
+// Pass shaders can `#include` the crate's shared WGSL files (see src/preprocess.rs).
+// Passes added with `Nnpipe::add_reflected_pass` take their params from the
+// `Params` struct itself (see src/reflect.rs), and typed module constants can be
+// specialized with `Nnpipe::set_pass_constants` (see src/specialize.rs).
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::error::NnpipeError;
+use crate::preprocess::ShaderPreprocessor;
+use crate::specialize::specialize;
+use crate::upload::UniformBuffer;
+
+/// A shader that failed to compile or link, as reported by wgpu.
+#[derive(Clone, Debug)]
+pub struct ShaderError {
+    /// Label of the pass the shader belongs to
+    pub label: String,
+    /// The full wgpu/naga error message
+    pub message: String,
+}
+
+impl std::fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "shader error in pass '{}': {}", self.label, self.message)
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// A small RGBA image a pass reads at binding 6, such as curves or a palette.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct LookupTexture {
+    pub width: u32,
+    pub height: u32,
+    /// Linear RGBA texels in row-major order.
+    pub data: Vec<[f32; 4]>,
+}
+
+impl LookupTexture {
+    // Fail unless the texture has texels, all `width` x `height` of them
+    pub(crate) fn check(&self) -> Result<(), NnpipeError> {
+        let len = self.width as usize * self.height as usize;
+        if len == 0 || self.data.len() != len {
+            return Err(NnpipeError::PixelCount {
+                width: self.width,
+                height: self.height,
+                len: self.data.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// How a pass's `src_sampler` treats coordinates outside 0..1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub enum SamplerAddressing {
+    /// Repeat the edge texels.
+    #[default]
+    ClampToEdge,
+    /// Tile the texture.
+    Repeat,
+    /// Tile the texture, flipping every other tile.
+    MirrorRepeat,
+}
+
+/// How a pass's `src_sampler` filters between texels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub enum SamplerFilter {
+    #[default]
+    Linear,
+    /// The nearest texel, for hard pixel edges.
+    Nearest,
+}
+
+/// The sampler a pass sees as `src_sampler` at binding 1, see
+/// [`Nnpipe::set_pass_sampler`](crate::Nnpipe::set_pass_sampler). The default is the
+/// pipeline's shared clamped, linear sampler.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct PassSampler {
+    pub addressing: SamplerAddressing,
+    pub filter: SamplerFilter,
+}
+
+impl PassSampler {
+    pub fn new(addressing: SamplerAddressing, filter: SamplerFilter) -> Self {
+        Self { addressing, filter }
+    }
+
+    fn create(&self, device: &wgpu::Device) -> wgpu::Sampler {
+        let address_mode = match self.addressing {
+            SamplerAddressing::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+            SamplerAddressing::Repeat => wgpu::AddressMode::Repeat,
+            SamplerAddressing::MirrorRepeat => wgpu::AddressMode::MirrorRepeat,
+        };
+        let filter = match self.filter {
+            SamplerFilter::Linear => wgpu::FilterMode::Linear,
+            SamplerFilter::Nearest => wgpu::FilterMode::Nearest,
+        };
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Pass sampler"),
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            ..Default::default()
+        })
+    }
+}
+
+pub struct Pass {
+    pub label: String,
+    pub enabled: bool,
+    /// How much of the pass's output replaces its input, from 0 (bypassed) to 1 (the
+    /// full effect, the default).
+    pub mix: f32,
+    /// Whether the pass's param group is muted, see
+    /// [`Nnpipe::set_group_muted`](crate::Nnpipe::set_group_muted).
+    pub muted: bool,
+    /// Whether the pass's param group is soloed, see
+    /// [`Nnpipe::set_group_soloed`](crate::Nnpipe::set_group_soloed).
+    pub soloed: bool,
+
+    // WGSL source of the currently running shader, before specialization
+    source: String,
+
+    // Values of the source's constants the shader is specialized with
+    constants: Vec<(String, f32)>,
+
+    // Named f32 parameters, uploaded in order to the params buffer
+    params: Vec<(String, f32)>,
+    params_buffer: UniformBuffer,
+
+    // Array data too large for the params (e.g. kernel weights), in a storage buffer
+    data: Vec<f32>,
+    data_buffer: wgpu::Buffer,
+
+    // Optional lookup texture, with the image it was uploaded from
+    lookup: Option<(LookupTexture, wgpu::Texture, wgpu::TextureView)>,
+
+    // The user's resources bound in group 1, if any
+    inputs: Option<PassInputs>,
+
+    // Sampler of the pass's own, if it doesn't use the pipeline's shared one
+    sampler: Option<(PassSampler, wgpu::Sampler)>,
+
+    // Whether the shader is a compute shader writing to `dst_tex`
+    compute: bool,
+    pipeline: PassPipeline,
+
+    // One bind group per ping-pong input texture (compute passes writing the other)
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+// The compiled shader of a pass
+enum PassPipeline {
+    Render(Arc<wgpu::RenderPipeline>),
+    Compute(Arc<wgpu::ComputePipeline>),
+}
+
+// Pipeline-owned resources bound by every pass
+pub(crate) struct PassBindings<'a> {
+    // The two ping-pong textures a pass can read from
+    pub inputs: [&'a wgpu::TextureView; 2],
+    pub sampler: &'a wgpu::Sampler,
+    pub globals_buffer: &'a wgpu::Buffer,
+    pub depth_view: &'a wgpu::TextureView,
+    pub matte_view: &'a wgpu::TextureView,
+}
+
+/// A buffer or texture of the sketch's own, bound to a custom pass in `@group(1)` at
+/// its position in the list of inputs. See
+/// [`Nnpipe::add_custom_pass_with_inputs`](crate::Nnpipe::add_custom_pass_with_inputs).
+#[derive(Clone, Copy, Debug)]
+pub enum PassInput<'a> {
+    /// A read-only storage buffer, `var<storage, read>`. It needs `STORAGE` usage.
+    Buffer(&'a wgpu::Buffer),
+    /// A filterable float `texture_2d<f32>`, which can be sampled with `src_sampler`.
+    Texture(&'a wgpu::TextureView),
+    /// A `texture_2d<f32>` of unfilterable floats such as `Rgba32Float`, read with
+    /// `textureLoad`.
+    UnfilterableTexture(&'a wgpu::TextureView),
+}
+
+impl PassInput<'_> {
+    fn kind(&self) -> InputKind {
+        match self {
+            PassInput::Buffer(_) => InputKind::Buffer,
+            PassInput::Texture(_) => InputKind::Texture,
+            PassInput::UnfilterableTexture(_) => InputKind::UnfilterableTexture,
+        }
+    }
+}
+
+// What a pass input binds, which decides the layout it needs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum InputKind {
+    Buffer,
+    Texture,
+    UnfilterableTexture,
+}
+
+impl InputKind {
+    fn layout_entry(
+        self,
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> wgpu::BindGroupLayoutEntry {
+        let texture = |filterable| wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        };
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: match self {
+                InputKind::Buffer => wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                InputKind::Texture => texture(true),
+                InputKind::UnfilterableTexture => texture(false),
+            },
+            count: None,
+        }
+    }
+}
+
+// A pass's inputs, bound once: the bind group keeps the resources alive, so the
+// pass doesn't borrow them, and it doesn't depend on the pipeline's textures, so
+// resizes carry it over as it is
+#[derive(Clone)]
+pub(crate) struct PassInputs {
+    kinds: Vec<InputKind>,
+    pipeline_layout: Arc<wgpu::PipelineLayout>,
+    bind_group: Arc<wgpu::BindGroup>,
+    device: wgpu_upstream::Id<wgpu::Device>,
+}
+
+impl PassInputs {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        resources: &PassResources,
+        compute: bool,
+        inputs: &[PassInput],
+    ) -> Option<Self> {
+        if inputs.is_empty() {
+            return None;
+        }
+        let kinds: Vec<InputKind> = inputs.iter().map(PassInput::kind).collect();
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = kinds
+            .iter()
+            .zip(0..)
+            .map(|(kind, binding)| kind.layout_entry(binding, visibility(compute)))
+            .collect();
+        let pass_layout = if compute {
+            &resources.compute_bind_group_layout
+        } else {
+            &resources.bind_group_layout
+        };
+        let (bind_group_layout, pipeline_layout) =
+            resources
+                .cache
+                .input_layouts(device, pass_layout, &kinds, &entries);
+        let entries: Vec<wgpu::BindGroupEntry> = inputs
+            .iter()
+            .zip(0..)
+            .map(|(input, binding)| wgpu::BindGroupEntry {
+                binding,
+                resource: match input {
+                    PassInput::Buffer(buffer) => {
+                        wgpu::BindingResource::Buffer(buffer.as_entire_buffer_binding())
+                    }
+                    PassInput::Texture(view) | PassInput::UnfilterableTexture(view) => {
+                        wgpu::BindingResource::TextureView(view)
+                    }
+                },
+            })
+            .collect();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Pass Inputs Bind Group"),
+            layout: &bind_group_layout,
+            entries: &entries,
+        });
+        Some(Self {
+            kinds,
+            pipeline_layout,
+            bind_group: Arc::new(bind_group),
+            device: device.global_id(),
+        })
+    }
+
+    // Whether the inputs belong to `device`, and not one lost before a recovery
+    pub(crate) fn belongs_to(&self, device: &wgpu::Device) -> bool {
+        self.device == device.global_id()
+    }
+}
+
+impl Pass {
+    // Build a pass, falling back to a passthrough shader if `source` doesn't compile
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        resources: &PassResources,
+        bindings: &PassBindings,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+        data: &[f32],
+        compute: bool,
+        inputs: Option<PassInputs>,
+    ) -> (Self, Option<ShaderError>) {
+        let params: Vec<(String, f32)> = params
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+
+        let params_buffer = UniformBuffer::new(
+            device,
+            "Pass Params Buffer",
+            bytemuck::cast_slice(&params_data(&params)),
+        );
+
+        // Storage bindings can't be empty
+        let data_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Pass Data Buffer"),
+            contents: bytemuck::cast_slice(if data.is_empty() { &[0.0] } else { data }),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = pipeline_layout(resources, compute, &inputs);
+        let (pipeline, source, error) =
+            match compile_pass(device, resources, layout, compute, label, source) {
+                Ok(pipeline) => (pipeline, source.to_string(), None),
+                Err(error) => {
+                    let passthrough = if compute {
+                        COMPUTE_PASSTHROUGH_SOURCE
+                    } else {
+                        PASSTHROUGH_SOURCE
+                    };
+                    (
+                        resources.passthrough_pipeline(device, layout, compute, label),
+                        passthrough.to_string(),
+                        Some(error),
+                    )
+                }
+            };
+
+        let bind_groups = create_bind_groups(
+            device,
+            resources,
+            bindings,
+            bindings.sampler,
+            compute,
+            &params_buffer,
+            &data_buffer,
+            &resources.empty_lookup_view,
+        );
+
+        let pass = Self {
+            label: label.to_string(),
+            enabled: true,
+            mix: 1.0,
+            muted: false,
+            soloed: false,
+            source,
+            constants: Vec::new(),
+            params,
+            params_buffer,
+            data: data.to_vec(),
+            data_buffer,
+            lookup: None,
+            inputs,
+            sampler: None,
+            compute,
+            pipeline,
+            bind_groups,
+        };
+
+        (pass, error)
+    }
+
+    /// Swap in a new shader, specialized with the pass's constants. If it fails to
+    /// compile, the previous shader keeps running.
+    pub(crate) fn reload(
+        &mut self,
+        device: &wgpu::Device,
+        resources: &PassResources,
+        source: &str,
+    ) -> Result<(), ShaderError> {
+        let layout = pipeline_layout(resources, self.compute, &self.inputs);
+        self.pipeline = self.compile(device, resources, layout, source, &self.constants)?;
+        self.source = source.to_string();
+        Ok(())
+    }
+
+    // Rebuild the shader with `constants`. On failure the previous shader keeps
+    // running with the previous constants.
+    pub(crate) fn set_constants(
+        &mut self,
+        device: &wgpu::Device,
+        resources: &PassResources,
+        constants: &[(&str, f32)],
+    ) -> Result<(), ShaderError> {
+        let constants: Vec<(String, f32)> = constants
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        let layout = pipeline_layout(resources, self.compute, &self.inputs);
+        self.pipeline = self.compile(device, resources, layout, &self.source, &constants)?;
+        self.constants = constants;
+        Ok(())
+    }
+
+    // Bind `inputs` in place of the previous ones, rebuilding the shader for their
+    // layout if it changed. On failure the pass keeps its previous inputs.
+    pub(crate) fn set_inputs(
+        &mut self,
+        device: &wgpu::Device,
+        resources: &PassResources,
+        inputs: Option<PassInputs>,
+    ) -> Result<(), ShaderError> {
+        let kinds =
+            |inputs: &Option<PassInputs>| inputs.as_ref().map(|inputs| inputs.kinds.clone());
+        if kinds(&inputs) != kinds(&self.inputs) {
+            let layout = pipeline_layout(resources, self.compute, &inputs);
+            self.pipeline =
+                self.compile(device, resources, layout, &self.source, &self.constants)?;
+        }
+        self.inputs = inputs;
+        Ok(())
+    }
+
+    pub(crate) fn inputs(&self) -> Option<&PassInputs> {
+        self.inputs.as_ref()
+    }
+
+    // Specialize `source` with `constants` and compile it for `layout`
+    fn compile(
+        &self,
+        device: &wgpu::Device,
+        resources: &PassResources,
+        layout: &wgpu::PipelineLayout,
+        source: &str,
+        constants: &[(String, f32)],
+    ) -> Result<PassPipeline, ShaderError> {
+        let specialized = specialize(source, constants).map_err(|message| ShaderError {
+            label: self.label.clone(),
+            message,
+        })?;
+        compile_pass(
+            device,
+            resources,
+            layout,
+            self.compute,
+            &self.label,
+            &specialized,
+        )
+    }
+
+    /// The WGSL source of the shader currently running in this pass, before its
+    /// constants are specialized.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The constants the shader is specialized with, see
+    /// [`Nnpipe::set_pass_constants`](crate::Nnpipe::set_pass_constants).
+    pub fn constants(&self) -> &[(String, f32)] {
+        &self.constants
+    }
+
+    /// Whether the pass runs a compute shader, see
+    /// [`Nnpipe::add_compute_pass`](crate::Nnpipe::add_compute_pass).
+    pub fn is_compute(&self) -> bool {
+        self.compute
+    }
+
+    // How much of the input the passthrough blends back over the pass's output
+    pub(crate) fn dry(&self) -> f32 {
+        1.0 - self.mix.clamp(0.0, 1.0)
+    }
+
+    pub fn params(&self) -> &[(String, f32)] {
+        &self.params
+    }
+
+    pub fn param(&self, name: &str) -> Option<f32> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Set a named parameter. Returns `false` if the pass has no parameter by that name.
+    ///
+    /// The value is uploaded with the pipeline's next frame, along with every other
+    /// param set in between.
+    pub fn set_param(&mut self, name: &str, value: f32) -> bool {
+        let Some(index) = self.params.iter().position(|(param, _)| param == name) else {
+            return false;
+        };
+        self.params[index].1 = value;
+        self.write_param(name, value)
+    }
+
+    // Write a param for the GPU only, leaving its stored value alone (for triggers)
+    pub(crate) fn write_param(&self, name: &str, value: f32) -> bool {
+        let Some(index) = self.params.iter().position(|(param, _)| param == name) else {
+            return false;
+        };
+        self.params_buffer.write(
+            index * std::mem::size_of::<f32>(),
+            bytemuck::cast_slice(&[value]),
+        );
+        true
+    }
+
+    pub(crate) fn params_buffer(&self) -> &UniformBuffer {
+        &self.params_buffer
+    }
+
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    // Replace the data array, in place if the length is unchanged. Returns `true` if
+    // the buffer was recreated and the pass has to be rebound.
+    pub(crate) fn set_data(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[f32],
+    ) -> bool {
+        if data.len() == self.data.len() {
+            self.data.copy_from_slice(data);
+            if !data.is_empty() {
+                queue.write_buffer(&self.data_buffer, 0, bytemuck::cast_slice(data));
+            }
+            return false;
+        }
+
+        self.data_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Pass Data Buffer"),
+            contents: bytemuck::cast_slice(if data.is_empty() { &[0.0] } else { data }),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+        self.data = data.to_vec();
+        true
+    }
+
+    pub fn sampler(&self) -> PassSampler {
+        self.sampler
+            .as_ref()
+            .map_or_else(PassSampler::default, |(sampler, _)| *sampler)
+    }
+
+    // Replace the sampler, the default one going back to the pipeline's shared
+    // sampler. Returns `true` if it changed and the pass has to be rebound.
+    pub(crate) fn set_sampler(&mut self, device: &wgpu::Device, sampler: PassSampler) -> bool {
+        if sampler == self.sampler() {
+            return false;
+        }
+        self.sampler =
+            (sampler != PassSampler::default()).then(|| (sampler, sampler.create(device)));
+        true
+    }
+
+    pub fn lookup(&self) -> Option<&LookupTexture> {
+        self.lookup.as_ref().map(|(lookup, _, _)| lookup)
+    }
+
+    // Replace the lookup texture, in place if the size is unchanged. Returns `true`
+    // if the texture was recreated and the pass has to be rebound. `lookup` must be
+    // valid, see `LookupTexture::check`.
+    pub(crate) fn set_lookup(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lookup: LookupTexture,
+    ) -> bool {
+        let (texture, view, rebind) = match self.lookup.take() {
+            Some((previous, texture, view))
+                if [previous.width, previous.height] == [lookup.width, lookup.height] =>
+            {
+                (texture, view, false)
+            }
+            _ => {
+                let texture = wgpu::TextureBuilder::new()
+                    .size([lookup.width, lookup.height])
+                    .dimension(wgpu::TextureDimension::D2)
+                    .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+                    .format(wgpu::TextureFormat::Rgba32Float)
+                    .build(device);
+                let view = texture.view().build();
+                (texture, view, true)
+            }
+        };
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&lookup.data),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(lookup.width * 16),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: lookup.width,
+                height: lookup.height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.lookup = Some((lookup, texture, view));
+        rebind
+    }
+
+    // Bind groups for the pass's current buffers and textures
+    pub(crate) fn create_bind_groups(
+        &self,
+        device: &wgpu::Device,
+        resources: &PassResources,
+        bindings: &PassBindings,
+    ) -> [wgpu::BindGroup; 2] {
+        let lookup_view = match &self.lookup {
+            Some((_, _, view)) => view,
+            None => &resources.empty_lookup_view,
+        };
+        let sampler = match &self.sampler {
+            Some((_, sampler)) => sampler,
+            None => bindings.sampler,
+        };
+        create_bind_groups(
+            device,
+            resources,
+            bindings,
+            sampler,
+            self.compute,
+            &self.params_buffer,
+            &self.data_buffer,
+            lookup_view,
+        )
+    }
+
+    pub(crate) fn set_bind_groups(&mut self, bind_groups: [wgpu::BindGroup; 2]) {
+        self.bind_groups = bind_groups;
+    }
+
+    // Record this pass, reading from ping-pong input `input` and writing to `target`
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "pass", skip_all, fields(label = %self.label)))]
+    pub(crate) fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: usize,
+        target: &wgpu::TextureView,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&self.label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        let PassPipeline::Render(pipeline) = &self.pipeline else {
+            unreachable!("compute passes are dispatched");
+        };
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &self.bind_groups[input], &[]);
+        if let Some(inputs) = &self.inputs {
+            pass.set_bind_group(1, &inputs.bind_group, &[]);
+        }
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+
+    // Blend ping-pong input `input` over `target` with weight `amount`, drawing with
+    // `pipeline`. Used on the passthrough, to mix passes' inputs back into their
+    // outputs.
+    pub(crate) fn encode_blend(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        input: usize,
+        target: &wgpu::TextureView,
+        amount: f32,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Pass mix"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        let amount = amount as f64;
+        pass.set_pipeline(pipeline);
+        pass.set_blend_constant(wgpu::Color {
+            r: amount,
+            g: amount,
+            b: amount,
+            a: amount,
+        });
+        pass.set_bind_group(0, &self.bind_groups[input], &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+
+    // Record this compute pass over a frame of `size`, reading from ping-pong input
+    // `input` and writing to the other
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "compute pass", skip_all, fields(label = %self.label)))]
+    pub(crate) fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        input: usize,
+        [width, height]: [u32; 2],
+    ) {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(&self.label),
+        });
+
+        let PassPipeline::Compute(pipeline) = &self.pipeline else {
+            unreachable!("render passes are encoded");
+        };
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &self.bind_groups[input], &[]);
+        if let Some(inputs) = &self.inputs {
+            pass.set_bind_group(1, &inputs.bind_group, &[]);
+        }
+        pass.dispatch_workgroups(
+            width.div_ceil(WORKGROUP_SIZE),
+            height.div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+}
+
+// Width and height of the workgroups compute passes are dispatched in
+const WORKGROUP_SIZE: u32 = 8;
+
+// Layouts and modules shared by every pass of a pipeline
+pub(crate) struct PassResources {
+    cache: PipelineCache,
+    pub bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    pipeline_layout: Arc<wgpu::PipelineLayout>,
+    compute_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    compute_pipeline_layout: Arc<wgpu::PipelineLayout>,
+    pub vertex_shader: Arc<wgpu::ShaderModule>,
+    passthrough_shader: Arc<wgpu::ShaderModule>,
+    compute_passthrough_shader: Arc<wgpu::ShaderModule>,
+
+    // The passthrough, blending with the blend constant, for mixing passes
+    pub mix_pipeline: Arc<wgpu::RenderPipeline>,
+
+    // Bound in place of the scene depth when depth is disabled
+    pub empty_depth_view: wgpu::TextureView,
+
+    // Bound in place of the lookup texture of passes without one
+    empty_lookup_view: wgpu::TextureView,
+
+    // Bound in place of the scene matte when the matte is disabled
+    pub empty_matte_view: wgpu::TextureView,
+}
+
+impl PassResources {
+    pub fn new(device: &wgpu::Device, cache: &PipelineCache) -> Self {
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Pass Bind Group Layout",
+            &layout_entries(wgpu::ShaderStages::FRAGMENT),
+        );
+
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Pass Pipeline Layout", &bind_group_layout);
+
+        // Compute passes also bind the texture they write to
+        let mut compute_entries = layout_entries(wgpu::ShaderStages::COMPUTE);
+        compute_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 7,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: wgpu::TextureFormat::Rgba16Float,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        });
+        let compute_bind_group_layout =
+            cache.bind_group_layout(device, "Compute Pass Bind Group Layout", &compute_entries);
+        let compute_pipeline_layout = cache.pipeline_layout(
+            device,
+            "Compute Pass Pipeline Layout",
+            &compute_bind_group_layout,
+        );
+
+        let vertex_shader = cache.shader(
+            device,
+            "Fullscreen Shader",
+            include_str!("shaders/fullscreen.wgsl"),
+        );
+
+        let passthrough_shader = cache.shader(device, "Passthrough Shader", PASSTHROUGH_SOURCE);
+        let compute_passthrough_shader = cache.shader(
+            device,
+            "Compute Passthrough Shader",
+            COMPUTE_PASSTHROUGH_SOURCE,
+        );
+
+        let mix_blend = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::OneMinusConstant,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let mix_pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &vertex_shader,
+            &passthrough_shader,
+            "Pass Mix Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            Some(wgpu::BlendState {
+                color: mix_blend,
+                alpha: mix_blend,
+            }),
+        );
+
+        let empty_depth_view = wgpu::TextureBuilder::new()
+            .size([1, 1])
+            .dimension(wgpu::TextureDimension::D2)
+            .usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(wgpu::TextureFormat::Depth32Float)
+            .build(device)
+            .view()
+            .build();
+
+        let empty_lookup_view = wgpu::TextureBuilder::new()
+            .size([1, 1])
+            .dimension(wgpu::TextureDimension::D2)
+            .usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(wgpu::TextureFormat::Rgba32Float)
+            .build(device)
+            .view()
+            .build();
+
+        let empty_matte_view = wgpu::TextureBuilder::new()
+            .size([1, 1])
+            .dimension(wgpu::TextureDimension::D2)
+            .usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device)
+            .view()
+            .build();
+
+        Self {
+            cache: cache.clone(),
+            bind_group_layout,
+            pipeline_layout,
+            compute_bind_group_layout,
+            compute_pipeline_layout,
+            vertex_shader,
+            passthrough_shader,
+            compute_passthrough_shader,
+            mix_pipeline,
+            empty_depth_view,
+            empty_lookup_view,
+            empty_matte_view,
+        }
+    }
+
+    fn passthrough_pipeline(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        compute: bool,
+        label: &str,
+    ) -> PassPipeline {
+        if compute {
+            PassPipeline::Compute(self.cache.compute_pipeline(
+                device,
+                layout,
+                &self.compute_passthrough_shader,
+                label,
+            ))
+        } else {
+            PassPipeline::Render(self.cache.pipeline(
+                device,
+                layout,
+                &self.vertex_shader,
+                &self.passthrough_shader,
+                label,
+                wgpu::TextureFormat::Rgba16Float,
+                None,
+            ))
+        }
+    }
+}
+
+pub(crate) const PASSTHROUGH_SOURCE: &str = include_str!("shaders/passthrough.wgsl");
+pub(crate) const COMPUTE_PASSTHROUGH_SOURCE: &str =
+    include_str!("shaders/compute_passthrough.wgsl");
+
+// The bindings every pass sees, visible to the shader stage of the pass
+fn layout_entries(visibility: wgpu::ShaderStages) -> Vec<wgpu::BindGroupLayoutEntry> {
+    vec![
+        // Input texture binding
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        // Sampler binding
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility,
+            ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+            count: None,
+        },
+        // Params uniform binding
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        // Globals uniform binding
+        wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        // Scene depth binding
+        wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        // Data array binding
+        wgpu::BindGroupLayoutEntry {
+            binding: 5,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        },
+        // Lookup texture binding
+        wgpu::BindGroupLayoutEntry {
+            binding: 6,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        // Scene matte binding, after the compute passes' output
+        wgpu::BindGroupLayoutEntry {
+            binding: 8,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+    ]
+}
+
+// The shader stage pass inputs are visible to
+fn visibility(compute: bool) -> wgpu::ShaderStages {
+    if compute {
+        wgpu::ShaderStages::COMPUTE
+    } else {
+        wgpu::ShaderStages::FRAGMENT
+    }
+}
+
+// Expand the crate's includes and any defines in a pass's `source`
+pub(crate) fn preprocess(label: &str, source: &str) -> Result<String, ShaderError> {
+    ShaderPreprocessor::new()
+        .process(source)
+        .map_err(|error| ShaderError {
+            label: label.to_string(),
+            message: error.to_string(),
+        })
+}
+
+// The pipeline layout of a pass with `inputs`
+fn pipeline_layout<'a>(
+    resources: &'a PassResources,
+    compute: bool,
+    inputs: &'a Option<PassInputs>,
+) -> &'a wgpu::PipelineLayout {
+    match inputs {
+        Some(inputs) => &inputs.pipeline_layout,
+        None if compute => &resources.compute_pipeline_layout,
+        None => &resources.pipeline_layout,
+    }
+}
+
+// Compile `source` into a pass pipeline, after preprocessing it, capturing validation
+// errors instead of panicking
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip(device, resources, layout, source), err)
+)]
+fn compile_pass(
+    device: &wgpu::Device,
+    resources: &PassResources,
+    layout: &wgpu::PipelineLayout,
+    compute: bool,
+    label: &str,
+    source: &str,
+) -> Result<PassPipeline, ShaderError> {
+    let source = &preprocess(label, source)?;
+    device.push_error_scope(wgpu_upstream::ErrorFilter::Validation);
+
+    let shader = resources.cache.shader(device, label, source);
+    let pipeline = if compute {
+        PassPipeline::Compute(
+            resources
+                .cache
+                .compute_pipeline(device, layout, &shader, label),
+        )
+    } else {
+        PassPipeline::Render(resources.cache.pipeline(
+            device,
+            layout,
+            &resources.vertex_shader,
+            &shader,
+            label,
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        ))
+    };
+
+    match pop_error_scope(device) {
+        None => Ok(pipeline),
+        Some(error) => {
+            resources.cache.evict_shader(device, source);
+            Err(ShaderError {
+                label: label.to_string(),
+                message: error.to_string(),
+            })
+        }
+    }
+}
+
+// Wait for the validation result of the error scope
+#[cfg(not(target_arch = "wasm32"))]
+fn pop_error_scope(device: &wgpu::Device) -> Option<wgpu_upstream::Error> {
+    futures::executor::block_on(device.pop_error_scope())
+}
+
+// The browser resolves the error scope on its event loop, which blocking here would
+// stall. Shader errors on the web are left to the browser console instead.
+#[cfg(target_arch = "wasm32")]
+fn pop_error_scope(device: &wgpu::Device) -> Option<wgpu_upstream::Error> {
+    use futures::FutureExt;
+    device.pop_error_scope().now_or_never().flatten()
+}
+
+// One bind group per ping-pong input, with the other as compute passes' output
+#[allow(clippy::too_many_arguments)]
+fn create_bind_groups(
+    device: &wgpu::Device,
+    resources: &PassResources,
+    bindings: &PassBindings,
+    sampler: &wgpu::Sampler,
+    compute: bool,
+    params_buffer: &wgpu::Buffer,
+    data_buffer: &wgpu::Buffer,
+    lookup_view: &wgpu::TextureView,
+) -> [wgpu::BindGroup; 2] {
+    [0, 1].map(|input| {
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(bindings.inputs[input]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(params_buffer.as_entire_buffer_binding()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    bindings.globals_buffer.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(bindings.depth_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Buffer(data_buffer.as_entire_buffer_binding()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::TextureView(lookup_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::TextureView(bindings.matte_view),
+            },
+        ];
+        let layout = if compute {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::TextureView(bindings.inputs[1 - input]),
+            });
+            &resources.compute_bind_group_layout
+        } else {
+            &resources.bind_group_layout
+        };
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Pass Bind Group"),
+            layout,
+            entries: &entries,
+        })
+    })
+}
+
+// Pack params into a uniform-compatible buffer (at least 16 bytes, 16-byte multiple)
+fn params_data(params: &[(String, f32)]) -> Vec<f32> {
+    let mut data: Vec<f32> = params.iter().map(|(_, value)| *value).collect();
+    data.resize(data.len().max(1).next_multiple_of(4), 0.0);
+    data
+}
@@ -0,0 +1,430 @@
+// src/pass_graph.rs
+//
+// Pass graphs
+//
+// Effect passes run in a line, each reading the one before. A pass graph lets passes
+// read any number of named textures and write one of their own, so a blur branch and
+// a streak branch can both feed a composite pass. The crate orders the passes so each
+// runs after the ones it reads, leaves out passes nothing reads, and gives every
+// intermediate texture a place in a pool. The graph runs after the effect passes,
+// reading their output as `input` and writing the chain's output as `output`.
+//
+// A texture lives from the pass writing it to the last pass reading it, and textures
+// whose lives don't overlap share a pool texture, `input`'s included, so a chain of
+// fifteen passes needs two. See `PassGraph::allocate`.
+//
+// A graph pass is a fragment shader with the bindings of any effect pass (see
+// src/pass.rs). Its first input is bound as `src_tex`, and the others as textures in
+// `@group(1)`, in order:
+//
+//     @group(1) @binding(0) var streaks_tex: texture_2d<f32>;
+
+use nannou::wgpu;
+
+use crate::pass::{Pass, PassBindings, PassInput, PassInputs, PassResources, ShaderError};
+
+/// Name of the texture a [`PassGraph`] starts from: the output of the effect passes,
+/// or of the composite without any.
+pub const GRAPH_INPUT: &str = "input";
+/// Name of the texture a [`PassGraph`] ends in, the chain's output.
+pub const GRAPH_OUTPUT: &str = "output";
+
+/// A pass of a [`PassGraph`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(deny_unknown_fields))]
+pub struct GraphPass {
+    pub label: String,
+    /// WGSL source of the pass's fragment shader.
+    pub source: String,
+    /// The pass's f32 params, in order, like an effect pass's.
+    pub params: Vec<(String, f32)>,
+    /// Names of the textures the pass reads, the first as `src_tex`.
+    pub inputs: Vec<String>,
+    /// Name of the texture the pass writes, unique in the graph.
+    pub output: String,
+}
+
+/// Passes wired together by the textures they read and write, see
+/// [`Nnpipe::set_pass_graph`](crate::Nnpipe::set_pass_graph).
+///
+/// ```ignore
+/// let graph = PassGraph::new()
+///     .pass("Blur", BLUR, &[("radius", 8.0)], &["input"], "blurred")
+///     .pass("Streaks", STREAKS, &[], &["input"], "streaks")
+///     .pass("Combine", COMBINE, &[], &["blurred", "streaks"], "output");
+/// pipeline.set_pass_graph(device, Some(graph))?;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct PassGraph {
+    pub passes: Vec<GraphPass>,
+}
+
+impl PassGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a pass reading the textures named in `inputs`, [`GRAPH_INPUT`] or the
+    /// outputs of other passes, and writing `output`, or [`GRAPH_OUTPUT`].
+    pub fn pass(
+        mut self,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+        inputs: &[&str],
+        output: &str,
+    ) -> Self {
+        self.passes.push(GraphPass {
+            label: label.to_string(),
+            source: source.to_string(),
+            params: params
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+            output: output.to_string(),
+        });
+        self
+    }
+
+    /// Indices of the passes [`GRAPH_OUTPUT`] depends on, in an order that runs each
+    /// after the passes it reads, or why the graph can't run. Passes that could run
+    /// in either order keep the order they were added in.
+    pub fn schedule(&self) -> Result<Vec<usize>, PassGraphError> {
+        let writer = |name: &str| self.passes.iter().position(|pass| pass.output == name);
+        for (index, pass) in self.passes.iter().enumerate() {
+            if pass.inputs.is_empty() {
+                return Err(PassGraphError::NoInputs(pass.label.clone()));
+            }
+            if pass.output == GRAPH_INPUT || writer(&pass.output) != Some(index) {
+                return Err(PassGraphError::DuplicateOutput(pass.output.clone()));
+            }
+            if let Some(input) = pass.inputs.iter().find(|input| {
+                *input != GRAPH_INPUT && (*input == GRAPH_OUTPUT || writer(input).is_none())
+            }) {
+                return Err(PassGraphError::UnknownInput {
+                    pass: pass.label.clone(),
+                    input: input.clone(),
+                });
+            }
+        }
+        let last = writer(GRAPH_OUTPUT).ok_or(PassGraphError::NoOutput)?;
+
+        // The passes each pass reads
+        let dependencies: Vec<Vec<usize>> = self
+            .passes
+            .iter()
+            .map(|pass| {
+                pass.inputs
+                    .iter()
+                    .filter_map(|input| writer(input))
+                    .collect()
+            })
+            .collect();
+
+        // The passes the output depends on
+        let mut needed = vec![false; self.passes.len()];
+        let mut stack = vec![last];
+        while let Some(index) = stack.pop() {
+            if !std::mem::replace(&mut needed[index], true) {
+                stack.extend(&dependencies[index]);
+            }
+        }
+
+        // Run the first pass whose inputs are ready, until none is left
+        let mut order = Vec::new();
+        let mut done = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            let Some(next) = (0..self.passes.len()).find(|&index| {
+                !done[index] && dependencies[index].iter().all(|&input| done[input])
+            }) else {
+                let cycle = (0..self.passes.len())
+                    .filter(|&index| !done[index])
+                    .map(|index| self.passes[index].label.clone())
+                    .collect();
+                return Err(PassGraphError::Cycle(cycle));
+            };
+            done[next] = true;
+            order.push(next);
+        }
+        order.retain(|&index| needed[index]);
+        Ok(order)
+    }
+
+    /// Schedule the graph and share out its textures between a pool of textures at
+    /// the pipeline's size, or why the graph can't run.
+    ///
+    /// Each texture holds [`GRAPH_INPUT`], or a pass's output, from the pass writing
+    /// it to the last pass reading it, then goes back to the pool for the next pass
+    /// writing one. A pass never writes a texture it reads.
+    pub fn allocate(&self) -> Result<GraphAllocation, PassGraphError> {
+        let order = self.schedule()?;
+        // The position in the order each texture is read for the last time
+        let last_read = |name: &str| {
+            order
+                .iter()
+                .rposition(|&index| self.passes[index].inputs.iter().any(|input| input == name))
+        };
+
+        let mut held: Vec<(&str, usize)> = vec![(GRAPH_INPUT, 0)];
+        let mut free: Vec<usize> = Vec::new();
+        let mut textures = 1;
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for (position, &index) in order.iter().enumerate() {
+            let pass = &self.passes[index];
+            inputs.push(
+                pass.inputs
+                    .iter()
+                    .map(|input| holding(&held, input))
+                    .collect(),
+            );
+            // Taken before the inputs are given back
+            let output = (pass.output != GRAPH_OUTPUT).then(|| {
+                let texture = free.pop().unwrap_or_else(|| {
+                    textures += 1;
+                    textures - 1
+                });
+                held.push((&pass.output, texture));
+                texture
+            });
+            outputs.push(output);
+            for input in &pass.inputs {
+                let texture = holding(&held, input);
+                if last_read(input) == Some(position) && !free.contains(&texture) {
+                    free.push(texture);
+                }
+            }
+        }
+        Ok(GraphAllocation {
+            order,
+            inputs,
+            outputs,
+            textures,
+        })
+    }
+}
+
+// The pool texture holding `name`, which is scheduled to be written before it's read
+fn holding(held: &[(&str, usize)], name: &str) -> usize {
+    let (_, texture) = held
+        .iter()
+        .find(|(held, _)| *held == name)
+        .expect("written");
+    *texture
+}
+
+/// How a [`PassGraph`] runs, see [`PassGraph::allocate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphAllocation {
+    /// Indices of the passes to run, in order, as [`PassGraph::schedule`] returns them.
+    pub order: Vec<usize>,
+    /// The pool textures each pass reads, in the order of its inputs.
+    pub inputs: Vec<Vec<usize>>,
+    /// The pool texture each pass writes, or `None` for [`GRAPH_OUTPUT`].
+    pub outputs: Vec<Option<usize>>,
+    /// Number of textures in the pool, the first holding [`GRAPH_INPUT`].
+    pub textures: usize,
+}
+
+/// Why a [`PassGraph`] can't run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PassGraphError {
+    /// A pass, by label, that reads no texture.
+    NoInputs(String),
+    /// A pass reading a texture no pass writes.
+    UnknownInput { pass: String, input: String },
+    /// A texture written by more than one pass, or [`GRAPH_INPUT`] written by one.
+    DuplicateOutput(String),
+    /// No pass writes [`GRAPH_OUTPUT`].
+    NoOutput,
+    /// Passes, by label, that read each other's textures in a loop, along with the
+    /// passes depending on them.
+    Cycle(Vec<String>),
+}
+
+impl std::fmt::Display for PassGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoInputs(pass) => write!(f, "graph pass '{pass}' reads no texture"),
+            Self::UnknownInput { pass, input } => {
+                write!(
+                    f,
+                    "graph pass '{pass}' reads '{input}', which no pass writes"
+                )
+            }
+            Self::DuplicateOutput(output) => {
+                write!(f, "graph texture '{output}' is written more than once")
+            }
+            Self::NoOutput => write!(f, "no graph pass writes '{GRAPH_OUTPUT}'"),
+            Self::Cycle(passes) => write!(f, "graph passes loop: {}", passes.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for PassGraphError {}
+
+// A scheduled pass, with the pool texture it writes
+struct ScheduledPass {
+    // Index in the graph's passes
+    index: usize,
+    pass: Pass,
+    // `None` for the graph's output
+    output: Option<usize>,
+}
+
+pub(crate) struct GraphLayer {
+    graph: PassGraph,
+    // The pool, the first texture being where the chain renders ahead of the graph
+    textures: Vec<wgpu::TextureView>,
+    passes: Vec<ScheduledPass>,
+}
+
+impl GraphLayer {
+    // The layer running `graph`, built with the shader errors of its passes
+    pub fn new(
+        device: &wgpu::Device,
+        resources: &PassResources,
+        bindings: &PassBindings,
+        size: [u32; 2],
+        graph: PassGraph,
+    ) -> Result<(Self, Vec<ShaderError>), PassGraphError> {
+        let allocation = graph.allocate()?;
+        let textures: Vec<wgpu::TextureView> = (0..allocation.textures)
+            .map(|_| {
+                wgpu::TextureBuilder::new()
+                    .size(size)
+                    .usage(
+                        wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::TEXTURE_BINDING,
+                    )
+                    .format(wgpu::TextureFormat::Rgba16Float)
+                    .build(device)
+                    .view()
+                    .build()
+            })
+            .collect();
+
+        let mut errors = Vec::new();
+        let passes = allocation
+            .order
+            .iter()
+            .zip(&allocation.inputs)
+            .zip(&allocation.outputs)
+            .map(|((&index, inputs), &output)| {
+                let graph_pass = &graph.passes[index];
+                let src = &textures[inputs[0]];
+                let extra: Vec<PassInput> = inputs[1..]
+                    .iter()
+                    .map(|&input| PassInput::Texture(&textures[input]))
+                    .collect();
+                let params: Vec<(&str, f32)> = graph_pass
+                    .params
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), *value))
+                    .collect();
+                let (pass, error) = Pass::new(
+                    device,
+                    resources,
+                    &PassBindings {
+                        inputs: [src, src],
+                        ..*bindings
+                    },
+                    &graph_pass.label,
+                    &graph_pass.source,
+                    &params,
+                    &[],
+                    false,
+                    PassInputs::new(device, resources, false, &extra),
+                );
+                errors.extend(error);
+                ScheduledPass {
+                    index,
+                    pass,
+                    output,
+                }
+            })
+            .collect();
+
+        let layer = Self {
+            graph,
+            textures,
+            passes,
+        };
+        Ok((layer, errors))
+    }
+
+    // The graph, with its params as last set
+    pub fn graph(&self) -> &PassGraph {
+        &self.graph
+    }
+
+    // Where the chain renders ahead of the graph
+    pub fn source_view(&self) -> &wgpu::TextureView {
+        &self.textures[0]
+    }
+
+    // The scheduled passes in order, with the pool texture each writes, `None` for
+    // the graph's output
+    pub fn scheduled(&self) -> impl Iterator<Item = (&GraphPass, Option<usize>)> {
+        self.passes
+            .iter()
+            .map(|scheduled| (&self.graph.passes[scheduled.index], scheduled.output))
+    }
+
+    pub fn texture_view(&self, texture: usize) -> &wgpu::TextureView {
+        &self.textures[texture]
+    }
+
+    pub fn params_buffers(&self) -> impl Iterator<Item = &crate::upload::UniformBuffer> {
+        self.passes
+            .iter()
+            .map(|scheduled| scheduled.pass.params_buffer())
+    }
+
+    // Set a param of the pass writing `output`
+    pub fn set_param(&mut self, output: &str, name: &str, value: f32) -> bool {
+        let Some(index) = self
+            .graph
+            .passes
+            .iter()
+            .position(|pass| pass.output == output)
+        else {
+            return false;
+        };
+        let Some(param) = self.graph.passes[index]
+            .params
+            .iter_mut()
+            .find(|(param, _)| param == name)
+        else {
+            return false;
+        };
+        param.1 = value;
+        // Passes nothing reads aren't scheduled, and only keep the value
+        if let Some(scheduled) = self
+            .passes
+            .iter_mut()
+            .find(|scheduled| scheduled.index == index)
+        {
+            scheduled.pass.set_param(name, value);
+        }
+        true
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "pass_graph", skip_all)
+    )]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        for scheduled in &self.passes {
+            let view = match scheduled.output {
+                Some(texture) => &self.textures[texture],
+                None => target,
+            };
+            scheduled.pass.encode(encoder, 0, view);
+        }
+    }
+}
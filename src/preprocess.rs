@@ -0,0 +1,227 @@
+// src/preprocess.rs
+//
+// A minimal WGSL preprocessor
+//
+// WGSL has no way to share code between modules, so the fullscreen vertex stage,
+// color conversions and hashes used to be pasted into every shader needing them.
+// Shaders can instead pull in shared files and switch code on defines:
+//
+//     #include "nnpipe/color.wgsl"
+//     #define TAPS 9
+//     #ifdef DITHER
+//     ...
+//     #else
+//     ...
+//     #endif
+//
+// Each include is expanded once, however often it's included, so files can include
+// what they use without clashing definitions. A define replaces its name wherever it
+// appears as a whole word after the `#define`. Effect passes are preprocessed with the
+// crate's own includes (`nnpipe/fullscreen.wgsl`, `nnpipe/color.wgsl` and
+// `nnpipe/noise.wgsl`) before they're compiled.
+
+use std::collections::{BTreeMap, HashSet};
+
+// The crate's shared shader files
+const INCLUDES: &[(&str, &str)] = &[
+    (
+        "nnpipe/fullscreen.wgsl",
+        include_str!("shaders/fullscreen.wgsl"),
+    ),
+    (
+        "nnpipe/color.wgsl",
+        include_str!("shaders/common/color.wgsl"),
+    ),
+    (
+        "nnpipe/noise.wgsl",
+        include_str!("shaders/common/noise.wgsl"),
+    ),
+];
+
+/// A shader that failed to preprocess.
+#[derive(Clone, Debug)]
+pub struct PreprocessError {
+    pub message: String,
+}
+
+impl std::fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "preprocess error: {}", self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Expands `#include`, `#define` and `#ifdef`/`#ifndef`/`#else`/`#endif` in WGSL.
+///
+/// Knows the crate's includes from the start; add a sketch's own shared files with
+/// [`ShaderPreprocessor::with_include`] and run its shaders through it before adding
+/// them as passes:
+///
+/// ```ignore
+/// let source = ShaderPreprocessor::new()
+///     .with_include("sketch/palette.wgsl", PALETTE_LIB)
+///     .with_define("STEPS", "8")
+///     .process(GLOW_PASS)?;
+/// pipeline.add_reflected_pass(&device, "Glow", &source);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShaderPreprocessor {
+    includes: BTreeMap<String, String>,
+    defines: BTreeMap<String, String>,
+}
+
+impl Default for ShaderPreprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderPreprocessor {
+    pub fn new() -> Self {
+        Self {
+            includes: INCLUDES
+                .iter()
+                .map(|(name, source)| (name.to_string(), source.to_string()))
+                .collect(),
+            defines: BTreeMap::new(),
+        }
+    }
+
+    /// Make `source` available as `#include "<name>"`, replacing any file of that
+    /// name.
+    pub fn with_include(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.includes.insert(name.into(), source.into());
+        self
+    }
+
+    /// Define `name` as `value` before the shader's first line, as `#define` would.
+    pub fn with_define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.insert(name.into(), value.into());
+        self
+    }
+
+    /// Expand `source`. Fails on unknown directives and includes, and unbalanced
+    /// conditionals, with the line in the message.
+    pub fn process(&self, source: &str) -> Result<String, PreprocessError> {
+        let mut state = State {
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+        };
+        let mut output = String::with_capacity(source.len());
+        self.expand(source, "shader", &mut state, &mut output)?;
+        Ok(output)
+    }
+
+    fn expand(
+        &self,
+        source: &str,
+        file: &str,
+        state: &mut State,
+        output: &mut String,
+    ) -> Result<(), PreprocessError> {
+        // Whether each open conditional's current branch is taken, and its line
+        let mut branches: Vec<(bool, usize)> = Vec::new();
+        for (number, line) in source.lines().enumerate() {
+            let error = |message: String| PreprocessError {
+                message: format!("{file}:{}: {message}", number + 1),
+            };
+            let active = branches.iter().all(|&(taken, _)| taken);
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if active {
+                    output.push_str(&substitute(line, &state.defines));
+                }
+                output.push('\n');
+                continue;
+            };
+            output.push('\n');
+
+            let (keyword, argument) = directive
+                .trim()
+                .split_once(char::is_whitespace)
+                .map_or((directive.trim(), ""), |(keyword, rest)| {
+                    (keyword, rest.trim())
+                });
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let defined = state.defines.contains_key(argument);
+                    branches.push((defined == (keyword == "ifdef"), number + 1));
+                }
+                "else" => {
+                    let (taken, _) = branches
+                        .last_mut()
+                        .ok_or_else(|| error("#else without #ifdef".to_string()))?;
+                    *taken = !*taken;
+                }
+                "endif" => {
+                    branches
+                        .pop()
+                        .ok_or_else(|| error("#endif without #ifdef".to_string()))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let (name, value) = argument
+                        .split_once(char::is_whitespace)
+                        .map_or((argument, ""), |(name, value)| (name, value.trim()));
+                    if name.is_empty() {
+                        return Err(error("#define without a name".to_string()));
+                    }
+                    state.defines.insert(name.to_string(), value.to_string());
+                }
+                "include" => {
+                    let name = argument
+                        .strip_prefix('"')
+                        .and_then(|name| name.strip_suffix('"'))
+                        .ok_or_else(|| error(format!("include '{argument}' isn't quoted")))?;
+                    let included = self
+                        .includes
+                        .get(name)
+                        .ok_or_else(|| error(format!("no include '{name}'")))?;
+                    if state.included.insert(name.to_string()) {
+                        self.expand(included, name, state, output)?;
+                    }
+                }
+                _ => return Err(error(format!("unknown directive '#{keyword}'"))),
+            }
+        }
+        if let Some((_, line)) = branches.last() {
+            return Err(PreprocessError {
+                message: format!("{file}:{line}: #ifdef without #endif"),
+            });
+        }
+        Ok(())
+    }
+}
+
+// Defines and includes seen so far, shared across included files
+struct State {
+    defines: BTreeMap<String, String>,
+    included: HashSet<String>,
+}
+
+// `line` with every whole-word define replaced by its value
+fn substitute(line: &str, defines: &BTreeMap<String, String>) -> String {
+    if defines.is_empty() {
+        return line.to_string();
+    }
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(is_ident) {
+        output.push_str(&rest[..start]);
+        let word = &rest[start..];
+        let end = word.find(|c: char| !is_ident(c)).unwrap_or(word.len());
+        let word = &word[..end];
+        output.push_str(defines.get(word).map_or(word, String::as_str));
+        rest = &rest[start + end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+// Expand one of the crate's own shaders, which only use the crate's includes
+pub(crate) fn expand(source: &str) -> String {
+    ShaderPreprocessor::new()
+        .process(source)
+        .expect("crate shaders preprocess")
+}
@@ -0,0 +1,112 @@
+// src/projection.rs
+//
+// Dome projections
+//
+// Planetarium and dome projectors take a domemaster: a circular fisheye image in
+// which the distance from the center is the angle from the dome's axis. An output
+// with a fisheye projection turns each pixel of the circle into a direction, turns it
+// by the projection's orientation and looks the direction up in the frame, taken as
+// either a flat view with a field of view or a 360° equirectangular panorama.
+
+use std::f32::consts::PI;
+
+/// How an output lays the frame out, see
+/// [`Output::set_projection`](crate::Output::set_projection).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "snake_case"))]
+pub enum OutputProjection {
+    /// The frame as it is, fitted by the output's fit mode.
+    #[default]
+    Flat,
+    /// A domemaster for dome installations.
+    Fisheye(FisheyeProjection),
+}
+
+/// A circular fisheye filling the largest circle of the target, centered. The circle's
+/// center looks at the frame's center unless turned by the orientation; its edge is
+/// `fov / 2` away from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct FisheyeProjection {
+    /// Field of view across the circle in radians, π for a hemisphere, up to 2π.
+    pub fov: f32,
+    /// Turn of the view to the right, in radians.
+    pub yaw: f32,
+    /// Tilt of the view upwards, in radians.
+    pub pitch: f32,
+    /// Clockwise turn of the picture within the circle, in radians.
+    pub roll: f32,
+    pub source: FisheyeSource,
+}
+
+impl Default for FisheyeProjection {
+    fn default() -> Self {
+        Self {
+            fov: PI,
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            source: FisheyeSource::default(),
+        }
+    }
+}
+
+/// What the frame of a [`FisheyeProjection`] shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "snake_case"))]
+pub enum FisheyeSource {
+    /// A flat view with a horizontal field of view in radians, below π. Directions
+    /// outside it show the output's background.
+    Perspective { fov: f32 },
+    /// A 360° panorama, longitude across and latitude down.
+    Equirectangular,
+}
+
+impl Default for FisheyeSource {
+    fn default() -> Self {
+        FisheyeSource::Perspective { fov: PI / 2.0 }
+    }
+}
+
+impl OutputProjection {
+    // Values of the output shader's projection uniforms: the mode, half the dome's
+    // field of view, the tangent of half the source's, and the orientation's rows
+    pub(crate) fn shader_values(self) -> ([f32; 3], [[f32; 3]; 3]) {
+        let OutputProjection::Fisheye(fisheye) = self else {
+            return ([0.0; 3], IDENTITY);
+        };
+        let half_fov = fisheye.fov.clamp(0.01, 2.0 * PI) * 0.5;
+        let (mode, tangent) = match fisheye.source {
+            FisheyeSource::Perspective { fov } => (1.0, (fov.clamp(0.01, PI - 0.01) * 0.5).tan()),
+            FisheyeSource::Equirectangular => (2.0, 0.0),
+        };
+        ([mode, half_fov, tangent], fisheye.rotation())
+    }
+}
+
+const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+impl FisheyeProjection {
+    // Rows of the rotation taking the dome's directions to the frame's, in a space of
+    // x right, y down and z into the frame: roll, then pitch, then yaw
+    fn rotation(&self) -> [[f32; 3]; 3] {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        let (sr, cr) = self.roll.sin_cos();
+        let yaw = [[cy, 0.0, sy], [0.0, 1.0, 0.0], [-sy, 0.0, cy]];
+        // Up is -y, so tilting up turns z towards -y
+        let pitch = [[1.0, 0.0, 0.0], [0.0, cp, -sp], [0.0, sp, cp]];
+        // Turning the view counterclockwise turns the picture clockwise
+        let roll = [[cr, sr, 0.0], [-sr, cr, 0.0], [0.0, 0.0, 1.0]];
+        multiply(multiply(yaw, pitch), roll)
+    }
+}
+
+fn multiply(a: [[f32; 3]; 3], b: [[f32; 3]; 3]) -> [[f32; 3]; 3] {
+    std::array::from_fn(|row| {
+        std::array::from_fn(|column| (0..3).map(|k| a[row][k] * b[k][column]).sum())
+    })
+}
@@ -0,0 +1,135 @@
+// src/quality.rs
+//
+// Quality presets
+//
+// The bloom is the pipeline's most expensive stage, and its cost scales with the
+// bloom textures' size and format, the blur radius (one texture tap per texel of it)
+// and the FFT domain. A preset sets all of these at once, so integrated, software
+// and web adapters keep a usable frame rate instead of the sketch failing or
+// crawling, and fast GPUs can spend more. `QualityPreset::detect` picks one from what
+// an adapter reports.
+
+use nannou::wgpu;
+
+use crate::nnpipe::UpsampleFilter;
+
+/// Bundled quality settings, from the cheapest to the most faithful.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum QualityPreset {
+    /// A quarter resolution, 8-bit bloom with a short blur.
+    Low,
+    /// A half resolution bloom, upsampled with Catmull-Rom to hide the lower
+    /// resolution.
+    Medium,
+    /// The full resolution bloom [`Nnpipe::new`](crate::Nnpipe::new) uses.
+    #[default]
+    High,
+    /// Like `High`, with a longer blur and a finer FFT domain.
+    Ultra,
+}
+
+impl QualityPreset {
+    /// Pick a preset for `adapter` from its type, limits and downlevel capabilities.
+    ///
+    /// Software rasterizers get `Low`; downlevel adapters (WebGL, GLES, older mobile
+    /// GPUs) and ones with small texture limits get `Medium`; everything else `High`.
+    /// `Ultra` is never picked, as it's only worth it where the frame time allows.
+    pub fn detect(adapter: &wgpu::Adapter) -> Self {
+        let info = adapter.get_info();
+        let limits = adapter.limits();
+        if info.device_type == wgpu::DeviceType::Cpu {
+            QualityPreset::Low
+        } else if !adapter.get_downlevel_capabilities().is_webgpu_compliant()
+            || limits.max_texture_dimension_2d < 8192
+        {
+            QualityPreset::Medium
+        } else {
+            QualityPreset::High
+        }
+    }
+
+    /// The settings of the preset.
+    pub fn profile(self) -> QualityProfile {
+        let (bloom_format, bloom_scale, max_blur_radius, upsample_filter, max_fft_size) = match self
+        {
+            QualityPreset::Low => (
+                wgpu::TextureFormat::Rgba8Unorm,
+                0.25,
+                10.0,
+                UpsampleFilter::Bilinear,
+                128,
+            ),
+            QualityPreset::Medium => (
+                wgpu::TextureFormat::Rgba16Float,
+                0.5,
+                20.0,
+                UpsampleFilter::CatmullRom,
+                256,
+            ),
+            QualityPreset::High => (
+                wgpu::TextureFormat::Rgba16Float,
+                1.0,
+                40.0,
+                UpsampleFilter::Bilinear,
+                512,
+            ),
+            QualityPreset::Ultra => (
+                wgpu::TextureFormat::Rgba16Float,
+                1.0,
+                64.0,
+                UpsampleFilter::Bilinear,
+                1024,
+            ),
+        };
+        QualityProfile {
+            preset: self,
+            bloom_format,
+            bloom_scale,
+            max_blur_radius,
+            upsample_filter,
+            max_fft_size,
+        }
+    }
+}
+
+/// The settings a pipeline is created with by
+/// [`Nnpipe::with_quality`](crate::Nnpipe::with_quality), or switched to with
+/// [`Nnpipe::set_quality`](crate::Nnpipe::set_quality).
+///
+/// Start from a preset's and change fields to fine-tune it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityProfile {
+    /// The preset the settings come from.
+    pub preset: QualityPreset,
+    /// Format of the bloom textures. `Rgba8Unorm` halves their bandwidth, at the cost
+    /// of clipping the bright pass at 1 and banding in faint glows.
+    pub bloom_format: wgpu::TextureFormat,
+    /// Resolution of the bloom relative to the pipeline's, see
+    /// [`Nnpipe::set_bloom_scale`](crate::Nnpipe::set_bloom_scale).
+    pub bloom_scale: f32,
+    /// Cap on the blur radius, in bloom texels, see
+    /// [`Nnpipe::set_max_blur_radius`](crate::Nnpipe::set_max_blur_radius).
+    pub max_blur_radius: f32,
+    /// See [`Nnpipe::set_upsample_filter`](crate::Nnpipe::set_upsample_filter).
+    pub upsample_filter: UpsampleFilter,
+    /// Largest FFT bloom domain, in texels per side, a power of two from 64. The
+    /// device's compute limits may allow less.
+    pub max_fft_size: u32,
+}
+
+impl From<QualityPreset> for QualityProfile {
+    fn from(preset: QualityPreset) -> Self {
+        preset.profile()
+    }
+}
+
+impl Default for QualityProfile {
+    fn default() -> Self {
+        QualityPreset::High.profile()
+    }
+}
@@ -0,0 +1,152 @@
+// src/randomize.rs
+//
+// Constrained randomization of parameters
+//
+// Exploring looks by hand means nudging one knob at a time. The randomizer rolls new
+// values for many params at once, each within a range the host gives, or within a
+// spread around its current value, and reports what it rolled as a config: a look
+// worth keeping is promoted to a preset by saving that config. Rolls are seeded, so
+// a result can also be found again from its seed. Only built with the `config`
+// feature.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use nannou::rand::rngs::SmallRng;
+use nannou::rand::{random, Rng, SeedableRng};
+
+use crate::config::{PassConfig, PipelineConfig};
+use crate::nnpipe::Nnpipe;
+use crate::params::CHANNEL_THRESHOLD_PARAMS;
+
+/// The params [`Nnpipe::randomize`] rolls.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum RandomScope {
+    /// The bloom settings and the params of every pass.
+    #[default]
+    All,
+    Bloom,
+    /// The params of the passes at these indices.
+    Passes(Vec<usize>),
+}
+
+impl RandomScope {
+    fn contains(&self, pass: Option<usize>) -> bool {
+        match self {
+            RandomScope::All => true,
+            RandomScope::Bloom => pass.is_none(),
+            RandomScope::Passes(indices) => pass.is_some_and(|pass| indices.contains(&pass)),
+        }
+    }
+}
+
+/// Where [`Nnpipe::randomize`] rolls values.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RandomConstraints {
+    /// Ranges of params by path (see [`Nnpipe::list_params`]).
+    pub ranges: BTreeMap<String, Range<f32>>,
+    /// How far params without a range stray from their value, as a fraction of it
+    /// either way. At 0, the default, they're left alone.
+    pub spread: f32,
+    /// Seed of the rolls, for results that can be found again. Random unless set.
+    pub seed: Option<u64>,
+}
+
+impl RandomConstraints {
+    /// Roll the param at `path` within `range`.
+    pub fn with_range(mut self, path: impl Into<String>, range: Range<f32>) -> Self {
+        self.ranges.insert(path.into(), range);
+        self
+    }
+
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl Nnpipe {
+    /// Roll new values for the params in `scope`, within `constraints`, and return
+    /// them as a config.
+    ///
+    /// Params with a range are rolled uniformly within it, and the others within the
+    /// constraints' spread around their value. Passes' `enabled` is never rolled, and
+    /// their `mix` and the bloom's channel thresholds only with a range. Save the
+    /// returned config to keep the look as a preset, or apply it to another pipeline.
+    /// With the history enabled, the settings before the roll are recorded as one
+    /// step.
+    pub fn randomize(
+        &mut self,
+        scope: &RandomScope,
+        constraints: &RandomConstraints,
+    ) -> PipelineConfig {
+        let mut rng = SmallRng::seed_from_u64(constraints.seed.unwrap_or_else(random));
+        let mut preset = PipelineConfig::default();
+
+        for path in self.list_params() {
+            let (pass, name) = if let Some(name) = path.strip_prefix("bloom.") {
+                (None, name)
+            } else if let Some(path) = path.strip_prefix("passes.") {
+                let (index, name) = path.split_once('.').expect("under passes.<index>.");
+                (Some(index.parse().expect("an index")), name)
+            } else {
+                // Macros only drive the other params
+                continue;
+            };
+            if name == "enabled" || !scope.contains(pass) {
+                continue;
+            }
+
+            let value = match constraints.ranges.get(&path) {
+                Some(range) => range.start + (range.end - range.start) * rng.gen::<f32>(),
+                None if name != "mix"
+                    && !CHANNEL_THRESHOLD_PARAMS.contains(&name)
+                    && constraints.spread > 0.0 =>
+                {
+                    let value = self.get_param(&path).expect("a listed param");
+                    value * (1.0 + constraints.spread * rng.gen_range(-1.0..=1.0))
+                }
+                None => continue,
+            };
+
+            let Some(index) = pass else {
+                if let Some(channel) = CHANNEL_THRESHOLD_PARAMS.iter().position(|n| *n == name) {
+                    let thresholds = preset.bloom.channel_thresholds.get_or_insert_with(|| {
+                        [0, 1, 2].map(|channel| {
+                            let path = format!("bloom.{}", CHANNEL_THRESHOLD_PARAMS[channel]);
+                            self.get_param(&path).expect("a listed param")
+                        })
+                    });
+                    thresholds[channel] = value;
+                    continue;
+                }
+                let setting = preset.bloom.param_mut(name).expect("a bloom param");
+                *setting = Some(value);
+                continue;
+            };
+            if preset.passes.last().and_then(|config| config.index) != Some(index) {
+                preset.passes.push(PassConfig {
+                    index: Some(index),
+                    label: self.custom_pass(index).map(|pass| pass.label.clone()),
+                    ..Default::default()
+                });
+            }
+            let pass_config = preset.passes.last_mut().expect("pushed above");
+            if name == "mix" {
+                pass_config.mix = Some(value);
+            } else {
+                pass_config.params.insert(name.to_string(), value);
+            }
+        }
+
+        // Every rolled param was listed by the pipeline, so applying can't fail
+        self.record(None);
+        let _ = self.apply_settings(&preset);
+        preset
+    }
+}
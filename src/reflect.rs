@@ -0,0 +1,191 @@
+// src/reflect.rs
+//
+// Params reflected from a pass's WGSL
+//
+// A custom pass's params are matched to its `Params` struct by position only, so
+// declaring them by hand means keeping two lists in step. Reflection reads the
+// struct with naga instead: each member becomes a param, vectors one per component
+// (`color.x`, `color.y`, ...), with a default taken from a trailing comment:
+//
+//     struct Params {
+//         tint: vec3<f32>,   // default: 1.0, 0.8, 0.6
+//         amount: f32,       // default: 0.5
+//     }
+//
+// Members without a default start at 0. Params are written as packed f32s, so
+// every member must be an f32 or a vector of f32 laid out without padding; a
+// `vec3` aligns to 16 bytes, so it goes first or after a multiple of four floats.
+
+use crate::pass::{preprocess, ShaderError};
+
+/// The WGSL type of a reflected param.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamType {
+    F32,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+impl ParamType {
+    /// Number of f32 components.
+    pub fn components(self) -> usize {
+        match self {
+            ParamType::F32 => 1,
+            ParamType::Vec2 => 2,
+            ParamType::Vec3 => 3,
+            ParamType::Vec4 => 4,
+        }
+    }
+}
+
+/// A member of a pass's `Params` struct, as found by [`ParamDescriptor::reflect`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamDescriptor {
+    pub name: String,
+    pub ty: ParamType,
+    /// One value per component.
+    pub default: Vec<f32>,
+}
+
+impl ParamDescriptor {
+    /// Reflect the members of the uniform at `@group(0) @binding(2)` in `source`.
+    /// A shader without one has no params.
+    ///
+    /// Fails, with `label` in the error, if `source` doesn't parse, if the uniform
+    /// isn't a struct of f32s and f32 vectors packed without padding, or if a default
+    /// doesn't have one value or one per component.
+    pub fn reflect(label: &str, source: &str) -> Result<Vec<Self>, ShaderError> {
+        let error = |message: String| ShaderError {
+            label: label.to_string(),
+            message,
+        };
+        let source = &preprocess(label, source)?;
+        let module = naga::front::wgsl::parse_str(source)
+            .map_err(|parse_error| error(parse_error.emit_to_string(source)))?;
+
+        let Some((_, uniform)) = module.global_variables.iter().find(|(_, global)| {
+            global.space == naga::AddressSpace::Uniform
+                && global
+                    .binding
+                    .as_ref()
+                    .is_some_and(|binding| binding.group == 0 && binding.binding == 2)
+        }) else {
+            return Ok(Vec::new());
+        };
+        let ty = &module.types[uniform.ty];
+        let naga::TypeInner::Struct { members, .. } = &ty.inner else {
+            return Err(error("params uniform is not a struct".to_string()));
+        };
+        let struct_source = ty
+            .name
+            .as_deref()
+            .and_then(|name| struct_body(source, name))
+            .unwrap_or_default();
+
+        let mut offset = 0;
+        let mut descriptors = Vec::with_capacity(members.len());
+        for member in members {
+            let name = member.name.clone().unwrap_or_default();
+            let ty = match module.types[member.ty].inner {
+                naga::TypeInner::Scalar {
+                    kind: naga::ScalarKind::Float,
+                    width: 4,
+                } => ParamType::F32,
+                naga::TypeInner::Vector {
+                    size,
+                    kind: naga::ScalarKind::Float,
+                    width: 4,
+                } => match size {
+                    naga::VectorSize::Bi => ParamType::Vec2,
+                    naga::VectorSize::Tri => ParamType::Vec3,
+                    naga::VectorSize::Quad => ParamType::Vec4,
+                },
+                _ => {
+                    return Err(error(format!(
+                        "param '{name}' is not an f32 or a vector of f32"
+                    )))
+                }
+            };
+            if member.offset != offset {
+                return Err(error(format!(
+                    "param '{name}' is padded to offset {}, after {offset} bytes of params",
+                    member.offset
+                )));
+            }
+            offset += 4 * ty.components() as u32;
+
+            let default = match member_default(struct_source, &name) {
+                None => vec![0.0; ty.components()],
+                Some(Some(values)) if values.len() == 1 => vec![values[0]; ty.components()],
+                Some(Some(values)) if values.len() == ty.components() => values,
+                Some(_) => {
+                    return Err(error(format!(
+                        "default of param '{name}' needs 1 or {} numbers",
+                        ty.components()
+                    )))
+                }
+            };
+            descriptors.push(Self { name, ty, default });
+        }
+        Ok(descriptors)
+    }
+
+    /// The params as a pass takes them: one per component, with vectors' named
+    /// `<name>.x`, `<name>.y`, ...
+    pub fn params(descriptors: &[Self]) -> Vec<(String, f32)> {
+        descriptors
+            .iter()
+            .flat_map(|descriptor| {
+                let names: Vec<String> = match descriptor.ty {
+                    ParamType::F32 => vec![descriptor.name.clone()],
+                    ty => ["x", "y", "z", "w"][..ty.components()]
+                        .iter()
+                        .map(|component| format!("{}.{component}", descriptor.name))
+                        .collect(),
+                };
+                names.into_iter().zip(descriptor.default.iter().copied())
+            })
+            .collect()
+    }
+}
+
+// The text between the braces of `struct <name>`
+fn struct_body<'a>(source: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = source;
+    while let Some(start) = rest.find("struct") {
+        let after = &rest[start + "struct".len()..];
+        let is_word = rest[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric() && c != '_');
+        if is_word {
+            if let Some(body) = after.trim_start().strip_prefix(name) {
+                if let Some(body) = body.trim_start().strip_prefix('{') {
+                    return body.find('}').map(|end| &body[..end]);
+                }
+            }
+        }
+        rest = after;
+    }
+    None
+}
+
+// The values in a `// default:` comment on the line declaring `member`: `None` if
+// there's no such comment, `Some(None)` if it doesn't parse
+fn member_default(struct_source: &str, member: &str) -> Option<Option<Vec<f32>>> {
+    struct_source.lines().find_map(|line| {
+        let (code, comment) = line.split_once("//")?;
+        let declared = code.split(':').next()?.split_whitespace().next_back()?;
+        if declared != member {
+            return None;
+        }
+        let values = comment.trim().strip_prefix("default:")?;
+        Some(
+            values
+                .split(',')
+                .map(|value| value.trim().parse().ok())
+                .collect(),
+        )
+    })
+}
@@ -0,0 +1,147 @@
+// src/regions.rs
+//
+// Param regions
+//
+// Split stages and side-by-side comparisons want one look on the left and another on
+// the right. A param region is a rect of the frame with overrides of any params; after
+// the frame is processed, the chain runs again per region with its overrides applied
+// and the region's part of the result is drawn over the frame. Each region costs a
+// run of the whole chain.
+
+use nannou::wgpu;
+
+use crate::nnpipe::Nnpipe;
+
+/// A part of the frame rendered with other params, see
+/// [`Nnpipe::add_param_region`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamRegion {
+    /// Left, top, width and height, in fractions of the frame from its top left.
+    pub rect: [f32; 4],
+    /// Paths of the params to override, see [`Nnpipe::list_params`], and their values
+    /// in the region.
+    pub overrides: Vec<(String, f32)>,
+}
+
+impl ParamRegion {
+    pub fn new(rect: [f32; 4]) -> Self {
+        Self {
+            rect,
+            overrides: Vec::new(),
+        }
+    }
+
+    pub fn left_half() -> Self {
+        Self::new([0.0, 0.0, 0.5, 1.0])
+    }
+
+    pub fn right_half() -> Self {
+        Self::new([0.5, 0.0, 0.5, 1.0])
+    }
+
+    pub fn top_half() -> Self {
+        Self::new([0.0, 0.0, 1.0, 0.5])
+    }
+
+    pub fn bottom_half() -> Self {
+        Self::new([0.0, 0.5, 1.0, 0.5])
+    }
+
+    /// The quarter of the frame at `column` and `row`, each 0 or 1.
+    pub fn quadrant(column: u32, row: u32) -> Self {
+        let [x, y] = [column, row].map(|index| index.min(1) as f32 * 0.5);
+        Self::new([x, y, 0.5, 0.5])
+    }
+
+    /// The region with the param at `path` set to `value`, replacing any override of
+    /// it.
+    pub fn with_override(mut self, path: impl Into<String>, value: f32) -> Self {
+        let path = path.into();
+        self.overrides.retain(|(other, _)| *other != path);
+        self.overrides.push((path, value));
+        self
+    }
+
+    // The region in pixels of a frame of `size`, as x, y, width and height, or None if
+    // it covers none. Edges round to the nearest pixel, so regions sharing an edge
+    // tile the frame.
+    pub(crate) fn pixels(&self, size: [u32; 2]) -> Option<[u32; 4]> {
+        let [x, y, width, height] = self.rect;
+        let edge =
+            |fraction: f32, side: u32| (fraction.clamp(0.0, 1.0) * side as f32).round() as u32;
+        let [left, top] = [edge(x, size[0]), edge(y, size[1])];
+        let [right, bottom] = [edge(x + width, size[0]), edge(y + height, size[1])];
+        (right > left && bottom > top).then_some([left, top, right - left, bottom - top])
+    }
+}
+
+impl Nnpipe {
+    /// Add a region drawn by [`Nnpipe::process_regions`] and return its index.
+    /// Regions added later are drawn over earlier ones where they overlap.
+    pub fn add_param_region(&mut self, region: ParamRegion) -> usize {
+        self.param_regions.push(region);
+        self.param_regions.len() - 1
+    }
+
+    /// Remove the region at `index`, moving later ones down.
+    pub fn remove_param_region(&mut self, index: usize) -> Option<ParamRegion> {
+        (index < self.param_regions.len()).then(|| self.param_regions.remove(index))
+    }
+
+    pub fn param_regions(&self) -> &[ParamRegion] {
+        &self.param_regions
+    }
+
+    pub fn param_region_mut(&mut self, index: usize) -> Option<&mut ParamRegion> {
+        self.param_regions.get_mut(index)
+    }
+
+    /// Draw the param regions over a processed frame, each rendered by running the
+    /// chain again with its overrides applied. The params are back to their own
+    /// values afterwards.
+    ///
+    /// Call it right after processing the frame into `output_view`, or into the
+    /// pipeline's output texture with [`Nnpipe::render`] if `None`. `input` is the
+    /// scene, as for [`Nnpipe::process_texture`], or `None` for the scene last drawn
+    /// by [`Nnpipe::process`] or [`Nnpipe::process_with`]. Overrides the pipeline
+    /// doesn't have are skipped. Temporal effects see each region's run as a frame.
+    pub fn process_regions(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        input: Option<&wgpu::TextureView>,
+        output_view: Option<&wgpu::TextureView>,
+    ) {
+        let size = self.size();
+        let regions: Vec<_> = self
+            .param_regions
+            .iter()
+            .filter_map(|region| Some((region.pixels(size)?, region.overrides.clone())))
+            .collect();
+        if regions.is_empty() {
+            return;
+        }
+        let region_view = self.take_region_view(device);
+
+        for (pixels, overrides) in regions {
+            let previous: Vec<(String, f32)> = overrides
+                .iter()
+                .filter_map(|(path, _)| Some((path.clone(), self.get_param(path)?)))
+                .collect();
+            // Overriding one channel's threshold switches on the channel thresholds
+            let channel_thresholds = self.channel_thresholds;
+            for (path, value) in &overrides {
+                self.apply_param(path, *value);
+            }
+            let scene = input.unwrap_or(&self.scene_view);
+            self.process_texture(device, queue, scene, &region_view);
+            for (path, value) in previous.iter().rev() {
+                self.apply_param(path, *value);
+            }
+            self.set_channel_thresholds(channel_thresholds);
+            self.draw_region(device, queue, &region_view, output_view, pixels);
+        }
+
+        self.finish_regions(device, queue, region_view, output_view);
+    }
+}
@@ -0,0 +1,184 @@
+// src/replay.rs
+//
+// Instant replay
+//
+// The best moments of an improvised set are only known to be worth keeping after
+// they happened. An instant replay keeps the last few seconds of output frames on
+// the GPU in a ring of textures, scaled down to keep the memory in check, and writes
+// them out as an image sequence on request. Recording a frame is a single draw into
+// the ring; nothing is read back until the export.
+
+use std::collections::VecDeque;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use nannou::wgpu;
+
+use crate::error::{check_size, Result};
+use crate::nnpipe::Nnpipe;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::nnpipe::{encode_srgb8, read_texture};
+
+// A recorded frame and the pipeline's time when it was taken
+#[derive(Debug)]
+struct ReplayFrame {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    time: f32,
+}
+
+/// Keeps the last seconds of a pipeline's output for [`InstantReplay::export_replay`].
+///
+/// Call [`InstantReplay::record`] after rendering every frame:
+///
+/// ```ignore
+/// let mut replay = InstantReplay::new(&mut pipeline, device, 10.0, 30.0, 0.5)?;
+/// // every frame
+/// pipeline.render(device, queue, &mut renderer, &draw);
+/// replay.record(&pipeline, device, queue);
+/// // when something great just happened
+/// replay.export_replay(device, queue, "replays/encore")?;
+/// ```
+#[derive(Debug)]
+pub struct InstantReplay {
+    // Pipeline output the frames are drawn through, scaling them to `size`
+    output: usize,
+    size: [u32; 2],
+    interval: f32,
+    capacity: usize,
+    // Recorded frames, oldest first
+    frames: VecDeque<ReplayFrame>,
+}
+
+impl InstantReplay {
+    /// A replay of the last `seconds` of `pipeline`'s output at `fps` frames per
+    /// second, with frames at `scale` times the pipeline's size.
+    ///
+    /// Frames are drawn through an output of the pipeline, which this adds; its
+    /// adjustments and transform apply to the replay. Memory for the frames is
+    /// allocated as they're recorded, at 8 bytes per pixel. Fails if the scaled size
+    /// exceeds the device's texture limits.
+    pub fn new(
+        pipeline: &mut Nnpipe,
+        device: &wgpu::Device,
+        seconds: f32,
+        fps: f32,
+        scale: f32,
+    ) -> Result<Self> {
+        let size = pipeline
+            .size()
+            .map(|side| ((side as f32 * scale).round() as u32).max(1));
+        check_size(device, size[0], size[1])?;
+        let output = pipeline.add_output(device, wgpu::TextureFormat::Rgba16Float)?;
+        let fps = fps.max(1e-3);
+        Ok(Self {
+            output,
+            size,
+            interval: 1.0 / fps,
+            capacity: ((seconds * fps).ceil() as usize).max(1),
+            frames: VecDeque::new(),
+        })
+    }
+
+    /// Size of the recorded frames.
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Frames recorded and not yet pushed out by newer ones.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Seconds between the oldest and the newest recorded frame.
+    pub fn duration(&self) -> f32 {
+        match (self.frames.front(), self.frames.back()) {
+            (Some(oldest), Some(newest)) => newest.time - oldest.time,
+            _ => 0.0,
+        }
+    }
+
+    /// Drop every recorded frame.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Record `pipeline`'s output texture, as left by the last [`Nnpipe::render`],
+    /// replacing the oldest frame once the replay is full.
+    ///
+    /// Frames are spaced by the pipeline's [`time`](Nnpipe::time): calls less than a
+    /// frame interval after the last recorded frame are skipped and return `false`.
+    /// Time going backwards, e.g. after pinning it, records right away. `pipeline`
+    /// must be the one the replay was made for; others record nothing.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub fn record(
+        &mut self,
+        pipeline: &Nnpipe,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> bool {
+        if pipeline.output(self.output).is_none() {
+            return false;
+        }
+        let time = pipeline.time();
+        if let Some(newest) = self.frames.back() {
+            let elapsed = time - newest.time;
+            if (0.0..self.interval).contains(&elapsed) {
+                return false;
+            }
+        }
+
+        let mut frame = if self.frames.len() == self.capacity {
+            self.frames.pop_front().expect("full")
+        } else {
+            let texture = wgpu::TextureBuilder::new()
+                .size(self.size)
+                .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC)
+                .format(wgpu::TextureFormat::Rgba16Float)
+                .build(device);
+            let view = texture.view().build();
+            ReplayFrame {
+                texture,
+                view,
+                time,
+            }
+        };
+        frame.time = time;
+        // Checked on entry
+        let _ = pipeline.present(device, queue, self.output, &frame.view);
+        self.frames.push_back(frame);
+        true
+    }
+
+    /// Write the recorded frames to `dir` as sRGB PNGs numbered from the oldest,
+    /// `frame_00000.png` onwards, and return how many were written. The directory is
+    /// created if needed.
+    ///
+    /// Reads every frame back from the GPU, waiting for it, so it takes a moment for
+    /// long replays. Fails if a readback fails or a file can't be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(frames = self.frames.len())))]
+    pub fn export_replay(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        dir: impl AsRef<Path>,
+    ) -> Result<usize> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        for (index, frame) in self.frames.iter().enumerate() {
+            let pixels = futures::executor::block_on(read_texture(device, queue, &frame.texture))?;
+            let data = encode_srgb8(&pixels);
+            let image = nannou::image::RgbaImage::from_raw(self.size[0], self.size[1], data)
+                .expect("one pixel per texel");
+            image
+                .save(dir.join(format!("frame_{index:05}.png")))
+                .map_err(std::io::Error::other)?;
+        }
+        Ok(self.frames.len())
+    }
+}
@@ -0,0 +1,142 @@
+// src/resolution.rs
+//
+// Dynamic resolution
+//
+// A controller watches frame times and lowers the pipeline's render scale when frames
+// run over budget, raising it again once there's headroom. Changes are smoothed,
+// quantized and rate limited, so the resolution settles instead of oscillating, and
+// the rebuilds a change costs (textures only; pipelines come from the cache) stay
+// rare.
+
+use nannou::wgpu;
+use web_time::Instant;
+
+use crate::error::Result;
+use crate::nnpipe::Nnpipe;
+
+// Scales are rounded to multiples of this, so small swings don't rebuild
+const SCALE_STEP: f32 = 0.05;
+
+/// Scales a pipeline's render resolution to hold a target frame rate.
+///
+/// Call [`ResolutionController::update`] once per frame, or
+/// [`ResolutionController::update_with`] with frame times measured another way (e.g.
+/// GPU timestamps). With vsync, the wall-clock times `update` measures never drop
+/// below the refresh interval, so when aiming for the refresh rate itself the scale
+/// only recovers from GPU times.
+#[derive(Clone, Debug)]
+pub struct ResolutionController {
+    target_frame_time: f32,
+    min_scale: f32,
+    max_scale: f32,
+    max_step: f32,
+    smoothing: f32,
+    cooldown: u32,
+
+    average: Option<f32>,
+    frames_since_change: u32,
+    last_update: Option<Instant>,
+}
+
+impl ResolutionController {
+    /// A controller aiming for `target_fps`, scaling between half and full resolution.
+    pub fn new(target_fps: f32) -> Self {
+        Self {
+            target_frame_time: 1.0 / target_fps.max(1.0),
+            min_scale: 0.5,
+            max_scale: 1.0,
+            max_step: 0.1,
+            smoothing: 0.1,
+            cooldown: 30,
+            average: None,
+            frames_since_change: 0,
+            last_update: None,
+        }
+    }
+
+    /// Limit the render scale to `min`..`max`, within 0.1..1.
+    pub fn with_scale_range(mut self, min: f32, max: f32) -> Self {
+        self.min_scale = min.clamp(0.1, 1.0);
+        self.max_scale = max.clamp(self.min_scale, 1.0);
+        self
+    }
+
+    /// Limit how much the scale changes at once (0.1 by default), and how many frames
+    /// must pass between changes (30 by default). Larger steps and shorter cooldowns
+    /// react faster, at the cost of more visible jumps.
+    pub fn with_rate(mut self, max_step: f32, cooldown: u32) -> Self {
+        self.max_step = max_step.max(SCALE_STEP);
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn target_fps(&self) -> f32 {
+        1.0 / self.target_frame_time
+    }
+
+    /// The smoothed frame time in seconds, once a frame has been measured.
+    pub fn frame_time(&self) -> Option<f32> {
+        self.average
+    }
+
+    /// Measure the time since the last call and adjust `pipeline`'s render scale.
+    /// Returns whether the scale changed.
+    pub fn update(
+        &mut self,
+        pipeline: &mut Nnpipe,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<bool> {
+        let now = Instant::now();
+        let last_update = self.last_update.replace(now);
+        match last_update {
+            Some(last) => {
+                let frame_time = now.duration_since(last).as_secs_f32();
+                self.update_with(frame_time, pipeline, device, queue)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Like [`ResolutionController::update`], with a frame time in seconds measured
+    /// by the caller.
+    pub fn update_with(
+        &mut self,
+        frame_time: f32,
+        pipeline: &mut Nnpipe,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<bool> {
+        let average = match self.average {
+            Some(average) => average + (frame_time - average) * self.smoothing,
+            None => frame_time,
+        };
+        self.average = Some(average);
+        self.frames_since_change = self.frames_since_change.saturating_add(1);
+        if self.frames_since_change < self.cooldown {
+            return Ok(false);
+        }
+
+        // Cost goes with the pixel count, the square of the scale. Drop as soon as
+        // frames run over; rise only with clear headroom, to avoid bouncing.
+        let scale = pipeline.render_scale();
+        let ratio = self.target_frame_time / average;
+        if ratio > 0.97 && ratio < 1.2 {
+            return Ok(false);
+        }
+        let wanted = scale * ratio.sqrt();
+        let step = (wanted - scale).clamp(-self.max_step, self.max_step);
+        let new_scale = (((scale + step) / SCALE_STEP).round() * SCALE_STEP)
+            .clamp(self.min_scale, self.max_scale);
+        if (new_scale - scale).abs() < SCALE_STEP * 0.5 {
+            return Ok(false);
+        }
+
+        pipeline.set_render_scale(device, queue, new_scale)?;
+        // Frames around the rebuild aren't representative
+        self.average = None;
+        self.frames_since_change = 0;
+        self.last_update = None;
+        Ok(true)
+    }
+}
@@ -0,0 +1,399 @@
+// src/scopes.rs
+//
+// Video scopes
+//
+// Grading by eye on an uncalibrated monitor drifts; scopes show the frame's levels as
+// numbers would. While they're on, the chain renders into a texture of the scopes',
+// a compute pass counts its pixels into the bins of an RGB histogram, a luma
+// waveform and a vectorscope, and one fullscreen pass per scope draws the counts
+// into a small texture of its own. A last pass copies the frame on to the target.
+// The counts themselves can be read back for hosts that draw their own scopes or
+// check levels automatically.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::error::Result;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+// Levels of the histogram and waveform, and bins across and down the vectorscope
+const LEVELS: u32 = 256;
+
+/// Settings of the scopes, see [`Nnpipe::set_scopes`](crate::Nnpipe::set_scopes).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scopes {
+    /// Side of the square scope textures, in pixels, and the number of columns the
+    /// waveform splits the frame into.
+    pub size: u32,
+    /// Brightness of the waveform and vectorscope traces. At 1 the busiest bin is
+    /// white, and the others fall off with the log of their count.
+    pub gain: f32,
+}
+
+impl Default for Scopes {
+    fn default() -> Self {
+        Self {
+            size: 256,
+            gain: 1.0,
+        }
+    }
+}
+
+impl Scopes {
+    // The settings as the shaders' uniform
+    fn uniform(&self) -> [f32; 4] {
+        [self.gain.max(0.0), self.size.max(1) as f32, 0.0, 0.0]
+    }
+}
+
+/// One of the scopes, see [`Nnpipe::scope_view`](crate::Nnpipe::scope_view).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// Red, green and blue bars of the pixels per level, adding up where they
+    /// overlap, over the luma's in grey. Levels run from 0 on the left to 1 on the
+    /// right.
+    Histogram,
+    /// The luma of each column of the frame, from 0 at the bottom to 1 at the top.
+    Waveform,
+    /// The chroma of the pixels, Cb across and Cr up (BT.709), with the center
+    /// neutral and the edges at full saturation. Traces are tinted with the hue of
+    /// their bin.
+    Vectorscope,
+}
+
+/// The bins of the scopes for a frame, see
+/// [`Nnpipe::read_scopes`](crate::Nnpipe::read_scopes). Values are clamped to 0 to 1
+/// and split into 256 levels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScopeData {
+    /// Pixels per level of red, green, blue and luma.
+    pub histogram: [Vec<u32>; 4],
+    /// Pixels per luma level of each of `columns` columns across the frame, column
+    /// by column from the left.
+    pub waveform: Vec<u32>,
+    pub columns: u32,
+    /// Pixels per chroma bin of a 256 by 256 grid, row by row from the top left, with
+    /// Cb across and Cr up.
+    pub vectorscope: Vec<u32>,
+}
+
+impl ScopeData {
+    /// Pixels at `level` of `column` of the waveform.
+    pub fn waveform_at(&self, column: u32, level: u32) -> u32 {
+        self.waveform[(column * LEVELS + level) as usize]
+    }
+}
+
+pub(crate) struct ScopesLayer {
+    settings: Scopes,
+    // The chain's output, at the pipeline's size
+    source_view: wgpu::TextureView,
+    // Histogram, waveform and vectorscope bins, then each scope's busiest bin
+    counts_buffer: wgpu::Buffer,
+    uniform_buffer: UniformBuffer,
+    count_pipeline: wgpu::ComputePipeline,
+    peak_pipeline: wgpu::ComputePipeline,
+    compute_bind_group: wgpu::BindGroup,
+    draw_pipeline: Arc<wgpu::RenderPipeline>,
+    // Per scope, then the copy of the frame
+    draw_bind_groups: [wgpu::BindGroup; 4],
+    scope_views: [wgpu::TextureView; 3],
+}
+
+impl ScopesLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        size: [u32; 2],
+        settings: Scopes,
+    ) -> Self {
+        let texture = |size: [u32; 2]| {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC,
+                )
+                .format(wgpu::TextureFormat::Rgba16Float)
+                .build(device)
+                .view()
+                .build()
+        };
+        let source_view = texture(size);
+        let side = settings.size.max(1);
+        let scope_views = [(); 3].map(|_| texture([side, side]));
+
+        let counts_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Scopes Counts Buffer"),
+            size: (count_len(side) + 3) as wgpu::BufferAddress * 4,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Scopes Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+
+        let texture_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let buffer_entry = |binding, visibility, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        // Counting the bins
+        let compute = wgpu::ShaderStages::COMPUTE;
+        let compute_layout = cache.bind_group_layout(
+            device,
+            "Scopes Compute Bind Group Layout",
+            &[
+                texture_entry(0, compute),
+                buffer_entry(
+                    1,
+                    compute,
+                    wgpu::BufferBindingType::Storage { read_only: false },
+                ),
+                buffer_entry(2, compute, wgpu::BufferBindingType::Uniform),
+            ],
+        );
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Scopes Compute Bind Group"),
+            layout: &compute_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: counts_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let compute_shader = cache.shader(
+            device,
+            "Scopes Compute Shader",
+            &expand(include_str!("shaders/scopes.wgsl")),
+        );
+        let compute_pipeline_layout =
+            cache.pipeline_layout(device, "Scopes Compute Pipeline Layout", &compute_layout);
+        let compute_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Scopes Compute Pipeline"),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point,
+            })
+        };
+        let count_pipeline = compute_pipeline("cs_main");
+        let peak_pipeline = compute_pipeline("cs_peak");
+
+        // Drawing the scopes and copying the frame
+        let fragment = wgpu::ShaderStages::FRAGMENT;
+        let draw_layout = cache.bind_group_layout(
+            device,
+            "Scopes Draw Bind Group Layout",
+            &[
+                texture_entry(0, fragment),
+                buffer_entry(
+                    1,
+                    fragment,
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                ),
+                buffer_entry(2, fragment, wgpu::BufferBindingType::Uniform),
+                buffer_entry(3, fragment, wgpu::BufferBindingType::Uniform),
+            ],
+        );
+        let draw_bind_groups = [0.0f32, 1.0, 2.0, 3.0].map(|kind| {
+            let kind_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Scopes Kind Buffer"),
+                contents: bytemuck::cast_slice(&[kind, 0.0, 0.0, 0.0]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Scopes Draw Bind Group"),
+                layout: &draw_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&source_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: counts_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: kind_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+        let draw_shader = cache.shader(
+            device,
+            "Scopes Draw Shader",
+            &expand(include_str!("shaders/scopes_draw.wgsl")),
+        );
+        let draw_pipeline_layout =
+            cache.pipeline_layout(device, "Scopes Draw Pipeline Layout", &draw_layout);
+        let draw_pipeline = cache.pipeline(
+            device,
+            &draw_pipeline_layout,
+            &draw_shader,
+            &draw_shader,
+            "Scopes Draw Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        Self {
+            settings,
+            source_view,
+            counts_buffer,
+            uniform_buffer,
+            count_pipeline,
+            peak_pipeline,
+            compute_bind_group,
+            draw_pipeline,
+            draw_bind_groups,
+            scope_views,
+        }
+    }
+
+    pub fn settings(&self) -> Scopes {
+        self.settings
+    }
+
+    // Take settings of the same size; others need a new layer
+    pub fn set_settings(&mut self, settings: Scopes) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Where the chain renders while the scopes are on
+    pub fn source_view(&self) -> &wgpu::TextureView {
+        &self.source_view
+    }
+
+    pub fn scope_view(&self, scope: Scope) -> &wgpu::TextureView {
+        &self.scope_views[scope as usize]
+    }
+
+    // Read the last frame's bins back from the GPU
+    pub async fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<ScopeData> {
+        let size = self.counts_buffer.size();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Scopes Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Scopes Readback"),
+        });
+        encoder.copy_buffer_to_buffer(&self.counts_buffer, 0, &buffer, 0, size);
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = futures::channel::oneshot::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Wait);
+        // The buffer is only dropped with the device if the sender never ran
+        receiver.await.unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+        let counts: Vec<u32> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        buffer.unmap();
+
+        let columns = self.settings.size.max(1);
+        let levels = LEVELS as usize;
+        let waveform_start = 4 * levels;
+        let vectorscope_start = waveform_start + columns as usize * levels;
+        Ok(ScopeData {
+            histogram: [0, 1, 2, 3]
+                .map(|channel| counts[channel * levels..(channel + 1) * levels].to_vec()),
+            waveform: counts[waveform_start..vectorscope_start].to_vec(),
+            columns,
+            vectorscope: counts[vectorscope_start..vectorscope_start + levels * levels].to_vec(),
+        })
+    }
+
+    // Count the chain's output into the bins, draw the scopes, and copy the frame to
+    // `target`, which is the pipeline's size
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "scopes", skip_all))]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        encoder.clear_buffer(&self.counts_buffer, 0, None);
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Scopes count pass"),
+            });
+            let [width, height] = self.source_view.size();
+            pass.set_bind_group(0, &self.compute_bind_group, &[]);
+            pass.set_pipeline(&self.count_pipeline);
+            pass.dispatch_workgroups(width.div_ceil(8), height.div_ceil(8), 1);
+            pass.set_pipeline(&self.peak_pipeline);
+            pass.dispatch_workgroups(count_len(self.settings.size.max(1)).div_ceil(64), 1, 1);
+        }
+
+        let targets = self.scope_views.iter().chain(Some(target));
+        for (bind_group, view) in self.draw_bind_groups.iter().zip(targets) {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Scopes draw pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            pass.set_pipeline(&self.draw_pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+    }
+}
+
+// Bins of all three scopes, with `columns` waveform columns
+fn count_len(columns: u32) -> u32 {
+    4 * LEVELS + columns * LEVELS + LEVELS * LEVELS
+}
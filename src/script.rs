@@ -0,0 +1,282 @@
+// src/script.rs
+//
+// Per-frame modulation scripts
+//
+// A Rhai script runs once per frame with the time, the host's inputs (audio levels,
+// OSC values, ...) and the pipeline's params, and writes params back, so performers
+// can change modulation logic without recompiling the sketch. Only built with the
+// `scripting` feature.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{Engine, Map, Scope, AST};
+use web_time::Instant;
+
+use crate::nnpipe::Nnpipe;
+
+/// A script that failed to load, compile or run.
+#[derive(Clone, Debug)]
+pub struct ScriptError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "script error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+fn error(message: impl ToString) -> ScriptError {
+    ScriptError {
+        message: message.to_string(),
+    }
+}
+
+// A change the script asked for, applied to the pipeline after it ran
+enum Command {
+    Param(usize, String, f32),
+    Enabled(usize, bool),
+    Mix(usize, f32),
+    Bloom(String, f32),
+    Fire(String),
+}
+
+// What the script's functions see and produce during a run
+#[derive(Default)]
+struct Frame {
+    inputs: HashMap<String, f32>,
+    params: Vec<Vec<(String, f32)>>,
+    commands: Vec<Command>,
+}
+
+/// A Rhai script run every frame by [`Nnpipe::run_script`].
+///
+/// Scripts see `time` (seconds since the script was created), `dt` (seconds since the
+/// last run), `frame` (runs so far) and `state`, a map kept between runs. They can call:
+///
+/// - `input(name)`: an input set by the host with [`Script::set_input`], 0 if unset
+/// - `param(pass, name)` and `set_param(pass, name, value)`: params of effect passes
+/// - `set_enabled(pass, enabled)` and `set_mix(pass, mix)`: the pass's wet/dry mix
+/// - `set_bloom(name, value)`: one of `threshold`, `intensity`, `saturation`,
+///   `hue_shift`, `stretch`, `angle`, `horizontal_blur` and `vertical_blur`
+/// - `fire(name)`: fire the triggers registered under `name`
+///
+/// ```text
+/// let kick = input("kick");
+/// set_bloom("intensity", 1.0 + kick * 2.0);
+/// set_param(0, "amplitude", sin(time * 3.0) * 10.0);
+/// if kick > 0.8 { fire("flash"); }
+/// ```
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    frame: Rc<RefCell<Frame>>,
+    start: Instant,
+    last_run: Option<Instant>,
+    runs: i64,
+}
+
+impl Script {
+    pub fn new(source: &str) -> Result<Self, ScriptError> {
+        let frame = Rc::new(RefCell::new(Frame::default()));
+        let engine = create_engine(&frame);
+        let ast = engine.compile(source).map_err(error)?;
+
+        let mut scope = Scope::new();
+        scope.push("state", Map::new());
+        scope.push("time", 0.0_f64);
+        scope.push("dt", 0.0_f64);
+        scope.push("frame", 0_i64);
+
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            frame,
+            start: Instant::now(),
+            last_run: None,
+            runs: 0,
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|e| error(format!("{}: {e}", path.display())))?;
+        Self::new(&source)
+    }
+
+    /// Swap the script's source, keeping its `state`, inputs and clock. On error the
+    /// previous source keeps running.
+    pub fn set_source(&mut self, source: &str) -> Result<(), ScriptError> {
+        self.ast = self.engine.compile(source).map_err(error)?;
+        Ok(())
+    }
+
+    /// Set an input the script reads with `input(name)`, e.g. an audio level or an
+    /// OSC value. Inputs keep their value until set again.
+    pub fn set_input(&mut self, name: &str, value: f32) {
+        self.frame
+            .borrow_mut()
+            .inputs
+            .insert(name.to_string(), value);
+    }
+
+    pub fn input(&self, name: &str) -> Option<f32> {
+        self.frame.borrow().inputs.get(name).copied()
+    }
+}
+
+fn create_engine(frame: &Rc<RefCell<Frame>>) -> Engine {
+    let mut engine = Engine::new();
+    // Keep a runaway loop from freezing the show
+    engine.set_max_operations(1_000_000);
+
+    let f = frame.clone();
+    engine.register_fn("input", move |name: &str| {
+        f.borrow().inputs.get(name).copied().unwrap_or(0.0) as f64
+    });
+
+    let f = frame.clone();
+    engine.register_fn("param", move |pass: i64, name: &str| {
+        let frame = f.borrow();
+        let params = usize::try_from(pass)
+            .ok()
+            .and_then(|pass| frame.params.get(pass));
+        params
+            .and_then(|params| params.iter().find(|(param, _)| param == name))
+            .map_or(0.0, |(_, value)| *value as f64)
+    });
+
+    let f = frame.clone();
+    let set_param = move |pass: i64, name: &str, value: f64| {
+        if let Ok(pass) = usize::try_from(pass) {
+            let command = Command::Param(pass, name.to_string(), value as f32);
+            f.borrow_mut().commands.push(command);
+        }
+    };
+    let set_param_int = set_param.clone();
+    engine.register_fn("set_param", set_param);
+    engine.register_fn("set_param", move |pass: i64, name: &str, value: i64| {
+        set_param_int(pass, name, value as f64)
+    });
+
+    let f = frame.clone();
+    engine.register_fn("set_enabled", move |pass: i64, enabled: bool| {
+        if let Ok(pass) = usize::try_from(pass) {
+            f.borrow_mut()
+                .commands
+                .push(Command::Enabled(pass, enabled));
+        }
+    });
+
+    let f = frame.clone();
+    engine.register_fn("set_mix", move |pass: i64, mix: f64| {
+        if let Ok(pass) = usize::try_from(pass) {
+            f.borrow_mut().commands.push(Command::Mix(pass, mix as f32));
+        }
+    });
+
+    let f = frame.clone();
+    let set_bloom = move |name: &str, value: f64| {
+        let command = Command::Bloom(name.to_string(), value as f32);
+        f.borrow_mut().commands.push(command);
+    };
+    let set_bloom_int = set_bloom.clone();
+    engine.register_fn("set_bloom", set_bloom);
+    engine.register_fn("set_bloom", move |name: &str, value: i64| {
+        set_bloom_int(name, value as f64)
+    });
+
+    let f = frame.clone();
+    engine.register_fn("fire", move |name: &str| {
+        f.borrow_mut()
+            .commands
+            .push(Command::Fire(name.to_string()));
+    });
+
+    engine
+}
+
+impl Nnpipe {
+    /// Run `script` once and apply what it set. Call it once per frame, before
+    /// rendering.
+    ///
+    /// If the script fails partway, the changes it made up to that point are still
+    /// applied and the error is returned, so a typo doesn't blank the show.
+    pub fn run_script(&mut self, script: &mut Script) -> Result<(), ScriptError> {
+        let now = Instant::now();
+        let dt = script
+            .last_run
+            .map_or(0.0, |last| (now - last).as_secs_f64());
+        script.last_run = Some(now);
+        script
+            .scope
+            .set_value("time", (now - script.start).as_secs_f64());
+        script.scope.set_value("dt", dt);
+        script.scope.set_value("frame", script.runs);
+        script.runs += 1;
+
+        script.frame.borrow_mut().params = (0..)
+            .map_while(|index| self.custom_pass(index))
+            .map(|pass| pass.params().to_vec())
+            .collect();
+
+        // Variables the script declares don't outlive the run; `state` does
+        let globals = script.scope.len();
+        let result = script
+            .engine
+            .run_ast_with_scope(&mut script.scope, &script.ast);
+        script.scope.rewind(globals);
+
+        let commands = std::mem::take(&mut script.frame.borrow_mut().commands);
+        let mut unknown = None;
+        for command in commands {
+            match command {
+                Command::Param(index, name, value) => {
+                    if let Some(pass) = self.custom_pass_mut(index) {
+                        pass.set_param(&name, value);
+                    }
+                }
+                Command::Enabled(index, enabled) => {
+                    if let Some(pass) = self.custom_pass_mut(index) {
+                        pass.enabled = enabled;
+                    }
+                }
+                Command::Mix(index, mix) => {
+                    if let Some(pass) = self.custom_pass_mut(index) {
+                        pass.mix = mix;
+                    }
+                }
+                Command::Bloom(name, value) => match name.as_str() {
+                    "threshold" => self.set_brightness_threshold(value),
+                    "intensity" => self.set_bloom_intensity(value),
+                    "saturation" => self.set_bloom_saturation(value),
+                    "hue_shift" => self.set_bloom_hue_shift(value),
+                    "stretch" => self.set_bloom_stretch(value),
+                    "angle" => self.set_blur_angle(value),
+                    "horizontal_blur" => self.set_horizontal_blur_strength(value),
+                    "vertical_blur" => self.set_vertical_blur_strength(value),
+                    _ => unknown = Some(name),
+                },
+                Command::Fire(name) => {
+                    self.fire(&name);
+                }
+            }
+        }
+
+        result.map_err(error)?;
+        match unknown {
+            Some(name) => Err(error(format!("unknown bloom setting '{name}'"))),
+            None => Ok(()),
+        }
+    }
+}
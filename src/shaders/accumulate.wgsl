@@ -0,0 +1,31 @@
+#include "nnpipe/fullscreen.wgsl"
+#include "nnpipe/color.wgsl"
+
+// Long-exposure fragment shader: folds the chain's output into the accumulation of
+// the frames before it, as a running average or maximum
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var previous: texture_2d<f32>;
+
+struct Accumulation {
+    mode: f32, // 0 average, 1 maximum
+    show: f32,
+    frames: f32, // accumulated before this one
+    _padding: f32,
+}
+@group(0) @binding(2) var<uniform> accumulation: Accumulation;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(pos.xy);
+    // A single NaN would stay in the accumulation for good
+    let frame = finite_or_black(textureLoad(source, texel, 0));
+    if (accumulation.frames < 0.5) {
+        return frame;
+    }
+
+    let accumulated = textureLoad(previous, texel, 0);
+    if (accumulation.mode > 0.5) {
+        return max(accumulated, frame);
+    }
+    return mix(accumulated, frame, 1.0 / (accumulation.frames + 1.0));
+}
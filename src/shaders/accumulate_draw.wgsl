@@ -0,0 +1,23 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Long-exposure draw fragment shader: copies the chain's output on, or shows the
+// accumulation in its place
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var accumulated: texture_2d<f32>;
+
+struct Accumulation {
+    mode: f32,
+    show: f32,
+    frames: f32,
+    _padding: f32,
+}
+@group(0) @binding(2) var<uniform> accumulation: Accumulation;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let texel = vec2<i32>(pos.xy);
+    if (accumulation.show > 0.5) {
+        return textureLoad(accumulated, texel, 0);
+    }
+    return textureLoad(source, texel, 0);
+}
@@ -0,0 +1,10 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Bloom stabilization fragment shader: copies its source texel for texel. Blending
+// it into the history is left to the pipeline's blend state.
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(source, vec2<i32>(pos.xy), 0);
+}
@@ -1,15 +1,4 @@
-// Vertex shader for a fullscreen triangle
-@vertex
-fn vs_main(@builtin(vertex_index) vert_id: u32) -> @builtin(position) vec4<f32> {
-    // Create a fullscreen triangle with just the vertex id
-    var positions = array<vec2<f32>, 3>(
-        vec2<f32>(-1.0, -1.0),
-        vec2<f32>(3.0, -1.0),
-        vec2<f32>(-1.0, 3.0)
-    );
-    
-    return vec4<f32>(positions[vert_id], 0.0, 1.0);
-}
+#include "nnpipe/fullscreen.wgsl"
 
 // Gaussian blur fragment shader
 @group(0) @binding(0) var tex: texture_2d<f32>;
@@ -43,9 +32,19 @@ fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
     var result = vec4<f32>(0.0, 0.0, 0.0, 0.0);
     var weight_sum = 0.0;
     
+    // Pixels of a panorama span less of the sphere towards its poles, so the blur
+    // reaches across more of them there, at most half way around
+#ifdef EQUIRECTANGULAR
+    let latitude = (tex_coord.y - 0.5) * 3.14159265;
+    let widening = min(1.0 / max(cos(latitude), 1e-3), 0.5 * tex_size.x / blur_radius);
+    let step = vec2<f32>(direction.x * max(widening, 1.0), direction.y);
+#else
+    let step = direction;
+#endif
+
     // Sample multiple pixels along the blur direction
     for (var i = -blur_radius; i <= blur_radius; i += 1.0) {
-        let offset = direction * i / tex_size;
+        let offset = step * i / tex_size;
         let sample_pos = tex_coord + offset;
         
         // Calculate Gaussian weight
@@ -0,0 +1,38 @@
+// Border pass: a frame of solid color around the picture, whose opening can have
+// rounded corners and a feathered inner edge
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct Params {
+    thickness: f32, // width of the border in pixels
+    feather: f32,   // width of the fade into the picture, in pixels
+    radius: f32,    // radius of the opening's corners in pixels
+    color_r: f32,   // straight RGBA color of the border
+    color_g: f32,
+    color_b: f32,
+    color_a: f32,
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+// Signed distance from a rounded rectangle of `half_size` around the origin
+fn rounded_rect(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let q = abs(p) - half_size + vec2<f32>(radius);
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(src_tex));
+    let frame = textureSample(src_tex, src_sampler, pos.xy / size);
+
+    let half_opening = max(size * 0.5 - vec2<f32>(params.thickness), vec2<f32>(0.0));
+    let radius = clamp(params.radius, 0.0, min(half_opening.x, half_opening.y));
+    let distance = rounded_rect(pos.xy - size * 0.5, half_opening, radius);
+
+    // Antialiased over a pixel at the opening's edge, ramping in from the feather
+    let feather = max(params.feather, 0.0);
+    let coverage = clamp((distance + feather + 0.5) / (feather + 1.0), 0.0, 1.0);
+    let color = vec4<f32>(params.color_r, params.color_g, params.color_b, 1.0);
+    return mix(frame, color, params.color_a * coverage);
+}
@@ -1,42 +1,47 @@
-// Vertex shader for a fullscreen triangle
-@vertex
-fn vs_main(@builtin(vertex_index) vert_id: u32) -> @builtin(position) vec4<f32> {
-    // Create a fullscreen triangle with just the vertex id
-    var positions = array<vec2<f32>, 3>(
-        vec2<f32>(-1.0, -1.0),
-        vec2<f32>(3.0, -1.0),
-        vec2<f32>(-1.0, 3.0)
-    );
-    
-    return vec4<f32>(positions[vert_id], 0.0, 1.0);
-}
+#include "nnpipe/fullscreen.wgsl"
+#include "nnpipe/color.wgsl"
 
 // Brightness extraction fragment shader
 @group(0) @binding(0) var tex: texture_2d<f32>;
 @group(0) @binding(1) var tex_sampler: sampler;
-@group(0) @binding(2) var<uniform> threshold_uniform: f32;
+@group(0) @binding(2) var<uniform> threshold_uniform: vec4<f32>; // rgb thresholds, per-channel flag
+@group(0) @binding(3) var exclusion_mask: texture_2d<f32>;
+@group(0) @binding(4) var<uniform> resolution: vec4<f32>; // pipeline size, bloom size
+@group(0) @binding(5) var<uniform> sanitize_scene: f32;
 
 @fragment
 fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
-    let tex_size = vec2<f32>(textureDimensions(tex));
-    let tex_coord = pos.xy / tex_size;
+    // The target is the bloom texture, which may be smaller than the scene
+    let tex_coord = pos.xy / resolution.zw;
     
-    let color = textureSample(tex, tex_sampler, tex_coord);
+    var color = textureSample(tex, tex_sampler, tex_coord);
+    if (sanitize_scene > 0.5) {
+        color = finite_or_black(color);
+    }
     
     // Calculate luminance
-    let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let luminance = luma(color.rgb);
     
     // Apply threshold with smooth transition
-    let threshold = threshold_uniform;
+    let threshold = threshold_uniform.x;
     let knee = 0.15; // Increased softness of the threshold
     
-    // Soft thresholding
-    let brightness = smoothstep(threshold - knee, threshold + knee, luminance);
+    // Soft thresholding, of the luminance or of each channel on its own
+    var brightness = smoothstep(threshold - knee, threshold + knee, luminance);
+    var channel_brightness = vec3<f32>(brightness);
+    if (threshold_uniform.w > 0.5) {
+        let thresholds = threshold_uniform.xyz;
+        channel_brightness = smoothstep(thresholds - knee, thresholds + knee, color.rgb);
+        brightness = max(channel_brightness.r, max(channel_brightness.g, channel_brightness.b));
+    }
     
     // Enhanced adaptive intensity - brighter pixels bloom more intensely
     // Store original brightness in alpha for later stages
-    let intensity = pow(brightness, 1.4); // Reduced power for wider bloom range
+    let intensity = pow(channel_brightness, vec3<f32>(1.4)); // Reduced power for wider bloom range
+    
+    // Excluded regions don't contribute to the bloom
+    let keep = 1.0 - textureSample(exclusion_mask, tex_sampler, tex_coord).r;
     
     // Apply to color and store original brightness in alpha
-    return vec4<f32>(color.rgb * intensity, brightness);
+    return vec4<f32>(color.rgb * intensity * keep, brightness * keep);
 }
\ No newline at end of file
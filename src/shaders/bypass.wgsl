@@ -0,0 +1,7 @@
+// Bypass fragment shader, copies the scene to the output in place of the chain
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(scene_tex, vec2<i32>(pos.xy), 0);
+}
@@ -0,0 +1,38 @@
+// Color LUT pass: maps colors through a 3D lookup table. The table's entries are
+// laid out in the lookup texture in order, red fastest, wrapping across its rows.
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    size: f32,   // entries along each axis of the table
+    amount: f32, // blend between the original (0) and the mapped color (1)
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(6) var lookup_tex: texture_2d<f32>;
+
+fn entry(r: i32, g: i32, b: i32) -> vec3<f32> {
+    let size = i32(params.size);
+    let width = i32(textureDimensions(lookup_tex).x);
+    let index = r + g * size + b * size * size;
+    return textureLoad(lookup_tex, vec2<i32>(index % width, index / width), 0).rgb;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+
+    // Trilinear interpolation between the eight surrounding entries
+    let last = params.size - 1.0;
+    let position = clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)) * last;
+    let low = vec3<i32>(floor(position));
+    let high = min(low + 1, vec3<i32>(i32(last)));
+    let f = position - vec3<f32>(low);
+
+    let c00 = mix(entry(low.x, low.y, low.z), entry(high.x, low.y, low.z), f.x);
+    let c10 = mix(entry(low.x, high.y, low.z), entry(high.x, high.y, low.z), f.x);
+    let c01 = mix(entry(low.x, low.y, high.z), entry(high.x, low.y, high.z), f.x);
+    let c11 = mix(entry(low.x, high.y, high.z), entry(high.x, high.y, high.z), f.x);
+    let mapped = mix(mix(c00, c10, f.y), mix(c01, c11, f.y), f.z);
+
+    return vec4<f32>(mix(color.rgb, mapped, params.amount), color.a);
+}
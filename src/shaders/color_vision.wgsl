@@ -0,0 +1,57 @@
+// Color vision pass: shows the frame as seen with a color vision deficiency, or
+// daltonizes it, moving the detail the deficiency loses into colors it keeps
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    deficiency: f32, // 0 protanopia, 1 deuteranopia, 2 tritanopia
+    daltonize: f32,  // 0 simulates, 1 corrects
+    severity: f32,   // 0 normal vision to 1 dichromacy
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+// Dichromat simulation in linear RGB, by row (Machado, Oliveira and Fernandes 2009)
+fn simulate(rgb: vec3<f32>) -> vec3<f32> {
+    var rows: array<vec3<f32>, 3>;
+    if params.deficiency < 0.5 {
+        rows = array<vec3<f32>, 3>(
+            vec3<f32>(0.152286, 1.052583, -0.204868),
+            vec3<f32>(0.114503, 0.786281, 0.099216),
+            vec3<f32>(-0.003882, -0.048116, 1.051998),
+        );
+    } else if params.deficiency < 1.5 {
+        rows = array<vec3<f32>, 3>(
+            vec3<f32>(0.367322, 0.860646, -0.227968),
+            vec3<f32>(0.280085, 0.672501, 0.047413),
+            vec3<f32>(-0.011820, 0.042940, 0.968881),
+        );
+    } else {
+        rows = array<vec3<f32>, 3>(
+            vec3<f32>(1.255528, -0.076749, -0.178779),
+            vec3<f32>(-0.078411, 0.930809, 0.147602),
+            vec3<f32>(0.004733, 0.691367, 0.303900),
+        );
+    }
+    let dichromat = vec3<f32>(dot(rows[0], rgb), dot(rows[1], rgb), dot(rows[2], rgb));
+    return mix(rgb, dichromat, clamp(params.severity, 0.0, 1.0));
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    let simulated = simulate(color.rgb);
+    if params.daltonize < 0.5 {
+        return vec4<f32>(simulated, color.a);
+    }
+
+    // What the deficiency loses goes into the channels it still tells apart: red-green
+    // losses into green and blue, blue-yellow losses into red and green
+    let lost = color.rgb - simulated;
+    var shift: vec3<f32>;
+    if params.deficiency < 1.5 {
+        shift = vec3<f32>(0.0, 0.7 * lost.r + lost.g, 0.7 * lost.r + lost.b);
+    } else {
+        shift = vec3<f32>(lost.r + 0.7 * lost.b, lost.g + 0.7 * lost.b, 0.0);
+    }
+    return vec4<f32>(max(color.rgb + shift, vec3<f32>(0.0)), color.a);
+}
@@ -0,0 +1,30 @@
+// Lift/gamma/gain pass: three-way color correction of shadows, midtones and highlights
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    lift_r: f32,
+    lift_g: f32,
+    lift_b: f32,
+    gamma_r: f32,
+    gamma_g: f32,
+    gamma_b: f32,
+    gain_r: f32,
+    gain_g: f32,
+    gain_b: f32,
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    let lift = vec3<f32>(params.lift_r, params.lift_g, params.lift_b);
+    let gamma = vec3<f32>(params.gamma_r, params.gamma_g, params.gamma_b);
+    let gain = vec3<f32>(params.gain_r, params.gain_g, params.gain_b);
+
+    // Lift raises the blacks while leaving white in place, gain scales, gamma bends
+    // the midtones
+    let lifted = max(gain * (color.rgb + lift * (1.0 - min(color.rgb, vec3<f32>(1.0)))), vec3<f32>(0.0));
+    let graded = pow(lifted, 1.0 / max(gamma, vec3<f32>(0.01)));
+    return vec4<f32>(graded, color.a);
+}
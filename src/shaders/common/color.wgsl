@@ -0,0 +1,23 @@
+// Color conversions shared by the crate's shaders
+
+// Rec. 709 luma weights of linear RGB
+const LUMA: vec3<f32> = vec3<f32>(0.2126, 0.7152, 0.0722);
+
+fn luma(rgb: vec3<f32>) -> f32 {
+    return dot(rgb, LUMA);
+}
+
+// Any channel NaN or infinite, by the exponent bits, which compilers can't fold away
+// like x != x
+fn is_non_finite(color: vec4<f32>) -> bool {
+    let exponent = bitcast<vec4<u32>>(color) & vec4<u32>(0x7f800000u);
+    return any(exponent == vec4<u32>(0x7f800000u));
+}
+
+// Opaque black in place of a color with any NaN or infinite channel
+fn finite_or_black(color: vec4<f32>) -> vec4<f32> {
+    if (is_non_finite(color)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return color;
+}
@@ -0,0 +1,8 @@
+// Hashes shared by the crate's shaders
+
+// Output permutation of the PCG hash, scrambling a linearly stepped state. Integer
+// math gives the same values on every GPU, unlike the usual sin() hash.
+fn pcg_permute(state: u32) -> u32 {
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
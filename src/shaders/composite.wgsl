@@ -1,15 +1,5 @@
-// Vertex shader for a fullscreen triangle
-@vertex
-fn vs_main(@builtin(vertex_index) vert_id: u32) -> @builtin(position) vec4<f32> {
-    // Create a fullscreen triangle with just the vertex id
-    var positions = array<vec2<f32>, 3>(
-        vec2<f32>(-1.0, -1.0),
-        vec2<f32>(3.0, -1.0),
-        vec2<f32>(-1.0, 3.0)
-    );
-    
-    return vec4<f32>(positions[vert_id], 0.0, 1.0);
-}
+#include "nnpipe/fullscreen.wgsl"
+#include "nnpipe/color.wgsl"
 
 // Composite fragment shader
 @group(0) @binding(0) var scene_tex: texture_2d<f32>;
@@ -17,21 +7,105 @@ fn vs_main(@builtin(vertex_index) vert_id: u32) -> @builtin(position) vec4<f32>
 @group(0) @binding(2) var tex_sampler: sampler;
 @group(0) @binding(3) var<uniform> intensity_uniform: f32;
 @group(0) @binding(4) var<uniform> intensity_curve: f32;
+@group(0) @binding(5) var<uniform> premultiplied_alpha: f32;
+@group(0) @binding(6) var<uniform> resolution: vec4<f32>; // pipeline size, bloom size
+@group(0) @binding(7) var<uniform> upsample_filter: f32;
+@group(0) @binding(8) var<uniform> bloom_grade: vec4<f32>; // saturation, hue shift
+@group(0) @binding(9) var reflection_tex: texture_2d<f32>; // roughness in alpha
+@group(0) @binding(10) var reflection_blur_tex: texture_2d<f32>;
+@group(0) @binding(11) var<uniform> sanitize_scene: f32;
+
+// Catmull-Rom bicubic upsampling in 9 bilinear taps
+fn sample_catmull_rom(uv: vec2<f32>) -> vec4<f32> {
+    let tex_size = resolution.zw;
+    let sample_pos = uv * tex_size;
+    let tex_pos1 = floor(sample_pos - 0.5) + 0.5;
+    let f = sample_pos - tex_pos1;
+    
+    let w0 = f * (-0.5 + f * (1.0 - 0.5 * f));
+    let w1 = 1.0 + f * f * (-2.5 + 1.5 * f);
+    let w2 = f * (0.5 + f * (2.0 - 1.5 * f));
+    let w3 = f * f * (-0.5 + 0.5 * f);
+    
+    // Merge the middle taps into one bilinear fetch
+    let w12 = w1 + w2;
+    let offset12 = w2 / w12;
+    
+    let tex_pos0 = (tex_pos1 - 1.0) / tex_size;
+    let tex_pos3 = (tex_pos1 + 2.0) / tex_size;
+    let tex_pos12 = (tex_pos1 + offset12) / tex_size;
+    
+    var result = vec4<f32>(0.0);
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos0.x, tex_pos0.y), 0.0) * w0.x * w0.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos12.x, tex_pos0.y), 0.0) * w12.x * w0.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos3.x, tex_pos0.y), 0.0) * w3.x * w0.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos0.x, tex_pos12.y), 0.0) * w0.x * w12.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos12.x, tex_pos12.y), 0.0) * w12.x * w12.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos3.x, tex_pos12.y), 0.0) * w3.x * w12.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos0.x, tex_pos3.y), 0.0) * w0.x * w3.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos12.x, tex_pos3.y), 0.0) * w12.x * w3.y;
+    result += textureSampleLevel(bloom_tex, tex_sampler, vec2<f32>(tex_pos3.x, tex_pos3.y), 0.0) * w3.x * w3.y;
+    
+    // The negative lobes can undershoot around hard edges
+    return max(result, vec4<f32>(0.0));
+}
 
+// 3x3 tent upsampling
+fn sample_tent(uv: vec2<f32>) -> vec4<f32> {
+    let texel = 1.0 / resolution.zw;
+    
+    var result = textureSampleLevel(bloom_tex, tex_sampler, uv, 0.0) * 4.0;
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(-texel.x, 0.0), 0.0) * 2.0;
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(texel.x, 0.0), 0.0) * 2.0;
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(0.0, -texel.y), 0.0) * 2.0;
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(0.0, texel.y), 0.0) * 2.0;
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(-texel.x, -texel.y), 0.0);
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(texel.x, -texel.y), 0.0);
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(-texel.x, texel.y), 0.0);
+    result += textureSampleLevel(bloom_tex, tex_sampler, uv + vec2<f32>(texel.x, texel.y), 0.0);
+    
+    return result / 16.0;
+}
+
+// Saturation and hue rotation (about the grey axis) of the bloom alone
+fn grade_bloom(color: vec3<f32>) -> vec3<f32> {
+    let angle = bloom_grade.y;
+    let k = vec3<f32>(0.57735);
+    let c = cos(angle);
+    let rotated = color * c + cross(k, color) * sin(angle) + k * dot(k, color) * (1.0 - c);
+    
+    let luminance = luma(rotated);
+    return max(mix(vec3<f32>(luminance), rotated, bloom_grade.x), vec3<f32>(0.0));
+}
 
 @fragment
 fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
-    let tex_size = vec2<f32>(textureDimensions(scene_tex));
-    let tex_coord = pos.xy / tex_size;
+    // The target has the pipeline's size, the scene and bloom may not
+    let tex_coord = pos.xy / resolution.xy;
     
     // Sample original scene
-    let scene_color = textureSample(scene_tex, tex_sampler, tex_coord);
+    var scene_color = textureSample(scene_tex, tex_sampler, tex_coord);
+    if (sanitize_scene > 0.5) {
+        scene_color = finite_or_black(scene_color);
+    }
     
-    // Sample bloom texture
-    let bloom_color = textureSample(bloom_tex, tex_sampler, tex_coord);
+    // Screen-space reflections, blurred on rough surfaces. Black unless enabled.
+    let reflection = textureSampleLevel(reflection_tex, tex_sampler, tex_coord, 0.0);
+    let reflection_blur = textureSampleLevel(reflection_blur_tex, tex_sampler, tex_coord, 0.0);
+    let reflected = mix(reflection.rgb, reflection_blur.rgb, reflection.a);
+    
+    // Sample bloom texture, upsampling it if it runs at a lower resolution
+    var bloom_color: vec4<f32>;
+    if (upsample_filter > 1.5) {
+        bloom_color = sample_tent(tex_coord);
+    } else if (upsample_filter > 0.5) {
+        bloom_color = sample_catmull_rom(tex_coord);
+    } else {
+        bloom_color = textureSampleLevel(bloom_tex, tex_sampler, tex_coord, 0.0);
+    }
     
     // Get scene brightness
-    let scene_luminance = dot(scene_color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    let scene_luminance = luma(scene_color.rgb);
     
     // Get bloom brightness info passed through the pipeline
     let bloom_brightness = bloom_color.a;
@@ -48,11 +122,17 @@ fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
     let adaptive_intensity = mix(min_intensity, max_intensity, brightness_factor);
     
     // Apply HDR-like tone mapping to prevent over-saturation
-    let bloom_contribution = bloom_color.rgb * base_intensity * adaptive_intensity;
-    let combined = scene_color.rgb + bloom_contribution;
+    let bloom_contribution = grade_bloom(bloom_color.rgb) * base_intensity * adaptive_intensity;
+    let combined = scene_color.rgb + reflected + bloom_contribution;
     
     // Basic tone mapping to prevent excessive brightness
     let mapped = combined / (combined + 1.0);
     
+    // Premultiplied: bloom is added light, so it also covers transparent areas
+    if (premultiplied_alpha > 0.5) {
+        let coverage = max(mapped.r, max(mapped.g, mapped.b));
+        return vec4<f32>(mapped, clamp(max(scene_color.a, coverage), 0.0, 1.0));
+    }
+    
     return vec4<f32>(mapped, scene_color.a);
 }
\ No newline at end of file
@@ -0,0 +1,12 @@
+// Passthrough compute shader, used in place of a compute pass whose shader failed to
+// compile
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(7) var dst_tex: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(dst_tex)) {
+        return;
+    }
+    textureStore(dst_tex, id.xy, textureLoad(src_tex, id.xy, 0));
+}
@@ -0,0 +1,31 @@
+// Convolution pass: weights the neighbourhood of each pixel by an N x N kernel read
+// from the data array, in row-major order
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    size: f32,  // kernel width and height
+    scale: f32, // applied to the weighted sum, 1 / sum of weights when normalized
+    bias: f32,  // added after scaling
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(5) var<storage, read> data: array<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let max_coord = vec2<i32>(textureDimensions(src_tex)) - 1;
+    let coord = vec2<i32>(pos.xy);
+    let size = i32(params.size);
+    let center = (size - 1) / 2;
+
+    var sum = vec3<f32>(0.0);
+    for (var y = 0; y < size; y++) {
+        for (var x = 0; x < size; x++) {
+            let tap = clamp(coord + vec2<i32>(x - center, y - center), vec2<i32>(0), max_coord);
+            sum += textureLoad(src_tex, tap, 0).rgb * data[y * size + x];
+        }
+    }
+
+    let color = max(sum * params.scale + params.bias, vec3<f32>(0.0));
+    return vec4<f32>(color, textureLoad(src_tex, coord, 0).a);
+}
@@ -0,0 +1,25 @@
+// Curves pass: per-channel and master tone curves, rasterized by the crate into a
+// lookup texture with one row holding (red, green, blue, master) at each input
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(6) var lookup_tex: texture_2d<f32>;
+
+// All four curves at `x`, linearly interpolated between texels. Inputs above 1 keep
+// their distance above the curves' end points.
+fn curves_at(x: f32) -> vec4<f32> {
+    let last = i32(textureDimensions(lookup_tex).x) - 1;
+    let position = clamp(x, 0.0, 1.0) * f32(last);
+    let index = i32(floor(position));
+    let a = textureLoad(lookup_tex, vec2<i32>(index, 0), 0);
+    let b = textureLoad(lookup_tex, vec2<i32>(min(index + 1, last), 0), 0);
+    return mix(a, b, position - f32(index)) + max(x - 1.0, 0.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+
+    // Master curve first, then each channel's own
+    let master = vec3<f32>(curves_at(color.r).a, curves_at(color.g).a, curves_at(color.b).a);
+    let graded = vec3<f32>(curves_at(master.r).r, curves_at(master.g).g, curves_at(master.b).b);
+    return vec4<f32>(max(graded, vec3<f32>(0.0)), color.a);
+}
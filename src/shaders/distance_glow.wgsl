@@ -0,0 +1,37 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Distance glow fragment shader: lights a band around the matte's silhouette by each
+// pixel's distance to its nearest seed, added over the composite
+@group(0) @binding(0) var seed_tex: texture_2d<f32>;
+
+struct Glow {
+    color: vec3<f32>,
+    intensity: f32,
+    offset: f32,
+    radius: f32,
+    softness: f32,
+    _padding: f32,
+}
+@group(0) @binding(1) var<uniform> glow: Glow;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let seed = textureLoad(seed_tex, vec2<i32>(pos.xy), 0);
+    let dist = length(seed.xy - floor(pos.xy));
+    // Nothing inside the silhouette, or without one
+    if (seed.a < 0.5 || dist < 0.5) {
+        return vec4<f32>(0.0);
+    }
+
+    // On past the offset, fading out over the outer part of the band set by the
+    // softness. A hard edge without softness, as smoothstep is undefined for equal
+    // edges.
+    let outer = glow.offset + glow.radius;
+    let fade_start = outer - glow.radius * glow.softness;
+    var amount = 1.0 - step(outer, dist);
+    if (fade_start < outer) {
+        amount = 1.0 - smoothstep(fade_start, outer, dist);
+    }
+    amount *= step(glow.offset, dist);
+    return vec4<f32>(glow.color * glow.intensity * amount, 0.0);
+}
@@ -0,0 +1,35 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Drop shadow fragment shader: composites the scene over its blurred shadow
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+@group(0) @binding(1) var shadow_tex: texture_2d<f32>;
+@group(0) @binding(2) var tex_sampler: sampler;
+
+struct Shadow {
+    offset: vec2<f32>,
+    opacity: f32,
+    _padding0: f32,
+    color: vec3<f32>,
+    _padding1: f32,
+}
+@group(0) @binding(3) var<uniform> shadow: Shadow;
+@group(0) @binding(4) var<uniform> premultiplied_alpha: f32;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = pos.xy / vec2<f32>(textureDimensions(shadow_tex));
+    let scene = textureSampleLevel(scene_tex, tex_sampler, uv, 0.0);
+    let coverage = textureLoad(shadow_tex, vec2<i32>(pos.xy), 0).r * shadow.opacity;
+
+    // The shadow shows through where the scene is transparent
+    let under = coverage * (1.0 - scene.a);
+    if (under <= 0.0) {
+        return scene;
+    }
+    let alpha = scene.a + under;
+    if (premultiplied_alpha > 0.5) {
+        return vec4<f32>(scene.rgb + shadow.color * under, alpha);
+    }
+    let rgb = (scene.rgb * scene.a + shadow.color * under) / max(alpha, 1e-6);
+    return vec4<f32>(rgb, alpha);
+}
@@ -0,0 +1,27 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Drop shadow silhouette fragment shader: the scene's alpha, shifted by the shadow's
+// offset, in the color channels the blur spreads. The alpha of 1 keeps the blur at
+// its full radius.
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+@group(0) @binding(1) var shadow_tex: texture_2d<f32>;
+@group(0) @binding(2) var tex_sampler: sampler;
+
+struct Shadow {
+    offset: vec2<f32>,
+    opacity: f32,
+    _padding0: f32,
+    color: vec3<f32>,
+    _padding1: f32,
+}
+@group(0) @binding(3) var<uniform> shadow: Shadow;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = (pos.xy - shadow.offset) / vec2<f32>(textureDimensions(shadow_tex));
+    var coverage = 0.0;
+    if (all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0))) {
+        coverage = clamp(textureSampleLevel(scene_tex, tex_sampler, uv, 0.0).a, 0.0, 1.0);
+    }
+    return vec4<f32>(vec3<f32>(coverage), 1.0);
+}
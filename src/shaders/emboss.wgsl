@@ -0,0 +1,35 @@
+// Emboss pass: shades the frame's luminance as a relief lit from one side
+#include "nnpipe/color.wgsl"
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    light_angle: f32, // direction the light comes from, in radians (0 = from the left,
+                      // pi/2 = from the top)
+    depth: f32,       // height of the relief
+    blend: f32,       // 0 = grey relief, 1 = the frame's colors shaded by the relief
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+fn luminance(coord: vec2<i32>) -> f32 {
+    let max_coord = vec2<i32>(textureDimensions(src_tex)) - 1;
+    let color = textureLoad(src_tex, clamp(coord, vec2<i32>(0), max_coord), 0).rgb;
+    return luma(color);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(pos.xy);
+    let original = textureLoad(src_tex, coord, 0);
+
+    // Slope toward the light, from a pixel on either side; surfaces rising away from
+    // the light face it
+    let to_light = -vec2<f32>(cos(params.light_angle), sin(params.light_angle));
+    let light = vec2<i32>(round(to_light));
+    let slope = luminance(coord - light) - luminance(coord + light);
+    let relief = clamp(0.5 + slope * params.depth, 0.0, 1.0);
+
+    let shaded = original.rgb * relief * 2.0;
+    return vec4<f32>(mix(vec3<f32>(relief), shaded, params.blend), original.a);
+}
@@ -0,0 +1,6 @@
+// Exclusion mask fragment shader. The pipeline's stencil test limits it to the
+// regions marked in the scene's stencil buffer.
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
@@ -0,0 +1,17 @@
+// Exclusion restore fragment shader, draws the untouched scene back over the
+// excluded regions at the end of the chain
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+@group(0) @binding(1) var mask_tex: texture_2d<f32>;
+@group(0) @binding(2) var tex_sampler: sampler;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let tex_size = vec2<f32>(textureDimensions(mask_tex));
+    let tex_coord = pos.xy / tex_size;
+    
+    let scene_color = textureSample(scene_tex, tex_sampler, tex_coord);
+    let mask = textureSample(mask_tex, tex_sampler, tex_coord).r;
+    
+    // The pipeline blends by alpha, so the mask picks between scene and chain
+    return vec4<f32>(scene_color.rgb, mask);
+}
@@ -0,0 +1,68 @@
+#include "nnpipe/color.wgsl"
+
+// False color fragment shader: paints the HDR scene's luminance in indicator colors
+// by exposure band, like a camera's false color, and hatches what the brightness
+// pass lets into the bloom
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+@group(0) @binding(1) var brightness_tex: texture_2d<f32>;
+@group(0) @binding(2) var blur_tex: texture_2d<f32>;
+@group(0) @binding(3) var composite_tex: texture_2d<f32>;
+@group(0) @binding(4) var tex_sampler: sampler;
+
+struct DebugUniforms {
+    target_size: vec2<f32>,
+    _padding: vec2<f32>,
+}
+@group(0) @binding(5) var<uniform> debug: DebugUniforms;
+
+// Band colors, from crushed blacks to over display white
+const PURPLE: vec3<f32> = vec3<f32>(0.5, 0.0, 0.8);
+const BLUE: vec3<f32> = vec3<f32>(0.0, 0.3, 1.0);
+const GREEN: vec3<f32> = vec3<f32>(0.1, 0.8, 0.1);
+const PINK: vec3<f32> = vec3<f32>(1.0, 0.5, 0.7);
+const YELLOW: vec3<f32> = vec3<f32>(1.0, 0.9, 0.0);
+const RED: vec3<f32> = vec3<f32>(1.0, 0.0, 0.0);
+
+// The indicator color of linear luminance `y`. Bands between the colored ones show
+// the luminance as grey.
+fn false_color(y: f32) -> vec3<f32> {
+    if (y < 0.0025) {
+        return PURPLE;
+    }
+    if (y < 0.02) {
+        return BLUE;
+    }
+    // Middle grey, half a stop either side
+    if (y >= 0.13 && y < 0.25) {
+        return GREEN;
+    }
+    // A stop over middle grey, where faces usually sit
+    if (y >= 0.3 && y < 0.45) {
+        return PINK;
+    }
+    if (y >= 0.75 && y < 1.0) {
+        return YELLOW;
+    }
+    if (y >= 1.0) {
+        return RED;
+    }
+    return vec3<f32>(y);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    // Both stretched over the target, as the bloom may run at a lower resolution. The
+    // scene rather than the composite, which is tone mapped below display white.
+    let uv = pos.xy / debug.target_size;
+    let color = textureSampleLevel(scene_tex, tex_sampler, uv, 0.0);
+    var rgb = false_color(luma(color.rgb));
+
+    // White diagonal stripes where the brightness pass passes anything
+    let bright = textureSampleLevel(brightness_tex, tex_sampler, uv, 0.0);
+    let stripe = (u32(pos.x + pos.y) / 4u) % 2u == 0u;
+    if (max(bright.r, max(bright.g, bright.b)) > 1e-3 && stripe) {
+        rgb = mix(rgb, vec3<f32>(1.0), 0.6);
+    }
+
+    return vec4<f32>(rgb, 1.0);
+}
@@ -0,0 +1,113 @@
+// FFT convolution bloom compute shaders
+//
+// The bright parts of the frame are copied into an N x N complex domain, transformed
+// with one workgroup per row and then per column, multiplied by the kernel's
+// spectrum and transformed back. N and LOG_N are replaced when the module is built.
+const N: u32 = 256u;
+const LOG_N: u32 = 8u;
+const HALF_N: u32 = 128u;
+
+const PI: f32 = 3.14159265358979;
+
+struct FftParams {
+    domain_scale: vec2<f32>, // frame UV per domain UV
+    target_size: vec2<f32>,
+    vertical: u32,
+    inverse: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: FftParams;
+@group(0) @binding(1) var<storage, read_write> re: array<vec4<f32>>;
+@group(0) @binding(2) var<storage, read_write> im: array<vec4<f32>>;
+@group(0) @binding(3) var<storage, read> kernel_re: array<vec4<f32>>;
+@group(0) @binding(4) var<storage, read> kernel_im: array<vec4<f32>>;
+@group(0) @binding(5) var bright_tex: texture_2d<f32>;
+@group(0) @binding(6) var bright_sampler: sampler;
+
+var<workgroup> line_re: array<vec4<f32>, N>;
+var<workgroup> line_im: array<vec4<f32>, N>;
+
+// Copy the brightness texture into the domain, zero-padding outside the frame
+@compute @workgroup_size(8, 8)
+fn load(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= N || id.y >= N) {
+        return;
+    }
+
+    let uv = (vec2<f32>(id.xy) + 0.5) / f32(N) * params.domain_scale;
+    var color = vec4<f32>(0.0);
+    if (all(uv <= vec2<f32>(1.0))) {
+        color = textureSampleLevel(bright_tex, bright_sampler, uv, 0.0);
+    }
+
+    let index = id.y * N + id.x;
+    re[index] = vec4<f32>(color.rgb, 0.0);
+    im[index] = vec4<f32>(0.0);
+}
+
+fn element_index(line: u32, i: u32) -> u32 {
+    if (params.vertical == 1u) {
+        return i * N + line;
+    }
+    return line * N + i;
+}
+
+// Radix-2 FFT of one row or column per workgroup, in workgroup memory
+@compute @workgroup_size(128)
+fn fft(@builtin(workgroup_id) group: vec3<u32>, @builtin(local_invocation_index) t: u32) {
+    let line = group.x;
+
+    // Load in bit-reversed order
+    for (var e = t; e < N; e += HALF_N) {
+        let index = element_index(line, e);
+        let reversed = reverseBits(e) >> (32u - LOG_N);
+        line_re[reversed] = re[index];
+        line_im[reversed] = im[index];
+    }
+    workgroupBarrier();
+
+    // Butterflies; each invocation owns one pair per stage
+    let sign = select(-1.0, 1.0, params.inverse == 1u);
+    for (var half = 1u; half < N; half = half << 1u) {
+        let k = t % half;
+        let a = (t / half) * half * 2u + k;
+        let b = a + half;
+
+        let angle = sign * PI * f32(k) / f32(half);
+        let w = vec2<f32>(cos(angle), sin(angle));
+        let b_re = line_re[b] * w.x - line_im[b] * w.y;
+        let b_im = line_re[b] * w.y + line_im[b] * w.x;
+        let a_re = line_re[a];
+        let a_im = line_im[a];
+
+        line_re[a] = a_re + b_re;
+        line_im[a] = a_im + b_im;
+        line_re[b] = a_re - b_re;
+        line_im[b] = a_im - b_im;
+        workgroupBarrier();
+    }
+
+    let scale = select(1.0, 1.0 / f32(N), params.inverse == 1u);
+    for (var e = t; e < N; e += HALF_N) {
+        let index = element_index(line, e);
+        re[index] = line_re[e] * scale;
+        im[index] = line_im[e] * scale;
+    }
+}
+
+// Convolution: complex multiply by the kernel's spectrum
+@compute @workgroup_size(8, 8)
+fn multiply(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= N || id.y >= N) {
+        return;
+    }
+
+    let index = id.y * N + id.x;
+    let a_re = re[index];
+    let a_im = im[index];
+    let b_re = kernel_re[index];
+    let b_im = kernel_im[index];
+
+    re[index] = a_re * b_re - a_im * b_im;
+    im[index] = a_re * b_im + a_im * b_re;
+}
@@ -0,0 +1,39 @@
+// FFT bloom resolve fragment shader, writes the convolved domain into the bloom
+// texture. N is replaced when the module is built.
+#include "nnpipe/color.wgsl"
+
+const N: u32 = 256u;
+
+struct FftParams {
+    domain_scale: vec2<f32>,
+    target_size: vec2<f32>,
+    vertical: u32,
+    inverse: u32,
+}
+
+@group(0) @binding(0) var<storage, read> re: array<vec4<f32>>;
+@group(0) @binding(1) var<uniform> params: FftParams;
+
+fn fetch(coord: vec2<i32>) -> vec3<f32> {
+    let c = clamp(coord, vec2<i32>(0), vec2<i32>(i32(N) - 1));
+    return re[u32(c.y) * N + u32(c.x)].rgb;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = pos.xy / params.target_size;
+    let coord = uv / params.domain_scale * f32(N) - 0.5;
+
+    // Bilinear filtering by hand, the domain lives in a buffer
+    let base = vec2<i32>(floor(coord));
+    let f = fract(coord);
+    let top = mix(fetch(base), fetch(base + vec2<i32>(1, 0)), f.x);
+    let bottom = mix(fetch(base + vec2<i32>(0, 1)), fetch(base + vec2<i32>(1, 1)), f.x);
+
+    // Ringing from the transform can dip slightly below zero
+    let color = max(mix(top, bottom, f.y), vec3<f32>(0.0));
+
+    // Brightness in alpha, like the blur passes leave it for the composite
+    let brightness = clamp(luma(color), 0.0, 1.0);
+    return vec4<f32>(color, brightness);
+}
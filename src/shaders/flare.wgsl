@@ -0,0 +1,72 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Lens flare: ghosts of the bright areas along the line through the center, and a halo
+struct LensFlare {
+    tint: vec3<f32>,
+    ghost_intensity: f32,
+    ghosts: f32,
+    spacing: f32,
+    halo_radius: f32,
+    halo_intensity: f32,
+    chromatic_shift: f32,
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+}
+
+struct Globals {
+    resolution: vec2<f32>,
+    time: f32,
+    seed: u32,
+}
+
+@group(0) @binding(0) var brightness_tex: texture_2d<f32>;
+@group(0) @binding(1) var brightness_sampler: sampler;
+@group(0) @binding(2) var<uniform> flare: LensFlare;
+@group(0) @binding(3) var<uniform> globals: Globals;
+
+const MAX_GHOSTS: i32 = 16;
+
+// The bright areas at `uv`, with red and blue pulled apart along `direction`. Fades
+// out toward the edges of the frame, where real ghosts are cut off by the lens.
+fn sample_chromatic(uv: vec2<f32>, direction: vec2<f32>) -> vec3<f32> {
+    let shift = direction * flare.chromatic_shift;
+    let color = vec3<f32>(
+        textureSampleLevel(brightness_tex, brightness_sampler, uv + shift, 0.0).r,
+        textureSampleLevel(brightness_tex, brightness_sampler, uv, 0.0).g,
+        textureSampleLevel(brightness_tex, brightness_sampler, uv - shift, 0.0).b
+    );
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    let falloff = pow(max(1.0 - length(vec2<f32>(0.5) - uv) / 0.7071, 0.0), 4.0);
+    return select(vec3<f32>(0.0), color * falloff, inside);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = pos.xy / globals.resolution;
+    let to_center = vec2<f32>(0.5) - uv;
+    let direction = to_center / max(length(to_center), 1e-4);
+
+    // Ghosts: each pixel gathers the bright areas at steps toward and past the
+    // center, which places mirrored copies of them on the other side
+    var ghosts = vec3<f32>(0.0);
+    let ghost_step = to_center * flare.spacing;
+    for (var i = 1; i <= MAX_GHOSTS; i++) {
+        if f32(i) > flare.ghosts {
+            break;
+        }
+        ghosts += sample_chromatic(uv + ghost_step * f32(i), direction);
+    }
+
+    // Halo: a ring of the bright areas at a fixed distance from the center, kept
+    // round on wide frames
+    let aspect = vec2<f32>(globals.resolution.x / globals.resolution.y, 1.0);
+    let centered = to_center * aspect;
+    let halo_step = centered / max(length(centered), 1e-4) * flare.halo_radius / aspect;
+    let ring = abs(length(centered) - flare.halo_radius) / max(flare.halo_radius, 1e-4);
+    let halo_weight = pow(max(1.0 - ring, 0.0), 5.0);
+    let halo = sample_chromatic(uv + halo_step, direction) * halo_weight;
+
+    let color = ghosts * flare.tint * flare.ghost_intensity + halo * flare.halo_intensity;
+    return vec4<f32>(color, 0.0);
+}
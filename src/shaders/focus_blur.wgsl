@@ -0,0 +1,48 @@
+// Gradient focus pass: a fake depth of field, blurring more the further a pixel is
+// from a focus region drawn in screen space
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct Params {
+    shape: f32,    // 0 = radial around the center, 1 = linear band through it
+    center_x: f32, // focus center, in UV
+    center_y: f32,
+    radius: f32,   // distance kept sharp, in UV of the frame's height
+    falloff: f32,  // distance over which the blur ramps up
+    max_blur: f32, // blur radius in pixels, reached at radius + falloff
+    angle: f32,    // rotation of the linear band, in radians
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+const GOLDEN_ANGLE: f32 = 2.39996323;
+const SAMPLES: i32 = 32;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let tex_size = vec2<f32>(textureDimensions(src_tex));
+    let tex_coord = pos.xy / tex_size;
+
+    // Distance from the focus region, with x in units of the height
+    let aspect = vec2<f32>(tex_size.x / tex_size.y, 1.0);
+    let offset = (tex_coord - vec2<f32>(params.center_x, params.center_y)) * aspect;
+    var distance = length(offset);
+    if (params.shape > 0.5) {
+        let normal = vec2<f32>(-sin(params.angle), cos(params.angle));
+        distance = abs(dot(offset, normal));
+    }
+
+    let amount = smoothstep(params.radius, params.radius + max(params.falloff, 0.0001), distance);
+    let blur_radius = amount * params.max_blur;
+
+    // Disc blur on a golden-angle spiral
+    var sum = textureSampleLevel(src_tex, src_sampler, tex_coord, 0.0);
+    for (var i = 1; i < SAMPLES; i++) {
+        let r = sqrt(f32(i) / f32(SAMPLES)) * blur_radius;
+        let theta = f32(i) * GOLDEN_ANGLE;
+        let tap = tex_coord + vec2<f32>(cos(theta), sin(theta)) * r / tex_size;
+        sum += textureSampleLevel(src_tex, src_sampler, tap, 0.0);
+    }
+
+    return sum / f32(SAMPLES);
+}
@@ -0,0 +1,12 @@
+// Vertex shader for a fullscreen triangle, shared by all effect passes
+@vertex
+fn vs_main(@builtin(vertex_index) vert_id: u32) -> @builtin(position) vec4<f32> {
+    // Create a fullscreen triangle with just the vertex id
+    var positions = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(3.0, -1.0),
+        vec2<f32>(-1.0, 3.0)
+    );
+    
+    return vec4<f32>(positions[vert_id], 0.0, 1.0);
+}
@@ -0,0 +1,62 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Alignment guides: grid lines, a center crosshair and the safe-area rectangles,
+// blended over the target
+struct Guides {
+    color: vec4<f32>,
+    target_size: vec2<f32>,
+    grid: vec2<f32>,
+    title_safe: f32,
+    action_safe: f32,
+    crosshair: f32,
+    line_width: f32,
+}
+
+@group(0) @binding(0) var<uniform> guides: Guides;
+
+// Whether pixels `offset` from a line are on it, for lines `width` either side. The
+// range is half-open so that odd widths cover whole pixels.
+fn on_line(offset: vec2<f32>, width: f32) -> vec2<bool> {
+    return (offset >= vec2<f32>(-width)) & (offset < vec2<f32>(width));
+}
+
+// Whether the pixel `offset` from the center lies on the outline of the centered
+// rectangle covering `fraction` of the target
+fn on_safe_area(offset: vec2<f32>, fraction: f32) -> bool {
+    if (fraction <= 0.0) {
+        return false;
+    }
+    let half_size = guides.target_size * fraction * 0.5;
+    let width = guides.line_width * 0.5;
+    let edges = on_line(offset - half_size, width) | on_line(offset + half_size, width);
+    let within = abs(offset) <= half_size + width;
+    return all(within) && any(edges);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let width = guides.line_width * 0.5;
+    let center = guides.target_size * 0.5;
+    let offset = pos.xy - center;
+
+    // Inner grid lines; the frame's edges are left out
+    let cell = guides.target_size / max(guides.grid, vec2<f32>(1.0));
+    let line = round(pos.xy / cell);
+    let inner = (line > vec2<f32>(0.0)) & (line < guides.grid);
+    var hit = any(on_line(pos.xy - line * cell, width) & inner);
+
+    // Crosshair arms reach a twentieth of the shorter side from the center
+    let arm = min(guides.target_size.x, guides.target_size.y) * 0.05;
+    if (guides.crosshair > 0.5) {
+        let on_axis = on_line(offset, width);
+        let on_arm = abs(offset) < vec2<f32>(arm);
+        hit = hit || (on_axis.x && on_arm.y) || (on_axis.y && on_arm.x);
+    }
+
+    hit = hit || on_safe_area(offset, guides.title_safe);
+    hit = hit || on_safe_area(offset, guides.action_safe);
+    if (!hit) {
+        discard;
+    }
+    return guides.color;
+}
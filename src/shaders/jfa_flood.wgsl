@@ -0,0 +1,39 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Jump flood fragment shader: each pixel keeps the nearest of the seeds found by
+// itself and its eight neighbors a step away
+@group(0) @binding(0) var seed_tex: texture_2d<f32>;
+
+struct Flood {
+    step: f32,
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+}
+@group(0) @binding(2) var<uniform> flood: Flood;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(seed_tex));
+    let here = vec2<i32>(pos.xy);
+    let jump = i32(flood.step);
+
+    var best = vec4<f32>(0.0);
+    var best_distance = 3.4e38;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor = here + vec2<i32>(x, y) * jump;
+            if (any(neighbor < vec2<i32>(0)) || any(neighbor >= size)) {
+                continue;
+            }
+            let seed = textureLoad(seed_tex, neighbor, 0);
+            let offset = seed.xy - vec2<f32>(here);
+            let dist = dot(offset, offset);
+            if (seed.a > 0.5 && dist < best_distance) {
+                best = seed;
+                best_distance = dist;
+            }
+        }
+    }
+    return best;
+}
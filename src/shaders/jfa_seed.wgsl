@@ -0,0 +1,14 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Jump flood seed fragment shader: every pixel inside the matte is a seed, stored as
+// its own coordinates with an alpha of 1. Pixels outside start without a seed.
+@group(0) @binding(0) var matte_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let matte = textureLoad(matte_tex, vec2<i32>(pos.xy), 0).r;
+    if (matte < 0.5) {
+        return vec4<f32>(0.0);
+    }
+    return vec4<f32>(floor(pos.xy), 0.0, 1.0);
+}
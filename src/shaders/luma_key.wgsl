@@ -0,0 +1,35 @@
+// Luma key pass: composites a second texture over the frame wherever the keyed
+// image, the frame or the second texture, is brighter than a threshold
+#include "nnpipe/color.wgsl"
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct Params {
+    source: f32,    // 0 = key on the frame, 1 = key on the second texture
+    threshold: f32, // luma at the middle of the key's edge
+    softness: f32,  // width of the edge in luma, 0 for a hard key
+    invert: f32,    // 1 = composite where the keyed image is darker instead
+    opacity: f32,   // strength of the second texture where it's keyed in
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+@group(1) @binding(0) var key_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = pos.xy / vec2<f32>(textureDimensions(src_tex));
+    let frame = textureSample(src_tex, src_sampler, uv);
+    let layer = textureSample(key_tex, src_sampler, uv);
+
+    var keyed = luma(frame.rgb);
+    if (params.source > 0.5) {
+        keyed = luma(layer.rgb);
+    }
+    let edge = max(params.softness, 1e-5) * 0.5;
+    var key = smoothstep(params.threshold - edge, params.threshold + edge, keyed);
+    if (params.invert > 0.5) {
+        key = 1.0 - key;
+    }
+    return mix(frame, layer, key * params.opacity);
+}
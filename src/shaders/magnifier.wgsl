@@ -0,0 +1,50 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Magnifier fragment shader: copies the frame and draws a square inset into one of
+// its corners, showing the pixels around a point as nearest-neighbour blocks
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+struct Magnifier {
+    center: vec2<f32>,
+    zoom: f32,
+    size: f32,
+    corner: f32, // 0 top left, 1 top right, 2 bottom left, 3 bottom right
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+}
+@group(0) @binding(1) var<uniform> magnifier: Magnifier;
+
+// Gap between the inset and the frame's edges, in pixels
+const MARGIN: f32 = 8.0;
+
+fn load(pixel: vec2<f32>) -> vec4<f32> {
+    let size = vec2<f32>(textureDimensions(source));
+    if (any(pixel < vec2<f32>(0.0)) || any(pixel >= size)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return textureLoad(source, vec2<i32>(pixel), 0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let frame_size = vec2<f32>(textureDimensions(source));
+    var origin = vec2<f32>(MARGIN);
+    if (magnifier.corner == 1.0 || magnifier.corner == 3.0) {
+        origin.x = frame_size.x - magnifier.size - MARGIN;
+    }
+    if (magnifier.corner >= 2.0) {
+        origin.y = frame_size.y - magnifier.size - MARGIN;
+    }
+
+    let local = pos.xy - origin;
+    if (any(local < vec2<f32>(0.0)) || any(local >= vec2<f32>(magnifier.size))) {
+        return load(floor(pos.xy));
+    }
+    // A white frame around the inset
+    if (any(local < vec2<f32>(1.0)) || any(local >= vec2<f32>(magnifier.size - 1.0))) {
+        return vec4<f32>(1.0);
+    }
+    let offset = (local - vec2<f32>(magnifier.size * 0.5)) / magnifier.zoom;
+    return load(floor(magnifier.center + offset));
+}
@@ -0,0 +1,34 @@
+#include "nnpipe/fullscreen.wgsl"
+#include "nnpipe/color.wgsl"
+
+// Matte fragment shader: thresholds the scene's alpha or luminance into a soft-edged
+// mask, written to every channel
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+
+struct Matte {
+    threshold: f32,
+    feather: f32,
+    source: f32, // 0 alpha, 1 luminance
+    invert: f32,
+}
+@group(0) @binding(1) var<uniform> matte: Matte;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(scene_tex, vec2<i32>(pos.xy), 0);
+    var value = color.a;
+    if (matte.source > 0.5) {
+        value = luma(color.rgb);
+    }
+
+    // A hard edge without feather, as smoothstep is undefined for equal edges
+    var mask = step(matte.threshold, value);
+    let half_feather = 0.5 * matte.feather;
+    if (half_feather > 0.0) {
+        mask = smoothstep(matte.threshold - half_feather, matte.threshold + half_feather, value);
+    }
+    if (matte.invert > 0.5) {
+        mask = 1.0 - mask;
+    }
+    return vec4<f32>(mask);
+}
@@ -0,0 +1,260 @@
+// Output fragment shader: draws the processed frame into a window or other target
+#include "nnpipe/color.wgsl"
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct OutputUniforms {
+    target_size: vec2<f32>,
+    exposure: f32,
+    gamma: f32,
+    saturation: f32,
+    fit: f32, // 0 stretch, 1 letterbox, 2 pillarbox, 3 cover, 4 contain
+    source_size: vec2<f32>,
+    background: vec4<f32>,
+    pan: vec2<f32>,
+    zoom: f32,
+    rotation: f32, // clockwise, radians
+    crop: vec4<f32>, // x, y, width, height
+    scaling: f32, // 0 linear, 1 nearest, 2 integer
+    mask_shape: f32, // 0 none, 1 rounded rect, 2 ellipse, 3 circle, 4 coverage, 5 distance
+    mask_radius: f32,
+    mask_feather: f32,
+    mask_invert: f32,
+    warp_subdivisions: f32, // cells of the tessellated frame along either side
+    mesh_size: vec2<f32>, // columns and rows of mesh points, 0 without a mesh
+    // Rows of the corner pin's homography
+    warp_x: vec4<f32>,
+    warp_y: vec4<f32>,
+    warp_w: vec4<f32>,
+    projection: f32, // 0 flat, 1 fisheye of a perspective view, 2 fisheye of a panorama
+    dome_half_fov: f32,
+    source_tangent: f32, // tan of half the perspective view's horizontal fov
+    // Rows of the rotation from the dome's directions to the frame's
+    rotation_x: vec4<f32>,
+    rotation_y: vec4<f32>,
+    rotation_z: vec4<f32>,
+}
+@group(0) @binding(2) var<uniform> output: OutputUniforms;
+
+// The warp mesh's points in rows from the top left, in xy
+@group(0) @binding(3) var<uniform> mesh: array<vec4<f32>, 289>;
+
+// Red holds the mask's coverage or signed distance
+@group(1) @binding(0) var mask_tex: texture_2d<f32>;
+
+// Signed distance from `p` to a rectangle of `half_size` around the origin with
+// corners rounded by `radius`, negative inside
+fn rounded_rect_distance(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let r = min(radius, min(half_size.x, half_size.y));
+    let q = abs(p) - half_size + r;
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - r;
+}
+
+// Approximate signed distance from `p` to an ellipse with radii `half_size`
+fn ellipse_distance(p: vec2<f32>, half_size: vec2<f32>) -> f32 {
+    let k0 = length(p / half_size);
+    let k1 = length(p / (half_size * half_size));
+    if (k1 < 1e-6) {
+        return -min(half_size.x, half_size.y);
+    }
+    return k0 * (k0 - 1.0) / k1;
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position in the unwarped target, 0..1 from its top left
+    @location(0) frame_pos: vec2<f32>,
+}
+
+fn mesh_point(column: u32, row: u32) -> vec2<f32> {
+    return mesh[row * u32(output.mesh_size.x) + column].xy;
+}
+
+// Where the mesh moves the even grid position `uv`, blending the four points around it
+fn bend(uv: vec2<f32>) -> vec2<f32> {
+    if (output.mesh_size.x < 2.0) {
+        return uv;
+    }
+    let grid = uv * (output.mesh_size - 1.0);
+    let cell = min(floor(grid), output.mesh_size - 2.0);
+    let f = grid - cell;
+    let column = u32(cell.x);
+    let row = u32(cell.y);
+    let top = mix(mesh_point(column, row), mesh_point(column + 1u, row), f.x);
+    let bottom = mix(mesh_point(column, row + 1u), mesh_point(column + 1u, row + 1u), f.x);
+    return mix(top, bottom, f.y);
+}
+
+// Vertices of the frame tessellated into cells of two triangles, moved by the mesh
+// and the corner pin. The pin's w goes into the position, so the frame's position is
+// interpolated across each triangle in perspective.
+@vertex
+fn vs_main(@builtin(vertex_index) vert_id: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    let subdivisions = u32(output.warp_subdivisions);
+    let cell_index = vert_id / 6u;
+    let cell = vec2<f32>(f32(cell_index % subdivisions), f32(cell_index / subdivisions));
+    let uv = (cell + corners[vert_id % 6u]) / f32(subdivisions);
+
+    let p = vec3<f32>(bend(uv), 1.0);
+    let x = dot(output.warp_x.xyz, p);
+    let y = dot(output.warp_y.xyz, p);
+    let w = dot(output.warp_w.xyz, p);
+
+    var out: VertexOutput;
+    // Target 0..1 from the top left to clip space, before the divide by w
+    out.position = vec4<f32>(2.0 * x - w, w - 2.0 * y, 0.0, w);
+    out.frame_pos = uv;
+    return out;
+}
+
+const PI: f32 = 3.14159265;
+
+// Where the fisheye looks up the frame for the pixel at `pos`, in xy, with z 1 if the
+// pixel shows the frame at all
+fn fisheye_coord(pos: vec2<f32>) -> vec3<f32> {
+    let radius = min(output.target_size.x, output.target_size.y) * 0.5;
+    let d = (pos - output.target_size * 0.5) / radius;
+    let r = length(d);
+    if (r > 1.0) {
+        return vec3<f32>(0.0);
+    }
+    // Equidistant: the distance from the center is the angle from the axis
+    let theta = r * output.dome_half_fov;
+    var across = vec2<f32>(0.0);
+    if (r > 0.0) {
+        across = d / r * sin(theta);
+    }
+    let local = vec3<f32>(across, cos(theta));
+    let dir = vec3<f32>(
+        dot(output.rotation_x.xyz, local),
+        dot(output.rotation_y.xyz, local),
+        dot(output.rotation_z.xyz, local)
+    );
+
+    if (output.projection > 1.5) {
+        let longitude = atan2(dir.x, dir.z);
+        let latitude = asin(clamp(dir.y, -1.0, 1.0));
+        return vec3<f32>(longitude / (2.0 * PI) + 0.5, latitude / PI + 0.5, 1.0);
+    }
+    if (dir.z <= 0.0) {
+        return vec3<f32>(0.0);
+    }
+    // The view's horizontal fov spans the frame's width, keeping its pixels square
+    let plane = dir.xy / dir.z / output.source_tangent;
+    let aspect = output.source_size.x / output.source_size.y;
+    return vec3<f32>(0.5 + 0.5 * plane.x, 0.5 + 0.5 * plane.y * aspect, 1.0);
+}
+
+// How much of the pixel at `pos` the mask lets through
+fn mask_coverage(pos: vec2<f32>) -> f32 {
+    let mask = textureSampleLevel(mask_tex, src_sampler, pos / output.target_size, 0.0).r;
+    let half_size = output.target_size * 0.5;
+    let p = pos - half_size;
+    var distance = 0.0;
+    var coverage = 1.0;
+    if (output.mask_shape > 4.5) {
+        distance = mask;
+    } else if (output.mask_shape > 3.5) {
+        coverage = mask;
+    } else if (output.mask_shape > 2.5) {
+        distance = length(p) - min(half_size.x, half_size.y);
+    } else if (output.mask_shape > 1.5) {
+        distance = ellipse_distance(p, half_size);
+    } else if (output.mask_shape > 0.5) {
+        distance = rounded_rect_distance(p, half_size, output.mask_radius);
+    }
+    // Shapes given by distance ramp from the edge inwards over the feather, with
+    // half a pixel either side of the edge for antialiasing
+    if (output.mask_shape > 0.5 && (output.mask_shape < 3.5 || output.mask_shape > 4.5)) {
+        coverage = clamp((0.5 - distance) / (output.mask_feather + 1.0), 0.0, 1.0);
+    }
+    if (output.mask_invert > 0.5) {
+        coverage = 1.0 - coverage;
+    }
+    return coverage;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Pixel of the unwarped target, which the fit, transform and mask work in
+    let pos = in.frame_pos * output.target_size;
+    let coverage = mask_coverage(pos);
+    
+    // Scale from frame to target pixels for the fit mode
+    let scale = output.target_size / output.source_size;
+    var fit_scale: vec2<f32>;
+    if (output.fit > 3.5) {
+        fit_scale = vec2<f32>(min(scale.x, scale.y));
+    } else if (output.fit > 2.5) {
+        fit_scale = vec2<f32>(max(scale.x, scale.y));
+    } else if (output.fit > 1.5) {
+        fit_scale = vec2<f32>(scale.y);
+    } else if (output.fit > 0.5) {
+        fit_scale = vec2<f32>(scale.x);
+    } else {
+        fit_scale = scale;
+    }
+    
+    // Integer scaling enlarges by whole numbers only, from a whole pixel offset
+    let integer = output.scaling > 1.5;
+    if (integer) {
+        fit_scale = select(fit_scale, floor(fit_scale), fit_scale >= vec2<f32>(1.0));
+    }
+    
+    // Sample by normalized position so the output can have any resolution
+    let content_size = output.source_size * fit_scale;
+    var offset = (output.target_size - content_size) * 0.5;
+    if (integer) {
+        offset = floor(offset);
+    }
+    var frame_coord = (pos - offset) / content_size;
+    
+    // Domes look the frame up by direction instead
+    if (output.projection > 0.5) {
+        let projected = fisheye_coord(pos);
+        if (projected.z < 0.5) {
+            return output.background;
+        }
+        frame_coord = projected.xy;
+    }
+    
+    // Undo pan, zoom and rotation around the frame's center. Rotate in pixels so
+    // the frame keeps its aspect ratio.
+    let centered = (frame_coord - 0.5 - output.pan) * output.source_size / output.zoom;
+    let c = cos(output.rotation);
+    let s = sin(output.rotation);
+    let rotated = vec2<f32>(c * centered.x + s * centered.y, -s * centered.x + c * centered.y);
+    let tex_coord = rotated / output.source_size + 0.5;
+    
+    // Bars outside the fitted frame and the crop rect
+    let crop_min = output.crop.xy;
+    let crop_max = output.crop.xy + output.crop.zw;
+    if (any(tex_coord < crop_min) || any(tex_coord > crop_max)) {
+        return output.background;
+    }
+    
+    // Point sampling: sample the center of the texel the coordinate falls in
+    var sample_coord = tex_coord;
+    if (output.scaling > 0.5) {
+        sample_coord = (floor(tex_coord * output.source_size) + 0.5) / output.source_size;
+    }
+    let color = textureSampleLevel(src_tex, src_sampler, sample_coord, 0.0);
+    
+    // Per-output adjustments
+    var rgb = color.rgb * output.exposure;
+    let luminance = luma(rgb);
+    rgb = mix(vec3<f32>(luminance), rgb, output.saturation);
+    rgb = pow(max(rgb, vec3<f32>(0.0)), vec3<f32>(1.0 / output.gamma));
+    
+    // Masked areas show the background, like the bars
+    return mix(output.background, vec4<f32>(rgb, color.a), coverage);
+}
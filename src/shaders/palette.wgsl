@@ -0,0 +1,67 @@
+// Palette pass: snaps every pixel to the nearest color of a palette, compared in
+// CIE Lab. The lookup texture holds the palette's linear colors in its first row
+// and their Lab coordinates in the second.
+#include "nnpipe/color.wgsl"
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    count: f32,  // colors in the palette
+    dither: f32, // strength of the ordered dithering, 0 for none
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(6) var lookup_tex: texture_2d<f32>;
+
+fn lab_f(t: f32) -> f32 {
+    if (t > 0.008856) {
+        return pow(t, 1.0 / 3.0);
+    }
+    return 7.787 * t + 16.0 / 116.0;
+}
+
+// Linear sRGB to CIE Lab, D65 white
+fn to_lab(rgb: vec3<f32>) -> vec3<f32> {
+    let c = clamp(rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    let x = dot(c, vec3<f32>(0.4124, 0.3576, 0.1805)) / 0.95047;
+    let y = luma(c);
+    let z = dot(c, vec3<f32>(0.0193, 0.1192, 0.9505)) / 1.08883;
+    let fx = lab_f(x);
+    let fy = lab_f(y);
+    let fz = lab_f(z);
+    return vec3<f32>(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz));
+}
+
+// 4x4 Bayer threshold in 0..1
+fn bayer(coord: vec2<u32>) -> f32 {
+    var matrix = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0
+    );
+    return (matrix[(coord.y % 4u) * 4u + coord.x % 4u] + 0.5) / 16.0;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+
+    // Dither in a roughly perceptual space so the pattern is even across tones
+    let offset = (bayer(vec2<u32>(pos.xy)) - 0.5) * params.dither * 0.25;
+    let dithered = pow(pow(max(color.rgb, vec3<f32>(0.0)), vec3<f32>(1.0 / 2.2)) + offset, vec3<f32>(2.2));
+    let lab = to_lab(max(dithered, vec3<f32>(0.0)));
+
+    var best = 0;
+    var best_distance = 1e20;
+    for (var i = 0; i < i32(params.count); i++) {
+        let delta = lab - textureLoad(lookup_tex, vec2<i32>(i, 1), 0).xyz;
+        let distance = dot(delta, delta);
+        if (distance < best_distance) {
+            best_distance = distance;
+            best = i;
+        }
+    }
+
+    return vec4<f32>(textureLoad(lookup_tex, vec2<i32>(best, 0), 0).rgb, color.a);
+}
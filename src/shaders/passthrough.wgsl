@@ -0,0 +1,11 @@
+// Passthrough fragment shader, used in place of a pass whose shader failed to compile
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let tex_size = vec2<f32>(textureDimensions(src_tex));
+    let tex_coord = pos.xy / tex_size;
+    
+    return textureSample(src_tex, src_sampler, tex_coord);
+}
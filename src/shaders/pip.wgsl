@@ -0,0 +1,43 @@
+// Picture-in-picture pass: places a second texture as a rectangular inset over the
+// frame, keeping its aspect ratio, with an optional border around it
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct Params {
+    center_x: f32,   // center of the inset in UV
+    center_y: f32,
+    width: f32,      // width of the inset as a fraction of the frame's width
+    border: f32,     // width of the border in pixels, 0 for none
+    border_r: f32,   // straight RGBA color of the border
+    border_g: f32,
+    border_b: f32,
+    border_a: f32,
+    opacity: f32,    // opacity of the inset
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+@group(1) @binding(0) var inset_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let frame_size = vec2<f32>(textureDimensions(src_tex));
+    let frame = textureSample(src_tex, src_sampler, pos.xy / frame_size);
+
+    let inset_dims = vec2<f32>(textureDimensions(inset_tex));
+    let width = params.width * frame_size.x;
+    let inset_size = vec2<f32>(width, width * inset_dims.y / inset_dims.x);
+    let origin = vec2<f32>(params.center_x, params.center_y) * frame_size - inset_size * 0.5;
+    let local = pos.xy - origin;
+    // Sampled in uniform control flow, ahead of the branches
+    let inset = textureSample(inset_tex, src_sampler, local / inset_size);
+
+    if (all(local >= vec2<f32>(0.0)) && all(local < inset_size)) {
+        return mix(frame, inset, params.opacity);
+    }
+    let border = vec2<f32>(params.border);
+    if (all(local >= -border) && all(local < inset_size + border)) {
+        let color = vec4<f32>(params.border_r, params.border_g, params.border_b, 1.0);
+        return mix(frame, color, params.border_a * params.opacity);
+    }
+    return frame;
+}
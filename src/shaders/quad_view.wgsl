@@ -0,0 +1,110 @@
+// Quad view fragment shader: tiles four stages of the chain into a 2x2 grid, each
+// labeled in its top left corner
+@group(0) @binding(0) var scene_tex: texture_2d<f32>;
+@group(0) @binding(1) var brightness_tex: texture_2d<f32>;
+@group(0) @binding(2) var blur_tex: texture_2d<f32>;
+@group(0) @binding(3) var composite_tex: texture_2d<f32>;
+@group(0) @binding(4) var tex_sampler: sampler;
+
+struct QuadUniforms {
+    target_size: vec2<f32>,
+    _padding: vec2<f32>,
+}
+@group(0) @binding(5) var<uniform> quad: QuadUniforms;
+
+// 3x5 pixel glyphs, one bit per pixel from the top left, row by row
+const B: u32 = 27566u;
+const C: u32 = 31015u;
+const E: u32 = 31207u;
+const G: u32 = 31087u;
+const H: u32 = 23533u;
+const I: u32 = 29847u;
+const L: u32 = 18727u;
+const M: u32 = 24557u;
+const N: u32 = 27501u;
+const O: u32 = 31599u;
+const P: u32 = 31716u;
+const R: u32 = 27565u;
+const S: u32 = 31183u;
+const T: u32 = 29842u;
+const U: u32 = 23407u;
+
+const MAX_LABEL: u32 = 10u;
+
+// Glyphs of a quadrant's label, 0 past its end
+fn label_glyph(quadrant: u32, index: u32) -> u32 {
+    var labels = array<array<u32, 10>, 4>(
+        array<u32, 10>(S, C, E, N, E, 0u, 0u, 0u, 0u, 0u),
+        array<u32, 10>(B, R, I, G, H, T, N, E, S, S),
+        array<u32, 10>(B, L, U, R, 0u, 0u, 0u, 0u, 0u, 0u),
+        array<u32, 10>(C, O, M, P, O, S, I, T, E, 0u),
+    );
+    return labels[quadrant][index];
+}
+
+fn label_length(quadrant: u32) -> u32 {
+    var count = 0u;
+    for (var i = 0u; i < MAX_LABEL; i++) {
+        if (label_glyph(quadrant, i) != 0u) {
+            count = i + 1u;
+        }
+    }
+    return count;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let half_size = quad.target_size * 0.5;
+    let cell = min(floor(pos.xy / half_size), vec2<f32>(1.0));
+    let quadrant = u32(cell.x) + 2u * u32(cell.y);
+    let local = pos.xy - cell * half_size;
+    let uv = local / half_size;
+
+    // Stages can differ in size (the bloom may run at a lower resolution), so each
+    // is stretched over its quadrant
+    var color: vec4<f32>;
+    switch quadrant {
+        case 0u: {
+            color = textureSampleLevel(scene_tex, tex_sampler, uv, 0.0);
+        }
+        case 1u: {
+            color = textureSampleLevel(brightness_tex, tex_sampler, uv, 0.0);
+        }
+        case 2u: {
+            color = textureSampleLevel(blur_tex, tex_sampler, uv, 0.0);
+        }
+        default: {
+            color = textureSampleLevel(composite_tex, tex_sampler, uv, 0.0);
+        }
+    }
+    var rgb = color.rgb;
+
+    // Label in font pixels, scaled with the target (while it fits the quadrant) and
+    // inset from the corner
+    let chars = f32(label_length(quadrant));
+    let fit = floor(half_size.x / (chars * 4.0 + 6.0));
+    let scale = max(min(floor(quad.target_size.y / 270.0), fit), 1.0);
+    let text = floor(local / scale) - vec2<f32>(3.0);
+    if (text.x >= -1.0 && text.x < chars * 4.0 && text.y >= -1.0 && text.y < 6.0) {
+        // Dark backdrop, so labels read over bright content
+        rgb = rgb * 0.25;
+
+        let column = u32(max(text.x, 0.0)) % 4u;
+        let row = u32(max(text.y, 0.0));
+        let glyph = label_glyph(quadrant, min(u32(max(text.x, 0.0)) / 4u, MAX_LABEL - 1u));
+        if (text.x >= 0.0 && text.y >= 0.0 && column < 3u && row < 5u) {
+            let bit = 14u - (row * 3u + column);
+            if (((glyph >> bit) & 1u) == 1u) {
+                rgb = vec3<f32>(1.0);
+            }
+        }
+    }
+
+    // A thin line between the quadrants
+    let edge = abs(pos.xy - half_size);
+    if (min(edge.x, edge.y) < 1.0) {
+        rgb = vec3<f32>(0.5);
+    }
+
+    return vec4<f32>(rgb, 1.0);
+}
@@ -0,0 +1,113 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Screen-space reflections, traced at the bloom's resolution
+struct Reflections {
+    projection: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+    intensity: f32,
+    max_distance: f32,
+    thickness: f32,
+    steps: f32,
+    target_size: vec2<f32>,
+    edge_fade: f32,
+    _padding: f32,
+}
+
+@group(0) @binding(0) var depth_tex: texture_2d<f32>; // depth as unfilterable floats
+@group(0) @binding(1) var normal_tex: texture_2d<f32>; // view-space normal * 0.5 + 0.5, roughness
+@group(0) @binding(2) var scene_sampler: sampler;
+@group(0) @binding(3) var<uniform> reflections: Reflections;
+@group(1) @binding(0) var scene_tex: texture_2d<f32>;
+
+const MAX_STEPS: i32 = 256;
+const REFINE_STEPS: i32 = 5;
+
+// Texel of a texture of `size` under `uv`
+fn texel(uv: vec2<f32>, size: vec2<u32>) -> vec2<i32> {
+    let clamped = clamp(uv, vec2<f32>(0.0), vec2<f32>(1.0));
+    return min(vec2<i32>(clamped * vec2<f32>(size)), vec2<i32>(size) - 1);
+}
+
+fn scene_depth(uv: vec2<f32>) -> f32 {
+    return textureLoad(depth_tex, texel(uv, textureDimensions(depth_tex)), 0).r;
+}
+
+fn view_position(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = reflections.inverse_projection * ndc;
+    return position.xyz / position.w;
+}
+
+fn project(position: vec3<f32>) -> vec2<f32> {
+    let clip = reflections.projection * vec4<f32>(position, 1.0);
+    let ndc = clip.xy / clip.w;
+    return vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+}
+
+// How far `position` lies behind the surface the depth buffer holds there
+fn depth_behind(position: vec3<f32>) -> f32 {
+    let uv = project(position);
+    return view_position(uv, scene_depth(uv)).z - position.z;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = pos.xy / reflections.target_size;
+    let normal_sample = textureLoad(normal_tex, texel(uv, textureDimensions(normal_tex)), 0);
+    let roughness = normal_sample.a;
+    let depth = scene_depth(uv);
+    // Nothing was drawn here
+    if depth >= 1.0 {
+        return vec4<f32>(0.0, 0.0, 0.0, roughness);
+    }
+
+    let origin = view_position(uv, depth);
+    let normal = normalize(normal_sample.xyz * 2.0 - 1.0);
+    let ray = reflect(normalize(origin), normal);
+
+    // March along the reflected ray until it passes just behind a surface
+    let steps = clamp(reflections.steps, 1.0, f32(MAX_STEPS));
+    let step_length = reflections.max_distance / steps;
+    var previous = 0.0;
+    var hit = -1.0;
+    for (var i = 1; i <= MAX_STEPS; i++) {
+        if f32(i) > steps {
+            break;
+        }
+        let travelled = step_length * f32(i);
+        let position = origin + ray * travelled;
+        let position_uv = project(position);
+        if any(position_uv < vec2<f32>(0.0)) || any(position_uv > vec2<f32>(1.0)) {
+            break;
+        }
+        let behind = depth_behind(position);
+        if behind > 0.0 && behind < reflections.thickness {
+            hit = travelled;
+            break;
+        }
+        previous = travelled;
+    }
+    if hit < 0.0 {
+        return vec4<f32>(0.0, 0.0, 0.0, roughness);
+    }
+
+    // Narrow the hit down between the last two steps
+    var near = previous;
+    var far = hit;
+    for (var i = 0; i < REFINE_STEPS; i++) {
+        let middle = 0.5 * (near + far);
+        if depth_behind(origin + ray * middle) > 0.0 {
+            far = middle;
+        } else {
+            near = middle;
+        }
+    }
+    let hit_uv = project(origin + ray * far);
+
+    // Fade out toward the frame's edges, where the scene runs out, and with distance
+    let edge = min(min(hit_uv.x, 1.0 - hit_uv.x), min(hit_uv.y, 1.0 - hit_uv.y));
+    let edge_fade = clamp(edge / max(reflections.edge_fade, 1e-4), 0.0, 1.0);
+    let distance_fade = 1.0 - far / reflections.max_distance;
+    let color = textureSampleLevel(scene_tex, scene_sampler, hit_uv, 0.0).rgb;
+    return vec4<f32>(color * reflections.intensity * edge_fade * distance_fade, roughness);
+}
@@ -0,0 +1,63 @@
+// Scanline displacement pass: shifts each row of the frame sideways by an animated
+// amount, for rolling shutter wobble and broken-signal looks
+#include "nnpipe/noise.wgsl"
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    mode: f32,      // 0 = sine, 1 = noise, 2 = waveform from the data array
+    amplitude: f32, // largest shift, in pixels
+    frequency: f32, // sine waves or noise bands per frame height
+    speed: f32,     // how fast the pattern moves, per second
+}
+
+struct Globals {
+    resolution: vec2<f32>,
+    time: f32,
+    seed: u32,
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(3) var<uniform> globals: Globals;
+@group(0) @binding(5) var<storage, read> data: array<f32>;
+
+const TAU: f32 = 6.28318530718;
+
+// PCG hash of a lattice point and the seed, in -1..1
+fn hash(n: f32) -> f32 {
+    let h = pcg_permute(bitcast<u32>(i32(n)) * 747796405u + globals.seed * 2891336453u + 1u);
+    return f32(h) / 2147483647.5 - 1.0;
+}
+
+// Smooth value noise in -1..1
+fn noise(x: f32) -> f32 {
+    let i = floor(x);
+    let f = fract(x);
+    return mix(hash(i), hash(i + 1.0), f * f * (3.0 - 2.0 * f));
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(src_tex));
+    let v = pos.y / f32(size.y);
+
+    var shift = 0.0;
+    if (params.mode < 0.5) {
+        shift = sin((v * params.frequency - globals.time * params.speed) * TAU);
+    } else if (params.mode < 1.5) {
+        // Bands scroll through the frame; the time offset keeps them changing shape
+        let t = globals.time * params.speed;
+        shift = noise(v * params.frequency - t) * noise(t * 3.7 + 17.0);
+    } else {
+        // The waveform spans the frame's height, scrolling with speed
+        let count = arrayLength(&data);
+        let x = fract(v - globals.time * params.speed) * f32(count);
+        let i = u32(x) % count;
+        shift = mix(data[i], data[(i + 1u) % count], fract(x));
+    }
+
+    // Rows wrap around the frame's edges, like a signal losing horizontal sync
+    let x = (i32(pos.x) + i32(round(shift * params.amplitude))) % size.x;
+    let coord = vec2<i32>((x + size.x) % size.x, i32(pos.y));
+    return textureLoad(src_tex, coord, 0);
+}
@@ -0,0 +1,70 @@
+#include "nnpipe/color.wgsl"
+
+// Scopes compute shader: counts the frame's pixels into the bins of the histogram,
+// the waveform and the vectorscope, then finds each scope's busiest bin
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> counts: array<atomic<u32>>;
+
+struct Scopes {
+    gain: f32,
+    columns: f32,
+    _padding0: f32,
+    _padding1: f32,
+}
+@group(0) @binding(2) var<uniform> scopes: Scopes;
+
+// Levels per channel; the histogram's red, green, blue and luma come first
+const LEVELS: u32 = 256u;
+const WAVEFORM: u32 = 1024u;
+
+fn vectorscope_start() -> u32 {
+    return WAVEFORM + u32(scopes.columns) * LEVELS;
+}
+
+// The busiest bin of each scope follows the bins
+fn peaks_start() -> u32 {
+    return vectorscope_start() + LEVELS * LEVELS;
+}
+
+fn level(value: f32) -> u32 {
+    return min(u32(clamp(value, 0.0, 1.0) * f32(LEVELS)), LEVELS - 1u);
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(source);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    let rgb = textureLoad(source, vec2<i32>(id.xy), 0).rgb;
+    let y = luma(rgb);
+
+    atomicAdd(&counts[level(rgb.r)], 1u);
+    atomicAdd(&counts[LEVELS + level(rgb.g)], 1u);
+    atomicAdd(&counts[2u * LEVELS + level(rgb.b)], 1u);
+    atomicAdd(&counts[3u * LEVELS + level(y)], 1u);
+
+    let column = id.x * u32(scopes.columns) / size.x;
+    atomicAdd(&counts[WAVEFORM + column * LEVELS + level(y)], 1u);
+
+    // BT.709 chroma, -0.5 to 0.5 each, with Cr up
+    let cb = (rgb.b - y) / 1.8556;
+    let cr = (rgb.r - y) / 1.5748;
+    atomicAdd(&counts[vectorscope_start() + level(0.5 - cr) * LEVELS + level(cb + 0.5)], 1u);
+}
+
+@compute @workgroup_size(64)
+fn cs_peak(@builtin(global_invocation_id) id: vec3<u32>) {
+    let bin = id.x;
+    let peaks = peaks_start();
+    if (bin >= peaks) {
+        return;
+    }
+    var scope = 2u;
+    if (bin < WAVEFORM) {
+        scope = 0u;
+    } else if (bin < vectorscope_start()) {
+        scope = 1u;
+    }
+    atomicMax(&counts[peaks + scope], atomicLoad(&counts[bin]));
+}
@@ -0,0 +1,86 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Scopes draw shader: draws one scope from the counted bins into its texture, or
+// copies the frame on
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read> counts: array<u32>;
+
+struct Scopes {
+    gain: f32,
+    columns: f32,
+    _padding0: f32,
+    _padding1: f32,
+}
+@group(0) @binding(2) var<uniform> scopes: Scopes;
+
+struct Draw {
+    kind: f32, // 0 histogram, 1 waveform, 2 vectorscope, 3 copy
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+}
+@group(0) @binding(3) var<uniform> draw: Draw;
+
+// Laid out as in scopes.wgsl
+const LEVELS: u32 = 256u;
+const WAVEFORM: u32 = 1024u;
+
+fn vectorscope_start() -> u32 {
+    return WAVEFORM + u32(scopes.columns) * LEVELS;
+}
+
+fn peaks_start() -> u32 {
+    return vectorscope_start() + LEVELS * LEVELS;
+}
+
+const BACKGROUND: vec3<f32> = vec3<f32>(0.05, 0.05, 0.05);
+
+fn bin(fraction: f32) -> u32 {
+    return min(u32(max(fraction, 0.0) * f32(LEVELS)), LEVELS - 1u);
+}
+
+// Brightness of a trace through a bin, by the log of its count against the peak's
+fn trace(count: u32, peak: u32) -> f32 {
+    if (count == 0u) {
+        return 0.0;
+    }
+    return clamp(log2(1.0 + f32(count)) / log2(1.0 + f32(peak)) * scopes.gain, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    if (draw.kind > 2.5) {
+        return textureLoad(source, vec2<i32>(pos.xy), 0);
+    }
+
+    let uv = pos.xy / scopes.columns;
+    let peaks = peaks_start();
+
+    if (draw.kind < 0.5) {
+        // Bars reach up to their count against the busiest level's
+        let level = bin(uv.x);
+        let height = (1.0 - uv.y) * f32(counts[peaks]);
+        let on = vec4<f32>(
+            f32(f32(counts[level]) > height),
+            f32(f32(counts[LEVELS + level]) > height),
+            f32(f32(counts[2u * LEVELS + level]) > height),
+            f32(f32(counts[3u * LEVELS + level]) > height),
+        );
+        return vec4<f32>(BACKGROUND + vec3<f32>(0.25 * on.w) + 0.7 * on.rgb, 1.0);
+    }
+
+    if (draw.kind < 1.5) {
+        let column = min(u32(uv.x * scopes.columns), u32(scopes.columns) - 1u);
+        let count = counts[WAVEFORM + column * LEVELS + bin(1.0 - uv.y)];
+        return vec4<f32>(BACKGROUND + vec3<f32>(trace(count, counts[peaks + 1u])), 1.0);
+    }
+
+    let u = bin(uv.x);
+    let v = bin(uv.y);
+    let t = trace(counts[vectorscope_start() + v * LEVELS + u], counts[peaks + 2u]);
+    // Tinted with the bin's hue, at middle luma
+    let cb = (f32(u) + 0.5) / f32(LEVELS) - 0.5;
+    let cr = 0.5 - (f32(v) + 0.5) / f32(LEVELS);
+    let hue = vec3<f32>(0.5 + 1.5748 * cr, 0.5 - 0.1873 * cb - 0.4681 * cr, 0.5 + 1.8556 * cb);
+    return vec4<f32>(BACKGROUND + max(hue, vec3<f32>(0.0)) * t, 1.0);
+}
@@ -0,0 +1,38 @@
+// Shockwave pass: a ring of refraction expanding from a point, driven by a trigger
+// that ramps progress from 0 to 1
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct Params {
+    center_x: f32, // where the wave starts, in UV
+    center_y: f32,
+    progress: f32, // 0 = idle, then 0..1 over the wave's life
+    radius: f32,   // radius reached at the end, in UV of the frame's height
+    width: f32,    // thickness of the ring, same units
+    strength: f32, // displacement at the ring's crest, in pixels
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let tex_size = vec2<f32>(textureDimensions(src_tex));
+    let tex_coord = pos.xy / tex_size;
+    if (params.progress <= 0.0) {
+        return textureSampleLevel(src_tex, src_sampler, tex_coord, 0.0);
+    }
+
+    // Offset from the center with x in units of the height, so the ring stays round
+    let aspect = vec2<f32>(tex_size.x / tex_size.y, 1.0);
+    let offset = (tex_coord - vec2<f32>(params.center_x, params.center_y)) * aspect;
+    let distance = length(offset);
+
+    // A smooth bump around the ring's current radius, fading as the wave spreads
+    let ring = (distance - params.progress * params.radius) / max(params.width, 1e-4);
+    let crest = max(1.0 - ring * ring, 0.0);
+    let amount = crest * crest * (1.0 - params.progress) * params.strength;
+
+    let direction = offset / max(distance, 1e-4);
+    let uv = tex_coord - direction * amount / tex_size;
+    return textureSampleLevel(src_tex, src_sampler, uv, 0.0);
+}
@@ -0,0 +1,92 @@
+#include "nnpipe/noise.wgsl"
+
+// Sparkle layer: one glint per grid cell at most, spawned on bright spots
+struct Sparkles {
+    threshold: f32,
+    density: f32,
+    size: f32,
+    lifetime: f32,
+    intensity: f32,
+    cell_size: f32,
+    _padding: vec2<f32>,
+}
+
+struct Globals {
+    resolution: vec2<f32>,
+    time: f32,
+    seed: u32,
+}
+
+@group(0) @binding(0) var brightness_tex: texture_2d<f32>;
+@group(0) @binding(1) var brightness_sampler: sampler;
+@group(0) @binding(2) var<uniform> sparkles: Sparkles;
+@group(0) @binding(3) var<uniform> globals: Globals;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position within the glint, -1 to 1 across
+    @location(0) offset: vec2<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+fn random(state: u32) -> f32 {
+    return f32(pcg_permute(state)) / 4294967295.0;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32, @builtin(instance_index) cell: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0)
+    );
+    let corner = corners[vertex];
+
+    // Each cell lives through glint generations at its own phase, so they don't
+    // all blink together
+    let cell_state = pcg_permute(cell ^ pcg_permute(globals.seed));
+    let cycle = globals.time / sparkles.lifetime + random(cell_state);
+    let generation = u32(floor(cycle));
+    let age = fract(cycle);
+    let state = pcg_permute(cell_state + generation * 747796405u);
+
+    // A random spot in the cell, which sparkles if it's bright and the dice say so
+    let columns = u32(ceil(globals.resolution.x / sparkles.cell_size));
+    let cell_origin = vec2<f32>(f32(cell % columns), f32(cell / columns)) * sparkles.cell_size;
+    let spot = cell_origin + vec2<f32>(random(state + 1u), random(state + 2u)) * sparkles.cell_size;
+    let bright = textureSampleLevel(brightness_tex, brightness_sampler, spot / globals.resolution, 0.0);
+    let alive = bright.a >= sparkles.threshold && random(state + 3u) < sparkles.density;
+
+    var out: VertexOutput;
+    out.offset = corner;
+    if !alive {
+        // Collapse the quad off screen
+        out.position = vec4<f32>(2.0, 2.0, 0.0, 1.0);
+        out.color = vec3<f32>(0.0);
+        return out;
+    }
+
+    // Grow in and fade out over the glint's life
+    let envelope = sin(3.14159265 * age);
+    let radius = sparkles.size * (0.5 + random(state + 4u)) * envelope;
+    let pixel = spot + corner * radius;
+    let ndc = pixel / globals.resolution * 2.0 - 1.0;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+
+    // Tinted by the spot's color, with a white core
+    let hue = bright.rgb / max(max(bright.r, max(bright.g, bright.b)), 1e-4);
+    out.color = mix(hue, vec3<f32>(1.0), 0.5) * sparkles.intensity * envelope;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // A four-pointed star: a soft core and two thin rays fading toward the tips
+    let d = abs(in.offset);
+    let core = exp(-dot(in.offset, in.offset) * 24.0);
+    let rays = exp(-d.y * 40.0) * (1.0 - d.x) + exp(-d.x * 40.0) * (1.0 - d.y);
+    return vec4<f32>(in.color * (core + 0.6 * rays), 0.0);
+}
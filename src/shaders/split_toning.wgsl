@@ -0,0 +1,36 @@
+// Split toning pass: tints the shadows and highlights with separate colors
+#include "nnpipe/color.wgsl"
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    shadow_r: f32,
+    shadow_g: f32,
+    shadow_b: f32,
+    highlight_r: f32,
+    highlight_g: f32,
+    highlight_b: f32,
+    balance: f32, // -1 favors the shadow tint, 1 the highlight tint
+}
+
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    let luminance = luma(color.rgb);
+
+    // Only the tints' hue and saturation are applied, grey tints change nothing
+    let shadow = vec3<f32>(params.shadow_r, params.shadow_g, params.shadow_b);
+    let highlight = vec3<f32>(params.highlight_r, params.highlight_g, params.highlight_b);
+    let shadow_shift = shadow - dot(shadow, LUMA);
+    let highlight_shift = highlight - dot(highlight, LUMA);
+
+    // The balance moves the split point between the two tints
+    let pivot = 0.5 - params.balance * 0.5;
+    let highlight_weight = smoothstep(pivot - 0.5, pivot + 0.5, luminance);
+
+    // Applied as a gain so black stays black
+    let toned = color.rgb * (1.0 + 2.0 * mix(shadow_shift, highlight_shift, highlight_weight));
+    return vec4<f32>(max(toned, vec3<f32>(0.0)), color.a);
+}
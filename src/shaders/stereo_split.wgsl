@@ -0,0 +1,20 @@
+#include "nnpipe/fullscreen.wgsl"
+
+// Copies one eye's part of a packed stereo texture, scaled to the eye's scene
+@group(0) @binding(0) var packed_tex: texture_2d<f32>;
+@group(0) @binding(1) var packed_sampler: sampler;
+
+struct Split {
+    // Top left and size of the eye's part, in fractions of the packed texture
+    offset: vec2<f32>,
+    scale: vec2<f32>,
+    target_size: vec2<f32>,
+    _padding: vec2<f32>,
+}
+@group(0) @binding(2) var<uniform> split: Split;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = split.offset + pos.xy / split.target_size * split.scale;
+    return textureSampleLevel(packed_tex, packed_sampler, uv, 0.0);
+}
@@ -0,0 +1,86 @@
+// Text overlay fragment shader: draws lines of text, and optionally a bar graph
+// below them, on a dark backdrop into a corner of the target, from a 3x5 pixel
+// glyph atlas
+@group(0) @binding(0) var atlas: texture_2d<f32>;
+
+struct OverlayUniforms {
+    target_size: vec2<f32>,
+    scale: f32,
+    corner: f32, // 0 top left, 1 top right, 2 bottom left, 3 bottom right
+    lines: u32,
+    columns: u32,
+    graph_len: u32,
+    _padding: u32,
+    // ASCII codes by line, `columns` per line, four per u32 from the lowest byte
+    text: array<vec4<u32>, 30>,
+    // Bar heights, 1 filling the graph, one font pixel wide each, oldest first
+    graph: array<vec4<f32>, 16>,
+}
+@group(0) @binding(1) var<uniform> overlay: OverlayUniforms;
+
+// A character cell in font pixels: the glyph, a column and two rows of spacing
+const CELL: vec2<f32> = vec2<f32>(4.0, 7.0);
+const INSET: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 12.0;
+
+fn char_code(index: u32) -> u32 {
+    let word = overlay.text[index / 16u][(index / 4u) % 4u];
+    return (word >> ((index % 4u) * 8u)) & 0xffu;
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let text_size = vec2<f32>(f32(overlay.columns), f32(overlay.lines)) * CELL;
+    // The last cell's spacing is left out
+    var content = max(text_size - vec2<f32>(1.0, 2.0), vec2<f32>(0.0));
+    if (overlay.graph_len > 0u) {
+        content = vec2<f32>(max(content.x, f32(overlay.graph_len)), text_size.y + GRAPH_HEIGHT);
+    }
+    let box_size = (content + 2.0 * INSET) * overlay.scale;
+    var origin = vec2<f32>(0.0);
+    if (overlay.corner == 1.0 || overlay.corner == 3.0) {
+        origin.x = overlay.target_size.x - box_size.x;
+    }
+    if (overlay.corner >= 2.0) {
+        origin.y = overlay.target_size.y - box_size.y;
+    }
+
+    let local = pos.xy - origin;
+    if (any(local < vec2<f32>(0.0)) || any(local >= box_size)) {
+        discard;
+    }
+
+    let font = floor(local / overlay.scale) - vec2<f32>(INSET);
+    if (all(font >= vec2<f32>(0.0)) && all(font < text_size)) {
+        let cell = vec2<u32>(font / CELL);
+        let pixel = vec2<u32>(font % CELL);
+        let code = char_code(cell.y * overlay.columns + cell.x);
+        if (pixel.x < 3u && pixel.y < 5u && code >= 32u && code < 96u) {
+            let texel = vec2<i32>(i32((code - 32u) * 3u + pixel.x), i32(pixel.y));
+            if (textureLoad(atlas, texel, 0).r > 0.5) {
+                return vec4<f32>(1.0);
+            }
+        }
+    }
+
+    // Bars rise from the bottom of the graph, red where they pass its middle
+    let graph = font - vec2<f32>(0.0, text_size.y);
+    let graph_size = vec2<f32>(f32(overlay.graph_len), GRAPH_HEIGHT);
+    if (all(graph >= vec2<f32>(0.0)) && all(graph < graph_size)) {
+        let index = u32(font.x);
+        let value = overlay.graph[index / 4u][index % 4u];
+        let height = GRAPH_HEIGHT - graph.y;
+        if (height <= ceil(clamp(value, 0.0, 1.0) * GRAPH_HEIGHT)) {
+            if (height > GRAPH_HEIGHT * 0.5) {
+                return vec4<f32>(1.0, 0.2, 0.2, 1.0);
+            }
+            return vec4<f32>(0.3, 1.0, 0.4, 1.0);
+        }
+        if (height == GRAPH_HEIGHT * 0.5) {
+            return vec4<f32>(0.5, 0.5, 0.5, 1.0);
+        }
+    }
+
+    // Dark backdrop, so the text reads over bright content
+    return vec4<f32>(0.0, 0.0, 0.0, 0.6);
+}
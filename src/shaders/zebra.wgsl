@@ -0,0 +1,33 @@
+#include "nnpipe/fullscreen.wgsl"
+#include "nnpipe/color.wgsl"
+
+// Zebra fragment shader: copies the frame, striping pixels over a luminance and
+// painting NaN and infinite ones a solid color
+@group(0) @binding(0) var source: texture_2d<f32>;
+
+struct Zebra {
+    color: vec4<f32>,
+    non_finite_color: vec3<f32>,
+    mark_non_finite: f32,
+    threshold: f32,
+    stripe_width: f32,
+    _padding0: f32,
+    _padding1: f32,
+}
+@group(0) @binding(1) var<uniform> zebra: Zebra;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(source, vec2<i32>(pos.xy), 0);
+
+    if (zebra.mark_non_finite > 0.5 && is_non_finite(color)) {
+        return vec4<f32>(zebra.non_finite_color, 1.0);
+    }
+
+    // Diagonal stripes, running from the bottom left to the top right
+    let stripe = u32(floor((pos.x + pos.y) / zebra.stripe_width)) % 2u == 0u;
+    if (stripe && luma(color.rgb) > zebra.threshold) {
+        return vec4<f32>(mix(color.rgb, zebra.color.rgb, zebra.color.a), color.a);
+    }
+    return color;
+}
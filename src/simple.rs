@@ -0,0 +1,109 @@
+// src/simple.rs
+//
+// Quick-start wrapper
+//
+// The full API leaves the host in charge of sizes, the scene's `Draw` renderer,
+// resizes and presenting, which a sketch that just wants a glow around its shapes
+// shouldn't have to learn first. `SimplePipe` does all of that for a window's frames:
+//
+// ```ignore
+// fn model(app: &App) -> SimplePipe {
+//     app.new_window().view(view).build().unwrap();
+//     SimplePipe::new(app).unwrap()
+// }
+//
+// fn view(app: &App, pipe: &SimplePipe, frame: Frame) {
+//     pipe.draw(app, &frame, |draw| {
+//         draw.ellipse().w_h(200.0, 200.0).color(WHITE);
+//     });
+// }
+// ```
+
+use std::cell::{Ref, RefCell};
+
+use nannou::prelude::*;
+use nannou::wgpu;
+
+use crate::error::Result;
+use crate::nnpipe::Nnpipe;
+
+/// A pipeline sized to the app's main window, drawn into with a closure and
+/// presented to the window's frames.
+///
+/// The pipeline follows the window's size in pixels, and draws use the window's
+/// coordinates in points, like `app.draw()`. Reach the full API through
+/// [`SimplePipe::pipeline_mut`].
+pub struct SimplePipe {
+    pipeline: RefCell<Nnpipe>,
+    renderer: RefCell<nannou::draw::Renderer>,
+}
+
+impl SimplePipe {
+    /// A pipeline for the app's main window, multisampling the scene like the window.
+    ///
+    /// Fails if the window's size or sample count can't be used for the pipeline's
+    /// textures.
+    pub fn new(app: &App) -> Result<Self> {
+        let window = app.main_window();
+        let device = window.device();
+        let (width, height) = window.inner_size_pixels();
+        let samples = window.msaa_samples();
+        let pipeline = Nnpipe::new(device, width, height, samples)?;
+        let renderer = nannou::draw::RendererBuilder::new().build(
+            device,
+            [width, height],
+            1.0,
+            samples,
+            wgpu::TextureFormat::Rgba16Float,
+        );
+        Ok(Self {
+            pipeline: RefCell::new(pipeline),
+            renderer: RefCell::new(renderer),
+        })
+    }
+
+    pub fn pipeline(&self) -> Ref<'_, Nnpipe> {
+        self.pipeline.borrow()
+    }
+
+    pub fn pipeline_mut(&mut self) -> &mut Nnpipe {
+        self.pipeline.get_mut()
+    }
+
+    /// Draw the scene with `draw_scene`, run it through the pipeline and present the
+    /// result to `frame`.
+    ///
+    /// The scene starts out black every frame. If the window was resized since the
+    /// last frame, the pipeline is resized to it first; while that fails (e.g. for a
+    /// minimized window), it keeps its size and gets scaled into the frame.
+    pub fn draw<F>(&self, app: &App, frame: &Frame, draw_scene: F)
+    where
+        F: FnOnce(&Draw),
+    {
+        let device_queue_pair = frame.device_queue_pair();
+        let (device, queue) = (device_queue_pair.device(), device_queue_pair.queue());
+        let Some(window) = app.window(frame.window_id()) else {
+            return;
+        };
+
+        let mut pipeline = self.pipeline.borrow_mut();
+        let size = frame.texture_size();
+        if pipeline.base_size() != size {
+            // On failure the pipeline keeps its size, scaled into the frame below
+            let _ = pipeline.resize(device, queue, size[0], size[1]);
+        }
+
+        // Points scaled to pixels, as the scene renders at the window's size in pixels
+        let draw = Draw::new();
+        draw.background().color(BLACK);
+        draw_scene(&draw.scale(window.scale_factor()));
+        let mut renderer = self.renderer.borrow_mut();
+        pipeline.render(device, queue, &mut renderer, &draw);
+
+        let present = Draw::new();
+        let (width, height) = window.rect().w_h();
+        present.texture(&pipeline.output_texture).w_h(width, height);
+        // Drawing a texture into a frame only fails for missing textures
+        let _ = present.to_frame(app, frame);
+    }
+}
@@ -0,0 +1,231 @@
+// src/sparkle.rs
+//
+// Sparkle layer
+//
+// A glitter of short-lived glints over the bright parts of the frame. The frame is
+// divided into a grid of cells, each drawn as one instance of a quad: every lifetime
+// its vertex shader picks a random spot in the cell, looks it up in the bloom's
+// brightness texture, and either collapses the quad or grows a glint there that fades
+// in and out. Nothing is simulated on the CPU or kept between frames, so the
+// sparkles follow the time and seed like everything else and renders with both
+// pinned are reproducible. The glints are added over the composite, ahead of the
+// effect passes.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// Settings of the sparkle layer, see [`Nnpipe::set_sparkles`](crate::Nnpipe::set_sparkles).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct Sparkles {
+    /// Brightness a spot needs to sparkle, from 0 to 1, as the bloom's threshold
+    /// leaves it.
+    pub threshold: f32,
+    /// Chance of a cell sparkling in each lifetime, from 0 to 1.
+    pub density: f32,
+    /// Radius of the glints' rays in pixels, varying by half either way.
+    pub size: f32,
+    /// Seconds a glint lasts.
+    pub lifetime: f32,
+    pub intensity: f32,
+    /// Side of the grid cells in pixels. Each cell shows one glint at a time, so
+    /// smaller cells make a denser glitter.
+    pub cell_size: f32,
+}
+
+impl Default for Sparkles {
+    fn default() -> Self {
+        Self {
+            threshold: 0.5,
+            density: 0.3,
+            size: 12.0,
+            lifetime: 0.4,
+            intensity: 2.0,
+            cell_size: 24.0,
+        }
+    }
+}
+
+impl Sparkles {
+    // The settings as the shader's uniform, with sizes kept where they make sense
+    fn uniform(&self) -> [f32; 8] {
+        [
+            self.threshold,
+            self.density,
+            self.size.max(0.0),
+            self.lifetime.max(1e-3),
+            self.intensity,
+            self.cell_size.max(1.0),
+            0.0,
+            0.0,
+        ]
+    }
+
+    // Number of grid cells over a frame of `width` x `height`
+    fn cells(&self, [width, height]: [u32; 2]) -> u32 {
+        let cell_size = self.cell_size.max(1.0);
+        let columns = (width as f32 / cell_size).ceil() as u32;
+        let rows = (height as f32 / cell_size).ceil() as u32;
+        columns * rows
+    }
+}
+
+pub(crate) struct SparkleLayer {
+    settings: Sparkles,
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl SparkleLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        brightness_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        globals_buffer: &UniformBuffer,
+        settings: Sparkles,
+    ) -> Self {
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Sparkle Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Sparkle Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                uniform_entry(2),
+                uniform_entry(3),
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Sparkle Pipeline Layout", &bind_group_layout);
+
+        // Glints add their light to the frame
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let shader = cache.shader(
+            device,
+            "Sparkle Shader",
+            &expand(include_str!("shaders/sparkle.wgsl")),
+        );
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Sparkle Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            Some(wgpu::BlendState {
+                color: additive,
+                alpha: additive,
+            }),
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sparkle Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(brightness_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: globals_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            settings,
+            uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> Sparkles {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Sparkles) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Add the glints over `target`, a frame of `size`
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "sparkles", skip_all))]
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        size: [u32; 2],
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Sparkle pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..6, 0..self.settings.cells(size)); // One quad per cell
+    }
+}
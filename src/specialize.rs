@@ -0,0 +1,69 @@
+// src/specialize.rs
+//
+// Compile-time constants of pass shaders
+//
+// A loop over a uniform radius or sample count costs the same dynamic branching at
+// every quality level. Specializing bakes such values into the shader instead: a
+// pass declares them as typed module constants,
+//
+//     const SAMPLES: u32 = 16u;
+//     const USE_DITHER: bool = true;
+//
+// and gets rebuilt with other values written into the declarations. wgpu doesn't
+// support WGSL's pipeline-overridable constants yet, so this is done on the source.
+// The pipeline cache keys shaders by their source, so every set of values compiles
+// once and switching back to it is a cache hit.
+
+// A copy of `source` with the value of each of `constants` substituted into its
+// `const` declaration. Values are rounded for integers and `bool`s are true unless 0.
+pub(crate) fn specialize(source: &str, constants: &[(String, f32)]) -> Result<String, String> {
+    let mut source = source.to_string();
+    for (name, value) in constants {
+        let (ty, value_range) =
+            declaration(&source, name).ok_or_else(|| format!("no constant '{name}'"))?;
+        let ty = ty.ok_or_else(|| format!("constant '{name}' has no type to specialize it as"))?;
+        if !value.is_finite() {
+            return Err(format!("constant '{name}' can't be {value}"));
+        }
+        let literal = match ty.as_str() {
+            "bool" => (*value != 0.0).to_string(),
+            "i32" => format!("{}i", value.round() as i32),
+            "u32" => format!("{}u", value.round().max(0.0) as u32),
+            "f32" => format!("{value:?}f"),
+            _ => {
+                return Err(format!(
+                    "constant '{name}' is a {ty}; only bool, i32, u32 and f32 can be specialized"
+                ))
+            }
+        };
+        source.replace_range(value_range, &format!(" {literal}"));
+    }
+    Ok(source)
+}
+
+// The type (if annotated) of the `const` declaration of `name`, and the byte range
+// of its value, between the `=` and the `;`
+fn declaration(source: &str, name: &str) -> Option<(Option<String>, std::ops::Range<usize>)> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    source.match_indices("const").find_map(|(start, _)| {
+        if source[..start].chars().next_back().is_some_and(is_ident) {
+            return None;
+        }
+        let after = &source[start + "const".len()..];
+        if !after.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let rest = after.trim_start().strip_prefix(name)?;
+        if rest.starts_with(is_ident) {
+            return None;
+        }
+        let (head, _) = rest.split_once('=')?;
+        let ty = head
+            .trim()
+            .strip_prefix(':')
+            .map(|ty| ty.trim().to_string());
+        let value_start = source.len() - rest.len() + head.len() + 1;
+        let value_end = value_start + source[value_start..].find(';')?;
+        Some((ty, value_start..value_end))
+    })
+}
@@ -0,0 +1,540 @@
+// src/ssr.rs
+//
+// Screen-space reflections
+//
+// For scenes rendered with their own depth and normal buffers. Every pixel of the
+// bloom-sized reflection texture reconstructs its view-space position from the
+// depth, reflects the view ray about its normal and marches along it until it passes
+// behind the depth buffer, then picks up the scene's color there. The reflections
+// are blurred with the bloom's blur shader, further on rougher surfaces, and added to
+// the scene in the composite, ahead of its tone mapping.
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// Settings of the screen-space reflections, see
+/// [`Nnpipe::enable_reflections`](crate::Nnpipe::enable_reflections).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct Reflections {
+    /// The scene camera's projection matrix, in columns, taking right-handed view
+    /// space to wgpu's clip space (depth from 0 to 1).
+    pub projection: [[f32; 4]; 4],
+    pub intensity: f32,
+    /// Farthest a reflected ray is followed, in view-space units.
+    pub max_distance: f32,
+    /// Steps along each ray, up to 256.
+    pub steps: u32,
+    /// Depth behind a surface, in view-space units, at which a ray still counts as
+    /// hitting it rather than passing behind it.
+    pub thickness: f32,
+    /// Blur radius of the roughest surfaces, in pixels of the bloom textures.
+    pub blur_radius: f32,
+    /// Width of the fade toward the frame's edges, as a fraction of the frame.
+    pub edge_fade: f32,
+}
+
+impl Default for Reflections {
+    fn default() -> Self {
+        Self {
+            projection: Mat4::perspective_rh(std::f32::consts::FRAC_PI_3, 1.0, 0.1, 100.0)
+                .to_cols_array_2d(),
+            intensity: 0.5,
+            max_distance: 10.0,
+            steps: 48,
+            thickness: 0.5,
+            blur_radius: 16.0,
+            edge_fade: 0.1,
+        }
+    }
+}
+
+impl Reflections {
+    // The settings as the trace shader's uniform, for a target of `size`
+    fn uniform(&self, size: [u32; 2]) -> [f32; 40] {
+        let projection = Mat4::from_cols_array_2d(&self.projection);
+        let mut data = [0.0; 40];
+        data[..16].copy_from_slice(&projection.to_cols_array());
+        data[16..32].copy_from_slice(&projection.inverse().to_cols_array());
+        data[32..].copy_from_slice(&[
+            self.intensity,
+            self.max_distance.max(1e-3),
+            self.thickness,
+            self.steps.clamp(1, 256) as f32,
+            size[0] as f32,
+            size[1] as f32,
+            self.edge_fade,
+            0.0,
+        ]);
+        data
+    }
+}
+
+// Bloom-sized textures the reflections are traced into and blurred through
+struct ReflectionTargets {
+    size: [u32; 2],
+    trace_view: wgpu::TextureView,
+    blur_h_view: wgpu::TextureView,
+    blur_v_view: wgpu::TextureView,
+    blur_h_bind_group: wgpu::BindGroup,
+    blur_v_bind_group: wgpu::BindGroup,
+}
+
+pub(crate) struct ReflectionLayer {
+    settings: Reflections,
+    uniform_buffer: UniformBuffer,
+    // The blur shader's uniforms: directions, radius scaling and roughest radius
+    blur_h_buffer: UniformBuffer,
+    blur_v_buffer: UniformBuffer,
+    adaptive_scaling_buffer: UniformBuffer,
+    max_radius_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    blur_pipeline: Arc<wgpu::RenderPipeline>,
+    blur_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    // Depth, normals and settings in group 0; the scene in group 1, bound per scene
+    inputs_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    inputs_bind_group: wgpu::BindGroup,
+    scene_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+    scene_bind_group: wgpu::BindGroup,
+    targets: ReflectionTargets,
+    device: wgpu_upstream::Id<wgpu::Device>,
+}
+
+impl ReflectionLayer {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        depth_view: &wgpu::TextureView,
+        normal_view: &wgpu::TextureView,
+        scene_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        blur_bind_group_layout: &Arc<wgpu::BindGroupLayout>,
+        size: [u32; 2],
+        format: wgpu::TextureFormat,
+        settings: Reflections,
+    ) -> Self {
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Reflection Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform(size)),
+        );
+        let blur_h_buffer = UniformBuffer::new(
+            device,
+            "Reflection Horizontal Blur Buffer",
+            bytemuck::cast_slice(&[1.0f32, 0.0]),
+        );
+        let blur_v_buffer = UniformBuffer::new(
+            device,
+            "Reflection Vertical Blur Buffer",
+            bytemuck::cast_slice(&[0.0f32, 1.0]),
+        );
+        // The blur radius follows the roughness in the trace's alpha
+        let adaptive_scaling_buffer = UniformBuffer::new(
+            device,
+            "Reflection Blur Scaling Buffer",
+            bytemuck::cast_slice(&[1.0f32]),
+        );
+        let max_radius_buffer = UniformBuffer::new(
+            device,
+            "Reflection Blur Radius Buffer",
+            bytemuck::cast_slice(&[settings.blur_radius]),
+        );
+
+        let inputs_bind_group_layout = cache.bind_group_layout(
+            device,
+            "Reflection Inputs Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let scene_bind_group_layout = cache.bind_group_layout(
+            device,
+            "Reflection Scene Bind Group Layout",
+            &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        );
+        let [pipeline, blur_pipeline] = create_pipelines(
+            device,
+            cache,
+            [&inputs_bind_group_layout, &scene_bind_group_layout],
+            blur_bind_group_layout,
+            format,
+        );
+
+        let inputs_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Reflection Inputs Bind Group"),
+            layout: &inputs_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(normal_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let scene_bind_group =
+            create_scene_bind_group(device, &scene_bind_group_layout, scene_view);
+
+        let targets = ReflectionTargets::new(
+            device,
+            sampler,
+            blur_bind_group_layout,
+            size,
+            format,
+            [&blur_h_buffer, &blur_v_buffer],
+            &adaptive_scaling_buffer,
+            &max_radius_buffer,
+        );
+
+        Self {
+            settings,
+            uniform_buffer,
+            blur_h_buffer,
+            blur_v_buffer,
+            adaptive_scaling_buffer,
+            max_radius_buffer,
+            pipeline,
+            blur_pipeline,
+            blur_bind_group_layout: blur_bind_group_layout.clone(),
+            inputs_bind_group_layout,
+            inputs_bind_group,
+            scene_bind_group_layout,
+            scene_bind_group,
+            targets,
+            device: device.global_id(),
+        }
+    }
+
+    // Move the layer over to a rebuilt pipeline: its scene, bloom size and format
+    pub fn rebuild(
+        &mut self,
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        scene_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        size: [u32; 2],
+        format: wgpu::TextureFormat,
+    ) {
+        self.scene_bind_group =
+            create_scene_bind_group(device, &self.scene_bind_group_layout, scene_view);
+        self.targets = ReflectionTargets::new(
+            device,
+            sampler,
+            &self.blur_bind_group_layout,
+            size,
+            format,
+            [&self.blur_h_buffer, &self.blur_v_buffer],
+            &self.adaptive_scaling_buffer,
+            &self.max_radius_buffer,
+        );
+        [self.pipeline, self.blur_pipeline] = create_pipelines(
+            device,
+            cache,
+            [
+                &self.inputs_bind_group_layout,
+                &self.scene_bind_group_layout,
+            ],
+            &self.blur_bind_group_layout,
+            format,
+        );
+        self.set_settings(self.settings);
+    }
+
+    // The depth and normal buffers were created on `device`, and are lost with it
+    pub fn belongs_to(&self, device: &wgpu::Device) -> bool {
+        self.device == device.global_id()
+    }
+
+    pub fn settings(&self) -> Reflections {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Reflections) {
+        self.settings = settings;
+        self.uniform_buffer.write(
+            0,
+            bytemuck::cast_slice(&settings.uniform(self.targets.size)),
+        );
+        self.max_radius_buffer
+            .write(0, bytemuck::cast_slice(&[settings.blur_radius]));
+    }
+
+    pub fn uniform_buffers(&self) -> [&UniformBuffer; 2] {
+        [&self.uniform_buffer, &self.max_radius_buffer]
+    }
+
+    // The sharp and blurred reflections, for the composite
+    pub fn views(&self) -> [&wgpu::TextureView; 2] {
+        [&self.targets.trace_view, &self.targets.blur_v_view]
+    }
+
+    // A group 1 bind group for tracing reflections of another scene view
+    pub fn scene_bind_group(
+        &self,
+        device: &wgpu::Device,
+        scene_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        create_scene_bind_group(device, &self.scene_bind_group_layout, scene_view)
+    }
+
+    // Trace and blur the reflections of the scene in `scene_bind_group`, or of the
+    // pipeline's scene texture
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "reflections", skip_all)
+    )]
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scene_bind_group: Option<&wgpu::BindGroup>,
+    ) {
+        let targets = &self.targets;
+        let passes = [
+            (
+                "Reflection trace pass",
+                &targets.trace_view,
+                &self.pipeline,
+                &self.inputs_bind_group,
+            ),
+            (
+                "Reflection horizontal blur pass",
+                &targets.blur_h_view,
+                &self.blur_pipeline,
+                &targets.blur_h_bind_group,
+            ),
+            (
+                "Reflection vertical blur pass",
+                &targets.blur_v_view,
+                &self.blur_pipeline,
+                &targets.blur_v_bind_group,
+            ),
+        ];
+        for (i, (label, view, pipeline, bind_group)) in passes.into_iter().enumerate() {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            if i == 0 {
+                let scene = scene_bind_group.unwrap_or(&self.scene_bind_group);
+                pass.set_bind_group(1, scene, &[]);
+            }
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+    }
+
+    // Leave the reflections the composite reads black, as if there were none
+    pub fn clear(&self, encoder: &mut wgpu::CommandEncoder) {
+        for view in self.views() {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Reflection clear pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+        }
+    }
+}
+
+impl ReflectionTargets {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &wgpu::Device,
+        sampler: &wgpu::Sampler,
+        blur_bind_group_layout: &wgpu::BindGroupLayout,
+        size: [u32; 2],
+        format: wgpu::TextureFormat,
+        direction_buffers: [&wgpu::Buffer; 2],
+        adaptive_scaling_buffer: &wgpu::Buffer,
+        max_radius_buffer: &wgpu::Buffer,
+    ) -> Self {
+        let target = || {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                )
+                .format(format)
+                .build(device)
+                .view()
+                .build()
+        };
+        let trace_view = target();
+        let blur_h_view = target();
+        let blur_v_view = target();
+
+        let blur_bind_group = |label, view, direction_buffer: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: blur_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: direction_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: adaptive_scaling_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: max_radius_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let blur_h_bind_group = blur_bind_group(
+            "Reflection Horizontal Blur Bind Group",
+            &trace_view,
+            direction_buffers[0],
+        );
+        let blur_v_bind_group = blur_bind_group(
+            "Reflection Vertical Blur Bind Group",
+            &blur_h_view,
+            direction_buffers[1],
+        );
+
+        Self {
+            size,
+            trace_view,
+            blur_h_view,
+            blur_v_view,
+            blur_h_bind_group,
+            blur_v_bind_group,
+        }
+    }
+}
+
+// The trace pipeline and a blur pipeline writing its result as is, where the bloom's
+// blends
+fn create_pipelines(
+    device: &wgpu::Device,
+    cache: &PipelineCache,
+    trace_bind_group_layouts: [&wgpu::BindGroupLayout; 2],
+    blur_bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+) -> [Arc<wgpu::RenderPipeline>; 2] {
+    let trace_layout = cache.grouped_pipeline_layout(
+        device,
+        "Reflection Pipeline Layout",
+        &trace_bind_group_layouts,
+    );
+    let trace_shader = cache.shader(
+        device,
+        "Reflection Shader",
+        &expand(include_str!("shaders/reflections.wgsl")),
+    );
+    let blur_layout = cache.pipeline_layout(device, "Blur Pipeline Layout", blur_bind_group_layout);
+    let blur_shader = cache.shader(
+        device,
+        "Blur Shader",
+        &expand(include_str!("shaders/blur.wgsl")),
+    );
+    [
+        (&trace_layout, &trace_shader, "Reflection Pipeline"),
+        (&blur_layout, &blur_shader, "Reflection Blur Pipeline"),
+    ]
+    .map(|(layout, shader, label)| {
+        cache.pipeline(device, layout, shader, shader, label, format, None)
+    })
+}
+
+fn create_scene_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    scene_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Reflection Scene Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(scene_view),
+        }],
+    })
+}
@@ -0,0 +1,232 @@
+// src/stabilize.rs
+//
+// Bloom stabilization
+//
+// Thin bright lines and specks that cover less than a pixel of the bloom texture
+// land on different texels from frame to frame, which makes the bloom shimmer. The
+// stabilizer keeps a history of the brightness texture and blends each frame's
+// brightness into it with a constant blend factor, then copies the blend back for the
+// blur passes, so a flickering highlight blooms with its average brightness. In
+// stereo each eye keeps a history of its own, so the eyes' highlights don't blend
+// into each other. Only built with the `temporal` feature.
+
+use nannou::wgpu;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+
+/// Settings of the bloom's temporal filter, see
+/// [`Nnpipe::set_bloom_stabilization`](crate::Nnpipe::set_bloom_stabilization).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct BloomStabilization {
+    /// Weight of the previous frames in each frame's brightness, from 0 to 0.95.
+    /// Higher values steady the bloom more, but make it trail behind moving lights.
+    pub feedback: f32,
+}
+
+impl Default for BloomStabilization {
+    fn default() -> Self {
+        Self { feedback: 0.6 }
+    }
+}
+
+// The brightness of the frames before, blended together
+struct History {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    // Whether the history holds a frame yet; the first one is taken as it is
+    primed: AtomicBool,
+}
+
+pub(crate) struct StabilizeLayer {
+    settings: BloomStabilization,
+    // Blends the brightness into a history, and copies the history back
+    blend_pipeline: Arc<wgpu::RenderPipeline>,
+    copy_pipeline: Arc<wgpu::RenderPipeline>,
+    brightness_bind_group: wgpu::BindGroup,
+    // One per eye in stereo
+    histories: Vec<History>,
+}
+
+impl StabilizeLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        brightness_view: &wgpu::TextureView,
+        format: wgpu::TextureFormat,
+        settings: BloomStabilization,
+        histories: usize,
+    ) -> Self {
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Bloom Stabilization Bind Group Layout",
+            &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        );
+        let pipeline_layout = cache.pipeline_layout(
+            device,
+            "Bloom Stabilization Pipeline Layout",
+            &bind_group_layout,
+        );
+        let shader = cache.shader(
+            device,
+            "Bloom Stabilization Shader",
+            &expand(include_str!("shaders/bloom_stabilize.wgsl")),
+        );
+
+        // history = brightness * (1 - feedback) + history * feedback
+        let feedback = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::OneMinusConstant,
+            dst_factor: wgpu::BlendFactor::Constant,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let blend_pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Bloom Stabilization Blend Pipeline",
+            format,
+            Some(wgpu::BlendState {
+                color: feedback,
+                alpha: feedback,
+            }),
+        );
+        let copy_pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Bloom Stabilization Copy Pipeline",
+            format,
+            None,
+        );
+
+        let bind_group = |label, view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }],
+            })
+        };
+        let brightness_bind_group = bind_group("Bloom Stabilization Brightness", brightness_view);
+        let histories = (0..histories)
+            .map(|_| {
+                let view = wgpu::TextureBuilder::new()
+                    .size(brightness_view.size())
+                    .usage(
+                        wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::TEXTURE_BINDING,
+                    )
+                    .format(format)
+                    .build(device)
+                    .view()
+                    .build();
+                let bind_group = bind_group("Bloom Stabilization History", &view);
+                History {
+                    view,
+                    bind_group,
+                    primed: AtomicBool::new(false),
+                }
+            })
+            .collect();
+
+        Self {
+            settings,
+            blend_pipeline,
+            copy_pipeline,
+            brightness_bind_group,
+            histories,
+        }
+    }
+
+    pub fn settings(&self) -> BloomStabilization {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: BloomStabilization) {
+        self.settings = settings;
+    }
+
+    // Start history `history` over, from the next frame encoded into it
+    pub fn reset(&self, history: usize) {
+        self.histories[history]
+            .primed
+            .store(false, Ordering::Relaxed);
+    }
+
+    // Blend the brightness into history `history`, and replace it with the blend
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "bloom_stabilization", skip_all)
+    )]
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        brightness_view: &wgpu::TextureView,
+        history: usize,
+    ) {
+        let history = &self.histories[history];
+        let feedback = if history.primed.swap(true, Ordering::Relaxed) {
+            self.settings.feedback.clamp(0.0, 0.95) as f64
+        } else {
+            0.0
+        };
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Bloom stabilization blend pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &history.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            pass.set_pipeline(&self.blend_pipeline);
+            pass.set_blend_constant(wgpu::Color {
+                r: feedback,
+                g: feedback,
+                b: feedback,
+                a: feedback,
+            });
+            pass.set_bind_group(0, &self.brightness_bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Bloom stabilization copy pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: brightness_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.copy_pipeline);
+        pass.set_bind_group(0, &history.bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
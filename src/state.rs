@@ -0,0 +1,321 @@
+// src/state.rs
+//
+// Pipeline state snapshots
+//
+// A config covers the settings, the chain's passes and the layers over the composite;
+// a state adds the rest of what a performance builds up: the matte, the temporal
+// layers, the pass graph, triggers, macros, the bypass and the noise seed. Snapshots
+// are plain data, so two looks can be flipped between for A/B comparisons, and
+// written to disk every so often so a crashed show comes back where it was. Restoring
+// keeps the passes the chain already has where it can, reordering them to the
+// snapshot's order, and only compiles the ones it lacks. The new passes are built
+// next to the chain's and swapped in once all of them are, so a state that fails to
+// restore leaves the pipeline as it was. Only built with the `config` feature.
+
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use nannou::wgpu;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "temporal")]
+use crate::accumulate::Accumulation;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::is_ron;
+use crate::config::{error, missing_error, ConfigError, PipelineConfig};
+use crate::error::Result;
+use crate::macros::{MacroState, MacroTarget};
+use crate::matte::Matte;
+use crate::nnpipe::Nnpipe;
+use crate::pass_graph::PassGraph;
+#[cfg(feature = "temporal")]
+use crate::stabilize::BloomStabilization;
+use crate::trigger::Trigger;
+
+/// Everything [`Nnpipe::snapshot`] captures, for [`Nnpipe::restore`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineState {
+    /// The chain and its settings, as [`Nnpipe::chain_config`] gives them.
+    pub config: PipelineConfig,
+    /// See [`Nnpipe::set_matte`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matte: Option<Matte>,
+    /// See [`Nnpipe::set_bloom_stabilization`].
+    #[cfg(feature = "temporal")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stabilization: Option<BloomStabilization>,
+    /// See [`Nnpipe::set_accumulation`].
+    #[cfg(feature = "temporal")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accumulation: Option<Accumulation>,
+    /// See [`Nnpipe::set_pass_graph`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pass_graph: Option<PassGraph>,
+    pub triggers: Vec<NamedTrigger>,
+    pub macros: Vec<NamedMacro>,
+    pub bypass: bool,
+    pub seed: u32,
+}
+
+/// A trigger of a [`PipelineState`] and the name it's fired by.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedTrigger {
+    pub name: String,
+    pub trigger: Trigger,
+}
+
+/// A macro of a [`PipelineState`], with its name and the value it was set to.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedMacro {
+    pub name: String,
+    pub targets: Vec<MacroTarget>,
+    pub value: f32,
+}
+
+impl PipelineState {
+    /// Load a `.ron` file, or any other file as TOML.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let source =
+            std::fs::read_to_string(path).map_err(|e| error(format!("{}: {e}", path.display())))?;
+        let result = if is_ron(path) {
+            Self::from_ron(&source)
+        } else {
+            Self::from_toml(&source)
+        };
+        result.map_err(|e| error(format!("{}: {}", path.display(), e.message)))
+    }
+
+    /// Write the state to `path`, as RON for `.ron` files and TOML otherwise.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let source = if is_ron(path) {
+            self.to_ron()?
+        } else {
+            self.to_toml()?
+        };
+        std::fs::write(path, source).map_err(|e| error(format!("{}: {e}", path.display())))
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, ConfigError> {
+        toml::from_str(source).map_err(error)
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, ConfigError> {
+        ron::from_str(source).map_err(error)
+    }
+
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(self).map_err(error)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ConfigError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()).map_err(error)
+    }
+}
+
+impl Nnpipe {
+    /// The pipeline's passes in order with their shaders and settings, its layers and
+    /// pass graph, its triggers, macros, bypass and seed, for [`Nnpipe::restore`].
+    ///
+    /// Buffers and textures bound with [`Nnpipe::add_custom_pass_with_inputs`] are the
+    /// sketch's own and aren't captured.
+    pub fn snapshot(&self) -> PipelineState {
+        PipelineState {
+            config: self.chain_config(),
+            matte: self.matte(),
+            #[cfg(feature = "temporal")]
+            stabilization: self.bloom_stabilization(),
+            #[cfg(feature = "temporal")]
+            accumulation: self.accumulation(),
+            pass_graph: self.pass_graph().cloned(),
+            triggers: self
+                .named_triggers()
+                .map(|(name, trigger)| NamedTrigger {
+                    name: name.to_string(),
+                    trigger: trigger.clone(),
+                })
+                .collect(),
+            macros: self
+                .macros
+                .iter()
+                .map(|state| NamedMacro {
+                    name: state.name.clone(),
+                    targets: state.targets.clone(),
+                    value: state.value,
+                })
+                .collect(),
+            bypass: self.bypass(),
+            seed: self.seed(),
+        }
+    }
+
+    /// Bring the pipeline back to `state`, taken with [`Nnpipe::snapshot`] on this or
+    /// another pipeline.
+    ///
+    /// The chain ends up with the state's passes in its order. Passes the chain
+    /// already has with the same label, shader, kind and constants are moved into
+    /// place, keeping their inputs; the others are added like in
+    /// [`Nnpipe::from_config`], and passes the state doesn't have are dropped. Layers
+    /// are switched on or off to match the state, except the reflections, which take
+    /// the host's buffers: they're only updated or switched off. Macros are put back at
+    /// their values without moving their targets, which the settings already hold. The
+    /// chain's size and formats stay as they are, and a running morph stops.
+    ///
+    /// Fails if a pass of the state has no source, if its constants can't be
+    /// specialized, if it lacks a param the state sets, if an output the state sets
+    /// doesn't exist or if the pass graph can't be scheduled. The pipeline is left as
+    /// it was then.
+    pub fn restore(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        state: &PipelineState,
+    ) -> Result<()> {
+        let sources = state.config.sources()?;
+        for lookup in state
+            .config
+            .passes
+            .iter()
+            .filter_map(|pass| pass.lookup.as_ref())
+        {
+            lookup.check()?;
+        }
+        let graph_layer = match &state.pass_graph {
+            Some(graph) => Some(
+                self.new_graph_layer(device, graph.clone())
+                    .map_err(|e| error(format!("pass graph: {e}")))?,
+            ),
+            None => None,
+        };
+
+        // Build the passes the chain lacks after its own, which stay in place until all
+        // of them are
+        let count = (0..).map_while(|index| self.custom_pass(index)).count();
+        let mut order = Vec::with_capacity(sources.len());
+        for (pass_config, source) in state.config.passes.iter().zip(sources) {
+            let label = pass_config.label.as_deref().unwrap_or("Custom Pass");
+            let kept = (0..count).find(|index| {
+                let pass = self.custom_pass(*index).expect("a pass of the chain");
+                !order.contains(index)
+                    && pass.label == label
+                    && pass.source() == source
+                    && pass.is_compute() == pass_config.compute
+                    && pass.constants().iter().cloned().collect::<BTreeMap<_, _>>()
+                        == pass_config.constants
+            });
+            let index = match kept {
+                Some(index) => index,
+                None => match self.add_config_pass(device, queue, pass_config, source) {
+                    Ok(index) => index,
+                    Err(e) => {
+                        self.truncate_passes(count);
+                        return Err(e);
+                    }
+                },
+            };
+            order.push(index);
+        }
+        if let Err(e) = missing_error(&self.missing_settings(state, &order)) {
+            self.truncate_passes(count);
+            return Err(e.into());
+        }
+
+        // Everything that could fail has passed; swap the passes into the state's order
+        let mut previous: Vec<_> = self.take_passes().into_iter().map(Some).collect();
+        let mut passes = Vec::with_capacity(order.len());
+        for (pass_config, index) in state.config.passes.iter().zip(order) {
+            let pass = previous[index].take().expect("each pass is used once");
+            let index = self.push_taken_pass(pass);
+            self.set_config_resources(device, queue, index, pass_config)?;
+            passes.push(pass_config.settings(index));
+        }
+        self.morph = None;
+
+        let names: Vec<String> = self
+            .named_triggers()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        for name in names {
+            self.remove_triggers(&name);
+        }
+
+        // The matte goes first, for the distance glow to bind
+        self.set_matte(device, state.matte);
+        let config = &state.config;
+        #[cfg(feature = "bloom")]
+        self.set_lens_flare(device, config.flare);
+        #[cfg(feature = "stylize")]
+        {
+            self.set_sparkles(device, config.sparkles);
+            self.set_drop_shadow(device, config.drop_shadow);
+            self.set_distance_glow(device, config.distance_glow);
+        }
+        match config.reflections {
+            Some(reflections) => self.set_reflections(reflections),
+            None => self.disable_reflections(device),
+        }
+        #[cfg(feature = "temporal")]
+        {
+            self.set_bloom_stabilization(device, state.stabilization);
+            self.set_accumulation(device, state.accumulation);
+        }
+        self.replace_graph_layer(graph_layer);
+
+        let settings = PipelineConfig {
+            bloom: config.bloom.clone(),
+            passes,
+            outputs: config.outputs.clone(),
+            ..Default::default()
+        };
+        self.apply_settings(&settings)?;
+        self.set_channel_thresholds(config.bloom.channel_thresholds);
+
+        for NamedTrigger { name, trigger } in &state.triggers {
+            self.add_trigger(name.clone(), trigger.clone());
+        }
+        self.macros = state
+            .macros
+            .iter()
+            .map(|named| MacroState {
+                name: named.name.clone(),
+                targets: named.targets.clone(),
+                value: named.value,
+            })
+            .collect();
+        self.set_bypass(state.bypass);
+        self.set_seed(state.seed);
+        Ok(())
+    }
+
+    // What `state` sets that the chain, with the state's passes at `order`, lacks
+    fn missing_settings(&self, state: &PipelineState, order: &[usize]) -> Vec<String> {
+        let params = state
+            .config
+            .passes
+            .iter()
+            .zip(order)
+            .flat_map(|(pass_config, index)| {
+                let pass = self.custom_pass(*index).expect("built for the state");
+                pass_config
+                    .params
+                    .keys()
+                    .filter(|name| pass.param(name).is_none())
+                    .map(|name| format!("param '{name}' of pass '{}'", pass.label))
+            });
+        let outputs = state
+            .config
+            .outputs
+            .iter()
+            .filter_map(|output| output.index)
+            .filter(|index| self.output(*index).is_none())
+            .map(|index| format!("output {index}"));
+        params.chain(outputs).collect()
+    }
+}
@@ -0,0 +1,232 @@
+// src/stereo.rs
+//
+// Stereo rendering
+//
+// Headsets and 3D projection show each eye its own view of the scene. In stereo, the
+// pipeline runs both views through the same chain, so the eyes always share their
+// params, into a frame per eye, which outputs present one eye per target or packed
+// side by side or top and bottom into one. The views come as two scene textures or
+// as one with both packed into it, which is split into eye scenes at the pipeline's
+// resolution first. Temporal effects keep a history per eye, so neither eye's
+// history sees the other's frames.
+
+use nannou::wgpu;
+use nannou::wgpu::util::DeviceExt;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+
+/// One of the two views of stereo rendering, see
+/// [`Nnpipe::set_stereo`](crate::Nnpipe::set_stereo).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+impl Eye {
+    pub const BOTH: [Eye; 2] = [Eye::Left, Eye::Right];
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// How both eyes are packed into one texture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StereoLayout {
+    /// The left eye in the left half, the right eye in the right half.
+    #[default]
+    SideBySide,
+    /// The left eye in the top half, the right eye in the bottom half.
+    TopBottom,
+}
+
+impl StereoLayout {
+    /// The eye's part of a packed texture of `size`, as x, y, width and height in
+    /// pixels. The right or bottom half gets the odd pixel.
+    pub fn region(self, eye: Eye, [width, height]: [u32; 2]) -> [u32; 4] {
+        match (self, eye) {
+            (StereoLayout::SideBySide, Eye::Left) => [0, 0, width / 2, height],
+            (StereoLayout::SideBySide, Eye::Right) => [width / 2, 0, width - width / 2, height],
+            (StereoLayout::TopBottom, Eye::Left) => [0, 0, width, height / 2],
+            (StereoLayout::TopBottom, Eye::Right) => [0, height / 2, width, height - height / 2],
+        }
+    }
+}
+
+pub(crate) struct StereoLayer {
+    // Eye scenes split from packed textures, at the pipeline's size
+    scene_views: [wgpu::TextureView; 2],
+    // The chain's output per eye
+    frame_views: [wgpu::TextureView; 2],
+    split_layout: Arc<wgpu::BindGroupLayout>,
+    split_pipeline: Arc<wgpu::RenderPipeline>,
+    split_buffers: [wgpu::Buffer; 2],
+}
+
+impl StereoLayer {
+    pub fn new(device: &wgpu::Device, cache: &PipelineCache, size: [u32; 2]) -> Self {
+        let view = || {
+            wgpu::TextureBuilder::new()
+                .size(size)
+                .usage(
+                    wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::COPY_SRC,
+                )
+                .format(wgpu::TextureFormat::Rgba16Float)
+                .build(device)
+                .view()
+                .build()
+        };
+        let scene_views = [view(), view()];
+        let frame_views = [view(), view()];
+
+        let split_layout = cache.bind_group_layout(
+            device,
+            "Stereo Split Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu_types::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Stereo Split Pipeline Layout", &split_layout);
+        let shader = cache.shader(
+            device,
+            "Stereo Split Shader",
+            &expand(include_str!("shaders/stereo_split.wgsl")),
+        );
+        let split_pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Stereo Split Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        // Written per split, as the layout may change between them
+        let split_buffer = || {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Stereo Split Buffer"),
+                contents: bytemuck::cast_slice(&[0.0f32; 8]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            })
+        };
+
+        Self {
+            scene_views,
+            frame_views,
+            split_layout,
+            split_pipeline,
+            split_buffers: [split_buffer(), split_buffer()],
+        }
+    }
+
+    pub fn scene_view(&self, eye: Eye) -> &wgpu::TextureView {
+        &self.scene_views[eye.index()]
+    }
+
+    pub fn frame_view(&self, eye: Eye) -> &wgpu::TextureView {
+        &self.frame_views[eye.index()]
+    }
+
+    // Split `packed` into the eye scenes
+    pub fn split(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        packed: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        layout: StereoLayout,
+    ) {
+        let ce_desc = wgpu::CommandEncoderDescriptor {
+            label: Some("Stereo Split"),
+        };
+        let mut encoder = device.create_command_encoder(&ce_desc);
+        let [packed_width, packed_height] = packed.size().map(|side| side as f32);
+        let [width, height] = self.scene_views[0].size().map(|side| side as f32);
+
+        for eye in Eye::BOTH {
+            let [x, y, region_width, region_height] =
+                layout.region(eye, packed.size()).map(|value| value as f32);
+            let split = [
+                x / packed_width,
+                y / packed_height,
+                region_width / packed_width,
+                region_height / packed_height,
+                width,
+                height,
+                0.0,
+                0.0,
+            ];
+            let buffer = &self.split_buffers[eye.index()];
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&split));
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Stereo Split Bind Group"),
+                layout: &self.split_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(packed),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Stereo split pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: self.scene_view(eye),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(&self.split_pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
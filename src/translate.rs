@@ -0,0 +1,127 @@
+// src/translate.rs
+//
+// Custom passes written in GLSL or SPIR-V
+//
+// Plenty of existing effects are GLSL fragment shaders. naga parses them (and
+// precompiled SPIR-V), and writes the module back out as WGSL, so a translated pass
+// is an ordinary WGSL pass from then on: it reloads, reflects its params and is saved
+// in chain configs like any other. The fragment entry point becomes `fs_main`.
+//
+// GLSL passes are `#version 450` fragment shaders with the usual pass bindings in
+// set 0:
+//
+//     layout(set = 0, binding = 0) uniform texture2D src_tex;
+//     layout(set = 0, binding = 1) uniform sampler src_sampler;
+//     layout(set = 0, binding = 2) uniform Params { float amount; } params;
+//     layout(location = 0) out vec4 color;
+//
+// Only built with the `shader-import` feature.
+
+use nannou::wgpu;
+
+use crate::nnpipe::Nnpipe;
+use crate::pass::{ShaderError, PASSTHROUGH_SOURCE};
+
+/// Translate a GLSL fragment shader into WGSL for a pass. Fails, with `label` in the
+/// error, if it doesn't parse or validate.
+pub fn glsl_to_wgsl(label: &str, source: &str) -> Result<String, ShaderError> {
+    let options = naga::front::glsl::Options::from(naga::ShaderStage::Fragment);
+    let module = naga::front::glsl::Frontend::default()
+        .parse(&options, source)
+        .map_err(|errors| {
+            let messages: Vec<String> = errors
+                .iter()
+                .map(|error| {
+                    let line = error.meta.location(source).line_number;
+                    format!("line {line}: {error}")
+                })
+                .collect();
+            error(label, messages.join("\n"))
+        })?;
+    write_wgsl(label, module, source)
+}
+
+/// Translate a SPIR-V binary with a fragment entry point into WGSL for a pass.
+pub fn spirv_to_wgsl(label: &str, spirv: &[u8]) -> Result<String, ShaderError> {
+    let options = naga::front::spv::Options {
+        // The pass's vertex stage is WGSL, so positions are in its coordinate space
+        adjust_coordinate_space: false,
+        ..Default::default()
+    };
+    let module = naga::front::spv::parse_u8_slice(spirv, &options)
+        .map_err(|parse_error| error(label, parse_error.to_string()))?;
+    write_wgsl(label, module, "")
+}
+
+// Validate `module`, parsed from `source` if it has one, and write it as WGSL with
+// its fragment entry point as `fs_main`
+fn write_wgsl(label: &str, mut module: naga::Module, source: &str) -> Result<String, ShaderError> {
+    let entry_point = module
+        .entry_points
+        .iter_mut()
+        .find(|entry_point| entry_point.stage == naga::ShaderStage::Fragment)
+        .ok_or_else(|| error(label, "no fragment entry point".to_string()))?;
+    entry_point.name = "fs_main".to_string();
+
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|validation_error| error(label, validation_error.emit_to_string(source)))?;
+    naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+        .map_err(|write_error| error(label, write_error.to_string()))
+}
+
+fn error(label: &str, message: String) -> ShaderError {
+    ShaderError {
+        label: label.to_string(),
+        message,
+    }
+}
+
+impl Nnpipe {
+    /// Append a custom pass written in GLSL (see `src/translate.rs`) and return its
+    /// index, like [`Nnpipe::add_custom_pass`]. If the shader fails to translate or
+    /// compile, the pass runs as a passthrough and the error is queued for
+    /// [`Nnpipe::take_shader_errors`].
+    pub fn add_glsl_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+        params: &[(&str, f32)],
+    ) -> usize {
+        let result = glsl_to_wgsl(label, source);
+        self.add_translated_pass(device, label, result, params)
+    }
+
+    /// Append a custom pass from a SPIR-V binary, like [`Nnpipe::add_glsl_pass`].
+    pub fn add_spirv_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        spirv: &[u8],
+        params: &[(&str, f32)],
+    ) -> usize {
+        let result = spirv_to_wgsl(label, spirv);
+        self.add_translated_pass(device, label, result, params)
+    }
+
+    fn add_translated_pass(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        result: Result<String, ShaderError>,
+        params: &[(&str, f32)],
+    ) -> usize {
+        match result {
+            Ok(source) => self.add_custom_pass(device, label, &source, params),
+            Err(error) => {
+                let index = self.add_custom_pass(device, label, PASSTHROUGH_SOURCE, params);
+                self.queue_shader_error(error);
+                index
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+// src/trigger.rs
+//
+// One-shot triggers
+//
+// A trigger pushes a pass param away from its value and back along an envelope each
+// time it fires, so beat-synced flashes, shockwaves and glitch bursts need no state
+// machine on the host side. Several triggers can share a name and fire together.
+
+/// A param bump fired by name with [`Nnpipe::fire`](crate::Nnpipe::fire).
+///
+/// While the envelope runs, `amount` scaled by it is added to the param's own value,
+/// which is what the pass sees again once the envelope is over.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trigger {
+    /// Index of the pass whose param is driven.
+    pub pass: usize,
+    pub param: String,
+    /// Added to the param at the top of the envelope.
+    pub amount: f32,
+    /// Seconds the envelope takes to rise linearly from 0 to 1.
+    pub attack: f32,
+    /// Seconds it then takes to fall back to 0, easing out.
+    pub decay: f32,
+    /// Params set to the point given to [`Nnpipe::fire_at`](crate::Nnpipe::fire_at),
+    /// x then y.
+    pub position: Option<[String; 2]>,
+}
+
+impl Trigger {
+    /// A trigger adding `amount` to `param` of pass `pass`, with an instant attack
+    /// and a 0.5s decay.
+    pub fn new(pass: usize, param: impl Into<String>, amount: f32) -> Self {
+        Self {
+            pass,
+            param: param.into(),
+            amount,
+            attack: 0.0,
+            decay: 0.5,
+            position: None,
+        }
+    }
+
+    pub fn with_envelope(mut self, attack: f32, decay: f32) -> Self {
+        self.attack = attack;
+        self.decay = decay;
+        self
+    }
+
+    /// Set params `x` and `y` of the pass to the point the trigger is fired at.
+    pub fn with_position(mut self, x: impl Into<String>, y: impl Into<String>) -> Self {
+        self.position = Some([x.into(), y.into()]);
+        self
+    }
+
+    // The envelope `elapsed` seconds after firing, 0 once it's over
+    fn envelope(&self, elapsed: f32) -> f32 {
+        if elapsed < self.attack {
+            elapsed / self.attack
+        } else if elapsed < self.attack + self.decay {
+            let t = 1.0 - (elapsed - self.attack) / self.decay;
+            t * t
+        } else {
+            0.0
+        }
+    }
+}
+
+// A registered trigger and the pipeline time it last fired at
+#[derive(Clone, Debug)]
+pub(crate) struct TriggerState {
+    pub name: String,
+    pub trigger: Trigger,
+    pub fired: Option<f32>,
+}
+
+impl TriggerState {
+    // The amount added to the param at pipeline time `now`
+    pub fn offset(&self, now: f32) -> f32 {
+        self.fired.map_or(0.0, |fired| {
+            let elapsed = (now - fired).max(0.0);
+            self.trigger.amount * self.trigger.envelope(elapsed)
+        })
+    }
+}
@@ -0,0 +1,112 @@
+// src/upload.rs
+//
+// Batched uniform uploads
+//
+// Setters only update a CPU copy of their uniform buffer and mark it dirty. Once per
+// frame, the dirty buffers are copied through a staging belt into the frame's own
+// command encoder, so animating dozens of params costs one batch of copies rather
+// than a queue write per setter call. Repeated sets of a param within a frame upload
+// only the last value, and sets that leave the copy as it was upload nothing, so
+// uniforms refreshed every frame cost nothing while they hold still.
+
+use std::num::NonZeroU64;
+use std::ops::Deref;
+use std::sync::Mutex;
+
+use nannou::wgpu;
+use nannou::wgpu::util::DeviceExt;
+
+// Staging chunk size; all of a frame's uniforms usually fit in one
+const CHUNK_SIZE: wgpu::BufferAddress = 4096;
+
+// A uniform buffer written through its CPU copy
+#[derive(Debug)]
+pub(crate) struct UniformBuffer {
+    buffer: wgpu::Buffer,
+    shadow: Mutex<Shadow>,
+}
+
+#[derive(Debug)]
+struct Shadow {
+    data: Vec<u8>,
+    dirty: bool,
+}
+
+impl UniformBuffer {
+    // A buffer created holding `contents`, whose size must be a multiple of 4
+    pub fn new(device: &wgpu::Device, label: &str, contents: &[u8]) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            buffer,
+            shadow: Mutex::new(Shadow {
+                data: contents.to_vec(),
+                dirty: false,
+            }),
+        }
+    }
+
+    // Write `data` at byte `offset`, uploaded with the next frame if it changed
+    pub fn write(&self, offset: usize, data: &[u8]) {
+        let mut shadow = self.shadow.lock().unwrap();
+        let range = offset..offset + data.len();
+        if shadow.data[range.clone()] != *data {
+            shadow.data[range].copy_from_slice(data);
+            shadow.dirty = true;
+        }
+    }
+}
+
+impl Deref for UniformBuffer {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+}
+
+// Copies dirty uniform buffers into a frame's encoder
+#[derive(Debug)]
+pub(crate) struct Uploader {
+    belt: Mutex<wgpu::util::StagingBelt>,
+}
+
+impl Uploader {
+    pub fn new() -> Self {
+        Self {
+            belt: Mutex::new(wgpu::util::StagingBelt::new(CHUNK_SIZE)),
+        }
+    }
+
+    // Record copies of the dirty `buffers` into `encoder` and close the belt for the
+    // frame. Must be recorded before the passes that read the buffers.
+    pub fn upload<'a>(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        buffers: impl IntoIterator<Item = &'a UniformBuffer>,
+    ) {
+        let mut belt = self.belt.lock().unwrap();
+        for uniform in buffers {
+            let mut shadow = uniform.shadow.lock().unwrap();
+            if !shadow.dirty {
+                continue;
+            }
+            shadow.dirty = false;
+            let Some(size) = NonZeroU64::new(shadow.data.len() as u64) else {
+                continue;
+            };
+            belt.write_buffer(encoder, &uniform.buffer, 0, size, device)
+                .copy_from_slice(&shadow.data);
+        }
+        belt.finish();
+    }
+
+    // Reclaim the staging chunks of submitted frames. Call after submitting.
+    pub fn recall(&self) {
+        self.belt.lock().unwrap().recall();
+    }
+}
@@ -0,0 +1,152 @@
+// src/warp.rs
+//
+// Projection mapping warps
+//
+// A projector that isn't square on to its surface throws a keystoned image, and
+// curved or uneven surfaces bend it further. An output's warp pins the frame's
+// corners to a quadrilateral of the target and bends it with a coarse mesh of control
+// points on top, so the frame lands square on the surface. The output pass draws the
+// frame as a tessellated quad whose vertices are moved by the warp; the corner pin is
+// a homography, interpolated exactly by giving the vertices its w, so a warp without
+// a mesh needs only two triangles.
+
+/// Most control points of a [`WarpMesh`] along either side.
+pub const MAX_MESH_SIZE: u32 = 17;
+
+/// Keystone and mesh warp of an output, see
+/// [`Output::set_warp`](crate::Output::set_warp).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default, deny_unknown_fields))]
+pub struct OutputWarp {
+    /// Where the frame's top left, top right, bottom right and bottom left corners
+    /// land, in fractions of the target from its top left.
+    pub corners: [[f32; 2]; 4],
+    /// Control points bending the frame within its corners.
+    #[cfg_attr(feature = "config", serde(skip_serializing_if = "Option::is_none"))]
+    pub mesh: Option<WarpMesh>,
+}
+
+impl Default for OutputWarp {
+    fn default() -> Self {
+        Self {
+            corners: [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+            mesh: None,
+        }
+    }
+}
+
+impl OutputWarp {
+    /// A warp pinning the frame's corners, in the order of [`OutputWarp::corners`].
+    pub fn corner_pin(corners: [[f32; 2]; 4]) -> Self {
+        Self {
+            corners,
+            mesh: None,
+        }
+    }
+
+    /// Whether the warp leaves the frame as it is.
+    pub fn is_identity(&self) -> bool {
+        let mesh_even = self.mesh.as_ref().is_none_or(WarpMesh::is_even);
+        self.corners == Self::default().corners && mesh_even
+    }
+
+    // The corner pin as the rows of a homography from the unit square to the target's
+    // unit square, or None if three corners are in line
+    pub(crate) fn homography(&self) -> Option<[[f32; 3]; 3]> {
+        let [[x0, y0], [x1, y1], [x2, y2], [x3, y3]] = self.corners;
+        let (dx1, dy1) = (x1 - x2, y1 - y2);
+        let (dx2, dy2) = (x3 - x2, y3 - y2);
+        let (dx3, dy3) = (x0 - x1 + x2 - x3, y0 - y1 + y2 - y3);
+        let (g, h) = if dx3 == 0.0 && dy3 == 0.0 {
+            // A parallelogram, mapped affinely
+            (0.0, 0.0)
+        } else {
+            let det = dx1 * dy2 - dx2 * dy1;
+            if det.abs() < f32::EPSILON {
+                return None;
+            }
+            ((dx3 * dy2 - dx2 * dy3) / det, (dx1 * dy3 - dx3 * dy1) / det)
+        };
+        Some([
+            [x1 - x0 + g * x1, x3 - x0 + h * x3, x0],
+            [y1 - y0 + g * y1, y3 - y0 + h * y3, y0],
+            [g, h, 1.0],
+        ])
+    }
+
+    // The mesh if it's usable: of a supported size, with a point for every place
+    pub(crate) fn valid_mesh(&self) -> Option<&WarpMesh> {
+        self.mesh.as_ref().filter(|mesh| {
+            let sizes = 2..=MAX_MESH_SIZE;
+            sizes.contains(&mesh.columns)
+                && sizes.contains(&mesh.rows)
+                && mesh.points.len() == (mesh.columns * mesh.rows) as usize
+        })
+    }
+}
+
+/// Grid of control points of an [`OutputWarp`], in rows from the top left. Each
+/// point is where its place on an even grid over the frame moves to, in fractions of
+/// the corner-pinned frame; the frame is bent smoothly between them.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(deny_unknown_fields))]
+pub struct WarpMesh {
+    pub columns: u32,
+    pub rows: u32,
+    pub points: Vec<[f32; 2]>,
+}
+
+impl WarpMesh {
+    /// An even grid of `columns` by `rows` points, from 2 to [`MAX_MESH_SIZE`] each,
+    /// which leaves the frame as it is until points are moved.
+    pub fn new(columns: u32, rows: u32) -> Self {
+        let columns = columns.clamp(2, MAX_MESH_SIZE);
+        let rows = rows.clamp(2, MAX_MESH_SIZE);
+        let points = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| even_point(column, row, columns, rows)))
+            .collect();
+        Self {
+            columns,
+            rows,
+            points,
+        }
+    }
+
+    pub fn point(&self, column: u32, row: u32) -> Option<[f32; 2]> {
+        self.index(column, row).map(|index| self.points[index])
+    }
+
+    /// Move the point at `column`, `row`. Returns whether the mesh has it.
+    pub fn set_point(&mut self, column: u32, row: u32, point: [f32; 2]) -> bool {
+        match self.index(column, row) {
+            Some(index) => {
+                self.points[index] = point;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index(&self, column: u32, row: u32) -> Option<usize> {
+        let index = (row * self.columns + column) as usize;
+        (column < self.columns && row < self.rows && index < self.points.len()).then_some(index)
+    }
+
+    fn is_even(&self) -> bool {
+        self.points.len() == (self.columns * self.rows) as usize
+            && self.points.iter().enumerate().all(|(index, point)| {
+                let (column, row) = (index as u32 % self.columns, index as u32 / self.columns);
+                *point == even_point(column, row, self.columns, self.rows)
+            })
+    }
+}
+
+// Where the point at `column`, `row` sits on an even grid
+fn even_point(column: u32, row: u32, columns: u32, rows: u32) -> [f32; 2] {
+    [
+        column as f32 / (columns - 1) as f32,
+        row as f32 / (rows - 1) as f32,
+    ]
+}
@@ -0,0 +1,208 @@
+// src/zebra.rs
+//
+// Zebra and clipping indicator
+//
+// A blown-out highlight is easy to miss on a bright frame, and a shader that divides
+// by zero leaves NaN or infinite pixels that later passes smear or blacken without a
+// trace. The zebra overlay stripes every pixel of the chain's output over a
+// luminance and paints non-finite ones a solid color. Like the magnifier, it has the
+// chain render into a texture of its own, which one pass then copies to the target
+// with the marks on top.
+
+use nannou::wgpu;
+use std::sync::Arc;
+
+use crate::cache::PipelineCache;
+use crate::preprocess::expand;
+use crate::upload::UniformBuffer;
+
+/// Settings of the zebra overlay, see [`Nnpipe::set_zebra`](crate::Nnpipe::set_zebra).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Zebra {
+    /// Linear luminance above which pixels are striped. The composite tone maps the
+    /// frame below 1, so pixels only get there through effect passes or a very bright
+    /// scene.
+    pub threshold: f32,
+    /// Width of the stripes and the gaps between them, in pixels.
+    pub stripe_width: f32,
+    /// Straight RGBA color of the stripes, blended over the frame by its alpha.
+    pub color: [f32; 4],
+    /// Color NaN and infinite pixels are painted, or `None` to leave them.
+    pub non_finite_color: Option<[f32; 3]>,
+}
+
+impl Default for Zebra {
+    fn default() -> Self {
+        Self {
+            threshold: 0.9,
+            stripe_width: 4.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+            non_finite_color: Some([1.0, 0.0, 1.0]),
+        }
+    }
+}
+
+impl Zebra {
+    // The settings as the shader's uniform
+    fn uniform(&self) -> [f32; 12] {
+        let [r, g, b, a] = self.color;
+        let ([nr, ng, nb], mark) = match self.non_finite_color {
+            Some(color) => (color, 1.0),
+            None => ([0.0; 3], 0.0),
+        };
+        [
+            r,
+            g,
+            b,
+            a,
+            nr,
+            ng,
+            nb,
+            mark,
+            self.threshold,
+            self.stripe_width.max(1.0),
+            0.0,
+            0.0,
+        ]
+    }
+}
+
+pub(crate) struct ZebraLayer {
+    settings: Zebra,
+    // The chain's output, at the pipeline's size
+    source_view: wgpu::TextureView,
+    uniform_buffer: UniformBuffer,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ZebraLayer {
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &PipelineCache,
+        size: [u32; 2],
+        settings: Zebra,
+    ) -> Self {
+        let source_view = wgpu::TextureBuilder::new()
+            .size(size)
+            .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+            .format(wgpu::TextureFormat::Rgba16Float)
+            .build(device)
+            .view()
+            .build();
+
+        let uniform_buffer = UniformBuffer::new(
+            device,
+            "Zebra Uniform Buffer",
+            bytemuck::cast_slice(&settings.uniform()),
+        );
+
+        let bind_group_layout = cache.bind_group_layout(
+            device,
+            "Zebra Bind Group Layout",
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        );
+        let pipeline_layout =
+            cache.pipeline_layout(device, "Zebra Pipeline Layout", &bind_group_layout);
+
+        let shader = cache.shader(
+            device,
+            "Zebra Shader",
+            &expand(include_str!("shaders/zebra.wgsl")),
+        );
+        let pipeline = cache.pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            &shader,
+            "Zebra Pipeline",
+            wgpu::TextureFormat::Rgba16Float,
+            None,
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Zebra Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Self {
+            settings,
+            source_view,
+            uniform_buffer,
+            pipeline,
+            bind_group,
+        }
+    }
+
+    pub fn settings(&self) -> Zebra {
+        self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Zebra) {
+        self.settings = settings;
+        self.uniform_buffer
+            .write(0, bytemuck::cast_slice(&settings.uniform()));
+    }
+
+    pub fn uniform_buffer(&self) -> &UniformBuffer {
+        &self.uniform_buffer
+    }
+
+    // Where the chain renders while the overlay is on
+    pub fn source_view(&self) -> &wgpu::TextureView {
+        &self.source_view
+    }
+
+    // Copy the chain's output to `target`, which is the pipeline's size, with the
+    // stripes and non-finite pixels marked
+    #[cfg_attr(feature = "tracing", tracing::instrument(name = "zebra", skip_all))]
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Zebra pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1); // Draw a fullscreen triangle
+    }
+}
@@ -0,0 +1,137 @@
+// tests/accumulate.rs
+//
+// Tests of the long-exposure accumulation and its EXR export
+
+#![cfg(feature = "temporal")]
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{encode_exr, Accumulation, AccumulationMode, Nnpipe, NnpipeError};
+
+const SIZE: u32 = 8;
+
+// A texture filled with `color`, as half floats
+fn fill(device: &wgpu::Device, queue: &wgpu::Queue, color: [u16; 4]) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([SIZE, SIZE])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba16Float)
+        .build(device);
+    let data = vec![color; (SIZE * SIZE) as usize];
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(SIZE * 8),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+fn assert_near(pixel: [f32; 4], expected: [f32; 4]) {
+    for (value, expected) in pixel.iter().zip(expected) {
+        assert!((value - expected).abs() < 1e-2, "{pixel:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn frames_are_averaged_or_max_blended() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping accumulation test: no adapter");
+        return;
+    };
+    // Opaque red and green, tone mapped to halves
+    let red = fill(&device, &queue, [0x3c00, 0, 0, 0x3c00]);
+    let green = fill(&device, &queue, [0, 0x3c00, 0, 0x3c00]);
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    assert_eq!(pipeline.accumulated_frames(), None);
+    let plain = golden::render(&pipeline, &device, &queue, &green).unwrap();
+
+    pipeline.set_accumulation(&device, Some(Accumulation::default()));
+    assert_eq!(pipeline.accumulation(), Some(Accumulation::default()));
+    golden::render(&pipeline, &device, &queue, &red).unwrap();
+    let frame = golden::render(&pipeline, &device, &queue, &green).unwrap();
+    assert_eq!(pipeline.accumulated_frames(), Some(2));
+    assert_near(frame[0], [0.25, 0.25, 0.0, 1.0]);
+    let accumulation = futures::executor::block_on(pipeline.read_accumulation(&device, &queue))
+        .unwrap()
+        .unwrap();
+    assert_eq!(accumulation.len(), (SIZE * SIZE) as usize);
+    assert_near(accumulation[0], [0.25, 0.25, 0.0, 1.0]);
+
+    // Hidden, the live frame shows while the accumulation carries on
+    pipeline.set_accumulation(
+        &device,
+        Some(Accumulation {
+            show: false,
+            ..Default::default()
+        }),
+    );
+    let frame = golden::render(&pipeline, &device, &queue, &green).unwrap();
+    assert_eq!(frame, plain);
+    assert_eq!(pipeline.accumulated_frames(), Some(3));
+
+    // Starting over, the first frame is the accumulation
+    pipeline.reset_accumulation();
+    assert_eq!(pipeline.accumulated_frames(), Some(0));
+    pipeline.set_accumulation(
+        &device,
+        Some(Accumulation {
+            mode: AccumulationMode::Max,
+            show: true,
+        }),
+    );
+    let frame = golden::render(&pipeline, &device, &queue, &red).unwrap();
+    assert_near(frame[0], [0.5, 0.0, 0.0, 1.0]);
+    let frame = golden::render(&pipeline, &device, &queue, &green).unwrap();
+    assert_near(frame[0], [0.5, 0.5, 0.0, 1.0]);
+
+    // A rebuild starts over too
+    pipeline.resize(&device, &queue, SIZE, SIZE).unwrap();
+    assert_eq!(pipeline.accumulated_frames(), Some(0));
+
+    pipeline.set_accumulation(&device, None);
+    assert_eq!(
+        golden::render(&pipeline, &device, &queue, &green).unwrap(),
+        plain
+    );
+}
+
+#[test]
+fn accumulation_is_saved_as_exr() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping accumulation test: no adapter");
+        return;
+    };
+    let red = fill(&device, &queue, [0x3c00, 0, 0, 0x3c00]);
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    let path = std::env::temp_dir().join("nnpipe-accumulation-test.exr");
+    let save = |pipeline: &Nnpipe| {
+        futures::executor::block_on(pipeline.save_accumulation_exr(&device, &queue, &path)).unwrap()
+    };
+    assert!(!save(&pipeline));
+
+    pipeline.set_accumulation(&device, Some(Accumulation::default()));
+    golden::render(&pipeline, &device, &queue, &red).unwrap();
+    assert!(save(&pipeline));
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let accumulation = futures::executor::block_on(pipeline.read_accumulation(&device, &queue))
+        .unwrap()
+        .unwrap();
+    assert_eq!(bytes, encode_exr(SIZE, SIZE, &accumulation).unwrap());
+
+    assert_eq!(bytes[..4], [0x76, 0x2f, 0x31, 0x01]);
+    // Each scanline ends with the red channel of its last pixel
+    let last = f32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+    assert_eq!(last, accumulation[accumulation.len() - 1][0]);
+    assert!(last > 0.4);
+
+    // Pixels that don't fill the size are refused
+    let result = encode_exr(SIZE + 1, SIZE, &accumulation);
+    assert!(matches!(result, Err(NnpipeError::PixelCount { .. })));
+}
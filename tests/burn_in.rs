@@ -0,0 +1,66 @@
+// tests/burn_in.rs
+//
+// Burn-in overlay tests
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{BurnIn, BurnInCorner, Nnpipe};
+
+#[test]
+fn burn_in_draws_into_its_corner() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping burn-in test: no adapter");
+        return;
+    };
+    let (width, height) = (128, 64);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let burn_in = BurnIn {
+        corner: BurnInCorner::BottomRight,
+        scale: 1,
+        ..BurnIn::default()
+    }
+    .with_param("bloom.intensity");
+    pipeline.set_burn_in(&device, &queue, Some(burn_in.clone()));
+    assert_eq!(pipeline.burn_in(), Some(&burn_in));
+    let burnt = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Three lines of up to 21 characters: the box is 87 x 23 pixels
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32| pixels[(y * width + x) as usize];
+    let (corner, plain_corner) = (
+        pixel(&burnt, width - 1, height - 1),
+        pixel(&plain, width - 1, height - 1),
+    );
+    assert!((corner[0] - plain_corner[0] * 0.4).abs() < 0.01);
+    let inked = (width - 87..width)
+        .flat_map(|x| (height - 23..height).map(move |y| (x, y)))
+        .filter(|&(x, y)| pixel(&burnt, x, y)[..3] == [1.0; 3])
+        .count();
+    assert!(inked > 50);
+    for x in 0..width - 87 {
+        for y in 0..height {
+            assert_eq!(pixel(&burnt, x, y), pixel(&plain, x, y));
+        }
+    }
+
+    // The frame number keeps counting through resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.burn_in(), Some(&burn_in));
+    let resized = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_ne!(resized, burnt);
+
+    // Nothing is drawn while bypassed
+    pipeline.set_bypass(true);
+    let bypassed = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    pipeline.set_bypass(false);
+
+    pipeline.set_burn_in(&device, &queue, None);
+    assert_eq!(pipeline.burn_in(), None);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 0.0).unwrap();
+    pipeline.set_bypass(true);
+    let plain_bypassed = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain_bypassed, &bypassed, width, 0.0).unwrap();
+}
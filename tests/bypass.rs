@@ -0,0 +1,52 @@
+// tests/bypass.rs
+//
+// Bypassing the whole chain
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::Nnpipe;
+
+const INVERT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let src = textureLoad(src_tex, vec2<i32>(position.xy), 0);
+    return vec4<f32>(1.0 - src.rgb, src.a);
+}
+";
+
+#[test]
+fn bypass_copies_the_scene() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping bypass test: no adapter");
+        return;
+    };
+    let (width, height) = (32, 24);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(2.0);
+    pipeline.add_custom_pass(&device, "Invert", INVERT, &[]);
+    pipeline.set_bypass(true);
+    assert!(pipeline.bypass());
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let scene = TestPattern::Gradient.pixels(width, height);
+    golden::compare(&scene, &output, width, 1.0 / 255.0).unwrap();
+
+    // Settings changed while bypassed apply afterwards, and resizing keeps the bypass
+    pipeline.set_bloom_intensity(0.0);
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert!(pipeline.bypass());
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&scene, &output, width, 1.0 / 255.0).unwrap();
+
+    pipeline.set_bypass(false);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let mut plain = Nnpipe::new(&device, width, height, 1).unwrap();
+    plain.set_bloom_intensity(0.0);
+    let inverted: Vec<[f32; 4]> = golden::render(&plain, &device, &queue, &input)
+        .unwrap()
+        .iter()
+        .map(|[r, g, b, a]| [1.0 - r, 1.0 - g, 1.0 - b, *a])
+        .collect();
+    golden::compare(&inverted, &output, width, 2.0 / 255.0).unwrap();
+}
@@ -0,0 +1,116 @@
+// tests/capture.rs
+//
+// Non-blocking frame capture tests
+
+use std::time::{Duration, Instant};
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Frame, FrameCapture, Nnpipe, Shutter};
+
+// Poll until a frame arrives, as a render loop would across frames
+fn wait_for_frame(capture: &mut FrameCapture, device: &nannou::wgpu::Device) -> Frame {
+    let start = Instant::now();
+    loop {
+        if let Some(frame) = capture.poll_ready(device) {
+            return frame.unwrap();
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "no frame arrived"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn captures_match_readback_in_order() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping capture test: no adapter");
+        return;
+    };
+    let pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    let mut capture = FrameCapture::new(3);
+
+    let patterns = [
+        TestPattern::Gradient,
+        TestPattern::Checkerboard { cell: 4 },
+        TestPattern::Dots,
+    ];
+    let mut expected = Vec::new();
+    for pattern in patterns {
+        let input = pattern.create_texture(&device, &queue, 64, 48);
+        expected.push(golden::render(&pipeline, &device, &queue, &input).unwrap());
+        assert!(capture.capture(&pipeline, &device, &queue));
+    }
+
+    // The pool is full until a frame is collected
+    assert!(!capture.capture(&pipeline, &device, &queue));
+    assert_eq!(capture.dropped(), 1);
+
+    for (index, expected) in expected.iter().enumerate() {
+        let frame = wait_for_frame(&mut capture, &device);
+        assert_eq!(frame.index, index as u64);
+        assert_eq!([frame.width, frame.height], [64, 48]);
+        assert_eq!(&frame.pixels, expected);
+    }
+    assert_eq!(capture.pending(), 0);
+}
+
+#[test]
+fn shutter_blends_the_frames_it_was_open_for() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping capture test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    let mut capture = FrameCapture::new(4);
+    let shutter = Shutter {
+        fps: 10.0,
+        angle: 180.0,
+    };
+    capture.set_shutter(Some(shutter));
+    assert_eq!(capture.shutter(), Some(shutter));
+
+    // Two frames with the first recorded frame's shutter open, one with it closed,
+    // and one of the next recorded frame
+    let patterns = [
+        (0.0, TestPattern::Gradient),
+        (0.02, TestPattern::Checkerboard { cell: 4 }),
+        (0.06, TestPattern::Dots),
+        (0.11, TestPattern::Dots),
+    ];
+    let mut rendered = Vec::new();
+    for (time, pattern) in patterns {
+        pipeline.set_time(Some(time));
+        let input = pattern.create_texture(&device, &queue, 64, 48);
+        rendered.push(golden::render(&pipeline, &device, &queue, &input).unwrap());
+        assert_eq!(capture.capture(&pipeline, &device, &queue), time != 0.06);
+    }
+
+    // The first is handed out once its shutter closed, as the average of its frames
+    let frame = wait_for_frame(&mut capture, &device);
+    assert_eq!((frame.index, frame.time), (0, 0.0));
+    let average: Vec<[f32; 4]> = rendered[0]
+        .iter()
+        .zip(&rendered[1])
+        .map(|(a, b)| std::array::from_fn(|i| (a[i] + b[i]) * 0.5))
+        .collect();
+    assert_eq!(frame.pixels, average);
+    assert_ne!(frame.pixels, rendered[0]);
+
+    // The last waits for its shutter, or the end of the recording
+    let start = Instant::now();
+    while capture.pending() > 0 {
+        assert!(capture.poll_ready(&device).is_none());
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "no frame arrived"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(capture.poll_ready(&device).is_none());
+    let frame = capture.finish().unwrap();
+    assert_eq!(frame.index, 1);
+    assert_eq!(frame.pixels, rendered[3]);
+    assert!(capture.finish().is_none());
+}
@@ -0,0 +1,90 @@
+// tests/color_vision.rs
+//
+// Tests of the color vision simulation and daltonization pass
+
+#![cfg(feature = "grading")]
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{ColorDeficiency, ColorVision, Nnpipe};
+
+const WIDTH: u32 = 12;
+const HEIGHT: u32 = 4;
+
+// Grey, red and green swatches side by side
+fn swatches(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([WIDTH, HEIGHT])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba8Unorm)
+        .build(device);
+    let colors: [[u8; 4]; 3] = [[128, 128, 128, 255], [200, 60, 40, 255], [60, 160, 40, 255]];
+    let data: Vec<[u8; 4]> = (0..WIDTH * HEIGHT)
+        .map(|i| colors[(i % WIDTH / 4) as usize])
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(WIDTH * 4),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+// A pixel of each swatch
+fn swatch_colors(frame: &[[f32; 4]]) -> [[f32; 4]; 3] {
+    [0, 1, 2].map(|swatch| frame[(WIDTH + 4 * swatch + 1) as usize])
+}
+
+fn distance(a: [f32; 4], b: [f32; 4]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f32>().sqrt()
+}
+
+#[test]
+fn daltonizing_keeps_red_and_green_apart() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping color vision test: no adapter");
+        return;
+    };
+    let input = swatches(&device, &queue);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = swatch_colors(&golden::render(&pipeline, &device, &queue, &input).unwrap());
+
+    let simulation = ColorVision {
+        deficiency: ColorDeficiency::Deuteranopia,
+        ..Default::default()
+    };
+    let index = pipeline.add_color_vision_pass(&device, &simulation);
+    let seen = swatch_colors(&golden::render(&pipeline, &device, &queue, &input).unwrap());
+    // Greys look the same, red and green much closer
+    assert!(distance(seen[0], plain[0]) < 1e-2);
+    assert!(distance(seen[1], seen[2]) < 0.5 * distance(plain[1], plain[2]));
+
+    // Seen through the simulation, daltonized red and green are further apart
+    let correction = ColorVision {
+        daltonize: true,
+        ..simulation
+    };
+    pipeline.set_color_vision(index, &correction);
+    pipeline.add_color_vision_pass(&device, &simulation);
+    let corrected = swatch_colors(&golden::render(&pipeline, &device, &queue, &input).unwrap());
+    assert!(distance(corrected[0], plain[0]) < 1e-2);
+    assert!(distance(corrected[1], corrected[2]) > distance(seen[1], seen[2]));
+
+    // At no severity the pass changes nothing
+    pipeline.set_color_vision(
+        index,
+        &ColorVision {
+            severity: 0.0,
+            ..simulation
+        },
+    );
+    pipeline.custom_pass_mut(index + 1).unwrap().enabled = false;
+    let unchanged = swatch_colors(&golden::render(&pipeline, &device, &queue, &input).unwrap());
+    golden::compare(&plain, &unchanged, 3, 1e-3).unwrap();
+}
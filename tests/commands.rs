@@ -0,0 +1,125 @@
+// tests/commands.rs
+//
+// Deferred pipeline commands
+
+use nnpipe::golden;
+use nnpipe::{MacroTarget, Nnpipe, PipelineCommand, Trigger, MAX_COMMAND_ERRORS};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+#[test]
+fn commands_run_in_order_on_update() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping commands test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    let first = pipeline.add_custom_pass(&device, "First", TINT, &[("amount", 1.0)]);
+    let second = pipeline.add_custom_pass(&device, "Second", TINT, &[("amount", 0.5)]);
+    pipeline.add_trigger("beat", Trigger::new(second, "amount", 1.0));
+    pipeline.add_macro("fade", vec![MacroTarget::new("passes.1.amount", 0.0, 1.0)]);
+
+    let commands = pipeline.command_queue();
+    let worker = commands.clone();
+    std::thread::spawn(move || {
+        worker.push(PipelineCommand::SetParam {
+            path: "passes.0.amount".to_string(),
+            value: 0.25,
+        });
+        worker.push(PipelineCommand::TogglePass {
+            pass: first,
+            enabled: false,
+        });
+        worker.push(PipelineCommand::ReorderChain(vec![second, first]));
+        // After the reorder, index 0 is the second pass
+        worker.push(PipelineCommand::SetParam {
+            path: "passes.0.amount".to_string(),
+            value: 0.75,
+        });
+        worker.push(PipelineCommand::TriggerEvent("beat".to_string()));
+        worker.push(PipelineCommand::ReorderChain(vec![0, 0]));
+        worker.push(PipelineCommand::TogglePass {
+            pass: 5,
+            enabled: true,
+        });
+        worker.push(PipelineCommand::TriggerEvent("missing".to_string()));
+    })
+    .join()
+    .unwrap();
+
+    assert!(commands.is_pending());
+    assert_eq!(pipeline.custom_pass(first).unwrap().label, "First");
+    pipeline.update(&device, &queue, 0.0);
+    assert!(!commands.is_pending());
+
+    let pass = pipeline.custom_pass(0).unwrap();
+    assert_eq!(pass.label, "Second");
+    assert_eq!(pass.param("amount"), Some(0.75));
+    let pass = pipeline.custom_pass(1).unwrap();
+    assert_eq!(pass.label, "First");
+    assert_eq!(pass.param("amount"), Some(0.25));
+    assert!(!pass.enabled);
+
+    // Triggers and macros follow their passes
+    assert_eq!(pipeline.triggers("beat").next().unwrap().pass, 0);
+    assert_eq!(
+        pipeline.macro_targets("fade").unwrap()[0].path,
+        "passes.0.amount"
+    );
+
+    let errors = pipeline.take_command_errors();
+    assert_eq!(errors.len(), 3);
+    assert_eq!(errors[0].command, PipelineCommand::ReorderChain(vec![0, 0]));
+    assert!(errors[1].message.contains("pass 5"));
+    assert!(errors[2].message.contains("'missing'"));
+    assert!(pipeline.take_command_errors().is_empty());
+
+    // Errors nobody drains keep only the latest
+    for value in 0..MAX_COMMAND_ERRORS + 10 {
+        commands.push(PipelineCommand::SetParam {
+            path: "bloom.missing".to_string(),
+            value: value as f32,
+        });
+    }
+    pipeline.update(&device, &queue, 0.0);
+    let errors = pipeline.take_command_errors();
+    assert_eq!(errors.len(), MAX_COMMAND_ERRORS);
+    let values = [&errors[0], &errors[MAX_COMMAND_ERRORS - 1]].map(|error| match error.command {
+        PipelineCommand::SetParam { value, .. } => value,
+        _ => unreachable!(),
+    });
+    assert_eq!(values, [10.0, (MAX_COMMAND_ERRORS + 9) as f32]);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn presets_load_through_commands() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping commands test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.enable_history(4);
+
+    let mut preset = nnpipe::PipelineConfig::default();
+    preset.bloom.intensity = Some(2.5);
+    pipeline
+        .command_queue()
+        .push(PipelineCommand::LoadPreset(Box::new(preset)));
+    pipeline.update(&device, &queue, 0.0);
+    assert_eq!(pipeline.bloom_intensity, 2.5);
+    assert!(pipeline.can_undo());
+}
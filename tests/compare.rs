@@ -0,0 +1,159 @@
+// tests/compare.rs
+//
+// Preset comparison tests
+
+#![cfg(feature = "config")]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{BloomConfig, Nnpipe, NnpipeError, PassConfig, PipelineConfig, PresetComparison};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+fn intensity(intensity: f32) -> PipelineConfig {
+    PipelineConfig {
+        bloom: BloomConfig {
+            intensity: Some(intensity),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+// Pixels of tile `index` in a comparison two tiles wide
+fn tile(pixels: &[[f32; 4]], index: usize) -> Vec<[f32; 4]> {
+    let (column, row) = (index % 2, index / 2);
+    let width = WIDTH as usize;
+    let height = HEIGHT as usize;
+    (0..height)
+        .flat_map(|y| {
+            let start = (row * height + y) * width * 2 + column * width;
+            pixels[start..start + width].iter().copied()
+        })
+        .collect()
+}
+
+#[test]
+fn tiles_match_separate_renders() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping comparison test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_brightness_threshold(0.5);
+    let input = TestPattern::Dots.create_texture(&device, &queue, WIDTH, HEIGHT);
+    let presets = [intensity(0.0), intensity(2.0), intensity(4.0)];
+
+    let mut expected = Vec::new();
+    for preset in &presets {
+        pipeline.apply_config(preset).unwrap();
+        expected.push(golden::render(&pipeline, &device, &queue, &input).unwrap());
+    }
+    pipeline.set_bloom_intensity(1.0);
+
+    let comparison = PresetComparison::new(&device, WIDTH, HEIGHT, 3).unwrap();
+    assert_eq!(comparison.size(), [WIDTH * 2, HEIGHT * 2]);
+    let input_view = input.view().build();
+    comparison
+        .render(&mut pipeline, &device, &queue, &input_view, &presets)
+        .unwrap();
+    let pixels = futures::executor::block_on(comparison.read_output(&device, &queue)).unwrap();
+
+    for (index, expected) in expected.iter().enumerate() {
+        golden::compare(expected, &tile(&pixels, index), WIDTH, 1.0 / 255.0).unwrap();
+    }
+    // The fourth tile has no preset
+    assert!(tile(&pixels, 3).iter().all(|pixel| pixel[..3] == [0.0; 3]));
+    // The pipeline's own settings are back
+    assert_eq!(pipeline.bloom_intensity, 1.0);
+}
+
+#[test]
+fn thumbnails_follow_presets() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping thumbnail test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_brightness_threshold(0.5);
+    let input = TestPattern::Dots.create_texture(&device, &queue, WIDTH, HEIGHT);
+    let input_view = input.view().build();
+    let config = pipeline.config();
+
+    let presets = [intensity(0.0), intensity(4.0)];
+    let thumbnails = futures::executor::block_on(pipeline.preset_thumbnails(
+        &device,
+        &queue,
+        &input_view,
+        &presets,
+        32,
+        24,
+    ))
+    .unwrap();
+    assert_eq!(thumbnails.len(), 2);
+    assert!(thumbnails.iter().all(|t| t.dimensions() == (32, 24)));
+    let brightness = |index: usize| -> u64 {
+        thumbnails[index]
+            .pixels()
+            .map(|pixel| pixel.0[..3].iter().map(|&c| c as u64).sum::<u64>())
+            .sum()
+    };
+    assert!(brightness(1) > brightness(0));
+    assert_eq!(pipeline.config(), config);
+    assert_eq!(pipeline.fixed_time(), None);
+
+    // A preset for a pass the pipeline doesn't have
+    let missing = PipelineConfig {
+        passes: vec![PassConfig {
+            label: Some("Missing".to_string()),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let result = futures::executor::block_on(pipeline.preset_thumbnails(
+        &device,
+        &queue,
+        &input_view,
+        &[missing],
+        32,
+        24,
+    ));
+    assert!(matches!(result, Err(NnpipeError::Config(_))));
+}
+
+#[test]
+fn thumbnails_are_srgb() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping thumbnail test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, WIDTH, HEIGHT);
+    let input_view = input.view().build();
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let thumbnails = futures::executor::block_on(pipeline.preset_thumbnails(
+        &device,
+        &queue,
+        &input_view,
+        &[intensity(0.0)],
+        WIDTH,
+        HEIGHT,
+    ))
+    .unwrap();
+    for (pixel, expected) in thumbnails[0].pixels().zip(&output) {
+        for channel in 0..3 {
+            let srgb = match expected[channel].clamp(0.0, 1.0) {
+                c if c <= 0.0031308 => c * 12.92,
+                c => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+            };
+            let difference = pixel.0[channel] as f32 - (srgb * 255.0).round();
+            assert!(difference.abs() <= 1.0, "{pixel:?} for {expected:?}");
+        }
+        assert_eq!(
+            pixel.0[3],
+            (expected[3].clamp(0.0, 1.0) * 255.0).round() as u8
+        );
+    }
+}
@@ -0,0 +1,110 @@
+// tests/compute.rs
+//
+// Compute pass tests
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::Nnpipe;
+
+const INVERT_COMPUTE: &str = "
+struct Params {
+    amount: f32, // default: 1.0
+}
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(7) var dst_tex: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(dst_tex)) {
+        return;
+    }
+    let src = textureLoad(src_tex, id.xy, 0);
+    textureStore(dst_tex, id.xy, vec4<f32>(mix(src.rgb, 1.0 - src.rgb, params.amount), src.a));
+}
+";
+
+const INVERT_FRAGMENT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let src = textureLoad(src_tex, vec2<i32>(position.xy), 0);
+    return vec4<f32>(1.0 - src.rgb, src.a);
+}
+";
+
+#[test]
+fn compute_passes_run_in_the_chain() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping compute test: no adapter");
+        return;
+    };
+    // Not a multiple of the workgroup size
+    let (width, height) = (30, 20);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let inverted: Vec<[f32; 4]> = plain
+        .iter()
+        .map(|[r, g, b, a]| [1.0 - r, 1.0 - g, 1.0 - b, *a])
+        .collect();
+
+    // Ending the chain, copied to the output
+    let compute = pipeline.add_reflected_compute_pass(&device, "Invert", INVERT_COMPUTE);
+    assert!(pipeline.take_shader_errors().is_empty());
+    assert!(pipeline.custom_pass(compute).unwrap().is_compute());
+    assert_eq!(
+        pipeline.custom_pass(compute).unwrap().params(),
+        [("amount".to_string(), 1.0)]
+    );
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&inverted, &output, width, 1.0 / 255.0).unwrap();
+
+    // Followed by a fragment pass
+    let fragment = pipeline.add_custom_pass(&device, "Invert Again", INVERT_FRAGMENT, &[]);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 1.0 / 255.0).unwrap();
+
+    // With its params
+    pipeline.custom_pass_mut(fragment).unwrap().enabled = false;
+    assert!(pipeline.set_param(&format!("passes.{compute}.amount"), 0.0));
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 1.0 / 255.0).unwrap();
+
+    // After a fragment pass
+    let mut after = Nnpipe::new(&device, width, height, 1).unwrap();
+    after.set_bloom_intensity(0.0);
+    after.add_custom_pass(&device, "Invert", INVERT_FRAGMENT, &[]);
+    after.add_compute_pass(&device, "Invert Again", INVERT_COMPUTE, &[("amount", 1.0)]);
+    assert!(after.take_shader_errors().is_empty());
+    let output = golden::render(&after, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 1.0 / 255.0).unwrap();
+    assert!(pipeline.set_param(&format!("passes.{compute}.amount"), 1.0));
+
+    // Resizing keeps compute passes compute passes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert!(pipeline.custom_pass(compute).unwrap().is_compute());
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&inverted, &output, width, 1.0 / 255.0).unwrap();
+}
+
+#[test]
+fn broken_compute_passes_fall_back() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping compute test: no adapter");
+        return;
+    };
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 16, 16);
+    let mut pipeline = Nnpipe::new(&device, 16, 16, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    pipeline.add_compute_pass(&device, "Broken", INVERT_FRAGMENT, &[]);
+    let errors = pipeline.take_shader_errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].label, "Broken");
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, 16, 1.0 / 255.0).unwrap();
+}
@@ -0,0 +1,81 @@
+// tests/config.rs
+//
+// Pipelines rebuilt from chain configs
+
+#![cfg(all(feature = "config", feature = "stylize"))]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{ConvolutionKernel, Nnpipe, NnpipeError, Palette, PipelineConfig, QualityPreset};
+
+const GAIN: &str = "
+struct Params {
+    gain: f32, // default: 1.0
+}
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+@group(0) @binding(7) var dst_tex: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if all(id.xy < textureDimensions(dst_tex)) {
+        textureStore(dst_tex, id.xy, textureLoad(src_tex, id.xy, 0) * params.gain);
+    }
+}
+";
+
+#[test]
+fn chain_round_trips_through_toml() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping config test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::with_quality(&device, 64, 48, 1, QualityPreset::Medium).unwrap();
+    pipeline.set_brightness_threshold(0.5);
+    pipeline.set_bloom_intensity(2.0);
+    pipeline.add_convolution_pass(&device, &ConvolutionKernel::sharpen(0.5));
+    let palette = pipeline.add_palette_pass(&device, &queue, &Palette::pico8(), 0.25);
+    pipeline.custom_pass_mut(palette).unwrap().enabled = false;
+    let gain = pipeline.add_reflected_compute_pass(&device, "Gain", GAIN);
+    assert!(pipeline.set_param(&format!("passes.{gain}.gain"), 0.75));
+
+    let config = pipeline.chain_config();
+    let toml = config.to_toml().unwrap();
+    assert_eq!(PipelineConfig::from_toml(&toml).unwrap(), config);
+
+    let mut rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert!(rebuilt.take_shader_errors().is_empty());
+    assert_eq!(rebuilt.chain_config(), config);
+    assert_eq!(rebuilt.quality().preset, QualityPreset::Medium);
+
+    let input = TestPattern::Dots.create_texture(&device, &queue, 64, 48);
+    let expected = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let actual = golden::render(&rebuilt, &device, &queue, &input).unwrap();
+    golden::compare(&expected, &actual, 64, 1.0 / 255.0).unwrap();
+}
+
+#[test]
+fn chain_needs_sources() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping config test: no adapter");
+        return;
+    };
+    let config = PipelineConfig::from_toml(
+        "
+        [chain]
+        width = 64
+        height = 48
+        bloom_format = \"rgba8unorm\"
+
+        [[passes]]
+        label = \"Missing\"
+        ",
+    )
+    .unwrap();
+    let result = Nnpipe::from_config(&device, &queue, &config);
+    assert!(matches!(result, Err(NnpipeError::Config(_))));
+
+    let settings = PipelineConfig::default();
+    let result = Nnpipe::from_config(&device, &queue, &settings);
+    assert!(matches!(result, Err(NnpipeError::Config(_))));
+}
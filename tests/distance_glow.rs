@@ -0,0 +1,167 @@
+// tests/distance_glow.rs
+//
+// Tests of the distance-field glow
+
+#![cfg(feature = "stylize")]
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{DistanceGlow, Matte, Nnpipe};
+
+const SIZE: u32 = 32;
+// Corners of the opaque square, inclusive
+const SQUARE: [u32; 2] = [12, 19];
+
+// An opaque grey square on a transparent background
+fn square(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([SIZE, SIZE])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba8Unorm)
+        .build(device);
+    let inside = |v: u32| (SQUARE[0]..=SQUARE[1]).contains(&v);
+    let data: Vec<[u8; 4]> = (0..SIZE * SIZE)
+        .map(|i| match inside(i % SIZE) && inside(i / SIZE) {
+            true => [100, 100, 100, 255],
+            false => [0, 0, 0, 0],
+        })
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(SIZE * 4),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+// Distance from a pixel to the nearest pixel of the square
+fn distance(i: usize) -> f32 {
+    let axis = |v: u32| v.clamp(SQUARE[0], SQUARE[1]).abs_diff(v) as f32;
+    let (x, y) = (i as u32 % SIZE, i as u32 / SIZE);
+    axis(x).hypot(axis(y))
+}
+
+#[test]
+fn glow_lights_a_band_around_the_matte() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping distance glow test: no adapter");
+        return;
+    };
+    let input = square(&device, &queue);
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // A hard red ring from 4 to 8 pixels off the square
+    let ring = DistanceGlow {
+        color: [1.0, 0.0, 0.0],
+        offset: 4.0,
+        radius: 4.0,
+        softness: 0.0,
+        ..Default::default()
+    };
+    pipeline.set_distance_glow(&device, Some(ring));
+    assert_eq!(pipeline.distance_glow(), Some(ring));
+
+    // Nothing glows without a matte
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(frame, plain);
+
+    // The glow follows the matte added after it, and a rebuild
+    pipeline.set_matte(
+        &device,
+        Some(Matte {
+            feather: 0.0,
+            ..Default::default()
+        }),
+    );
+    pipeline.resize(&device, &queue, SIZE, SIZE).unwrap();
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    for (i, (glowing, plain)) in frame.iter().zip(&plain).enumerate() {
+        let d = distance(i);
+        let expected = if (4.0..8.0).contains(&d) { 1.0 } else { 0.0 };
+        assert_eq!(glowing[0] - plain[0], expected, "at {i}, {d} away");
+        assert_eq!(glowing[1..], plain[1..]);
+    }
+
+    // A soft glow fades out from the silhouette
+    pipeline.set_distance_glow(
+        &device,
+        Some(DistanceGlow {
+            offset: 0.0,
+            radius: 8.0,
+            softness: 1.0,
+            ..ring
+        }),
+    );
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let glow = |x: u32| frame[(16 * SIZE + x) as usize][0] - plain[(16 * SIZE + x) as usize][0];
+    assert_eq!(glow(16), 0.0);
+    assert!(glow(SQUARE[1] + 1) > glow(SQUARE[1] + 4));
+    assert!(glow(SQUARE[1] + 4) > glow(SQUARE[1] + 7));
+    assert_eq!(glow(SQUARE[1] + 8), 0.0);
+
+    pipeline.set_distance_glow(&device, None);
+    assert_eq!(
+        golden::render(&pipeline, &device, &queue, &input).unwrap(),
+        plain
+    );
+}
+
+#[test]
+fn glow_settings_are_params() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping distance glow test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    assert!(!pipeline.set_param("distance_glow.radius", 4.0));
+
+    pipeline.set_distance_glow(&device, Some(DistanceGlow::default()));
+    assert!(pipeline.set_param("distance_glow.radius", 4.0));
+    assert!(pipeline.set_param("distance_glow.color.z", 0.75));
+    assert_eq!(pipeline.get_param("distance_glow.color.z"), Some(0.75));
+    let glow = pipeline.distance_glow().unwrap();
+    assert_eq!((glow.radius, glow.color), (4.0, [1.0, 0.6, 0.75]));
+    assert!(pipeline
+        .list_params()
+        .contains(&"distance_glow.softness".to_string()));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn glow_round_trips_through_config() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping distance glow test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    let glow = DistanceGlow {
+        offset: 3.0,
+        softness: 0.0,
+        ..DistanceGlow::default()
+    };
+    pipeline.set_distance_glow(&device, Some(glow));
+
+    let source = pipeline.chain_config().to_toml().unwrap();
+    let config = nnpipe::PipelineConfig::from_toml(&source).unwrap();
+    assert_eq!(config.distance_glow, Some(glow));
+    let rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert_eq!(rebuilt.distance_glow(), Some(glow));
+
+    pipeline.set_distance_glow(&device, Some(DistanceGlow::default()));
+    pipeline.apply_config(&config).unwrap();
+    assert_eq!(pipeline.distance_glow(), Some(glow));
+    let mut plain = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    let error = plain.apply_config(&config).unwrap_err();
+    assert!(
+        error.message.contains("no distance glow"),
+        "{}",
+        error.message
+    );
+}
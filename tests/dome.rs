@@ -0,0 +1,113 @@
+// tests/dome.rs
+//
+// Tests of the fisheye projection of outputs
+
+use std::f32::consts::PI;
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{FisheyeProjection, FisheyeSource, Nnpipe, OutputProjection, OutputScaling};
+
+#[test]
+fn fisheyes_look_the_frame_up_by_direction() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping dome test: no adapter");
+        return;
+    };
+    let (width, height) = (16, 8);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // A square target, point sampled so every pixel shows a whole frame pixel
+    let index = pipeline
+        .add_output(&device, nannou::wgpu::TextureFormat::Rgba16Float)
+        .unwrap();
+    let background = [1.0, 0.0, 1.0, 1.0];
+    let output = pipeline.output_mut(index).unwrap();
+    output.set_scaling(OutputScaling::Nearest);
+    output.set_background(background);
+    assert_eq!(output.projection(), OutputProjection::Flat);
+    let size = 16;
+    let target = Nnpipe::new(&device, size, size, 1).unwrap();
+    let mut project = |projection: FisheyeProjection| {
+        let output = pipeline.output_mut(index).unwrap();
+        output.set_projection(OutputProjection::Fisheye(projection));
+        pipeline
+            .present(&device, &queue, index, &target.output_view)
+            .unwrap();
+        futures::executor::block_on(target.read_output(&device, &queue)).unwrap()
+    };
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32, width: u32| pixels[(y * width + x) as usize];
+    // Red and blue only change across the gradient
+    let column = |pixel: [f32; 4]| [pixel[0], pixel[2]];
+
+    // A hemisphere of a flat view: the circle's center shows the frame's, and the
+    // corners outside the circle the background
+    let dome = FisheyeProjection::default();
+    let ahead = project(dome);
+    assert_eq!(pixel(&ahead, 8, 8, size), pixel(&frame, 8, 4, width));
+    for (x, y) in [(0, 0), (15, 0), (0, 15), (15, 15)] {
+        assert_eq!(pixel(&ahead, x, y, size), background, "at {x}, {y}");
+    }
+    // Directions outside the view's field show the background too
+    assert_eq!(pixel(&ahead, 0, 8, size), background);
+
+    // Rolled a quarter turn, what was right of the center is below it
+    let rolled = project(FisheyeProjection {
+        roll: PI / 2.0,
+        ..dome
+    });
+    let right = pixel(&ahead, 12, 8, size);
+    assert_ne!(column(right), column(pixel(&frame, 8, 4, width)));
+    assert_eq!(column(pixel(&rolled, 8, 12, size)), column(right));
+
+    // Turned around, a flat view is behind the dome's center, a panorama isn't
+    let behind = FisheyeProjection { yaw: PI, ..dome };
+    assert_eq!(pixel(&project(behind), 8, 8, size), background);
+    let panorama = FisheyeProjection {
+        source: FisheyeSource::Equirectangular,
+        ..dome
+    };
+    let around = project(panorama);
+    assert_eq!(pixel(&around, 8, 8, size), pixel(&frame, 8, 4, width));
+    assert_ne!(pixel(&around, 0, 8, size), background);
+    let behind = project(FisheyeProjection {
+        yaw: PI,
+        ..panorama
+    });
+    assert_eq!(pixel(&behind, 8, 8, size), pixel(&frame, 0, 4, width));
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    let output = pipeline.output(index).unwrap();
+    let expected = OutputProjection::Fisheye(FisheyeProjection {
+        yaw: PI,
+        ..panorama
+    });
+    assert_eq!(output.projection(), expected);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn chain_configs_keep_projections() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping dome test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 32, 8, 1).unwrap();
+    let projection = OutputProjection::Fisheye(FisheyeProjection {
+        fov: 1.5 * PI,
+        pitch: 0.25,
+        source: FisheyeSource::Equirectangular,
+        ..Default::default()
+    });
+    pipeline.scaler_mut().set_projection(projection);
+
+    let config = pipeline.chain_config();
+    let source = config.to_toml().unwrap();
+    let config = nnpipe::PipelineConfig::from_toml(&source).unwrap();
+    let rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert_eq!(rebuilt.scaler().projection(), projection);
+    assert!(rebuilt.scaler().warp().is_identity());
+}
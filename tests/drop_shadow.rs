@@ -0,0 +1,164 @@
+// tests/drop_shadow.rs
+//
+// Tests of the drop shadow
+
+#![cfg(feature = "stylize")]
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{DropShadow, Nnpipe};
+
+const SIZE: u32 = 32;
+// Corners of the opaque square, inclusive
+const SQUARE: [u32; 2] = [12, 19];
+
+// An opaque grey square on a transparent background, as half floats
+fn square(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([SIZE, SIZE])
+        .usage(
+            wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+        )
+        .format(wgpu::TextureFormat::Rgba16Float)
+        .build(device);
+    let inside = |v: u32| (SQUARE[0]..=SQUARE[1]).contains(&v);
+    let data: Vec<[u16; 4]> = (0..SIZE * SIZE)
+        .map(|i| match inside(i % SIZE) && inside(i / SIZE) {
+            true => [0x3800, 0x3800, 0x3800, 0x3c00],
+            false => [0; 4],
+        })
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(SIZE * 8),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+fn at(frame: &[[f32; 4]], x: u32, y: u32) -> [f32; 4] {
+    frame[(y * SIZE + x) as usize]
+}
+
+#[test]
+fn shadow_is_composited_under_the_scene() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping drop shadow test: no adapter");
+        return;
+    };
+    let input = square(&device, &queue);
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // A sharp, opaque red shadow six pixels to the right
+    let shadow = DropShadow {
+        offset: [6.0, 0.0],
+        blur_radius: 1.0,
+        color: [1.0, 0.0, 0.0],
+        opacity: 1.0,
+    };
+    pipeline.set_drop_shadow(&device, Some(shadow));
+    assert_eq!(pipeline.drop_shadow(), Some(shadow));
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Red, tone mapped to a half, where only the shadow is
+    let shadowed = at(&frame, SQUARE[1] + 4, 16);
+    assert!((shadowed[0] - 0.5).abs() < 1e-2, "{shadowed:?}");
+    assert!(shadowed[1] < 1e-2 && shadowed[2] < 1e-2);
+    assert_eq!(shadowed[3], 1.0);
+    // The square covers its own shadow, and the rest is left alone
+    for (x, y) in [(16, 16), (SQUARE[0] + 1, 16), (4, 4), (SQUARE[1] + 4, 8)] {
+        assert_eq!(at(&frame, x, y), at(&plain, x, y), "at {x}, {y}");
+    }
+
+    // A blurred, fainter shadow fades out past its edge
+    pipeline.set_drop_shadow(
+        &device,
+        Some(DropShadow {
+            blur_radius: 6.0,
+            opacity: 0.5,
+            ..shadow
+        }),
+    );
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let red = |x| at(&frame, x, 16)[0];
+    assert!(red(SQUARE[1] + 3) < 0.5 * 0.5 + 1e-2);
+    assert!(red(SQUARE[1] + 3) > red(SQUARE[1] + 7));
+    assert!(red(SQUARE[1] + 7) > red(SQUARE[1] + 10));
+
+    // Shadowed in place when the scene is drawn into the pipeline
+    pipeline.process_with(&device, &queue, &pipeline.output_view, |encoder, _| {
+        encoder.copy_texture_to_texture(
+            input.as_image_copy(),
+            pipeline.scene_texture.as_image_copy(),
+            input.extent(),
+        );
+    });
+    let drawn = futures::executor::block_on(pipeline.read_output(&device, &queue)).unwrap();
+    assert_eq!(drawn, frame);
+
+    pipeline.set_drop_shadow(&device, None);
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(frame, plain);
+}
+
+#[test]
+fn shadow_settings_are_params() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping drop shadow test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    assert!(!pipeline.set_param("drop_shadow.opacity", 0.5));
+
+    pipeline.set_drop_shadow(&device, Some(DropShadow::default()));
+    assert!(pipeline.set_param("drop_shadow.offset.y", -4.0));
+    assert!(pipeline.set_param("drop_shadow.color.x", 0.5));
+    assert_eq!(pipeline.get_param("drop_shadow.offset.y"), Some(-4.0));
+    let shadow = pipeline.drop_shadow().unwrap();
+    assert_eq!((shadow.offset, shadow.color[0]), ([8.0, -4.0], 0.5));
+    assert!(pipeline
+        .list_params()
+        .contains(&"drop_shadow.blur_radius".to_string()));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn shadow_round_trips_through_config() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping drop shadow test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    let shadow = DropShadow {
+        offset: [2.0, 3.0],
+        color: [0.1, 0.0, 0.2],
+        ..DropShadow::default()
+    };
+    pipeline.set_drop_shadow(&device, Some(shadow));
+
+    let source = pipeline.chain_config().to_toml().unwrap();
+    let config = nnpipe::PipelineConfig::from_toml(&source).unwrap();
+    assert_eq!(config.drop_shadow, Some(shadow));
+    let rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert_eq!(rebuilt.drop_shadow(), Some(shadow));
+
+    pipeline.set_drop_shadow(&device, Some(DropShadow::default()));
+    pipeline.apply_config(&config).unwrap();
+    assert_eq!(pipeline.drop_shadow(), Some(shadow));
+    let mut plain = Nnpipe::new(&device, SIZE, SIZE, 1).unwrap();
+    let error = plain.apply_config(&config).unwrap_err();
+    assert!(
+        error.message.contains("no drop shadow"),
+        "{}",
+        error.message
+    );
+}
@@ -0,0 +1,132 @@
+// tests/equirect.rs
+//
+// Tests of processing 360° equirectangular panoramas
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::Nnpipe;
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+
+// A black panorama with white dots at `dots`
+fn dots(device: &wgpu::Device, queue: &wgpu::Queue, dots: &[(u32, u32)]) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([WIDTH, HEIGHT])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba8Unorm)
+        .build(device);
+    let mut data = vec![[0, 0, 0, 255u8]; (WIDTH * HEIGHT) as usize];
+    for &(x, y) in dots {
+        data[(y * WIDTH + x) as usize] = [255; 4];
+    }
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(WIDTH * 4),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+fn red(pixels: &[[f32; 4]], x: u32, y: u32) -> f32 {
+    pixels[(y * WIDTH + x) as usize][0]
+}
+
+#[test]
+fn panoramas_bloom_across_the_wrap_line() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping equirectangular test: no adapter");
+        return;
+    };
+    // One dot just left of the wrap line, on the equator, and one near the top pole
+    let input = dots(&device, &queue, &[(WIDTH - 2, HEIGHT / 2), (WIDTH / 2, 1)]);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_max_blur_radius(6.0);
+    assert!(!pipeline.equirectangular());
+    let flat = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    pipeline.set_equirectangular(&device, &queue, true);
+    assert!(pipeline.equirectangular());
+    let panorama = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // The glow of the dot by the wrap line reaches the left edge only when wrapping,
+    // as far as it reaches back from the dot
+    let (seam_x, seam_y) = (WIDTH - 2, HEIGHT / 2);
+    assert!(red(&panorama, 0, seam_y) > red(&flat, 0, seam_y) + 0.1);
+    assert!((red(&panorama, 0, seam_y) - red(&panorama, seam_x - 2, seam_y)).abs() < 0.01);
+    assert!((red(&panorama, seam_x - 1, seam_y) - red(&flat, seam_x - 1, seam_y)).abs() < 0.01);
+
+    // The glow of the dot by the pole spreads wider across
+    let (pole_x, pole_y) = (WIDTH / 2, 1);
+    let far = |pixels: &[[f32; 4]]| -> f32 {
+        (0..WIDTH)
+            .filter(|x| x.abs_diff(pole_x) > 8)
+            .map(|x| red(pixels, x, pole_y))
+            .sum()
+    };
+    assert!(far(&panorama) > far(&flat) + 0.1);
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, WIDTH, HEIGHT).unwrap();
+    assert!(pipeline.equirectangular());
+    let resized = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&panorama, &resized, WIDTH, 0.0).unwrap();
+
+    pipeline.set_equirectangular(&device, &queue, false);
+    let unwrapped = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&flat, &unwrapped, WIDTH, 0.0).unwrap();
+}
+
+// Samples half the frame to the right, so the right half shows what's beyond the
+// frame's right edge
+const SHIFT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(src_tex));
+    return textureSample(src_tex, src_sampler, (pos.xy + vec2<f32>(size.x * 0.5, 0.0)) / size);
+}
+";
+
+#[test]
+fn passes_sample_panoramas_around_the_wrap_line() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping equirectangular test: no adapter");
+        return;
+    };
+    let input = dots(&device, &queue, &[(3, 5)]);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let dot = red(&plain, 3, 5);
+    assert!(dot > 0.0);
+    pipeline.add_custom_pass(&device, "Shift", SHIFT, &[]);
+    let clamped = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(red(&clamped, WIDTH / 2 + 3, 5), 0.0);
+
+    pipeline.set_equirectangular(&device, &queue, true);
+    let wrapped = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(red(&wrapped, WIDTH / 2 + 3, 5), dot);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn chain_configs_keep_the_equirectangular_mode() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping equirectangular test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_equirectangular(&device, &queue, true);
+    let config = pipeline.chain_config();
+    assert!(config.chain.as_ref().unwrap().equirectangular);
+    let rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert!(rebuilt.equirectangular());
+}
@@ -0,0 +1,80 @@
+// tests/false_color.rs
+//
+// Tests of the false color debug view
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{DebugView, Nnpipe};
+
+const WIDTH: u32 = 12;
+const HEIGHT: u32 = 4;
+
+// Black, middle grey and white swatches side by side
+fn swatches(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([WIDTH, HEIGHT])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba8Unorm)
+        .build(device);
+    let colors: [[u8; 4]; 3] = [[0, 0, 0, 255], [46, 46, 46, 255], [255, 255, 255, 255]];
+    let data: Vec<[u8; 4]> = (0..WIDTH * HEIGHT)
+        .map(|i| colors[(i % WIDTH / 4) as usize])
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(WIDTH * 4),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+fn close(a: [f32; 4], b: [f32; 3]) -> bool {
+    (0..3).all(|i| (a[i] - b[i]).abs() < 1e-2)
+}
+
+#[test]
+fn bands_and_threshold_stripes() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping false color test: no adapter");
+        return;
+    };
+    let input = swatches(&device, &queue);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    pipeline.set_brightness_threshold(0.8);
+    pipeline.set_debug_view(&device, DebugView::FalseColor);
+    assert_eq!(pipeline.debug_view(), DebugView::FalseColor);
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let pixel = |x: u32, y: u32| frame[(y * WIDTH + x) as usize];
+
+    // Crushed black and middle grey, neither past the threshold
+    assert!(close(pixel(1, 1), [0.5, 0.0, 0.8]));
+    assert!(close(pixel(5, 1), [0.1, 0.8, 0.1]));
+
+    // White is at display white, and striped where the bloom picks it up
+    let white: Vec<_> = (8..12)
+        .flat_map(|x| (0..HEIGHT).map(move |y| (x, y)))
+        .collect();
+    assert!(white.iter().all(|&(x, y)| {
+        close(pixel(x, y), [1.0, 0.0, 0.0]) || close(pixel(x, y), [1.0, 0.6, 0.6])
+    }));
+    assert!(white
+        .iter()
+        .any(|&(x, y)| close(pixel(x, y), [1.0, 0.6, 0.6])));
+
+    // With the threshold over white nothing blooms, so nothing is striped
+    pipeline.set_brightness_threshold(2.0);
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(close(frame[(WIDTH + 9) as usize], [1.0, 0.0, 0.0]));
+    assert!(close(frame[(WIDTH + 10) as usize], [1.0, 0.0, 0.0]));
+
+    pipeline.set_debug_view(&device, DebugView::Off);
+    // Back to the tone mapped frame
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(plain[(WIDTH + 5) as usize][1] < 0.5);
+}
@@ -0,0 +1,118 @@
+// tests/flare.rs
+//
+// Lens flare tests
+
+#![cfg(feature = "bloom")]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{LensFlare, Nnpipe};
+
+#[test]
+fn flares_mirror_bright_areas() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping flare test: no adapter");
+        return;
+    };
+    let (width, height) = (64, 48);
+    let input = TestPattern::Dots.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Light lands on the black between the dots, and nothing gets darker
+    let flare = LensFlare::default();
+    pipeline.set_lens_flare(&device, Some(flare));
+    assert_eq!(pipeline.lens_flare(), Some(flare));
+    let flared = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let between: f32 = flared
+        .iter()
+        .zip(&plain)
+        .filter(|(_, plain)| plain[..3] == [0.0; 3])
+        .map(|(flared, _)| flared[0] + flared[1] + flared[2])
+        .sum();
+    assert!(between > 0.1);
+    for (flared, plain) in flared.iter().zip(&plain) {
+        assert!(flared[..3].iter().zip(plain).all(|(f, p)| f >= p));
+    }
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.lens_flare(), Some(flare));
+    let resized = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&flared, &resized, width, 1.0 / 255.0).unwrap();
+
+    // Without ghosts or halo there's nothing to add
+    let off = LensFlare {
+        ghosts: 0,
+        halo_intensity: 0.0,
+        ..flare
+    };
+    pipeline.set_lens_flare(&device, Some(off));
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 0.0).unwrap();
+
+    pipeline.set_lens_flare(&device, None);
+    assert_eq!(pipeline.lens_flare(), None);
+}
+
+#[test]
+fn flare_settings_are_params() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping flare test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    assert!(!pipeline.set_param("flare.spacing", 0.5));
+    assert_eq!(pipeline.get_param("flare.spacing"), None);
+
+    pipeline.set_lens_flare(&device, Some(LensFlare::default()));
+    assert!(pipeline.set_param("flare.spacing", 0.5));
+    assert!(pipeline.set_param("flare.tint.y", 0.25));
+    assert!(pipeline.set_param("flare.ghosts", 6.2));
+    assert!(!pipeline.set_param("flare.tint", 1.0));
+    let flare = pipeline.lens_flare().unwrap();
+    assert_eq!((flare.spacing, flare.tint[1], flare.ghosts), (0.5, 0.25, 6));
+    assert_eq!(pipeline.get_param("flare.ghosts"), Some(6.0));
+
+    // Listed after the bloom's params, ahead of the passes'
+    let params = pipeline.list_params();
+    let first = params.iter().position(|path| path.starts_with("flare."));
+    assert_eq!(first, params.iter().position(|path| path == "flare.ghosts"));
+    assert!(first > params.iter().position(|path| path == "bloom.hue_shift"));
+    assert!(params.contains(&"flare.chromatic_shift".to_string()));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn flare_round_trips_through_config() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping flare test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    assert_eq!(pipeline.config().flare, None);
+    let flare = LensFlare {
+        ghosts: 7,
+        tint: [0.5, 0.6, 0.7],
+        ..LensFlare::default()
+    };
+    pipeline.set_lens_flare(&device, Some(flare));
+
+    let source = pipeline.chain_config().to_ron().unwrap();
+    let config = nnpipe::PipelineConfig::from_ron(&source).unwrap();
+    assert_eq!(config.flare, Some(flare));
+    let rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert_eq!(rebuilt.lens_flare(), Some(flare));
+
+    // Applied to a flare that's on, and reported for a pipeline without one
+    let config = nnpipe::PipelineConfig::from_toml("[flare]\nspacing = 0.5\n").unwrap();
+    pipeline.apply_config(&config).unwrap();
+    let applied = LensFlare {
+        spacing: 0.5,
+        ..LensFlare::default()
+    };
+    assert_eq!(pipeline.lens_flare(), Some(applied));
+    let mut plain = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    let error = plain.apply_config(&config).unwrap_err();
+    assert!(error.message.contains("no lens flare"), "{}", error.message);
+}
@@ -0,0 +1,258 @@
+// tests/golden.rs
+//
+// Golden-image regression tests
+//
+// Each test runs a pipeline over a procedural pattern and compares the frame with a
+// reference in `tests/golden`. After an intended change to a shader, regenerate the
+// references with `NNPIPE_UPDATE_GOLDEN=1 cargo test --test golden` and review them.
+// Tests are skipped when there's no adapter to run on.
+
+use std::path::PathBuf;
+
+use nannou::wgpu;
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{DebugView, Nnpipe, QualityPreset};
+
+const WIDTH: u32 = 128;
+const HEIGHT: u32 = 96;
+
+// Leaves room for differences between drivers and rasterizers
+const TOLERANCE: f32 = 2.0 / 255.0;
+
+fn run(
+    name: &str,
+    pattern: TestPattern,
+    setup: impl FnOnce(&mut Nnpipe, &wgpu::Device, &wgpu::Queue),
+) {
+    run_with_quality(name, pattern, QualityPreset::High, setup);
+}
+
+fn run_with_quality(
+    name: &str,
+    pattern: TestPattern,
+    quality: QualityPreset,
+    setup: impl FnOnce(&mut Nnpipe, &wgpu::Device, &wgpu::Queue),
+) {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping golden test '{name}': no adapter");
+        return;
+    };
+
+    // Pin the clock, so animated effects render the same frame every run
+    let mut pipeline = Nnpipe::with_quality(&device, WIDTH, HEIGHT, 1, quality).unwrap();
+    pipeline.set_time(Some(0.0));
+    setup(&mut pipeline, &device, &queue);
+    let input = pattern.create_texture(&device, &queue, WIDTH, HEIGHT);
+    let pixels = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "golden",
+        &format!("{name}.png"),
+    ]
+    .iter()
+    .collect();
+    if let Err(e) = golden::check(&path, &pixels, WIDTH, HEIGHT, TOLERANCE) {
+        panic!("{name}: {e}");
+    }
+}
+
+#[test]
+fn bloom_dots() {
+    run("bloom_dots", TestPattern::Dots, |pipeline, _, _| {
+        pipeline.set_brightness_threshold(0.8);
+        pipeline.set_bloom_intensity(1.5);
+    });
+}
+
+#[test]
+fn bloom_dots_low_quality() {
+    run_with_quality(
+        "bloom_dots_low_quality",
+        TestPattern::Dots,
+        QualityPreset::Low,
+        |pipeline, _, _| {
+            pipeline.set_brightness_threshold(0.8);
+            pipeline.set_bloom_intensity(1.5);
+        },
+    );
+}
+
+#[test]
+fn bloom_gradient() {
+    run("bloom_gradient", TestPattern::Gradient, |_, _, _| {});
+}
+
+#[test]
+fn quad_view_dots() {
+    run(
+        "quad_view_dots",
+        TestPattern::Dots,
+        |pipeline, device, _| {
+            pipeline.set_brightness_threshold(0.8);
+            pipeline.set_debug_view(device, DebugView::Quad);
+        },
+    );
+}
+
+#[test]
+fn false_color_gradient() {
+    run(
+        "false_color_gradient",
+        TestPattern::Gradient,
+        |pipeline, device, _| {
+            pipeline.set_brightness_threshold(0.8);
+            pipeline.set_debug_view(device, DebugView::FalseColor);
+        },
+    );
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn emboss_checkerboard() {
+    run(
+        "emboss_checkerboard",
+        TestPattern::Checkerboard { cell: 8 },
+        |pipeline, device, _| {
+            pipeline.add_emboss_pass(device, 0.8, 2.0, 0.5);
+        },
+    );
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn palette_gradient() {
+    run(
+        "palette_gradient",
+        TestPattern::Gradient,
+        |pipeline, device, queue| {
+            pipeline.add_palette_pass(device, queue, &nnpipe::Palette::pico8(), 1.0);
+        },
+    );
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn scanline_noise_checkerboard() {
+    run(
+        "scanline_noise_checkerboard",
+        TestPattern::Checkerboard { cell: 8 },
+        |pipeline, device, _| {
+            pipeline.set_time(Some(1.5));
+            pipeline.set_seed(7);
+            let displacement = nnpipe::ScanlineDisplacement {
+                mode: nnpipe::ScanlineMode::Noise,
+                ..Default::default()
+            };
+            pipeline.add_scanline_pass(device, &displacement);
+        },
+    );
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn luma_key_gradient() {
+    run(
+        "luma_key_gradient",
+        TestPattern::Gradient,
+        |pipeline, device, queue| {
+            let layer =
+                TestPattern::Checkerboard { cell: 8 }.create_texture(device, queue, WIDTH, HEIGHT);
+            let key = nnpipe::LumaKey {
+                threshold: 0.3,
+                softness: 0.2,
+                ..Default::default()
+            };
+            pipeline.add_luma_key_pass(device, &key, &layer.view().build());
+        },
+    );
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn pip_gradient() {
+    run(
+        "pip_gradient",
+        TestPattern::Gradient,
+        |pipeline, device, queue| {
+            let inset = TestPattern::Checkerboard { cell: 4 }.create_texture(device, queue, 64, 32);
+            let pip = nnpipe::PictureInPicture {
+                center: [0.7, 0.3],
+                width: 0.4,
+                border: 2.0,
+                border_color: [1.0, 0.5, 0.0, 1.0],
+                opacity: 1.0,
+            };
+            pipeline.add_pip_pass(device, &pip, &inset.view().build());
+        },
+    );
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn border_gradient() {
+    run(
+        "border_gradient",
+        TestPattern::Gradient,
+        |pipeline, device, _| {
+            let border = nnpipe::Border {
+                thickness: 12.0,
+                color: [0.1, 0.2, 0.8, 1.0],
+                feather: 6.0,
+                corner_radius: 16.0,
+            };
+            pipeline.add_border_pass(device, &border);
+        },
+    );
+}
+
+#[cfg(feature = "grading")]
+#[test]
+fn split_toning_gradient() {
+    run(
+        "split_toning_gradient",
+        TestPattern::Gradient,
+        |pipeline, device, _| {
+            pipeline.add_split_toning_pass(device, &nnpipe::SplitToning::default());
+        },
+    );
+}
+
+#[cfg(feature = "grading")]
+#[test]
+fn color_vision_gradient() {
+    run(
+        "color_vision_gradient",
+        TestPattern::Gradient,
+        |pipeline, device, _| {
+            let vision = nnpipe::ColorVision {
+                deficiency: nnpipe::ColorDeficiency::Deuteranopia,
+                ..Default::default()
+            };
+            pipeline.add_color_vision_pass(device, &vision);
+        },
+    );
+}
+
+#[test]
+fn set_quality_matches_construction() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping quality switch test: no adapter");
+        return;
+    };
+    let input = TestPattern::Dots.create_texture(&device, &queue, WIDTH, HEIGHT);
+
+    let constructed =
+        Nnpipe::with_quality(&device, WIDTH, HEIGHT, 1, QualityPreset::Medium).unwrap();
+    let expected = golden::render(&constructed, &device, &queue, &input).unwrap();
+
+    let mut switched = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    switched
+        .set_quality(&device, &queue, QualityPreset::Medium)
+        .unwrap();
+    assert_eq!(switched.quality(), QualityPreset::Medium.profile());
+    let actual = golden::render(&switched, &device, &queue, &input).unwrap();
+
+    golden::compare(&expected, &actual, WIDTH, TOLERANCE).unwrap();
+}
@@ -0,0 +1,85 @@
+// tests/graph.rs
+//
+// Tests of the chain graph
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{Nnpipe, Scopes};
+
+const PASSTHROUGH: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+}
+";
+
+#[test]
+fn graph_follows_the_chain() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping graph test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+
+    let graph = pipeline.chain_graph();
+    let ids: Vec<_> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        ["scene", "brightness", "blur_h", "blur_v", "composite"]
+    );
+    let composite = graph.node("composite").unwrap();
+    assert_eq!(composite.texture, "output");
+    assert_eq!(composite.size, Some([64, 48]));
+    let inputs: Vec<_> = graph.inputs("composite").map(|node| &node.id).collect();
+    assert_eq!(inputs, ["scene", "blur_v"]);
+    assert_eq!(graph.edges.len(), 5);
+
+    // Disabled passes are left out, and the others ping-pong to the scopes' texture
+    for label in ["First", "Second", "Third"] {
+        pipeline.add_custom_pass(&device, label, PASSTHROUGH, &[]);
+    }
+    pipeline.custom_pass_mut(1).unwrap().enabled = false;
+    pipeline.set_scopes(&device, Some(Scopes::default()));
+    pipeline
+        .add_output(&device, wgpu::TextureFormat::Bgra8UnormSrgb)
+        .unwrap();
+    let graph = pipeline.chain_graph();
+    let ids: Vec<_> = graph.nodes[4..]
+        .iter()
+        .map(|node| node.id.as_str())
+        .collect();
+    assert_eq!(
+        ids,
+        ["composite", "passes.0", "passes.2", "scopes", "outputs.0"]
+    );
+    let textures: Vec<_> = graph.nodes[4..].iter().map(|node| &node.texture).collect();
+    assert_eq!(
+        textures,
+        [
+            "composite",
+            "effect",
+            "scopes_source",
+            "output",
+            "outputs.0"
+        ]
+    );
+    assert_eq!(graph.node("passes.2").unwrap().label, "Third");
+    let output = graph.node("outputs.0").unwrap();
+    assert_eq!(
+        (output.format.as_str(), output.size),
+        ("Bgra8UnormSrgb", None)
+    );
+    assert_eq!(graph.inputs("passes.2").next().unwrap().id, "passes.0");
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph chain {"));
+    assert!(dot.contains("\"passes.0\" -> \"passes.2\" [label=\"effect\"];"));
+
+    // Bypassed, the scene goes straight to the outputs
+    pipeline.set_bypass(true);
+    let graph = pipeline.chain_graph();
+    let ids: Vec<_> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+    assert_eq!(ids, ["scene", "bypass", "outputs.0"]);
+}
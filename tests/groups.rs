@@ -0,0 +1,148 @@
+// tests/groups.rs
+//
+// Tests of param groups and their mute and solo
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::Nnpipe;
+#[cfg(feature = "bloom")]
+use nnpipe::{LensFlare, Matte};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+#[test]
+fn muted_and_soloed_groups_bypass_their_effects() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping groups test: no adapter");
+        return;
+    };
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 64, 48);
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.set_bloom_intensity(2.0);
+    pipeline.add_custom_pass(&device, "Dim", TINT, &[("amount", 0.5)]);
+    pipeline.add_custom_pass(&device, "Darken", TINT, &[("amount", 0.25)]);
+
+    let groups = pipeline.param_groups();
+    let names: Vec<_> = groups.iter().map(|group| group.name.as_str()).collect();
+    assert_eq!(names, ["bloom", "Dim", "Darken"]);
+    assert_eq!(groups[1].path, "passes.0");
+    assert_eq!(
+        groups[1].params,
+        ["passes.0.enabled", "passes.0.mix", "passes.0.amount"]
+    );
+    assert!(groups[0].params.contains(&"bloom.intensity".to_string()));
+    assert!(!pipeline
+        .list_params()
+        .iter()
+        .any(|path| path.ends_with(".mute")));
+
+    // Renders with effects switched off by hand, to compare with
+    let render = |pipeline: &Nnpipe| golden::render(pipeline, &device, &queue, &input).unwrap();
+    let full = render(&pipeline);
+    pipeline.custom_pass_mut(0).unwrap().enabled = false;
+    let without_dim = render(&pipeline);
+    pipeline.set_bloom_intensity(0.0);
+    let darken_alone = render(&pipeline);
+    pipeline.custom_pass_mut(0).unwrap().enabled = true;
+    pipeline.set_bloom_intensity(2.0);
+    assert_ne!(without_dim, darken_alone);
+
+    // Muting leaves the effect's settings alone
+    assert!(pipeline.set_group_muted("passes.0", true));
+    assert!(pipeline.custom_pass(0).unwrap().enabled);
+    assert_eq!(pipeline.get_param("passes.0.mute"), Some(1.0));
+    assert_eq!(render(&pipeline), without_dim);
+
+    // Soloing bypasses everything else, muted groups included
+    assert!(pipeline.set_param("passes.1.solo", 1.0));
+    assert!(pipeline.param_groups()[2].soloed);
+    assert_eq!(render(&pipeline), darken_alone);
+    assert!(pipeline.set_group_soloed("passes.0", true));
+    assert_eq!(render(&pipeline), darken_alone);
+    assert_eq!(pipeline.bloom_intensity, 2.0);
+
+    pipeline.clear_solo();
+    assert!(pipeline.set_param("passes.0.mute", 0.0));
+    assert_eq!(render(&pipeline), full);
+
+    // The bloom is a group too
+    assert!(pipeline.set_param("bloom.mute", 1.0));
+    pipeline.custom_pass_mut(0).unwrap().enabled = false;
+    assert_eq!(render(&pipeline), darken_alone);
+    assert!(pipeline.set_group_muted("bloom", false));
+    assert_eq!(render(&pipeline), without_dim);
+
+    assert!(!pipeline.set_group_muted("passes.2", true));
+    assert!(!pipeline.set_group_soloed("macros", true));
+    assert_eq!(pipeline.get_param("passes.2.solo"), None);
+}
+
+#[cfg(feature = "bloom")]
+#[test]
+fn layers_have_groups_of_their_own() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping groups test: no adapter");
+        return;
+    };
+    let input = TestPattern::Dots.create_texture(&device, &queue, 64, 48);
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    pipeline.add_custom_pass(&device, "Dim", TINT, &[("amount", 0.5)]);
+
+    // Renders with effects switched off by hand, to compare with
+    let render = |pipeline: &Nnpipe| golden::render(pipeline, &device, &queue, &input).unwrap();
+    let dimmed = render(&pipeline);
+    assert!(!pipeline.set_group_muted("flare", true));
+    assert_eq!(pipeline.get_param("flare.mute"), None);
+    pipeline.set_lens_flare(&device, Some(LensFlare::default()));
+    let full = render(&pipeline);
+    pipeline.custom_pass_mut(0).unwrap().enabled = false;
+    let flare_alone = render(&pipeline);
+    pipeline.custom_pass_mut(0).unwrap().enabled = true;
+    assert_ne!(full, dimmed);
+
+    // Listed in chain order, with the layer's params, or none
+    pipeline.set_matte(&device, Some(Matte::default()));
+    let groups = pipeline.param_groups();
+    let names: Vec<_> = groups.iter().map(|group| group.name.as_str()).collect();
+    assert_eq!(names, ["bloom", "matte", "lens flare", "Dim"]);
+    assert_eq!(groups[1].path, "matte");
+    assert!(groups[1].params.is_empty());
+    assert_eq!(groups[2].path, "flare");
+    assert!(groups[2].params.contains(&"flare.spacing".to_string()));
+    pipeline.set_matte(&device, None);
+
+    // Muting the flare leaves its settings alone
+    assert!(pipeline.set_param("flare.mute", 1.0));
+    assert_eq!(pipeline.get_param("flare.mute"), Some(1.0));
+    assert_eq!(pipeline.lens_flare(), Some(LensFlare::default()));
+    assert_eq!(render(&pipeline), dimmed);
+    assert!(pipeline.set_group_muted("flare", false));
+    assert_eq!(render(&pipeline), full);
+
+    // Soloing the flare bypasses the pass, and soloing the pass the flare
+    assert!(pipeline.set_group_soloed("flare", true));
+    assert!(pipeline.param_groups()[1].soloed);
+    assert_eq!(render(&pipeline), flare_alone);
+    pipeline.clear_solo();
+    assert!(pipeline.set_param("passes.0.solo", 1.0));
+    assert_eq!(render(&pipeline), dimmed);
+
+    // A soloed layer that's switched off no longer bypasses the others
+    pipeline.clear_solo();
+    assert!(pipeline.set_group_soloed("flare", true));
+    pipeline.set_lens_flare(&device, None);
+    assert_eq!(render(&pipeline), dimmed);
+}
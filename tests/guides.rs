@@ -0,0 +1,69 @@
+// tests/guides.rs
+//
+// Alignment guide tests
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Guides, Nnpipe};
+
+#[test]
+fn guides_draw_lines_over_the_frame() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping guides test: no adapter");
+        return;
+    };
+    let (width, height) = (120, 60);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32| pixels[(y * width + x) as usize];
+    let red = [1.0, 0.0, 0.0, 1.0];
+
+    // Lines between four cells across and two down, one pixel wide
+    let grid = Guides {
+        grid: [4, 2],
+        crosshair: false,
+        title_safe: 0.0,
+        action_safe: 0.0,
+        color: red,
+        line_width: 1.0,
+    };
+    pipeline.set_guides(&device, Some(grid));
+    assert_eq!(pipeline.guides(), Some(grid));
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    for x in 0..width {
+        for y in 0..height {
+            let expected = if [29, 59, 89].contains(&x) || y == 29 {
+                red
+            } else {
+                pixel(&plain, x, y)
+            };
+            assert_eq!(pixel(&output, x, y), expected, "at {x}, {y}");
+        }
+    }
+
+    // A safe area half the frame's size, and the crosshair
+    let safe = Guides {
+        grid: [0, 0],
+        crosshair: true,
+        title_safe: 0.5,
+        ..grid
+    };
+    pipeline.set_guides(&device, Some(safe));
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    for (x, y) in [(29, 20), (89, 40), (60, 14), (60, 44), (59, 27), (57, 29)] {
+        assert_eq!(pixel(&output, x, y), red, "at {x}, {y}");
+    }
+    for (x, y) in [(27, 20), (29, 10), (60, 20), (10, 29)] {
+        assert_eq!(pixel(&output, x, y), pixel(&plain, x, y), "at {x}, {y}");
+    }
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.guides(), Some(safe));
+
+    pipeline.set_guides(&device, None);
+    assert_eq!(pipeline.guides(), None);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 0.0).unwrap();
+}
@@ -0,0 +1,112 @@
+// tests/gutter.rs
+//
+// Bloom gutter tests
+
+use nannou::prelude::*;
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::Nnpipe;
+
+// Just past the right edge of the frame
+const OUTSIDE: f32 = 38.0;
+
+#[test]
+fn lights_outside_the_frame_bloom_into_it() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping gutter test: no adapter");
+        return;
+    };
+    let (width, height) = (64, 32);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    let mut renderer = nannou::draw::RendererBuilder::new().build(
+        &device,
+        [width, height],
+        1.0,
+        1,
+        wgpu::TextureFormat::Rgba16Float,
+    );
+    // A white bar at `x`. Rendering drains the Draw, so each frame gets a new one.
+    let mut render_bar = |pipeline: &Nnpipe, x: f32| {
+        let draw = nannou::Draw::new();
+        draw.background().color(BLACK);
+        draw.rect().x_y(x, 0.0).w_h(4.0, 8.0).color(WHITE);
+        pipeline.render(&device, &queue, &mut renderer, &draw);
+        futures::executor::block_on(pipeline.read_output(&device, &queue)).unwrap()
+    };
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32| pixels[(y * width + x) as usize];
+    let centered = render_bar(&pipeline, 0.0);
+
+    // Nothing in the frame without a gutter
+    let output = render_bar(&pipeline, OUTSIDE);
+    assert!(output.iter().all(|pixel| pixel[..3] == [0.0; 3]));
+
+    // The bar glows in from the right edge with one
+    assert_eq!(pipeline.bloom_gutter(), 0);
+    let reach = pipeline.bloom_reach();
+    assert!(reach > 8);
+    pipeline.set_bloom_gutter(&device, reach).unwrap();
+    assert_eq!(pipeline.bloom_gutter(), reach);
+    let output = render_bar(&pipeline, OUTSIDE);
+    assert!(pixel(&output, width - 1, height / 2)[0] > 0.01);
+    assert!(pixel(&output, width - 1, height / 2)[0] > pixel(&output, width - 8, height / 2)[0]);
+    assert_eq!(pixel(&output, 0, height / 2)[..3], [0.0; 3]);
+    // Lights away from the edges bloom the same
+    let padded = render_bar(&pipeline, 0.0);
+    golden::compare(&centered, &padded, width, 1.0 / 255.0).unwrap();
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.bloom_gutter(), reach);
+    let resized = render_bar(&pipeline, OUTSIDE);
+    golden::compare(&output, &resized, width, 0.0).unwrap();
+
+    pipeline.set_bloom_gutter(&device, 0).unwrap();
+    assert_eq!(pipeline.bloom_gutter(), 0);
+    let output = render_bar(&pipeline, OUTSIDE);
+    assert!(output.iter().all(|pixel| pixel[..3] == [0.0; 3]));
+}
+
+#[test]
+fn recorded_scenes_get_the_gutter_too() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping gutter test: no adapter");
+        return;
+    };
+    let (width, height) = (64, 32);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    let mut renderer = nannou::draw::RendererBuilder::new().build(
+        &device,
+        [width, height],
+        1.0,
+        1,
+        wgpu::TextureFormat::Rgba16Float,
+    );
+    let reach = pipeline.bloom_reach();
+    pipeline.set_bloom_gutter(&device, reach).unwrap();
+
+    // The scene view is padded by the gutter, with the Draw's origin at its center
+    let draw = nannou::Draw::new();
+    draw.background().color(BLACK);
+    draw.rect().x_y(OUTSIDE, 0.0).w_h(4.0, 8.0).color(WHITE);
+    pipeline.process_with(
+        &device,
+        &queue,
+        &pipeline.output_view,
+        |encoder, scene_view| {
+            assert_eq!(scene_view.size(), [width + 2 * reach, height + 2 * reach]);
+            renderer.encode_render_pass(
+                &device,
+                encoder,
+                &draw,
+                1.0,
+                scene_view.size(),
+                scene_view,
+                None,
+            );
+        },
+    );
+    let output = futures::executor::block_on(pipeline.read_output(&device, &queue)).unwrap();
+    let pixel = |x: u32, y: u32| output[(y * width + x) as usize];
+    assert!(pixel(width - 1, height / 2)[0] > 0.01);
+    assert_eq!(pixel(0, height / 2)[..3], [0.0; 3]);
+}
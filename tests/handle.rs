@@ -0,0 +1,42 @@
+// tests/handle.rs
+//
+// Param changes from other threads
+
+use nnpipe::golden;
+use nnpipe::Nnpipe;
+
+#[test]
+fn handle_changes_land_on_update() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping handle test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.set_bloom_intensity(1.0);
+
+    let handle = pipeline.params_handle();
+    let worker = handle.clone();
+    std::thread::spawn(move || {
+        worker.set("bloom.intensity", 2.0);
+        worker.set("bloom.intensity", 3.0);
+        worker.set("bloom.missing", 1.0);
+    })
+    .join()
+    .unwrap();
+
+    // Nothing changes until the update
+    assert!(handle.is_pending());
+    assert_eq!(pipeline.bloom_intensity, 1.0);
+    pipeline.update(&device, &queue, 0.0);
+    assert!(!handle.is_pending());
+    assert_eq!(pipeline.bloom_intensity, 3.0);
+    let errors = pipeline.take_command_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("bloom.missing"));
+
+    // Handles keep working across rebuilds
+    pipeline.resize(&device, &queue, 32, 24).unwrap();
+    handle.set("bloom.intensity", 0.5);
+    pipeline.update(&device, &queue, 0.0);
+    assert_eq!(pipeline.bloom_intensity, 0.5);
+}
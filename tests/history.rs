@@ -0,0 +1,115 @@
+// tests/history.rs
+//
+// Undoing and redoing settings changes
+
+#![cfg(feature = "config")]
+
+use nnpipe::golden;
+use nnpipe::{Easing, Nnpipe, PipelineConfig};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+#[test]
+fn changes_undo_and_redo() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping history test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.add_custom_pass(&device, "Tint", TINT, &[("amount", 1.0)]);
+    pipeline.set_bloom_intensity(1.0);
+
+    // Nothing is recorded without a history
+    assert!(pipeline.set_param("bloom.intensity", 2.0));
+    assert!(!pipeline.can_undo());
+    assert!(!pipeline.undo());
+
+    pipeline.enable_history(3);
+    // A sweep of one param is one step
+    for amount in [0.9, 0.8, 0.7] {
+        assert!(pipeline.set_param("passes.0.amount", amount));
+    }
+    assert!(pipeline.set_param("passes.0.enabled", 0.0));
+    // Unknown params aren't changes
+    assert!(!pipeline.set_param("passes.0.missing", 1.0));
+
+    assert!(pipeline.undo());
+    assert!(pipeline.custom_pass(0).unwrap().enabled);
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(0.7));
+    assert!(pipeline.undo());
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(1.0));
+    assert!(!pipeline.undo());
+
+    assert!(pipeline.can_redo());
+    assert!(pipeline.redo());
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(0.7));
+    assert!(pipeline.redo());
+    assert!(!pipeline.custom_pass(0).unwrap().enabled);
+    assert!(!pipeline.redo());
+
+    // Setter changes after a checkpoint, and a new change drops the redo steps
+    pipeline.checkpoint();
+    pipeline.set_bloom_intensity(4.0);
+    pipeline.set_channel_thresholds(Some([0.1, 0.2, 0.3]));
+    assert!(pipeline.undo());
+    assert_eq!(pipeline.bloom_intensity, 2.0);
+    assert_eq!(pipeline.channel_thresholds, None);
+    let mut preset = PipelineConfig::default();
+    preset.bloom.intensity = Some(3.0);
+    pipeline.apply_config(&preset).unwrap();
+    assert!(!pipeline.can_redo());
+
+    // Undoing stops a morph, which is one step
+    preset.bloom.intensity = Some(5.0);
+    pipeline
+        .morph_to_preset(&preset, 1.0, Easing::Linear)
+        .unwrap();
+    pipeline.update(&device, &queue, 0.5);
+    assert!(pipeline.undo());
+    assert!(!pipeline.morphing());
+    assert_eq!(pipeline.bloom_intensity, 3.0);
+
+    // However many settings a morph moves, it's still one step
+    preset.bloom.threshold = Some(0.2);
+    preset.bloom.saturation = Some(0.5);
+    let threshold = pipeline.brightness_threshold;
+    let saturation = pipeline.bloom_saturation;
+    pipeline
+        .morph_to_preset(&preset, 1.0, Easing::Linear)
+        .unwrap();
+    for _ in 0..4 {
+        pipeline.update(&device, &queue, 0.1);
+    }
+    assert!((pipeline.bloom_intensity - 3.8).abs() < 1e-4);
+    assert!(pipeline.undo());
+    assert_eq!(pipeline.bloom_intensity, 3.0);
+    assert_eq!(pipeline.brightness_threshold, threshold);
+    assert_eq!(pipeline.bloom_saturation, saturation);
+    assert!(pipeline.redo());
+    assert!(pipeline.undo());
+
+    // The history keeps its latest steps, and resizing keeps the history
+    pipeline.resize(&device, &queue, 32, 24).unwrap();
+    let mut steps = 0;
+    while pipeline.undo() {
+        steps += 1;
+    }
+    assert_eq!(steps, 2);
+    assert_eq!(pipeline.bloom_intensity, 2.0);
+
+    pipeline.disable_history();
+    assert!(!pipeline.can_redo());
+}
@@ -0,0 +1,106 @@
+// tests/hud.rs
+//
+// Stats HUD tests
+
+use nannou::wgpu;
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{BurnInCorner, Nnpipe, StatsHud};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+#[test]
+fn hud_graphs_frame_times() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping HUD test: no adapter");
+        return;
+    };
+    let (width, height) = (128, 64);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let hud = StatsHud {
+        corner: BurnInCorner::BottomLeft,
+        scale: 1,
+        // Keeps the bars short and green however slow the adapter is
+        target_fps: 1.0,
+        ..StatsHud::default()
+    };
+    pipeline.set_stats_hud(&device, &queue, Some(hud));
+    assert_eq!(pipeline.stats_hud(), Some(hud));
+    // The first frame has nothing to time yet, the second one a bar
+    golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32| pixels[(y * width + x) as usize];
+    let (corner, plain_corner) = (pixel(&output, 0, height - 1), pixel(&plain, 0, height - 1));
+    assert!((corner[1] - plain_corner[1] * 0.4).abs() < 0.01);
+    let bar = [2, 3].map(|x| (height - 16..height).any(|y| pixel(&output, x, y)[1] == 1.0));
+    assert_eq!(bar, [true, false]);
+    for x in width / 2..width {
+        for y in 0..height / 2 {
+            assert_eq!(pixel(&output, x, y), pixel(&plain, x, y));
+        }
+    }
+
+    // Survives resizes, and toggles off and back on with the defaults
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.stats_hud(), Some(hud));
+    assert!(!pipeline.toggle_stats_hud(&device, &queue));
+    assert_eq!(pipeline.stats_hud(), None);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 0.0).unwrap();
+    assert!(pipeline.toggle_stats_hud(&device, &queue));
+    assert_eq!(pipeline.stats_hud(), Some(StatsHud::default()));
+}
+
+#[test]
+fn hud_lists_stage_times() {
+    // On the GPU where the adapter has timestamp queries, and on the CPU otherwise
+    for features in [wgpu::Features::TIMESTAMP_QUERY, wgpu::Features::empty()] {
+        let Some((device, queue)) = golden::headless_device_with(features) else {
+            eprintln!("skipping HUD test: no adapter");
+            return;
+        };
+        let (width, height) = (64, 32);
+        let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+        let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+        pipeline.add_custom_pass(&device, "Tint", TINT, &[("amount", 1.0)]);
+        let hud = StatsHud {
+            stages: true,
+            ..StatsHud::default()
+        };
+        pipeline.set_stats_hud(&device, &queue, Some(StatsHud::default()));
+        golden::render(&pipeline, &device, &queue, &input).unwrap();
+        assert!(pipeline.stage_times().is_empty());
+
+        pipeline.set_stats_hud(&device, &queue, Some(hud));
+        // Timestamps are read back a couple of frames later
+        for _ in 0..4 {
+            golden::render(&pipeline, &device, &queue, &input).unwrap();
+        }
+        let times = pipeline.stage_times();
+        let labels: Vec<&str> = times.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(
+            labels,
+            ["Brightness", "Blur", "Composite", "Tint", "Finish"]
+        );
+        assert!(times
+            .iter()
+            .all(|(_, time)| time.is_finite() && *time >= 0.0));
+    }
+}
@@ -0,0 +1,86 @@
+// tests/inputs.rs
+//
+// Tests of custom passes binding the sketch's own buffers and textures
+
+use nannou::wgpu::{self, util::DeviceExt};
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, PassInput, PipelineCache};
+
+const OVERLAY: &str = "
+@group(1) @binding(0) var<storage, read> tint: array<f32>;
+@group(1) @binding(1) var overlay_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let overlay = textureLoad(overlay_tex, vec2<i32>(position.xy), 0);
+    return overlay * vec4<f32>(tint[0], tint[1], tint[2], tint[3]);
+}
+";
+
+fn tint_buffer(device: &wgpu::Device, tint: [f32; 4]) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Tint"),
+        contents: bytemuck::cast_slice(&tint),
+        usage: wgpu::BufferUsages::STORAGE,
+    })
+}
+
+fn tinted(tint: [f32; 4]) -> Vec<[f32; 4]> {
+    TestPattern::Gradient
+        .pixels(32, 24)
+        .iter()
+        .map(|pixel| std::array::from_fn(|channel| pixel[channel] * tint[channel]))
+        .collect()
+}
+
+#[test]
+fn inputs_are_bound_to_the_pass() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping input test: no adapter");
+        return;
+    };
+    let cache = PipelineCache::new();
+    let mut pipeline = Nnpipe::with_cache(&device, 32, 24, 1, &cache).unwrap();
+    let input = TestPattern::Dots.create_texture(&device, &queue, 32, 24);
+    let overlay = TestPattern::Gradient.create_texture(&device, &queue, 32, 24);
+    let overlay_view = overlay.view().build();
+
+    let tint = [0.5, 1.0, 0.25, 1.0];
+    let index = pipeline.add_custom_pass_with_inputs(
+        &device,
+        "Overlay",
+        OVERLAY,
+        &[],
+        &[
+            PassInput::Buffer(&tint_buffer(&device, tint)),
+            PassInput::Texture(&overlay_view),
+        ],
+    );
+    assert!(pipeline.take_shader_errors().is_empty());
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&tinted(tint), &output, 32, 1.0 / 255.0).unwrap();
+
+    // Inputs of the same kinds are swapped without a new pipeline
+    let count = cache.pipeline_count();
+    let red = [1.0, 0.0, 0.0, 1.0];
+    let inputs = [
+        PassInput::Buffer(&tint_buffer(&device, red)),
+        PassInput::Texture(&overlay_view),
+    ];
+    pipeline.set_pass_inputs(&device, index, &inputs).unwrap();
+    assert_eq!(cache.pipeline_count(), count);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&tinted(red), &output, 32, 1.0 / 255.0).unwrap();
+
+    // Inputs the shader can't bind are refused, keeping the previous ones
+    let missing_texture = [PassInput::Buffer(&tint_buffer(&device, tint))];
+    assert!(pipeline
+        .set_pass_inputs(&device, index, &missing_texture)
+        .is_err());
+
+    // and they survive resizes
+    pipeline.resize(&device, &queue, 32, 24).unwrap();
+    assert!(pipeline.take_shader_errors().is_empty());
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&tinted(red), &output, 32, 1.0 / 255.0).unwrap();
+}
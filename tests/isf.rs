@@ -0,0 +1,113 @@
+// tests/isf.rs
+//
+// ISF import tests
+
+#![cfg(feature = "isf")]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{IsfInputType, IsfShader, Nnpipe};
+
+const TINT: &str = r#"/*{
+    "DESCRIPTION": "Mixes the frame with a color, flipped vertically on request",
+    "CREDIT": "nnpipe",
+    "ISFVSN": "2",
+    "CATEGORIES": ["Color Effect"],
+    "INPUTS": [
+        { "NAME": "inputImage", "TYPE": "image" },
+        { "NAME": "amount", "TYPE": "float", "DEFAULT": 0.0, "MIN": 0.0, "MAX": 1.0 },
+        { "NAME": "flip", "TYPE": "bool", "DEFAULT": false },
+        { "NAME": "tint", "TYPE": "color", "DEFAULT": [1.0, 0.0, 0.0, 1.0] },
+        { "NAME": "center", "TYPE": "point2D" }
+    ]
+}*/
+
+void main() {
+    vec2 coord = isf_FragNormCoord;
+    if (flip) {
+        coord.y = 1.0 - coord.y;
+    }
+    vec4 src = IMG_NORM_PIXEL(inputImage, coord);
+    gl_FragColor = vec4(mix(src.rgb, tint.rgb, amount), src.a);
+}
+"#;
+
+#[test]
+fn inputs_become_params() {
+    let shader = IsfShader::parse(TINT).unwrap();
+    assert_eq!(
+        shader.description(),
+        Some("Mixes the frame with a color, flipped vertically on request")
+    );
+    let types: Vec<IsfInputType> = shader.inputs().iter().map(|input| input.ty).collect();
+    assert_eq!(
+        types,
+        [
+            IsfInputType::Float,
+            IsfInputType::Bool,
+            IsfInputType::Color,
+            IsfInputType::Point2D
+        ]
+    );
+    assert_eq!(shader.inputs()[0].max, Some(vec![1.0]));
+    let params = shader.params();
+    let params: Vec<(&str, f32)> = params
+        .iter()
+        .map(|(name, value)| (name.as_str(), *value))
+        .collect();
+    assert_eq!(
+        params,
+        [
+            ("amount", 0.0),
+            ("flip", 0.0),
+            ("tint_r", 1.0),
+            ("tint_g", 0.0),
+            ("tint_b", 0.0),
+            ("tint_a", 1.0),
+            ("center_x", 0.0),
+            ("center_y", 0.0),
+        ]
+    );
+
+    let error = IsfShader::parse(&TINT.replace("\"point2D\"", "\"audioFFT\"")).unwrap_err();
+    assert!(error.message.contains("unsupported type"), "{error}");
+    assert!(IsfShader::parse("void main() {}").is_err());
+}
+
+#[test]
+fn isf_passes_run() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping ISF test: no adapter");
+        return;
+    };
+    let input = TestPattern::Dots.create_texture(&device, &queue, 32, 24);
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let index = pipeline.add_isf_pass(&device, "Tint", TINT);
+    assert!(pipeline.take_shader_errors().is_empty());
+    let unchanged = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &unchanged, 32, 1.0 / 255.0).unwrap();
+
+    // Flipping twice, in ISF's and the chain's coordinates, gives the frame back
+    assert!(pipeline.set_param(&format!("passes.{index}.flip"), 1.0));
+    let flipped = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let expected: Vec<[f32; 4]> = plain
+        .chunks(32)
+        .rev()
+        .flat_map(|row| row.iter().copied())
+        .collect();
+    golden::compare(&expected, &flipped, 32, 1.0 / 255.0).unwrap();
+
+    assert!(pipeline.set_param(&format!("passes.{index}.flip"), 0.0));
+    assert!(pipeline.set_param(&format!("passes.{index}.amount"), 1.0));
+    let tinted = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let expected: Vec<[f32; 4]> = plain.iter().map(|[.., a]| [1.0, 0.0, 0.0, *a]).collect();
+    golden::compare(&expected, &tinted, 32, 1.0 / 255.0).unwrap();
+
+    // A broken file runs as a passthrough
+    pipeline.add_isf_pass(&device, "Broken", "/*{ \"INPUTS\": [ }*/");
+    let errors = pipeline.take_shader_errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].label, "Broken");
+}
@@ -0,0 +1,64 @@
+// tests/macros.rs
+//
+// Macro controls
+
+use nnpipe::golden;
+use nnpipe::{MacroTarget, Nnpipe};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+#[test]
+fn macros_drive_their_targets() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping macros test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.add_custom_pass(&device, "Tint", TINT, &[("amount", 1.0)]);
+
+    let targets = vec![
+        MacroTarget::new("bloom.intensity", 1.0, 3.0),
+        MacroTarget::new("bloom.threshold", 0.8, 0.4),
+        MacroTarget::new("passes.0.amount", 0.0, 1.0).with_curve(2.0),
+        MacroTarget::new("passes.5.amount", 0.0, 1.0),
+    ];
+    pipeline.add_macro("lift", targets.clone());
+    assert_eq!(pipeline.macro_targets("lift"), Some(targets.as_slice()));
+    assert_eq!(pipeline.macro_value("lift"), Some(0.0));
+    // Registering leaves the targets alone
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(1.0));
+
+    assert!(pipeline.set_macro("lift", 0.5));
+    assert_eq!(pipeline.macro_value("lift"), Some(0.5));
+    assert_eq!(pipeline.bloom_intensity, 2.0);
+    assert!((pipeline.get_param("bloom.threshold").unwrap() - 0.6).abs() < 1e-6);
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(0.25));
+    assert!(!pipeline.set_macro("missing", 0.5));
+
+    // Macros are params of their own, clamped to 0-1
+    assert!(pipeline
+        .list_params()
+        .ends_with(&["macros.lift".to_string()]));
+    assert!(pipeline.set_param("macros.lift", 2.0));
+    assert_eq!(pipeline.get_param("macros.lift"), Some(1.0));
+    assert_eq!(pipeline.bloom_intensity, 3.0);
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(1.0));
+
+    assert!(pipeline.remove_macro("lift"));
+    assert!(!pipeline.remove_macro("lift"));
+    assert_eq!(pipeline.get_param("macros.lift"), None);
+    assert_eq!(pipeline.bloom_intensity, 3.0);
+}
@@ -0,0 +1,53 @@
+// tests/magnifier.rs
+//
+// Magnifier tests
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{BurnInCorner, Magnifier, Nnpipe};
+
+#[test]
+fn magnifier_blows_up_pixels_into_an_inset() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping magnifier test: no adapter");
+        return;
+    };
+    let (width, height) = (128, 64);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32| pixels[(y * width + x) as usize];
+    let read = |pipeline: &Nnpipe| {
+        futures::executor::block_on(pipeline.read_magnified_pixel(&device, &queue)).unwrap()
+    };
+    assert_eq!(read(&pipeline), None);
+
+    // A 32 pixel inset 8 pixels in from the top left, 4 pixels per magnified one
+    let magnifier = Magnifier {
+        center: [100.5, 40.5],
+        zoom: 4.0,
+        size: 32,
+        corner: BurnInCorner::TopLeft,
+    };
+    pipeline.set_magnifier(&device, Some(magnifier));
+    assert_eq!(pipeline.magnifier(), Some(magnifier));
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    for (x, y, source) in [(24, 24, (100, 40)), (28, 24, (101, 40)), (20, 20, (99, 39))] {
+        let expected = pixel(&plain, source.0, source.1);
+        assert_eq!(pixel(&output, x, y), expected, "at {x}, {y}");
+    }
+    assert_eq!(pixel(&output, 8, 20), [1.0; 4]);
+    for (x, y) in [(7, 20), (40, 20), (100, 40), (127, 63)] {
+        assert_eq!(pixel(&output, x, y), pixel(&plain, x, y), "at {x}, {y}");
+    }
+    assert_eq!(read(&pipeline), Some(pixel(&plain, 100, 40)));
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.magnifier(), Some(magnifier));
+
+    pipeline.set_magnifier(&device, None);
+    assert_eq!(pipeline.magnifier(), None);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 0.0).unwrap();
+}
@@ -0,0 +1,132 @@
+// tests/matte.rs
+//
+// Tests of the scene matte
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{Matte, MatteSource, Nnpipe};
+
+const WIDTH: u32 = 8;
+const HEIGHT: u32 = 2;
+
+// Writes the matte it sees at binding 8
+const SHOW_MATTE: &str = "
+@group(0) @binding(8) var matte_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(matte_tex, vec2<i32>(pos.xy), 0);
+}
+";
+
+// Grey brightening from left to right while the alpha fades out
+fn ramp(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([WIDTH, HEIGHT])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba8Unorm)
+        .build(device);
+    let data: Vec<[u8; 4]> = (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let v = (i % WIDTH * 32) as u8;
+            [v, v, v, 255 - v]
+        })
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(WIDTH * 4),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[test]
+fn matte_thresholds_alpha_or_luminance() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping matte test: no adapter");
+        return;
+    };
+    let input = ramp(&device, &queue);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    let read = |pipeline: &Nnpipe| {
+        golden::render(pipeline, &device, &queue, &input).unwrap();
+        futures::executor::block_on(pipeline.read_matte(&device, &queue)).unwrap()
+    };
+    assert_eq!(read(&pipeline), None);
+
+    // A hard edge on the alpha
+    let hard = Matte {
+        feather: 0.0,
+        ..Default::default()
+    };
+    pipeline.set_matte(&device, Some(hard));
+    assert_eq!(pipeline.matte(), Some(hard));
+    assert!(pipeline.matte_view().is_some());
+    let matte = read(&pipeline).unwrap();
+    for (i, value) in matte.iter().enumerate() {
+        let alpha = (255 - i as u32 % WIDTH * 32) as f32 / 255.0;
+        let expected = if alpha >= 0.5 { 1.0 } else { 0.0 };
+        assert_eq!(*value, expected, "at {i}");
+    }
+
+    // A feathered, inverted edge on the luminance
+    let soft = Matte {
+        source: MatteSource::Luminance,
+        threshold: 0.5,
+        feather: 0.4,
+        invert: true,
+    };
+    pipeline.set_matte(&device, Some(soft));
+    let matte = read(&pipeline).unwrap();
+    for (i, value) in matte.iter().enumerate() {
+        let luminance = (i as u32 % WIDTH * 32) as f32 / 255.0;
+        let expected = 1.0 - smoothstep(0.3, 0.7, luminance);
+        assert!(
+            (value - expected).abs() < 2e-3,
+            "at {i}: {value} vs {expected}"
+        );
+    }
+    assert!(matte.iter().any(|value| *value > 0.0 && *value < 1.0));
+
+    pipeline.set_matte(&device, None);
+    assert_eq!(pipeline.matte(), None);
+    assert_eq!(read(&pipeline), None);
+}
+
+#[test]
+fn passes_see_the_matte() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping matte test: no adapter");
+        return;
+    };
+    let input = ramp(&device, &queue);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.add_custom_pass(&device, "Show Matte", SHOW_MATTE, &[]);
+
+    // Black without a matte
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(frame.iter().all(|pixel| *pixel == [0.0; 4]));
+
+    // Passes added before the matte are rebound to it, and it survives resizes
+    pipeline.set_matte(&device, Some(Matte::default()));
+    pipeline.resize(&device, &queue, WIDTH, HEIGHT).unwrap();
+    assert_eq!(pipeline.matte(), Some(Matte::default()));
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let matte = futures::executor::block_on(pipeline.read_matte(&device, &queue))
+        .unwrap()
+        .unwrap();
+    assert!(matte.contains(&0.0) && matte.contains(&1.0));
+    for (pixel, value) in frame.iter().zip(&matte) {
+        assert_eq!(*pixel, [*value; 4]);
+    }
+}
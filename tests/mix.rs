@@ -0,0 +1,81 @@
+// tests/mix.rs
+//
+// Wet/dry mix of effect passes
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::Nnpipe;
+
+const INVERT_FRAGMENT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let src = textureLoad(src_tex, vec2<i32>(position.xy), 0);
+    return vec4<f32>(1.0 - src.rgb, src.a);
+}
+";
+
+const INVERT_COMPUTE: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(7) var dst_tex: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= textureDimensions(dst_tex)) {
+        return;
+    }
+    let src = textureLoad(src_tex, id.xy, 0);
+    textureStore(dst_tex, id.xy, vec4<f32>(1.0 - src.rgb, src.a));
+}
+";
+
+#[test]
+fn mix_blends_passes_with_their_input() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping mix test: no adapter");
+        return;
+    };
+    let (width, height) = (30, 20);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let mixed = |amount: f32| -> Vec<[f32; 4]> {
+        plain
+            .iter()
+            .map(|&[r, g, b, a]| {
+                let mix = |c: f32| c + (1.0 - 2.0 * c) * amount;
+                [mix(r), mix(g), mix(b), a]
+            })
+            .collect()
+    };
+
+    for (label, source, compute) in [
+        ("Invert", INVERT_FRAGMENT, false),
+        ("Invert Compute", INVERT_COMPUTE, true),
+    ] {
+        let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+        pipeline.set_bloom_intensity(0.0);
+        let pass = if compute {
+            pipeline.add_compute_pass(&device, label, source, &[])
+        } else {
+            pipeline.add_custom_pass(&device, label, source, &[])
+        };
+        assert!(pipeline.take_shader_errors().is_empty());
+        assert_eq!(pipeline.custom_pass(pass).unwrap().mix, 1.0);
+
+        for amount in [0.0, 0.25, 0.5, 1.0] {
+            pipeline.custom_pass_mut(pass).unwrap().mix = amount;
+            let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+            golden::compare(&mixed(amount), &output, width, 2.0 / 255.0)
+                .unwrap_or_else(|error| panic!("{label} at {amount}: {error:?}"));
+        }
+
+        // Mixed passes ahead of others feed them the blend
+        let again = pipeline.add_custom_pass(&device, "Invert Again", INVERT_FRAGMENT, &[]);
+        assert!(pipeline.set_param(&format!("passes.{pass}.mix"), 0.5));
+        assert!(pipeline.set_param(&format!("passes.{again}.mix"), 0.0));
+        let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+        golden::compare(&mixed(0.5), &output, width, 2.0 / 255.0).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+// tests/morph.rs
+//
+// Morphs between presets
+
+#![cfg(feature = "config")]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Easing, LookupTexture, Nnpipe, PipelineConfig};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+    hue: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+fn lookup(value: f32) -> LookupTexture {
+    LookupTexture {
+        width: 2,
+        height: 1,
+        data: vec![[value, value, value, 1.0]; 2],
+    }
+}
+
+#[test]
+fn easings_run_from_0_to_1() {
+    for easing in [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ] {
+        assert_eq!(easing.apply(-1.0), 0.0, "{easing:?}");
+        assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+        assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+        assert_eq!(easing.apply(2.0), 1.0, "{easing:?}");
+    }
+    assert_eq!(Easing::EaseIn.apply(0.5), 0.25);
+    assert_eq!(Easing::EaseOut.apply(0.5), 0.75);
+    assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+}
+
+#[test]
+fn presets_morph_over_time() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping morph test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.set_bloom_intensity(1.0);
+    let tint = pipeline.add_custom_pass(&device, "Tint", TINT, &[("amount", 1.0), ("hue", 0.0)]);
+    pipeline
+        .set_pass_lookup(&device, &queue, tint, lookup(0.0))
+        .unwrap();
+    let faded = pipeline.add_custom_pass(&device, "Faded", TINT, &[("amount", 1.0)]);
+    pipeline.custom_pass_mut(faded).unwrap().enabled = false;
+
+    let preset = PipelineConfig::from_toml(
+        "
+        [bloom]
+        intensity = 3.0
+        upsample_filter = \"tent\"
+
+        [[passes]]
+        label = \"Tint\"
+        enabled = false
+        params = { amount = 0.0 }
+        lookup = { width = 2, height = 1, data = [[1.0, 1.0, 1.0, 1.0], [1.0, 1.0, 1.0, 1.0]] }
+
+        [[passes]]
+        label = \"Faded\"
+        enabled = true
+        mix = 0.5
+
+        [[passes]]
+        label = \"Missing\"
+        ",
+    )
+    .unwrap();
+    let result = pipeline.morph_to_preset(&preset, 2.0, Easing::Linear);
+    assert!(result.unwrap_err().message.contains("'Missing'"));
+    assert!(pipeline.morphing());
+    // Passes turned on fade in from their input
+    assert!(pipeline.custom_pass(faded).unwrap().enabled);
+
+    pipeline.update(&device, &queue, 1.0);
+    assert!(pipeline.morphing());
+    assert_eq!(pipeline.bloom_intensity, 2.0);
+    let pass = pipeline.custom_pass(tint).unwrap();
+    assert_eq!(pass.param("amount"), Some(0.5));
+    assert_eq!(pass.param("hue"), Some(0.0));
+    assert!(pass.enabled);
+    assert_eq!(pass.mix, 0.5);
+    assert_eq!(pass.lookup(), Some(&lookup(0.5)));
+    assert_eq!(pipeline.custom_pass(faded).unwrap().mix, 0.25);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 64, 48);
+    golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Overshooting ends on the preset, switching what can't be interpolated
+    pipeline.update(&device, &queue, 5.0);
+    assert!(!pipeline.morphing());
+    assert_eq!(pipeline.bloom_intensity, 3.0);
+    assert_eq!(
+        pipeline.config().bloom.upsample_filter,
+        preset.bloom.upsample_filter
+    );
+    let pass = pipeline.custom_pass(tint).unwrap();
+    assert_eq!(pass.param("amount"), Some(0.0));
+    assert!(!pass.enabled);
+    assert_eq!(pass.mix, 1.0);
+    assert_eq!(pass.lookup(), Some(&lookup(1.0)));
+    let pass = pipeline.custom_pass(faded).unwrap();
+    assert!(pass.enabled);
+    assert_eq!(pass.mix, 0.5);
+
+    // Later updates leave the settings alone
+    pipeline.set_bloom_intensity(1.0);
+    pipeline.update(&device, &queue, 1.0);
+    assert_eq!(pipeline.bloom_intensity, 1.0);
+
+    // A morph without a duration lands on the next update
+    let mut back = PipelineConfig::default();
+    back.bloom.intensity = Some(0.5);
+    pipeline
+        .morph_to_preset(&back, 0.0, Easing::EaseInOut)
+        .unwrap();
+    pipeline.update(&device, &queue, 0.0);
+    assert!(!pipeline.morphing());
+    assert_eq!(pipeline.bloom_intensity, 0.5);
+}
@@ -0,0 +1,166 @@
+// tests/output.rs
+//
+// Tests of the scaler drawing the frame into targets of other sizes
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{MaskShape, Nnpipe, OutputMask, OutputScaling};
+
+#[test]
+fn point_sampled_scaling_keeps_pixels_crisp() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping output test: no adapter");
+        return;
+    };
+    let (width, height) = (4, 2);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(pipeline.scaler().scaling(), OutputScaling::Linear);
+
+    // A second pipeline's output texture serves as a larger target to read back
+    let (target_width, target_height) = (13, 7);
+    let target = Nnpipe::new(&device, target_width, target_height, 1).unwrap();
+    let scale = |pipeline: &Nnpipe| {
+        let input_view = input.view().build();
+        pipeline.process_texture(&device, &queue, &input_view, &target.output_view);
+        futures::executor::block_on(target.read_output(&device, &queue)).unwrap()
+    };
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32, width: u32| pixels[(y * width + x) as usize];
+    let close = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 2e-3);
+
+    // Contained at 3.25 times, with blocks of three or four target pixels
+    pipeline.scaler_mut().set_scaling(OutputScaling::Nearest);
+    let output = scale(&pipeline);
+    for y in 0..6 {
+        for x in 0..target_width {
+            let source = [(x as f32 + 0.5) / 3.25, (y as f32 + 0.25) / 3.25];
+            let expected = pixel(&plain, source[0] as u32, source[1] as u32, width);
+            let actual = pixel(&output, x, y, target_width);
+            assert!(close(actual, expected), "at {x}, {y}: {actual:?}");
+        }
+    }
+
+    // Snapped to three times, from the top left corner, with the rest left as bars
+    pipeline.scaler_mut().set_scaling(OutputScaling::Integer);
+    assert_eq!(pipeline.scaler().scaling(), OutputScaling::Integer);
+    let output = scale(&pipeline);
+    for y in 0..target_height {
+        for x in 0..target_width {
+            let expected = if x < 12 && y < 6 {
+                pixel(&plain, x / 3, y / 3, width)
+            } else {
+                [0.0, 0.0, 0.0, 1.0]
+            };
+            let actual = pixel(&output, x, y, target_width);
+            assert!(close(actual, expected), "at {x}, {y}: {actual:?}");
+        }
+    }
+
+    // Bilinear filtering blends neighbouring pixels
+    pipeline.scaler_mut().set_scaling(OutputScaling::Linear);
+    let output = scale(&pipeline);
+    let blended = pixel(&output, 6, 1, target_width);
+    assert!(!plain.iter().any(|&pixel| close(pixel, blended)));
+
+    // Survives resizes
+    pipeline.scaler_mut().set_scaling(OutputScaling::Integer);
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.scaler().scaling(), OutputScaling::Integer);
+}
+
+#[test]
+fn masks_shape_the_output() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping output test: no adapter");
+        return;
+    };
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 4, 4);
+    let mut pipeline = Nnpipe::new(&device, 4, 4, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let background = [1.0, 0.0, 1.0, 1.0];
+    pipeline.scaler_mut().set_background(background);
+    assert_eq!(pipeline.scaler().mask(), OutputMask::default());
+
+    let size = 16;
+    let target = Nnpipe::new(&device, size, size, 1).unwrap();
+    let scale = |pipeline: &Nnpipe| {
+        let input_view = input.view().build();
+        pipeline.process_texture(&device, &queue, &input_view, &target.output_view);
+        futures::executor::block_on(target.read_output(&device, &queue)).unwrap()
+    };
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32| pixels[(y * size + x) as usize];
+    let close = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 4e-3);
+    let blend =
+        |a: [f32; 4], b: [f32; 4], t: f32| std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t);
+    let plain = scale(&pipeline);
+    let set_mask = |pipeline: &mut Nnpipe, shape, feather, invert| {
+        let mask = OutputMask {
+            shape,
+            feather,
+            invert,
+        };
+        pipeline.scaler_mut().set_mask(mask);
+        scale(pipeline)
+    };
+
+    // Circles and rounded corners cut the corners away but keep the edges' middles
+    for shape in [MaskShape::Circle, MaskShape::RoundedRect { radius: 4.0 }] {
+        let output = set_mask(&mut pipeline, shape, 0.0, false);
+        for (x, y) in [(0, 0), (15, 0), (0, 15), (15, 15)] {
+            assert_eq!(pixel(&output, x, y), background, "{shape:?} at {x}, {y}");
+        }
+        for (x, y) in [(8, 8), (8, 1), (14, 7)] {
+            let expected = pixel(&plain, x, y);
+            assert!(
+                close(pixel(&output, x, y), expected),
+                "{shape:?} at {x}, {y}"
+            );
+        }
+    }
+
+    // Inverted, only the corners are left
+    let output = set_mask(&mut pipeline, MaskShape::Ellipse, 0.0, true);
+    assert_eq!(pixel(&output, 8, 8), background);
+    assert!(close(pixel(&output, 0, 0), pixel(&plain, 0, 0)));
+
+    // Feathering fades in from the edge over four pixels
+    let output = set_mask(
+        &mut pipeline,
+        MaskShape::RoundedRect { radius: 0.0 },
+        4.0,
+        false,
+    );
+    for x in 0..5 {
+        let expected = blend(background, pixel(&plain, x, 8), (x + 1) as f32 / 5.0);
+        let actual = pixel(&output, x, 8);
+        assert!(close(actual, expected), "at {x}: {actual:?}");
+    }
+
+    // Texture masks show everything until a texture is set, then its red coverage,
+    // here rising from left to right
+    let texture = MaskShape::Texture { distance: false };
+    let output = set_mask(&mut pipeline, texture, 0.0, false);
+    golden::compare(&plain, &output, size, 0.0).unwrap();
+    let mask = TestPattern::Gradient.create_texture(&device, &queue, size, size);
+    let mask_view = mask.view().build();
+    pipeline
+        .scaler_mut()
+        .set_mask_texture(&device, Some(&mask_view));
+    assert!(pipeline.scaler().has_mask_texture());
+    let covered = scale(&pipeline);
+    for x in [0, 5, 15] {
+        let expected = blend(background, pixel(&plain, x, 3), (x as f32 + 0.5) / 16.0);
+        let actual = pixel(&covered, x, 3);
+        assert!(close(actual, expected), "at {x}: {actual:?}");
+    }
+
+    // Survives resizes, texture and all
+    pipeline.resize(&device, &queue, 4, 4).unwrap();
+    assert_eq!(pipeline.scaler().mask().shape, texture);
+    assert!(pipeline.scaler().has_mask_texture());
+    golden::compare(&covered, &scale(&pipeline), size, 0.0).unwrap();
+
+    pipeline.scaler_mut().set_mask_texture(&device, None);
+    golden::compare(&plain, &scale(&pipeline), size, 0.0).unwrap();
+}
@@ -0,0 +1,96 @@
+// tests/params.rs
+//
+// String-keyed parameter tests
+
+use nnpipe::golden;
+use nnpipe::Nnpipe;
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+    hue: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+#[test]
+fn paths_reach_bloom_and_passes() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping params test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.add_custom_pass(&device, "Tint", TINT, &[("amount", 1.0), ("hue", 0.0)]);
+
+    assert!(pipeline.set_param("bloom.intensity", 2.0));
+    assert_eq!(pipeline.bloom_intensity, 2.0);
+    assert_eq!(pipeline.get_param("bloom.intensity"), Some(2.0));
+
+    assert!(pipeline.set_param("passes.0.amount", 0.5));
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(0.5));
+    assert!(pipeline.set_param("passes.0.enabled", 0.0));
+    assert!(!pipeline.custom_pass(0).unwrap().enabled);
+    assert_eq!(pipeline.get_param("passes.0.enabled"), Some(0.0));
+    assert!(pipeline.set_param("passes.0.mix", 0.25));
+    assert_eq!(pipeline.custom_pass(0).unwrap().mix, 0.25);
+    assert_eq!(pipeline.get_param("passes.0.mix"), Some(0.25));
+
+    for path in [
+        "bloom.missing",
+        "passes.1.amount",
+        "passes.0.missing",
+        "amount",
+    ] {
+        assert!(!pipeline.set_param(path, 1.0), "{path}");
+        assert_eq!(pipeline.get_param(path), None, "{path}");
+    }
+
+    // Every listed path can be read and written
+    let paths = pipeline.list_params();
+    assert!(paths.contains(&"bloom.threshold".to_string()));
+    assert!(paths.ends_with(&[
+        "passes.0.enabled".to_string(),
+        "passes.0.mix".to_string(),
+        "passes.0.amount".to_string(),
+        "passes.0.hue".to_string(),
+    ]));
+    for path in &paths {
+        let value = pipeline.get_param(path).unwrap();
+        assert!(pipeline.set_param(path, value), "{path}");
+    }
+}
+
+#[test]
+fn channel_thresholds_have_paths() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping params test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.set_brightness_threshold(0.6);
+    let paths = pipeline.list_params();
+    for path in [
+        "bloom.threshold_r",
+        "bloom.threshold_g",
+        "bloom.threshold_b",
+    ] {
+        assert!(paths.contains(&path.to_string()), "{path}");
+        // The luminance threshold until they're set
+        assert_eq!(pipeline.get_param(path), Some(0.6), "{path}");
+    }
+
+    assert!(pipeline.set_param("bloom.threshold_g", 0.2));
+    assert_eq!(pipeline.channel_thresholds, Some([0.6, 0.2, 0.6]));
+    assert!(pipeline.set_param("bloom.threshold_b", 0.9));
+    assert_eq!(pipeline.channel_thresholds, Some([0.6, 0.2, 0.9]));
+    assert_eq!(pipeline.get_param("bloom.threshold_b"), Some(0.9));
+    assert_eq!(pipeline.brightness_threshold, 0.6);
+}
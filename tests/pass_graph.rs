@@ -0,0 +1,170 @@
+// tests/pass_graph.rs
+//
+// Tests of pass graphs: scheduling, intermediate textures and params
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, PassGraph, PassGraphError};
+
+const SCALE: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    gain: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.gain, color.a);
+}
+";
+
+const ADD: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(1) @binding(0) var other_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    let other = textureLoad(other_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb + other.rgb, color.a);
+}
+";
+
+fn scaled(pixels: &[[f32; 4]], gain: f32) -> Vec<[f32; 4]> {
+    pixels
+        .iter()
+        .map(|[r, g, b, a]| [r * gain, g * gain, b * gain, *a])
+        .collect()
+}
+
+#[test]
+fn diamond_graph_joins_its_branches() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping pass graph test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 32, 24);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Added out of order, with a pass nothing reads
+    let graph = PassGraph::new()
+        .pass("Join", ADD, &[], &["left", "right"], "output")
+        .pass("Unused", SCALE, &[("gain", 2.0)], &["input"], "unused")
+        .pass("Left", SCALE, &[("gain", 0.25)], &["input"], "left")
+        .pass("Right", SCALE, &[("gain", 0.5)], &["input"], "right");
+    assert_eq!(graph.schedule(), Ok(vec![2, 3, 0]));
+    pipeline.set_pass_graph(&device, Some(graph)).unwrap();
+    assert!(pipeline.take_shader_errors().is_empty());
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&scaled(&plain, 0.75), &output, 32, 1.0 / 255.0).unwrap();
+
+    let chain = pipeline.chain_graph();
+    let inputs: Vec<_> = chain.inputs("graph.output").map(|node| &node.id).collect();
+    assert_eq!(inputs, ["graph.left", "graph.right"]);
+    assert_eq!(chain.node("graph.output").unwrap().texture, "output");
+    assert!(chain.node("graph.unused").is_none());
+
+    // Params are set by the texture their pass writes, and survive resizes
+    assert!(pipeline.set_graph_param("right", "gain", 0.75));
+    assert!(pipeline.set_graph_param("unused", "gain", 3.0));
+    assert!(!pipeline.set_graph_param("right", "missing", 1.0));
+    pipeline.resize(&device, &queue, 32, 24).unwrap();
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, 32, 1.0 / 255.0).unwrap();
+    assert_eq!(pipeline.pass_graph().unwrap().passes[1].params[0].1, 3.0);
+
+    pipeline.set_pass_graph(&device, None).unwrap();
+    assert!(pipeline.pass_graph().is_none());
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, 32, 1.0 / 255.0).unwrap();
+}
+
+#[test]
+fn graphs_that_cannot_run_are_refused() {
+    let scale = |graph: PassGraph, inputs: &[&str], output| {
+        graph.pass("Scale", SCALE, &[("gain", 1.0)], inputs, output)
+    };
+    assert_eq!(
+        scale(PassGraph::new(), &["input"], "blurred").schedule(),
+        Err(PassGraphError::NoOutput)
+    );
+    assert_eq!(
+        scale(PassGraph::new(), &["missing"], "output").schedule(),
+        Err(PassGraphError::UnknownInput {
+            pass: "Scale".to_string(),
+            input: "missing".to_string(),
+        })
+    );
+    assert_eq!(
+        scale(PassGraph::new(), &[], "output").schedule(),
+        Err(PassGraphError::NoInputs("Scale".to_string()))
+    );
+    let twice = scale(
+        scale(PassGraph::new(), &["input"], "output"),
+        &["input"],
+        "output",
+    );
+    assert_eq!(
+        twice.schedule(),
+        Err(PassGraphError::DuplicateOutput("output".to_string()))
+    );
+    let cycle = PassGraph::new()
+        .pass("A", ADD, &[], &["input", "b"], "a")
+        .pass("B", SCALE, &[], &["a"], "b")
+        .pass("Out", SCALE, &[], &["b"], "output");
+    assert_eq!(
+        cycle.schedule(),
+        Err(PassGraphError::Cycle(vec![
+            "A".to_string(),
+            "B".to_string(),
+            "Out".to_string()
+        ]))
+    );
+}
+
+#[test]
+fn textures_are_shared_once_read_for_the_last_time() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping pass graph test: no adapter");
+        return;
+    };
+    // The input is read by both branches, and freed after the second
+    let diamond = PassGraph::new()
+        .pass("Left", SCALE, &[("gain", 0.25)], &["input"], "left")
+        .pass("Right", SCALE, &[("gain", 0.5)], &["input"], "right")
+        .pass("Join", ADD, &[], &["left", "right"], "output");
+    let allocation = diamond.allocate().unwrap();
+    assert_eq!(allocation.inputs, [vec![0], vec![0], vec![1, 2]]);
+    assert_eq!(allocation.outputs, [Some(1), Some(2), None]);
+    assert_eq!(allocation.textures, 3);
+
+    // A chain of fifteen passes ping-pongs between the input's texture and one more
+    let names: Vec<String> = (0..15).map(|i| format!("step{i}")).collect();
+    let mut chain = PassGraph::new();
+    for i in 0..15 {
+        let input = if i == 0 { "input" } else { &names[i - 1] };
+        let output = if i == 14 { "output" } else { &names[i] };
+        let gain = if i == 7 { 0.5 } else { 1.0 };
+        chain = chain.pass(&names[i], SCALE, &[("gain", gain)], &[input], output);
+    }
+    let allocation = chain.allocate().unwrap();
+    assert_eq!(allocation.textures, 2);
+    assert_eq!(allocation.outputs[..3], [Some(1), Some(0), Some(1)]);
+
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 32, 24);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    pipeline.set_pass_graph(&device, Some(chain)).unwrap();
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&scaled(&plain, 0.5), &output, 32, 1.0 / 255.0).unwrap();
+
+    let graph = pipeline.chain_graph();
+    assert_eq!(graph.node("composite").unwrap().texture, "graph_pool.0");
+    assert_eq!(graph.node("graph.step0").unwrap().texture, "graph_pool.1");
+    assert_eq!(graph.node("graph.step1").unwrap().texture, "graph_pool.0");
+}
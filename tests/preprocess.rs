@@ -0,0 +1,80 @@
+// tests/preprocess.rs
+//
+// Shader preprocessor tests
+
+use nnpipe::golden;
+use nnpipe::{Nnpipe, ShaderPreprocessor};
+
+#[test]
+fn directives_expand() {
+    let preprocessor = ShaderPreprocessor::new()
+        .with_include("lib.wgsl", "#include \"inner.wgsl\"\nfn lib() {}")
+        .with_include("inner.wgsl", "fn inner() {}")
+        .with_define("GAIN", "2.0");
+    let source = "
+#include \"lib.wgsl\"
+#include \"inner.wgsl\"
+#define TAPS 9
+let taps = TAPS * GAIN; // TAPS_MAX stays
+#ifdef TAPS
+tapped
+#ifndef GAIN
+ungained
+#endif
+#else
+untapped
+#endif
+";
+    let output = preprocessor.process(source).unwrap();
+    let lines: Vec<&str> = output.lines().filter(|line| !line.is_empty()).collect();
+    assert_eq!(
+        lines,
+        [
+            "fn inner() {}",
+            "fn lib() {}",
+            "let taps = 9 * 2.0; // TAPS_MAX stays",
+            "tapped",
+        ]
+    );
+
+    for source in [
+        "#include \"missing.wgsl\"",
+        "#include lib.wgsl",
+        "#ifdef GAIN",
+        "#endif",
+        "#pragma once",
+    ] {
+        let error = preprocessor.process(source).unwrap_err();
+        assert!(error.message.starts_with("shader:1: "), "{error}");
+    }
+}
+
+#[test]
+fn passes_include_shared_code() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping preprocessor test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 16, 16, 1).unwrap();
+    let source = "
+#include \"nnpipe/color.wgsl\"
+#include \"nnpipe/noise.wgsl\"
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    let grain = f32(pcg_permute(u32(pos.x)) & 1u) * 0.01;
+    return vec4<f32>(vec3<f32>(luma(color.rgb) + grain), color.a);
+}
+";
+    pipeline.add_custom_pass(&device, "Grey", source, &[]);
+    assert!(pipeline.take_shader_errors().is_empty());
+    assert_eq!(pipeline.custom_pass(0).unwrap().source(), source);
+
+    pipeline.add_custom_pass(&device, "Broken", "#include \"nope.wgsl\"", &[]);
+    let errors = pipeline.take_shader_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("no include 'nope.wgsl'"));
+}
@@ -0,0 +1,25 @@
+// tests/quality.rs
+//
+// Quality presets
+
+use nnpipe::golden;
+use nnpipe::{Nnpipe, PipelineCache, QualityPreset};
+
+#[test]
+fn pipelines_at_a_quality_share_a_cache() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping quality test: no adapter");
+        return;
+    };
+    let cache = PipelineCache::new();
+    let first =
+        Nnpipe::with_quality_and_cache(&device, 32, 24, 1, QualityPreset::Low, &cache).unwrap();
+    assert_eq!(first.quality().preset, QualityPreset::Low);
+    let count = cache.pipeline_count();
+    assert!(count > 0);
+
+    let second =
+        Nnpipe::with_quality_and_cache(&device, 64, 48, 1, QualityPreset::Low, &cache).unwrap();
+    assert_eq!(second.quality().preset, QualityPreset::Low);
+    assert_eq!(cache.pipeline_count(), count);
+}
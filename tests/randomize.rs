@@ -0,0 +1,91 @@
+// tests/randomize.rs
+//
+// Randomized params
+
+#![cfg(feature = "config")]
+
+use nnpipe::golden;
+use nnpipe::{Nnpipe, PipelineConfig, RandomConstraints, RandomScope};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+    hue: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+fn pipeline(device: &nannou::wgpu::Device) -> Nnpipe {
+    let mut pipeline = Nnpipe::new(device, 32, 24, 1).unwrap();
+    pipeline.add_custom_pass(device, "Tint", TINT, &[("amount", 1.0), ("hue", 0.25)]);
+    pipeline.add_custom_pass(device, "Tint Again", TINT, &[("amount", 2.0), ("hue", 0.5)]);
+    pipeline
+}
+
+#[test]
+fn rolls_stay_within_constraints() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping randomize test: no adapter");
+        return;
+    };
+    let mut pipeline = pipeline(&device);
+    let constraints = RandomConstraints::default()
+        .with_range("bloom.intensity", 1.0..2.0)
+        .with_range("passes.0.amount", 0.0..0.5)
+        .with_range("passes.0.mix", 0.5..1.0)
+        .with_seed(1);
+
+    let preset = pipeline.randomize(&RandomScope::All, &constraints);
+    let intensity = preset.bloom.intensity.unwrap();
+    assert!((1.0..2.0).contains(&intensity));
+    assert_eq!(pipeline.bloom_intensity, intensity);
+    assert_eq!(preset.bloom.threshold, None);
+    assert_eq!(preset.passes.len(), 1);
+    assert_eq!(preset.passes[0].index, Some(0));
+    assert_eq!(preset.passes[0].label.as_deref(), Some("Tint"));
+    let amount = preset.passes[0].params["amount"];
+    assert!((0.0..0.5).contains(&amount));
+    assert_eq!(preset.passes[0].params.get("hue"), None);
+    assert!((0.5..1.0).contains(&preset.passes[0].mix.unwrap()));
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(amount));
+    assert_eq!(pipeline.get_param("passes.0.hue"), Some(0.25));
+
+    // Seeds find results again
+    let mut again = self::pipeline(&device);
+    assert_eq!(again.randomize(&RandomScope::All, &constraints), preset);
+
+    // Only the selected passes, around their values
+    let spread = RandomConstraints::default().with_spread(0.5);
+    let rolled = pipeline.randomize(&RandomScope::Passes(vec![1]), &spread);
+    assert_eq!(rolled.bloom, Default::default());
+    assert_eq!(rolled.passes.len(), 1);
+    assert_eq!(rolled.passes[0].index, Some(1));
+    assert_eq!(rolled.passes[0].mix, None);
+    let amount = pipeline.get_param("passes.1.amount").unwrap();
+    assert!((1.0..=3.0).contains(&amount));
+    let hue = pipeline.get_param("passes.1.hue").unwrap();
+    assert!((0.25..=0.75).contains(&hue));
+    assert_eq!(pipeline.bloom_intensity, intensity);
+    assert!(pipeline.custom_pass(1).unwrap().enabled);
+
+    let rolled = pipeline.randomize(&RandomScope::Bloom, &constraints);
+    assert!(rolled.passes.is_empty());
+
+    // Promoted to a preset
+    let saved = PipelineConfig::from_toml(&preset.to_toml().unwrap()).unwrap();
+    let mut loaded = self::pipeline(&device);
+    loaded.apply_config(&saved).unwrap();
+    assert_eq!(loaded.bloom_intensity, intensity);
+    assert_eq!(
+        loaded.custom_pass(0).unwrap().mix,
+        preset.passes[0].mix.unwrap()
+    );
+}
@@ -0,0 +1,86 @@
+// tests/reflect.rs
+//
+// Param reflection tests
+
+use nnpipe::golden;
+use nnpipe::{Nnpipe, ParamDescriptor, ParamType};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    tint: vec3<f32>,     // default: 1.0, 0.8, 0.6
+    amount: f32,         // default: 0.5
+    offset: vec2<f32>,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy + params.offset), 0);
+    return vec4<f32>(color.rgb * params.tint * params.amount, color.a);
+}
+";
+
+#[test]
+fn struct_members_become_params() {
+    let descriptors = ParamDescriptor::reflect("Tint", TINT).unwrap();
+    assert_eq!(
+        descriptors
+            .iter()
+            .map(|d| (d.name.as_str(), d.ty))
+            .collect::<Vec<_>>(),
+        [
+            ("tint", ParamType::Vec3),
+            ("amount", ParamType::F32),
+            ("offset", ParamType::Vec2),
+        ]
+    );
+    assert_eq!(descriptors[0].default, [1.0, 0.8, 0.6]);
+    assert_eq!(descriptors[2].default, [0.0, 0.0]);
+
+    // No params uniform, no params
+    let plain = "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }";
+    assert_eq!(ParamDescriptor::reflect("Plain", plain).unwrap(), []);
+
+    for source in [
+        // Padding between members
+        TINT.replace("amount: f32,         // default: 0.5", ""),
+        // Integers
+        TINT.replace("amount: f32", "amount: u32"),
+        // A default with the wrong count
+        TINT.replace("1.0, 0.8, 0.6", "1.0, 0.8"),
+        // Not WGSL
+        TINT.replace("fn fs_main", "fn"),
+    ] {
+        let error = ParamDescriptor::reflect("Tint", &source).unwrap_err();
+        assert_eq!(error.label, "Tint");
+    }
+}
+
+#[test]
+fn reflected_params_reach_the_registry() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping reflection test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    let index = pipeline.add_reflected_pass(&device, "Tint", TINT);
+    assert!(pipeline.take_shader_errors().is_empty());
+    assert_eq!(pipeline.get_param("passes.0.amount"), Some(0.5));
+    assert_eq!(pipeline.get_param("passes.0.tint.y"), Some(0.8));
+    assert!(pipeline.set_param("passes.0.offset.x", 2.0));
+    assert!(pipeline.list_params().ends_with(&[
+        "passes.0.offset.x".to_string(),
+        "passes.0.offset.y".to_string(),
+    ]));
+    assert_eq!(pipeline.custom_pass(index).unwrap().params().len(), 6);
+
+    // An unsupported struct runs as a passthrough
+    let integers = TINT.replace("amount: f32", "amount: u32");
+    let index = pipeline.add_reflected_pass(&device, "Integers", &integers);
+    assert_eq!(pipeline.take_shader_errors().len(), 1);
+    assert!(pipeline.custom_pass(index).unwrap().params().is_empty());
+    let input = golden::TestPattern::Dots.create_texture(&device, &queue, 64, 48);
+    golden::render(&pipeline, &device, &queue, &input).unwrap();
+}
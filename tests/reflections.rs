@@ -0,0 +1,195 @@
+// tests/reflections.rs
+//
+// Screen-space reflection tests
+
+use nannou::prelude::Mat4;
+use nannou::wgpu;
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, Reflections};
+
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 24;
+
+// Depth of a wall facing the camera, everywhere
+fn depth_view(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::TextureView {
+    let texture = wgpu::TextureBuilder::new()
+        .size([WIDTH, HEIGHT])
+        .usage(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+        .format(wgpu::TextureFormat::Depth32Float)
+        .build(device);
+    let view = texture.view().build();
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Depth clear"),
+        color_attachments: &[],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: &view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(0.5),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    });
+    queue.submit(Some(encoder.finish()));
+    view
+}
+
+// Normals facing up on the bottom half, a floor, and toward the camera on the top
+fn normal_view(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::TextureView {
+    let texture = wgpu::TextureBuilder::new()
+        .size([WIDTH, HEIGHT])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba8Unorm)
+        .build(device);
+    let data: Vec<[u8; 4]> = (0..WIDTH * HEIGHT)
+        .map(|i| {
+            if i / WIDTH >= HEIGHT / 2 {
+                [128, 255, 128, 0]
+            } else {
+                [128, 128, 255, 0]
+            }
+        })
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(WIDTH * 4),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture.view().build()
+}
+
+fn brightness(pixels: &[[f32; 4]]) -> f32 {
+    pixels.iter().map(|[r, g, b, _]| r + g + b).sum()
+}
+
+#[test]
+fn floors_reflect_the_scene() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping reflection test: no adapter");
+        return;
+    };
+    let input = TestPattern::Gradient.create_texture(&device, &queue, WIDTH, HEIGHT);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let aspect = WIDTH as f32 / HEIGHT as f32;
+    let reflections = Reflections {
+        projection: Mat4::perspective_rh(1.0, aspect, 0.1, 100.0).to_cols_array_2d(),
+        intensity: 1.0,
+        max_distance: 2.0,
+        steps: 16,
+        thickness: 1.0,
+        ..Reflections::default()
+    };
+    let depth = depth_view(&device, &queue);
+    let normals = normal_view(&device, &queue);
+    pipeline.enable_reflections(&device, &depth, &normals, reflections);
+    assert_eq!(pipeline.reflections(), Some(reflections));
+    let reflected = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // The wall reflects nothing, the floor brightens
+    let half = (WIDTH * HEIGHT / 2) as usize;
+    golden::compare(&plain[..half], &reflected[..half], WIDTH, 1.0 / 255.0).unwrap();
+    assert!(brightness(&reflected[half..]) > brightness(&plain[half..]) + 1.0);
+
+    // Without intensity, nothing is added
+    pipeline.set_reflections(Reflections {
+        intensity: 0.0,
+        ..reflections
+    });
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, WIDTH, 1.0 / 255.0).unwrap();
+
+    // The buffers are kept through resizes
+    pipeline.set_reflections(reflections);
+    pipeline.resize(&device, &queue, WIDTH, HEIGHT).unwrap();
+    assert_eq!(pipeline.reflections(), Some(reflections));
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&reflected, &output, WIDTH, 1.0 / 255.0).unwrap();
+
+    pipeline.disable_reflections(&device);
+    assert_eq!(pipeline.reflections(), None);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, WIDTH, 1.0 / 255.0).unwrap();
+}
+
+#[test]
+fn reflection_settings_are_params() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping reflection test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    assert!(!pipeline.set_param("reflections.intensity", 1.0));
+    assert_eq!(pipeline.get_param("reflections.intensity"), None);
+    assert!(!pipeline
+        .list_params()
+        .iter()
+        .any(|path| path.starts_with("reflections.")));
+
+    let depth = depth_view(&device, &queue);
+    let normals = normal_view(&device, &queue);
+    pipeline.enable_reflections(&device, &depth, &normals, Reflections::default());
+    assert!(pipeline.set_param("reflections.intensity", 0.75));
+    assert!(pipeline.set_param("reflections.steps", 31.6));
+    assert!(!pipeline.set_param("reflections.missing", 1.0));
+    assert_eq!(pipeline.get_param("reflections.steps"), Some(32.0));
+    let reflections = pipeline.reflections().unwrap();
+    assert_eq!((reflections.intensity, reflections.steps), (0.75, 32));
+    let params = pipeline.list_params();
+    assert!(params.contains(&"reflections.edge_fade".to_string()));
+    assert!(!params.contains(&"reflections.projection".to_string()));
+
+    // Muted, the composite sees no reflections, even ones traced before
+    let input = TestPattern::Gradient.create_texture(&device, &queue, WIDTH, HEIGHT);
+    pipeline.set_param("reflections.intensity", 1.0);
+    let reflected = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(pipeline.set_group_muted("reflections", true));
+    let muted = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    pipeline.disable_reflections(&device);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_ne!(reflected, plain);
+    golden::compare(&plain, &muted, WIDTH, 1.0 / 255.0).unwrap();
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn reflections_round_trip_through_config() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping reflection test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    assert_eq!(pipeline.config().reflections, None);
+    let reflections = Reflections {
+        intensity: 0.8,
+        steps: 20,
+        ..Reflections::default()
+    };
+    let depth = depth_view(&device, &queue);
+    let normals = normal_view(&device, &queue);
+    pipeline.enable_reflections(&device, &depth, &normals, reflections);
+
+    let source = pipeline.config().to_toml().unwrap();
+    let config = nnpipe::PipelineConfig::from_toml(&source).unwrap();
+    assert_eq!(config.reflections, Some(reflections));
+
+    // Applied to reflections that are on, and reported for a pipeline without them
+    pipeline.set_reflections(Reflections::default());
+    pipeline.apply_config(&config).unwrap();
+    assert_eq!(pipeline.reflections(), Some(reflections));
+    let mut plain = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    let error = plain.apply_config(&config).unwrap_err();
+    assert!(
+        error.message.contains("no reflections"),
+        "{}",
+        error.message
+    );
+}
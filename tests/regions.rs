@@ -0,0 +1,101 @@
+// tests/regions.rs
+//
+// Tests of param regions
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, ParamRegion};
+
+const PASSTHROUGH: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct Params {
+    gain: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.gain, color.a);
+}
+";
+
+#[test]
+fn regions_override_params_in_their_part() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping param region test: no adapter");
+        return;
+    };
+    let (width, height) = (8, 4);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let input_view = input.view().build();
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    let gain = pipeline.add_custom_pass(&device, "Gain", PASSTHROUGH, &[("gain", 1.0)]);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Full-frame renders with the overrides, to compare the parts with
+    let mut render_with = |path: &str, value: f32| {
+        let previous = pipeline.get_param(path).unwrap();
+        pipeline.set_param(path, value);
+        let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+        pipeline.set_param(path, previous);
+        frame
+    };
+    let gain_path = format!("passes.{gain}.gain");
+    let dimmed = render_with(&gain_path, 0.5);
+    let unbloomed = render_with("bloom.intensity", 0.0);
+    assert_ne!(plain, dimmed);
+    assert_ne!(plain, unbloomed);
+
+    let right = ParamRegion::right_half().with_override("bloom.intensity", 0.0);
+    pipeline.add_param_region(right);
+    let corner = ParamRegion::quadrant(0, 1)
+        .with_override(gain_path.as_str(), 0.7)
+        .with_override(gain_path.as_str(), 0.5);
+    assert_eq!(corner.overrides.len(), 1);
+    pipeline.add_param_region(corner);
+    assert_eq!(pipeline.param_regions().len(), 2);
+
+    let frame = |pipeline: &mut Nnpipe| {
+        golden::render(pipeline, &device, &queue, &input).unwrap();
+        pipeline.process_regions(&device, &queue, Some(&input_view), None);
+        futures::executor::block_on(pipeline.read_output(&device, &queue)).unwrap()
+    };
+    let regions = frame(&mut pipeline);
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            let expected = match (x < width / 2, y < height / 2) {
+                (false, _) => unbloomed[i],
+                (true, false) => dimmed[i],
+                (true, true) => plain[i],
+            };
+            assert_eq!(regions[i], expected, "at {x}, {y}");
+        }
+    }
+    // The params are back to their own values
+    assert_eq!(pipeline.get_param(&gain_path), Some(1.0));
+    assert_ne!(pipeline.get_param("bloom.intensity"), Some(0.0));
+
+    // Survive resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.param_regions().len(), 2);
+    assert!(pipeline.remove_param_region(1).is_some());
+    assert!(pipeline.remove_param_region(1).is_none());
+    let bottom_left = ((height - 1) * width) as usize;
+    assert_eq!(frame(&mut pipeline)[bottom_left], plain[bottom_left]);
+
+    // Regions drawn into a scaled target are scaled with the frame
+    let target = Nnpipe::new(&device, 2 * width, 2 * height, 1).unwrap();
+    pipeline.process_texture(&device, &queue, &input_view, &target.output_view);
+    pipeline.process_regions(
+        &device,
+        &queue,
+        Some(&input_view),
+        Some(&target.output_view),
+    );
+    let scaled = futures::executor::block_on(target.read_output(&device, &queue)).unwrap();
+    let unbloomed_right = unbloomed[(width - 1) as usize];
+    assert_eq!(scaled[(2 * width - 1) as usize], unbloomed_right);
+}
@@ -0,0 +1,63 @@
+// tests/replay.rs
+//
+// Instant replay
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{InstantReplay, Nnpipe};
+
+#[test]
+fn replay_keeps_the_last_frames() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping replay test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let mut replay = InstantReplay::new(&mut pipeline, &device, 0.1, 20.0, 0.5).unwrap();
+    assert_eq!(replay.size(), [32, 24]);
+    assert!(replay.is_empty());
+
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 64, 48);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    pipeline.set_time(Some(0.0));
+    assert!(replay.record(&pipeline, &device, &queue));
+    // Frames closer than the interval are skipped
+    pipeline.set_time(Some(0.01));
+    assert!(!replay.record(&pipeline, &device, &queue));
+    pipeline.set_time(Some(0.05));
+    assert!(replay.record(&pipeline, &device, &queue));
+    // Once full, the oldest frame makes room
+    pipeline.set_time(Some(0.1));
+    assert!(replay.record(&pipeline, &device, &queue));
+    assert_eq!(replay.len(), 2);
+    assert!((replay.duration() - 0.05).abs() < 1e-6);
+
+    let dir = std::env::temp_dir().join(format!("nnpipe-replay-{}", std::process::id()));
+    assert_eq!(replay.export_replay(&device, &queue, &dir).unwrap(), 2);
+    let image = nannou::image::open(dir.join("frame_00001.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(image.dimensions(), (32, 24));
+    // The gradient runs red across and green down
+    let [left, right] = [image.get_pixel(2, 12), image.get_pixel(29, 12)];
+    assert!(left[0] < right[0]);
+    // Encoded to sRGB, from the mean of the four output pixels each one covers
+    let mean = |channel: usize| {
+        let pixels = [(4, 24), (5, 24), (4, 25), (5, 25)];
+        pixels
+            .iter()
+            .map(|&(x, y)| output[y * 64 + x][channel])
+            .sum::<f32>()
+            / 4.0
+    };
+    for channel in 0..3 {
+        let srgb = 1.055 * mean(channel).powf(1.0 / 2.4) - 0.055;
+        let expected = (srgb * 255.0).round();
+        assert!((left[channel] as f32 - expected).abs() <= 2.0, "{left:?}");
+    }
+    assert!(!dir.join("frame_00002.png").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    replay.clear();
+    assert_eq!(replay.duration(), 0.0);
+}
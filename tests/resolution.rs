@@ -0,0 +1,47 @@
+// tests/resolution.rs
+//
+// Dynamic resolution tests, driven by synthetic frame times
+
+use nnpipe::golden;
+use nnpipe::{Nnpipe, ResolutionController};
+
+#[test]
+fn scale_follows_frame_time() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping dynamic resolution test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 200, 100, 1).unwrap();
+    let mut controller = ResolutionController::new(60.0).with_rate(0.1, 10);
+
+    // Frames at 30 fps bring the scale down to the minimum, a step at a time
+    let mut changes = 0;
+    for _ in 0..200 {
+        if controller
+            .update_with(1.0 / 30.0, &mut pipeline, &device, &queue)
+            .unwrap()
+        {
+            changes += 1;
+        }
+    }
+    assert_eq!(changes, 5);
+    assert_eq!(pipeline.render_scale(), 0.5);
+    assert_eq!(pipeline.size(), [100, 50]);
+    assert_eq!(pipeline.base_size(), [200, 100]);
+
+    // Frames well under budget bring it back up
+    for _ in 0..200 {
+        controller
+            .update_with(1.0 / 240.0, &mut pipeline, &device, &queue)
+            .unwrap();
+    }
+    assert_eq!(pipeline.render_scale(), 1.0);
+    assert_eq!(pipeline.size(), [200, 100]);
+
+    // Frames on budget leave it alone
+    for _ in 0..200 {
+        assert!(!controller
+            .update_with(1.0 / 60.0, &mut pipeline, &device, &queue)
+            .unwrap());
+    }
+}
@@ -0,0 +1,84 @@
+// tests/sampler.rs
+//
+// Tests of custom passes with samplers of their own
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, PassSampler, SamplerAddressing, SamplerFilter};
+
+// Samples the frame shifted by half of it and half a pixel, so the addressing decides
+// the right half and the filter the blend between neighbours
+const SHIFT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(src_tex));
+    let uv = (pos.xy + vec2<f32>(size.x * 0.5 + 0.5, 0.0)) / size;
+    return textureSample(src_tex, src_sampler, uv);
+}
+";
+
+#[test]
+fn passes_sample_with_their_own_sampler() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping sampler test: no adapter");
+        return;
+    };
+    let (width, height) = (32, 8);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let index = pipeline.add_custom_pass(&device, "Shift", SHIFT, &[]);
+    assert_eq!(
+        pipeline.custom_pass(index).unwrap().sampler(),
+        PassSampler::default()
+    );
+    let pixel = |pixels: &[[f32; 4]], x: u32| pixels[(4 * width + x) as usize];
+
+    // Clamped: the right half repeats the last column
+    let clamped = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(pixel(&clamped, 24), pixel(&plain, width - 1));
+
+    // Repeating and nearest: the frame wraps around, pixel for pixel
+    let sampler = PassSampler::new(SamplerAddressing::Repeat, SamplerFilter::Nearest);
+    pipeline.set_pass_sampler(&device, index, sampler);
+    assert_eq!(pipeline.custom_pass(index).unwrap().sampler(), sampler);
+    let repeated = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    for x in 0..width {
+        let source = (x + width / 2 + 1) % width;
+        assert_eq!(pixel(&repeated, x), pixel(&plain, source), "at {x}");
+    }
+
+    // Linear filtering lands halfway between neighbours
+    let linear = PassSampler::new(SamplerAddressing::Repeat, SamplerFilter::Linear);
+    pipeline.set_pass_sampler(&device, index, linear);
+    let blended = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let [left, right] = [pixel(&plain, 20), pixel(&plain, 21)];
+    assert!((pixel(&blended, 4)[0] - (left[0] + right[0]) / 2.0).abs() < 2e-3);
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.custom_pass(index).unwrap().sampler(), linear);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn chain_configs_keep_samplers() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping sampler test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 32, 8, 1).unwrap();
+    let plain = pipeline.add_custom_pass(&device, "Plain", SHIFT, &[]);
+    let mirrored = pipeline.add_custom_pass(&device, "Mirrored", SHIFT, &[]);
+    let sampler = PassSampler::new(SamplerAddressing::MirrorRepeat, SamplerFilter::Linear);
+    pipeline.set_pass_sampler(&device, mirrored, sampler);
+
+    let config = pipeline.chain_config();
+    assert_eq!(config.passes[plain].sampler, None);
+    assert_eq!(config.passes[mirrored].sampler, Some(sampler));
+    let rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert_eq!(rebuilt.custom_pass(mirrored).unwrap().sampler(), sampler);
+}
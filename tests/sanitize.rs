@@ -0,0 +1,76 @@
+// tests/sanitize.rs
+//
+// Tests of scrubbing NaN and infinite scene pixels
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::Nnpipe;
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 8;
+
+// Half float bits of mid grey, with a NaN and an infinite pixel in the middle
+fn broken_scene(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([WIDTH, HEIGHT])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba16Float)
+        .build(device);
+    let (grey, one, nan, infinity) = (0x3800u16, 0x3c00, 0x7e00, 0x7c00);
+    let data: Vec<[u16; 4]> = (0..WIDTH * HEIGHT)
+        .map(|i| match i {
+            _ if i == 4 * WIDTH + 7 => [nan, grey, grey, one],
+            _ if i == 4 * WIDTH + 8 => [infinity, infinity, infinity, one],
+            _ => [grey, grey, grey, one],
+        })
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(WIDTH * 8),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+fn finite(frame: &[[f32; 4]]) -> usize {
+    frame
+        .iter()
+        .filter(|pixel| pixel.iter().all(|channel| channel.is_finite()))
+        .count()
+}
+
+#[test]
+fn sanitized_scenes_stay_finite() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping sanitize test: no adapter");
+        return;
+    };
+    let input = broken_scene(&device, &queue);
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_brightness_threshold(0.1);
+
+    // Left alone, the broken pixels spread through the bloom
+    let broken = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(finite(&broken) < broken.len() - 2);
+
+    pipeline.set_sanitize_scene(true);
+    let sanitized = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(finite(&sanitized), sanitized.len());
+    let corner = sanitized[0];
+    for x in [7, 8] {
+        let pixel = sanitized[(4 * WIDTH + x) as usize];
+        assert!(pixel[0] < corner[0], "at {x}");
+    }
+
+    // Carried through rebuilds
+    pipeline.resize(&device, &queue, 2 * WIDTH, HEIGHT).unwrap();
+    assert!(pipeline.sanitize_scene);
+    pipeline.resize(&device, &queue, WIDTH, HEIGHT).unwrap();
+    let rebuilt = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(finite(&rebuilt), rebuilt.len());
+}
@@ -0,0 +1,72 @@
+// tests/scopes.rs
+//
+// Tests of the video scopes
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Magnifier, Nnpipe, Scope, Scopes, Zebra};
+
+fn level(value: f32) -> usize {
+    ((value.clamp(0.0, 1.0) * 256.0) as usize).min(255)
+}
+
+#[test]
+fn scopes_count_the_frame() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping scopes test: no adapter");
+        return;
+    };
+    let (width, height) = (16, 8);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let read = |pipeline: &Nnpipe| {
+        futures::executor::block_on(pipeline.read_scopes(&device, &queue)).unwrap()
+    };
+    assert!(read(&pipeline).is_none());
+    assert!(pipeline.scope_view(Scope::Histogram).is_none());
+    let scopes = Scopes {
+        size: 4,
+        ..Default::default()
+    };
+    pipeline.set_scopes(&device, Some(scopes));
+    assert_eq!(pipeline.scopes(), Some(scopes));
+    assert_eq!(pipeline.scope_view(Scope::Waveform).unwrap().size(), [4, 4]);
+
+    // The frame goes through untouched
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(frame, plain);
+
+    let data = read(&pipeline).unwrap();
+    let pixels = width * height;
+    for channel in 0..3 {
+        let mut expected = vec![0; 256];
+        for pixel in &frame {
+            expected[level(pixel[channel])] += 1;
+        }
+        assert_eq!(data.histogram[channel], expected, "channel {channel}");
+    }
+    assert_eq!(data.histogram[3].iter().sum::<u32>(), pixels);
+    assert_eq!(data.vectorscope.iter().sum::<u32>(), pixels);
+    assert_eq!(data.columns, 4);
+    for column in 0..4 {
+        let count: u32 = (0..256).map(|level| data.waveform_at(column, level)).sum();
+        assert_eq!(count, pixels / 4, "column {column}");
+    }
+
+    // Ahead of the magnifier and zebra stripes, and across resizes
+    pipeline.set_magnifier(&device, Some(Magnifier::default()));
+    pipeline.set_zebra(&device, Some(Zebra::default()));
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(read(&pipeline).unwrap(), data);
+
+    // A new size gets new textures
+    pipeline.set_scopes(&device, Some(Scopes { size: 8, ..scopes }));
+    golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let data = read(&pipeline).unwrap();
+    assert_eq!(data.columns, 8);
+    assert_eq!(data.waveform.len(), 8 * 256);
+    pipeline.set_scopes(&device, None);
+    assert!(pipeline.scopes().is_none());
+}
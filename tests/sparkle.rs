@@ -0,0 +1,115 @@
+// tests/sparkle.rs
+//
+// Sparkle layer tests
+
+#![cfg(feature = "stylize")]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, Sparkles};
+
+fn total(pixels: &[[f32; 4]]) -> f32 {
+    pixels.iter().map(|[r, g, b, _]| r + g + b).sum()
+}
+
+#[test]
+fn sparkles_glint_on_bright_spots() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping sparkle test: no adapter");
+        return;
+    };
+    let (width, height) = (64, 48);
+    let input = TestPattern::Dots.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    pipeline.set_time(Some(0.3));
+    pipeline.set_seed(7);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let sparkles = Sparkles {
+        threshold: 0.1,
+        density: 1.0,
+        lifetime: 1.0,
+        cell_size: 8.0,
+        ..Sparkles::default()
+    };
+    pipeline.set_sparkles(&device, Some(sparkles));
+    assert_eq!(pipeline.sparkles(), Some(sparkles));
+    let sparkled = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(total(&sparkled) > total(&plain) + 1.0);
+    for (sparkled, plain) in sparkled.iter().zip(&plain) {
+        assert!(sparkled[..3].iter().zip(plain).all(|(s, p)| s >= p));
+    }
+
+    // The same time and seed give the same glints
+    let again = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&sparkled, &again, width, 0.0).unwrap();
+
+    // and they survive resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.sparkles(), Some(sparkles));
+    let resized = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&sparkled, &resized, width, 0.0).unwrap();
+
+    // Nothing is bright enough
+    let unreachable = Sparkles {
+        threshold: 2.0,
+        ..sparkles
+    };
+    pipeline.set_sparkles(&device, Some(unreachable));
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 0.0).unwrap();
+
+    pipeline.set_sparkles(&device, None);
+    assert_eq!(pipeline.sparkles(), None);
+    let output = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &output, width, 0.0).unwrap();
+}
+
+#[test]
+fn sparkle_settings_are_params() {
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping sparkle test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    assert!(!pipeline.set_param("sparkles.density", 0.5));
+
+    pipeline.set_sparkles(&device, Some(Sparkles::default()));
+    assert!(pipeline.set_param("sparkles.density", 0.5));
+    assert!(pipeline.set_param("sparkles.cell_size", 16.0));
+    assert_eq!(pipeline.get_param("sparkles.density"), Some(0.5));
+    let sparkles = pipeline.sparkles().unwrap();
+    assert_eq!((sparkles.density, sparkles.cell_size), (0.5, 16.0));
+    assert!(pipeline
+        .list_params()
+        .contains(&"sparkles.lifetime".to_string()));
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn sparkles_round_trip_through_config() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping sparkle test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    let sparkles = Sparkles {
+        threshold: 0.25,
+        size: 6.0,
+        ..Sparkles::default()
+    };
+    pipeline.set_sparkles(&device, Some(sparkles));
+
+    let source = pipeline.chain_config().to_toml().unwrap();
+    let config = nnpipe::PipelineConfig::from_toml(&source).unwrap();
+    assert_eq!(config.sparkles, Some(sparkles));
+    let rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert_eq!(rebuilt.sparkles(), Some(sparkles));
+
+    pipeline.set_sparkles(&device, Some(Sparkles::default()));
+    pipeline.apply_config(&config).unwrap();
+    assert_eq!(pipeline.sparkles(), Some(sparkles));
+    let mut plain = Nnpipe::new(&device, 64, 48, 1).unwrap();
+    let error = plain.apply_config(&config).unwrap_err();
+    assert!(error.message.contains("no sparkles"), "{}", error.message);
+}
@@ -0,0 +1,66 @@
+// tests/specialize.rs
+//
+// Specialization constant tests
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, PipelineCache};
+
+const LEVELS: &str = "
+const STEPS: i32 = 1i;
+const SAMPLES: u32 = 1u;
+const GAIN: f32 = 0.1;
+const GREEN: bool = false;
+const UNTYPED = 1.0;
+const SIZE: vec2<f32> = vec2<f32>(1.0, 1.0);
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    let red = f32(STEPS) * f32(SAMPLES) * GAIN;
+    return vec4<f32>(red, select(0.0, 1.0, GREEN), UNTYPED * SIZE.x - 1.0, 1.0);
+}
+";
+
+#[test]
+fn constants_rebuild_the_shader() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping specialization test: no adapter");
+        return;
+    };
+    let cache = PipelineCache::new();
+    let mut pipeline = Nnpipe::with_cache(&device, 16, 16, 1, &cache).unwrap();
+    let index = pipeline.add_custom_pass(&device, "Levels", LEVELS, &[]);
+    let input = TestPattern::Dots.create_texture(&device, &queue, 16, 16);
+    let center = |pipeline: &Nnpipe| {
+        let pixels = golden::render(pipeline, &device, &queue, &input).unwrap();
+        pixels[8 * 16 + 8]
+    };
+    assert!((center(&pipeline)[0] - 0.1).abs() < 1e-3);
+
+    let high = [("STEPS", 2.0), ("SAMPLES", 3.0), ("GREEN", 1.0)];
+    pipeline.set_pass_constants(&device, index, &high).unwrap();
+    let pixel = center(&pipeline);
+    assert!((pixel[0] - 0.6).abs() < 1e-3, "{pixel:?}");
+    assert_eq!(pixel[1], 1.0);
+    assert_eq!(pipeline.custom_pass(index).unwrap().constants().len(), 3);
+    assert_eq!(pipeline.custom_pass(index).unwrap().source(), LEVELS);
+
+    // Going back to a set of values reuses its pipeline
+    let count = cache.pipeline_count();
+    pipeline.set_pass_constants(&device, index, &[]).unwrap();
+    pipeline.set_pass_constants(&device, index, &high).unwrap();
+    assert_eq!(cache.pipeline_count(), count);
+
+    // Failures keep the previous shader and constants
+    for constants in [
+        [("MISSING", 1.0)],
+        [("UNTYPED", 2.0)],
+        [("SIZE", 2.0)],
+        [("GAIN", f32::NAN)],
+    ] {
+        assert!(pipeline
+            .set_pass_constants(&device, index, &constants)
+            .is_err());
+    }
+    assert_eq!(pipeline.custom_pass(index).unwrap().constants().len(), 3);
+    assert!((center(&pipeline)[0] - 0.6).abs() < 1e-3);
+}
@@ -0,0 +1,57 @@
+// tests/stabilize.rs
+//
+// Bloom stabilization tests
+
+#![cfg(feature = "temporal")]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{BloomStabilization, Nnpipe};
+
+#[test]
+fn stabilized_bloom_lingers_and_settles() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping bloom stabilization test: no adapter");
+        return;
+    };
+    let (width, height) = (64, 48);
+    let dots = TestPattern::Dots.create_texture(&device, &queue, width, height);
+    let gradient = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    let plain_dots = golden::render(&pipeline, &device, &queue, &dots).unwrap();
+    let plain_gradient = golden::render(&pipeline, &device, &queue, &gradient).unwrap();
+    // Center of the top left dot
+    let dot = (6 * width + 8) as usize;
+
+    // The first frame has no history to blend with
+    let stabilization = BloomStabilization::default();
+    pipeline.set_bloom_stabilization(&device, Some(stabilization));
+    assert_eq!(pipeline.bloom_stabilization(), Some(stabilization));
+    let output = golden::render(&pipeline, &device, &queue, &dots).unwrap();
+    golden::compare(&plain_dots, &output, width, 0.0).unwrap();
+
+    // The dots keep blooming after they're gone, fading over the following frames
+    let output = golden::render(&pipeline, &device, &queue, &gradient).unwrap();
+    assert!(output[dot][0] > plain_gradient[dot][0] + 0.01);
+    for _ in 0..40 {
+        golden::render(&pipeline, &device, &queue, &gradient).unwrap();
+    }
+    let output = golden::render(&pipeline, &device, &queue, &gradient).unwrap();
+    golden::compare(&plain_gradient, &output, width, 1.0 / 255.0).unwrap();
+
+    // Without feedback each frame stands alone
+    pipeline.set_bloom_stabilization(&device, Some(BloomStabilization { feedback: 0.0 }));
+    golden::render(&pipeline, &device, &queue, &dots).unwrap();
+    let output = golden::render(&pipeline, &device, &queue, &gradient).unwrap();
+    golden::compare(&plain_gradient, &output, width, 0.0).unwrap();
+
+    // Survives resizes
+    pipeline.set_bloom_stabilization(&device, Some(stabilization));
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.bloom_stabilization(), Some(stabilization));
+
+    pipeline.set_bloom_stabilization(&device, None);
+    assert_eq!(pipeline.bloom_stabilization(), None);
+    golden::render(&pipeline, &device, &queue, &dots).unwrap();
+    let output = golden::render(&pipeline, &device, &queue, &gradient).unwrap();
+    golden::compare(&plain_gradient, &output, width, 0.0).unwrap();
+}
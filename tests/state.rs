@@ -0,0 +1,169 @@
+// tests/state.rs
+//
+// Pipeline state snapshots
+
+#![cfg(feature = "config")]
+
+use nnpipe::golden::{self, TestPattern};
+#[cfg(all(feature = "stylize", feature = "temporal"))]
+use nnpipe::{Accumulation, BloomStabilization, Matte, Sparkles};
+use nnpipe::{Nnpipe, NnpipeError, PassGraph, PipelineState, Trigger};
+
+const TINT: &str = "
+struct Params {
+    amount: f32, // default: 1.0
+}
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+const INVERT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(1.0 - color.rgb, color.a);
+}
+";
+
+fn labels(pipeline: &Nnpipe) -> Vec<String> {
+    (0..)
+        .map_while(|index| pipeline.custom_pass(index))
+        .map(|pass| pass.label.clone())
+        .collect()
+}
+
+#[test]
+fn snapshots_restore_the_chain() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping state test: no adapter");
+        return;
+    };
+    let input = TestPattern::Gradient.create_texture(&device, &queue, 32, 24);
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.set_bloom_intensity(0.5);
+    let tint = pipeline.add_reflected_pass(&device, "Tint", TINT);
+    pipeline.add_reflected_pass(&device, "Invert", INVERT);
+    assert!(pipeline.set_param(&format!("passes.{tint}.amount"), 0.5));
+    pipeline.add_trigger("flash", Trigger::new(tint, "amount", 1.0));
+    pipeline.set_seed(7);
+
+    let state = pipeline.snapshot();
+    assert_eq!(state.triggers.len(), 1);
+    assert_eq!(state.seed, 7);
+    let toml = state.to_toml().unwrap();
+    assert_eq!(PipelineState::from_toml(&toml).unwrap(), state);
+    let ron = state.to_ron().unwrap();
+    assert_eq!(PipelineState::from_ron(&ron).unwrap(), state);
+    let expected = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Another order, other settings and no triggers
+    let mut reordered = state.clone();
+    reordered.config.passes.reverse();
+    reordered.config.passes[0].enabled = Some(false);
+    reordered.config.bloom.intensity = Some(2.0);
+    reordered.triggers.clear();
+    reordered.bypass = true;
+    pipeline.restore(&device, &queue, &reordered).unwrap();
+    assert_eq!(labels(&pipeline), ["Invert", "Tint"]);
+    assert!(!pipeline.custom_pass(0).unwrap().enabled);
+    assert_eq!(pipeline.bloom_intensity, 2.0);
+    assert!(pipeline.bypass());
+    assert!(!pipeline.fire("flash"));
+    assert_eq!(pipeline.snapshot(), reordered);
+
+    // And back
+    pipeline.restore(&device, &queue, &state).unwrap();
+    assert!(pipeline.take_shader_errors().is_empty());
+    assert_eq!(pipeline.snapshot(), state);
+    let actual = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&expected, &actual, 32, 1.0 / 255.0).unwrap();
+
+    // A fresh pipeline picks up where the snapshot left, e.g. after a crash
+    let mut recovered = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    recovered.restore(&device, &queue, &state).unwrap();
+    assert_eq!(recovered.snapshot(), state);
+    let actual = golden::render(&recovered, &device, &queue, &input).unwrap();
+    golden::compare(&expected, &actual, 32, 1.0 / 255.0).unwrap();
+    assert!(recovered.fire("flash"));
+
+    // States need the passes' shaders
+    let mut broken = state.clone();
+    broken.config.passes[0].source = None;
+    let result = recovered.restore(&device, &queue, &broken);
+    assert!(matches!(result, Err(NnpipeError::Config(_))));
+}
+
+#[cfg(all(feature = "stylize", feature = "temporal"))]
+#[test]
+fn snapshots_restore_the_layers_and_graph() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping state test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.set_matte(&device, Some(Matte::default()));
+    pipeline.set_sparkles(&device, Some(Sparkles::default()));
+    pipeline.set_bloom_stabilization(&device, Some(BloomStabilization::default()));
+    pipeline.set_accumulation(&device, Some(Accumulation::default()));
+    let graph = PassGraph::new().pass("Invert", INVERT, &[], &["input"], "output");
+    pipeline.set_pass_graph(&device, Some(graph)).unwrap();
+
+    let state = pipeline.snapshot();
+    assert!(state.matte.is_some() && state.config.sparkles.is_some());
+    assert!(state.stabilization.is_some() && state.accumulation.is_some());
+    assert_eq!(
+        PipelineState::from_toml(&state.to_toml().unwrap()).unwrap(),
+        state
+    );
+
+    let mut recovered = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    recovered.restore(&device, &queue, &state).unwrap();
+    assert_eq!(recovered.snapshot(), state);
+
+    // And off again
+    recovered
+        .restore(&device, &queue, &PipelineState::default())
+        .unwrap();
+    assert!(recovered.matte().is_none() && recovered.sparkles().is_none());
+    assert!(recovered.accumulation().is_none() && recovered.pass_graph().is_none());
+}
+
+#[test]
+fn failed_restores_leave_the_pipeline_alone() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping state test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.add_reflected_pass(&device, "Tint", TINT);
+    pipeline.set_seed(7);
+    let state = pipeline.snapshot();
+
+    // A new pass setting a param it lacks, after a pass that would be kept
+    let mut missing = state.clone();
+    missing.config.passes.push(missing.config.passes[0].clone());
+    missing.config.passes[1].label = Some("Invert".to_string());
+    missing.config.passes[1].source = Some(INVERT.to_string());
+    missing.seed = 3;
+    let result = pipeline.restore(&device, &queue, &missing);
+    assert!(matches!(result, Err(NnpipeError::Config(_))));
+    assert_eq!(labels(&pipeline), ["Tint"]);
+    assert_eq!(pipeline.snapshot(), state);
+
+    // A graph that can't be scheduled
+    let mut unscheduled = state.clone();
+    unscheduled.config.passes.clear();
+    unscheduled.pass_graph = Some(PassGraph::new().pass("A", INVERT, &[], &["input"], "a"));
+    let result = pipeline.restore(&device, &queue, &unscheduled);
+    assert!(matches!(result, Err(NnpipeError::Config(_))));
+    assert_eq!(pipeline.snapshot(), state);
+}
@@ -0,0 +1,243 @@
+// tests/stereo.rs
+//
+// Tests of stereo rendering
+
+use nannou::wgpu;
+use nnpipe::golden;
+use nnpipe::{Eye, Nnpipe, NnpipeError, StereoLayout};
+
+const WIDTH: u32 = 8;
+const HEIGHT: u32 = 4;
+
+// A texture of `width` x `height` whose pixels come from `color`
+fn texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    [width, height]: [u32; 2],
+    color: impl Fn(u32, u32) -> [u8; 4],
+) -> wgpu::Texture {
+    let texture = wgpu::TextureBuilder::new()
+        .size([width, height])
+        .usage(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST)
+        .format(wgpu::TextureFormat::Rgba8Unorm)
+        .build(device);
+    let data: Vec<[u8; 4]> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| color(x, y))
+        .collect();
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&data),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 4),
+            rows_per_image: None,
+        },
+        texture.extent(),
+    );
+    texture
+}
+
+// Different gradients per eye
+fn left(x: u32, y: u32) -> [u8; 4] {
+    [(x * 30) as u8, (y * 60) as u8, 20, 255]
+}
+
+fn right(x: u32, y: u32) -> [u8; 4] {
+    [20, (x * 30) as u8, (y * 60) as u8, 255]
+}
+
+#[test]
+fn eyes_share_the_chain() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping stereo test: no adapter");
+        return;
+    };
+    let size = [WIDTH, HEIGHT];
+    let left_input = texture(&device, &queue, size, left);
+    let right_input = texture(&device, &queue, size, right);
+    let packed_input = texture(&device, &queue, [2 * WIDTH, HEIGHT], |x, y| {
+        match x.checked_sub(WIDTH) {
+            Some(x) => right(x, y),
+            None => left(x, y),
+        }
+    });
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    pipeline.set_bloom_intensity(0.5);
+    let index = pipeline
+        .add_output(&device, wgpu::TextureFormat::Rgba16Float)
+        .unwrap();
+
+    // Each eye alone, through the output
+    let target = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    let read = |target: &Nnpipe| futures::executor::block_on(target.read_output(&device, &queue));
+    let mono = |input: &wgpu::Texture| {
+        golden::render(&pipeline, &device, &queue, input).unwrap();
+        pipeline
+            .present(&device, &queue, index, &target.output_view)
+            .unwrap();
+        read(&target).unwrap()
+    };
+    let expected = [mono(&left_input), mono(&right_input)];
+    assert_ne!(expected[0], expected[1]);
+
+    assert!(!pipeline.stereo());
+    assert!(pipeline.eye_view(Eye::Left).is_none());
+    pipeline.set_stereo(&device, true);
+    assert!(pipeline.stereo());
+    let present_eyes = |pipeline: &Nnpipe| {
+        Eye::BOTH.map(|eye| {
+            pipeline
+                .present_eye(&device, &queue, eye, index, &target.output_view)
+                .unwrap();
+            read(&target).unwrap()
+        })
+    };
+
+    let left_view = left_input.view().build();
+    let right_view = right_input.view().build();
+    pipeline
+        .process_stereo(&device, &queue, &left_view, &right_view)
+        .unwrap();
+    let eyes = present_eyes(&pipeline);
+    for (eye, expected) in eyes.iter().zip(&expected) {
+        golden::compare(expected, eye, WIDTH, 0.0).unwrap();
+    }
+
+    // Split from one side-by-side texture
+    let packed_view = packed_input.view().build();
+    pipeline
+        .process_packed_stereo(&device, &queue, &packed_view, StereoLayout::SideBySide)
+        .unwrap();
+    let eyes = present_eyes(&pipeline);
+    for (eye, expected) in eyes.iter().zip(&expected) {
+        golden::compare(expected, eye, WIDTH, 1e-3).unwrap();
+    }
+
+    // Packed top and bottom into one target
+    let packed_target = Nnpipe::new(&device, WIDTH, 2 * HEIGHT, 1).unwrap();
+    pipeline
+        .present_stereo(
+            &device,
+            &queue,
+            index,
+            &packed_target.output_view,
+            StereoLayout::TopBottom,
+        )
+        .unwrap();
+
+    // Unknown outputs are refused
+    let unknown = pipeline.present_eye(&device, &queue, Eye::Left, 7, &target.output_view);
+    assert!(matches!(unknown, Err(NnpipeError::UnknownOutput(7))));
+    let packed = read(&packed_target).unwrap();
+    let halves: Vec<_> = packed.chunks((WIDTH * HEIGHT) as usize).collect();
+    for (half, expected) in halves.iter().zip(&expected) {
+        golden::compare(expected, half, WIDTH, 1e-3).unwrap();
+    }
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, WIDTH, HEIGHT).unwrap();
+    assert!(pipeline.stereo());
+    pipeline.set_stereo(&device, false);
+    assert!(pipeline.eye_view(Eye::Right).is_none());
+
+    // Stereo calls are refused while it's off
+    let stereo_off = |result| matches!(result, Err(NnpipeError::StereoOff));
+    assert!(stereo_off(pipeline.process_stereo(
+        &device,
+        &queue,
+        &left_view,
+        &right_view
+    )));
+    assert!(stereo_off(pipeline.present_eye(
+        &device,
+        &queue,
+        Eye::Left,
+        index,
+        &target.output_view
+    )));
+    assert!(stereo_off(pipeline.present_stereo(
+        &device,
+        &queue,
+        index,
+        &packed_target.output_view,
+        StereoLayout::SideBySide
+    )));
+}
+
+#[test]
+fn layouts_split_the_odd_pixel_to_the_second_eye() {
+    let layout = StereoLayout::SideBySide;
+    assert_eq!(layout.region(Eye::Left, [5, 2]), [0, 0, 2, 2]);
+    assert_eq!(layout.region(Eye::Right, [5, 2]), [2, 0, 3, 2]);
+    let layout = StereoLayout::TopBottom;
+    assert_eq!(layout.region(Eye::Left, [4, 3]), [0, 0, 4, 1]);
+    assert_eq!(layout.region(Eye::Right, [4, 3]), [0, 1, 4, 2]);
+}
+
+#[cfg(feature = "temporal")]
+#[test]
+fn eyes_keep_temporal_histories_of_their_own() {
+    use nnpipe::{Accumulation, BloomStabilization};
+
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping stereo test: no adapter");
+        return;
+    };
+    let size = [WIDTH, HEIGHT];
+    let red = texture(&device, &queue, size, |_, _| [255, 0, 0, 255]);
+    let green = texture(&device, &queue, size, |_, _| [0, 255, 0, 255]);
+    let black = texture(&device, &queue, size, |_, _| [0, 0, 0, 255]);
+    let [red, green, black] = [red, green, black].map(|texture| texture.view().build());
+
+    let mut pipeline = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    let index = pipeline
+        .add_output(&device, wgpu::TextureFormat::Rgba16Float)
+        .unwrap();
+    let target = Nnpipe::new(&device, WIDTH, HEIGHT, 1).unwrap();
+    let eyes = |pipeline: &Nnpipe| {
+        Eye::BOTH.map(|eye| {
+            pipeline
+                .present_eye(&device, &queue, eye, index, &target.output_view)
+                .unwrap();
+            futures::executor::block_on(target.read_output(&device, &queue)).unwrap()
+        })
+    };
+    pipeline.set_stereo(&device, true);
+
+    // Each eye averages only its own frames
+    pipeline.set_bloom_intensity(0.0);
+    pipeline.set_accumulation(&device, Some(Accumulation::default()));
+    for _ in 0..3 {
+        pipeline
+            .process_stereo(&device, &queue, &red, &green)
+            .unwrap();
+    }
+    let [left, right] = eyes(&pipeline);
+    assert_eq!(pipeline.accumulated_frames(), Some(3));
+    assert!(
+        left.iter().all(|pixel| pixel[0] > 0.1 && pixel[1] == 0.0),
+        "{left:?}"
+    );
+    assert!(
+        right.iter().all(|pixel| pixel[0] == 0.0 && pixel[1] > 0.1),
+        "{right:?}"
+    );
+
+    // A bright left eye doesn't bloom into a black right eye
+    pipeline.set_accumulation(&device, None);
+    pipeline.set_bloom_intensity(1.0);
+    pipeline.set_brightness_threshold(0.0);
+    pipeline.set_bloom_stabilization(&device, Some(BloomStabilization { feedback: 0.95 }));
+    for _ in 0..3 {
+        pipeline
+            .process_stereo(&device, &queue, &red, &black)
+            .unwrap();
+    }
+    let [left, right] = eyes(&pipeline);
+    assert!(left.iter().all(|pixel| pixel[0] > 0.1));
+    assert!(
+        right.iter().all(|pixel| pixel[..3] == [0.0; 3]),
+        "{right:?}"
+    );
+}
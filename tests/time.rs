@@ -0,0 +1,50 @@
+// tests/time.rs
+//
+// Tests of the effect clock
+
+use std::time::Duration;
+
+use nnpipe::golden;
+use nnpipe::Nnpipe;
+
+#[test]
+fn clock_scales_pauses_and_jumps() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping time test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 8, 8, 1).unwrap();
+    assert_eq!(pipeline.time_rate(), 1.0);
+    assert!(!pipeline.time_paused());
+
+    // Frozen where it jumped to
+    pipeline.set_time_paused(true);
+    pipeline.jump_time(10.0);
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(pipeline.time(), 10.0);
+
+    // Ten times as fast from there
+    pipeline.set_time_rate(10.0);
+    pipeline.set_time_paused(false);
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(pipeline.time() >= 10.2, "{}", pipeline.time());
+
+    // Stopped by a zero rate, keeping the time reached
+    pipeline.set_time_rate(0.0);
+    let time = pipeline.time();
+    std::thread::sleep(Duration::from_millis(20));
+    assert_eq!(pipeline.time(), time);
+
+    // A pinned time wins, and the clock is back once unpinned
+    pipeline.set_time(Some(1.0));
+    assert_eq!(pipeline.time(), 1.0);
+    pipeline.set_time(None);
+    assert_eq!(pipeline.time(), time);
+
+    // Rebuilds keep the clock
+    pipeline.set_time_paused(true);
+    pipeline.jump_time(3.0);
+    pipeline.resize(&device, &queue, 16, 16).unwrap();
+    assert_eq!(pipeline.time(), 3.0);
+    assert!(pipeline.time_paused());
+}
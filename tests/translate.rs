@@ -0,0 +1,87 @@
+// tests/translate.rs
+//
+// GLSL and SPIR-V pass tests
+
+#![cfg(feature = "shader-import")]
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{glsl_to_wgsl, Nnpipe};
+
+const INVERT: &str = "
+#version 450
+layout(set = 0, binding = 0) uniform texture2D src_tex;
+layout(set = 0, binding = 1) uniform sampler src_sampler;
+layout(set = 0, binding = 2) uniform Params { float amount; } params;
+layout(location = 0) out vec4 color;
+
+void main() {
+    vec4 src = texelFetch(sampler2D(src_tex, src_sampler), ivec2(gl_FragCoord.xy), 0);
+    color = vec4(mix(src.rgb, 1.0 - src.rgb, params.amount), src.a);
+}
+";
+
+// INVERT compiled to SPIR-V
+fn invert_spirv() -> Vec<u8> {
+    let options = naga::front::glsl::Options::from(naga::ShaderStage::Fragment);
+    let module = naga::front::glsl::Frontend::default()
+        .parse(&options, INVERT)
+        .unwrap();
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .unwrap();
+    let words =
+        naga::back::spv::write_vec(&module, &info, &naga::back::spv::Options::default(), None)
+            .unwrap();
+    bytemuck::cast_slice(&words).to_vec()
+}
+
+#[test]
+fn glsl_and_spirv_passes_run() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping translation test: no adapter");
+        return;
+    };
+    let input = TestPattern::Dots.create_texture(&device, &queue, 32, 24);
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    let glsl = pipeline.add_glsl_pass(&device, "Invert", INVERT, &[("amount", 1.0)]);
+    assert!(pipeline.take_shader_errors().is_empty());
+    assert!(pipeline
+        .custom_pass(glsl)
+        .unwrap()
+        .source()
+        .contains("fn fs_main"));
+    let inverted = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let expected: Vec<[f32; 4]> = plain
+        .iter()
+        .map(|[r, g, b, a]| [1.0 - r, 1.0 - g, 1.0 - b, *a])
+        .collect();
+    golden::compare(&expected, &inverted, 32, 1.0 / 255.0).unwrap();
+
+    // Inverting twice gives the frame back
+    let spirv = pipeline.add_spirv_pass(&device, "Invert", &invert_spirv(), &[("amount", 1.0)]);
+    assert!(pipeline.take_shader_errors().is_empty());
+    assert!(pipeline.custom_pass(spirv).is_some());
+    let restored = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    golden::compare(&plain, &restored, 32, 1.0 / 255.0).unwrap();
+}
+
+#[test]
+fn broken_shaders_fall_back() {
+    let error = glsl_to_wgsl("Broken", "#version 450\nvoid main() { nope(); }").unwrap_err();
+    assert_eq!(error.label, "Broken");
+    assert!(error.message.starts_with("line 2: "), "{}", error.message);
+
+    let Some((device, _queue)) = golden::headless_device() else {
+        eprintln!("skipping translation test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 32, 24, 1).unwrap();
+    pipeline.add_spirv_pass(&device, "Garbage", &[1, 2, 3, 4], &[]);
+    assert_eq!(pipeline.take_shader_errors().len(), 1);
+}
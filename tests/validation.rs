@@ -0,0 +1,133 @@
+// tests/validation.rs
+//
+// Errors for kernels, palettes, lookups and outputs that don't fit
+
+use nnpipe::golden;
+use nnpipe::{LookupTexture, Nnpipe, NnpipeError};
+
+const TINT: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+
+struct Params {
+    amount: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    return vec4<f32>(color.rgb * params.amount, color.a);
+}
+";
+
+#[test]
+fn bad_lookups_and_outputs_are_refused() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping validation test: no adapter");
+        return;
+    };
+    let mut pipeline = Nnpipe::new(&device, 16, 8, 1).unwrap();
+    let tint = pipeline.add_custom_pass(&device, "Tint", TINT, &[("amount", 1.0)]);
+    let short = LookupTexture {
+        width: 2,
+        height: 2,
+        data: vec![[1.0; 4]; 3],
+    };
+    assert!(matches!(
+        pipeline.set_pass_lookup(&device, &queue, tint, short),
+        Err(NnpipeError::PixelCount {
+            width: 2,
+            height: 2,
+            len: 3
+        })
+    ));
+    let empty = LookupTexture {
+        width: 0,
+        height: 1,
+        data: Vec::new(),
+    };
+    assert!(pipeline
+        .set_pass_lookup(&device, &queue, tint, empty)
+        .is_err());
+    let lookup = LookupTexture {
+        width: 2,
+        height: 1,
+        data: vec![[1.0; 4]; 2],
+    };
+    assert!(pipeline
+        .set_pass_lookup(&device, &queue, tint, lookup)
+        .is_ok());
+
+    let target = Nnpipe::new(&device, 16, 8, 1).unwrap();
+    let unknown = pipeline.present(&device, &queue, 3, &target.output_view);
+    assert!(matches!(unknown, Err(NnpipeError::UnknownOutput(3))));
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn convolution_kernels_need_a_full_grid() {
+    use nnpipe::ConvolutionKernel;
+
+    let empty = ConvolutionKernel::new(0, Vec::new());
+    assert!(matches!(
+        empty,
+        Err(NnpipeError::InvalidKernel {
+            width: 0,
+            height: 0
+        })
+    ));
+    let short = ConvolutionKernel::new(3, vec![1.0; 8]);
+    assert!(matches!(
+        short,
+        Err(NnpipeError::PixelCount {
+            width: 3,
+            height: 3,
+            len: 8
+        })
+    ));
+    assert!(ConvolutionKernel::box_blur(0).is_err());
+    assert!(ConvolutionKernel::new(3, vec![1.0; 9]).is_ok());
+}
+
+#[cfg(feature = "stylize")]
+#[test]
+fn palettes_need_a_color() {
+    use nnpipe::Palette;
+
+    assert!(matches!(
+        Palette::new(Vec::new()),
+        Err(NnpipeError::EmptyPalette)
+    ));
+    assert!(matches!(
+        Palette::from_hex(&[]),
+        Err(NnpipeError::EmptyPalette)
+    ));
+    assert!(Palette::from_hex(&[0xff8800]).is_ok());
+}
+
+#[cfg(feature = "bloom")]
+#[test]
+fn aperture_kernels_need_pixels() {
+    use nnpipe::ApertureKernel;
+
+    let empty = ApertureKernel::from_rgba(0, 4, Vec::new());
+    assert!(matches!(
+        empty,
+        Err(NnpipeError::InvalidKernel {
+            width: 0,
+            height: 4
+        })
+    ));
+    let short = ApertureKernel::from_rgba(2, 2, vec![[1.0; 4]; 3]);
+    assert!(matches!(
+        short,
+        Err(NnpipeError::PixelCount {
+            width: 2,
+            height: 2,
+            len: 3
+        })
+    ));
+    assert!(ApertureKernel::star(6, 0).is_err());
+    assert!(ApertureKernel::hexagon(0).is_err());
+    assert!(ApertureKernel::from_rgba(2, 2, vec![[1.0; 4]; 4]).is_ok());
+}
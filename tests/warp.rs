@@ -0,0 +1,138 @@
+// tests/warp.rs
+//
+// Tests of the corner pin and mesh warps of outputs
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, OutputScaling, OutputWarp, WarpMesh};
+
+#[test]
+fn warps_pin_and_bend_the_frame() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping warp test: no adapter");
+        return;
+    };
+    let (width, height) = (8, 8);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.set_bloom_intensity(0.0);
+    let frame = golden::render(&pipeline, &device, &queue, &input).unwrap();
+
+    // Presented twice the size, point sampled so target pixels map to whole frame ones
+    let index = pipeline
+        .add_output(&device, nannou::wgpu::TextureFormat::Rgba16Float)
+        .unwrap();
+    let output = pipeline.output_mut(index).unwrap();
+    output.set_scaling(OutputScaling::Nearest);
+    let background = [1.0, 0.0, 1.0, 1.0];
+    output.set_background(background);
+    assert!(output.warp().is_identity());
+    let size = 16;
+    let target = Nnpipe::new(&device, size, size, 1).unwrap();
+    let present = |pipeline: &Nnpipe| {
+        pipeline
+            .present(&device, &queue, index, &target.output_view)
+            .unwrap();
+        futures::executor::block_on(target.read_output(&device, &queue)).unwrap()
+    };
+    let pixel = |pixels: &[[f32; 4]], x: u32, y: u32, width: u32| pixels[(y * width + x) as usize];
+    let plain = present(&pipeline);
+    for (x, y) in [(0, 0), (5, 9), (15, 15)] {
+        assert_eq!(
+            pixel(&plain, x, y, size),
+            pixel(&frame, x / 2, y / 2, width)
+        );
+    }
+
+    // Pinned into the right half, squashed to one target pixel per frame pixel
+    let squashed = OutputWarp::corner_pin([[0.5, 0.0], [1.0, 0.0], [1.0, 1.0], [0.5, 1.0]]);
+    let output = pipeline.output_mut(index).unwrap();
+    output.set_warp(squashed.clone());
+    assert_eq!(output.warp(), &squashed);
+    let pinned = present(&pipeline);
+    for y in 0..size {
+        for x in 0..size {
+            let expected = match x.checked_sub(8) {
+                Some(x) => pixel(&frame, x, y / 2, width),
+                None => background,
+            };
+            assert_eq!(pixel(&pinned, x, y, size), expected, "at {x}, {y}");
+        }
+    }
+
+    // A keystone narrowing towards the top moves the frame's middle row up to where
+    // the diagonals cross, a third of the way down, as in perspective
+    let keystone = OutputWarp::corner_pin([[0.25, 0.0], [0.75, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+    let output = pipeline.output_mut(index).unwrap();
+    output.set_warp(keystone);
+    let keystoned = present(&pipeline);
+    assert_eq!(pixel(&keystoned, 0, 2, size), background);
+    assert_eq!(pixel(&keystoned, 8, 4, size), pixel(&frame, 4, 3, width));
+    assert_eq!(pixel(&keystoned, 8, 6, size), pixel(&frame, 4, 4, width));
+
+    // An even mesh changes nothing; moving its center point right drags the frame's
+    // middle along
+    let mut mesh = WarpMesh::new(3, 3);
+    let even = OutputWarp {
+        mesh: Some(mesh.clone()),
+        ..Default::default()
+    };
+    assert!(even.is_identity());
+    let output = pipeline.output_mut(index).unwrap();
+    output.set_warp(even);
+    golden::compare(&plain, &present(&pipeline), size, 0.0).unwrap();
+    assert!(mesh.set_point(1, 1, [0.75, 0.5]));
+    assert!(!mesh.set_point(3, 0, [1.0, 0.0]));
+    assert_eq!(mesh.point(1, 1), Some([0.75, 0.5]));
+    let bent = OutputWarp {
+        mesh: Some(mesh),
+        ..Default::default()
+    };
+    let output = pipeline.output_mut(index).unwrap();
+    output.set_warp(bent.clone());
+    let meshed = present(&pipeline);
+    assert_eq!(pixel(&meshed, 12, 8, size), pixel(&frame, 4, 4, width));
+    assert_ne!(pixel(&meshed, 8, 8, size), pixel(&frame, 4, 4, width));
+    assert_eq!(pixel(&meshed, 0, 0, size), pixel(&frame, 0, 0, width));
+
+    // Survives resizes
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    assert_eq!(pipeline.output(index).unwrap().warp(), &bent);
+}
+
+#[cfg(feature = "config")]
+#[test]
+fn chain_configs_keep_warps() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping warp test: no adapter");
+        return;
+    };
+    let format = nannou::wgpu::TextureFormat::Rgba16Float;
+    let mut pipeline = Nnpipe::new(&device, 32, 8, 1).unwrap();
+    let plain = pipeline.add_output(&device, format).unwrap();
+    let warped = pipeline.add_output(&device, format).unwrap();
+    let keystone = OutputWarp::corner_pin([[0.1, 0.0], [0.9, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+    let scaler_warp = OutputWarp {
+        mesh: Some(WarpMesh::new(2, 4)),
+        ..keystone.clone()
+    };
+    let output = pipeline.output_mut(warped).unwrap();
+    output.set_warp(keystone.clone());
+    pipeline.scaler_mut().set_warp(scaler_warp.clone());
+
+    let config = pipeline.chain_config();
+    let indices: Vec<_> = config.outputs.iter().map(|output| output.index).collect();
+    assert_eq!(indices, [None, Some(warped)]);
+    let source = config.to_toml().unwrap();
+    let config = nnpipe::PipelineConfig::from_toml(&source).unwrap();
+
+    // New pipelines have no outputs to warp until they're added
+    let mut rebuilt = Nnpipe::from_config(&device, &queue, &config).unwrap();
+    assert_eq!(rebuilt.scaler().warp(), &scaler_warp);
+    assert!(rebuilt.apply_config(&config).is_err());
+    for _ in 0..2 {
+        rebuilt.add_output(&device, format).unwrap();
+    }
+    rebuilt.apply_config(&config).unwrap();
+    assert!(rebuilt.output(plain).unwrap().warp().is_identity());
+    assert_eq!(rebuilt.output(warped).unwrap().warp(), &keystone);
+}
@@ -0,0 +1,93 @@
+// tests/zebra.rs
+//
+// Tests of the zebra and clipping indicator
+
+use nnpipe::golden::{self, TestPattern};
+use nnpipe::{Nnpipe, Zebra};
+
+// Brightens the frame, with a NaN and an infinite pixel in the top left
+const BROKEN: &str = "
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+struct Params {
+    gain: f32,
+    zero: f32,
+}
+@group(0) @binding(2) var<uniform> params: Params;
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(src_tex, vec2<i32>(pos.xy), 0);
+    if (pos.y < 1.0 && pos.x < 1.0) {
+        return vec4<f32>(params.zero / params.zero);
+    }
+    if (pos.y < 1.0 && pos.x < 2.0) {
+        return vec4<f32>(1.0 / params.zero);
+    }
+    return vec4<f32>(color.rgb * params.gain, color.a);
+}
+";
+
+fn luma(color: [f32; 4]) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+#[test]
+fn marks_bright_and_non_finite_pixels() {
+    let Some((device, queue)) = golden::headless_device() else {
+        eprintln!("skipping zebra test: no adapter");
+        return;
+    };
+    let (width, height) = (8, 4);
+    let input = TestPattern::Gradient.create_texture(&device, &queue, width, height);
+    let mut pipeline = Nnpipe::new(&device, width, height, 1).unwrap();
+    pipeline.add_custom_pass(&device, "Broken", BROKEN, &[("gain", 1.8), ("zero", 0.0)]);
+    let plain = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(plain[0][0].is_nan());
+    assert!(plain[1][0].is_infinite());
+
+    let zebra = Zebra {
+        stripe_width: 2.0,
+        ..Default::default()
+    };
+    pipeline.set_zebra(&device, Some(zebra));
+    assert_eq!(pipeline.zebra(), Some(zebra));
+    let marked = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    let mut striped = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let i = (y * width + x) as usize;
+            if y == 0 && x < 2 {
+                assert_eq!(marked[i], [1.0, 0.0, 1.0, 1.0], "at {x}, {y}");
+                continue;
+            }
+            // Pixel centers, as the shader sees them
+            let stripe = (x + y).div_ceil(2) % 2 == 0;
+            if stripe && luma(plain[i]) > zebra.threshold {
+                assert_eq!(marked[i][..3], [0.0; 3], "at {x}, {y}");
+                striped += 1;
+            } else {
+                assert_eq!(marked[i], plain[i], "at {x}, {y}");
+            }
+        }
+    }
+    assert!(striped > 0);
+
+    // Without a non-finite color, broken pixels go through as they are
+    pipeline.set_zebra(
+        &device,
+        Some(Zebra {
+            non_finite_color: None,
+            ..zebra
+        }),
+    );
+    pipeline.resize(&device, &queue, width, height).unwrap();
+    let unmarked = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert!(unmarked[0][0].is_nan());
+    assert!(unmarked[1][0].is_infinite());
+
+    pipeline.set_zebra(&device, None);
+    let off = golden::render(&pipeline, &device, &queue, &input).unwrap();
+    assert_eq!(off[width as usize..], plain[width as usize..]);
+}